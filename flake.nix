@@ -50,6 +50,108 @@
       "rust-analyzer"
       "rustfmt"
     ];
+
+    windowsTargets = {
+      x86_64 = {
+        target = "x86_64-pc-windows-gnu";
+        crossSystem.config = "x86_64-w64-mingw32";
+      };
+      # Snapdragon laptops. The LLVM-based mingw toolchain is the only one that supports ARM64.
+      aarch64 = {
+        target = "aarch64-pc-windows-gnullvm";
+        crossSystem = {
+          config = "aarch64-w64-mingw32";
+          libc = "ucrt";
+          useLLVM = true;
+        };
+      };
+    };
+
+    windowsPackagesFor = system: {
+      target,
+      crossSystem,
+    }: let
+      pkgs = import nixpkgs {
+        inherit system crossSystem;
+        overlays = [(import rust-overlay)];
+      };
+
+      rustToolchain = pkgs.pkgsBuildHost.rust-bin.nightly.latest.default.override {
+        inherit extensions;
+        targets = [target];
+      };
+
+      craneLib = (crane.mkLib pkgs).overrideScope' (final: prev: {
+        rustc = rustToolchain;
+        cargo = rustToolchain;
+        rustfmt = rustToolchain;
+      });
+
+      sdl2Static = pkgs.callPackage sdl2StaticDrv {};
+
+      linkerVar = "CARGO_TARGET_${pkgs.lib.toUpper (builtins.replaceStrings ["-"] ["_"] target)}_LINKER";
+    in {
+      devShell = pkgs.pkgsBuildHost.mkShell {
+        # inputsFrom = [packages.default];
+
+        packages = with pkgs.pkgsBuildHost; [
+          rustToolchain
+          sdl2Static
+          cargo-outdated
+          fontconfig
+          cmake
+          alejandra
+          nsis
+        ];
+
+        RUSTFLAGS = "-L ${sdl2Static}/lib";
+      };
+
+      packages = rec {
+        default = installer;
+
+        installer = pkgs.stdenvNoCC.mkDerivation {
+          name = "flux-screensaver-installer";
+          version = flux.version;
+          src = ./windows/installer;
+
+          buildInputs = with pkgs.pkgsBuildHost; [nsis flux];
+
+          installPhase = ''
+            mkdir -p $out/bin
+            ${pkgs.pkgsBuildHost.nsis}/bin/makensis \
+              -DDSTDIR=${flux}/bin/ \
+              -DOUTDIR=$out/bin \
+              -DVERSION=${flux.version} \
+              $src/setup.nsi
+          '';
+        };
+
+        flux = craneLib.buildPackage {
+          src = ./windows;
+          release = true;
+          doCheck = false;
+
+          buildInputs = [
+            pkgs.windows.pthreads
+            pkgs.windows.mingw_w64_pthreads
+            sdl2Static
+          ];
+
+          CARGO_BUILD_TARGET = target;
+          ${linkerVar} = "${pkgs.stdenv.cc.targetPrefix}cc";
+          # Link to the static SDL2 library and export the static GPU preference symbols
+          RUSTFLAGS = "-L ${sdl2Static}/lib -Zexport-executable-symbols";
+
+          # Change the extension to .scr (Windows screensaver)
+          postInstall = ''
+            if [[ $out != *"deps"* ]]; then
+              cp $out/bin/Flux.exe "$out/bin/Flux.scr"
+            fi
+          '';
+        };
+      };
+    };
   in
     nixpkgs.lib.foldAttrs nixpkgs.lib.recursiveUpdate {} [
       (flake-utils.lib.eachSystem ["x86_64-linux" "aarch64-linux" "x86_64-darwin" "aarch64-darwin"] (
@@ -151,88 +253,10 @@
             };
         }
       ))
-      (flake-utils.lib.eachSystem ["x86_64-linux" "aarch64-linux"] (system: let
-        target = "x86_64-pc-windows-gnu";
-
-        pkgs = import nixpkgs {
-          inherit system;
-          crossSystem.config = "x86_64-w64-mingw32";
-          overlays = [(import rust-overlay)];
-        };
-
-        rustToolchain = pkgs.pkgsBuildHost.rust-bin.nightly.latest.default.override {
-          inherit extensions;
-          targets = [target];
-        };
-
-        craneLib = (crane.mkLib pkgs).overrideScope' (final: prev: {
-          rustc = rustToolchain;
-          cargo = rustToolchain;
-          rustfmt = rustToolchain;
-        });
-
-        sdl2Static = pkgs.callPackage sdl2StaticDrv {};
-      in {
-        devShells.cross = pkgs.pkgsBuildHost.mkShell {
-          # inputsFrom = [packages.default];
-
-          packages = with pkgs.pkgsBuildHost; [
-            rustToolchain
-            sdl2Static
-            cargo-outdated
-            fontconfig
-            cmake
-            alejandra
-            nsis
-          ];
-
-          RUSTFLAGS = "-L ${sdl2Static}/lib";
-        };
-
-        packages.windows = rec {
-          default = installer;
-
-          installer = pkgs.stdenvNoCC.mkDerivation {
-            name = "flux-screensaver-installer";
-            version = flux.version;
-            src = ./windows/installer;
-
-            buildInputs = with pkgs.pkgsBuildHost; [nsis flux];
-
-            installPhase = ''
-              mkdir -p $out/bin
-              ${pkgs.pkgsBuildHost.nsis}/bin/makensis \
-                -DDSTDIR=${flux}/bin/ \
-                -DOUTDIR=$out/bin \
-                -DVERSION=${flux.version} \
-                $src/setup.nsi
-            '';
-          };
-
-          flux = craneLib.buildPackage {
-            src = ./windows;
-            release = true;
-            doCheck = false;
-
-            buildInputs = [
-              pkgs.windows.pthreads
-              pkgs.windows.mingw_w64_pthreads
-              sdl2Static
-            ];
-
-            CARGO_BUILD_TARGET = target;
-            CARGO_TARGET_X86_64_PC_WINDOWS_GNU_LINKER = "${pkgs.stdenv.cc.targetPrefix}cc";
-            # Link to the static SDL2 library and export the static GPU preference symbols
-            RUSTFLAGS = "-L ${sdl2Static}/lib -Zexport-executable-symbols";
-
-            # Change the extension to .scr (Windows screensaver)
-            postInstall = ''
-              if [[ $out != *"deps"* ]]; then
-                cp $out/bin/Flux.exe "$out/bin/Flux.scr"
-              fi
-            '';
-          };
-        };
+      (flake-utils.lib.eachSystem ["x86_64-linux" "aarch64-linux"] (system: {
+        devShells.cross = (windowsPackagesFor system windowsTargets.x86_64).devShell;
+        packages.windows = (windowsPackagesFor system windowsTargets.x86_64).packages;
+        packages.windows-arm64 = (windowsPackagesFor system windowsTargets.aarch64).packages;
       }))
     ];
 }
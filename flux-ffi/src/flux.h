@@ -3,12 +3,30 @@
 #include <stdint.h>
 #include <stdlib.h>
 
-typedef struct Flux Flux;
+typedef struct FluxInstance FluxInstance;
 
-struct Flux *flux_new(float width, float height, float physical_width, float physical_height, const char *settings_json_ptr);
+typedef struct FluxFrameStats {
+  uint64_t frame_number;
+  double timestamp;
+  double delta_time;
+  double cpu_time;
+} FluxFrameStats;
 
-void flux_animate(struct Flux *ptr, double timestamp);
+typedef struct FluxHooks {
+  void *user_data;
+  void (*on_frame_start)(void *user_data, double timestamp);
+  void (*on_frame_end)(void *user_data, const struct FluxFrameStats *stats);
+  void (*on_error)(void *user_data, const char *message);
+} FluxHooks;
 
-void flux_resize(struct Flux *ptr, float logical_width, float logical_height, float physical_width, float physical_height);
+struct FluxInstance *flux_new(float logical_width, float logical_height, float physical_width, float physical_height, const char *settings_json_ptr);
 
-void flux_destroy(struct Flux *ptr);
+struct FluxInstance *flux_new_with_hooks(float logical_width, float logical_height, float physical_width, float physical_height, const char *settings_json_ptr, const struct FluxHooks *hooks);
+
+void flux_set_hooks(struct FluxInstance *instance, const struct FluxHooks *hooks);
+
+void flux_animate(struct FluxInstance *instance, double timestamp);
+
+void flux_resize(struct FluxInstance *instance, float logical_width, float logical_height, float physical_width, float physical_height);
+
+void flux_destroy(struct FluxInstance *instance);
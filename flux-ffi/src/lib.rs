@@ -1,7 +1,9 @@
 use flux::{settings::Settings, Flux};
-use std::ffi::CStr;
+use std::ffi::{c_void, CStr, CString};
 use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
 use std::rc::Rc;
+use std::time::Instant;
 
 fn init_flux(
     logical_width: f32,
@@ -30,6 +32,62 @@ fn init_flux(
     .map_err(|err| err.to_string())
 }
 
+/// Optional callbacks that let the host application follow along with rendering.
+///
+/// Every callback receives `user_data` as its first argument.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct FluxHooks {
+    pub user_data: *mut c_void,
+    /// Called before each frame with its timestamp.
+    pub on_frame_start: Option<extern "C" fn(user_data: *mut c_void, timestamp: f64)>,
+    /// Called once each frame has been rendered.
+    pub on_frame_end: Option<extern "C" fn(user_data: *mut c_void, stats: *const FluxFrameStats)>,
+    /// Called with a null-terminated message when something goes wrong.
+    pub on_error: Option<extern "C" fn(user_data: *mut c_void, message: *const c_char)>,
+}
+
+impl Default for FluxHooks {
+    fn default() -> Self {
+        Self {
+            user_data: std::ptr::null_mut(),
+            on_frame_start: None,
+            on_frame_end: None,
+            on_error: None,
+        }
+    }
+}
+
+impl FluxHooks {
+    fn error(&self, message: &str) {
+        if let Some(on_error) = self.on_error {
+            let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+            on_error(self.user_data, message.as_ptr());
+        }
+    }
+}
+
+/// Timing for a single frame, passed to `on_frame_end`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FluxFrameStats {
+    /// The number of frames rendered so far, including this one.
+    pub frame_number: u64,
+    /// The timestamp passed to `flux_animate`, in milliseconds.
+    pub timestamp: f64,
+    /// The time since the previous frame’s timestamp, in milliseconds.
+    pub delta_time: f64,
+    /// How long it took to issue the frame’s draw calls, in milliseconds.
+    pub cpu_time: f64,
+}
+
+pub struct FluxInstance {
+    flux: Flux,
+    hooks: FluxHooks,
+    frame_number: u64,
+    last_timestamp: Option<f64>,
+}
+
 #[no_mangle]
 pub extern "C" fn flux_new(
     logical_width: f32,
@@ -37,7 +95,33 @@ pub extern "C" fn flux_new(
     physical_width: f32,
     physical_height: f32,
     settings_json_ptr: *const c_char,
-) -> *mut Flux {
+) -> *mut FluxInstance {
+    unsafe {
+        flux_new_with_hooks(
+            logical_width,
+            logical_height,
+            physical_width,
+            physical_height,
+            settings_json_ptr,
+            std::ptr::null(),
+        )
+    }
+}
+
+/// Like `flux_new`, but with hooks that also report any errors during initialization.
+///
+/// `hooks` may be null.
+#[no_mangle]
+pub unsafe extern "C" fn flux_new_with_hooks(
+    logical_width: f32,
+    logical_height: f32,
+    physical_width: f32,
+    physical_height: f32,
+    settings_json_ptr: *const c_char,
+    hooks: *const FluxHooks,
+) -> *mut FluxInstance {
+    let hooks = hooks.as_ref().copied().unwrap_or_default();
+
     let flux = match init_flux(
         logical_width,
         logical_height,
@@ -45,30 +129,76 @@ pub extern "C" fn flux_new(
         physical_height,
         settings_json_ptr,
     ) {
-        Err(_msg) => {
-            // TODO: log stuff
+        Err(msg) => {
+            hooks.error(&msg);
             return std::ptr::null_mut();
         }
         Ok(flux) => flux,
     };
 
-    Box::into_raw(Box::new(flux))
+    Box::into_raw(Box::new(FluxInstance {
+        flux,
+        hooks,
+        frame_number: 0,
+        last_timestamp: None,
+    }))
+}
+
+/// Replace the hooks of an existing instance. Pass null to remove them.
+#[no_mangle]
+pub unsafe extern "C" fn flux_set_hooks(instance: *mut FluxInstance, hooks: *const FluxHooks) {
+    (&mut *instance).hooks = hooks.as_ref().copied().unwrap_or_default();
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn flux_animate(flux: *mut Flux, timestamp: f64) {
-    (&mut *flux).animate(timestamp);
+pub unsafe extern "C" fn flux_animate(instance: *mut FluxInstance, timestamp: f64) {
+    let instance = &mut *instance;
+    let hooks = instance.hooks;
+
+    if let Some(on_frame_start) = hooks.on_frame_start {
+        on_frame_start(hooks.user_data, timestamp);
+    }
+
+    let start = Instant::now();
+    // Unwinding into the host application is undefined behaviour, so report panics instead.
+    let result = panic::catch_unwind(AssertUnwindSafe(|| instance.flux.animate(timestamp)));
+    let cpu_time = start.elapsed().as_secs_f64() * 1000.0;
+
+    if let Err(err) = result {
+        let message = err
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| err.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "Flux panicked while rendering".to_string());
+        hooks.error(&message);
+        return;
+    }
+
+    instance.frame_number += 1;
+    let stats = FluxFrameStats {
+        frame_number: instance.frame_number,
+        timestamp,
+        delta_time: instance
+            .last_timestamp
+            .map_or(0.0, |last_timestamp| timestamp - last_timestamp),
+        cpu_time,
+    };
+    instance.last_timestamp = Some(timestamp);
+
+    if let Some(on_frame_end) = hooks.on_frame_end {
+        on_frame_end(hooks.user_data, &stats);
+    }
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn flux_resize(
-    flux: *mut Flux,
+    instance: *mut FluxInstance,
     logical_width: f32,
     logical_height: f32,
     physical_width: f32,
     physical_height: f32,
 ) {
-    (&mut *flux).resize(
+    (&mut *instance).flux.resize(
         logical_width as u32,
         logical_height as u32,
         physical_width as u32,
@@ -77,9 +207,9 @@ pub unsafe extern "C" fn flux_resize(
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn flux_destroy(flux: *mut Flux) {
-    if !flux.is_null() {
-        drop(Box::from_raw(flux));
+pub unsafe extern "C" fn flux_destroy(instance: *mut FluxInstance) {
+    if !instance.is_null() {
+        drop(Box::from_raw(instance));
     }
 }
 
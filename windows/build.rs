@@ -42,9 +42,13 @@ fn main() {
         // Here’s where this stuff is on
         // NixOS: pkgs.pkgsCross.mingwW64.stdenv.cc.bintools.bintools_bin
         if cfg!(unix) {
+            let prefix = match env("CARGO_CFG_TARGET_ARCH").as_str() {
+                "aarch64" => "aarch64-w64-mingw32",
+                _ => "x86_64-w64-mingw32",
+            };
             resource
-                .set_ar_path("x86_64-w64-mingw32-ar")
-                .set_windres_path("x86_64-w64-mingw32-windres");
+                .set_ar_path(&format!("{prefix}-ar"))
+                .set_windres_path(&format!("{prefix}-windres"));
         }
 
         resource
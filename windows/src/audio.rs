@@ -0,0 +1,129 @@
+use crate::config::AmbientAudioConfig;
+
+use sdl2::audio::{
+    AudioCVT, AudioCallback, AudioDevice, AudioFormat, AudioSpecDesired, AudioSpecWAV,
+};
+
+/// A sound looping in the background while the screensaver runs. The sound stops as soon as this
+/// is dropped.
+pub struct Ambient {
+    device: AudioDevice<Loop>,
+}
+
+impl Ambient {
+    /// Start looping the sound from the settings, fading it in. `None` if there’s no sound to
+    /// play, or it can’t be played.
+    pub fn start(sdl: &sdl2::Sdl, config: &AmbientAudioConfig) -> Option<Self> {
+        let path = config.path.as_deref()?;
+        match Self::open(sdl, path, config) {
+            Ok(ambient) => {
+                log::info!("Playing {}", path.display());
+                Some(ambient)
+            }
+            Err(err) => {
+                log::warn!("Failed to play {}: {}", path.display(), err);
+                None
+            }
+        }
+    }
+
+    fn open(
+        sdl: &sdl2::Sdl,
+        path: &std::path::Path,
+        config: &AmbientAudioConfig,
+    ) -> Result<Self, String> {
+        let wav = AudioSpecWAV::load_wav(path)?;
+        // Mix in floats, at the rate and channels of the file.
+        let converter = AudioCVT::new(
+            wav.format,
+            wav.channels,
+            wav.freq,
+            AudioFormat::f32_sys(),
+            wav.channels,
+            wav.freq,
+        )?;
+        let samples = converter
+            .convert(wav.buffer().to_vec())
+            .chunks_exact(4)
+            .map(|bytes| f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect::<Vec<f32>>();
+        if samples.is_empty() {
+            return Err("The file has no sound in it".to_string());
+        }
+
+        let samples_per_ms = wav.freq as f32 * wav.channels as f32 / 1000.0;
+        let fade_in_samples = config.fade_in_ms as f32 * samples_per_ms;
+        let desired = AudioSpecDesired {
+            freq: Some(wav.freq),
+            channels: Some(wav.channels),
+            samples: None,
+        };
+        let device = sdl.audio()?.open_playback(None, &desired, |_| {
+            Loop::new(samples, config.volume.clamp(0.0, 1.0), fade_in_samples)
+        })?;
+        device.resume();
+
+        Ok(Self { device })
+    }
+
+    /// Turn the sound down on top of the volume in the settings, from 1.0 for no change to 0.0
+    /// for silent, like while the screensaver fades out.
+    pub fn set_fade(&mut self, level: f32) {
+        self.device.lock().fade = level.clamp(0.0, 1.0);
+    }
+}
+
+// Plays the samples over and over, ramping up the gain at the start.
+struct Loop {
+    samples: Vec<f32>,
+    position: usize,
+    volume: f32,
+    // Ramps up from 0 to 1 as the sound fades in
+    gain: f32,
+    gain_step: f32,
+    fade: f32,
+}
+
+impl Loop {
+    fn new(samples: Vec<f32>, volume: f32, fade_in_samples: f32) -> Self {
+        let fade_in = fade_in_samples >= 1.0;
+        Self {
+            samples,
+            position: 0,
+            volume,
+            gain: if fade_in { 0.0 } else { 1.0 },
+            gain_step: if fade_in { 1.0 / fade_in_samples } else { 0.0 },
+            fade: 1.0,
+        }
+    }
+}
+
+impl AudioCallback for Loop {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        for sample in out {
+            *sample = self.samples[self.position] * self.volume * self.gain * self.fade;
+            self.position = (self.position + 1) % self.samples.len();
+            self.gain = (self.gain + self.gain_step).min(1.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_fades_in_and_loops_around() {
+        let mut sound = Loop::new(vec![1.0, -1.0, 0.5], 0.5, 4.0);
+
+        let mut out = [0.0; 7];
+        sound.callback(&mut out);
+        assert_eq!(out, [0.0, -0.125, 0.125, 0.375, -0.5, 0.25, 0.5]);
+
+        sound.fade = 0.0;
+        sound.callback(&mut out);
+        assert!(out.iter().all(|sample| *sample == 0.0));
+    }
+}
@@ -0,0 +1,217 @@
+use crate::config::{Config, GpuWorkaround, GpuWorkarounds, Rotation};
+use crate::render_thread::{self, Command, RenderThread};
+use crate::winit_compat::HasWinitWindow;
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
+
+// How long to render through each swapchain
+const DURATION: Duration = Duration::from_secs(10);
+
+// Frames to discard while shaders compile and the driver warms up.
+const WARMUP_FRAMES: usize = 30;
+
+/// Render the same scene on the primary monitor and report how long presenting takes, and how
+/// evenly the frames are paced.
+///
+/// The screensaver presents through DXGI where it can, because the GL swapchain stutters on some
+/// drivers. Comparing both on the same machine shows whether that still holds, and catches
+/// regressions in the interop.
+pub fn run(
+    video_subsystem: &sdl2::VideoSubsystem,
+    event_pump: &mut sdl2::EventPump,
+    config: &Config,
+    compare_swapchains: bool,
+) -> Result<(), String> {
+    let runs = if compare_swapchains {
+        vec![with_gl_swapchain(config), config.clone()]
+    } else {
+        vec![config.clone()]
+    };
+
+    let mut results = Vec::with_capacity(runs.len());
+    for config in runs {
+        results.push(benchmark(video_subsystem, event_pump, &config)?);
+    }
+
+    for result in results.iter() {
+        log::info!("{}", result);
+        println!("{}", result);
+    }
+
+    Ok(())
+}
+
+// Turn off the DXGI swapchain for every GPU.
+fn with_gl_swapchain(config: &Config) -> Config {
+    let mut config = config.clone();
+    config
+        .platform
+        .windows
+        .gpu_workarounds
+        .push(GpuWorkaround::for_every_gpu(GpuWorkarounds {
+            disable_dxgi_interop: true,
+            ..Default::default()
+        }));
+    config
+}
+
+fn benchmark(
+    video_subsystem: &sdl2::VideoSubsystem,
+    event_pump: &mut sdl2::EventPump,
+    config: &Config,
+) -> Result<Report, String> {
+    let bounds = video_subsystem.display_bounds(0)?;
+    let window = video_subsystem
+        .window("Flux benchmark", bounds.width(), bounds.height())
+        .position(bounds.x(), bounds.y())
+        .borderless()
+        .allow_highdpi()
+        .build()
+        .map_err(|err| err.to_string())?;
+
+    let handles = render_thread::Handles {
+        display: window.raw_display_handle(),
+        window: window.raw_window_handle(),
+        attr_window: None,
+    };
+    let options = render_thread::Options {
+        size: window.inner_size(),
+        scale_factor: window.scale_factor(),
+        wallpaper: None,
+        compositor_opacity: None,
+        rotation: Rotation::None,
+        tiles: (1, 1),
+        resizable: false,
+        letterbox: Vec::new(),
+    };
+    let (mut renderer, ready) = RenderThread::spawn(config, handles, options)?;
+    log::info!(
+        "Benchmarking the {} swapchain on {}",
+        ready.swapchain,
+        ready.gpu
+    );
+
+    let mut present_times = Vec::new();
+    let mut frame_times = Vec::new();
+    let start = Instant::now();
+    let mut last_present = None;
+
+    while start.elapsed() < DURATION {
+        // Keep the window responsive, but don’t let input end the run.
+        for _ in event_pump.poll_iter() {}
+
+        let frame_start = Instant::now();
+        renderer.run(Command::Draw(start.elapsed().as_secs_f64() * 1000.0))?;
+        let presented = Instant::now();
+
+        present_times.push(presented - frame_start);
+        if let Some(last_present) = last_present {
+            frame_times.push(presented - last_present);
+        }
+        last_present = Some(presented);
+    }
+
+    // Release the swapchain before the window goes away.
+    drop(renderer);
+
+    let skip = |samples: &[Duration]| samples.get(WARMUP_FRAMES..).unwrap_or_default().to_vec();
+    Ok(Report {
+        swapchain: ready.swapchain,
+        gpu: ready.gpu,
+        present_time: Summary::new(&skip(&present_times)),
+        frame_time: Summary::new(&skip(&frame_times)),
+    })
+}
+
+struct Report {
+    swapchain: &'static str,
+    gpu: String,
+    // From handing a frame to the render thread to it being presented
+    present_time: Option<Summary>,
+    // Between one present and the next
+    frame_time: Option<Summary>,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} swapchain on {}", self.swapchain, self.gpu)?;
+        match (&self.present_time, &self.frame_time) {
+            (Some(present_time), Some(frame_time)) => {
+                writeln!(f, "  Present latency: {}", present_time)?;
+                write!(f, "  Frame time:      {}", frame_time)
+            }
+            _ => write!(f, "  Not enough frames to measure"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Summary {
+    mean: Duration,
+    std_dev: Duration,
+    p99: Duration,
+    max: Duration,
+}
+
+impl Summary {
+    fn new(samples: &[Duration]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+
+        let mean = samples.iter().sum::<Duration>() / samples.len() as u32;
+        let variance = samples
+            .iter()
+            .map(|sample| (sample.as_secs_f64() - mean.as_secs_f64()).powi(2))
+            .sum::<f64>()
+            / samples.len() as f64;
+
+        let mut sorted = samples.to_vec();
+        sorted.sort_unstable();
+        let p99 = sorted[(sorted.len() - 1) * 99 / 100];
+
+        Some(Self {
+            mean,
+            std_dev: Duration::from_secs_f64(variance.sqrt()),
+            p99,
+            max: sorted[sorted.len() - 1],
+        })
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        write!(
+            f,
+            "{:.2} ms mean, {:.2} ms std dev, {:.2} ms p99, {:.2} ms max",
+            ms(self.mean),
+            ms(self.std_dev),
+            ms(self.p99),
+            ms(self.max)
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_summarizes_frame_times() {
+        let samples = [16, 17, 16, 15, 16, 16, 32, 16]
+            .map(Duration::from_millis)
+            .to_vec();
+        let summary = Summary::new(&samples).unwrap();
+
+        assert_eq!(summary.mean, Duration::from_millis(18));
+        assert_eq!(summary.max, Duration::from_millis(32));
+        assert_eq!(summary.p99, Duration::from_millis(17));
+        assert!((summary.std_dev.as_secs_f64() * 1000.0 - 5.3).abs() < 0.1);
+
+        assert_eq!(Summary::new(&[]), None);
+    }
+}
@@ -0,0 +1,162 @@
+use crate::config::BlankingConfig;
+
+use std::time::{Duration, Instant};
+
+// How long the animation takes to fade out before the displays go off. Slower than the exit
+// transition, so that it doesn’t look like the screensaver crashed.
+const FADE_DURATION: Duration = Duration::from_secs(3);
+
+/// What the main loop should do next for the blanking schedule.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    /// Keep going as before
+    None,
+    /// Dim the animation to this brightness, from 1.0 down to 0.0
+    Fade(f32),
+    /// Pause the animation and turn the displays off
+    Blank,
+    /// Turn the displays back on and pick up the animation again, dimmed all the way, for the
+    /// `Fade`s that follow to bring back up
+    Wake,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum State {
+    Running { since: Instant },
+    FadingOut { since: Instant },
+    Blanked { since: Instant },
+    // Running again since the displays woke up, and fading back in
+    FadingIn { since: Instant },
+}
+
+/// Fades the screensaver out and turns the displays off after it’s been running for a while, and
+/// wakes back into the animation later on.
+pub struct Schedule {
+    blank_after: Duration,
+    wake_after: Option<Duration>,
+    state: State,
+}
+
+impl Schedule {
+    /// `None` if the displays are to be left on.
+    pub fn new(config: &BlankingConfig, now: Instant) -> Option<Self> {
+        let minutes = |mins: u32| Duration::from_secs(mins as u64 * 60);
+        Some(Self {
+            blank_after: minutes(config.after_mins.filter(|&mins| mins > 0)?),
+            wake_after: config.wake_after_mins.filter(|&mins| mins > 0).map(minutes),
+            state: State::Running { since: now },
+        })
+    }
+
+    /// Whether the animation is fading out or the displays are off.
+    pub fn is_blanking(&self) -> bool {
+        matches!(self.state, State::FadingOut { .. } | State::Blanked { .. })
+    }
+
+    pub fn poll(&mut self, now: Instant) -> Action {
+        match self.state {
+            State::Running { since }
+                if now.saturating_duration_since(since) >= self.blank_after =>
+            {
+                self.state = State::FadingOut { since: now };
+                Action::Fade(1.0)
+            }
+            State::Running { .. } => Action::None,
+            State::FadingOut { since } => {
+                let progress = now.saturating_duration_since(since).as_secs_f32()
+                    / FADE_DURATION.as_secs_f32();
+                if progress >= 1.0 {
+                    self.state = State::Blanked { since: now };
+                    Action::Blank
+                } else {
+                    Action::Fade(1.0 - progress)
+                }
+            }
+            State::Blanked { since }
+                if self.wake_after.is_some_and(|wake_after| {
+                    now.saturating_duration_since(since) >= wake_after
+                }) =>
+            {
+                self.wake(now)
+            }
+            State::Blanked { .. } => Action::None,
+            State::FadingIn { since } => {
+                let progress = now.saturating_duration_since(since).as_secs_f32()
+                    / FADE_DURATION.as_secs_f32();
+                if progress >= 1.0 {
+                    self.state = State::Running { since };
+                }
+                Action::Fade(progress.min(1.0))
+            }
+        }
+    }
+
+    /// Wake back into the animation, like after input too slight to exit the screensaver, and
+    /// fade it back in. The schedule starts over from here.
+    pub fn wake(&mut self, now: Instant) -> Action {
+        if !self.is_blanking() {
+            return Action::None;
+        }
+        self.state = State::FadingIn { since: now };
+        Action::Wake
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn minutes(mins: u64) -> Duration {
+        Duration::from_secs(mins * 60)
+    }
+
+    #[test]
+    fn it_blanks_and_wakes_on_schedule() {
+        let start = Instant::now();
+        let config = BlankingConfig {
+            after_mins: Some(10),
+            wake_after_mins: Some(5),
+        };
+        let mut schedule = Schedule::new(&config, start).unwrap();
+
+        assert_eq!(schedule.poll(start + minutes(9)), Action::None);
+        let fade_start = start + minutes(10);
+        assert_eq!(schedule.poll(fade_start), Action::Fade(1.0));
+        assert!(schedule.is_blanking());
+        assert_eq!(
+            schedule.poll(fade_start + FADE_DURATION / 2),
+            Action::Fade(0.5)
+        );
+        let blanked = fade_start + FADE_DURATION;
+        assert_eq!(schedule.poll(blanked), Action::Blank);
+        assert_eq!(schedule.poll(blanked + minutes(4)), Action::None);
+
+        let woken = blanked + minutes(5);
+        assert_eq!(schedule.poll(woken), Action::Wake);
+        assert!(!schedule.is_blanking());
+        assert_eq!(schedule.poll(woken + FADE_DURATION / 2), Action::Fade(0.5));
+        assert_eq!(schedule.poll(woken + FADE_DURATION), Action::Fade(1.0));
+        // The next round starts from when it woke up.
+        assert_eq!(schedule.poll(woken + minutes(9)), Action::None);
+        assert_eq!(schedule.poll(woken + minutes(10)), Action::Fade(1.0));
+    }
+
+    #[test]
+    fn it_stays_blank_until_woken() {
+        let start = Instant::now();
+        let config = BlankingConfig {
+            after_mins: Some(1),
+            wake_after_mins: None,
+        };
+        let mut schedule = Schedule::new(&config, start).unwrap();
+
+        assert_eq!(schedule.wake(start), Action::None);
+        schedule.poll(start + minutes(1));
+        let blanked = start + minutes(1) + FADE_DURATION;
+        assert_eq!(schedule.poll(blanked), Action::Blank);
+        assert_eq!(schedule.poll(blanked + minutes(600)), Action::None);
+        assert_eq!(schedule.wake(blanked + minutes(600)), Action::Wake);
+
+        assert!(Schedule::new(&BlankingConfig::default(), start).is_none());
+    }
+}
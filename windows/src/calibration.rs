@@ -0,0 +1,147 @@
+use crate::config::{Calibration, Config, Quality};
+use crate::{gl_context, gpu, surface};
+
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use flux::Flux;
+use glow::HasContext;
+use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
+
+// How long to benchmark each quality preset for. All presets together should take no more than
+// a couple of seconds.
+const DURATION_PER_PRESET: Duration = Duration::from_millis(600);
+
+// Frames to discard while shaders compile and the driver warms up.
+const WARMUP_FRAMES: u32 = 5;
+
+// The slowest average frame time that still leaves some headroom at 60 FPS.
+const FRAME_TIME_BUDGET: Duration = Duration::from_millis(12);
+
+/// Make sure the config has a recommended quality for the current GPU, running a short
+/// benchmark if this is the first launch or the GPU has changed since the last one.
+///
+/// The benchmark renders to a hidden window the size of the primary surface, so nothing shows on
+/// screen.
+pub fn run_if_needed(
+    video_subsystem: &sdl2::VideoSubsystem,
+    config: &mut Config,
+    surfaces: &[surface::Surface],
+) -> Result<(), String> {
+    let Some(surface) = surfaces.first() else {
+        return Ok(());
+    };
+    if config.flux.quality != Quality::Automatic {
+        return Ok(());
+    }
+
+    // Check against the GPUs that DXGI lists first, to avoid opening a window and a GL context
+    // just to find out that the GPU hasn’t changed. OpenGL names the GPU the same way, with a
+    // suffix on some drivers.
+    #[cfg(windows)]
+    if config.flux.calibration.as_ref().is_some_and(|calibration| {
+        crate::platform::windows::gpu_preference::adapters()
+            .iter()
+            .any(|adapter| {
+                !adapter.name.is_empty() && calibration.renderer.starts_with(&adapter.name)
+            })
+    }) {
+        return Ok(());
+    }
+
+    let size = surface.size();
+    let window = video_subsystem
+        .window("Flux calibration", size.width, size.height)
+        .hidden()
+        .build()
+        .map_err(|err| err.to_string())?;
+
+    let gl_context = gl_context::new_gl_context(
+        window.raw_display_handle(),
+        size,
+        window.raw_window_handle(),
+        None,
+        false,
+        false,
+    )?;
+
+    let renderer = gpu::renderer(&gl_context.gl);
+    if config
+        .flux
+        .calibration
+        .as_ref()
+        .is_some_and(|calibration| calibration.renderer == renderer)
+    {
+        return Ok(());
+    }
+
+    log::info!("Calibrating the quality for {}", renderer);
+
+    // Pick the highest quality that stays within budget. Each preset is more expensive than the
+    // last, so stop as soon as one is too slow.
+    let mut quality = Quality::Low;
+    for preset in [Quality::Low, Quality::Medium, Quality::High, Quality::Ultra] {
+        let frame_time = benchmark(&gl_context.gl, surface, preset)?;
+        log::debug!("{} quality: {:?} per frame", preset, frame_time);
+
+        if frame_time > FRAME_TIME_BUDGET {
+            break;
+        }
+        quality = preset;
+    }
+
+    log::info!("Recommending {} quality for {}", quality, renderer);
+    let calibration = Calibration { renderer, quality };
+    config.flux.calibration = Some(calibration.clone());
+    config
+        .save_change(|saved| saved.flux.calibration = Some(calibration))
+        .map_err(|err| err.to_string())
+}
+
+// Measure the average time it takes to render a frame at the given quality.
+fn benchmark(
+    gl: &Rc<glow::Context>,
+    surface: &surface::Surface,
+    quality: Quality,
+) -> Result<Duration, String> {
+    let mut settings = flux::settings::Settings::default();
+    quality.apply(&mut settings);
+
+    let physical_size = surface.size();
+    let logical_size = physical_size.to_logical(surface.scale_factor());
+    let mut flux = Flux::new(
+        gl,
+        logical_size.width,
+        logical_size.height,
+        physical_size.width,
+        physical_size.height,
+        &Rc::new(settings),
+    )
+    .map_err(|err| err.to_string())?;
+
+    let start = Instant::now();
+    let mut frames = 0;
+    let mut measured_since = start;
+
+    while start.elapsed() < DURATION_PER_PRESET {
+        if frames == WARMUP_FRAMES {
+            measured_since = Instant::now();
+        }
+
+        flux.compute(start.elapsed().as_secs_f64() * 1000.0);
+        unsafe { gl.bind_framebuffer(glow::FRAMEBUFFER, None) };
+        flux.render();
+        // Wait for the GPU, otherwise we’re only measuring how quickly commands are queued.
+        unsafe { gl.finish() };
+
+        frames += 1;
+    }
+
+    let measured_frames = frames.saturating_sub(WARMUP_FRAMES);
+    if measured_frames == 0 {
+        // Couldn’t even get through the warmup
+        return Ok(Duration::MAX);
+    }
+
+    Ok(measured_since.elapsed() / measured_frames)
+}
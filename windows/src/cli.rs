@@ -6,12 +6,119 @@ use std::ffi::c_void;
 #[cfg(windows)]
 use windows::Win32::System::LibraryLoader::GetModuleHandleW;
 
+use std::path::PathBuf;
+#[cfg(windows)]
+use std::time::Duration;
+
 #[derive(PartialEq)]
 pub enum Mode {
     #[cfg(windows)]
     Preview(RawWindowHandle),
-    Screensaver,
+    // Draw into an X11 window created by XScreenSaver
+    #[cfg(target_os = "linux")]
+    XScreenSaver(u64),
+    Screensaver {
+        // Lock the workstation once the screensaver exits
+        lock_on_exit: bool,
+    },
+    // Run the screensaver on a single surface, as a child of a screensaver that isolates its
+    // monitors
+    Instance {
+        surface: usize,
+    },
+    #[cfg(windows)]
+    Wallpaper,
+    // Stay in the background and start the screensaver once the computer is idle
+    #[cfg(windows)]
+    Daemon,
+    // Run as a Windows service, keeping an instance going in each session
+    #[cfg(windows)]
+    Service(ServiceOptions),
+    // Register the service with Windows and start it
+    #[cfg(windows)]
+    InstallService(ServiceOptions),
+    // Stop the service and remove it from Windows
+    #[cfg(windows)]
+    UninstallService,
     Settings,
+    // Run in a regular, resizable window
+    Windowed,
+    // Change and save the settings without opening the settings window
+    SetConfig(Vec<String>),
+    // Change and save the settings like `SetConfig`, but refuse unknown keys and invalid values
+    SetConfigStrictly(Vec<String>),
+    // Shuffle the look of the simulation and save it, for a running screensaver to pick up
+    Shuffle,
+    // Print a single setting from the settings file
+    GetConfig(String),
+    // Put one setting, or all of them, back to the default
+    ResetConfig(Option<String>),
+    // Print the effective settings, with optional overrides on top
+    PrintConfig(Vec<String>),
+    // Print a JSON Schema for the settings file
+    ConfigSchema,
+    // Measure how long presenting takes, optionally through both swapchains
+    #[cfg(windows)]
+    Benchmark {
+        compare_swapchains: bool,
+    },
+    // Run for hours, watching the memory and handles for leaks
+    #[cfg(windows)]
+    Soak {
+        duration: Duration,
+        windowed: bool,
+    },
+    // Render a clip offscreen and save it as a video
+    Record {
+        seconds: u32,
+        output: PathBuf,
+    },
+}
+
+// What the service runs in each session
+#[cfg(windows)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ServiceOptions {
+    // Run the screensaver, and start it again whenever it exits, instead of the live wallpaper
+    pub screensaver: bool,
+    // Also run on the login screen, before anyone logs in
+    pub login_screen: bool,
+}
+
+#[cfg(windows)]
+impl ServiceOptions {
+    // The flags to pass back to the service when Windows starts it
+    pub fn to_args(&self) -> Vec<&'static str> {
+        let mut args = Vec::new();
+        if self.screensaver {
+            args.push("--screensaver");
+        }
+        if self.login_screen {
+            args.push("--login-screen");
+        }
+        args
+    }
+}
+
+impl Mode {
+    // Whether Flux is drawn behind the desktop icons
+    pub fn is_wallpaper(&self) -> bool {
+        #[cfg(windows)]
+        if let Mode::Wallpaper = self {
+            return true;
+        }
+
+        false
+    }
+
+    // Whether to create an instance for the surface at this index
+    #[cfg(feature = "sdl")]
+    pub fn shows_surface(&self, index: usize) -> bool {
+        match self {
+            Mode::Instance { surface } => *surface == index,
+            _ => true,
+        }
+    }
 }
 
 #[cfg(windows)]
@@ -39,7 +146,136 @@ pub fn read_flags() -> Result<Mode, String> {
         //
         // /S -> <right click + test> sends an uppercase /S, which doesn’t
         // seem to be documented anywhere.
-        Some("/s") => Ok(Mode::Screensaver),
+        Some("/s") => Ok(Mode::Screensaver {
+            lock_on_exit: false,
+        }),
+
+        // Blank all monitors on demand
+        //
+        // --blank-now -> run the screensaver immediately. This is meant to be
+        // bound to a hotkey, like the one on the Start menu shortcut.
+        //
+        // --blank-now --lock -> also lock the workstation when the screensaver
+        // exits.
+        Some("--blank-now") => {
+            let lock_on_exit = std::env::args()
+                .nth(2)
+                .map_or(false, |arg| arg.eq_ignore_ascii_case("--lock"));
+
+            Ok(Mode::Screensaver { lock_on_exit })
+        }
+
+        // Run as a live wallpaper
+        //
+        // --wallpaper -> draw behind the desktop icons until the process is closed.
+        Some("--wallpaper") => Ok(Mode::Wallpaper),
+
+        // Start the screensaver by ourselves
+        //
+        // --daemon -> wait in the background, and start the screensaver whenever there’s been no
+        // input for a while. This works without installing the screensaver in Windows.
+        Some("--daemon") => Ok(Mode::Daemon),
+
+        // Run on signage and kiosks
+        //
+        // --service install [--screensaver] [--login-screen] -> register a Windows service that
+        // keeps the live wallpaper going in every session, or the screensaver with
+        // `--screensaver`. With `--login-screen`, it also runs before anyone logs in. This needs
+        // an administrator.
+        //
+        // --service uninstall -> stop the service and remove it.
+        //
+        // --service [--screensaver] [--login-screen] -> what Windows runs to start the service.
+        Some("--service") => read_service(std::env::args().skip(2)),
+
+        // Run in a window
+        //
+        // --window -> show Flux in a regular window that can be moved and resized, which is
+        // handy for demos and testing.
+        Some("--window") => Ok(Mode::Windowed),
+
+        // Configure silently
+        //
+        // --set key=value [--set key=value ...] -> change the settings file and exit. This lets
+        // the installer and deployment scripts preconfigure defaults. Settings this release
+        // doesn’t know about are dropped, unlike with `config set`.
+        Some("--set") => read_overrides(std::env::args().skip(1)).map(Mode::SetConfig),
+
+        // --shuffle -> randomize the look of the simulation, like the button in the settings
+        // window. A running screensaver switches over to it on every monitor.
+        Some("--shuffle") => Ok(Mode::Shuffle),
+
+        // Script the settings
+        //
+        // config get <key> -> print a single setting as JSON, like `config get
+        // platform.windows.fillMode`.
+        //
+        // config set <key>=<value> [<key>=<value> ...] -> change the settings file and exit.
+        // Unknown keys and invalid values are refused, and leave the file as it was.
+        //
+        // config reset [<key>] -> put one setting, or all of them, back to the default.
+        Some("config") => read_config_command(std::env::args().skip(2)),
+
+        // Debug the settings
+        //
+        // --print-config [--set key=value ...] -> print the effective settings as JSON, along
+        // with where each value came from.
+        Some("--print-config") => read_overrides(std::env::args().skip(2)).map(Mode::PrintConfig),
+
+        // --config-schema -> print a JSON Schema for the settings file, for editor autocomplete
+        // and validation.
+        Some("--config-schema") => Ok(Mode::ConfigSchema),
+
+        // Compare presentation paths
+        //
+        // --benchmark -> render on the primary monitor for a few seconds, and report the present
+        // latency and frame pacing.
+        //
+        // --benchmark --compare-swapchains -> do so once through the GL swapchain and once
+        // through DXGI, to see which one suits this machine better.
+        Some("--benchmark") => {
+            let compare_swapchains = std::env::args().nth(2).map_or(false, |arg| {
+                arg.eq_ignore_ascii_case("--compare-swapchains")
+            });
+
+            Ok(Mode::Benchmark { compare_swapchains })
+        }
+
+        // Catch slow leaks
+        //
+        // --soak <hours> -> render in a hidden window for that long, logging the memory, handles,
+        // and frame rate as it goes, and fail once any of them grows past the limits in the
+        // settings.
+        //
+        // --soak <hours> --window -> do so on the primary monitor instead, through the same
+        // swapchain as the screensaver.
+        Some("--soak") => read_soak(std::env::args().skip(2)),
+
+        // Run with a saved look
+        //
+        // --preset <name> -> run the screensaver with one of the presets saved in the settings
+        // window. It can also follow the other modes, like `--window --preset <name>`.
+        Some("--preset") => Ok(Mode::Screensaver {
+            lock_on_exit: false,
+        }),
+
+        // Debug the performance
+        //
+        // --stats -> draw the frame rate, frame times, and GPU memory over the animation. It can
+        // also follow the other modes, like `--window --stats`.
+        Some("--stats") => Ok(Mode::Screensaver {
+            lock_on_exit: false,
+        }),
+
+        // Make a demo clip
+        //
+        // --record <seconds> <output.mp4> -> render Flux offscreen and encode it to a video with
+        // ffmpeg.
+        Some("--record") => read_record(std::env::args().skip(2)),
+
+        // --instance <surface> -> run the screensaver on one surface only. The screensaver
+        // starts one of these for each surface when it isolates the monitors.
+        Some("--instance") => read_instance(std::env::args().nth(2)),
 
         // Run preview or in Wallpaper Engine
         //
@@ -70,7 +306,170 @@ pub fn read_flags() -> Result<Mode, String> {
     }
 }
 
+// Read a list of `--set key=value` pairs.
+#[cfg(windows)]
+fn read_overrides(mut args: impl Iterator<Item = String>) -> Result<Vec<String>, String> {
+    let mut overrides = Vec::new();
+    while let Some(arg) = args.next() {
+        if !arg.eq_ignore_ascii_case("--set") {
+            return Err(format!("Expected --set, but found {}.", arg));
+        }
+        overrides.push(args.next().ok_or("Missing a key=value pair after --set.")?);
+    }
+
+    Ok(overrides)
+}
+
+// Find the preset to run with, from `--preset <name>` anywhere in the arguments.
+pub fn read_preset() -> Result<Option<String>, String> {
+    let mut args = std::env::args().skip(1);
+    match args.find(|arg| arg.eq_ignore_ascii_case("--preset")) {
+        Some(_) => args
+            .next()
+            .ok_or("Missing the name of the preset after --preset.".to_string())
+            .map(Some),
+        None => Ok(None),
+    }
+}
+
+// Whether `--stats` is anywhere in the arguments.
+pub fn read_stats() -> bool {
+    std::env::args()
+        .skip(1)
+        .any(|arg| arg.eq_ignore_ascii_case("--stats"))
+}
+
+// Read the subcommand after `config`.
+fn read_config_command(mut args: impl Iterator<Item = String>) -> Result<Mode, String> {
+    let command = args
+        .next()
+        .ok_or("Missing the config command: get, set, or reset.")?;
+
+    match command.as_str() {
+        "get" => args
+            .next()
+            .ok_or("Missing the setting to get.".to_string())
+            .map(Mode::GetConfig),
+        "set" => {
+            let settings = args.collect::<Vec<String>>();
+            if settings.is_empty() {
+                return Err("Missing a key=value pair to set.".to_string());
+            }
+
+            Ok(Mode::SetConfigStrictly(settings))
+        }
+        "reset" => Ok(Mode::ResetConfig(args.next())),
+        _ => Err(format!(
+            "I don’t know what the config command {} is.",
+            command
+        )),
+    }
+}
+
+// Read the length and output file of a recording.
+fn read_record(mut args: impl Iterator<Item = String>) -> Result<Mode, String> {
+    let seconds = args
+        .next()
+        .ok_or("Missing the number of seconds to record.")?
+        .parse::<u32>()
+        .map_err(|e| format!("Can't parse the number of seconds to record: {}", e))?;
+    if seconds == 0 {
+        return Err("Can't record a clip that’s 0 seconds long.".to_string());
+    }
+    let output = args
+        .next()
+        .ok_or("Missing the file to save the video to.")?;
+
+    Ok(Mode::Record {
+        seconds,
+        output: PathBuf::from(output),
+    })
+}
+
+// Read the length of a soak test, and whether to run it on screen.
+#[cfg(windows)]
+fn read_soak(mut args: impl Iterator<Item = String>) -> Result<Mode, String> {
+    let hours = args
+        .next()
+        .ok_or("Missing the number of hours to run the soak test for.")?
+        .parse::<f64>()
+        .map_err(|e| {
+            format!(
+                "Can't parse the number of hours to run the soak test for: {}",
+                e
+            )
+        })?;
+    if !(hours.is_finite() && hours > 0.0) {
+        return Err("The soak test needs to run for more than 0 hours.".to_string());
+    }
+    let windowed = args
+        .next()
+        .map_or(false, |arg| arg.eq_ignore_ascii_case("--window"));
+
+    Ok(Mode::Soak {
+        duration: Duration::from_secs_f64(hours * 3600.0),
+        windowed,
+    })
+}
+
+#[cfg(windows)]
+fn read_service(args: impl Iterator<Item = String>) -> Result<Mode, String> {
+    let mut args = args.map(|arg| arg.to_ascii_lowercase()).peekable();
+    let command = args.next_if(|arg| arg == "install" || arg == "uninstall");
+
+    let mut options = ServiceOptions::default();
+    for arg in args {
+        match arg.as_str() {
+            "--screensaver" => options.screensaver = true,
+            "--login-screen" => options.login_screen = true,
+            _ => return Err(format!("I don’t know what the service option {} is.", arg)),
+        }
+    }
+
+    Ok(match command.as_deref() {
+        Some("install") => Mode::InstallService(options),
+        Some("uninstall") => Mode::UninstallService,
+        _ => Mode::Service(options),
+    })
+}
+
+fn read_instance(arg: Option<String>) -> Result<Mode, String> {
+    let surface = arg
+        .ok_or("Missing the surface to run the instance on.")?
+        .parse::<usize>()
+        .map_err(|e| format!("Can't parse the surface index: {}", e))?;
+
+    Ok(Mode::Instance { surface })
+}
+
 #[cfg(not(windows))]
 pub fn read_flags() -> Result<Mode, String> {
-    Ok(Mode::Screensaver)
+    match std::env::args().nth(1).as_deref() {
+        Some("--window") => Ok(Mode::Windowed),
+        Some("--record") => read_record(std::env::args().skip(2)),
+        Some("--instance") => read_instance(std::env::args().nth(2)),
+        Some("config") => read_config_command(std::env::args().skip(2)),
+        Some("--shuffle") => Ok(Mode::Shuffle),
+
+        // Run as an XScreenSaver hack
+        //
+        // -root -> XScreenSaver runs hacks with -root when blanking the screen, although the
+        // window it wants us to draw into is passed down separately.
+        //
+        // -window-id ID -> draw into the preview in the XScreenSaver settings.
+        #[cfg(target_os = "linux")]
+        Some("-root") | Some("--root") => {
+            crate::platform::linux::xscreensaver::window_from_env().map(Mode::XScreenSaver)
+        }
+        #[cfg(target_os = "linux")]
+        Some("-window-id") => std::env::args()
+            .nth(2)
+            .ok_or("Missing the window ID after -window-id.".to_string())
+            .and_then(|id| crate::platform::linux::xscreensaver::parse_window_id(&id))
+            .map(Mode::XScreenSaver),
+
+        _ => Ok(Mode::Screensaver {
+            lock_on_exit: false,
+        }),
+    }
 }
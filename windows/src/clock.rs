@@ -0,0 +1,369 @@
+use std::time::{Duration, Instant};
+
+/// The animation clock of a single instance.
+///
+/// Each instance keeps its own clock, so that pausing or throttling one monitor doesn’t make the
+/// animation on the other monitors jump ahead. Time spent paused is excluded from the elapsed
+/// time, and the animation picks up where it left off when resumed.
+///
+/// Clocks that share an epoch count their timestamps from the same moment, so instances that
+/// start at different times still show the same moment of the animation until one of them pauses.
+#[derive(Clone, Copy, Debug)]
+pub struct Clock {
+    // Where the timestamps count from
+    epoch: Instant,
+    // When the animation starts
+    start: Instant,
+    // Total time spent paused
+    paused_for: Duration,
+    // When the clock was last paused, if it’s paused now
+    paused_at: Option<Instant>,
+}
+
+impl Clock {
+    pub fn new(start: Instant) -> Self {
+        Self::in_step_with(start, start)
+    }
+
+    /// A clock that starts at `start`, but counts from the `epoch` of another clock.
+    pub fn in_step_with(epoch: Instant, start: Instant) -> Self {
+        Self {
+            epoch: epoch.min(start),
+            start,
+            paused_for: Duration::ZERO,
+            paused_at: None,
+        }
+    }
+
+    pub fn epoch(&self) -> Instant {
+        self.epoch
+    }
+
+    /// How far along the animation is at `now`, or `None` if it hasn’t started yet.
+    pub fn elapsed(&self, now: Instant) -> Option<Duration> {
+        let now = self.paused_at.unwrap_or(now);
+        if now < self.start {
+            return None;
+        }
+        Some(
+            now.duration_since(self.epoch)
+                .saturating_sub(self.paused_for),
+        )
+    }
+
+    /// The elapsed time in milliseconds, as expected by Flux.
+    pub fn timestamp(&self, now: Instant) -> Option<f64> {
+        self.elapsed(now)
+            .map(|elapsed| elapsed.as_secs_f64() * 1000.0)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    pub fn pause(&mut self, now: Instant) {
+        if self.paused_at.is_none() {
+            self.paused_at = Some(now);
+        }
+    }
+
+    pub fn resume(&mut self, now: Instant) {
+        if let Some(paused_at) = self.paused_at.take() {
+            // Time spent waiting to start isn’t time spent paused.
+            let paused_from = paused_at.max(self.start);
+            self.paused_for += now.saturating_duration_since(paused_from);
+        }
+    }
+}
+
+/// Sleep until the deadline.
+///
+/// The OS can wake the thread a little late, so this sleeps for most of the time and yields for
+/// the rest.
+#[cfg(feature = "sdl")]
+pub fn sleep_until(deadline: Instant) {
+    const SPIN_MARGIN: Duration = Duration::from_millis(1);
+
+    let Some(remaining) = deadline.checked_duration_since(Instant::now()) else {
+        return;
+    };
+    if remaining > SPIN_MARGIN {
+        std::thread::sleep(remaining - SPIN_MARGIN);
+    }
+    while Instant::now() < deadline {
+        std::thread::yield_now();
+    }
+}
+
+/// Steps the simulation at a fixed rate, regardless of how often frames are presented.
+///
+/// Without this, the simulation advances once per presented frame, and runs at a visibly
+/// different speed on a 60 Hz monitor than on a 165 Hz one.
+#[derive(Clone, Copy, Debug)]
+pub struct FixedTimestep {
+    // Milliseconds between ticks
+    interval: f64,
+    // The timestamp of the next tick
+    next_tick: Option<f64>,
+}
+
+impl FixedTimestep {
+    // Give up on catching up after a long stall, rather than running dozens of ticks at once.
+    const MAX_TICKS_PER_FRAME: usize = 4;
+
+    pub fn new(rate_hz: f64) -> Self {
+        Self {
+            interval: 1000.0 / rate_hz,
+            next_tick: None,
+        }
+    }
+
+    /// The timestamps of the ticks that are due by `timestamp`, in milliseconds.
+    pub fn ticks(&mut self, timestamp: f64) -> Vec<f64> {
+        let mut next_tick = self.next_tick.unwrap_or(timestamp);
+        let mut ticks = Vec::new();
+
+        while next_tick <= timestamp {
+            if ticks.len() == Self::MAX_TICKS_PER_FRAME {
+                next_tick = timestamp + self.interval;
+                break;
+            }
+            ticks.push(next_tick);
+            next_tick += self.interval;
+        }
+
+        self.next_tick = Some(next_tick);
+        ticks
+    }
+
+    /// How far `timestamp` is from the last tick towards the next one, from 0 to 1, for blending
+    /// between ticks.
+    pub fn progress(&self, timestamp: f64) -> f32 {
+        let Some(next_tick) = self.next_tick else {
+            return 1.0;
+        };
+        (1.0 - (next_tick - timestamp) / self.interval).clamp(0.0, 1.0) as f32
+    }
+}
+
+/// Eases the animation in when it first starts.
+///
+/// Flux starts from a still fluid, and some settings kick off with a harsh burst of motion. For
+/// the first moments, time runs slower and the lines fade in from black, both easing up to normal
+/// by the end of the ramp.
+#[derive(Clone, Copy, Debug)]
+pub struct StartupRamp {
+    // In milliseconds
+    duration: f64,
+    // The timestamp of the first frame
+    started_at: Option<f64>,
+}
+
+impl StartupRamp {
+    // How fast time runs at the very start, speeding up steadily to normal.
+    const INITIAL_RATE: f64 = 0.25;
+
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            duration: duration.as_secs_f64() * 1000.0,
+            started_at: None,
+        }
+    }
+
+    /// Slow down the timestamp while the ramp is on. Time carries on at the normal rate after that,
+    /// just a little behind.
+    pub fn warp(&mut self, timestamp: f64) -> f64 {
+        let started_at = *self.started_at.get_or_insert(timestamp);
+        let elapsed = (timestamp - started_at).max(0.0);
+        let ramped = elapsed.min(self.duration);
+
+        // The time that has passed at a rate rising from the initial rate to 1
+        let slowed = if self.duration > 0.0 {
+            Self::INITIAL_RATE * ramped
+                + (1.0 - Self::INITIAL_RATE) * ramped * ramped / (2.0 * self.duration)
+        } else {
+            0.0
+        };
+
+        started_at + slowed + (elapsed - ramped)
+    }
+
+    /// How bright to draw the lines, from 0 to 1, or `None` once the ramp is over.
+    pub fn brightness(&self, timestamp: f64) -> Option<f32> {
+        let Some(started_at) = self.started_at else {
+            return Some(0.0);
+        };
+        ease_in(timestamp - started_at, self.duration)
+    }
+}
+
+/// Fades the lines back in from black, like at startup, but without slowing down time.
+#[cfg_attr(not(windows), allow(dead_code))]
+#[derive(Clone, Copy, Debug)]
+pub struct FadeIn {
+    // In milliseconds
+    duration: f64,
+    // The timestamp of the first frame of the fade
+    started_at: Option<f64>,
+}
+
+impl FadeIn {
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            duration: duration.as_secs_f64() * 1000.0,
+            started_at: None,
+        }
+    }
+
+    /// How bright to draw the lines, from 0 to 1, or `None` once the fade is over.
+    pub fn brightness(&mut self, timestamp: f64) -> Option<f32> {
+        let started_at = *self.started_at.get_or_insert(timestamp);
+        ease_in(timestamp - started_at, self.duration)
+    }
+}
+
+// Ease from 0 to 1 over the duration, or `None` once it has passed.
+fn ease_in(elapsed: f64, duration: f64) -> Option<f32> {
+    let progress = if duration > 0.0 {
+        elapsed / duration
+    } else {
+        1.0
+    };
+    if progress >= 1.0 {
+        return None;
+    }
+
+    let progress = progress.clamp(0.0, 1.0) as f32;
+    Some(progress * progress * (3.0 - 2.0 * progress))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_ticks_at_a_fixed_rate() {
+        let mut timestep = FixedTimestep::new(50.0);
+
+        assert_eq!(timestep.ticks(0.0), vec![0.0]);
+        // A high refresh rate presents several frames per tick.
+        assert_eq!(timestep.ticks(7.0), Vec::<f64>::new());
+        assert_eq!(timestep.ticks(14.0), Vec::<f64>::new());
+        assert_eq!(timestep.ticks(21.0), vec![20.0]);
+        // A low refresh rate runs several ticks per frame.
+        assert_eq!(timestep.ticks(65.0), vec![40.0, 60.0]);
+    }
+
+    #[test]
+    fn it_measures_the_progress_between_ticks() {
+        let mut timestep = FixedTimestep::new(50.0);
+        assert_eq!(timestep.progress(0.0), 1.0);
+
+        timestep.ticks(0.0);
+        assert_eq!(timestep.progress(0.0), 0.0);
+        assert_eq!(timestep.progress(5.0), 0.25);
+        timestep.ticks(15.0);
+        assert_eq!(timestep.progress(15.0), 0.75);
+    }
+
+    #[test]
+    fn it_skips_ahead_after_a_stall() {
+        let mut timestep = FixedTimestep::new(50.0);
+        timestep.ticks(0.0);
+
+        assert_eq!(timestep.ticks(1000.0), vec![20.0, 40.0, 60.0, 80.0]);
+        assert_eq!(timestep.ticks(1010.0), Vec::<f64>::new());
+        assert_eq!(timestep.ticks(1020.0), vec![1020.0]);
+    }
+
+    #[test]
+    fn it_eases_in_at_startup() {
+        let mut ramp = StartupRamp::new(Duration::from_secs(2));
+
+        assert_eq!(ramp.warp(1000.0), 1000.0);
+        assert_eq!(ramp.brightness(1000.0), Some(0.0));
+
+        // A quarter of the speed at first, and the average of a quarter and full speed overall
+        assert!((ramp.warp(1010.0) - 1002.5).abs() < 0.1);
+        assert_eq!(ramp.warp(3000.0), 2250.0);
+        assert_eq!(ramp.brightness(2000.0), Some(0.5));
+
+        assert_eq!(ramp.warp(4000.0), 3250.0);
+        assert_eq!(ramp.brightness(3000.0), None);
+    }
+
+    #[test]
+    fn it_fades_in_from_the_first_frame() {
+        let mut fade = FadeIn::new(Duration::from_millis(500));
+
+        assert_eq!(fade.brightness(5000.0), Some(0.0));
+        assert_eq!(fade.brightness(5250.0), Some(0.5));
+        assert_eq!(fade.brightness(5500.0), None);
+    }
+
+    #[cfg(feature = "sdl")]
+    #[test]
+    fn it_sleeps_until_the_deadline() {
+        let deadline = Instant::now() + Duration::from_millis(5);
+        sleep_until(deadline);
+        assert!(Instant::now() >= deadline);
+    }
+
+    #[test]
+    fn it_waits_for_the_start() {
+        let now = Instant::now();
+        let clock = Clock::new(now + Duration::from_secs(1));
+
+        assert_eq!(clock.elapsed(now), None);
+        assert_eq!(
+            clock.elapsed(now + Duration::from_secs(3)),
+            Some(Duration::from_secs(2))
+        );
+    }
+
+    #[test]
+    fn it_excludes_time_spent_paused() {
+        let now = Instant::now();
+        let mut clock = Clock::new(now);
+        let at = |secs| now + Duration::from_secs(secs);
+
+        clock.pause(at(2));
+        assert!(clock.is_paused());
+        assert_eq!(clock.elapsed(at(5)), Some(Duration::from_secs(2)));
+
+        clock.resume(at(5));
+        assert!(!clock.is_paused());
+        assert_eq!(clock.elapsed(at(6)), Some(Duration::from_secs(3)));
+    }
+
+    #[test]
+    fn it_keeps_independent_clocks_in_step() {
+        let now = Instant::now();
+        let at = |secs| now + Duration::from_secs(secs);
+        let mut paused = Clock::new(now);
+        let running = Clock::new(now);
+
+        paused.pause(at(1));
+        paused.resume(at(4));
+
+        assert_eq!(running.elapsed(at(10)), Some(Duration::from_secs(10)));
+        assert_eq!(paused.elapsed(at(10)), Some(Duration::from_secs(7)));
+    }
+
+    #[test]
+    fn it_counts_from_a_shared_epoch() {
+        let now = Instant::now();
+        let at = |secs| now + Duration::from_secs(secs);
+        let first = Clock::new(now);
+        let mut later = Clock::in_step_with(first.epoch(), at(2));
+
+        assert_eq!(later.elapsed(at(1)), None);
+        assert_eq!(later.elapsed(at(2)), Some(Duration::from_secs(2)));
+        assert_eq!(later.elapsed(at(5)), first.elapsed(at(5)));
+
+        // Pausing one of them still only holds that one back.
+        later.pause(at(5));
+        later.resume(at(6));
+        assert_eq!(later.elapsed(at(8)), Some(Duration::from_secs(7)));
+    }
+}
@@ -0,0 +1,247 @@
+use crate::config::Presets;
+
+use std::path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+
+// How many of the best matches to list at once. The palette is shown in the title bar, which
+// only has room for a few.
+const VISIBLE_MATCHES: usize = 4;
+
+/// Something to do from the command palette.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Switch to one of the saved presets, by name
+    UsePreset(String),
+    TogglePause,
+    /// Save the current frame as an image
+    Screenshot,
+    #[cfg(windows)]
+    OpenSettings,
+}
+
+impl Command {
+    fn label(&self) -> String {
+        match self {
+            Command::UsePreset(name) => format!("Preset: {}", name),
+            Command::TogglePause => "Pause or resume".to_string(),
+            Command::Screenshot => "Take a screenshot".to_string(),
+            #[cfg(windows)]
+            Command::OpenSettings => "Open settings".to_string(),
+        }
+    }
+}
+
+/// A searchable list of commands, opened with a key while Flux runs in a window.
+///
+/// Typing narrows down the commands to the ones that contain the typed letters in order, best
+/// matches first. The animation carries on underneath.
+#[derive(Debug, Default)]
+pub struct CommandPalette {
+    commands: Vec<Command>,
+    is_open: bool,
+    query: String,
+    // Index into the current matches
+    selected: usize,
+}
+
+impl CommandPalette {
+    pub fn new(presets: &Presets) -> Self {
+        let mut commands = vec![Command::TogglePause, Command::Screenshot];
+        #[cfg(windows)]
+        commands.push(Command::OpenSettings);
+        commands.extend(
+            presets
+                .presets
+                .iter()
+                .map(|preset| Command::UsePreset(preset.name.clone())),
+        );
+
+        Self {
+            commands,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.is_open
+    }
+
+    /// Open the palette with an empty search, or close it.
+    pub fn toggle(&mut self) {
+        self.is_open = !self.is_open;
+        self.query.clear();
+        self.selected = 0;
+    }
+
+    /// Handle input while the palette is open, returning the command to run once one is picked.
+    pub fn handle_event(&mut self, event: &Event) -> Option<Command> {
+        match event {
+            Event::TextInput { text, .. } => self.type_text(text),
+            Event::KeyDown {
+                keycode: Some(keycode),
+                ..
+            } => match *keycode {
+                Keycode::Escape => self.toggle(),
+                Keycode::Return | Keycode::KpEnter => return self.submit(),
+                Keycode::Backspace => {
+                    self.query.pop();
+                    self.selected = 0;
+                }
+                Keycode::Up => self.selected = self.selected.saturating_sub(1),
+                Keycode::Down | Keycode::Tab => {
+                    let count = self.matches().len();
+                    self.selected = (self.selected + 1).min(count.saturating_sub(1));
+                }
+                _ => (),
+            },
+            _ => (),
+        }
+
+        None
+    }
+
+    fn type_text(&mut self, text: &str) {
+        self.query.push_str(text);
+        self.selected = 0;
+    }
+
+    // Run the selected command, closing the palette.
+    fn submit(&mut self) -> Option<Command> {
+        let command = self.matches().get(self.selected).cloned().cloned();
+        if command.is_some() {
+            self.toggle();
+        }
+        command
+    }
+
+    /// The commands that match the search, best matches first.
+    pub fn matches(&self) -> Vec<&Command> {
+        let mut matches = self
+            .commands
+            .iter()
+            .filter_map(|command| Some((score(&self.query, &command.label())?, command)))
+            .collect::<Vec<_>>();
+        // Keep ties in their original order.
+        matches.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        matches.into_iter().map(|(_, command)| command).collect()
+    }
+
+    /// The search on the first line, then the best matches below it, the selected one in brackets.
+    pub fn summary(&self) -> String {
+        let matches = self.matches();
+        if matches.is_empty() {
+            return format!("> {}\nNo matching commands", self.query);
+        }
+
+        // Scroll the list along with the selection.
+        let first = self.selected.saturating_sub(VISIBLE_MATCHES - 1);
+        let listed = matches
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(VISIBLE_MATCHES)
+            .map(|(index, command)| match index == self.selected {
+                true => format!("[{}]", command.label()),
+                false => command.label(),
+            })
+            .collect::<Vec<_>>();
+
+        format!("> {}\n{}", self.query, listed.join("\n"))
+    }
+}
+
+// Score how well the query matches the label, if all of its letters appear in the label in
+// order. Letters that follow on from each other, or that start a word, count for more.
+fn score(query: &str, label: &str) -> Option<u32> {
+    let label = label.to_lowercase().chars().collect::<Vec<char>>();
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous: Option<usize> = None;
+
+    for letter in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = (next..label.len()).find(|&index| label[index] == letter)?;
+
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == found) {
+            score += 2;
+        }
+        if found == 0 || !label[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+
+        previous = Some(found);
+        next = found + 1;
+    }
+
+    Some(score)
+}
+
+/// Where to save a screenshot taken now: in a Flux folder in the user’s pictures, if there is
+/// one.
+pub fn screenshot_path() -> path::PathBuf {
+    let dir = directories::UserDirs::new()
+        .and_then(|dirs| dirs.picture_dir().map(path::Path::to_path_buf))
+        .unwrap_or_else(std::env::temp_dir);
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+
+    dir.join("Flux").join(format!("Flux {}.png", seconds))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::{FluxSettings, Preset};
+
+    fn palette() -> CommandPalette {
+        let mut presets = Presets::default();
+        presets.insert(Preset::new("Calm".to_string(), &FluxSettings::default()));
+        presets.insert(Preset::new("Party".to_string(), &FluxSettings::default()));
+        CommandPalette::new(&presets)
+    }
+
+    #[test]
+    fn it_ranks_the_closest_matches_first() {
+        assert_eq!(score("", "Anything"), Some(0));
+        assert_eq!(score("xyz", "Pause or resume"), None);
+        assert!(score("pa", "Pause or resume") > score("pa", "Preset: Party"));
+
+        let mut palette = palette();
+        palette.toggle();
+        palette.type_text("part");
+        assert_eq!(
+            palette.matches(),
+            [&Command::UsePreset("Party".to_string())]
+        );
+    }
+
+    #[test]
+    fn it_runs_the_selected_command_and_closes() {
+        let mut palette = palette();
+        palette.toggle();
+        palette.type_text("p");
+        let down = Event::KeyDown {
+            timestamp: 0,
+            window_id: 0,
+            keycode: Some(Keycode::Down),
+            scancode: None,
+            keymod: sdl2::keyboard::Mod::NOMOD,
+            repeat: false,
+        };
+        palette.handle_event(&down);
+        let expected = palette.matches()[1].clone();
+
+        assert_eq!(palette.submit(), Some(expected));
+        assert!(!palette.is_open());
+
+        palette.toggle();
+        palette.type_text("nothing like it");
+        assert_eq!(palette.submit(), None);
+        assert!(palette.is_open());
+        assert!(palette.summary().ends_with("No matching commands"));
+    }
+}
@@ -0,0 +1,958 @@
+use crate::config::Rotation;
+use crate::mask::Mask;
+
+use std::path;
+use std::rc::Rc;
+
+use glow as GL;
+use glow::HasContext;
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+// The most monitors that can be letterboxed in a single window
+const MAX_LETTERBOXES: usize = 8;
+
+const VERTEX_SHADER: &str = r#"
+out vec2 vTexCoord;
+
+void main() {
+    // Draw a single triangle that covers the whole viewport.
+    vec2 position = vec2(float((gl_VertexID << 1) & 2), float(gl_VertexID & 2));
+    vTexCoord = position;
+    gl_Position = vec4(position * 2.0 - 1.0, 0.0, 1.0);
+}
+"#;
+
+const FRAGMENT_SHADER: &str = r#"
+in vec2 vTexCoord;
+out vec4 fragColor;
+
+uniform sampler2D uTexture;
+uniform sampler2D uMask;
+uniform float uOpacity;
+uniform bool uFlipY;
+uniform bool uUseMask;
+// The colors are already multiplied by the alpha, like in the calmed frames
+uniform bool uPremultiplied;
+// Scale the alpha by the motion mask, to take in only part of the frame
+uniform sampler2D uMotion;
+uniform bool uUseMotion;
+// Quarter turns to undo, for a monitor that is mounted turned clockwise
+uniform int uQuarterTurns;
+// The monitors across and down a video wall, which are each turned on their own
+uniform vec2 uTiles;
+// Convert to linear light, with white at this level, for an scRGB swapchain
+uniform bool uLinearOutput;
+uniform float uWhiteLevel;
+// Monitors to shrink the frame into, with black around it, each as a left, bottom, width and
+// height, and how much to shrink it by
+uniform int uLetterboxCount;
+uniform vec4 uLetterboxAreas[8];
+uniform float uLetterboxScales[8];
+
+vec2 unrotateTile(vec2 coord) {
+    if (uQuarterTurns == 1) return vec2(coord.y, 1.0 - coord.x);
+    if (uQuarterTurns == 2) return vec2(1.0 - coord.x, 1.0 - coord.y);
+    if (uQuarterTurns == 3) return vec2(1.0 - coord.y, coord.x);
+    return coord;
+}
+
+// The tiles sit in the same places in the texture as on screen, so only each tile is turned.
+vec2 unrotate(vec2 coord) {
+    vec2 tile = min(floor(coord * uTiles), uTiles - 1.0);
+    return (tile + unrotateTile(coord * uTiles - tile)) / uTiles;
+}
+
+vec3 srgbToLinear(vec3 color) {
+    vec3 low = color / 12.92;
+    vec3 high = pow((color + 0.055) / 1.055, vec3(2.4));
+    return mix(low, high, step(vec3(0.04045), color));
+}
+
+// Where to sample the frame from, or outside of 0 to 1 for the bars around the letterbox.
+vec2 letterbox(vec2 coord) {
+    for (int i = 0; i < uLetterboxCount; i++) {
+        vec4 area = uLetterboxAreas[i];
+        vec2 local = (coord - area.xy) / area.zw;
+        if (all(greaterThanEqual(local, vec2(0.0))) && all(lessThan(local, vec2(1.0)))) {
+            vec2 boxed = (local - 0.5) / uLetterboxScales[i] + 0.5;
+            if (any(lessThan(boxed, vec2(0.0))) || any(greaterThan(boxed, vec2(1.0)))) {
+                return vec2(-1.0);
+            }
+            return area.xy + boxed * area.zw;
+        }
+    }
+    return coord;
+}
+
+void main() {
+    vec2 flipped = vec2(vTexCoord.x, 1.0 - vTexCoord.y);
+    vec2 coord = vTexCoord;
+    if (!uFlipY && !uPremultiplied) {
+        coord = letterbox(vTexCoord);
+        if (coord.x < 0.0) {
+            fragColor = vec4(0.0, 0.0, 0.0, 1.0);
+            return;
+        }
+    }
+    vec4 color = texture(uTexture, uFlipY ? flipped : unrotate(coord));
+    if (uPremultiplied && color.a > 0.0) {
+        color.rgb /= color.a;
+    }
+    if (uUseMask) {
+        color.rgb *= texture(uMask, flipped).r;
+    }
+    if (uLinearOutput) {
+        color.rgb = srgbToLinear(color.rgb) * uWhiteLevel;
+    }
+    float opacity = uOpacity;
+    if (uUseMotion) {
+        opacity *= texture(uMotion, flipped).r;
+    }
+    // Premultiplied, so that layers blended into a texture can be blended again later on
+    float alpha = color.a * opacity;
+    fragColor = vec4(color.rgb * alpha, alpha);
+}
+"#;
+
+/// Renders Flux into an offscreen texture, which is then dimmed by an optional mask and blended
+/// over a background image when presenting the frame.
+///
+/// To calm the motion in parts of the surface, each frame is blended into a texture that holds
+/// on to the frames before it, with a motion mask setting how much of the new frame to take in.
+///
+/// With a simulation that steps slower than the display refreshes, the frame from the previous
+/// step is kept in a texture of its own, and each frame in between blends from it towards the
+/// latest step.
+///
+/// The layers are blended with premultiplied alpha. Two layers that blend into each other, like
+/// the scenes in a cross-fade, are weighted so that together they cover as much of the
+/// background as one would.
+///
+/// For a rotated monitor, Flux renders upright into a texture with the sides swapped as needed,
+/// which is turned to match the monitor when it’s drawn. The background, the mask and the calmed
+/// frames stay as they are on screen. A video wall is split into tiles, one for each monitor, and
+/// each tile is turned on its own.
+///
+/// While cross-fading between two scenes, the incoming scene renders into a second texture,
+/// which is blended over the first.
+///
+/// A window spanning monitors of different densities can letterbox the less dense ones, so that
+/// Flux looks the same size on each of them.
+pub struct Compositor {
+    gl: Rc<glow::Context>,
+    size: PhysicalSize<u32>,
+    rotation: Rotation,
+    // The monitors across and down, for a video wall
+    tiles: (u32, u32),
+    // The size of the offscreen textures, relative to the window, which are stretched to fit
+    render_scale: f64,
+    program: GL::NativeProgram,
+    vertex_array: GL::NativeVertexArray,
+    framebuffer: GL::NativeFramebuffer,
+    texture: GL::NativeTexture,
+    // The scene fading in, if there’s a cross-fade going on
+    incoming: Option<(GL::NativeFramebuffer, GL::NativeTexture)>,
+    // The frame from the simulation step before the latest one, if interpolating
+    previous: Option<(GL::NativeFramebuffer, GL::NativeTexture)>,
+    // How many steps have been rendered since the frames were reset, up to 2
+    steps: u8,
+    // How far along to the latest step the frame is, from 0 to 1
+    step_progress: f32,
+    background: Option<GL::NativeTexture>,
+    mask: Option<GL::NativeTexture>,
+    // The frames so far blended together, and the motion mask for them
+    calm: Option<Calm>,
+    opacity: f32,
+    // Dims Flux, but not the background, while it fades in
+    fade: f32,
+    // The level of white, if the frame is presented in linear light
+    white_level: Option<f32>,
+    letterbox: Vec<Letterbox>,
+}
+
+/// A monitor within the window to shrink the frame into, with black bars around it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Letterbox {
+    /// Relative to the top left of the window
+    pub position: PhysicalPosition<i32>,
+    pub size: PhysicalSize<u32>,
+    /// How much to shrink the frame by, from 0 to 1
+    pub scale: f32,
+}
+
+impl Compositor {
+    pub fn new(
+        gl: &Rc<glow::Context>,
+        size: PhysicalSize<u32>,
+        rotation: Rotation,
+        tiles: (u32, u32),
+        render_scale: f64,
+        opacity: f32,
+        background: Option<&path::Path>,
+    ) -> Result<Self, String> {
+        let program = unsafe { create_program(gl, VERTEX_SHADER, FRAGMENT_SHADER)? };
+
+        let (vertex_array, (framebuffer, texture)) = unsafe {
+            let vertex_array = gl.create_vertex_array()?;
+            let render_size = scale_size(render_size(size, rotation, tiles), render_scale);
+            (vertex_array, create_target(gl, render_size)?)
+        };
+
+        let background = background.and_then(|path| load_background(gl, path, size));
+
+        Ok(Self {
+            gl: Rc::clone(gl),
+            size,
+            rotation,
+            tiles,
+            render_scale,
+            program,
+            vertex_array,
+            framebuffer,
+            texture,
+            incoming: None,
+            previous: None,
+            steps: 0,
+            step_progress: 1.0,
+            background,
+            mask: None,
+            calm: None,
+            opacity: opacity.clamp(0.0, 1.0),
+            fade: 1.0,
+            white_level: None,
+            letterbox: Vec::new(),
+        })
+    }
+
+    /// Resize the offscreen textures to fit a window that has changed size. The frames they held
+    /// are gone, so the steps start over, and so does the calmed motion.
+    pub fn resize(&mut self, size: PhysicalSize<u32>) -> Result<(), String> {
+        if size == self.size {
+            return Ok(());
+        }
+        self.size = size;
+
+        let render_size = self.render_size();
+        unsafe {
+            let (framebuffer, texture) = create_target(&self.gl, render_size)?;
+            self.gl.delete_framebuffer(self.framebuffer);
+            self.gl.delete_texture(self.texture);
+            self.framebuffer = framebuffer;
+            self.texture = texture;
+
+            for target in [&mut self.incoming, &mut self.previous] {
+                if let Some((framebuffer, texture)) = target.take() {
+                    self.gl.delete_framebuffer(framebuffer);
+                    self.gl.delete_texture(texture);
+                    *target = Some(create_target(&self.gl, render_size)?);
+                }
+            }
+
+            if let Some(calm) = &mut self.calm {
+                let (framebuffer, texture) =
+                    create_target(&self.gl, scale_size(size, self.render_scale))?;
+                self.gl.delete_framebuffer(calm.framebuffer);
+                self.gl.delete_texture(calm.texture);
+                calm.framebuffer = framebuffer;
+                calm.texture = texture;
+            }
+        }
+        self.reset_steps();
+
+        Ok(())
+    }
+
+    /// Replace the image that Flux is blended over, like when the wallpaper changes.
+    pub fn set_background(&mut self, background: Option<&path::Path>) {
+        if let Some(texture) = self.background.take() {
+            unsafe { self.gl.delete_texture(texture) };
+        }
+
+        self.background = background.and_then(|path| load_background(&self.gl, path, self.size));
+    }
+
+    /// Replace the brightness mask applied to Flux.
+    pub fn set_mask(&mut self, mask: Option<&Mask>) {
+        unsafe {
+            if let Some(texture) = self.mask.take() {
+                self.gl.delete_texture(texture);
+            }
+
+            self.mask = mask.and_then(|mask| create_mask_texture(&self.gl, mask).ok());
+        }
+    }
+
+    /// Calm the motion where the mask is below full brightness, by taking in only that much of
+    /// each new frame, or stop calming it.
+    pub fn set_motion_mask(&mut self, mask: Option<&Mask>) -> Result<(), String> {
+        if let Some(calm) = self.calm.take() {
+            unsafe { calm.delete(&self.gl) };
+        }
+
+        if let Some(mask) = mask {
+            let size = scale_size(self.size, self.render_scale);
+            unsafe {
+                let (framebuffer, texture) = create_target(&self.gl, size)?;
+                let mask = create_mask_texture(&self.gl, mask)?;
+                self.calm = Some(Calm {
+                    framebuffer,
+                    texture,
+                    mask,
+                    is_primed: false,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Keep a second texture for the frame from the previous simulation step, or release it.
+    pub fn set_interpolation(&mut self, is_interpolating: bool) -> Result<(), String> {
+        match (is_interpolating, self.previous) {
+            (true, None) => {
+                self.previous = Some(unsafe { create_target(&self.gl, self.render_size())? });
+            }
+            (false, Some((framebuffer, texture))) => unsafe {
+                self.gl.delete_framebuffer(framebuffer);
+                self.gl.delete_texture(texture);
+                self.previous = None;
+            },
+            _ => (),
+        }
+        self.reset_steps();
+
+        Ok(())
+    }
+
+    pub fn is_interpolating(&self) -> bool {
+        self.previous.is_some()
+    }
+
+    /// Keep the current frame as the previous step, before rendering the next step into the
+    /// offscreen texture.
+    pub fn next_step(&mut self) {
+        if let Some((framebuffer, texture)) = &mut self.previous {
+            std::mem::swap(framebuffer, &mut self.framebuffer);
+            std::mem::swap(texture, &mut self.texture);
+        }
+        self.steps = (self.steps + 1).min(2);
+    }
+
+    /// Forget the frames, like when the simulation restarts, so that nothing blends from a frame
+    /// that no longer belongs.
+    pub fn reset_steps(&mut self) {
+        self.steps = 0;
+        if let Some(calm) = &mut self.calm {
+            calm.is_primed = false;
+        }
+    }
+
+    /// Whether a step has been rendered since the frames were last reset.
+    pub fn has_step(&self) -> bool {
+        self.steps > 0
+    }
+
+    /// How far to blend from the previous step towards the latest one, from 0 to 1.
+    pub fn set_step_progress(&mut self, step_progress: f32) {
+        self.step_progress = step_progress.clamp(0.0, 1.0);
+    }
+
+    /// Fade Flux in from black, or over the background, from 0 to 1.
+    pub fn set_fade(&mut self, fade: f32) {
+        self.fade = fade.clamp(0.0, 1.0);
+    }
+
+    /// Convert the frame from sRGB to linear light as it’s drawn, for a swapchain that takes
+    /// scRGB. Flux and the background are in sRGB, with white at 1.0 before the conversion.
+    pub fn set_linear_output(&mut self, white_level: Option<f32>) {
+        self.white_level = white_level;
+    }
+
+    /// Shrink the frame into these monitors, with black bars around it. Only the first few are
+    /// letterboxed.
+    pub fn set_letterbox(&mut self, letterbox: &[Letterbox]) {
+        if letterbox.len() > MAX_LETTERBOXES {
+            log::warn!(
+                "Only letterboxing {} of {} monitors",
+                MAX_LETTERBOXES,
+                letterbox.len()
+            );
+        }
+        self.letterbox = letterbox.iter().take(MAX_LETTERBOXES).copied().collect();
+    }
+
+    /// Set up a second texture for a scene to fade in over the current one, or release it once
+    /// the cross-fade is over.
+    pub fn set_crossfade(&mut self, is_crossfading: bool) -> Result<(), String> {
+        match (is_crossfading, self.incoming) {
+            (true, None) => {
+                self.incoming = Some(unsafe { create_target(&self.gl, self.render_size())? });
+            }
+            (false, Some((framebuffer, texture))) => unsafe {
+                self.gl.delete_framebuffer(framebuffer);
+                self.gl.delete_texture(texture);
+                self.incoming = None;
+            },
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    /// Redirect rendering to the offscreen texture.
+    pub fn bind(&self) {
+        let size = self.render_size();
+        unsafe {
+            self.gl
+                .bind_framebuffer(GL::FRAMEBUFFER, Some(self.framebuffer));
+            // The texture is the other way around from the window on a monitor turned sideways.
+            self.gl
+                .viewport(0, 0, size.width as i32, size.height as i32);
+        };
+    }
+
+    // The size of the offscreen textures that Flux renders into
+    fn render_size(&self) -> PhysicalSize<u32> {
+        scale_size(
+            render_size(self.size, self.rotation, self.tiles),
+            self.render_scale,
+        )
+    }
+
+    /// Redirect rendering to the texture for the scene fading in.
+    pub fn bind_incoming(&self) {
+        if let Some((framebuffer, _)) = self.incoming {
+            let size = self.render_size();
+            unsafe {
+                self.gl.bind_framebuffer(GL::FRAMEBUFFER, Some(framebuffer));
+                self.gl
+                    .viewport(0, 0, size.width as i32, size.height as i32);
+            };
+        }
+    }
+
+    /// Blend the offscreen texture over the background and draw the result into the target
+    /// framebuffer. Midway through a cross-fade, the incoming scene is blended over it by
+    /// `crossfade`, from 0 to 1.
+    pub fn draw(&mut self, target: Option<GL::NativeFramebuffer>, crossfade: Option<f32>) {
+        if let Some(calm) = self.calm {
+            unsafe { self.draw_calm(calm) };
+            if let Some(calm) = &mut self.calm {
+                calm.is_primed = true;
+            }
+        }
+
+        let gl = &self.gl;
+
+        unsafe {
+            gl.bind_framebuffer(GL::FRAMEBUFFER, target);
+            gl.viewport(0, 0, self.size.width as i32, self.size.height as i32);
+            gl.clear_color(0.0, 0.0, 0.0, 1.0);
+            gl.clear(GL::COLOR_BUFFER_BIT);
+
+            gl.use_program(Some(self.program));
+            gl.bind_vertex_array(Some(self.vertex_array));
+            gl.active_texture(GL::TEXTURE0);
+            gl.uniform_1_i32(
+                gl.get_uniform_location(self.program, "uTexture").as_ref(),
+                0,
+            );
+            gl.uniform_1_i32(gl.get_uniform_location(self.program, "uMask").as_ref(), 1);
+            gl.uniform_1_i32(gl.get_uniform_location(self.program, "uMotion").as_ref(), 2);
+            gl.uniform_1_i32(
+                gl.get_uniform_location(self.program, "uLinearOutput")
+                    .as_ref(),
+                self.white_level.is_some() as i32,
+            );
+            gl.uniform_1_f32(
+                gl.get_uniform_location(self.program, "uWhiteLevel")
+                    .as_ref(),
+                self.white_level.unwrap_or(1.0),
+            );
+
+            if let Some(background) = self.background {
+                self.draw_layer(background, 1.0, true, false, Rotation::None, false);
+            }
+
+            let (areas, scales) = letterbox_uniforms(&self.letterbox, self.size);
+            gl.uniform_1_i32(
+                gl.get_uniform_location(self.program, "uLetterboxCount")
+                    .as_ref(),
+                scales.len() as i32,
+            );
+            if !scales.is_empty() {
+                gl.uniform_4_f32_slice(
+                    gl.get_uniform_location(self.program, "uLetterboxAreas")
+                        .as_ref(),
+                    &areas,
+                );
+                gl.uniform_1_f32_slice(
+                    gl.get_uniform_location(self.program, "uLetterboxScales")
+                        .as_ref(),
+                    &scales,
+                );
+            }
+
+            if let Some(mask) = self.mask {
+                gl.active_texture(GL::TEXTURE1);
+                gl.bind_texture(GL::TEXTURE_2D, Some(mask));
+                gl.active_texture(GL::TEXTURE0);
+            }
+
+            let opacity = self.opacity * self.fade;
+            let incoming = self.incoming.zip(crossfade);
+            let (outgoing_opacity, incoming_opacity) = match incoming {
+                Some((_, crossfade)) => layer_opacities(opacity, crossfade.clamp(0.0, 1.0)),
+                None => (opacity, 0.0),
+            };
+
+            gl.enable(GL::BLEND);
+            gl.blend_func(GL::ONE, GL::ONE_MINUS_SRC_ALPHA);
+            match self.calm {
+                // The calmed frames are already upright.
+                Some(calm) => self.draw_layer(
+                    calm.texture,
+                    outgoing_opacity,
+                    false,
+                    self.mask.is_some(),
+                    Rotation::None,
+                    true,
+                ),
+                None => self.draw_frame(outgoing_opacity, self.mask.is_some()),
+            }
+            if let Some(((_, incoming), _)) = incoming {
+                self.draw_layer(
+                    incoming,
+                    incoming_opacity,
+                    false,
+                    self.mask.is_some(),
+                    self.rotation,
+                    false,
+                );
+            }
+            gl.disable(GL::BLEND);
+
+            gl.active_texture(GL::TEXTURE1);
+            gl.bind_texture(GL::TEXTURE_2D, None);
+            gl.active_texture(GL::TEXTURE0);
+            gl.bind_texture(GL::TEXTURE_2D, None);
+            gl.bind_vertex_array(None);
+            gl.use_program(None);
+        }
+    }
+
+    // Draw the latest frame, blended from the previous step if interpolating.
+    unsafe fn draw_frame(&self, opacity: f32, use_mask: bool) {
+        let mut latest_opacity = opacity;
+        if let (Some((_, previous)), 2) = (self.previous, self.steps) {
+            let (previous_opacity, top_opacity) = layer_opacities(opacity, self.step_progress);
+            latest_opacity = top_opacity;
+            self.draw_layer(
+                previous,
+                previous_opacity,
+                false,
+                use_mask,
+                self.rotation,
+                false,
+            );
+        }
+        self.draw_layer(
+            self.texture,
+            latest_opacity,
+            false,
+            use_mask,
+            self.rotation,
+            false,
+        );
+    }
+
+    // Blend the latest frame into the calmed frames, by as much as the motion mask lets through.
+    // The first frame after a reset replaces them outright.
+    unsafe fn draw_calm(&self, calm: Calm) {
+        let gl = &self.gl;
+        let size = scale_size(self.size, self.render_scale);
+
+        gl.bind_framebuffer(GL::FRAMEBUFFER, Some(calm.framebuffer));
+        gl.viewport(0, 0, size.width as i32, size.height as i32);
+        gl.use_program(Some(self.program));
+        gl.bind_vertex_array(Some(self.vertex_array));
+        gl.uniform_1_i32(
+            gl.get_uniform_location(self.program, "uTexture").as_ref(),
+            0,
+        );
+        gl.uniform_1_i32(gl.get_uniform_location(self.program, "uMotion").as_ref(), 2);
+        // The frames are kept in sRGB, and converted along with everything else when presented.
+        gl.uniform_1_i32(
+            gl.get_uniform_location(self.program, "uLinearOutput")
+                .as_ref(),
+            0,
+        );
+        gl.uniform_1_i32(
+            gl.get_uniform_location(self.program, "uUseMotion").as_ref(),
+            calm.is_primed as i32,
+        );
+        // The calmed frames are letterboxed when they’re presented.
+        gl.uniform_1_i32(
+            gl.get_uniform_location(self.program, "uLetterboxCount")
+                .as_ref(),
+            0,
+        );
+        gl.active_texture(GL::TEXTURE2);
+        gl.bind_texture(GL::TEXTURE_2D, Some(calm.mask));
+        gl.active_texture(GL::TEXTURE0);
+
+        gl.enable(GL::BLEND);
+        gl.blend_func(GL::ONE, GL::ONE_MINUS_SRC_ALPHA);
+        self.draw_frame(1.0, false);
+        gl.disable(GL::BLEND);
+
+        gl.uniform_1_i32(
+            gl.get_uniform_location(self.program, "uUseMotion").as_ref(),
+            0,
+        );
+        gl.active_texture(GL::TEXTURE2);
+        gl.bind_texture(GL::TEXTURE_2D, None);
+        gl.active_texture(GL::TEXTURE0);
+    }
+
+    unsafe fn draw_layer(
+        &self,
+        texture: GL::NativeTexture,
+        opacity: f32,
+        flip_y: bool,
+        use_mask: bool,
+        rotation: Rotation,
+        is_premultiplied: bool,
+    ) {
+        let gl = &self.gl;
+        gl.bind_texture(GL::TEXTURE_2D, Some(texture));
+        gl.uniform_1_f32(
+            gl.get_uniform_location(self.program, "uOpacity").as_ref(),
+            opacity,
+        );
+        gl.uniform_1_i32(
+            gl.get_uniform_location(self.program, "uFlipY").as_ref(),
+            flip_y as i32,
+        );
+        gl.uniform_1_i32(
+            gl.get_uniform_location(self.program, "uUseMask").as_ref(),
+            use_mask as i32,
+        );
+        gl.uniform_1_i32(
+            gl.get_uniform_location(self.program, "uPremultiplied")
+                .as_ref(),
+            is_premultiplied as i32,
+        );
+        gl.uniform_1_i32(
+            gl.get_uniform_location(self.program, "uQuarterTurns")
+                .as_ref(),
+            rotation.quarter_turns() as i32,
+        );
+        let (columns, rows) = self.tiles;
+        gl.uniform_2_f32(
+            gl.get_uniform_location(self.program, "uTiles").as_ref(),
+            columns.max(1) as f32,
+            rows.max(1) as f32,
+        );
+        gl.draw_arrays(GL::TRIANGLES, 0, 3);
+    }
+}
+
+// The areas of the letterboxed monitors, as fractions of the window from the bottom left as GL
+// has it, and how much each shrinks the frame.
+fn letterbox_uniforms(letterbox: &[Letterbox], size: PhysicalSize<u32>) -> (Vec<f32>, Vec<f32>) {
+    let (width, height) = (size.width.max(1) as f32, size.height.max(1) as f32);
+    let areas = letterbox
+        .iter()
+        .flat_map(|letterbox| {
+            let bottom = letterbox.position.y as f32 + letterbox.size.height as f32;
+            [
+                letterbox.position.x as f32 / width,
+                1.0 - bottom / height,
+                letterbox.size.width as f32 / width,
+                letterbox.size.height as f32 / height,
+            ]
+        })
+        .collect();
+    let scales = letterbox
+        .iter()
+        .map(|letterbox| letterbox.scale.clamp(f32::EPSILON, 1.0))
+        .collect();
+    (areas, scales)
+}
+
+// The opacities to draw two layers at, one over the other, so that the top one makes up
+// `progress` of the pair, and the pair covers `opacity` of whatever is beneath it.
+fn layer_opacities(opacity: f32, progress: f32) -> (f32, f32) {
+    let top = opacity * progress;
+    if top >= 1.0 {
+        // The bottom layer is hidden.
+        return (0.0, 1.0);
+    }
+
+    ((opacity - top) / (1.0 - top), top)
+}
+
+impl Drop for Compositor {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.delete_program(self.program);
+            self.gl.delete_vertex_array(self.vertex_array);
+            self.gl.delete_framebuffer(self.framebuffer);
+            self.gl.delete_texture(self.texture);
+            for (framebuffer, texture) in self.incoming.into_iter().chain(self.previous) {
+                self.gl.delete_framebuffer(framebuffer);
+                self.gl.delete_texture(texture);
+            }
+            if let Some(background) = self.background {
+                self.gl.delete_texture(background);
+            }
+            if let Some(mask) = self.mask {
+                self.gl.delete_texture(mask);
+            }
+            if let Some(calm) = self.calm {
+                calm.delete(&self.gl);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Calm {
+    framebuffer: GL::NativeFramebuffer,
+    texture: GL::NativeTexture,
+    mask: GL::NativeTexture,
+    // Whether there are frames to blend into yet
+    is_primed: bool,
+}
+
+impl Calm {
+    unsafe fn delete(self, gl: &glow::Context) {
+        gl.delete_framebuffer(self.framebuffer);
+        gl.delete_texture(self.texture);
+        gl.delete_texture(self.mask);
+    }
+}
+
+fn load_background(
+    gl: &glow::Context,
+    path: &path::Path,
+    size: PhysicalSize<u32>,
+) -> Option<GL::NativeTexture> {
+    load_image(path, size)
+        .and_then(|image| unsafe { create_texture(gl, size, Some(image.as_raw())) })
+        .map_err(|err| log::warn!("Failed to load the background {}: {}", path.display(), err))
+        .ok()
+}
+
+// Decode an image and crop it to fill the surface, like the default “Fill” wallpaper style.
+fn load_image(path: &path::Path, size: PhysicalSize<u32>) -> Result<image::RgbaImage, String> {
+    let image = image::io::Reader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|err| err.to_string())?
+        .decode()
+        .map_err(|err| err.to_string())?;
+
+    Ok(image
+        .resize_to_fill(
+            size.width,
+            size.height,
+            image::imageops::FilterType::Triangle,
+        )
+        .into_rgba8())
+}
+
+unsafe fn create_texture(
+    gl: &glow::Context,
+    size: PhysicalSize<u32>,
+    pixels: Option<&[u8]>,
+) -> Result<GL::NativeTexture, String> {
+    let texture = gl.create_texture()?;
+    gl.bind_texture(GL::TEXTURE_2D, Some(texture));
+    gl.tex_parameter_i32(GL::TEXTURE_2D, GL::TEXTURE_MIN_FILTER, GL::LINEAR as i32);
+    gl.tex_parameter_i32(GL::TEXTURE_2D, GL::TEXTURE_MAG_FILTER, GL::LINEAR as i32);
+    gl.tex_parameter_i32(GL::TEXTURE_2D, GL::TEXTURE_WRAP_S, GL::CLAMP_TO_EDGE as i32);
+    gl.tex_parameter_i32(GL::TEXTURE_2D, GL::TEXTURE_WRAP_T, GL::CLAMP_TO_EDGE as i32);
+    gl.tex_image_2d(
+        GL::TEXTURE_2D,
+        0,
+        GL::RGBA8 as i32,
+        size.width as i32,
+        size.height as i32,
+        0,
+        GL::RGBA,
+        GL::UNSIGNED_BYTE,
+        pixels,
+    );
+    gl.bind_texture(GL::TEXTURE_2D, None);
+
+    Ok(texture)
+}
+
+// A single-channel texture with the values of a mask, stretched over the surface.
+unsafe fn create_mask_texture(
+    gl: &glow::Context,
+    mask: &Mask,
+) -> Result<GL::NativeTexture, String> {
+    let texture = gl.create_texture()?;
+    gl.bind_texture(GL::TEXTURE_2D, Some(texture));
+    gl.tex_parameter_i32(GL::TEXTURE_2D, GL::TEXTURE_MIN_FILTER, GL::LINEAR as i32);
+    gl.tex_parameter_i32(GL::TEXTURE_2D, GL::TEXTURE_MAG_FILTER, GL::LINEAR as i32);
+    gl.tex_parameter_i32(GL::TEXTURE_2D, GL::TEXTURE_WRAP_S, GL::CLAMP_TO_EDGE as i32);
+    gl.tex_parameter_i32(GL::TEXTURE_2D, GL::TEXTURE_WRAP_T, GL::CLAMP_TO_EDGE as i32);
+    // Rows of single-byte pixels aren’t aligned to 4 bytes.
+    gl.pixel_store_i32(GL::UNPACK_ALIGNMENT, 1);
+    gl.tex_image_2d(
+        GL::TEXTURE_2D,
+        0,
+        GL::R8 as i32,
+        mask.width as i32,
+        mask.height as i32,
+        0,
+        GL::RED,
+        GL::UNSIGNED_BYTE,
+        Some(&mask.values),
+    );
+    gl.pixel_store_i32(GL::UNPACK_ALIGNMENT, 4);
+    gl.bind_texture(GL::TEXTURE_2D, None);
+
+    Ok(texture)
+}
+
+/// The size to render at for a window of the given size, split into tiles across and down that
+/// are each turned on their own. Turning a quarter swaps the sides of each tile.
+pub fn render_size(
+    size: PhysicalSize<u32>,
+    rotation: Rotation,
+    tiles: (u32, u32),
+) -> PhysicalSize<u32> {
+    let (columns, rows) = (tiles.0.max(1), tiles.1.max(1));
+    let tile = rotation.render_size(PhysicalSize::new(size.width / columns, size.height / rows));
+    PhysicalSize::new(tile.width * columns, tile.height * rows)
+}
+
+/// Scale a size down, keeping at least a pixel on each side.
+pub fn scale_size(size: PhysicalSize<u32>, scale: f64) -> PhysicalSize<u32> {
+    PhysicalSize::new(
+        ((size.width as f64 * scale).round() as u32).max(1),
+        ((size.height as f64 * scale).round() as u32).max(1),
+    )
+}
+
+// A texture to render into, and the framebuffer that holds it.
+unsafe fn create_target(
+    gl: &glow::Context,
+    size: PhysicalSize<u32>,
+) -> Result<(GL::NativeFramebuffer, GL::NativeTexture), String> {
+    let texture = create_texture(gl, size, None)?;
+
+    let framebuffer = gl.create_framebuffer()?;
+    gl.bind_framebuffer(GL::FRAMEBUFFER, Some(framebuffer));
+    gl.framebuffer_texture_2d(
+        GL::FRAMEBUFFER,
+        GL::COLOR_ATTACHMENT0,
+        GL::TEXTURE_2D,
+        Some(texture),
+        0,
+    );
+    let status = gl.check_framebuffer_status(GL::FRAMEBUFFER);
+    gl.bind_framebuffer(GL::FRAMEBUFFER, None);
+
+    if status != GL::FRAMEBUFFER_COMPLETE {
+        gl.delete_framebuffer(framebuffer);
+        gl.delete_texture(texture);
+        return Err(format!("Offscreen framebuffer incomplete: {:#x}", status));
+    }
+
+    Ok((framebuffer, texture))
+}
+
+pub(crate) unsafe fn create_program(
+    gl: &glow::Context,
+    vertex_shader: &str,
+    fragment_shader: &str,
+) -> Result<GL::NativeProgram, String> {
+    // The context might be either OpenGL 3.3 or the GLES 3.0 fallback.
+    let header = if gl.version().is_embedded {
+        "#version 300 es\nprecision mediump float;\n"
+    } else {
+        "#version 330\n"
+    };
+
+    let program = gl.create_program()?;
+    let mut shaders = Vec::with_capacity(2);
+
+    for (shader_type, source) in [
+        (GL::VERTEX_SHADER, vertex_shader),
+        (GL::FRAGMENT_SHADER, fragment_shader),
+    ] {
+        let shader = gl.create_shader(shader_type)?;
+        gl.shader_source(shader, &format!("{header}{source}"));
+        gl.compile_shader(shader);
+        if !gl.get_shader_compile_status(shader) {
+            return Err(gl.get_shader_info_log(shader));
+        }
+        gl.attach_shader(program, shader);
+        shaders.push(shader);
+    }
+
+    gl.link_program(program);
+    if !gl.get_program_link_status(program) {
+        return Err(gl.get_program_info_log(program));
+    }
+
+    for shader in shaders {
+        gl.detach_shader(program, shader);
+        gl.delete_shader(shader);
+    }
+
+    Ok(program)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_turns_each_tile_of_a_video_wall() {
+        let size = PhysicalSize::new(3840, 1080);
+        assert_eq!(
+            render_size(size, Rotation::Clockwise90, (1, 1)),
+            PhysicalSize::new(1080, 3840)
+        );
+        // Two monitors side by side, each turned on its own
+        assert_eq!(
+            render_size(size, Rotation::Clockwise90, (2, 1)),
+            PhysicalSize::new(2160, 1920)
+        );
+        assert_eq!(render_size(size, Rotation::Clockwise180, (2, 1)), size);
+    }
+
+    #[test]
+    fn it_flips_letterboxes_to_the_bottom_left() {
+        let letterbox = Letterbox {
+            position: PhysicalPosition::new(0, 0),
+            size: PhysicalSize::new(1920, 1080),
+            scale: 0.5,
+        };
+        let (areas, scales) = letterbox_uniforms(&[letterbox], PhysicalSize::new(3840, 2160));
+        assert_eq!(areas, [0.0, 0.5, 0.5, 0.5]);
+        assert_eq!(scales, [0.5]);
+    }
+
+    #[test]
+    fn it_weighs_two_layers_to_cover_as_much_as_one() {
+        for (opacity, progress) in [(1.0, 0.0), (1.0, 0.5), (0.5, 0.5), (0.8, 0.25), (0.5, 1.0)] {
+            let (bottom, top) = layer_opacities(opacity, progress);
+            // The same as blending one layer over the background at `opacity`
+            let background = (1.0 - top) * (1.0 - bottom);
+            let bottom_share = (1.0 - top) * bottom;
+
+            assert!((background - (1.0 - opacity)).abs() < 1e-6);
+            assert!((top - opacity * progress).abs() < 1e-6);
+            assert!((bottom_share - opacity * (1.0 - progress)).abs() < 1e-6);
+        }
+
+        assert_eq!(layer_opacities(1.0, 1.0), (0.0, 1.0));
+    }
+}
@@ -1,19 +1,47 @@
+mod migrate;
+mod presets;
+mod schema;
+mod share_code;
 mod v1;
+mod validate;
+
+pub use presets::{Preset, Presets};
+
+use crate::image_scale;
+use crate::mask;
+use crate::winit_compat::MonitorHandle;
 
 use log::Level;
+use ordered_float::OrderedFloat;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
 use std::{fmt, fs, io, path};
+use winit::dpi::PhysicalSize;
 
 const LATEST_VERSION: u8 = 2;
 
-#[derive(Deserialize, Serialize, Debug, PartialEq)]
+const SETTINGS_FILE_NAME: &str = "settings.json";
+
+// Overrides settings without touching the settings file, as a list of key=value pairs separated
+// by semicolons.
+const OVERRIDES_ENV_VAR: &str = "FLUX_SET";
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(default, rename_all = "camelCase")]
+/// The Flux screensaver settings file
 pub struct Config {
     pub version: u8,
     #[serde(with = "LogLevelDef")]
+    #[schemars(with = "schema::LogLevel")]
     pub log_level: log::Level,
+    pub logging: LoggingConfig,
     pub flux: FluxSettings,
     pub platform: PlatformConfig,
+    pub playlist: PlaylistConfig,
+    pub ping: PingConfig,
+    pub ambient_audio: AmbientAudioConfig,
 
     // An optional path to the location of this config
     #[serde(skip)]
@@ -36,38 +64,141 @@ impl Default for Config {
             // Latest version of the config
             version: LATEST_VERSION,
             log_level: log::Level::Warn,
+            logging: Default::default(),
             flux: Default::default(),
             platform: Default::default(),
+            playlist: Default::default(),
+            ping: Default::default(),
+            ambient_audio: Default::default(),
             location: None,
         }
     }
 }
 
+/// Notices changes to the settings file while Flux is running, like from `--shuffle` or the
+/// settings window, by checking when it was last changed every so often.
+pub struct Watch {
+    modified: Option<std::time::SystemTime>,
+    last_check: std::time::Instant,
+}
+
+impl Watch {
+    const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+    pub fn new(config: &Config) -> Self {
+        Self {
+            modified: config.modified(),
+            last_check: std::time::Instant::now(),
+        }
+    }
+
+    /// The Flux settings as they are in the file now, if they have changed since the last check.
+    /// A change to the calibration alone, like the one saved as Flux starts, doesn’t count.
+    ///
+    /// The file only has the saved settings, so `reapply` puts back anything Flux was launched
+    /// with on top, like overrides from the command line or the environment.
+    pub fn poll(
+        &mut self,
+        config: &Config,
+        now: std::time::Instant,
+        reapply: impl FnOnce(&mut Config),
+    ) -> Option<FluxSettings> {
+        if now.duration_since(self.last_check) < Self::POLL_INTERVAL {
+            return None;
+        }
+        self.last_check = now;
+
+        let modified = config.modified();
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+
+        let mut reloaded = config.reload()?;
+        reapply(&mut reloaded);
+        let flux = reloaded.flux;
+        let recalibrated = FluxSettings {
+            calibration: config.flux.calibration.clone(),
+            ..flux.clone()
+        };
+        (recalibrated != config.flux).then_some(flux)
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(default, rename_all = "camelCase")]
+/// How much goes into the log, and how the log file is kept
+pub struct LoggingConfig {
+    /// Log levels for parts of Flux, or for the libraries it uses, by module path, like
+    /// `Flux::render_thread`, `flux_screensaver_host::platform`, or `glutin`. The longest
+    /// matching path wins, and anything unmatched logs at `logLevel`.
+    pub modules: BTreeMap<String, ModuleLogLevel>,
+    /// Write the log file as lines of JSON, instead of plain text
+    pub json: bool,
+    /// Start a new log file once the current one reaches this size, or never if 0
+    pub max_file_size_kb: u32,
+    /// How many of the older log files to keep around
+    pub max_old_files: u32,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            modules: BTreeMap::new(),
+            json: false,
+            max_file_size_kb: 1024,
+            max_old_files: 3,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+pub struct ModuleLogLevel(
+    #[serde(with = "LogLevelDef")]
+    #[schemars(with = "schema::LogLevel")]
+    pub log::Level,
+);
+
 impl Config {
     pub fn load(optional_config_dir: Option<&path::Path>) -> Self {
+        if let Some(config_path) = Self::portable_path() {
+            log::info!(
+                "Running in portable mode, with the settings at {}",
+                config_path.display()
+            );
+            return Self::load_from(&config_path);
+        }
+
         match optional_config_dir {
             None => Self::default(),
+            Some(config_dir) => Self::load_from(&config_dir.join(SETTINGS_FILE_NAME)),
+        }
+    }
 
-            Some(config_dir) => {
-                let config_path = config_dir.join("settings.json");
-                let config = Self::load_existing_config(config_path.as_path());
-                if let Err(err) = &config {
-                    match err {
-                        Problem::ReadSettings { err, path }
-                            if err.kind() == io::ErrorKind::NotFound =>
-                        {
-                            log::info!(
-                                "No settings file found at {}. Using defaults.",
-                                path.display()
-                            )
-                        }
-                        _ => log::error!("{}", err),
-                    }
-                }
+    /// A settings file next to the executable, for running Flux off a USB stick or deploying it
+    /// without the installer. When there is one, it’s used instead of the one in the user’s
+    /// profile, and changes are saved back to it.
+    pub fn portable_path() -> Option<path::PathBuf> {
+        let exe = std::env::current_exe().ok()?;
+        let config_path = exe.parent()?.join(SETTINGS_FILE_NAME);
+        config_path.is_file().then_some(config_path)
+    }
 
-                config.unwrap_or_default().attach_location(&config_path)
+    fn load_from(config_path: &path::Path) -> Self {
+        let config = Self::load_existing_config(config_path);
+        if let Err(err) = &config {
+            match err {
+                Problem::ReadSettings { err, path } if err.kind() == io::ErrorKind::NotFound => {
+                    log::info!(
+                        "No settings file found at {}. Using defaults.",
+                        path.display()
+                    )
+                }
+                _ => log::error!("{}", err),
             }
         }
+
+        config.unwrap_or_default().attach_location(config_path)
     }
 
     // Attach the config's location
@@ -84,7 +215,46 @@ impl Config {
                 err,
             })?;
 
-        Self::from_string(&config_string, Some(config_path))
+        let mut config =
+            Self::from_string(&config_string, Some(config_path))?.attach_location(config_path);
+        for issue in config.validate() {
+            log::warn!("{}", issue.locate(&config_string));
+        }
+
+        // Save the settings in the new format, keeping the old file around in case there’s an
+        // older release of Flux still reading it.
+        let old_version = serde_json::from_str(&config_string)
+            .ok()
+            .and_then(|config_ast| migrate::version_of(&config_ast).ok())
+            .filter(|version| *version < LATEST_VERSION);
+        if let Some(old_version) = old_version {
+            let backup_path = migrate::backup_path(config_path, old_version);
+            let backed_up = backup_path.exists() || {
+                fs::write(&backup_path, &config_string)
+                    .map_err(|err| {
+                        log::warn!(
+                            "Failed to back up the settings to {}: {}",
+                            backup_path.display(),
+                            err
+                        )
+                    })
+                    .is_ok()
+            };
+            if backed_up {
+                match config.save() {
+                    Ok(()) => log::info!(
+                        "Upgraded the settings from version {} to {}, and kept a copy of the old \
+                         settings at {}",
+                        old_version,
+                        LATEST_VERSION,
+                        backup_path.display()
+                    ),
+                    Err(err) => log::warn!("{}", err),
+                }
+            }
+        }
+
+        Ok(config)
     }
 
     fn from_string(config_string: &str, config_path: Option<&path::Path>) -> Result<Self, Problem> {
@@ -98,21 +268,248 @@ impl Config {
         let config_ast: serde_json::Value =
             serde_json::from_str(config_string).map_err(to_decode_error)?;
 
-        // Check for legacy string versions
-        let raw_version = &config_ast["version"];
-        if let serde_json::Value::String(_) = raw_version {
-            return serde_json::from_value::<v1::Config>(config_ast)
-                .map(|config| config.upgrade())
-                .map_err(to_decode_error);
+        let version = migrate::version_of(&config_ast).map_err(to_decode_error)?;
+        let steps = migrate::chain(version).ok_or_else(|| Problem::UnsupportedVersion {
+            version: version.to_string(),
+        })?;
+        let config_ast = migrate::run(config_ast, &steps).map_err(to_decode_error)?;
+
+        let (config, issues) = validate::decode_leniently(config_ast).map_err(to_decode_error)?;
+        // The positions only line up in a file that didn’t need migrating.
+        for issue in issues {
+            if steps.is_empty() {
+                log::warn!("{}", issue.locate(config_string));
+            } else {
+                log::warn!("{}", issue);
+            }
+        }
+        Ok(config)
+    }
+
+    /// Apply a list of `key=value` overrides, where the key is a dotted path into the settings
+    /// file, like `flux.presetName=Plasma`.
+    ///
+    /// Values are parsed as JSON, falling back to plain strings. The overrides are applied
+    /// together, so the config only needs to be valid once all of them are in place.
+    pub fn apply_overrides(&mut self, overrides: &[String]) -> Result<(), Problem> {
+        let mut config_ast = serde_json::to_value(&*self).map_err(|err| Problem::Override {
+            setting: String::new(),
+            reason: err.to_string(),
+        })?;
+
+        for setting in overrides {
+            let invalid = |reason: &str| Problem::Override {
+                setting: setting.clone(),
+                reason: reason.to_string(),
+            };
+
+            let (key, raw_value) = setting
+                .split_once('=')
+                .ok_or_else(|| invalid("expected a key=value pair"))?;
+            let value = serde_json::from_str(raw_value)
+                .unwrap_or_else(|_| serde_json::Value::String(raw_value.to_string()));
+
+            let mut path = key.split('.').peekable();
+            let mut node = &mut config_ast;
+            while let Some(segment) = path.next() {
+                let object = node
+                    .as_object_mut()
+                    .ok_or_else(|| invalid("the key doesn’t point into an object"))?;
+
+                if path.peek().is_none() {
+                    object.insert(segment.to_string(), value);
+                    break;
+                }
+
+                node = object
+                    .entry(segment)
+                    .or_insert_with(|| serde_json::Value::Object(Default::default()));
+            }
+        }
+
+        let mut config: Config =
+            serde_json::from_value(config_ast).map_err(|err| Problem::Override {
+                setting: overrides.join(" "),
+                reason: err.to_string(),
+            })?;
+        config.location = self.location.take();
+        *self = config;
+
+        Ok(())
+    }
+
+    /// Read the settings again from where they were loaded from, to pick up any changes made since.
+    pub fn reload(&self) -> Option<Self> {
+        self.location.as_deref().map(Self::load_from)
+    }
+
+    // When the settings file was last changed, if there is one.
+    fn modified(&self) -> Option<std::time::SystemTime> {
+        self.location
+            .as_deref()
+            .and_then(|path| fs::metadata(path).ok())
+            .and_then(|metadata| metadata.modified().ok())
+    }
+
+    /// Look up a single setting by its dotted key, like `platform.windows.fillMode`.
+    pub fn get(&self, key: &str) -> Result<serde_json::Value, Problem> {
+        let config_ast = serde_json::to_value(self).unwrap_or_default();
+        config_ast
+            .pointer(&format!("/{}", key.replace('.', "/")))
+            .cloned()
+            .ok_or_else(|| Problem::UnknownSetting {
+                key: key.to_string(),
+            })
+    }
+
+    /// Apply `key=value` settings like `apply_overrides`, but refuse any key that doesn’t name a
+    /// setting, or a value out of range, rather than quietly dropping it. Nothing changes unless
+    /// all of them apply.
+    pub fn set(&mut self, settings: &[String]) -> Result<(), Problem> {
+        let mut config = self.clone();
+        config.apply_overrides(settings)?;
+        let keys = settings
+            .iter()
+            .map(|setting| {
+                setting
+                    .split_once('=')
+                    .map_or(setting.as_str(), |(key, _)| key)
+            })
+            .collect::<Vec<_>>();
+        for key in &keys {
+            config.get(key)?;
+        }
+        if let Some(issue) = config
+            .validate()
+            .into_iter()
+            .find(|issue| issue.reset && keys.contains(&issue.key.as_str()))
+        {
+            return Err(Problem::Override {
+                setting: issue.key,
+                reason: issue.reason,
+            });
+        }
+
+        *self = config;
+        Ok(())
+    }
+
+    /// Where the named presets are saved, next to the settings file.
+    pub fn presets_path(&self) -> Option<path::PathBuf> {
+        self.location
+            .as_ref()
+            .and_then(|path| path.parent())
+            .map(|dir| dir.join("presets.json"))
+    }
+
+    /// Put a single setting back to its default, or all of them if there’s no key.
+    pub fn reset(&mut self, key: Option<&str>) -> Result<(), Problem> {
+        match key {
+            None => {
+                let location = self.location.take();
+                *self = Self {
+                    location,
+                    ..Self::default()
+                };
+                Ok(())
+            }
+            Some(key) => {
+                let default = Self::default().get(key)?;
+                self.set(&[format!("{}={}", key, default)])
+            }
+        }
+    }
+
+    /// A JSON Schema for the settings file.
+    pub fn schema() -> serde_json::Value {
+        serde_json::to_value(schemars::schema_for!(Config)).unwrap_or_default()
+    }
+
+    /// Read any overrides set in the environment.
+    pub fn env_overrides() -> Vec<String> {
+        std::env::var(OVERRIDES_ENV_VAR)
+            .map(|overrides| {
+                overrides
+                    .split(';')
+                    .map(str::trim)
+                    .filter(|setting| !setting.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Show the effective settings alongside where each one came from: the defaults, the
+    /// settings file, the environment, or the command line.
+    pub fn describe(
+        &self,
+        env_overrides: &[String],
+        cli_overrides: &[String],
+    ) -> serde_json::Value {
+        let effective = serde_json::to_value(self).unwrap_or_default();
+        let defaults = serde_json::to_value(Self::default()).unwrap_or_default();
+        let from_file = self
+            .location
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|config_string| serde_json::from_str(&config_string).ok())
+            .unwrap_or(serde_json::Value::Null);
+
+        let keys = |overrides: &[String]| {
+            overrides
+                .iter()
+                .filter_map(|setting| setting.split_once('='))
+                .map(|(key, _)| key.to_string())
+                .collect::<Vec<_>>()
+        };
+        let (env_keys, cli_keys) = (keys(env_overrides), keys(cli_overrides));
+
+        let mut leaves = Vec::new();
+        collect_leaves(&effective, "", &mut leaves);
+
+        let mut sources = serde_json::Map::new();
+        for (key, value) in leaves {
+            let is_overridden_by = |keys: &[String]| {
+                keys.iter()
+                    .any(|k| key == *k || key.starts_with(&format!("{k}.")))
+            };
+            let pointer = format!("/{}", key.replace('.', "/"));
+
+            let source = if is_overridden_by(&cli_keys) {
+                "cli"
+            } else if is_overridden_by(&env_keys) {
+                "env"
+            } else if from_file.pointer(&pointer).is_some()
+                || defaults.pointer(&pointer) != Some(value)
+            {
+                "file"
+            } else {
+                "default"
+            };
+            sources.insert(key, source.into());
         }
 
-        let version = serde_json::from_value::<u8>(raw_version.clone()).map_err(to_decode_error)?;
-        match version {
-            2 => serde_json::from_value(config_ast).map_err(to_decode_error),
-            _ => Err(Problem::UnsupportedVersion {
-                version: version.to_string(),
-            }),
+        serde_json::json!({
+            "config": effective,
+            "sources": sources,
+        })
+    }
+
+    /// Save a change to the settings file, without persisting any overrides applied to this
+    /// config.
+    pub fn save_change(&self, change: impl FnOnce(&mut Config)) -> Result<(), Problem> {
+        let config_path = self.location.as_ref().ok_or(Problem::NoSaveLocation)?;
+        let mut saved = match Self::load_existing_config(config_path) {
+            Ok(config) => config,
+            Err(Problem::ReadSettings { err, .. }) if err.kind() == io::ErrorKind::NotFound => {
+                Self::default()
+            }
+            Err(err) => return Err(err),
         }
+        .attach_location(config_path);
+
+        change(&mut saved);
+        saved.save()
     }
 
     pub fn save(&self) -> Result<(), Problem> {
@@ -137,46 +534,451 @@ impl Config {
         }
     }
 
-    pub fn to_settings(&self, wallpaper: Option<path::PathBuf>) -> flux::settings::Settings {
-        use flux::settings;
-
-        let color_mode = match &self.flux.color_mode {
-            ColorMode::Preset { preset_name } => settings::ColorMode::Preset(*preset_name),
-            ColorMode::ImageFile { image_path } => image_path.clone().map_or(
-                settings::ColorMode::default(),
-                settings::ColorMode::ImageFile,
-            ),
-            ColorMode::DesktopImage => wallpaper.map_or(
-                settings::ColorMode::default(),
-                settings::ColorMode::ImageFile,
-            ),
+    pub fn to_settings(
+        &self,
+        // One of the color sources from `color_modes`
+        color_mode: flux::settings::ColorMode,
+        // The GL renderer string, used to pick an automatic quality
+        renderer: &str,
+        // The size of the surface, to scale large images down to
+        physical_size: PhysicalSize<u32>,
+    ) -> flux::settings::Settings {
+        let color_mode = match color_mode {
+            flux::settings::ColorMode::ImageFile(path) => {
+                flux::settings::ColorMode::ImageFile(self.sample_image(path, physical_size))
+            }
+            color_mode => color_mode,
         };
-        flux::settings::Settings {
+        let mut settings = flux::settings::Settings {
             color_mode,
             ..Default::default()
+        };
+
+        let quality = match (self.flux.quality, &self.flux.calibration) {
+            (Quality::Automatic, Some(calibration)) if calibration.renderer == renderer => {
+                calibration.quality
+            }
+            (Quality::Automatic, _) => crate::gpu::recommended_quality(renderer),
+            (quality, _) => quality,
+        };
+        quality.apply(&mut settings);
+        self.flux.simulation.apply(&mut settings);
+
+        settings
+    }
+
+    // Flux decodes and uploads the whole image, only to sample its colors at the resolution of
+    // the surface. An image many times larger than the surface is scaled down first, so that it
+    // doesn’t hold up the start. The size noted in the settings decides whether to look at the
+    // image at all, to avoid reading every image twice.
+    fn sample_image(&self, path: path::PathBuf, physical_size: PhysicalSize<u32>) -> path::PathBuf {
+        let image_size = std::iter::once(&self.flux.color_mode)
+            .chain(&self.flux.color_fallbacks)
+            .find_map(|color_mode| match color_mode {
+                ColorMode::ImageFile {
+                    image_path: Some(image_path),
+                    image_size,
+                } if *image_path == path => *image_size,
+                _ => None,
+            });
+        if image_size
+            .and_then(|image_size| image_scale::scaled_size(image_size, physical_size))
+            .is_none()
+        {
+            return path;
+        }
+
+        match image_scale::scaled_copy(&path, physical_size) {
+            Ok(scaled_path) => scaled_path,
+            Err(err) => {
+                log::warn!("{}", err);
+                path
+            }
+        }
+    }
+
+    /// The color sources to try, in order of preference. Sources that aren’t available, like a
+    /// missing image, are skipped. The list always ends with the default preset.
+    ///
+    /// Without a wallpaper image, the desktop image falls back to shades of the solid background
+    /// color, if the desktop has one.
+    pub fn color_modes(
+        &self,
+        wallpaper: Option<&path::Path>,
+        background_color: Option<Color>,
+    ) -> Vec<flux::settings::ColorMode> {
+        use flux::settings;
+
+        let is_image = |path: &path::Path| path.is_file();
+
+        std::iter::once(&self.flux.color_mode)
+            .chain(&self.flux.color_fallbacks)
+            .filter_map(|color_mode| {
+                let resolved = match color_mode {
+                    ColorMode::Preset { preset_name } => {
+                        Some(settings::ColorMode::Preset(*preset_name))
+                    }
+                    ColorMode::ImageFile { image_path, .. } => image_path
+                        .as_deref()
+                        .filter(|path| is_image(path))
+                        .map(|path| settings::ColorMode::ImageFile(path.to_owned())),
+                    ColorMode::DesktopImage => match wallpaper.filter(|path| is_image(path)) {
+                        Some(path) => Some(settings::ColorMode::ImageFile(path.to_owned())),
+                        None => background_color.and_then(|color| {
+                            crate::gradient::image_file(&crate::gradient::from_color(color))
+                                .map_err(|err| {
+                                    log::warn!("Failed to draw the background color: {}", err)
+                                })
+                                .ok()
+                                .map(settings::ColorMode::ImageFile)
+                        }),
+                    },
+                    ColorMode::Spotlight => {
+                        crate::spotlight::current_image().map(settings::ColorMode::ImageFile)
+                    }
+                    // Flux only takes its colors from presets and images, so the gradient is
+                    // drawn into an image first.
+                    ColorMode::CustomGradient { stops } => crate::gradient::image_file(stops)
+                        .map_err(|err| log::warn!("Failed to draw the gradient: {}", err))
+                        .ok()
+                        .map(settings::ColorMode::ImageFile),
+                };
+
+                if resolved.is_none() {
+                    log::warn!(
+                        "The color source “{}” isn’t available. Trying the next one.",
+                        color_mode
+                    );
+                }
+                resolved
+            })
+            .chain(std::iter::once(settings::ColorMode::default()))
+            .collect()
+    }
+
+    /// The settings with the colors taken from an image, like one assigned to a monitor. The
+    /// color mode goes first among the fallbacks, in case the image can’t be read.
+    pub fn with_color_image(&self, image_path: &path::Path) -> Config {
+        let mut config = self.clone();
+        let color_mode = std::mem::replace(
+            &mut config.flux.color_mode,
+            ColorMode::ImageFile {
+                image_path: Some(image_path.to_owned()),
+                image_size: None,
+            },
+        );
+        config.flux.color_fallbacks.insert(0, color_mode);
+        config
+    }
+
+    /// Whether the desktop wallpaper is among the color sources at all.
+    pub fn uses_desktop_image(&self) -> bool {
+        std::iter::once(&self.flux.color_mode)
+            .chain(&self.flux.color_fallbacks)
+            .any(|color_mode| matches!(color_mode, ColorMode::DesktopImage))
+    }
+
+    /// Whether Windows Spotlight is among the color sources at all.
+    pub fn uses_spotlight(&self) -> bool {
+        std::iter::once(&self.flux.color_mode)
+            .chain(&self.flux.color_fallbacks)
+            .any(|color_mode| matches!(color_mode, ColorMode::Spotlight))
+    }
+
+    /// Whether the desktop wallpaper comes before any other available color source, in which
+    /// case finding the wallpaper later on would change the colors. Whether Spotlight has an
+    /// image is up to the caller, since it takes a look through Spotlight’s cache to tell.
+    pub fn prefers_desktop_image(&self, has_spotlight_image: bool) -> bool {
+        for color_mode in std::iter::once(&self.flux.color_mode).chain(&self.flux.color_fallbacks) {
+            match color_mode {
+                ColorMode::DesktopImage => return true,
+                ColorMode::ImageFile {
+                    image_path: Some(path),
+                    ..
+                } if path.is_file() => return false,
+                ColorMode::ImageFile { .. } => (),
+                ColorMode::Spotlight if has_spotlight_image => return false,
+                ColorMode::Spotlight => (),
+                ColorMode::CustomGradient { stops } if stops.is_empty() => (),
+                ColorMode::Preset { .. } | ColorMode::CustomGradient { .. } => return false,
+            }
+        }
+
+        false
+    }
+}
+
+// Flatten a JSON value into dotted paths to each of its leaves.
+fn collect_leaves<'a>(
+    value: &'a serde_json::Value,
+    prefix: &str,
+    leaves: &mut Vec<(String, &'a serde_json::Value)>,
+) {
+    match value {
+        serde_json::Value::Object(object) if !object.is_empty() => {
+            for (key, value) in object {
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                collect_leaves(value, &path, leaves);
+            }
         }
+        _ => leaves.push((prefix.to_string(), value)),
     }
 }
 
-#[derive(Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "camelCase")]
+/// Flux’s own settings, which map onto `flux::settings::Settings`. This is the one part of the
+/// settings file that ties it to Flux.
 pub struct FluxSettings {
     #[serde(flatten)]
     pub color_mode: ColorMode,
+    /// Other color sources to try, in order, if the preferred one isn’t available
+    #[serde(default)]
+    pub color_fallbacks: Vec<ColorMode>,
+    #[serde(default)]
+    pub quality: Quality,
+    /// The result of the last benchmark, used by the automatic quality
+    #[serde(default)]
+    pub calibration: Option<Calibration>,
+    /// Limit the frame rate. Without a limit, or with a limit of 0, Flux renders at the refresh
+    /// rate of the display.
+    #[serde(default)]
+    pub max_fps: Option<u32>,
+    /// Tweaks to the look of the simulation
+    #[serde(default)]
+    pub simulation: SimulationSettings,
+    /// A clock, the date, or some text drawn over the animation
+    #[serde(default)]
+    pub overlay: OverlaySettings,
+}
+
+#[derive(Default, Deserialize, Serialize, Copy, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(default, rename_all = "camelCase")]
+/// Tweaks to the simulation, as multiples of the Flux defaults. Anything left unset keeps the
+/// default.
+pub struct SimulationSettings {
+    /// Scale the length of the lines
+    #[schemars(with = "Option<f32>")]
+    pub line_length: Option<OrderedFloat<f32>>,
+    /// Scale the width of the lines
+    #[schemars(with = "Option<f32>")]
+    pub line_width: Option<OrderedFloat<f32>>,
+    /// Scale how strongly the noise pushes the lines around
+    #[schemars(with = "Option<f32>")]
+    pub noise_strength: Option<OrderedFloat<f32>>,
+    /// Scale the number of lines. More lines take more work to draw.
+    #[schemars(with = "Option<f32>")]
+    pub line_density: Option<OrderedFloat<f32>>,
+    /// Scale how quickly the fluid settles down after it’s been stirred up
+    #[schemars(with = "Option<f32>")]
+    pub velocity_dissipation: Option<OrderedFloat<f32>>,
+}
+
+impl SimulationSettings {
+    /// How far the settings can be scaled from the defaults
+    pub const SCALE_RANGE: RangeInclusive<f32> = 0.25..=2.0;
+    /// More lines take more work to draw, so the line count has less room.
+    pub const LINE_DENSITY_RANGE: RangeInclusive<f32> = 0.5..=1.5;
+
+    /// Keep every setting within the ranges offered in the settings window.
+    pub fn clamped(self) -> Self {
+        let clamp = |scale: Option<OrderedFloat<f32>>, range: RangeInclusive<f32>| {
+            scale
+                .map(OrderedFloat::into_inner)
+                .filter(|scale| scale.is_finite())
+                .map(|scale| OrderedFloat(scale.clamp(*range.start(), *range.end())))
+        };
+
+        Self {
+            line_length: clamp(self.line_length, Self::SCALE_RANGE),
+            line_width: clamp(self.line_width, Self::SCALE_RANGE),
+            noise_strength: clamp(self.noise_strength, Self::SCALE_RANGE),
+            line_density: clamp(self.line_density, Self::LINE_DENSITY_RANGE),
+            velocity_dissipation: clamp(self.velocity_dissipation, Self::SCALE_RANGE),
+        }
+    }
+
+    /// Take each setting from these, or from the fallback where these leave it unset.
+    pub fn or(self, fallback: Self) -> Self {
+        Self {
+            line_length: self.line_length.or(fallback.line_length),
+            line_width: self.line_width.or(fallback.line_width),
+            noise_strength: self.noise_strength.or(fallback.noise_strength),
+            line_density: self.line_density.or(fallback.line_density),
+            velocity_dissipation: self.velocity_dissipation.or(fallback.velocity_dissipation),
+        }
+    }
+
+    pub fn apply(self, settings: &mut flux::settings::Settings) {
+        if let Some(scale) = self.line_length {
+            settings.line_length *= scale.into_inner();
+        }
+        if let Some(scale) = self.line_width {
+            settings.line_width *= scale.into_inner();
+        }
+        if let Some(scale) = self.noise_strength {
+            for noise in settings.noise_channels.iter_mut() {
+                noise.multiplier *= scale.into_inner();
+            }
+        }
+        // The lines are laid out on a grid, so more lines means a tighter grid.
+        if let Some(scale) = self.line_density.filter(|scale| scale.into_inner() > 0.0) {
+            let spacing = settings.grid_spacing as f32 / scale.into_inner();
+            settings.grid_spacing = (spacing.round() as u32).max(1);
+        }
+        if let Some(scale) = self.velocity_dissipation {
+            settings.velocity_dissipation *= scale.into_inner();
+        }
+    }
+}
+
+/// The tallest the overlay text can be, in pixels, which is already taller than any monitor.
+pub const MAX_FONT_SIZE: u32 = 1000;
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(default, rename_all = "camelCase")]
+/// Text drawn over the animation, in a built-in pixel font
+pub struct OverlaySettings {
+    /// What to show. Nothing is drawn by default.
+    pub content: OverlayContent,
+    /// The text to show with the `text` content. The font only has letters, digits, and some
+    /// punctuation, and anything else shows up as a question mark.
+    pub text: String,
+    pub position: OverlayPosition,
+    /// How tall the letters are, in pixels at 100% scaling, from 1 to 1000. The font is scaled
+    /// up in whole steps, so this is rounded to the nearest one.
+    pub font_size: u32,
+    /// How opaque the text is, from 0 to 1
+    #[schemars(with = "f32")]
+    pub opacity: OrderedFloat<f32>,
+}
+
+impl Default for OverlaySettings {
+    fn default() -> Self {
+        Self {
+            content: OverlayContent::default(),
+            text: String::new(),
+            position: OverlayPosition::default(),
+            font_size: 42,
+            opacity: OrderedFloat(0.75),
+        }
+    }
+}
+
+#[derive(Default, Deserialize, Serialize, Copy, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum OverlayContent {
+    #[default]
+    None,
+    /// The time, like 21:45
+    Clock,
+    /// The day and date, like MON 14 OCT
+    Date,
+    /// The time, with the date underneath
+    ClockAndDate,
+    /// The text from the settings
+    Text,
+}
+
+/// Where the overlay sits on each window
+#[derive(Default, Deserialize, Serialize, Copy, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum OverlayPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+    Center,
+}
+
+impl OverlayContent {
+    pub const ALL: [OverlayContent; 5] = [
+        OverlayContent::None,
+        OverlayContent::Clock,
+        OverlayContent::Date,
+        OverlayContent::ClockAndDate,
+        OverlayContent::Text,
+    ];
+}
+
+impl fmt::Display for OverlayContent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                OverlayContent::None => "Nothing",
+                OverlayContent::Clock => "Clock",
+                OverlayContent::Date => "Date",
+                OverlayContent::ClockAndDate => "Clock and date",
+                OverlayContent::Text => "Text",
+            }
+        )
+    }
+}
+
+impl OverlayPosition {
+    pub const ALL: [OverlayPosition; 5] = [
+        OverlayPosition::TopLeft,
+        OverlayPosition::TopRight,
+        OverlayPosition::BottomLeft,
+        OverlayPosition::BottomRight,
+        OverlayPosition::Center,
+    ];
+}
+
+impl fmt::Display for OverlayPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                OverlayPosition::TopLeft => "Top left",
+                OverlayPosition::TopRight => "Top right",
+                OverlayPosition::BottomLeft => "Bottom left",
+                OverlayPosition::BottomRight => "Bottom right",
+                OverlayPosition::Center => "Center",
+            }
+        )
+    }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+/// The recommended quality for a particular GPU
+pub struct Calibration {
+    pub renderer: String,
+    pub quality: Quality,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, JsonSchema)]
 #[serde(tag = "colorMode", rename_all = "camelCase")]
 pub enum ColorMode {
     Preset {
         #[serde(rename = "presetName")]
+        #[schemars(with = "schema::ColorPreset")]
         preset_name: flux::settings::ColorPreset,
     },
     ImageFile {
         #[serde(rename = "imagePath")]
         image_path: Option<path::PathBuf>,
+        /// The size of the image in pixels, noted when it’s picked in the settings window
+        #[serde(rename = "imageSize", default, skip_serializing_if = "Option::is_none")]
+        image_size: Option<ImageSize>,
     },
     DesktopImage,
+    /// The image Windows Spotlight is showing on the lock screen, looked up again on each start
+    Spotlight,
+    /// A gradient of your own, as a list of positions from 0 to 1 and the color at each one
+    CustomGradient {
+        #[schemars(with = "Vec<(f32, Color)>")]
+        stops: Vec<(OrderedFloat<f32>, Color)>,
+    },
 }
 
 impl Default for ColorMode {
@@ -189,7 +991,7 @@ impl Default for ColorMode {
 
 use flux::settings::ColorPreset;
 impl ColorMode {
-    pub const ALL: [ColorMode; 5] = [
+    pub const ALL: [ColorMode; 7] = [
         ColorMode::Preset {
             preset_name: ColorPreset::Original,
         },
@@ -200,7 +1002,12 @@ impl ColorMode {
             preset_name: ColorPreset::Poolside,
         },
         ColorMode::DesktopImage,
-        ColorMode::ImageFile { image_path: None },
+        ColorMode::Spotlight,
+        ColorMode::ImageFile {
+            image_path: None,
+            image_size: None,
+        },
+        ColorMode::CustomGradient { stops: Vec::new() },
     ];
 }
 
@@ -220,76 +1027,964 @@ impl std::fmt::Display for ColorMode {
                     }
                 }
                 ColorMode::DesktopImage => "From wallpaper",
+                ColorMode::Spotlight => "Windows Spotlight",
                 ColorMode::ImageFile { .. } => "From image",
+                ColorMode::CustomGradient { .. } => "Custom gradient",
             }
         )
     }
 }
 
-#[derive(Default, Deserialize, Serialize, Debug, PartialEq)]
-#[serde(default, rename_all = "camelCase")]
-// Platform-specific configuration
-pub struct PlatformConfig {
-    pub windows: WindowsConfig,
+/// The width and height of an image, in pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub struct ImageSize {
+    pub width: u32,
+    pub height: u32,
 }
 
-#[derive(Default, Deserialize, Serialize, Debug, PartialEq)]
-#[serde(default, rename_all = "camelCase")]
-// Windows-specific configuration
-pub struct WindowsConfig {
-    pub fill_mode: FillMode,
+/// An sRGB color, written as a hex code like `#ff8800`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize, JsonSchema)]
+#[serde(try_from = "String", into = "String")]
+#[schemars(with = "String")]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl TryFrom<String> for Color {
+    type Error = String;
+
+    fn try_from(hex: String) -> Result<Self, Self::Error> {
+        let invalid = || format!("{} isn’t a color like #ff8800", hex);
+        let digits = hex.strip_prefix('#').unwrap_or(&hex);
+        if digits.len() != 6 || !digits.is_ascii() {
+            return Err(invalid());
+        }
+
+        let channel = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).map_err(|_| invalid());
+        Ok(Color {
+            r: channel(0)?,
+            g: channel(2)?,
+            b: channel(4)?,
+        })
+    }
+}
+
+impl From<Color> for String {
+    fn from(color: Color) -> Self {
+        format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+    }
 }
 
-#[derive(Default, Deserialize, Serialize, Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Default, Deserialize, Serialize, Copy, Clone, Debug, Eq, PartialEq, JsonSchema)]
 #[serde(rename_all = "camelCase")]
-// Configures how Flux works with multiple displays.
-pub enum FillMode {
-    // Display a separate instance on each display
-    None,
-    // Span across and up to adjacent displays with matching dimensions
+/// Trades simulation detail for performance.
+pub enum Quality {
+    /// Pick a quality based on the GPU
     #[default]
-    Span,
-    // Fill all displays with a single surface
-    Fill,
+    Automatic,
+    Low,
+    Medium,
+    High,
+    Ultra,
 }
 
-impl FillMode {
-    pub const ALL: [FillMode; 3] = [FillMode::None, FillMode::Span, FillMode::Fill];
+impl Quality {
+    pub const ALL: [Quality; 5] = [
+        Quality::Automatic,
+        Quality::Low,
+        Quality::Medium,
+        Quality::High,
+        Quality::Ultra,
+    ];
+
+    // Adjust the internal fluid resolution, the density of the lines, and the number of noise
+    // octaves. High matches the Flux defaults. Flux can’t add octaves of its own, so Ultra keeps
+    // every octave rather than asking for more than there are.
+    pub fn apply(self, settings: &mut flux::settings::Settings) {
+        let (fluid_size, grid_spacing, noise_octaves) = match self {
+            Quality::Low => (64, 24, Some(1)),
+            Quality::Medium => (96, 18, Some(2)),
+            Quality::Automatic | Quality::High => return,
+            Quality::Ultra => (256, 10, None),
+        };
+
+        settings.fluid_size = fluid_size;
+        settings.grid_spacing = grid_spacing;
+        if let Some(noise_octaves) = noise_octaves {
+            settings.noise_channels.truncate(noise_octaves);
+        }
+    }
 }
 
-impl fmt::Display for FillMode {
+impl fmt::Display for Quality {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
             "{}",
             match self {
-                FillMode::None => "None",
-                FillMode::Span => "Span",
-                FillMode::Fill => "Fill",
+                Quality::Automatic => "Automatic",
+                Quality::Low => "Low",
+                Quality::Medium => "Medium",
+                Quality::High => "High",
+                Quality::Ultra => "Ultra",
             }
         )
     }
 }
 
-#[derive(Debug)]
-pub enum Problem {
-    GetProjectDir,
-    CreateProjectDir {
-        path: path::PathBuf,
-        err: io::Error,
-    },
-    ReadSettings {
-        path: path::PathBuf,
-        err: io::Error,
-    },
-    DecodeSettings {
-        path: path::PathBuf,
+#[derive(Default, Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(default, rename_all = "camelCase")]
+/// An anonymous ping, sent at most once per release, with the version of Flux, the operating
+/// system, and the GPU vendor. Nothing else is sent, and nothing at all unless enabled.
+pub struct PingConfig {
+    /// Opt in to the ping
+    pub enabled: bool,
+    /// Where to send the ping
+    pub endpoint: Option<String>,
+    /// The last release to send a ping
+    pub last_sent_version: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(default, rename_all = "camelCase")]
+/// A sound to loop while the screensaver runs, for a more relaxing screensaver. It fades out
+/// along with the exit transition, or stops right away without one.
+pub struct AmbientAudioConfig {
+    /// The WAV file to loop. Nothing plays unless this is set.
+    pub path: Option<path::PathBuf>,
+    /// From 0.0 for silent to 1.0 for as loud as the file
+    pub volume: f32,
+    /// How long to fade the sound in when the screensaver starts
+    pub fade_in_ms: u32,
+}
+
+impl Default for AmbientAudioConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            volume: 0.5,
+            fade_in_ms: 3000,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(default, rename_all = "camelCase")]
+/// Scenes to rotate through in the screensaver and the live wallpaper, cross-fading from one to
+/// the next. The regular settings play first, followed by each scene in turn.
+pub struct PlaylistConfig {
+    pub scenes: Vec<Scene>,
+    /// How long each scene plays for
+    pub interval_mins: u32,
+    /// How long to fade from one scene to the next
+    pub crossfade_secs: u32,
+}
+
+impl Default for PlaylistConfig {
+    fn default() -> Self {
+        Self {
+            scenes: Vec::new(),
+            interval_mins: 10,
+            crossfade_secs: 5,
+        }
+    }
+}
+
+#[derive(Default, Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(default, rename_all = "camelCase")]
+/// A scene in the playlist
+pub struct Scene {
+    /// The name of a saved preset to start from, instead of the regular settings
+    pub preset: Option<String>,
+    /// Tweaks to the simulation, on top of the preset
+    pub simulation: SimulationSettings,
+}
+
+#[derive(Default, Deserialize, Serialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(default, rename_all = "camelCase")]
+/// Platform-specific configuration
+pub struct PlatformConfig {
+    pub windows: WindowsConfig,
+}
+
+#[derive(Default, Deserialize, Serialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(default, rename_all = "camelCase")]
+/// Windows-specific configuration
+pub struct WindowsConfig {
+    pub fill_mode: FillMode,
+    /// Monitors to span together in span mode. Any other monitors are grouped automatically.
+    pub span_groups: Vec<Vec<MonitorRef>>,
+    /// How to span monitors with different scale factors
+    pub mixed_dpi_span: MixedDpiSpan,
+    /// The surfaces to draw in custom mode. Any monitors left out get a surface of their own.
+    pub custom_layout: Vec<CustomSurface>,
+    /// Monitors to leave alone, showing the desktop as usual
+    pub excluded_monitors: Vec<MonitorRef>,
+    /// Delay the start of the animation after the windows appear
+    pub start_delay_ms: u32,
+    /// Delay the start of each additional monitor by this much
+    pub monitor_stagger_ms: u32,
+    /// Run each monitor in a process of its own, so that a driver crash on one monitor doesn’t
+    /// take down the screensaver on the others. Each of them logs to a file of its own.
+    pub isolate_monitors: bool,
+    /// Size the lines by the physical size of each monitor, as reported by the monitor itself,
+    /// so that they look the same size on monitors with different pixel densities. Monitors with
+    /// different densities aren’t spanned together, since a span can only have one.
+    pub uniform_physical_density: bool,
+    /// Monitors that are physically mounted rotated, like in a video wall, but that Windows
+    /// reports the right way up. Rotated monitors are turned on their own, unless a span group
+    /// puts them together into a video wall: a grid of monitors that are all the same size and
+    /// turned the same way.
+    pub monitor_rotations: Vec<MonitorRotation>,
+    /// Monitors that take their colors from an image of their own, instead of the color mode. A
+    /// surface spanning several monitors uses the image of the first one that has one.
+    pub monitor_images: Vec<MonitorImage>,
+    /// Present in 10 bits per channel for smoother gradients, or in scRGB on displays in HDR
+    /// mode. Only applies to the DXGI swapchain.
+    pub high_dynamic_range: bool,
+    /// Run the simulation at half the rate, and cross-fade from one step to the next on the
+    /// frames in between. This takes about half the load off the GPU, for a little less crisp
+    /// motion: the lines aren’t moved along to where they’d be in between, so fast ones leave a
+    /// faint double image. Applies to every window, including the preview.
+    pub interpolate_frames: bool,
+    /// Hold back the frames for all the monitors and present them together, right after the
+    /// vertical blank of the primary monitor, so that neighbouring monitors don’t show different
+    /// frames. Only the DXGI swapchain can hold frames back.
+    pub sync_presents: bool,
+    /// Queue up at most this many frames ahead of the display, from 1 to 16, and only start on
+    /// each frame once the swapchain is ready for it. 1 keeps the screensaver the most responsive
+    /// to input, and the GPU from working ahead. This takes a flip model swapchain, which doesn’t
+    /// get along with some NVIDIA cards. Only applies to the DXGI swapchain.
+    pub max_frame_latency: Option<u32>,
+    /// The GPU to render with, by part of its name or by its LUID, for computers with more than
+    /// one. Two GPUs with the same name are numbered, like “NVIDIA RTX A2000 #2”. Windows picks
+    /// one when this isn’t set.
+    pub preferred_gpu: Option<String>,
+    /// Lock the workstation once the screensaver exits, even when it wasn’t started by Windows,
+    /// like from the daemon or the blank-now shortcut
+    pub lock_on_exit: bool,
+    /// Draw the frame rate, frame times, and GPU memory over the animation, like with the
+    /// `--stats` flag
+    pub show_stats: bool,
+    /// Offer to open the folder with the log and the crash report after a crash. Never on the
+    /// login screen, where the service runs Flux as SYSTEM.
+    pub crash_dialog: bool,
+    /// Programs that keep the daemon from starting the screensaver, and pause the live wallpaper,
+    /// while they run, by the name of their executable, like `obs64.exe`
+    pub blocking_processes: Vec<String>,
+    pub live_wallpaper: LiveWallpaperConfig,
+    pub battery: BatteryConfig,
+    pub daemon: DaemonConfig,
+    pub command_palette: CommandPaletteConfig,
+    pub exit_transition: ExitTransitionConfig,
+    pub blanking: BlankingConfig,
+    pub input: InputConfig,
+    pub watchdog: WatchdogConfig,
+    pub soak: SoakConfig,
+    /// Extra workarounds for problematic drivers, on top of the built-in ones
+    pub gpu_workarounds: Vec<GpuWorkaround>,
+}
+
+impl WindowsConfig {
+    // Span two monitors together, along with any monitors they’re already spanned with.
+    pub fn span_together(&mut self, a: MonitorRef, b: MonitorRef) {
+        let mut group = vec![a.clone(), b.clone()];
+        self.span_groups.retain(|existing| {
+            let overlaps = existing.contains(&a) || existing.contains(&b);
+            if overlaps {
+                group.extend(existing.iter().cloned());
+            }
+            !overlaps
+        });
+
+        group.sort_unstable();
+        group.dedup();
+        if group.len() > 1 {
+            self.span_groups.push(group);
+        }
+    }
+
+    // Take a monitor out of its span group, leaving it to be grouped automatically.
+    pub fn unspan(&mut self, monitor: &MonitorRef) {
+        for group in self.span_groups.iter_mut() {
+            group.retain(|other| other != monitor);
+        }
+        self.span_groups.retain(|group| group.len() > 1);
+    }
+
+    /// Switch any monitors still listed by display index over to their stable IDs, while the
+    /// indices still point at the same monitors.
+    pub fn resolve_monitors(&mut self, monitors: &[MonitorHandle]) {
+        let resolve = |monitor_ref: &mut MonitorRef| {
+            if let Some(monitor) = monitors.iter().find(|monitor| monitor_ref.matches(monitor)) {
+                *monitor_ref = MonitorRef::from(monitor);
+            }
+        };
+
+        self.span_groups.iter_mut().flatten().for_each(resolve);
+        self.custom_layout
+            .iter_mut()
+            .flat_map(|surface| surface.monitors.iter_mut())
+            .for_each(resolve);
+        self.excluded_monitors.iter_mut().for_each(resolve);
+        self.monitor_rotations
+            .iter_mut()
+            .map(|rotation| &mut rotation.monitor)
+            .for_each(resolve);
+        self.monitor_images
+            .iter_mut()
+            .map(|monitor_image| &mut monitor_image.monitor)
+            .for_each(resolve);
+        self.live_wallpaper
+            .masked_regions
+            .iter_mut()
+            .map(|masked_region| &mut masked_region.monitor)
+            .for_each(resolve);
+        self.live_wallpaper
+            .mask_images
+            .iter_mut()
+            .map(|mask_image| &mut mask_image.monitor)
+            .for_each(resolve);
+    }
+
+    /// The image assigned to the first of the monitors that has one.
+    pub fn monitor_image<'a>(
+        &self,
+        monitors: impl IntoIterator<Item = &'a MonitorHandle>,
+    ) -> Option<&path::Path> {
+        monitors.into_iter().find_map(|monitor| {
+            self.monitor_images
+                .iter()
+                .find(|monitor_image| monitor_image.monitor.matches(monitor))
+                .map(|monitor_image| monitor_image.image_path.as_path())
+        })
+    }
+
+    /// Assign an image to a monitor, replacing any it had before.
+    pub fn set_monitor_image(&mut self, monitor: MonitorRef, image_path: Option<path::PathBuf>) {
+        self.monitor_images
+            .retain(|monitor_image| monitor_image.monitor != monitor);
+        if let Some(image_path) = image_path {
+            self.monitor_images.push(MonitorImage {
+                monitor,
+                image_path,
+            });
+        }
+    }
+
+    pub fn is_excluded(&self, monitor: &MonitorHandle) -> bool {
+        self.excluded_monitors
+            .iter()
+            .any(|excluded| excluded.matches(monitor))
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+/// Workarounds to apply to any GPU with a matching renderer string.
+pub struct GpuWorkaround {
+    /// A case-insensitive substring of the GL renderer string
+    pub renderer: String,
+    #[serde(flatten)]
+    pub workarounds: GpuWorkarounds,
+}
+
+impl GpuWorkaround {
+    /// Apply the workarounds to every GPU. An empty renderer string matches any of them.
+    pub fn for_every_gpu(workarounds: GpuWorkarounds) -> Self {
+        Self {
+            renderer: String::new(),
+            workarounds,
+        }
+    }
+}
+
+#[derive(Default, Deserialize, Serialize, Copy, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(default, rename_all = "camelCase")]
+pub struct GpuWorkarounds {
+    /// Present with plain OpenGL instead of a DXGI swapchain
+    pub disable_dxgi_interop: bool,
+    /// Use the GLES fallback instead of desktop OpenGL
+    pub force_gles: bool,
+    /// Limit the frame rate
+    pub max_fps: Option<u32>,
+}
+
+impl GpuWorkarounds {
+    // Combine two sets of workarounds, keeping the most conservative option of each.
+    pub fn merge(self, other: Self) -> Self {
+        Self {
+            disable_dxgi_interop: self.disable_dxgi_interop || other.disable_dxgi_interop,
+            force_gles: self.force_gles || other.force_gles,
+            max_fps: match (self.max_fps, other.max_fps) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            },
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(default, rename_all = "camelCase")]
+/// Configures Flux when running as a live wallpaper.
+pub struct LiveWallpaperConfig {
+    /// Blend Flux over the existing wallpaper. 1.0 replaces the wallpaper entirely.
+    pub opacity: f32,
+    /// Dim Flux behind the desktop icons to keep their labels readable
+    pub dim_icons: bool,
+    /// How dark to make the areas crowded with icons, from 0.0 (black) to 1.0 (no dimming)
+    pub icon_brightness: f32,
+    /// How quickly the fluid behind the desktop icons catches up with the animation each frame,
+    /// from 0.0 (frozen) to 1.0 (no calming), so that the labels sit over calmer motion
+    pub icon_motion: f32,
+    /// Render beneath the taskbar and any docked toolbars too
+    pub full_bleed: bool,
+    /// Start the live wallpaper when you log in
+    pub launch_at_login: bool,
+    /// Keep the light to the edges of the monitors, like ambient lighting behind them, and the
+    /// middle dim and calm enough to read over
+    pub edge_lighting: bool,
+    /// How far in from the edges the light reaches, as a fraction of the shorter side
+    pub edge_width: f32,
+    /// How dark to make the middle, from 0.0 (black) to 1.0 (no dimming). The motion there is
+    /// calmed by as much.
+    pub edge_center_brightness: f32,
+    /// Rectangles where the fluid is hidden or dimmed, like behind a desktop widget
+    pub masked_regions: Vec<MaskedRegion>,
+    /// Grayscale images stretched over whole monitors, where black hides the fluid and white
+    /// leaves it as is
+    pub mask_images: Vec<MaskImage>,
+    /// Limit the frame rate while you’re typing or moving the mouse, since the wallpaper is
+    /// mostly hidden behind windows then anyway
+    pub active_max_fps: Option<u32>,
+    /// How long after the last input to wait before speeding back up
+    pub active_cooldown_secs: u32,
+    /// Experimental. Every so often, look at what’s on the monitor and drift the colors toward
+    /// it, so that the wallpaper blends in with the apps that are open. Overrides the color mode,
+    /// except on monitors with an image of their own. Needs Windows 11, which can look at the
+    /// monitor without outlining it in yellow.
+    pub adaptive_palette: bool,
+    /// How often to look at the monitor for the adaptive palette
+    pub adaptive_palette_interval_secs: u32,
+}
+
+impl LiveWallpaperConfig {
+    pub fn edge_lighting(&self) -> Option<mask::EdgeLighting> {
+        self.edge_lighting.then_some(mask::EdgeLighting {
+            width: self.edge_width,
+            center_brightness: self.edge_center_brightness,
+        })
+    }
+
+    /// Whether any part of the wallpaper is dimmed, by the desktop icons, edge lighting, or the
+    /// masked areas.
+    pub fn is_masked(&self) -> bool {
+        self.dim_icons
+            || self.edge_lighting
+            || !self.masked_regions.is_empty()
+            || !self.mask_images.is_empty()
+    }
+
+    /// Everything that dims the wallpaper besides the desktop icons, laid out on the given
+    /// monitors. Regions on monitors that aren’t connected are left out, and so are images that
+    /// fail to load.
+    pub fn mask_layers(&self, monitors: &[MonitorHandle]) -> mask::Layers {
+        let find_monitor =
+            |monitor_ref: &MonitorRef| monitors.iter().find(|monitor| monitor_ref.matches(monitor));
+
+        let regions = self.masked_regions.iter().filter_map(|masked_region| {
+            let monitor = find_monitor(&masked_region.monitor)?;
+            Some(mask::MaskedArea {
+                region: masked_region.to_region(monitor),
+                shape: mask::MaskShape::Rectangle {
+                    brightness: masked_region.brightness,
+                },
+            })
+        });
+
+        let images = self.mask_images.iter().filter_map(|mask_image| {
+            let monitor = find_monitor(&mask_image.monitor)?;
+            let image = image::open(&mask_image.image_path)
+                .map_err(|err| {
+                    log::warn!(
+                        "Failed to load the mask image at {}: {}",
+                        mask_image.image_path.display(),
+                        err
+                    )
+                })
+                .ok()?;
+            Some(mask::MaskedArea {
+                region: mask::Region {
+                    position: monitor.position(),
+                    size: monitor.size(),
+                },
+                shape: mask::MaskShape::Image(image.to_luma8()),
+            })
+        });
+
+        mask::Layers {
+            edge_lighting: self.edge_lighting(),
+            areas: regions.chain(images).collect(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+/// A rectangle on a monitor where the live wallpaper is hidden or dimmed. The rectangle is given
+/// in fractions of the monitor, so that it stays put when the resolution changes.
+pub struct MaskedRegion {
+    pub monitor: MonitorRef,
+    /// The left edge, from 0.0 to 1.0 across the monitor
+    pub x: f32,
+    /// The top edge, from 0.0 to 1.0 down the monitor
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    /// How bright to leave the fluid inside, from 0.0 (hidden) to 1.0 (no dimming)
+    pub brightness: f32,
+}
+
+impl MaskedRegion {
+    pub fn to_region(&self, monitor: &MonitorHandle) -> mask::Region {
+        let position = monitor.position();
+        let size = monitor.size();
+        let left = (self.x.clamp(0.0, 1.0) * size.width as f32).round();
+        let top = (self.y.clamp(0.0, 1.0) * size.height as f32).round();
+        let right = ((self.x + self.width).clamp(0.0, 1.0) * size.width as f32).round();
+        let bottom = ((self.y + self.height).clamp(0.0, 1.0) * size.height as f32).round();
+
+        mask::Region {
+            position: (position.x + left as i32, position.y + top as i32).into(),
+            size: (
+                (right - left).max(0.0) as u32,
+                (bottom - top).max(0.0) as u32,
+            )
+                .into(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+/// A mask image for a monitor’s live wallpaper.
+pub struct MaskImage {
+    pub monitor: MonitorRef,
+    pub image_path: path::PathBuf,
+}
+
+impl Default for LiveWallpaperConfig {
+    fn default() -> Self {
+        Self {
+            opacity: 1.0,
+            dim_icons: false,
+            icon_brightness: 0.4,
+            icon_motion: 0.125,
+            full_bleed: false,
+            launch_at_login: false,
+            edge_lighting: false,
+            edge_width: 0.15,
+            edge_center_brightness: 0.2,
+            masked_regions: Vec::new(),
+            mask_images: Vec::new(),
+            active_max_fps: None,
+            active_cooldown_secs: 3,
+            adaptive_palette: false,
+            adaptive_palette_interval_secs: 60,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(default, rename_all = "camelCase")]
+/// Saves power on laptops while running on battery.
+pub struct BatteryConfig {
+    /// Limit the frame rate while on battery or in battery saver mode
+    pub max_fps: Option<u32>,
+    /// Pause the animation once the battery drops to this percentage
+    pub pause_below_percent: Option<u8>,
+}
+
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(default, rename_all = "camelCase")]
+/// Configures the daemon, which starts the screensaver by itself instead of waiting for Windows to.
+pub struct DaemonConfig {
+    /// Start the screensaver once there’s been no input for this many seconds
+    pub idle_timeout_secs: u32,
+    /// Keep a screensaver set up and hidden in the background, so that it shows within a frame
+    /// once the computer goes idle, instead of after a few seconds of setting up. It holds on to
+    /// its share of video memory the whole time, and only picks up changes to the settings the
+    /// next time round. It’s started again whenever the monitors change. With `isolateMonitors`,
+    /// the processes for each monitor can’t be set up ahead of time, so this saves starting the
+    /// first process at best.
+    pub warm_start: bool,
+}
+
+impl Default for DaemonConfig {
+    fn default() -> Self {
+        Self {
+            idle_timeout_secs: 5 * 60,
+            warm_start: false,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(default, rename_all = "camelCase")]
+/// Configures the command palette, which lists things to do while Flux runs in a window.
+pub struct CommandPaletteConfig {
+    /// The key that opens and closes the palette, by its SDL name, like `F1` or `Tab`
+    pub key: String,
+}
+
+impl Default for CommandPaletteConfig {
+    fn default() -> Self {
+        Self {
+            key: "F1".to_string(),
+        }
+    }
+}
+
+#[derive(Default, Deserialize, Serialize, Copy, Clone, Debug, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+/// How to span monitors that Windows scales differently, like a laptop screen next to a desktop
+/// monitor.
+pub enum MixedDpiSpan {
+    /// Span them anyway, sizing the lines for the first monitor in the span
+    #[default]
+    First,
+    /// Span them at the scale factor of the densest monitor, and shrink the picture on the
+    /// others to match, with black bars around it
+    Densest,
+    /// Only span monitors with the same scale factor, leaving the rest on surfaces of their own
+    Separate,
+}
+
+impl MixedDpiSpan {
+    pub const ALL: [MixedDpiSpan; 3] = [
+        MixedDpiSpan::First,
+        MixedDpiSpan::Densest,
+        MixedDpiSpan::Separate,
+    ];
+}
+
+#[derive(Default, Deserialize, Serialize, Copy, Clone, Debug, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+/// How the screensaver goes away once you’re back.
+pub enum ExitStyle {
+    /// Close straight away
+    None,
+    /// Fade the animation out to black
+    #[default]
+    FadeToBlack,
+    /// Fade the windows out, revealing the desktop underneath
+    FadeToDesktop,
+}
+
+impl ExitStyle {
+    pub const ALL: [ExitStyle; 3] = [
+        ExitStyle::None,
+        ExitStyle::FadeToBlack,
+        ExitStyle::FadeToDesktop,
+    ];
+}
+
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(default, rename_all = "camelCase")]
+/// Configures the transition out of the screensaver, after a key press or a mouse movement.
+pub struct ExitTransitionConfig {
+    pub style: ExitStyle,
+    /// How long the fade lasts. Input during the fade is ignored.
+    pub duration_ms: u32,
+}
+
+impl Default for ExitTransitionConfig {
+    fn default() -> Self {
+        Self {
+            style: ExitStyle::default(),
+            duration_ms: 300,
+        }
+    }
+}
+
+#[derive(Default, Deserialize, Serialize, Copy, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(default, rename_all = "camelCase")]
+/// Turns the displays off once the screensaver has been running for a while, to save power and
+/// spare OLED panels.
+pub struct BlankingConfig {
+    /// How long the screensaver runs before fading out and turning the displays off. The
+    /// displays are left on when this isn’t set.
+    pub after_mins: Option<u32>,
+    /// How long the displays stay off before waking back into the animation. They stay off
+    /// until there’s some input when this isn’t set.
+    pub wake_after_mins: Option<u32>,
+}
+
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(default, rename_all = "camelCase")]
+/// Gives up on a screensaver that stalls while starting, like on a driver that hangs, instead of
+/// leaving black windows over the monitors.
+pub struct WatchdogConfig {
+    /// How long the monitors have to start showing Flux before trying again in safe mode. 0
+    /// waits forever.
+    pub timeout_secs: u32,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self { timeout_secs: 30 }
+    }
+}
+
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(default, rename_all = "camelCase")]
+/// Limits for the soak test, which runs Flux for hours on end to catch slow leaks.
+pub struct SoakConfig {
+    /// How often to log the memory use, the handle counts, and the frame rate
+    pub sample_interval_secs: u32,
+    /// How long to let the caches and the driver settle before taking the baseline
+    pub warmup_secs: u32,
+    /// Fail once the private memory has grown by more than this many megabytes
+    pub max_memory_growth_mb: u32,
+    /// Fail once the process holds more than this many handles, GDI objects, or USER objects
+    /// over the baseline
+    pub max_handle_growth: u32,
+    /// Fail once the free video memory has dropped by more than this many megabytes, where the
+    /// driver reports it
+    pub max_gpu_memory_drop_mb: u32,
+}
+
+impl Default for SoakConfig {
+    fn default() -> Self {
+        Self {
+            sample_interval_secs: 60,
+            warmup_secs: 5 * 60,
+            max_memory_growth_mb: 64,
+            max_handle_growth: 100,
+            max_gpu_memory_drop_mb: 128,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(default, rename_all = "camelCase")]
+/// Configures which input ends the screensaver.
+pub struct InputConfig {
+    /// Devices that never wake the screensaver, like a flight-sim throttle that jitters on its
+    /// own. Mice, keyboards, and other HID devices go by their Raw Input device name, and
+    /// gamepads, joysticks, and remotes by the name or GUID that SDL reports for them. A HID
+    /// device also stays quiet as a controller.
+    pub ignored_devices: Vec<String>,
+    /// Wake the screensaver with gamepads, joysticks, and remotes that show up as one. Off by
+    /// default, since some, like flight-sim throttles, are left plugged in and jitter on their own.
+    pub controllers: bool,
+    /// How far a stick or trigger has to move from where it was when the screensaver started to
+    /// wake it, from 0.0 to 1.0 of the way from the center. Sticks drift a little at rest, so
+    /// this shouldn’t be too low.
+    pub controller_stick_threshold: OrderedFloat<f32>,
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        Self {
+            ignored_devices: Vec::new(),
+            controllers: false,
+            controller_stick_threshold: OrderedFloat(0.5),
+        }
+    }
+}
+
+impl InputConfig {
+    pub fn is_ignored(&self, name: &str) -> bool {
+        self.ignored_devices
+            .iter()
+            .any(|ignored| ignored.eq_ignore_ascii_case(name))
+    }
+
+    pub fn set_ignored(&mut self, name: &str, is_ignored: bool) {
+        self.ignored_devices
+            .retain(|ignored| !ignored.eq_ignore_ascii_case(name));
+        if is_ignored {
+            self.ignored_devices.push(name.to_string());
+        }
+    }
+}
+
+impl Default for BatteryConfig {
+    fn default() -> Self {
+        Self {
+            max_fps: Some(30),
+            pause_below_percent: Some(10),
+        }
+    }
+}
+
+#[derive(Default, Deserialize, Serialize, Copy, Clone, Debug, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+/// Configures how Flux works with multiple displays.
+pub enum FillMode {
+    /// Display a separate instance on each display
+    None,
+    /// Span across and up to adjacent displays with matching dimensions
+    #[default]
+    Span,
+    /// Fill all displays with a single surface
+    Fill,
+    /// Lay out the surfaces by hand, in the custom layout
+    Custom,
+}
+
+/// A monitor, as listed in the settings.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, JsonSchema)]
+#[serde(untagged)]
+pub enum MonitorRef {
+    /// The display index, which changes when displays are plugged in and out. Older settings
+    /// list monitors this way, and it’s the fallback where there are no stable IDs.
+    Index(u32),
+    /// The device path of the monitor, which stays the same when it’s replugged
+    Id(String),
+}
+
+impl MonitorRef {
+    pub fn matches(&self, monitor: &MonitorHandle) -> bool {
+        match self {
+            MonitorRef::Index(index) => monitor.id() == *index,
+            MonitorRef::Id(id) => monitor.device_path() == Some(id.as_str()),
+        }
+    }
+}
+
+impl From<&MonitorHandle> for MonitorRef {
+    fn from(monitor: &MonitorHandle) -> Self {
+        match monitor.device_path() {
+            Some(path) => MonitorRef::Id(path.to_string()),
+            None => MonitorRef::Index(monitor.id()),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+/// A surface in a custom layout, given in physical pixels on the virtual screen.
+pub struct CustomSurface {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    /// The monitors that the surface is drawn across. Each of them has to overlap the surface.
+    pub monitors: Vec<MonitorRef>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+/// A monitor that is mounted rotated.
+pub struct MonitorRotation {
+    pub monitor: MonitorRef,
+    pub rotation: Rotation,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+/// An image to take a monitor’s colors from.
+pub struct MonitorImage {
+    pub monitor: MonitorRef,
+    pub image_path: path::PathBuf,
+}
+
+/// How far a monitor is physically turned clockwise from the orientation that Windows reports.
+/// The picture is turned the other way before it’s presented, so that it comes out upright.
+#[derive(Default, Deserialize, Serialize, Copy, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub enum Rotation {
+    #[default]
+    #[serde(rename = "0")]
+    None,
+    #[serde(rename = "90")]
+    Clockwise90,
+    #[serde(rename = "180")]
+    Clockwise180,
+    #[serde(rename = "270")]
+    Clockwise270,
+}
+
+impl Rotation {
+    pub const ALL: [Rotation; 4] = [
+        Rotation::None,
+        Rotation::Clockwise90,
+        Rotation::Clockwise180,
+        Rotation::Clockwise270,
+    ];
+
+    pub fn quarter_turns(self) -> u32 {
+        match self {
+            Rotation::None => 0,
+            Rotation::Clockwise90 => 1,
+            Rotation::Clockwise180 => 2,
+            Rotation::Clockwise270 => 3,
+        }
+    }
+
+    /// The size to render at for a window of the given size. Turning a quarter swaps the sides.
+    pub fn render_size(self, size: PhysicalSize<u32>) -> PhysicalSize<u32> {
+        if self.quarter_turns() % 2 == 1 {
+            PhysicalSize::new(size.height, size.width)
+        } else {
+            size
+        }
+    }
+}
+
+impl FillMode {
+    pub const ALL: [FillMode; 4] = [
+        FillMode::None,
+        FillMode::Span,
+        FillMode::Fill,
+        FillMode::Custom,
+    ];
+}
+
+impl fmt::Display for FillMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                FillMode::None => "None",
+                FillMode::Span => "Span",
+                FillMode::Fill => "Fill",
+                FillMode::Custom => "Custom",
+            }
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum Problem {
+    GetProjectDir,
+    CreateProjectDir {
+        path: path::PathBuf,
+        err: io::Error,
+    },
+    ReadSettings {
+        path: path::PathBuf,
+        err: io::Error,
+    },
+    DecodeSettings {
+        path: path::PathBuf,
         err: serde_json::Error,
     },
     UnsupportedVersion {
         version: String,
     },
     NoSaveLocation,
+    Override {
+        setting: String,
+        reason: String,
+    },
+    UnknownSetting {
+        key: String,
+    },
     Save {
         path: path::PathBuf,
         err: serde_json::Error,
@@ -330,6 +2025,10 @@ impl fmt::Display for Problem {
                 write!(f, "Unsupported settings version {}.", version)
             }
             Problem::NoSaveLocation => write!(f, "No location available to save the settings"),
+            Problem::Override { setting, reason } => {
+                write!(f, "Can’t apply the setting {}: {}", setting, reason)
+            }
+            Problem::UnknownSetting { key } => write!(f, "There’s no setting called {}", key),
             Problem::Save { path, err } => {
                 write!(
                     f,
@@ -355,31 +2054,437 @@ trait UpgradableConfig {
 mod test {
     use super::*;
 
+    #[test]
+    fn it_applies_overrides() {
+        let mut config = Config::default();
+        config
+            .apply_overrides(&[
+                "flux.colorMode=preset".to_string(),
+                "flux.presetName=Plasma".to_string(),
+                "platform.windows.monitorStaggerMs=250".to_string(),
+            ])
+            .unwrap();
+
+        assert_eq!(
+            config.flux.color_mode,
+            ColorMode::Preset {
+                preset_name: flux::settings::ColorPreset::Plasma
+            }
+        );
+        assert_eq!(config.platform.windows.monitor_stagger_ms, 250);
+
+        assert!(config
+            .apply_overrides(&["platform.windows.fillMode=sideways".to_string()])
+            .is_err());
+        assert!(config.apply_overrides(&["logLevel".to_string()]).is_err());
+    }
+
+    #[test]
+    fn it_gets_sets_and_resets_single_settings() {
+        let mut config = Config::default();
+        assert_eq!(
+            config.get("platform.windows.fillMode").unwrap(),
+            serde_json::json!("span")
+        );
+
+        config
+            .set(&[
+                "platform.windows.fillMode=fill".to_string(),
+                "flux.maxFps=30".to_string(),
+            ])
+            .unwrap();
+        assert_eq!(config.platform.windows.fill_mode, FillMode::Fill);
+        assert_eq!(config.flux.max_fps, Some(30));
+
+        // Misspelled keys and invalid values leave the config alone.
+        assert!(matches!(
+            config.set(&["platform.windows.filMode=none".to_string()]),
+            Err(Problem::UnknownSetting { .. })
+        ));
+        assert!(config.set(&["flux.maxFps=-1".to_string()]).is_err());
+        assert!(config.get("flux.maxFps.value").is_err());
+        assert_eq!(config.flux.max_fps, Some(30));
+
+        config.reset(Some("flux.maxFps")).unwrap();
+        assert_eq!(config.flux.max_fps, None);
+        assert_eq!(config.platform.windows.fill_mode, FillMode::Fill);
+
+        config.reset(None).unwrap();
+        assert_eq!(config, Config::default());
+    }
+
+    #[test]
+    fn it_describes_where_settings_come_from() {
+        let env_overrides = ["platform.windows.fillMode=fill".to_string()];
+        let cli_overrides = ["flux.presetName=Plasma".to_string()];
+        let mut config = Config::default();
+        config
+            .apply_overrides(&["platform.windows.startDelayMs=500".to_string()])
+            .unwrap();
+        config.apply_overrides(&env_overrides).unwrap();
+        config.apply_overrides(&cli_overrides).unwrap();
+
+        let description = config.describe(&env_overrides, &cli_overrides);
+        let sources = &description["sources"];
+        assert_eq!(sources["flux.presetName"], "cli");
+        assert_eq!(sources["platform.windows.fillMode"], "env");
+        assert_eq!(sources["platform.windows.startDelayMs"], "file");
+        assert_eq!(sources["logLevel"], "default");
+        assert_eq!(
+            description["config"]["platform"]["windows"]["fillMode"],
+            "fill"
+        );
+    }
+
+    #[test]
+    fn it_falls_back_to_the_next_available_color_source() {
+        use flux::settings;
+
+        let image = path::Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+        let mut config = Config::default();
+        config.flux.color_mode = ColorMode::DesktopImage;
+        config.flux.color_fallbacks = vec![
+            ColorMode::ImageFile {
+                image_path: None,
+                image_size: None,
+            },
+            ColorMode::ImageFile {
+                image_path: Some(path::PathBuf::from("missing.png")),
+                image_size: None,
+            },
+            ColorMode::Preset {
+                preset_name: ColorPreset::Plasma,
+            },
+        ];
+
+        assert_eq!(
+            config.color_modes(None, None),
+            vec![
+                settings::ColorMode::Preset(ColorPreset::Plasma),
+                settings::ColorMode::default(),
+            ]
+        );
+        assert_eq!(
+            config.color_modes(Some(&image), None)[0],
+            settings::ColorMode::ImageFile(image.clone())
+        );
+        let background_color = Color {
+            r: 0x00,
+            g: 0x63,
+            b: 0xb1,
+        };
+        assert!(matches!(
+            &config.color_modes(None, Some(background_color))[0],
+            settings::ColorMode::ImageFile(path) if *path != image
+        ));
+        assert!(config.prefers_desktop_image(false));
+
+        config.flux.color_mode = ColorMode::Spotlight;
+        config.flux.color_fallbacks = vec![ColorMode::DesktopImage];
+        assert!(config.prefers_desktop_image(false));
+        assert!(!config.prefers_desktop_image(true));
+
+        config.flux.color_mode = ColorMode::Preset {
+            preset_name: ColorPreset::Freedom,
+        };
+        assert!(!config.prefers_desktop_image(false));
+    }
+
+    #[test]
+    fn it_groups_monitors_to_span() {
+        use MonitorRef::Index;
+
+        let mut windows = WindowsConfig::default();
+        windows.span_together(Index(0), Index(1));
+        windows.span_together(Index(3), Index(2));
+        assert_eq!(
+            windows.span_groups,
+            vec![vec![Index(0), Index(1)], vec![Index(2), Index(3)]]
+        );
+
+        // Joining two groups merges them
+        windows.span_together(Index(1), Index(2));
+        assert_eq!(
+            windows.span_groups,
+            vec![vec![Index(0), Index(1), Index(2), Index(3)]]
+        );
+
+        windows.unspan(&Index(0));
+        windows.unspan(&Index(1));
+        windows.unspan(&Index(2));
+        assert_eq!(windows.span_groups, Vec::<Vec<MonitorRef>>::new());
+    }
+
+    #[test]
+    fn it_ignores_devices_by_name_in_any_case() {
+        let throttle = r"\\?\HID#VID_044F&PID_B10A#7&1a2b3c4d&0&0000";
+        let mut input = InputConfig::default();
+        input.set_ignored(throttle, true);
+        input.set_ignored(&throttle.to_lowercase(), true);
+        assert_eq!(input.ignored_devices.len(), 1);
+        assert!(input.is_ignored(&throttle.to_uppercase()));
+
+        input.set_ignored(throttle, false);
+        assert!(!input.is_ignored(throttle));
+    }
+
+    #[test]
+    fn it_takes_the_colors_from_the_monitor_image() {
+        use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+        let monitor = |id| {
+            MonitorHandle::new(
+                id,
+                PhysicalPosition::new(1920 * id as i32, 0),
+                PhysicalSize::new(1920, 1080),
+                1.0,
+            )
+        };
+        let monitors = [monitor(0), monitor(1)];
+
+        let mut config = Config::default();
+        let windows = &mut config.platform.windows;
+        windows.set_monitor_image(MonitorRef::Index(1), Some("old.png".into()));
+        windows.set_monitor_image(MonitorRef::Index(1), Some("lake.png".into()));
+        assert_eq!(windows.monitor_images.len(), 1);
+        assert_eq!(windows.monitor_image(&monitors[..1]), None);
+        // A span takes the image of the first monitor that has one.
+        assert_eq!(
+            windows.monitor_image(&monitors),
+            Some(path::Path::new("lake.png"))
+        );
+
+        let with_image = config.with_color_image(path::Path::new("lake.png"));
+        assert_eq!(
+            with_image.flux.color_mode,
+            ColorMode::ImageFile {
+                image_path: Some("lake.png".into()),
+                image_size: None,
+            }
+        );
+        assert_eq!(with_image.flux.color_fallbacks[0], config.flux.color_mode);
+    }
+
+    #[test]
+    fn it_switches_monitors_over_to_stable_ids() {
+        use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+        let monitor = |id, device_path: Option<&str>| {
+            MonitorHandle::new(
+                id,
+                PhysicalPosition::new(1920 * id as i32, 0),
+                PhysicalSize::new(1920, 1080),
+                1.0,
+            )
+            .with_device_path(device_path.map(str::to_string))
+        };
+        let device_path = r"\\?\DISPLAY#DEL40A3#5&2b3c4d&0&UID4353";
+        let monitors = [monitor(0, Some(device_path)), monitor(1, None)];
+
+        let mut windows = WindowsConfig {
+            span_groups: vec![vec![MonitorRef::Index(0), MonitorRef::Index(1)]],
+            excluded_monitors: vec![MonitorRef::Index(0), MonitorRef::Index(2)],
+            ..Default::default()
+        };
+        windows.resolve_monitors(&monitors);
+
+        let stable = MonitorRef::Id(device_path.to_string());
+        assert_eq!(
+            windows.span_groups,
+            vec![vec![stable.clone(), MonitorRef::Index(1)]]
+        );
+        assert_eq!(
+            windows.excluded_monitors,
+            vec![stable, MonitorRef::Index(2)]
+        );
+        assert!(windows.is_excluded(&monitors[0]));
+        assert!(!windows.is_excluded(&monitors[1]));
+
+        // Both kinds can be written by hand in the settings file.
+        assert_eq!(
+            serde_json::to_value(&windows.span_groups).unwrap(),
+            serde_json::json!([[device_path, 1]])
+        );
+    }
+
+    #[test]
+    fn it_writes_rotations_in_degrees() {
+        let rotation = MonitorRotation {
+            monitor: MonitorRef::Index(2),
+            rotation: Rotation::Clockwise270,
+        };
+        assert_eq!(
+            serde_json::to_value(&rotation).unwrap(),
+            serde_json::json!({ "monitor": 2, "rotation": "270" })
+        );
+
+        let size = PhysicalSize::new(1920, 1080);
+        assert_eq!(
+            Rotation::Clockwise270.render_size(size),
+            PhysicalSize::new(1080, 1920)
+        );
+        assert_eq!(Rotation::Clockwise180.render_size(size), size);
+    }
+
+    #[test]
+    fn it_scales_the_simulation_settings() {
+        let defaults = flux::settings::Settings::default();
+        let mut settings = defaults.clone();
+        SimulationSettings {
+            line_width: Some(OrderedFloat(2.0)),
+            line_density: Some(OrderedFloat(2.0)),
+            ..Default::default()
+        }
+        .apply(&mut settings);
+
+        assert_eq!(settings.line_width, defaults.line_width * 2.0);
+        assert_eq!(settings.line_length, defaults.line_length);
+        assert_eq!(
+            settings.grid_spacing,
+            (defaults.grid_spacing as f32 / 2.0).round() as u32
+        );
+    }
+
     #[test]
     fn serialize_deserialize() {
         use serde_json::json;
         let config = Config {
             version: LATEST_VERSION,
             log_level: log::Level::Warn,
+            logging: LoggingConfig::default(),
             flux: FluxSettings {
                 color_mode: ColorMode::Preset {
                     preset_name: flux::settings::ColorPreset::Plasma,
                 },
+                ..Default::default()
             },
             platform: PlatformConfig::default(),
+            playlist: PlaylistConfig::default(),
+            ping: PingConfig::default(),
+            ambient_audio: AmbientAudioConfig::default(),
             location: None,
         };
         let expected = json!({
             "version": 2,
             "logLevel": "warn",
+            "logging": {
+                "modules": {},
+                "json": false,
+                "maxFileSizeKb": 1024,
+                "maxOldFiles": 3
+            },
             "flux": {
                 "colorMode": "preset",
-                "presetName": "Plasma"
+                "presetName": "Plasma",
+                "colorFallbacks": [],
+                "quality": "automatic",
+                "calibration": null,
+                "maxFps": null,
+                "simulation": {
+                    "lineLength": null,
+                    "lineWidth": null,
+                    "noiseStrength": null,
+                    "lineDensity": null,
+                    "velocityDissipation": null
+                },
+                "overlay": {
+                    "content": "none",
+                    "text": "",
+                    "position": "bottomRight",
+                    "fontSize": 42,
+                    "opacity": 0.75
+                }
             },
             "platform": {
                 "windows": {
-                    "fillMode": "span"
+                    "fillMode": "span",
+                    "spanGroups": [],
+                    "mixedDpiSpan": "first",
+                    "customLayout": [],
+                    "excludedMonitors": [],
+                    "startDelayMs": 0,
+                    "monitorStaggerMs": 0,
+                    "isolateMonitors": false,
+                    "uniformPhysicalDensity": false,
+                    "monitorRotations": [],
+                    "monitorImages": [],
+                    "highDynamicRange": false,
+                    "interpolateFrames": false,
+                    "syncPresents": false,
+                    "maxFrameLatency": null,
+                    "preferredGpu": null,
+                    "lockOnExit": false,
+                    "showStats": false,
+                    "crashDialog": false,
+                    "blockingProcesses": [],
+                    "liveWallpaper": {
+                        "opacity": 1.0,
+                        "dimIcons": false,
+                        "iconBrightness": 0.4f32,
+                        "iconMotion": 0.125,
+                        "fullBleed": false,
+                        "launchAtLogin": false,
+                        "edgeLighting": false,
+                        "edgeWidth": 0.15f32,
+                        "edgeCenterBrightness": 0.2f32,
+                        "maskedRegions": [],
+                        "maskImages": [],
+                        "activeMaxFps": null,
+                        "activeCooldownSecs": 3,
+                        "adaptivePalette": false,
+                        "adaptivePaletteIntervalSecs": 60
+                    },
+                    "battery": {
+                        "maxFps": 30,
+                        "pauseBelowPercent": 10
+                    },
+                    "daemon": {
+                        "idleTimeoutSecs": 300,
+                        "warmStart": false
+                    },
+                    "commandPalette": {
+                        "key": "F1"
+                    },
+                    "exitTransition": {
+                        "style": "fadeToBlack",
+                        "durationMs": 300
+                    },
+                    "blanking": {
+                        "afterMins": null,
+                        "wakeAfterMins": null
+                    },
+                    "input": {
+                        "ignoredDevices": [],
+                        "controllers": false,
+                        "controllerStickThreshold": 0.5
+                    },
+                    "watchdog": {
+                        "timeoutSecs": 30
+                    },
+                    "soak": {
+                        "sampleIntervalSecs": 60,
+                        "warmupSecs": 300,
+                        "maxMemoryGrowthMb": 64,
+                        "maxHandleGrowth": 100,
+                        "maxGpuMemoryDropMb": 128
+                    },
+                    "gpuWorkarounds": []
                 }
+            },
+            "playlist": {
+                "scenes": [],
+                "intervalMins": 10,
+                "crossfadeSecs": 5
+            },
+            "ping": {
+                "enabled": false,
+                "endpoint": null,
+                "lastSentVersion": null
+            },
+            "ambientAudio": {
+                "path": null,
+                "volume": 0.5,
+                "fadeInMs": 3000
             }
         });
         assert_eq!(serde_json::to_value(&config).unwrap(), expected);
@@ -407,14 +2512,616 @@ mod test {
             Config {
                 version: LATEST_VERSION,
                 log_level: log::Level::Warn,
+                logging: LoggingConfig::default(),
                 flux: FluxSettings {
                     color_mode: ColorMode::Preset {
                         preset_name: flux::settings::ColorPreset::Plasma,
                     },
+                    ..Default::default()
                 },
                 platform: PlatformConfig::default(),
+                playlist: PlaylistConfig::default(),
+                ping: PingConfig::default(),
+                ambient_audio: AmbientAudioConfig::default(),
                 location: None,
             }
         );
     }
+
+    mod properties {
+        use super::*;
+        use proptest::prelude::*;
+
+        fn color_mode() -> impl Strategy<Value = ColorMode> {
+            prop_oneof![
+                prop::sample::select(vec![
+                    ColorPreset::Original,
+                    ColorPreset::Plasma,
+                    ColorPreset::Poolside,
+                    ColorPreset::Freedom,
+                ])
+                .prop_map(|preset_name| ColorMode::Preset { preset_name }),
+                (
+                    prop::option::of("[a-zA-Z0-9 _.:/\\\\-]{0,32}"),
+                    prop::option::of((1u32..16384, 1u32..16384)),
+                )
+                    .prop_map(|(image_path, image_size)| ColorMode::ImageFile {
+                        image_path: image_path.map(path::PathBuf::from),
+                        image_size: image_size.map(|(width, height)| ImageSize { width, height }),
+                    }),
+                Just(ColorMode::DesktopImage),
+                Just(ColorMode::Spotlight),
+                prop::collection::vec((0.0f32..=1.0, any::<[u8; 3]>()), 0..4).prop_map(|stops| {
+                    ColorMode::CustomGradient {
+                        stops: stops
+                            .into_iter()
+                            .map(|(position, [r, g, b])| (position.into(), Color { r, g, b }))
+                            .collect(),
+                    }
+                }),
+            ]
+        }
+
+        fn quality() -> impl Strategy<Value = Quality> {
+            prop::sample::select(Quality::ALL.to_vec())
+        }
+
+        fn flux_settings() -> impl Strategy<Value = FluxSettings> {
+            (
+                color_mode(),
+                prop::collection::vec(color_mode(), 0..3),
+                quality(),
+                prop::option::of(
+                    (".*", quality())
+                        .prop_map(|(renderer, quality)| Calibration { renderer, quality }),
+                ),
+                any::<Option<u32>>(),
+                (simulation_settings(), overlay_settings()),
+            )
+                .prop_map(
+                    |(
+                        color_mode,
+                        color_fallbacks,
+                        quality,
+                        calibration,
+                        max_fps,
+                        (simulation, overlay),
+                    )| {
+                        FluxSettings {
+                            color_mode,
+                            color_fallbacks,
+                            quality,
+                            calibration,
+                            max_fps,
+                            simulation,
+                            overlay,
+                        }
+                    },
+                )
+        }
+
+        fn overlay_settings() -> impl Strategy<Value = OverlaySettings> {
+            (
+                prop::sample::select(vec![
+                    OverlayContent::None,
+                    OverlayContent::Clock,
+                    OverlayContent::Date,
+                    OverlayContent::ClockAndDate,
+                    OverlayContent::Text,
+                ]),
+                ".*",
+                prop::sample::select(vec![
+                    OverlayPosition::TopLeft,
+                    OverlayPosition::TopRight,
+                    OverlayPosition::BottomLeft,
+                    OverlayPosition::BottomRight,
+                    OverlayPosition::Center,
+                ]),
+                any::<u32>(),
+                (0.0f32..=1.0).prop_map(OrderedFloat),
+            )
+                .prop_map(|(content, text, position, font_size, opacity)| {
+                    OverlaySettings {
+                        content,
+                        text,
+                        position,
+                        font_size,
+                        opacity,
+                    }
+                })
+        }
+
+        fn simulation_settings() -> impl Strategy<Value = SimulationSettings> {
+            let scale = || prop::option::of((0.0f32..4.0).prop_map(OrderedFloat));
+            (scale(), scale(), scale(), scale(), scale()).prop_map(
+                |(line_length, line_width, noise_strength, line_density, velocity_dissipation)| {
+                    SimulationSettings {
+                        line_length,
+                        line_width,
+                        noise_strength,
+                        line_density,
+                        velocity_dissipation,
+                    }
+                },
+            )
+        }
+
+        fn live_wallpaper() -> impl Strategy<Value = LiveWallpaperConfig> {
+            (
+                -10.0f32..10.0,
+                any::<bool>(),
+                -10.0f32..10.0,
+                -10.0f32..10.0,
+                any::<bool>(),
+                any::<bool>(),
+                (any::<bool>(), -10.0f32..10.0, -10.0f32..10.0),
+                (
+                    prop::collection::vec(masked_region(), 0..3),
+                    prop::collection::vec(mask_image(), 0..3),
+                ),
+                any::<Option<u32>>(),
+                any::<u32>(),
+                (any::<bool>(), any::<u32>()),
+            )
+                .prop_map(
+                    |(
+                        opacity,
+                        dim_icons,
+                        icon_brightness,
+                        icon_motion,
+                        full_bleed,
+                        launch_at_login,
+                        (edge_lighting, edge_width, edge_center_brightness),
+                        (masked_regions, mask_images),
+                        active_max_fps,
+                        active_cooldown_secs,
+                        (adaptive_palette, adaptive_palette_interval_secs),
+                    )| {
+                        LiveWallpaperConfig {
+                            opacity,
+                            dim_icons,
+                            icon_brightness,
+                            icon_motion,
+                            full_bleed,
+                            launch_at_login,
+                            edge_lighting,
+                            edge_width,
+                            edge_center_brightness,
+                            masked_regions,
+                            mask_images,
+                            active_max_fps,
+                            active_cooldown_secs,
+                            adaptive_palette,
+                            adaptive_palette_interval_secs,
+                        }
+                    },
+                )
+        }
+
+        fn custom_surface() -> impl Strategy<Value = CustomSurface> {
+            (
+                any::<i32>(),
+                any::<i32>(),
+                any::<u32>(),
+                any::<u32>(),
+                prop::collection::vec(monitor_ref(), 0..3),
+            )
+                .prop_map(|(x, y, width, height, monitors)| CustomSurface {
+                    x,
+                    y,
+                    width,
+                    height,
+                    monitors,
+                })
+        }
+
+        fn gpu_workaround() -> impl Strategy<Value = GpuWorkaround> {
+            (".*", any::<bool>(), any::<bool>(), any::<Option<u32>>()).prop_map(
+                |(renderer, disable_dxgi_interop, force_gles, max_fps)| GpuWorkaround {
+                    renderer,
+                    workarounds: GpuWorkarounds {
+                        disable_dxgi_interop,
+                        force_gles,
+                        max_fps,
+                    },
+                },
+            )
+        }
+
+        fn monitor_ref() -> impl Strategy<Value = MonitorRef> {
+            prop_oneof![
+                any::<u32>().prop_map(MonitorRef::Index),
+                ".*".prop_map(MonitorRef::Id),
+            ]
+        }
+
+        fn masked_region() -> impl Strategy<Value = MaskedRegion> {
+            (
+                monitor_ref(),
+                0.0f32..1.0,
+                0.0f32..1.0,
+                0.0f32..1.0,
+                0.0f32..1.0,
+                0.0f32..1.0,
+            )
+                .prop_map(|(monitor, x, y, width, height, brightness)| MaskedRegion {
+                    monitor,
+                    x,
+                    y,
+                    width,
+                    height,
+                    brightness,
+                })
+        }
+
+        fn mask_image() -> impl Strategy<Value = MaskImage> {
+            (monitor_ref(), ".*").prop_map(|(monitor, image_path)| MaskImage {
+                monitor,
+                image_path: path::PathBuf::from(image_path),
+            })
+        }
+
+        fn soak() -> impl Strategy<Value = SoakConfig> {
+            (
+                any::<u32>(),
+                any::<u32>(),
+                any::<u32>(),
+                any::<u32>(),
+                any::<u32>(),
+            )
+                .prop_map(
+                    |(
+                        sample_interval_secs,
+                        warmup_secs,
+                        max_memory_growth_mb,
+                        max_handle_growth,
+                        max_gpu_memory_drop_mb,
+                    )| SoakConfig {
+                        sample_interval_secs,
+                        warmup_secs,
+                        max_memory_growth_mb,
+                        max_handle_growth,
+                        max_gpu_memory_drop_mb,
+                    },
+                )
+        }
+
+        fn monitor_rotation() -> impl Strategy<Value = MonitorRotation> {
+            (monitor_ref(), prop::sample::select(Rotation::ALL.to_vec()))
+                .prop_map(|(monitor, rotation)| MonitorRotation { monitor, rotation })
+        }
+
+        fn monitor_image() -> impl Strategy<Value = MonitorImage> {
+            (monitor_ref(), "[A-Za-z0-9 _.:/\\\\-]{1,32}").prop_map(|(monitor, image_path)| {
+                MonitorImage {
+                    monitor,
+                    image_path: path::PathBuf::from(image_path),
+                }
+            })
+        }
+
+        fn windows_config() -> impl Strategy<Value = WindowsConfig> {
+            (
+                prop::sample::select(FillMode::ALL.to_vec()),
+                (
+                    prop::collection::vec(prop::collection::vec(monitor_ref(), 0..4), 0..3),
+                    prop::sample::select(MixedDpiSpan::ALL.to_vec()),
+                    prop::collection::vec(custom_surface(), 0..3),
+                ),
+                prop::collection::vec(monitor_ref(), 0..3),
+                any::<u32>(),
+                any::<u32>(),
+                (
+                    any::<bool>(),
+                    any::<bool>(),
+                    (
+                        prop::collection::vec(monitor_rotation(), 0..3),
+                        prop::collection::vec(monitor_image(), 0..3),
+                    ),
+                    any::<bool>(),
+                    (any::<bool>(), any::<bool>(), any::<Option<u32>>()),
+                    prop::option::of("[A-Za-z0-9 ]{0,12}"),
+                    any::<bool>(),
+                    any::<bool>(),
+                    (
+                        any::<bool>(),
+                        prop::collection::vec("[A-Za-z0-9 _.]{1,16}", 0..3),
+                    ),
+                ),
+                live_wallpaper(),
+                (any::<Option<u32>>(), any::<Option<u8>>()),
+                ((any::<u32>(), any::<bool>()), "[A-Za-z0-9 ]{0,8}"),
+                (
+                    (prop::sample::select(ExitStyle::ALL.to_vec()), any::<u32>()),
+                    (any::<Option<u32>>(), any::<Option<u32>>()),
+                ),
+                (
+                    (
+                        prop::collection::vec(r"[A-Za-z0-9#&?\\_]{0,16}", 0..3),
+                        any::<bool>(),
+                        (0.0f32..1.0).prop_map(OrderedFloat),
+                    ),
+                    any::<u32>(),
+                    soak(),
+                ),
+                prop::collection::vec(gpu_workaround(), 0..3),
+            )
+                .prop_map(
+                    |(
+                        fill_mode,
+                        (span_groups, mixed_dpi_span, custom_layout),
+                        excluded_monitors,
+                        start_delay_ms,
+                        monitor_stagger_ms,
+                        (
+                            isolate_monitors,
+                            uniform_physical_density,
+                            (monitor_rotations, monitor_images),
+                            high_dynamic_range,
+                            (interpolate_frames, sync_presents, max_frame_latency),
+                            preferred_gpu,
+                            lock_on_exit,
+                            show_stats,
+                            (crash_dialog, blocking_processes),
+                        ),
+                        live_wallpaper,
+                        (max_fps, pause_below_percent),
+                        ((idle_timeout_secs, warm_start), command_palette_key),
+                        ((exit_style, exit_duration_ms), (blank_after_mins, wake_after_mins)),
+                        (
+                            (ignored_devices, controllers, controller_stick_threshold),
+                            watchdog_timeout_secs,
+                            soak,
+                        ),
+                        gpu_workarounds,
+                    )| WindowsConfig {
+                        fill_mode,
+                        span_groups,
+                        mixed_dpi_span,
+                        custom_layout,
+                        excluded_monitors,
+                        start_delay_ms,
+                        monitor_stagger_ms,
+                        isolate_monitors,
+                        uniform_physical_density,
+                        monitor_rotations,
+                        monitor_images,
+                        high_dynamic_range,
+                        interpolate_frames,
+                        sync_presents,
+                        max_frame_latency,
+                        preferred_gpu,
+                        lock_on_exit,
+                        show_stats,
+                        crash_dialog,
+                        blocking_processes,
+                        live_wallpaper,
+                        battery: BatteryConfig {
+                            max_fps,
+                            pause_below_percent,
+                        },
+                        daemon: DaemonConfig {
+                            idle_timeout_secs,
+                            warm_start,
+                        },
+                        command_palette: CommandPaletteConfig {
+                            key: command_palette_key,
+                        },
+                        exit_transition: ExitTransitionConfig {
+                            style: exit_style,
+                            duration_ms: exit_duration_ms,
+                        },
+                        blanking: BlankingConfig {
+                            after_mins: blank_after_mins,
+                            wake_after_mins,
+                        },
+                        input: InputConfig {
+                            ignored_devices,
+                            controllers,
+                            controller_stick_threshold,
+                        },
+                        watchdog: WatchdogConfig {
+                            timeout_secs: watchdog_timeout_secs,
+                        },
+                        soak,
+                        gpu_workarounds,
+                    },
+                )
+        }
+
+        fn log_level() -> impl Strategy<Value = log::Level> {
+            prop::sample::select(vec![
+                log::Level::Error,
+                log::Level::Warn,
+                log::Level::Info,
+                log::Level::Debug,
+                log::Level::Trace,
+            ])
+        }
+
+        fn ping() -> impl Strategy<Value = PingConfig> {
+            (
+                any::<bool>(),
+                prop::option::of(".*"),
+                prop::option::of(".*"),
+            )
+                .prop_map(|(enabled, endpoint, last_sent_version)| PingConfig {
+                    enabled,
+                    endpoint,
+                    last_sent_version,
+                })
+        }
+
+        fn ambient_audio() -> impl Strategy<Value = AmbientAudioConfig> {
+            (
+                prop::option::of("[a-zA-Z0-9 _.:/\\\\-]{0,32}"),
+                -10.0f32..10.0,
+                any::<u32>(),
+            )
+                .prop_map(|(path, volume, fade_in_ms)| AmbientAudioConfig {
+                    path: path.map(path::PathBuf::from),
+                    volume,
+                    fade_in_ms,
+                })
+        }
+
+        fn logging() -> impl Strategy<Value = LoggingConfig> {
+            (
+                prop::collection::btree_map(
+                    "[a-z_]{1,8}(::[a-z_]{1,8}){0,2}",
+                    log_level().prop_map(ModuleLogLevel),
+                    0..3,
+                ),
+                any::<bool>(),
+                any::<u32>(),
+                any::<u32>(),
+            )
+                .prop_map(|(modules, json, max_file_size_kb, max_old_files)| {
+                    LoggingConfig {
+                        modules,
+                        json,
+                        max_file_size_kb,
+                        max_old_files,
+                    }
+                })
+        }
+
+        fn config() -> impl Strategy<Value = Config> {
+            (
+                log_level(),
+                logging(),
+                flux_settings(),
+                windows_config(),
+                playlist(),
+                ping(),
+                ambient_audio(),
+            )
+                .prop_map(
+                    |(log_level, logging, flux, windows, playlist, ping, ambient_audio)| Config {
+                        version: LATEST_VERSION,
+                        log_level,
+                        logging,
+                        flux,
+                        platform: PlatformConfig { windows },
+                        playlist,
+                        ping,
+                        ambient_audio,
+                        location: None,
+                    },
+                )
+        }
+
+        fn playlist() -> impl Strategy<Value = PlaylistConfig> {
+            let scene = (
+                prop::option::of("[a-zA-Z0-9 ]{1,16}"),
+                simulation_settings(),
+            )
+                .prop_map(|(preset, simulation)| Scene { preset, simulation });
+
+            (
+                prop::collection::vec(scene, 0..3),
+                any::<u32>(),
+                any::<u32>(),
+            )
+                .prop_map(|(scenes, interval_mins, crossfade_secs)| PlaylistConfig {
+                    scenes,
+                    interval_mins,
+                    crossfade_secs,
+                })
+        }
+
+        fn v1_config() -> impl Strategy<Value = v1::Config> {
+            let color_mode = prop_oneof![
+                prop::sample::select(vec![
+                    ColorPreset::Original,
+                    ColorPreset::Plasma,
+                    ColorPreset::Poolside,
+                    ColorPreset::Freedom,
+                ])
+                .prop_map(v1::ColorMode::Preset),
+                Just(v1::ColorMode::DesktopImage),
+            ];
+
+            (0..2u64, 0..10u64, 0..10u64, log_level(), color_mode).prop_map(
+                |(major, minor, patch, log_level, color_mode)| v1::Config {
+                    version: semver::Version::new(major, minor, patch),
+                    log_level,
+                    flux: v1::FluxSettings { color_mode },
+                },
+            )
+        }
+
+        // Any JSON value, nested a few levels deep.
+        fn json() -> impl Strategy<Value = serde_json::Value> {
+            let leaf = prop_oneof![
+                Just(serde_json::Value::Null),
+                any::<bool>().prop_map(serde_json::Value::from),
+                any::<i64>().prop_map(serde_json::Value::from),
+                any::<f64>().prop_map(serde_json::Value::from),
+                ".*".prop_map(serde_json::Value::from),
+            ];
+
+            leaf.prop_recursive(4, 32, 8, |inner| {
+                prop_oneof![
+                    prop::collection::vec(inner.clone(), 0..8).prop_map(serde_json::Value::from),
+                    prop::collection::btree_map(".*", inner, 0..8).prop_map(|object| {
+                        serde_json::Value::Object(object.into_iter().collect())
+                    }),
+                ]
+            })
+        }
+
+        // Replace one of the settings in a valid config with any JSON value.
+        fn corrupted_config() -> impl Strategy<Value = serde_json::Value> {
+            (config(), any::<prop::sample::Index>(), json()).prop_map(|(config, index, value)| {
+                let mut config_ast = serde_json::to_value(&config).unwrap();
+
+                let mut leaves = Vec::new();
+                collect_leaves(&config_ast, "", &mut leaves);
+                let pointer = format!("/{}", leaves[index.index(leaves.len())].0.replace('.', "/"));
+
+                if let Some(leaf) = config_ast.pointer_mut(&pointer) {
+                    *leaf = value;
+                }
+                config_ast
+            })
+        }
+
+        proptest! {
+            #[test]
+            fn it_round_trips_the_settings(config in config()) {
+                let config_string = serde_json::to_string_pretty(&config).unwrap();
+                prop_assert_eq!(Config::from_string(&config_string, None).unwrap(), config);
+            }
+
+            #[test]
+            fn it_upgrades_any_legacy_settings(legacy in v1_config()) {
+                let config_string = serde_json::to_string(&legacy).unwrap();
+                prop_assert_eq!(
+                    Config::from_string(&config_string, None).unwrap(),
+                    legacy.upgrade()
+                );
+            }
+
+            // None of these should panic. Errors are fine, and fall back to the defaults.
+            #[test]
+            fn it_handles_any_text(config_string in ".*") {
+                let _ = Config::from_string(&config_string, None);
+            }
+
+            #[test]
+            fn it_handles_any_json(value in json()) {
+                let _ = Config::from_string(&value.to_string(), None);
+            }
+
+            #[test]
+            fn it_handles_corrupted_settings(config_ast in corrupted_config()) {
+                // Whatever is accepted must be safe to save and load again.
+                if let Ok(config) = Config::from_string(&config_ast.to_string(), None) {
+                    let saved = serde_json::to_string(&config).unwrap();
+                    let _ = Config::from_string(&saved, None);
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,103 @@
+use super::{v1, UpgradableConfig, LATEST_VERSION};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::path;
+
+/// An upgrade from one version of the settings file to the next.
+pub struct Migration {
+    /// The version that this upgrades from
+    pub from: u8,
+    pub upgrade: fn(Value) -> Result<Value, serde_json::Error>,
+}
+
+// One migration for every version before the latest, in order. When the settings file changes in
+// a way that older files won’t decode into, bump `LATEST_VERSION` and add the upgrade from the
+// previous version here.
+const MIGRATIONS: &[Migration] = &[Migration {
+    from: 1,
+    upgrade: upgrade_with::<v1::Config>,
+}];
+
+/// The version of a settings file. The first version was a semver string, like "0.1.0", and
+/// counts as version 1.
+pub fn version_of(config_ast: &Value) -> Result<u8, serde_json::Error> {
+    match &config_ast["version"] {
+        Value::String(_) => Ok(1),
+        raw_version => serde_json::from_value(raw_version.clone()),
+    }
+}
+
+/// The migrations that bring a settings file at `version` up to the latest version, in the order
+/// to run them. `None` if there’s no way there, like for a file from a newer release of Flux.
+pub fn chain(version: u8) -> Option<Vec<&'static Migration>> {
+    let mut next = version;
+    let mut steps = Vec::new();
+    while next < LATEST_VERSION {
+        let step = MIGRATIONS.iter().find(|migration| migration.from == next)?;
+        steps.push(step);
+        next += 1;
+    }
+
+    (next == LATEST_VERSION).then_some(steps)
+}
+
+/// Run each migration in turn, stamping the settings with each new version as it goes.
+pub fn run(
+    mut config_ast: Value,
+    steps: &[&'static Migration],
+) -> Result<Value, serde_json::Error> {
+    for step in steps {
+        config_ast = (step.upgrade)(config_ast)?;
+        config_ast["version"] = Value::from(step.from + 1);
+    }
+    Ok(config_ast)
+}
+
+/// Where to keep a copy of the settings file from before it was migrated, like
+/// `settings.v1.json` for `settings.json`.
+pub fn backup_path(config_path: &path::Path, version: u8) -> path::PathBuf {
+    let stem = config_path
+        .file_stem()
+        .map_or("settings".into(), |stem| stem.to_string_lossy());
+    config_path.with_file_name(format!("{}.v{}.json", stem, version))
+}
+
+// For versions with a config type of their own, which upgrades to the next version.
+fn upgrade_with<C>(config_ast: Value) -> Result<Value, serde_json::Error>
+where
+    C: DeserializeOwned + UpgradableConfig,
+    C::UpgradedConfig: Serialize,
+{
+    let config: C = serde_json::from_value(config_ast)?;
+    serde_json::to_value(config.upgrade())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_chains_every_version_up_to_the_latest() {
+        for version in 1..=LATEST_VERSION {
+            let steps = chain(version).unwrap();
+            assert_eq!(steps.len(), (LATEST_VERSION - version) as usize);
+            assert!(steps
+                .iter()
+                .zip(version..)
+                .all(|(step, from)| step.from == from));
+        }
+
+        assert!(chain(0).is_none());
+        assert!(chain(LATEST_VERSION + 1).is_none());
+    }
+
+    #[test]
+    fn it_names_the_backup_after_the_old_version() {
+        assert_eq!(
+            backup_path(path::Path::new("flux/settings.json"), 1),
+            path::Path::new("flux/settings.v1.json")
+        );
+    }
+}
@@ -0,0 +1,205 @@
+use super::{Config, FluxSettings};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{fs, io, path};
+
+/// A named look, with the colors and the simulation settings to go with them.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Preset {
+    pub name: String,
+    pub flux: FluxSettings,
+}
+
+impl Preset {
+    /// Capture the current look. The calibration belongs to the GPU rather than the look, so it’s
+    /// left out, and so is the overlay, which goes with any look.
+    pub fn new(name: String, flux: &FluxSettings) -> Self {
+        Self {
+            name,
+            flux: FluxSettings {
+                calibration: None,
+                overlay: Default::default(),
+                ..flux.clone()
+            },
+        }
+    }
+
+    /// Switch to this look, keeping the calibration for the current GPU and the overlay.
+    pub fn apply(&self, flux: &mut FluxSettings) {
+        *flux = FluxSettings {
+            calibration: flux.calibration.take(),
+            overlay: std::mem::take(&mut flux.overlay),
+            ..self.flux.clone()
+        };
+    }
+}
+
+/// The presets saved in `presets.json`, next to the settings file.
+#[derive(Debug, Default, PartialEq)]
+pub struct Presets {
+    pub presets: Vec<Preset>,
+    location: Option<path::PathBuf>,
+}
+
+impl Presets {
+    /// Read the presets saved alongside the config. A missing or broken file leaves no presets.
+    pub fn load(config: &Config) -> Self {
+        let location = config.presets_path();
+        let presets = location
+            .as_deref()
+            .and_then(|path| match fs::read_to_string(path) {
+                Ok(presets) => Some(presets),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+                Err(err) => {
+                    log::error!("Failed to read the presets at {}: {}", path.display(), err);
+                    None
+                }
+            })
+            .and_then(|presets| {
+                serde_json::from_str(&presets)
+                    .map_err(|err| log::error!("Failed to decode the presets: {}", err))
+                    .ok()
+            })
+            .unwrap_or_default();
+
+        Self { presets, location }
+    }
+
+    /// Add a preset, replacing any preset of the same name.
+    pub fn insert(&mut self, preset: Preset) {
+        match self
+            .presets
+            .iter_mut()
+            .find(|existing| existing.name == preset.name)
+        {
+            Some(existing) => *existing = preset,
+            None => self.presets.push(preset),
+        }
+    }
+
+    /// Add a preset, numbering its name if it’s already taken.
+    pub fn add(&mut self, mut preset: Preset) {
+        preset.name = self.unused_name(&preset.name);
+        self.presets.push(preset);
+    }
+
+    pub fn find(&self, name: &str) -> Option<&Preset> {
+        self.presets.iter().find(|preset| preset.name == name)
+    }
+
+    pub fn rename(&mut self, index: usize, name: String) -> Result<(), String> {
+        let name = name.trim().to_string();
+        if name.is_empty() {
+            return Err("A preset needs a name".to_string());
+        }
+        if self
+            .presets
+            .iter()
+            .enumerate()
+            .any(|(other, preset)| other != index && preset.name == name)
+        {
+            return Err(format!("There’s already a preset called {}", name));
+        }
+
+        let preset = self
+            .presets
+            .get_mut(index)
+            .ok_or("The preset no longer exists")?;
+        preset.name = name;
+        Ok(())
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index < self.presets.len() {
+            self.presets.remove(index);
+        }
+    }
+
+    /// Copy a preset under a new name, right after the original.
+    pub fn duplicate(&mut self, index: usize) {
+        if let Some(preset) = self.presets.get(index) {
+            let copy = Preset {
+                name: self.unused_name(&format!("{} copy", preset.name)),
+                flux: preset.flux.clone(),
+            };
+            self.presets.insert(index + 1, copy);
+        }
+    }
+
+    // The name itself if it’s free, or else the name with the first free number after it.
+    fn unused_name(&self, name: &str) -> String {
+        (1..)
+            .map(|n| match n {
+                1 => name.to_string(),
+                n => format!("{} {}", name, n),
+            })
+            .find(|candidate| self.find(candidate).is_none())
+            .unwrap()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = self
+            .location
+            .as_ref()
+            .ok_or("No location available to save the presets")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+
+        let presets = serde_json::to_string_pretty(&self.presets).map_err(|err| err.to_string())?;
+        fs::write(path, presets)
+            .map_err(|err| format!("Failed to save the presets to {}: {}", path.display(), err))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::{Calibration, Quality};
+
+    #[test]
+    fn it_replaces_presets_with_the_same_name() {
+        let mut flux = FluxSettings {
+            calibration: Some(Calibration {
+                renderer: "GPU".to_string(),
+                quality: Quality::High,
+            }),
+            ..Default::default()
+        };
+        let mut presets = Presets::default();
+        presets.insert(Preset::new("Calm".to_string(), &flux));
+        assert_eq!(presets.presets[0].flux.calibration, None);
+
+        flux.max_fps = Some(30);
+        presets.insert(Preset::new("Calm".to_string(), &flux));
+        presets.insert(Preset::new("Wild".to_string(), &flux));
+        assert_eq!(presets.presets.len(), 2);
+        assert_eq!(presets.presets[0].flux.max_fps, Some(30));
+    }
+
+    #[test]
+    fn it_renames_and_duplicates_presets() {
+        let mut presets = Presets::default();
+        presets.insert(Preset::new("Calm".to_string(), &FluxSettings::default()));
+        presets.insert(Preset::new("Wild".to_string(), &FluxSettings::default()));
+
+        assert!(presets.rename(0, "Wild".to_string()).is_err());
+        assert!(presets.rename(0, " ".to_string()).is_err());
+        assert_eq!(presets.rename(0, " Still ".to_string()), Ok(()));
+
+        presets.duplicate(0);
+        presets.duplicate(0);
+        let names = presets
+            .presets
+            .iter()
+            .map(|preset| preset.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["Still", "Still copy 2", "Still copy", "Wild"]);
+
+        presets.remove(1);
+        assert_eq!(presets.presets.len(), 3);
+        assert!(presets.find("Still copy 2").is_none());
+    }
+}
@@ -0,0 +1,24 @@
+// Stand-ins for types from other crates that don’t implement JsonSchema. These only describe how
+// the real types are serialized and are never constructed.
+
+use schemars::JsonSchema;
+
+#[allow(dead_code)]
+#[derive(JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+#[allow(dead_code)]
+#[derive(JsonSchema)]
+pub enum ColorPreset {
+    Original,
+    Plasma,
+    Poolside,
+    Freedom,
+}
@@ -0,0 +1,186 @@
+use super::{ColorMode, FluxSettings, Preset, SimulationSettings};
+use crate::gradient;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine as _;
+use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
+
+// Marks the text as a share code, and the version of its format.
+const PREFIX: &str = "flux1-";
+
+// Long enough for a preset with the most gradient stops, with plenty to spare.
+const MAX_LENGTH: usize = 4096;
+
+const MAX_NAME_LENGTH: usize = 64;
+
+// Only the look travels with a share code. The quality and the frame rate depend on the machine.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SharedLook {
+    name: String,
+    #[serde(flatten)]
+    color_mode: ColorMode,
+    #[serde(default)]
+    simulation: SimulationSettings,
+}
+
+impl Preset {
+    /// A line of text that holds this look, to paste into a forum post or a chat.
+    pub fn to_share_code(&self) -> String {
+        let look = SharedLook {
+            name: self.name.clone(),
+            color_mode: self.flux.color_mode.clone(),
+            simulation: self.flux.simulation,
+        };
+        let json = serde_json::to_vec(&look).expect("the look serializes to JSON");
+
+        format!("{}{}", PREFIX, URL_SAFE_NO_PAD.encode(json))
+    }
+
+    /// Read a look from a share code.
+    ///
+    /// Share codes come from strangers, so anything that could reach outside of Flux is refused,
+    /// and the settings are pulled back within the ranges offered in the settings window.
+    pub fn from_share_code(code: &str) -> Result<Self, String> {
+        let code = code.trim();
+        if code.len() > MAX_LENGTH {
+            return Err("The share code is too long".to_string());
+        }
+        let encoded = code
+            .strip_prefix(PREFIX)
+            .ok_or("That doesn’t look like a Flux share code")?;
+        let json = URL_SAFE_NO_PAD
+            .decode(encoded)
+            .map_err(|_| "The share code is incomplete or mistyped".to_string())?;
+        let look: SharedLook = serde_json::from_slice(&json)
+            .map_err(|err| format!("Failed to read the share code: {}", err))?;
+
+        let color_mode = match look.color_mode {
+            // A path from someone else’s computer means nothing here, and could point anywhere.
+            ColorMode::ImageFile { .. } => {
+                return Err("Share codes can’t refer to image files".to_string())
+            }
+            ColorMode::CustomGradient { stops } => ColorMode::CustomGradient {
+                stops: sanitize_stops(stops)?,
+            },
+            color_mode => color_mode,
+        };
+
+        Ok(Preset::new(
+            sanitize_name(&look.name),
+            &FluxSettings {
+                color_mode,
+                simulation: look.simulation.clamped(),
+                ..Default::default()
+            },
+        ))
+    }
+}
+
+fn sanitize_name(name: &str) -> String {
+    let name = name
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_NAME_LENGTH)
+        .collect::<String>();
+    match name.trim() {
+        "" => "Shared look".to_string(),
+        name => name.to_string(),
+    }
+}
+
+fn sanitize_stops(mut stops: Vec<gradient::Stop>) -> Result<Vec<gradient::Stop>, String> {
+    if stops.is_empty() {
+        return Err("The gradient in the share code has no colors".to_string());
+    }
+
+    stops.truncate(gradient::MAX_STOPS);
+    for (position, _) in stops.iter_mut() {
+        let clamped = match position.into_inner() {
+            position if position.is_finite() => position.clamp(0.0, 1.0),
+            _ => 0.0,
+        };
+        *position = OrderedFloat(clamped);
+    }
+
+    Ok(stops)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::Color;
+
+    #[test]
+    fn it_round_trips_a_look() {
+        let preset = Preset::new(
+            "Sunset".to_string(),
+            &FluxSettings {
+                color_mode: ColorMode::CustomGradient {
+                    stops: gradient::default_stops(),
+                },
+                simulation: SimulationSettings {
+                    line_width: Some(OrderedFloat(1.5)),
+                    ..Default::default()
+                },
+                // Left behind, since it depends on the machine
+                max_fps: Some(30),
+                ..Default::default()
+            },
+        );
+
+        let code = preset.to_share_code();
+        assert!(code.starts_with(PREFIX));
+
+        let imported = Preset::from_share_code(&format!("  {}\n", code)).unwrap();
+        assert_eq!(imported.name, "Sunset");
+        assert_eq!(imported.flux.color_mode, preset.flux.color_mode);
+        assert_eq!(imported.flux.simulation, preset.flux.simulation);
+        assert_eq!(imported.flux.max_fps, None);
+    }
+
+    #[test]
+    fn it_sandboxes_imported_looks() {
+        let encode = |json: serde_json::Value| {
+            format!("{}{}", PREFIX, URL_SAFE_NO_PAD.encode(json.to_string()))
+        };
+
+        let image_file = encode(serde_json::json!({
+            "name": "Sneaky",
+            "colorMode": "imageFile",
+            "imagePath": "C:\\Windows\\System32\\config\\SAM",
+        }));
+        assert!(Preset::from_share_code(&image_file).is_err());
+
+        let extreme = encode(serde_json::json!({
+            "name": "\u{7}",
+            "colorMode": "customGradient",
+            "stops": [[-4.0, "#000000"], [9.0, "#ffffff"]],
+            "simulation": { "lineDensity": 100.0, "lineLength": 0.0 },
+        }));
+        let preset = Preset::from_share_code(&extreme).unwrap();
+        assert_eq!(preset.name, "Shared look");
+        assert_eq!(
+            preset.flux.color_mode,
+            ColorMode::CustomGradient {
+                stops: vec![
+                    (OrderedFloat(0.0), Color { r: 0, g: 0, b: 0 }),
+                    (
+                        OrderedFloat(1.0),
+                        Color {
+                            r: 255,
+                            g: 255,
+                            b: 255
+                        }
+                    ),
+                ]
+            }
+        );
+        assert_eq!(preset.flux.simulation.line_density, Some(OrderedFloat(1.5)));
+        assert_eq!(preset.flux.simulation.line_length, Some(OrderedFloat(0.25)));
+
+        assert!(Preset::from_share_code("flux1-not base64!").is_err());
+        assert!(Preset::from_share_code("hello").is_err());
+    }
+}
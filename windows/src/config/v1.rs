@@ -32,10 +32,17 @@ impl config::UpgradableConfig for Config {
         };
 
         config::Config {
-            version: config::LATEST_VERSION,
+            version: 2,
             log_level: self.log_level,
-            flux: config::FluxSettings { color_mode },
+            logging: Default::default(),
+            flux: config::FluxSettings {
+                color_mode,
+                ..Default::default()
+            },
             platform: Default::default(),
+            playlist: Default::default(),
+            ping: Default::default(),
+            ambient_audio: Default::default(),
             location: None,
         }
     }
@@ -0,0 +1,487 @@
+use super::{ColorMode, Config, Presets, MAX_FONT_SIZE};
+
+use serde_json::Value;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+/// A setting that couldn’t be read, or that was out of range.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Issue {
+    /// The dotted key, like `platform.windows.battery.maxFps`
+    pub key: String,
+    pub reason: String,
+    /// Whether the setting was put back to the default, rather than just reported
+    pub reset: bool,
+    /// The line and column of the key in the settings file, if it can be found
+    pub position: Option<(usize, usize)>,
+}
+
+impl Issue {
+    fn new(key: &str, reason: impl Into<String>, reset: bool) -> Self {
+        Self {
+            key: key.to_string(),
+            reason: reason.into(),
+            reset,
+            position: None,
+        }
+    }
+
+    /// Find the key in the settings file. This goes by the first match for each part of the key
+    /// in turn, which is close enough for a file written by Flux or by hand.
+    pub fn locate(mut self, config_string: &str) -> Self {
+        let mut offset = 0;
+        for segment in self.key.split('.') {
+            // Indices into lists aren’t written out.
+            if segment.parse::<usize>().is_ok() {
+                continue;
+            }
+            let needle = format!("\"{}\"", segment);
+            match config_string[offset..].find(&needle) {
+                Some(found) => offset += found + needle.len(),
+                None => return self,
+            }
+        }
+
+        let key_start = offset - self.key.rsplit('.').next().map_or(0, |last| last.len() + 2);
+        let line_start = config_string[..key_start]
+            .rfind('\n')
+            .map_or(0, |at| at + 1);
+        self.position = Some((
+            config_string[..key_start].matches('\n').count() + 1,
+            config_string[line_start..key_start].chars().count() + 1,
+        ));
+        self
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The setting {}", self.key)?;
+        if let Some((line, column)) = self.position {
+            write!(f, " at line {}, column {}", line, column)?;
+        }
+        write!(f, " {}", self.reason)?;
+        if self.reset {
+            write!(f, ", so using the default instead")?;
+        }
+        Ok(())
+    }
+}
+
+/// Decode the settings, one setting at a time if need be, so that a single bad value only costs
+/// that value instead of the whole file.
+///
+/// Each object is tried as a whole first, and only taken apart if it doesn’t decode, which keeps
+/// settings that only make sense together, like a color mode and its options, together.
+pub fn decode_leniently(config_ast: Value) -> Result<(Config, Vec<Issue>), serde_json::Error> {
+    let err = match serde_json::from_value::<Config>(config_ast.clone()) {
+        Ok(config) => return Ok((config, Vec::new())),
+        Err(err) => err,
+    };
+    let Value::Object(object) = config_ast else {
+        return Err(err);
+    };
+
+    let mut accepted = serde_json::to_value(Config::default())?;
+    let mut issues = Vec::new();
+    let mut path = Vec::new();
+    for (key, value) in object {
+        path.push(key);
+        merge(&mut accepted, &mut path, value, &mut issues);
+        path.pop();
+    }
+
+    let config = serde_json::from_value(accepted)?;
+    Ok((config, issues))
+}
+
+fn merge(accepted: &mut Value, path: &mut Vec<String>, value: Value, issues: &mut Vec<Issue>) {
+    let err = match try_set(accepted, path, value.clone()) {
+        Ok(()) => return,
+        Err(err) => err,
+    };
+
+    match value {
+        Value::Object(object) if !object.is_empty() => {
+            for (key, value) in object {
+                path.push(key);
+                merge(accepted, path, value, issues);
+                path.pop();
+            }
+        }
+        _ => issues.push(Issue::new(
+            &path.join("."),
+            format!("is invalid: {}", err),
+            true,
+        )),
+    }
+}
+
+// Set the value, as long as the settings still decode with it.
+fn try_set(accepted: &mut Value, path: &[String], value: Value) -> Result<(), serde_json::Error> {
+    let mut candidate = accepted.clone();
+    let mut node = &mut candidate;
+    for segment in path {
+        if !node.is_object() {
+            *node = Value::Object(Default::default());
+        }
+        node = node
+            .as_object_mut()
+            .expect("the node was just made an object")
+            .entry(segment.as_str())
+            .or_insert(Value::Null);
+    }
+    *node = value;
+
+    serde_json::from_value::<Config>(candidate.clone())?;
+    *accepted = candidate;
+    Ok(())
+}
+
+impl Config {
+    /// Check the settings that decode fine but make no sense, like a frame rate limit of 0.
+    /// Values out of range are put back to the default. Paths and presets that can’t be found are
+    /// only reported, since they might turn up later, like a USB stick that isn’t plugged in.
+    pub fn validate(&mut self) -> Vec<Issue> {
+        let mut issues = Vec::new();
+        let defaults = Config::default();
+
+        // Presets are saved next to the settings file, so there are none to check against
+        // without one.
+        if self.presets_path().is_some() {
+            let presets = Presets::load(self);
+            for (index, scene) in self.playlist.scenes.iter().enumerate() {
+                if let Some(name) = &scene.preset {
+                    if presets.find(name).is_none() {
+                        issues.push(Issue::new(
+                            &format!("playlist.scenes.{}.preset", index),
+                            format!("names a preset that doesn’t exist: {}", name),
+                            false,
+                        ));
+                    }
+                }
+            }
+        }
+
+        if let ColorMode::ImageFile {
+            image_path: Some(image_path),
+            ..
+        } = &self.flux.color_mode
+        {
+            if !image_path.is_file() {
+                issues.push(Issue::new(
+                    "flux.imagePath",
+                    format!(
+                        "points to a file that doesn’t exist: {}",
+                        image_path.display()
+                    ),
+                    false,
+                ));
+            }
+        }
+
+        if let Some(path) = &self.ambient_audio.path {
+            if !path.is_file() {
+                issues.push(Issue::new(
+                    "ambientAudio.path",
+                    format!("points to a file that doesn’t exist: {}", path.display()),
+                    false,
+                ));
+            }
+        }
+        check_range(
+            &mut issues,
+            "ambientAudio.volume",
+            &mut self.ambient_audio.volume,
+            defaults.ambient_audio.volume,
+            0.0..=1.0,
+        );
+
+        check_fps(&mut issues, "flux.maxFps", &mut self.flux.max_fps);
+        let overlay = &mut self.flux.overlay;
+        check_range(
+            &mut issues,
+            "flux.overlay.opacity",
+            &mut overlay.opacity.0,
+            defaults.flux.overlay.opacity.0,
+            0.0..=1.0,
+        );
+        if !(1..=MAX_FONT_SIZE).contains(&overlay.font_size) {
+            issues.push(Issue::new(
+                "flux.overlay.fontSize",
+                format!(
+                    "is {}, outside of 1 to {}",
+                    overlay.font_size, MAX_FONT_SIZE
+                ),
+                true,
+            ));
+            overlay.font_size = defaults.flux.overlay.font_size;
+        }
+        let windows = &mut self.platform.windows;
+        let windows_defaults = &defaults.platform.windows;
+        check_range(
+            &mut issues,
+            "platform.windows.input.controllerStickThreshold",
+            &mut windows.input.controller_stick_threshold.0,
+            windows_defaults.input.controller_stick_threshold.0,
+            0.0..=1.0,
+        );
+        check_fps(
+            &mut issues,
+            "platform.windows.battery.maxFps",
+            &mut windows.battery.max_fps,
+        );
+        check_fps(
+            &mut issues,
+            "platform.windows.liveWallpaper.activeMaxFps",
+            &mut windows.live_wallpaper.active_max_fps,
+        );
+
+        if windows
+            .battery
+            .pause_below_percent
+            .is_some_and(|percent| percent > 100)
+        {
+            issues.push(Issue::new(
+                "platform.windows.battery.pauseBelowPercent",
+                "is more than 100%",
+                true,
+            ));
+            windows.battery.pause_below_percent = windows_defaults.battery.pause_below_percent;
+        }
+
+        if windows
+            .max_frame_latency
+            .is_some_and(|latency| !(1..=16).contains(&latency))
+        {
+            issues.push(Issue::new(
+                "platform.windows.maxFrameLatency",
+                "is outside of 1 to 16",
+                true,
+            ));
+            windows.max_frame_latency = windows_defaults.max_frame_latency;
+        }
+
+        // A schedule of 0 minutes would flicker the displays off and on.
+        for (key, mins) in [
+            ("afterMins", &mut windows.blanking.after_mins),
+            ("wakeAfterMins", &mut windows.blanking.wake_after_mins),
+        ] {
+            if *mins == Some(0) {
+                issues.push(Issue::new(
+                    &format!("platform.windows.blanking.{}", key),
+                    "must be above 0",
+                    true,
+                ));
+                *mins = None;
+            }
+        }
+
+        for (index, monitor_image) in windows.monitor_images.iter().enumerate() {
+            if !monitor_image.image_path.is_file() {
+                issues.push(Issue::new(
+                    &format!("platform.windows.monitorImages.{}.imagePath", index),
+                    format!(
+                        "points to a file that doesn’t exist: {}",
+                        monitor_image.image_path.display()
+                    ),
+                    false,
+                ));
+            }
+        }
+
+        let live_wallpaper = &mut windows.live_wallpaper;
+        let live_wallpaper_defaults = &windows_defaults.live_wallpaper;
+        for (key, value, default, range) in [
+            (
+                "opacity",
+                &mut live_wallpaper.opacity,
+                live_wallpaper_defaults.opacity,
+                0.0..=1.0,
+            ),
+            (
+                "iconBrightness",
+                &mut live_wallpaper.icon_brightness,
+                live_wallpaper_defaults.icon_brightness,
+                0.0..=1.0,
+            ),
+            (
+                "iconMotion",
+                &mut live_wallpaper.icon_motion,
+                live_wallpaper_defaults.icon_motion,
+                0.0..=1.0,
+            ),
+            (
+                "edgeWidth",
+                &mut live_wallpaper.edge_width,
+                live_wallpaper_defaults.edge_width,
+                0.0..=0.5,
+            ),
+            (
+                "edgeCenterBrightness",
+                &mut live_wallpaper.edge_center_brightness,
+                live_wallpaper_defaults.edge_center_brightness,
+                0.0..=1.0,
+            ),
+        ] {
+            check_range(
+                &mut issues,
+                &format!("platform.windows.liveWallpaper.{}", key),
+                value,
+                default,
+                range,
+            );
+        }
+
+        // A region has no default to go back to, so one with a value out of range is dropped.
+        let mut index = 0;
+        live_wallpaper.masked_regions.retain(|region| {
+            let key = format!("platform.windows.liveWallpaper.maskedRegions.{}", index);
+            index += 1;
+            let values = [
+                ("x", region.x),
+                ("y", region.y),
+                ("width", region.width),
+                ("height", region.height),
+                ("brightness", region.brightness),
+            ];
+            let Some((name, value)) = values
+                .into_iter()
+                .find(|(_, value)| !(0.0..=1.0).contains(value))
+            else {
+                return true;
+            };
+            issues.push(Issue::new(
+                &format!("{}.{}", key, name),
+                format!("is {}, outside of 0 to 1", value),
+                true,
+            ));
+            false
+        });
+
+        for (index, mask_image) in live_wallpaper.mask_images.iter().enumerate() {
+            if !mask_image.image_path.is_file() {
+                issues.push(Issue::new(
+                    &format!(
+                        "platform.windows.liveWallpaper.maskImages.{}.imagePath",
+                        index
+                    ),
+                    format!(
+                        "points to a file that doesn’t exist: {}",
+                        mask_image.image_path.display()
+                    ),
+                    false,
+                ));
+            }
+        }
+
+        issues
+    }
+}
+
+// A limit of 0 would never draw a frame.
+fn check_fps(issues: &mut Vec<Issue>, key: &str, max_fps: &mut Option<u32>) {
+    if *max_fps == Some(0) {
+        issues.push(Issue::new(key, "must be above 0", true));
+        *max_fps = None;
+    }
+}
+
+fn check_range(
+    issues: &mut Vec<Issue>,
+    key: &str,
+    value: &mut f32,
+    default: f32,
+    range: RangeInclusive<f32>,
+) {
+    if !range.contains(value) {
+        issues.push(Issue::new(
+            key,
+            format!(
+                "is {}, outside of {} to {}",
+                value,
+                range.start(),
+                range.end()
+            ),
+            true,
+        ));
+        *value = default;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::{MaskedRegion, MonitorRef, Quality};
+
+    #[test]
+    fn it_keeps_the_settings_around_a_bad_value() {
+        let config_string = indoc::indoc! {r#"
+            {
+              "version": 2,
+              "flux": {
+                "quality": "high",
+                "maxFps": "sixty"
+              },
+              "platform": {
+                "windows": {
+                  "lockOnExit": true
+                }
+              }
+            }
+        "#};
+        let config_ast = serde_json::from_str(&config_string).unwrap();
+
+        let (config, issues) = decode_leniently(config_ast).unwrap();
+        assert_eq!(config.flux.quality, Quality::High);
+        assert_eq!(config.flux.max_fps, None);
+        assert!(config.platform.windows.lock_on_exit);
+
+        let issues: Vec<Issue> = issues
+            .into_iter()
+            .map(|issue| issue.locate(&config_string))
+            .collect();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].key, "flux.maxFps");
+        assert_eq!(issues[0].position, Some((5, 5)));
+    }
+
+    #[test]
+    fn it_resets_values_out_of_range() {
+        let mut config = Config::default();
+        config.flux.max_fps = Some(0);
+        config.flux.overlay.font_size = u32::MAX;
+        config.platform.windows.live_wallpaper.opacity = 1.5;
+        config.platform.windows.live_wallpaper.masked_regions = [0.5, 2.0]
+            .map(|width| MaskedRegion {
+                monitor: MonitorRef::Index(0),
+                x: 0.25,
+                y: 0.25,
+                width,
+                height: 0.5,
+                brightness: 0.0,
+            })
+            .to_vec();
+        config.platform.windows.battery.max_fps = Some(30);
+
+        let issues = config.validate();
+        let keys: Vec<&str> = issues.iter().map(|issue| issue.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            vec![
+                "flux.maxFps",
+                "flux.overlay.fontSize",
+                "platform.windows.liveWallpaper.opacity",
+                "platform.windows.liveWallpaper.maskedRegions.1.width"
+            ]
+        );
+        assert_eq!(
+            config.platform.windows.live_wallpaper.masked_regions.len(),
+            1
+        );
+        assert_eq!(config.flux.max_fps, None);
+        assert_eq!(config.flux.overlay.font_size, 42);
+        assert_eq!(config.platform.windows.live_wallpaper.opacity, 1.0);
+        assert_eq!(config.platform.windows.battery.max_fps, Some(30));
+    }
+}
@@ -0,0 +1,339 @@
+pub mod protocol;
+
+use crate::config::{Config, FluxSettings};
+use protocol::{FromInstance, ToInstance};
+
+use std::io::{self, BufReader};
+use std::path::Path;
+use std::process;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// How often to check on the instances
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+// Restart an instance that hasn’t been heard from in this long. Starting up can take a few
+// seconds on a slow machine, so leave plenty of room for that.
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// How long to let the instances close on their own when the screensaver exits
+const EXIT_TIMEOUT: Duration = Duration::from_secs(1);
+
+// Stop restarting an instance after it has crashed this many times.
+const RESTART_BUDGET: u32 = 3;
+
+// Which surface a message came from, and which run of the instance on that surface sent it
+type Envelope = (usize, u32, FromInstance);
+
+/// Run the screensaver with each surface in a child process of its own.
+///
+/// A driver crash then only takes down the instance on the affected monitor, and the coordinator
+/// restarts it. When the user dismisses the screensaver on one monitor, the coordinator tells the
+/// instances on the others to exit.
+///
+/// The two sides talk over the instance’s standard input and output, using the messages in
+/// `protocol`. Changes to the Flux settings in the settings file are passed on to every instance
+/// together, so that the monitors keep looking the same.
+pub fn run(config: &Config, surface_count: usize) -> Result<(), String> {
+    let exe = std::env::current_exe()
+        .map_err(|err| format!("Failed to find the screensaver executable: {}", err))?;
+    let (sender, messages) = mpsc::channel();
+
+    // The settings as last passed on to the instances
+    let mut config = config.clone();
+    let mut children = Vec::with_capacity(surface_count);
+    for surface in 0..surface_count {
+        children.push(Child::spawn(
+            &exe,
+            &config.flux,
+            surface,
+            0,
+            sender.clone(),
+        )?);
+    }
+    let mut last_heartbeat = Instant::now();
+    let mut settings_watch = crate::config::Watch::new(&config);
+
+    loop {
+        while let Ok((surface, run, message)) = messages.try_recv() {
+            // Messages from a run that has since crashed are stale.
+            let Some(child) = children
+                .iter_mut()
+                .find(|child| child.surface == surface && child.restarts == run)
+            else {
+                continue;
+            };
+            child.last_heard = Instant::now();
+
+            match message {
+                FromInstance::Ready { version } if version != protocol::VERSION => log::warn!(
+                    "The instance on surface {} speaks version {} of the protocol, not {}",
+                    surface,
+                    version,
+                    protocol::VERSION
+                ),
+                FromInstance::Ready { .. } => {
+                    log::debug!("The instance on surface {} is ready", surface)
+                }
+                FromInstance::Dismissed => {
+                    exit_all(&mut children);
+                    return Ok(());
+                }
+                FromInstance::Heartbeat | FromInstance::Unknown => (),
+            }
+        }
+
+        let now = Instant::now();
+        if now.duration_since(last_heartbeat) >= HEARTBEAT_INTERVAL {
+            last_heartbeat = now;
+            for child in children.iter_mut() {
+                child.send(&ToInstance::Heartbeat);
+            }
+        }
+
+        if let Some(flux) = settings_watch.poll(&config, now, crate::reapply_launch_overrides) {
+            log::info!("The settings have changed, so passing them on to the instances");
+            for child in children.iter_mut() {
+                child.send(&ToInstance::Settings { flux: flux.clone() });
+            }
+            config.flux = flux;
+        }
+
+        let mut crashed = Vec::new();
+        for (index, child) in children.iter_mut().enumerate() {
+            match child.process.try_wait() {
+                Ok(None) if now.duration_since(child.last_heard) > HEARTBEAT_TIMEOUT => {
+                    log::error!(
+                        "The instance on surface {} has stopped responding",
+                        child.surface
+                    );
+                    child.kill();
+                    crashed.push(index);
+                }
+                Ok(None) => (),
+                // The instance closed without being dismissed, like when its window is closed.
+                Ok(Some(status)) if status.success() => {
+                    exit_all(&mut children);
+                    return Ok(());
+                }
+                Ok(Some(status)) => {
+                    log::error!(
+                        "The instance on surface {} exited unexpectedly: {}",
+                        child.surface,
+                        status
+                    );
+                    crashed.push(index);
+                }
+                Err(err) => {
+                    log::error!(
+                        "Failed to check on the instance on surface {}: {}",
+                        child.surface,
+                        err
+                    );
+                    // It might still be running, so make sure its window is gone before another
+                    // one takes its place.
+                    child.kill();
+                    crashed.push(index);
+                }
+            }
+        }
+
+        for index in crashed.into_iter().rev() {
+            let (surface, restarts) = (children[index].surface, children[index].restarts);
+            if restarts >= RESTART_BUDGET {
+                log::error!(
+                    "Giving up on surface {} after {} restarts",
+                    surface,
+                    restarts
+                );
+                children.remove(index);
+                continue;
+            }
+
+            match Child::spawn(&exe, &config.flux, surface, restarts + 1, sender.clone()) {
+                Ok(child) => children[index] = child,
+                Err(err) => {
+                    log::error!("{}", err);
+                    children.remove(index);
+                }
+            }
+        }
+
+        if children.is_empty() {
+            return Err("Every instance of the screensaver has crashed".to_string());
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+// Ask the instances to exit, and give them a moment to do so. Any that are still around when
+// they’re dropped get killed.
+fn exit_all(children: &mut [Child]) {
+    for child in children.iter_mut() {
+        child.send(&ToInstance::Exit);
+    }
+
+    let deadline = Instant::now() + EXIT_TIMEOUT;
+    while Instant::now() < deadline
+        && children
+            .iter_mut()
+            .any(|child| matches!(child.process.try_wait(), Ok(None)))
+    {
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+struct Child {
+    surface: usize,
+    process: process::Child,
+    stdin: process::ChildStdin,
+    // How many times the instance on this surface has been restarted
+    restarts: u32,
+    last_heard: Instant,
+}
+
+impl Child {
+    fn spawn(
+        exe: &Path,
+        flux_settings: &FluxSettings,
+        surface: usize,
+        restarts: u32,
+        sender: mpsc::Sender<Envelope>,
+    ) -> Result<Self, String> {
+        let mut process = process::Command::new(exe)
+            .arg("--instance")
+            .arg(surface.to_string())
+            .stdin(process::Stdio::piped())
+            .stdout(process::Stdio::piped())
+            .spawn()
+            .map_err(|err| {
+                format!(
+                    "Failed to start the instance on surface {}: {}",
+                    surface, err
+                )
+            })?;
+        let (Some(mut stdin), Some(stdout)) = (process.stdin.take(), process.stdout.take()) else {
+            let _ = process.kill();
+            return Err(format!(
+                "Failed to connect to the instance on surface {}",
+                surface
+            ));
+        };
+
+        let init = ToInstance::Init {
+            version: protocol::VERSION,
+            flux: flux_settings.clone(),
+        };
+        if let Err(err) = protocol::send(&mut stdin, &init) {
+            let _ = process.kill();
+            return Err(err);
+        }
+
+        thread::spawn(move || {
+            let mut stdout = BufReader::new(stdout);
+            while let Ok(Some(message)) = protocol::receive(&mut stdout) {
+                if sender.send((surface, restarts, message)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self {
+            surface,
+            process,
+            stdin,
+            restarts,
+            last_heard: Instant::now(),
+        })
+    }
+
+    // Stop the instance, if it hasn’t stopped already.
+    fn kill(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+
+    // The instance might have crashed, in which case there’s no one to tell.
+    fn send(&mut self, message: &ToInstance) {
+        if let Err(err) = protocol::send(&mut self.stdin, message) {
+            log::debug!("{} to surface {}", err, self.surface);
+        }
+    }
+}
+
+impl Drop for Child {
+    fn drop(&mut self) {
+        self.kill();
+    }
+}
+
+/// The instance’s side of the connection to the coordinator.
+pub struct Connection {
+    messages: mpsc::Receiver<ToInstance>,
+    stdout: io::Stdout,
+}
+
+impl Connection {
+    /// Wait for the coordinator to send the settings to run with.
+    ///
+    /// The instance exits as soon as the coordinator goes away, so that it can’t outlive it.
+    pub fn open() -> Result<(Self, FluxSettings), String> {
+        // The lock on standard input can’t move to another thread, so this buffers it instead.
+        let mut stdin = BufReader::new(io::stdin());
+        let flux_settings = match protocol::receive(&mut stdin)? {
+            Some(ToInstance::Init { version, flux }) if version == protocol::VERSION => flux,
+            Some(ToInstance::Init { version, .. }) => {
+                return Err(format!(
+                    "The coordinator speaks version {} of the protocol, but this instance only \
+                     knows version {}",
+                    version,
+                    protocol::VERSION
+                ))
+            }
+            Some(message) => {
+                return Err(format!(
+                    "Expected the coordinator to start with an init message, but got {:?}",
+                    message
+                ))
+            }
+            None => return Err("The coordinator exited before starting the instance".to_string()),
+        };
+
+        let (sender, messages) = mpsc::channel();
+        thread::spawn(move || {
+            while let Ok(Some(message)) = protocol::receive(&mut stdin) {
+                if sender.send(message).is_err() {
+                    return;
+                }
+            }
+
+            log::info!("The coordinator has exited, so exiting too");
+            process::exit(0);
+        });
+
+        let mut connection = Self {
+            messages,
+            stdout: io::stdout(),
+        };
+        connection.send(&FromInstance::Ready {
+            version: protocol::VERSION,
+        });
+
+        Ok((connection, flux_settings))
+    }
+
+    pub fn send(&mut self, message: &FromInstance) {
+        if let Err(err) = protocol::send(&mut self.stdout.lock(), message) {
+            log::warn!("{} to the coordinator", err);
+        }
+    }
+
+    /// The next message from the coordinator, if there is one.
+    pub fn try_recv(&self) -> Option<ToInstance> {
+        self.messages.try_recv().ok()
+    }
+}
@@ -0,0 +1,110 @@
+use crate::config::FluxSettings;
+
+use std::io::{BufRead, Write};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// The version of the protocol. Bump it whenever a message changes in a way that the other side
+/// wouldn’t understand.
+///
+/// Adding a message doesn’t need a new version: unknown messages are read as `Unknown` and
+/// ignored.
+pub const VERSION: u32 = 1;
+
+/// Messages from the coordinator to an instance
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ToInstance {
+    /// Always the first message. The instance runs with the coordinator’s Flux settings, so that
+    /// every monitor looks the same, even if the settings file changes while they start up.
+    Init { version: u32, flux: FluxSettings },
+    /// Restart the animation with new settings, like after they were changed in the settings file
+    Settings { flux: FluxSettings },
+    /// Close the instance, because the screensaver is exiting
+    Exit,
+    /// Check that the instance is still responsive
+    Heartbeat,
+    #[serde(other)]
+    Unknown,
+}
+
+/// Messages from an instance to the coordinator
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum FromInstance {
+    /// The instance has accepted the init message
+    Ready { version: u32 },
+    /// The answer to a heartbeat
+    Heartbeat,
+    /// The user dismissed the screensaver from this instance’s monitor
+    Dismissed,
+    #[serde(other)]
+    Unknown,
+}
+
+/// Send a message as a single line of JSON.
+pub fn send<T: Serialize>(writer: &mut impl Write, message: &T) -> Result<(), String> {
+    let mut line = serde_json::to_string(message).map_err(|err| err.to_string())?;
+    line.push('\n');
+    writer
+        .write_all(line.as_bytes())
+        .and_then(|_| writer.flush())
+        .map_err(|err| format!("Failed to send a message: {}", err))
+}
+
+/// Wait for the next message, or `None` once the other side has closed the pipe.
+///
+/// Instances share their standard output with the terminal logger, so anything that isn’t a
+/// message is skipped over.
+pub fn receive<T: DeserializeOwned>(reader: &mut impl BufRead) -> Result<Option<T>, String> {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        let read = reader
+            .read_line(&mut line)
+            .map_err(|err| format!("Failed to receive a message: {}", err))?;
+        if read == 0 {
+            return Ok(None);
+        }
+
+        match serde_json::from_str(line.trim()) {
+            Ok(message) => return Ok(Some(message)),
+            Err(err) => log::debug!("Skipping a line that isn’t a message: {}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_sends_and_receives_messages() {
+        let mut pipe = Vec::new();
+        let init = ToInstance::Init {
+            version: VERSION,
+            flux: FluxSettings::default(),
+        };
+        send(&mut pipe, &init).unwrap();
+        send(&mut pipe, &ToInstance::Heartbeat).unwrap();
+
+        let mut reader = pipe.as_slice();
+        assert_eq!(receive(&mut reader), Ok(Some(init)));
+        assert_eq!(receive(&mut reader), Ok(Some(ToInstance::Heartbeat)));
+        assert_eq!(receive::<ToInstance>(&mut reader), Ok(None));
+    }
+
+    #[test]
+    fn it_skips_log_output() {
+        let mut reader =
+            "12:00:00 [WARN] Failed to find the wallpaper\n{\"type\":\"dismissed\"}\n".as_bytes();
+        assert_eq!(receive(&mut reader), Ok(Some(FromInstance::Dismissed)));
+    }
+
+    #[test]
+    fn it_ignores_messages_from_newer_versions() {
+        let mut reader = "{\"type\":\"somethingNew\",\"value\":1}\n".as_bytes();
+        assert_eq!(receive(&mut reader), Ok(Some(FromInstance::Unknown)));
+    }
+}
@@ -0,0 +1,211 @@
+use crate::config::Config;
+use crate::platform::windows::{self, idle, named_event::NamedEvent};
+
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// How often to check how long the computer has been idle
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// Set on a screensaver started ahead of time, to the ID of the daemon holding it ready
+const STANDBY_ENV_VAR: &str = "FLUX_STANDBY";
+
+// How long to leave it before starting another screensaver on standby, after the last one exited
+// without being called on, so that one that fails to set up isn’t retried every second
+const STANDBY_RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The exit code of a screensaver on standby that quits to be started again right away, since the
+/// monitors changed after it was set up for them.
+pub const RESPAWN_EXIT_CODE: i32 = 3;
+
+/// A screensaver that the daemon started ahead of time, to set up everything hidden and then wait
+/// to be shown.
+#[cfg(feature = "sdl")]
+pub struct Standby {
+    event: NamedEvent,
+    daemon_pid: u32,
+}
+
+#[cfg(feature = "sdl")]
+impl Standby {
+    /// `None` unless the daemon started this screensaver on standby.
+    pub fn from_env() -> Option<Result<Self, String>> {
+        let daemon_pid = std::env::var(STANDBY_ENV_VAR).ok()?;
+        Some(
+            daemon_pid
+                .parse()
+                .map_err(|_| format!("The daemon ID isn’t a number: {}", daemon_pid))
+                .and_then(|daemon_pid| {
+                    Ok(Self {
+                        event: NamedEvent::open(&standby_event_name(daemon_pid))?,
+                        daemon_pid,
+                    })
+                }),
+        )
+    }
+
+    /// Wait at most `timeout` for the daemon to call on the screensaver. Returns whether it did,
+    /// or an error once the daemon is gone.
+    pub fn poll(&self, timeout: Duration) -> Result<bool, String> {
+        if self.event.wait(timeout) {
+            return Ok(true);
+        }
+        if !windows::process::is_running(self.daemon_pid) {
+            return Err("The daemon has exited".to_string());
+        }
+        Ok(false)
+    }
+}
+
+fn standby_event_name(daemon_pid: u32) -> String {
+    format!("Local\\FluxStandby-{}", daemon_pid)
+}
+
+/// Start the screensaver whenever the computer has been idle for long enough, without relying on
+/// Windows to do so.
+///
+/// The screensaver runs in a process of its own and exits on input as usual. It isn’t started
+/// again until there’s been some input in between, so that a screensaver that fails to start
+/// isn’t retried every second. Like the one in Windows, it also holds off while a program asks to
+/// keep the display on, like a video player, and while the user is presenting or playing a game
+/// full screen, and while any of the programs listed in the settings are running.
+///
+/// With a warm start, a screensaver is kept on standby the rest of the time, set up and hidden, so
+/// that it only has to show its windows once it’s called on. When the monitors change, it quits
+/// with [`RESPAWN_EXIT_CODE`], and another one is set up right away.
+pub fn run(config: &Config) -> Result<(), String> {
+    let exe = std::env::current_exe()
+        .map_err(|err| format!("Failed to find the screensaver executable: {}", err))?;
+    let daemon_config = config.platform.windows.daemon;
+    let idle_timeout = Duration::from_secs(daemon_config.idle_timeout_secs.into());
+    log::info!(
+        "Starting the screensaver after {} seconds without input",
+        idle_timeout.as_secs()
+    );
+
+    let standby_event = daemon_config
+        .warm_start
+        .then(|| NamedEvent::create(&standby_event_name(process::id())))
+        .transpose()
+        .map_err(|err| log::warn!("{}. Starting the screensaver cold instead.", err))
+        .ok()
+        .flatten();
+    let mut standby: Option<process::Child> = None;
+    let mut last_standby_start: Option<Instant> = None;
+
+    let mut screensaver: Option<process::Child> = None;
+    // Whether there’s been input since the screensaver last ran
+    let mut armed = true;
+    // The listed program that last held off the screensaver, to only log it once
+    let mut blocked_by: Option<String> = None;
+
+    loop {
+        thread::sleep(POLL_INTERVAL);
+
+        if let Some(child) = screensaver.as_mut() {
+            match child.try_wait() {
+                Ok(None) => continue,
+                Ok(Some(status)) => log::debug!("The screensaver exited: {}", status),
+                Err(err) => log::warn!("Failed to check on the screensaver: {}", err),
+            }
+            screensaver = None;
+        }
+
+        if let Some(child) = standby.as_mut() {
+            if let Ok(Some(status)) = child.try_wait() {
+                if status.code() == Some(RESPAWN_EXIT_CODE) {
+                    log::info!("The monitors changed, so starting another screensaver on standby");
+                    last_standby_start = None;
+                }
+                standby = None;
+            }
+        }
+
+        if let Some(standby_event) = &standby_event {
+            if screensaver.is_none()
+                && standby.as_mut().map_or(true, has_exited)
+                && last_standby_start
+                    .map_or(true, |started| started.elapsed() >= STANDBY_RETRY_INTERVAL)
+            {
+                // Take back any call that the last one didn’t live to answer.
+                standby_event.reset();
+                last_standby_start = Some(Instant::now());
+                standby = process::Command::new(&exe)
+                    .arg("/s")
+                    .env(STANDBY_ENV_VAR, process::id().to_string())
+                    .spawn()
+                    .map_err(|err| log::warn!("Failed to start a screensaver on standby: {}", err))
+                    .ok();
+            }
+        }
+
+        let idle_time = match idle::idle_time() {
+            Ok(idle_time) => idle_time,
+            Err(err) => {
+                log::warn!("{}", err);
+                continue;
+            }
+        };
+
+        if idle_time < idle_timeout {
+            armed = true;
+            continue;
+        }
+
+        if !armed || idle::is_display_required() || idle::is_do_not_disturb() {
+            continue;
+        }
+
+        let blocking_process =
+            match windows::process::find_running(&config.platform.windows.blocking_processes) {
+                Ok(blocking_process) => blocking_process,
+                Err(err) => {
+                    log::warn!("{}", err);
+                    None
+                }
+            };
+        if blocking_process != blocked_by {
+            if let Some(name) = &blocking_process {
+                log::info!("Holding off the screensaver while {} is running", name);
+            }
+            blocked_by = blocking_process;
+        }
+        if blocked_by.is_some() {
+            continue;
+        }
+
+        log::info!(
+            "Idle for {} seconds, starting the screensaver",
+            idle_time.as_secs()
+        );
+        armed = false;
+
+        // Call on the screensaver on standby, if it’s still there.
+        if let (Some(standby_event), Some(mut child)) = (&standby_event, standby.take()) {
+            if !has_exited(&mut child) {
+                match standby_event.set() {
+                    Ok(()) => {
+                        screensaver = Some(child);
+                        // Have the next one ready as soon as this one exits.
+                        last_standby_start = None;
+                        continue;
+                    }
+                    Err(err) => {
+                        log::warn!("{}", err);
+                        let _ = child.kill();
+                    }
+                }
+            }
+        }
+
+        match process::Command::new(&exe).arg("/s").spawn() {
+            Ok(child) => screensaver = Some(child),
+            Err(err) => log::error!("Failed to start the screensaver: {}", err),
+        }
+    }
+}
+
+fn has_exited(child: &mut process::Child) -> bool {
+    !matches!(child.try_wait(), Ok(None))
+}
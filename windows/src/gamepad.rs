@@ -0,0 +1,204 @@
+use crate::config::InputConfig;
+
+use std::collections::HashMap;
+
+use sdl2::controller::{Axis, GameController};
+use sdl2::joystick::Joystick;
+use sdl2::{GameControllerSubsystem, JoystickSubsystem};
+
+/// The gamepads, joysticks, and remotes that can wake the screensaver.
+///
+/// SDL only reports input from devices that are open, so each one is opened as it’s plugged in.
+/// SDL also reports the devices that are already plugged in as being added, once the subsystems
+/// start up.
+pub struct Controllers {
+    game_controller: GameControllerSubsystem,
+    joystick: JoystickSubsystem,
+    // Devices with a layout SDL knows, which report named buttons and sticks
+    controllers: Vec<GameController>,
+    // Everything else, like remotes, which only report numbered buttons
+    joysticks: Vec<Joystick>,
+    // Where each axis was when its device was opened, by instance ID and axis index. Some axes
+    // stay wherever they’re left, like a throttle, so they only count once they move from there.
+    rest: HashMap<(u32, u8), i16>,
+    stick_threshold: f32,
+    // Matched against the name and GUID of each device
+    input: InputConfig,
+}
+
+impl Controllers {
+    /// `None` if controllers are turned off in the settings, or SDL can’t read them.
+    pub fn open(sdl: &sdl2::Sdl, config: &InputConfig) -> Option<Self> {
+        if !config.controllers {
+            return None;
+        }
+
+        // Each monitor has a window of its own, but only one of them has the focus.
+        sdl2::hint::set("SDL_JOYSTICK_ALLOW_BACKGROUND_EVENTS", "1");
+        let subsystems = sdl
+            .joystick()
+            .and_then(|joystick| Ok((sdl.game_controller()?, joystick)));
+        match subsystems {
+            Ok((game_controller, joystick)) => Some(Self {
+                game_controller,
+                joystick,
+                controllers: Vec::new(),
+                joysticks: Vec::new(),
+                rest: HashMap::new(),
+                stick_threshold: config.controller_stick_threshold.into_inner(),
+                input: config.clone(),
+            }),
+            Err(err) => {
+                log::warn!("Failed to listen to controllers: {}", err);
+                None
+            }
+        }
+    }
+
+    /// Open a device that was just plugged in, by its device index. Devices that are ignored in
+    /// the settings are left closed, so they never report any input.
+    pub fn add(&mut self, index: u32) {
+        let name = self.joystick.name_for_index(index).ok();
+        let guid = self
+            .joystick
+            .device_guid(index)
+            .ok()
+            .map(|guid| guid.string());
+        if let Some(ignored) = name
+            .iter()
+            .chain(&guid)
+            .find(|id| self.input.is_ignored(id))
+        {
+            log::debug!("Ignoring the controller {}", ignored);
+            return;
+        }
+
+        // A HID device picked from the list in the settings goes by its Raw Input device name,
+        // which only has the USB vendor and product IDs in common with what SDL reports.
+        #[cfg(windows)]
+        if let Some(ignored) = guid.as_deref().and_then(hardware_id).and_then(|id| {
+            self.input.ignored_devices.iter().find(|ignored| {
+                crate::platform::windows::raw_input::hardware_id(ignored).as_ref() == Some(&id)
+            })
+        }) {
+            log::debug!("Ignoring the controller {}", ignored);
+            return;
+        }
+
+        if self.game_controller.is_game_controller(index) {
+            match self.game_controller.open(index) {
+                Ok(controller) => {
+                    log::debug!("Opened the controller {}", controller.name());
+                    let instance_id = controller.instance_id();
+                    for axis in AXES {
+                        self.rest
+                            .insert((instance_id, axis as u8), controller.axis(axis));
+                    }
+                    self.controllers.push(controller);
+                }
+                Err(err) => log::warn!("Failed to open controller {}: {}", index, err),
+            }
+        } else {
+            match self.joystick.open(index) {
+                Ok(joystick) => {
+                    log::debug!("Opened the joystick {}", joystick.name());
+                    let instance_id = joystick.instance_id();
+                    for axis in 0..joystick.num_axes() {
+                        if let Ok(value) = joystick.axis(axis) {
+                            self.rest.insert((instance_id, axis as u8), value);
+                        }
+                    }
+                    self.joysticks.push(joystick);
+                }
+                Err(err) => log::warn!("Failed to open joystick {}: {}", index, err),
+            }
+        }
+    }
+
+    /// Close a device that was unplugged, by the instance ID in its events.
+    pub fn remove(&mut self, instance_id: u32) {
+        self.controllers
+            .retain(|controller| controller.instance_id() != instance_id);
+        self.joysticks
+            .retain(|joystick| joystick.instance_id() != instance_id);
+        self.rest.retain(|(id, _), _| *id != instance_id);
+    }
+
+    /// Whether the device is open as a plain joystick, rather than a controller. SDL reports the
+    /// raw axes of controllers too, where the triggers rest at one end.
+    pub fn is_joystick(&self, instance_id: u32) -> bool {
+        self.joysticks
+            .iter()
+            .any(|joystick| joystick.instance_id() == instance_id)
+    }
+
+    /// Whether a stick or trigger has moved far enough from where it was when its device was
+    /// opened to wake the screensaver.
+    pub fn is_past_threshold(&self, instance_id: u32, axis: u8, value: i16) -> bool {
+        let rest = self.rest.get(&(instance_id, axis)).copied().unwrap_or(0);
+        is_past_threshold(rest, value, self.stick_threshold)
+    }
+}
+
+// The sticks and triggers of controllers SDL knows the layout of
+const AXES: [Axis; 6] = [
+    Axis::LeftX,
+    Axis::LeftY,
+    Axis::RightX,
+    Axis::RightY,
+    Axis::TriggerLeft,
+    Axis::TriggerRight,
+];
+
+fn is_past_threshold(rest: i16, value: i16, threshold: f32) -> bool {
+    ((value as f32 - rest as f32) / i16::MAX as f32).abs() > threshold
+}
+
+// The USB vendor and product IDs that SDL packs into the GUID of most devices, in hex. The words
+// in between are zero when they’re there.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn hardware_id(guid: &str) -> Option<(String, String)> {
+    let bytes = (0..16)
+        .map(|index| u8::from_str_radix(guid.get(index * 2..index * 2 + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    let word = |index: usize| u16::from_le_bytes([bytes[index], bytes[index + 1]]);
+    let (vendor, product) = (word(4), word(8));
+
+    (vendor != 0 && word(6) == 0 && word(10) == 0)
+        .then(|| (format!("{:04X}", vendor), format!("{:04X}", product)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_ignores_sticks_near_the_center() {
+        assert!(!is_past_threshold(0, 0, 0.5));
+        assert!(!is_past_threshold(0, 8_000, 0.5));
+        assert!(!is_past_threshold(0, -8_000, 0.5));
+        assert!(is_past_threshold(0, 20_000, 0.5));
+        assert!(is_past_threshold(0, i16::MIN, 0.5));
+        assert!(!is_past_threshold(0, i16::MAX, 1.0));
+    }
+
+    #[test]
+    fn it_ignores_axes_jittering_where_they_were_left() {
+        // A throttle parked most of the way up
+        assert!(!is_past_threshold(25_000, 25_100, 0.5));
+        assert!(!is_past_threshold(25_000, 20_000, 0.5));
+        assert!(is_past_threshold(25_000, 0, 0.5));
+        assert!(is_past_threshold(25_000, i16::MIN, 0.5));
+    }
+
+    #[test]
+    fn it_reads_the_hardware_id_from_the_guid() {
+        // A Thrustmaster throttle over USB
+        assert_eq!(
+            hardware_id("030000004f0400000ab1000000000000"),
+            Some(("044F".to_string(), "B10A".to_string()))
+        );
+        assert_eq!(hardware_id("00000000000000000000000000000000"), None);
+        assert_eq!(hardware_id("not a guid"), None);
+    }
+}
@@ -10,7 +10,9 @@ use winit::dpi::PhysicalSize;
 use glow as GL;
 use glow::HasContext;
 use glutin::config::{ColorBufferType, Config as GLConfig, ConfigTemplateBuilder};
-use glutin::context::{ContextApi, ContextAttributesBuilder, PossiblyCurrentContext, Version};
+use glutin::context::{
+    ContextApi, ContextAttributesBuilder, PossiblyCurrentContext, Robustness, Version,
+};
 use glutin::display::{Display, DisplayApiPreference, GetGlDisplay};
 use glutin::prelude::*;
 use glutin::surface::{Surface, SurfaceAttributesBuilder, WindowSurface};
@@ -22,6 +24,58 @@ pub struct GLContext {
     pub context: PossiblyCurrentContext,
     pub surface: Surface<WindowSurface>,
     pub gl: Rc<glow::Context>,
+    // Kept around to create a new surface, if the old one is lost
+    config: GLConfig,
+}
+
+/// Point the hybrid graphics drivers at the GPU that Windows was told to prefer, so that the choice
+/// holds from the first context on, rather than from the next launch. The drivers read these hints
+/// when they’re loaded, which is when the first context is created.
+///
+/// Without a preference, or with the high performance GPU preferred, they ask for the high
+/// performance GPU, like they always have.
+#[cfg(all(target_os = "windows", not(target_arch = "aarch64")))]
+pub(crate) fn set_hybrid_graphics_preference(
+    preference: Option<crate::platform::windows::gpu_preference::OsPreference>,
+) {
+    use crate::platform::windows::gpu_preference::OsPreference;
+
+    let high_performance = (preference != Some(OsPreference::MinimumPower)) as i32;
+    // Only ever written here, from the main thread, before any render threads start.
+    unsafe {
+        crate::NvOptimusEnablement = high_performance;
+        crate::AmdPowerXpressRequestHighPerformance = high_performance;
+    }
+}
+
+impl GLContext {
+    /// Replace the window surface, keeping the context and everything uploaded to it.
+    ///
+    /// Some drivers lose the surface when waking up from hybrid sleep or hibernation.
+    pub fn recreate_surface(
+        &mut self,
+        raw_window_handle: RawWindowHandle,
+        inner_size: PhysicalSize<u32>,
+    ) -> glutin::error::Result<()> {
+        let (width, height) = inner_size
+            .non_zero()
+            .ok_or(glutin::error::ErrorKind::BadNativeWindow)?;
+        let attrs = SurfaceAttributesBuilder::<WindowSurface>::new().build(
+            raw_window_handle,
+            width,
+            height,
+        );
+
+        let surface = unsafe {
+            self.config
+                .display()
+                .create_window_surface(&self.config, &attrs)?
+        };
+        self.context.make_current(&surface)?;
+        self.surface = surface;
+
+        Ok(())
+    }
 }
 
 /// Create an OpenGL context, surface, and initialize the glow API.
@@ -32,6 +86,11 @@ pub struct GLContext {
 /// using the handle to the preview window, pass the window handle for the invisible event window
 /// to work around a bug where Windows complains that it can't find the window class.
 ///
+/// With `software`, ask for a pixel format without hardware acceleration, for when the GPU driver
+/// is broken. This picks up a software renderer, like Mesa’s llvmpipe, if one is installed in
+/// place of the system OpenGL. The one that comes with Windows only goes up to OpenGL 1.1, so
+/// it’s turned down, and without Mesa there’s no software fallback on Windows.
+///
 /// This code has been modified from glutin-winit and only supports WGL (Windows).
 pub(crate) fn new_gl_context(
     raw_display_handle: RawDisplayHandle,
@@ -41,7 +100,10 @@ pub(crate) fn new_gl_context(
     // A hack to create the gl_display using the invisible event window
     // we create for the preview.
     _attr_window: Option<RawWindowHandle>,
-) -> GLContext {
+    // Skip straight to the GLES fallback, for drivers with a broken desktop OpenGL
+    force_gles: bool,
+    software: bool,
+) -> Result<GLContext, String> {
     let template = ConfigTemplateBuilder::new()
         .with_buffer_type(glutin::config::ColorBufferType::Rgb {
             r_size: 8,
@@ -50,6 +112,7 @@ pub(crate) fn new_gl_context(
         })
         .with_alpha_size(8)
         .with_transparency(true)
+        .prefer_hardware_accelerated(software.then_some(false))
         .compatible_with_native_window(raw_window_handle)
         .build();
 
@@ -66,14 +129,20 @@ pub(crate) fn new_gl_context(
     let _preference = DisplayApiPreference::Wgl(Some(raw_window_handle));
     #[cfg(all(wgl_backend, egl_backend))]
     let _preference = DisplayApiPreference::WglThenEgl(Some(_attr_window));
+    // The Qualcomm OpenGL driver is a translation layer with plenty of quirks. Prefer ANGLE, if it
+    // ships alongside the executable.
+    #[cfg(all(wgl_backend, egl_backend, target_arch = "aarch64"))]
+    let _preference = DisplayApiPreference::EglThenWgl(Some(_attr_window));
     #[cfg(all(glx_backend, egl_backend))]
     let _preference = DisplayApiPreference::GlxThenEgl(Box::new(register_xlib_error_hook));
-    let gl_display = unsafe { Display::new(raw_display_handle, _preference).unwrap() };
+    let gl_display = unsafe { Display::new(raw_display_handle, _preference) }
+        .map_err(|err| format!("Failed to open the GL display: {}", err))?;
 
     // Rank the configs by transparency and alpha size, while prefering the original order of the
     // configs.
     #[derive(Debug, Eq, Ord, PartialEq, PartialOrd)]
     struct Rank {
+        matches_acceleration: bool,
         supports_transparency: bool,
         alpha_size: u8,
         samples: i8,
@@ -84,20 +153,21 @@ pub(crate) fn new_gl_context(
     let (gl_config_index, gl_config) = unsafe {
         gl_display
             .find_configs(template)
-            .unwrap()
+            .map_err(|err| format!("Failed to find a GL config: {}", err))?
             .enumerate()
             .map(|(index, config)| {
                 log::debug!("Found config #{index}:\n{}", HumanConfig::new(&config));
                 (index, config)
             })
             .max_by_key(|(index, config)| Rank {
+                matches_acceleration: config.hardware_accelerated() != software,
                 supports_transparency: config.supports_transparency().unwrap_or(false),
                 alpha_size: config.alpha_size(),
                 samples: -(config.num_samples() as i8),
                 supports_srgb: config.srgb_capable(),
                 prefer_original_order: -(*index as isize),
             })
-            .expect("cannot find a suitable GL config")
+            .ok_or("Failed to find a suitable GL config")?
     };
 
     log::debug!(
@@ -105,27 +175,45 @@ pub(crate) fn new_gl_context(
         HumanConfig::new(&gl_config)
     );
 
-    // Request the minimum required OpenGL version for Flux
-    let context_attributes = ContextAttributesBuilder::new()
-        .with_context_api(ContextApi::OpenGl(Some(Version::new(3, 3))))
-        .build(Some(raw_window_handle));
-
-    // Fallback to GLES 3.0 (aka WebGL 2.0)
-    let fallback_context_attributes = ContextAttributesBuilder::new()
-        .with_context_api(ContextApi::Gles(Some(Version::new(3, 0))))
-        .build(Some(raw_window_handle));
+    // Request the minimum required OpenGL version for Flux, or fallback to GLES 3.0 (aka WebGL
+    // 2.0).
+    //
+    // Ask for a robust context first, which is lost when the GPU resets rather than leaving the
+    // driver in an undefined state, and reports the reset. Not every driver supports one.
+    let create_context = |api| {
+        let attributes = |robustness| {
+            ContextAttributesBuilder::new()
+                .with_context_api(api)
+                .with_robustness(robustness)
+                .build(Some(raw_window_handle))
+        };
 
-    let not_current_gl_context = unsafe {
-        gl_display
-            .create_context(&gl_config, &context_attributes)
-            .unwrap_or_else(|_| {
-                gl_display
-                    .create_context(&gl_config, &fallback_context_attributes)
-                    .expect("failed to create OpenGL context")
-            })
+        unsafe {
+            gl_display
+                .create_context(
+                    &gl_config,
+                    &attributes(Robustness::RobustLoseContextOnReset),
+                )
+                .or_else(|_| {
+                    gl_display.create_context(&gl_config, &attributes(Robustness::NotRobust))
+                })
+        }
     };
+    let create_fallback_context = || {
+        create_context(ContextApi::Gles(Some(Version::new(3, 0))))
+            .map_err(|err| format!("Failed to create an OpenGL context: {}", err))
+    };
+
+    let not_current_gl_context = if force_gles {
+        create_fallback_context()
+    } else {
+        create_context(ContextApi::OpenGl(Some(Version::new(3, 3))))
+            .or_else(|_| create_fallback_context())
+    }?;
 
-    let (width, height) = inner_size.non_zero().expect("non-zero window size");
+    let (width, height) = inner_size
+        .non_zero()
+        .ok_or("The window has no size to render into")?;
     let attrs =
         SurfaceAttributesBuilder::<WindowSurface>::new().build(raw_window_handle, width, height);
 
@@ -133,13 +221,13 @@ pub(crate) fn new_gl_context(
         gl_config
             .display()
             .create_window_surface(&gl_config, &attrs)
-            .unwrap()
+            .map_err(|err| format!("Failed to create the GL surface: {}", err))?
     };
 
     // Make it current.
     let gl_context = not_current_gl_context
         .make_current(&gl_surface)
-        .expect("failed to make the OpenGL context current during setup");
+        .map_err(|err| format!("Failed to make the OpenGL context current: {}", err))?;
 
     let glow_context = unsafe {
         glow::Context::from_loader_function(|s| {
@@ -148,6 +236,22 @@ pub(crate) fn new_gl_context(
     };
     log::debug!("{:?}", glow_context.version());
 
+    // Without a driver of its own, Windows hands out its ancient OpenGL 1.1 renderer instead,
+    // which can’t run Flux.
+    let version = glow_context.version();
+    let minimum = if version.is_embedded { (3, 0) } else { (3, 3) };
+    if (version.major, version.minor) < minimum {
+        let hint = if software {
+            ". Rendering without the GPU needs a software OpenGL driver, like Mesa’s llvmpipe"
+        } else {
+            ""
+        };
+        return Err(format!(
+            "OpenGL {}.{} is too old to run Flux{}",
+            version.major, version.minor, hint
+        ));
+    }
+
     // Set common GL state
     unsafe {
         glow_context.disable(GL::MULTISAMPLE);
@@ -155,11 +259,12 @@ pub(crate) fn new_gl_context(
         // glow_context.disable(GL::DEPTH_TEST);
     }
 
-    GLContext {
+    Ok(GLContext {
         context: gl_context,
         surface: gl_surface,
         gl: Rc::new(glow_context),
-    }
+        config: gl_config,
+    })
 }
 
 #[derive(Debug)]
@@ -0,0 +1,256 @@
+use crate::config::{GpuWorkaround, GpuWorkarounds, Quality};
+
+use std::fmt;
+
+use glow as GL;
+use glow::HasContext;
+
+/// The renderer string reported by the driver, like “NVIDIA GeForce RTX 3070/PCIe/SSE2”.
+pub fn renderer(gl: &glow::Context) -> String {
+    unsafe { gl.get_parameter_string(GL::RENDERER) }
+}
+
+// From GL_NVX_gpu_memory_info and GL_ATI_meminfo. Both report kilobytes.
+const GPU_MEMORY_INFO_TOTAL_AVAILABLE_MEMORY_NVX: u32 = 0x9048;
+const GPU_MEMORY_INFO_CURRENT_AVAILABLE_VIDMEM_NVX: u32 = 0x9049;
+const TEXTURE_FREE_MEMORY_ATI: u32 = 0x87FC;
+
+/// Video memory, in megabytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Memory {
+    pub free_mb: u32,
+    /// Only NVIDIA reports how much there is in total
+    pub total_mb: Option<u32>,
+}
+
+/// How much video memory is free, if the driver has an extension that says.
+pub fn memory(gl: &glow::Context) -> Option<Memory> {
+    let extensions = gl.supported_extensions();
+    let to_mb = |kb: i32| (kb.max(0) / 1024) as u32;
+
+    unsafe {
+        if extensions.contains("GL_NVX_gpu_memory_info") {
+            Some(Memory {
+                free_mb: to_mb(gl.get_parameter_i32(GPU_MEMORY_INFO_CURRENT_AVAILABLE_VIDMEM_NVX)),
+                total_mb: Some(to_mb(
+                    gl.get_parameter_i32(GPU_MEMORY_INFO_TOTAL_AVAILABLE_MEMORY_NVX),
+                )),
+            })
+        } else if extensions.contains("GL_ATI_meminfo") {
+            // The total free, the largest free block, and the same again for shared memory
+            let mut values = [0; 4];
+            gl.get_parameter_i32_slice(TEXTURE_FREE_MEMORY_ATI, &mut values);
+            Some(Memory {
+                free_mb: to_mb(values[0]),
+                total_mb: None,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for Memory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.total_mb {
+            Some(total_mb) => write!(
+                f,
+                "{} of {} MB used",
+                total_mb.saturating_sub(self.free_mb),
+                total_mb
+            ),
+            None => write!(f, "{} MB free", self.free_mb),
+        }
+    }
+}
+
+/// The family of the GPU, from its renderer string. Unlike the renderer, this doesn’t tell the
+/// model apart.
+pub fn vendor(renderer: &str) -> &'static str {
+    let renderer = renderer.to_lowercase();
+    let has = |needle: &str| renderer.contains(needle);
+
+    if has("llvmpipe") || has("softpipe") || has("swiftshader") || has("basic render") {
+        "software"
+    } else if has("nvidia") || has("geforce") || has("quadro") {
+        "nvidia"
+    } else if has("amd") || has("radeon") {
+        "amd"
+    } else if has("intel") {
+        "intel"
+    } else if has("qualcomm") || has("adreno") {
+        "qualcomm"
+    } else {
+        "other"
+    }
+}
+
+/// Guess a sensible quality for a GPU from its renderer string.
+///
+/// This is only a rough heuristic to pick a default that doesn’t stutter on integrated graphics.
+pub fn recommended_quality(renderer: &str) -> Quality {
+    let renderer = renderer.to_lowercase();
+    let has = |needle: &str| renderer.contains(needle);
+
+    // Software rasterizers
+    if has("llvmpipe")
+        || has("softpipe")
+        || has("swiftshader")
+        || has("basic render")
+        || has("gdi generic")
+    {
+        return Quality::Low;
+    }
+
+    if has("intel") {
+        return if has("arc") {
+            Quality::High
+        } else if has("iris") || has("uhd") || has("xe graphics") {
+            Quality::Medium
+        } else {
+            Quality::Low
+        };
+    }
+
+    // Snapdragon laptops and AMD APUs
+    if has("adreno") || has("vega") || has("radeon(tm) graphics") || has("radeon graphics") {
+        return Quality::Medium;
+    }
+
+    if has("rtx") || has("radeon rx 7") || has("radeon rx 6") {
+        return Quality::Ultra;
+    }
+
+    Quality::High
+}
+
+// Known problematic drivers, keyed by a lowercase substring of the renderer string.
+const KNOWN_WORKAROUNDS: &[(&str, GpuWorkarounds)] = &[
+    // The software fallback that Windows uses without a proper driver
+    (
+        "basic render",
+        GpuWorkarounds {
+            disable_dxgi_interop: true,
+            force_gles: false,
+            max_fps: Some(30),
+        },
+    ),
+    (
+        "llvmpipe",
+        GpuWorkarounds {
+            disable_dxgi_interop: true,
+            force_gles: false,
+            max_fps: Some(30),
+        },
+    ),
+    // Qualcomm GPUs on ARM64 only support OpenGL through a D3D12 translation layer
+    (
+        "adreno",
+        GpuWorkarounds {
+            disable_dxgi_interop: true,
+            force_gles: true,
+            max_fps: None,
+        },
+    ),
+    // Virtual machines
+    (
+        "vmware svga",
+        GpuWorkarounds {
+            disable_dxgi_interop: true,
+            force_gles: false,
+            max_fps: None,
+        },
+    ),
+    (
+        "parallels",
+        GpuWorkarounds {
+            disable_dxgi_interop: true,
+            force_gles: false,
+            max_fps: None,
+        },
+    ),
+];
+
+/// Collect the workarounds that apply to a GPU, from both the built-in list and the config.
+pub fn workarounds(renderer: &str, extra: &[GpuWorkaround]) -> GpuWorkarounds {
+    let renderer = renderer.to_lowercase();
+
+    let built_in = KNOWN_WORKAROUNDS
+        .iter()
+        .filter(|(needle, _)| renderer.contains(needle))
+        .map(|(_, workarounds)| *workarounds);
+    let from_config = extra
+        .iter()
+        .filter(|workaround| renderer.contains(&workaround.renderer.to_lowercase()))
+        .map(|workaround| workaround.workarounds);
+
+    built_in
+        .chain(from_config)
+        .fold(GpuWorkarounds::default(), GpuWorkarounds::merge)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_combines_built_in_and_configured_workarounds() {
+        let extra = [GpuWorkaround {
+            renderer: "LLVMPIPE".to_string(),
+            workarounds: GpuWorkarounds {
+                force_gles: true,
+                max_fps: Some(60),
+                ..Default::default()
+            },
+        }];
+
+        assert_eq!(
+            workarounds("llvmpipe (LLVM 15.0.7, 256 bits)", &extra),
+            GpuWorkarounds {
+                disable_dxgi_interop: true,
+                force_gles: true,
+                max_fps: Some(30),
+            }
+        );
+        assert!(workarounds("D3D12 (Qualcomm(R) Adreno(TM) 690 GPU)", &extra).force_gles);
+        assert_eq!(
+            workarounds("NVIDIA GeForce RTX 3070/PCIe/SSE2", &extra),
+            GpuWorkarounds::default()
+        );
+    }
+
+    #[test]
+    fn it_only_reports_the_vendor() {
+        let cases = [
+            ("llvmpipe (LLVM 15.0.7, 256 bits)", "software"),
+            ("NVIDIA GeForce RTX 3070/PCIe/SSE2", "nvidia"),
+            ("AMD Radeon(TM) Graphics", "amd"),
+            ("Intel(R) UHD Graphics 620", "intel"),
+            ("D3D12 (Qualcomm(R) Adreno(TM) 690 GPU)", "qualcomm"),
+            ("Some future GPU", "other"),
+        ];
+
+        for (renderer, vendor) in cases {
+            assert_eq!(super::vendor(renderer), vendor, "{renderer}");
+        }
+    }
+
+    #[test]
+    fn it_recommends_a_quality_for_known_gpus() {
+        let cases = [
+            ("llvmpipe (LLVM 15.0.7, 256 bits)", Quality::Low),
+            ("Intel(R) HD Graphics 4000", Quality::Low),
+            ("Intel(R) UHD Graphics 620", Quality::Medium),
+            ("Intel(R) Arc(TM) A770 Graphics", Quality::High),
+            ("AMD Radeon(TM) Graphics", Quality::Medium),
+            ("Qualcomm(R) Adreno(TM) 690 GPU", Quality::Medium),
+            ("NVIDIA GeForce GTX 1060 6GB/PCIe/SSE2", Quality::High),
+            ("NVIDIA GeForce RTX 3070/PCIe/SSE2", Quality::Ultra),
+            ("Some future GPU", Quality::High),
+        ];
+
+        for (renderer, quality) in cases {
+            assert_eq!(recommended_quality(renderer), quality, "{renderer}");
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use crate::config::Color;
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path;
+use std::sync::Mutex;
+
+use ordered_float::OrderedFloat;
+
+// The size of the image the gradient is drawn into. Flux scales the image to fill the screen, so
+// a single row is enough.
+const IMAGE_WIDTH: u32 = 512;
+const IMAGE_HEIGHT: u32 = 1;
+
+// Keep the editor short enough to fit in the settings window.
+pub const MAX_STOPS: usize = 6;
+
+pub type Stop = (OrderedFloat<f32>, Color);
+
+// The images drawn by this process, to clean up on exit
+static IMAGE_FILES: Mutex<Vec<path::PathBuf>> = Mutex::new(Vec::new());
+
+/// The gradient to start from when switching to a custom one.
+pub fn default_stops() -> Vec<Stop> {
+    vec![
+        (
+            OrderedFloat(0.0),
+            Color {
+                r: 0x1d,
+                g: 0x2b,
+                b: 0x64,
+            },
+        ),
+        (
+            OrderedFloat(0.5),
+            Color {
+                r: 0xd4,
+                g: 0x50,
+                b: 0x8c,
+            },
+        ),
+        (
+            OrderedFloat(1.0),
+            Color {
+                r: 0xf8,
+                g: 0xc3,
+                b: 0x6b,
+            },
+        ),
+    ]
+}
+
+/// The color at a position along the gradient, blending between the nearest stops. The stops
+/// don’t need to be in order.
+pub fn sample(stops: &[Stop], position: f32) -> Option<Color> {
+    let mut sorted = stops.to_vec();
+    sorted.sort_by_key(|(position, _)| *position);
+
+    let (first, last) = (sorted.first()?, sorted.last()?);
+    if position <= first.0.into_inner() {
+        return Some(first.1);
+    }
+    if position >= last.0.into_inner() {
+        return Some(last.1);
+    }
+
+    let (a, b) = sorted
+        .windows(2)
+        .map(|pair| (pair[0], pair[1]))
+        .find(|(_, b)| position <= b.0.into_inner())?;
+    let span = b.0.into_inner() - a.0.into_inner();
+    let t = if span > 0.0 {
+        (position - a.0.into_inner()) / span
+    } else {
+        0.0
+    };
+
+    let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+    Some(Color {
+        r: mix(a.1.r, b.1.r),
+        g: mix(a.1.g, b.1.g),
+        b: mix(a.1.b, b.1.b),
+    })
+}
+
+/// A gradient of shades around a single color, from a deep shade up to a pale tint, for a desktop
+/// with a solid color background. Flux needs some range to tell the lines apart.
+pub fn from_color(color: Color) -> Vec<Stop> {
+    let mix = |toward: u8, amount: f32| {
+        let mix = |channel: u8| {
+            (channel as f32 + (toward as f32 - channel as f32) * amount).round() as u8
+        };
+        Color {
+            r: mix(color.r),
+            g: mix(color.g),
+            b: mix(color.b),
+        }
+    };
+
+    vec![
+        (OrderedFloat(0.0), mix(0, 0.7)),
+        (OrderedFloat(0.5), color),
+        (OrderedFloat(1.0), mix(u8::MAX, 0.45)),
+    ]
+}
+
+/// Draw the gradient into an image for Flux to take its colors from.
+///
+/// The image is named after the stops, so it’s only drawn again when they change. It’s kept
+/// until [`remove_image_files`], by whichever process drew it.
+pub fn image_file(stops: &[Stop]) -> Result<path::PathBuf, String> {
+    if stops.is_empty() {
+        return Err("The gradient has no colors".to_string());
+    }
+
+    let mut hasher = DefaultHasher::new();
+    stops.hash(&mut hasher);
+    let path = std::env::temp_dir().join(format!("flux-gradient-{:016x}.png", hasher.finish()));
+    if path.is_file() {
+        return Ok(path);
+    }
+
+    save_image(stops, &path)?;
+    if let Ok(mut image_files) = IMAGE_FILES.lock() {
+        image_files.push(path.clone());
+    }
+    Ok(path)
+}
+
+/// Draw the gradient into an image at `path`, replacing whatever was there.
+pub fn save_image(stops: &[Stop], path: &path::Path) -> Result<(), String> {
+    if stops.is_empty() {
+        return Err("The gradient has no colors".to_string());
+    }
+
+    let image = image::RgbImage::from_fn(IMAGE_WIDTH, IMAGE_HEIGHT, |x, _| {
+        let position = x as f32 / (IMAGE_WIDTH - 1) as f32;
+        let color = sample(stops, position).unwrap_or(stops[0].1);
+        image::Rgb([color.r, color.g, color.b])
+    });
+    image
+        .save(path)
+        .map_err(|err| format!("Failed to save {}: {}", path.display(), err))
+}
+
+/// Remove the images drawn by [`image_file`] in this process, before it exits.
+pub fn remove_image_files() {
+    let Ok(mut image_files) = IMAGE_FILES.lock() else {
+        return;
+    };
+    for path in image_files.drain(..) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const BLACK: Color = Color { r: 0, g: 0, b: 0 };
+    const WHITE: Color = Color {
+        r: 255,
+        g: 255,
+        b: 255,
+    };
+
+    #[test]
+    fn it_blends_between_the_nearest_stops() {
+        let stops = [(OrderedFloat(1.0), WHITE), (OrderedFloat(0.25), BLACK)];
+
+        assert_eq!(sample(&stops, 0.0), Some(BLACK));
+        assert_eq!(sample(&stops, 0.25), Some(BLACK));
+        assert_eq!(
+            sample(&stops, 0.625),
+            Some(Color {
+                r: 128,
+                g: 128,
+                b: 128
+            })
+        );
+        assert_eq!(sample(&stops, 1.5), Some(WHITE));
+        assert_eq!(sample(&[], 0.5), None);
+    }
+
+    #[test]
+    fn it_spreads_a_single_color_into_shades() {
+        let stops = from_color(Color {
+            r: 0x20,
+            g: 0x60,
+            b: 0xa0,
+        });
+
+        assert_eq!(
+            stops.iter().map(|(_, color)| *color).collect::<Vec<_>>(),
+            vec![
+                Color {
+                    r: 0x0a,
+                    g: 0x1d,
+                    b: 0x30,
+                },
+                Color {
+                    r: 0x20,
+                    g: 0x60,
+                    b: 0xa0,
+                },
+                Color {
+                    r: 0x84,
+                    g: 0xa8,
+                    b: 0xcb,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn it_reads_and_writes_colors_as_hex() {
+        assert_eq!(
+            Color::try_from("#1D2b64".to_string()),
+            Ok(Color {
+                r: 0x1d,
+                g: 0x2b,
+                b: 0x64
+            })
+        );
+        assert_eq!(String::from(WHITE), "#ffffff");
+        assert!(Color::try_from("#fff".to_string()).is_err());
+        assert!(Color::try_from("#ggffff".to_string()).is_err());
+    }
+}
@@ -0,0 +1,501 @@
+use crate::compositor;
+use crate::config::{self, OverlayPosition, Rotation};
+#[cfg(feature = "sdl")]
+use crate::stats::{self, Stats};
+
+use std::rc::Rc;
+#[cfg(feature = "sdl")]
+use std::time::{Duration, Instant};
+
+use glow as GL;
+use glow::HasContext;
+use winit::dpi::PhysicalSize;
+
+const VERTEX_SHADER: &str = r#"
+// The left, top, width, and height of the quad, in clip space
+uniform vec4 uRect;
+// Quarter turns to undo, for a monitor that is mounted turned clockwise, as in the compositor
+uniform int uQuarterTurns;
+out vec2 vTexCoord;
+
+vec2 turn(vec2 position) {
+    if (uQuarterTurns == 1) return vec2(-position.y, position.x);
+    if (uQuarterTurns == 2) return -position;
+    if (uQuarterTurns == 3) return vec2(position.y, -position.x);
+    return position;
+}
+
+void main() {
+    // Draw a quad as a strip of two triangles, starting from the top left.
+    vec2 corner = vec2(float(gl_VertexID & 1), float(gl_VertexID >> 1));
+    vTexCoord = corner;
+    vec2 position = vec2(uRect.x + corner.x * uRect.z, uRect.y - corner.y * uRect.w);
+    gl_Position = vec4(turn(position), 0.0, 1.0);
+}
+"#;
+
+const FRAGMENT_SHADER: &str = r#"
+in vec2 vTexCoord;
+out vec4 fragColor;
+
+uniform sampler2D uTexture;
+uniform float uWhiteLevel;
+uniform float uOpacity;
+
+void main() {
+    vec4 color = texture(uTexture, vTexCoord);
+    fragColor = vec4(color.rgb * uWhiteLevel, color.a * uOpacity);
+}
+"#;
+
+// The glyphs are 5×7 pixels, one row to a byte, with the leftmost pixel in the fifth bit.
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+// The space around each glyph, and around the text as a whole, in font pixels
+const LETTER_SPACING: u32 = 1;
+const LINE_SPACING: u32 = 3;
+const PADDING: u32 = 4;
+// How far the overlay sits from the corner of the window, in font pixels
+const MARGIN: u32 = 4;
+// Longer lines, like ones with a long preset name, are cut short.
+const MAX_LINE_LENGTH: usize = 56;
+
+const BACKGROUND: [u8; 4] = [0, 0, 0, 160];
+const FOREGROUND: [u8; 4] = [255, 255, 255, 255];
+
+// Only uppercase letters, which is plenty for numbers, units and the names of commands.
+const FONT: &[(char, [u8; 7])] = &[
+    (' ', [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]),
+    ('A', [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11]),
+    ('B', [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E]),
+    ('C', [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E]),
+    ('D', [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C]),
+    ('E', [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F]),
+    ('F', [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10]),
+    ('G', [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F]),
+    ('H', [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11]),
+    ('I', [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+    ('J', [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C]),
+    ('K', [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11]),
+    ('L', [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F]),
+    ('M', [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11]),
+    ('N', [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11]),
+    ('O', [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E]),
+    ('P', [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10]),
+    ('Q', [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D]),
+    ('R', [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11]),
+    ('S', [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E]),
+    ('T', [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04]),
+    ('U', [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E]),
+    ('V', [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04]),
+    ('W', [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A]),
+    ('X', [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11]),
+    ('Y', [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04]),
+    ('Z', [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F]),
+    ('0', [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E]),
+    ('1', [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+    ('2', [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F]),
+    ('3', [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E]),
+    ('4', [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02]),
+    ('5', [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E]),
+    ('6', [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E]),
+    ('7', [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08]),
+    ('8', [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E]),
+    ('9', [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C]),
+    ('.', [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C]),
+    (',', [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08]),
+    (':', [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00]),
+    ('/', [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00]),
+    ('-', [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00]),
+    ('(', [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02]),
+    (')', [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08]),
+    ('#', [0x0A, 0x0A, 0x1F, 0x0A, 0x1F, 0x0A, 0x0A]),
+    ('%', [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03]),
+    ('>', [0x08, 0x04, 0x02, 0x01, 0x02, 0x04, 0x08]),
+    ('[', [0x0E, 0x08, 0x08, 0x08, 0x08, 0x08, 0x0E]),
+    (']', [0x0E, 0x02, 0x02, 0x02, 0x02, 0x02, 0x0E]),
+    ('?', [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04]),
+];
+
+/// Where the text goes, and how it looks.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Style {
+    pub position: OverlayPosition,
+    /// How many screen pixels make up a pixel of the font
+    pub scale: u32,
+    pub opacity: f32,
+    /// Whether to draw the text over a dark backing, which keeps it readable over anything
+    pub backing: bool,
+    /// How the monitor is mounted, to turn the text upright along with the picture. The tiles of a
+    /// video wall aren’t turned one by one, so the text is placed on the wall as a whole.
+    pub rotation: Rotation,
+}
+
+impl Style {
+    /// The look of the stats: small, in the top left corner, over a dark backing.
+    pub fn stats(scale_factor: f64) -> Self {
+        Self {
+            position: OverlayPosition::TopLeft,
+            scale: (2.0 * scale_factor).round().max(1.0) as u32,
+            opacity: 1.0,
+            backing: true,
+            rotation: Rotation::None,
+        }
+    }
+}
+
+/// The scale that brings the font closest to `font_size` pixels tall, at 100% scaling.
+pub fn scale_for_font_size(font_size: u32, scale_factor: f64) -> u32 {
+    let font_size = font_size.clamp(1, config::MAX_FONT_SIZE);
+    (font_size as f64 * scale_factor / GLYPH_HEIGHT as f64)
+        .round()
+        .max(1.0) as u32
+}
+
+/// Draws text over the frame, like the stats or a clock.
+///
+/// The text is drawn into a texture with a built-in pixel font whenever it changes, which is
+/// only every so often, and the texture is then drawn over each frame.
+pub struct Hud {
+    gl: Rc<glow::Context>,
+    program: GL::NativeProgram,
+    vertex_array: GL::NativeVertexArray,
+    texture: Option<(GL::NativeTexture, PhysicalSize<u32>)>,
+    style: Style,
+}
+
+impl Hud {
+    /// Draw the stats.
+    pub fn new(gl: &Rc<glow::Context>, scale_factor: f64) -> Result<Self, String> {
+        Self::with_style(gl, Style::stats(scale_factor))
+    }
+
+    pub fn with_style(gl: &Rc<glow::Context>, style: Style) -> Result<Self, String> {
+        let program = unsafe { compositor::create_program(gl, VERTEX_SHADER, FRAGMENT_SHADER)? };
+        let vertex_array = unsafe { gl.create_vertex_array()? };
+
+        Ok(Self {
+            gl: Rc::clone(gl),
+            program,
+            vertex_array,
+            texture: None,
+            style,
+        })
+    }
+
+    /// Fade the text, from 1.0 for solid to 0.0 for hidden.
+    pub fn set_opacity(&mut self, opacity: f32) {
+        self.style.opacity = opacity.clamp(0.0, 1.0);
+    }
+
+    /// Replace the text, which can span several lines.
+    pub fn set_text(&mut self, text: &str) {
+        let image = rasterize(text, self.style.backing);
+        let gl = &self.gl;
+
+        unsafe {
+            let texture = match self.texture {
+                Some((texture, _)) => texture,
+                None => match gl.create_texture() {
+                    Ok(texture) => texture,
+                    Err(err) => {
+                        log::debug!("Failed to create the texture for the text: {}", err);
+                        return;
+                    }
+                },
+            };
+
+            gl.bind_texture(GL::TEXTURE_2D, Some(texture));
+            gl.tex_parameter_i32(GL::TEXTURE_2D, GL::TEXTURE_MIN_FILTER, GL::NEAREST as i32);
+            gl.tex_parameter_i32(GL::TEXTURE_2D, GL::TEXTURE_MAG_FILTER, GL::NEAREST as i32);
+            gl.tex_parameter_i32(GL::TEXTURE_2D, GL::TEXTURE_WRAP_S, GL::CLAMP_TO_EDGE as i32);
+            gl.tex_parameter_i32(GL::TEXTURE_2D, GL::TEXTURE_WRAP_T, GL::CLAMP_TO_EDGE as i32);
+            gl.tex_image_2d(
+                GL::TEXTURE_2D,
+                0,
+                GL::RGBA8 as i32,
+                image.width() as i32,
+                image.height() as i32,
+                0,
+                GL::RGBA,
+                GL::UNSIGNED_BYTE,
+                Some(image.as_raw()),
+            );
+            gl.bind_texture(GL::TEXTURE_2D, None);
+
+            self.texture = Some((texture, PhysicalSize::new(image.width(), image.height())));
+        }
+    }
+
+    /// Draw the text over whatever is in the target framebuffer, which is `size` pixels large.
+    /// For a frame in linear light, white is drawn at `white_level`.
+    pub fn draw(
+        &self,
+        target: Option<GL::NativeFramebuffer>,
+        size: PhysicalSize<u32>,
+        white_level: Option<f32>,
+    ) {
+        let Some((texture, text_size)) = self.texture else {
+            return;
+        };
+        if size.width == 0 || size.height == 0 || self.style.opacity <= 0.0 {
+            return;
+        }
+
+        // The text is laid out on the picture as it’s meant to be seen, and turned with it. From
+        // pixels to clip space, which runs from -1 to 1 across the frame.
+        let upright = self.style.rotation.render_size(size);
+        let to_clip_x = |pixels: u32| 2.0 * pixels as f32 / upright.width as f32;
+        let to_clip_y = |pixels: u32| 2.0 * pixels as f32 / upright.height as f32;
+        let scale = self.style.scale;
+        let text_size = PhysicalSize::new(text_size.width * scale, text_size.height * scale);
+        let (left, top) = place(self.style.position, text_size, upright, MARGIN * scale);
+        let gl = &self.gl;
+
+        unsafe {
+            gl.bind_framebuffer(GL::FRAMEBUFFER, target);
+            gl.viewport(0, 0, size.width as i32, size.height as i32);
+            gl.use_program(Some(self.program));
+            gl.bind_vertex_array(Some(self.vertex_array));
+            gl.active_texture(GL::TEXTURE0);
+            gl.bind_texture(GL::TEXTURE_2D, Some(texture));
+            gl.uniform_1_i32(
+                gl.get_uniform_location(self.program, "uTexture").as_ref(),
+                0,
+            );
+            gl.uniform_1_f32(
+                gl.get_uniform_location(self.program, "uWhiteLevel")
+                    .as_ref(),
+                white_level.unwrap_or(1.0),
+            );
+            gl.uniform_1_f32(
+                gl.get_uniform_location(self.program, "uOpacity").as_ref(),
+                self.style.opacity,
+            );
+            gl.uniform_1_i32(
+                gl.get_uniform_location(self.program, "uQuarterTurns")
+                    .as_ref(),
+                self.style.rotation.quarter_turns() as i32,
+            );
+            gl.uniform_4_f32(
+                gl.get_uniform_location(self.program, "uRect").as_ref(),
+                -1.0 + to_clip_x(left),
+                1.0 - to_clip_y(top),
+                to_clip_x(text_size.width),
+                to_clip_y(text_size.height),
+            );
+
+            gl.enable(GL::BLEND);
+            gl.blend_func(GL::SRC_ALPHA, GL::ONE_MINUS_SRC_ALPHA);
+            gl.draw_arrays(GL::TRIANGLE_STRIP, 0, 4);
+            gl.disable(GL::BLEND);
+
+            gl.bind_texture(GL::TEXTURE_2D, None);
+            gl.bind_vertex_array(None);
+            gl.use_program(None);
+        }
+    }
+}
+
+impl Drop for Hud {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.delete_program(self.program);
+            self.gl.delete_vertex_array(self.vertex_array);
+            if let Some((texture, _)) = self.texture {
+                self.gl.delete_texture(texture);
+            }
+        }
+    }
+}
+
+// The top left corner of the text on the frame, in pixels. Text too large for the frame is pinned
+// to the top left, and cut off at the other edges.
+fn place(
+    position: OverlayPosition,
+    text_size: PhysicalSize<u32>,
+    size: PhysicalSize<u32>,
+    margin: u32,
+) -> (u32, u32) {
+    let start = margin;
+    let end = |extent: u32, text: u32| extent.saturating_sub(margin + text).max(margin);
+    let center = |extent: u32, text: u32| extent.saturating_sub(text) / 2;
+
+    match position {
+        OverlayPosition::TopLeft => (start, start),
+        OverlayPosition::TopRight => (end(size.width, text_size.width), start),
+        OverlayPosition::BottomLeft => (start, end(size.height, text_size.height)),
+        OverlayPosition::BottomRight => (
+            end(size.width, text_size.width),
+            end(size.height, text_size.height),
+        ),
+        OverlayPosition::Center => (
+            center(size.width, text_size.width),
+            center(size.height, text_size.height),
+        ),
+    }
+}
+
+// Lay the text out in the pixel font, one pixel per font pixel, over a dark backing if there is
+// one.
+fn rasterize(text: &str, backing: bool) -> image::RgbaImage {
+    let lines = text
+        .lines()
+        .map(|line| {
+            line.chars()
+                .take(MAX_LINE_LENGTH)
+                .map(|c| c.to_ascii_uppercase())
+                .collect::<Vec<char>>()
+        })
+        .collect::<Vec<_>>();
+    let columns = lines.iter().map(Vec::len).max().unwrap_or(0) as u32;
+    let rows = lines.len() as u32;
+
+    let advance = GLYPH_WIDTH + LETTER_SPACING;
+    let line_height = GLYPH_HEIGHT + LINE_SPACING;
+    let width = 2 * PADDING + (columns * advance).saturating_sub(LETTER_SPACING);
+    let height = 2 * PADDING + (rows * line_height).saturating_sub(LINE_SPACING);
+    let background = if backing { BACKGROUND } else { [0; 4] };
+    let mut image = image::RgbaImage::from_pixel(width, height, image::Rgba(background));
+
+    for (row, line) in lines.iter().enumerate() {
+        for (column, c) in line.iter().enumerate() {
+            let glyph = glyph(*c);
+            let left = PADDING + column as u32 * advance;
+            let top = PADDING + row as u32 * line_height;
+            for (y, bits) in glyph.iter().enumerate() {
+                for x in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - x)) != 0 {
+                        image.put_pixel(left + x, top + y as u32, image::Rgba(FOREGROUND));
+                    }
+                }
+            }
+        }
+    }
+
+    image
+}
+
+// Anything missing from the font shows up as a question mark.
+fn glyph(c: char) -> [u8; 7] {
+    let find = |c| {
+        FONT.iter()
+            .find(|(glyph, _)| *glyph == c)
+            .map(|(_, rows)| *rows)
+    };
+    find(c).or_else(|| find('?')).unwrap_or_default()
+}
+
+/// Puts together the stats of every instance, for each of them to show.
+///
+/// The frame times are pooled across the instances, so the percentiles show stutter on any of
+/// the monitors.
+#[cfg(feature = "sdl")]
+#[derive(Default)]
+pub struct Collector {
+    last_update: Option<Instant>,
+}
+
+#[cfg(feature = "sdl")]
+impl Collector {
+    const UPDATE_INTERVAL: Duration = Duration::from_millis(500);
+
+    /// The text to show, every so often. Each instance is given with the GPU it renders on.
+    pub fn report<'a>(
+        &mut self,
+        now: Instant,
+        instances: impl Iterator<Item = (&'a str, &'a Stats)>,
+    ) -> Option<String> {
+        let is_due = self.last_update.map_or(true, |last_update| {
+            now.duration_since(last_update) >= Self::UPDATE_INTERVAL
+        });
+        if !is_due {
+            return None;
+        }
+        self.last_update = Some(now);
+
+        Some(summarize(instances))
+    }
+}
+
+#[cfg(feature = "sdl")]
+fn summarize<'a>(instances: impl Iterator<Item = (&'a str, &'a Stats)>) -> String {
+    let mut frame_times = Vec::new();
+    let mut lines = Vec::new();
+
+    for (index, (gpu, stats)) in instances.enumerate() {
+        frame_times.extend(stats.frame_times());
+        let timings = match (stats.paused(), stats.average_render_time()) {
+            (Some(reason), _) => format!("paused ({})", reason),
+            (None, Some(render_time)) => format!("{}, {} rendering", stats, millis(render_time)),
+            (None, None) => stats.to_string(),
+        };
+        lines.push(format!("#{} {} on {}", index + 1, timings, gpu));
+    }
+
+    let mut summary = match (
+        stats::percentile(&mut frame_times, 0.5),
+        stats::percentile(&mut frame_times, 0.95),
+        stats::percentile(&mut frame_times, 0.99),
+    ) {
+        (Some(median), Some(p95), Some(p99)) if !median.is_zero() => format!(
+            "{:.0} FPS, 50% {}, 95% {}, 99% {}",
+            1.0 / median.as_secs_f64(),
+            millis(median),
+            millis(p95),
+            millis(p99)
+        ),
+        _ => "Starting".to_string(),
+    };
+    for line in lines {
+        summary.push('\n');
+        summary.push_str(&line);
+    }
+
+    summary
+}
+
+#[cfg(feature = "sdl")]
+fn millis(duration: Duration) -> String {
+    format!("{:.1} ms", duration.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_lays_out_each_line_in_the_pixel_font() {
+        let image = rasterize("1\n-", true);
+        let advance = GLYPH_WIDTH + LETTER_SPACING;
+        assert_eq!(
+            image.dimensions(),
+            (
+                2 * PADDING + advance - LETTER_SPACING,
+                2 * PADDING + 2 * GLYPH_HEIGHT + LINE_SPACING
+            )
+        );
+
+        // The top of the 1, and the middle of the dash on the second line
+        let lit = |x, y| image.get_pixel(PADDING + x, PADDING + y).0 == FOREGROUND;
+        assert!(lit(2, 0) && !lit(0, 0));
+        let dash = GLYPH_HEIGHT + LINE_SPACING + 3;
+        assert!((0..GLYPH_WIDTH).all(|x| lit(x, dash)));
+    }
+
+    #[test]
+    fn it_places_the_text_in_a_corner_or_the_center() {
+        let frame = PhysicalSize::new(1000, 500);
+        let text = PhysicalSize::new(200, 100);
+        assert_eq!(place(OverlayPosition::TopLeft, text, frame, 10), (10, 10));
+        assert_eq!(
+            place(OverlayPosition::BottomRight, text, frame, 10),
+            (790, 390)
+        );
+        assert_eq!(place(OverlayPosition::Center, text, frame, 10), (400, 200));
+
+        // Too wide to fit, so it starts from the left edge instead
+        let wide = PhysicalSize::new(1200, 100);
+        assert_eq!(place(OverlayPosition::TopRight, wide, frame, 10), (10, 10));
+    }
+}
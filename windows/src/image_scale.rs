@@ -0,0 +1,172 @@
+use crate::config::ImageSize;
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path;
+use std::time::Duration;
+
+use winit::dpi::PhysicalSize;
+
+// How many times larger than the surface an image can be on both sides before it’s scaled down.
+// A little extra keeps the colors from blurring together where the image is sampled.
+const MAX_OVERSAMPLING: u32 = 2;
+
+// How long to keep a scaled copy around, in case the image is used again. Copies of images that
+// have since changed are removed right away.
+const MAX_COPY_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+const COPY_PREFIX: &str = "flux-image-";
+
+/// The size to scale an image down to, so that it still covers the surface with some detail to
+/// spare, or `None` if it’s small enough to use as it is. The surface is in physical pixels, so a
+/// high-DPI monitor keeps more of the image.
+pub fn scaled_size(image: ImageSize, surface: PhysicalSize<u32>) -> Option<PhysicalSize<u32>> {
+    let (target_width, target_height) = (
+        surface.width.max(1) * MAX_OVERSAMPLING,
+        surface.height.max(1) * MAX_OVERSAMPLING,
+    );
+    if image.width <= target_width || image.height <= target_height {
+        return None;
+    }
+
+    // Keep the aspect ratio, and cover the target on both sides.
+    let scale = f64::max(
+        target_width as f64 / image.width as f64,
+        target_height as f64 / image.height as f64,
+    );
+    Some(PhysicalSize::new(
+        ((image.width as f64 * scale).round() as u32).max(1),
+        ((image.height as f64 * scale).round() as u32).max(1),
+    ))
+}
+
+/// Save a copy of the image, scaled down for the surface, into the temp directory, reusing one
+/// from an earlier start if the image hasn’t changed since. The image is scaled by its own size,
+/// rather than the one noted in the settings, which goes stale when the file is replaced. If it
+/// turns out to be small enough after all, the image is used as it is.
+///
+/// Earlier copies of the same image for the same surface size are removed, along with any copy
+/// that hasn’t been made in a month.
+pub fn scaled_copy(
+    image_path: &path::Path,
+    surface: PhysicalSize<u32>,
+) -> Result<path::PathBuf, String> {
+    let modified = std::fs::metadata(image_path)
+        .and_then(|metadata| metadata.modified())
+        .map_err(|err| format!("Failed to read {}: {}", image_path.display(), err))?;
+
+    let mut hasher = DefaultHasher::new();
+    (image_path, surface.width, surface.height).hash(&mut hasher);
+    let name = format!("{}{:016x}-", COPY_PREFIX, hasher.finish());
+    modified.hash(&mut hasher);
+    let path = std::env::temp_dir().join(format!("{}{:016x}.png", name, hasher.finish()));
+    if path.is_file() {
+        return Ok(path);
+    }
+    remove_old_copies(&name);
+
+    let image = image::open(image_path)
+        .map_err(|err| format!("Failed to open {}: {}", image_path.display(), err))?;
+    let image_size = ImageSize {
+        width: image.width(),
+        height: image.height(),
+    };
+    let Some(size) = scaled_size(image_size, surface) else {
+        return Ok(image_path.to_path_buf());
+    };
+
+    log::info!(
+        "Scaling {} down to {}×{}",
+        image_path.display(),
+        size.width,
+        size.height
+    );
+    image
+        .resize_exact(
+            size.width,
+            size.height,
+            image::imageops::FilterType::Triangle,
+        )
+        .into_rgb8()
+        .save(&path)
+        .map_err(|err| format!("Failed to save {}: {}", path.display(), err))?;
+
+    Ok(path)
+}
+
+// Remove the copies of this image for this surface size, and any copy that’s too old, on a best
+// effort basis.
+fn remove_old_copies(name: &str) {
+    let Ok(entries) = std::fs::read_dir(std::env::temp_dir()) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let file_name = entry.file_name();
+        let Some(file_name) = file_name.to_str() else {
+            continue;
+        };
+        if !file_name.starts_with(COPY_PREFIX) || !file_name.ends_with(".png") {
+            continue;
+        }
+
+        let is_too_old = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age > MAX_COPY_AGE);
+        if file_name.starts_with(name) || is_too_old {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_only_scales_down_images_much_larger_than_the_surface() {
+        let surface = PhysicalSize::new(1920, 1080);
+
+        let photo = ImageSize {
+            width: 3840,
+            height: 2160,
+        };
+        assert_eq!(scaled_size(photo, surface), None);
+
+        let panorama = ImageSize {
+            width: 20000,
+            height: 5000,
+        };
+        assert_eq!(
+            scaled_size(panorama, surface),
+            Some(PhysicalSize::new(8640, 2160))
+        );
+
+        // Keeps more of the image for a high-DPI monitor.
+        assert_eq!(
+            scaled_size(panorama, PhysicalSize::new(3840, 2160)),
+            Some(PhysicalSize::new(17280, 4320))
+        );
+    }
+
+    #[test]
+    fn it_scales_copies_by_the_size_of_the_image_itself() {
+        let dir = std::env::temp_dir().join(format!("flux-image-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let image_path = dir.join("banner.png");
+        image::RgbImage::new(2000, 400).save(&image_path).unwrap();
+
+        let surface = PhysicalSize::new(50, 50);
+        let copy = scaled_copy(&image_path, surface).unwrap();
+        assert_eq!(image::image_dimensions(&copy).unwrap(), (500, 100));
+
+        // Small enough to use as it is.
+        image::RgbImage::new(80, 80).save(&image_path).unwrap();
+        assert_eq!(scaled_copy(&image_path, surface).unwrap(), image_path);
+        assert!(!copy.exists());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,33 @@
+//! The parts of the Flux screensaver that aren’t specific to Flux, for other screensavers to
+//! build on.
+//!
+//! - [`config`]: the settings file, with the monitor layout, the Windows options, and the
+//!   migrations from older versions.
+//! - [`surface`]: lays out the surfaces to draw on across the monitors, spanning or filling them
+//!   as asked.
+//! - [`winit_compat`]: the monitors and windows, the same whether they come from SDL or winit.
+//! - [`platform`]: the Windows plumbing, like the DXGI swapchain, the wallpaper window behind the
+//!   desktop icons, display power, and idle detection.
+//! - [`power`]: throttles rendering on battery and while the user is busy.
+//!
+//! The library stops at the plumbing. The windows, the render threads, the instances, and the
+//! event loops that drive them stay in the `Flux` binary, since they’re built around Flux itself:
+//! its settings, its fades and cross-fades, and its frame pacing. Another screensaver brings a
+//! loop of its own, and lays out its windows with [`surface`] and [`winit_compat`].
+//!
+//! The library still shares its package with the binary, and so its dependencies. It pulls in Flux
+//! itself, which [`config::FluxSettings`] maps onto, and SDL with the default `sdl` feature. Build
+//! with `default-features = false` to leave out SDL and the settings window’s dependencies.
+
+pub mod config;
+pub mod gpu;
+pub mod gradient;
+pub mod image_scale;
+pub mod mask;
+pub mod platform;
+pub mod power;
+pub mod spotlight;
+pub mod surface;
+#[cfg(windows)]
+pub mod wallpaper;
+pub mod winit_compat;
@@ -0,0 +1,403 @@
+use crate::config::{Config, LoggingConfig, ModuleLogLevel};
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path;
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{LevelFilter, Log, Metadata, Record};
+use serde::Serialize;
+
+// The current log file is flux_screensaver.log, followed by flux_screensaver.1.log and so on.
+const LOG_FILE_STEM: &str = "flux_screensaver";
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+/// Start logging to the terminal, and to a file in the log directory. Everything logs at the
+/// default levels until [`configure`] picks up the settings.
+///
+/// The instance for an isolated monitor, given the surface it runs on, logs to a file of its own,
+/// like flux_screensaver_surface1.log. Windows won’t rotate a log file that another process has
+/// open.
+pub fn init(optional_log_dir: Option<&path::Path>, instance_surface: Option<usize>) {
+    let logger = LOGGER.get_or_init(|| {
+        let defaults = Config::default();
+        let filter = Filter::new(
+            defaults.log_level.to_level_filter(),
+            &defaults.logging.modules,
+        );
+        let stem = match instance_surface {
+            Some(surface) => format!("{}_surface{}", LOG_FILE_STEM, surface),
+            None => LOG_FILE_STEM.to_string(),
+        };
+        let file =
+            optional_log_dir.and_then(|log_dir| LogFile::open(log_dir, &stem, &defaults.logging));
+        Logger {
+            state: Mutex::new(State { filter, file }),
+        }
+    });
+
+    if log::set_logger(logger).is_ok() {
+        log::set_max_level(logger.lock().filter.max_level());
+    }
+    log_panics::init();
+}
+
+/// Switch over to the log levels and the log file settings from the config.
+pub fn configure(config: &Config) {
+    let Some(logger) = LOGGER.get() else {
+        return;
+    };
+
+    let mut state = logger.lock();
+    state.filter = Filter::new(config.log_level.to_level_filter(), &config.logging.modules);
+    if let Some(file) = &mut state.file {
+        file.configure(&config.logging);
+    }
+    log::set_max_level(state.filter.max_level());
+}
+
+struct Logger {
+    state: Mutex<State>,
+}
+
+struct State {
+    filter: Filter,
+    file: Option<LogFile>,
+}
+
+impl Logger {
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        // Keep logging after a panic, which is when the log matters most.
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.lock().filter.level_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        // Format the message before taking the lock, in case anything in it logs too.
+        let time = format_time(SystemTime::now());
+        let message = record.args().to_string();
+        let text = format!(
+            "{} [{}] {}: {}\n",
+            time,
+            record.level(),
+            record.target(),
+            message
+        );
+        let _ = io::stderr().write_all(text.as_bytes());
+
+        if let Some(file) = &mut self.lock().file {
+            let line = if file.json {
+                let json = JsonRecord {
+                    time: &time,
+                    level: record.level().as_str(),
+                    target: record.target(),
+                    message: &message,
+                    file: record.file(),
+                    line: record.line(),
+                    pid: std::process::id(),
+                };
+                serde_json::to_string(&json).expect("the log record serializes to JSON") + "\n"
+            } else {
+                text
+            };
+            file.write(&line);
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(file) = &mut self.lock().file {
+            if let Some(handle) = &mut file.handle {
+                let _ = handle.flush();
+            }
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonRecord<'a> {
+    time: &'a str,
+    level: &'a str,
+    target: &'a str,
+    message: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<u32>,
+    // Each monitor runs in a process of its own when they’re isolated.
+    pid: u32,
+}
+
+/// The log level for each module, going by the longest module path that matches.
+#[derive(Debug)]
+struct Filter {
+    default: LevelFilter,
+    // Longest first
+    modules: Vec<(String, LevelFilter)>,
+}
+
+impl Filter {
+    fn new(default: LevelFilter, modules: &BTreeMap<String, ModuleLogLevel>) -> Self {
+        let mut modules: Vec<(String, LevelFilter)> = modules
+            .iter()
+            .map(|(module, level)| (module.clone(), level.0.to_level_filter()))
+            .collect();
+        modules.sort_by_key(|(module, _)| std::cmp::Reverse(module.len()));
+        Self { default, modules }
+    }
+
+    fn level_for(&self, target: &str) -> LevelFilter {
+        self.modules
+            .iter()
+            .find(|(module, _)| {
+                target
+                    .strip_prefix(module.as_str())
+                    .map_or(false, |rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    // Lets the log macros skip anything that no module would log.
+    fn max_level(&self) -> LevelFilter {
+        self.modules
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, Ord::max)
+    }
+}
+
+struct LogFile {
+    log_dir: path::PathBuf,
+    // The name of the current file, without the extension
+    stem: String,
+    handle: Option<fs::File>,
+    // How much has been written to the current file
+    size: u64,
+    json: bool,
+    max_size: u64,
+    max_old_files: u32,
+    // Cleared once the current file can’t be moved out of the way, after which it keeps growing
+    can_rotate: bool,
+}
+
+impl LogFile {
+    fn open(log_dir: &path::Path, stem: &str, config: &LoggingConfig) -> Option<Self> {
+        if let Err(err) = fs::create_dir_all(log_dir) {
+            eprintln!(
+                "Failed to create the log directory at {}: {}",
+                log_dir.display(),
+                err
+            );
+            return None;
+        }
+
+        let mut file = Self {
+            log_dir: log_dir.to_owned(),
+            stem: stem.to_string(),
+            handle: None,
+            size: 0,
+            json: false,
+            max_size: 0,
+            max_old_files: 0,
+            can_rotate: true,
+        };
+        file.configure(config);
+        file.reopen();
+        Some(file)
+    }
+
+    fn configure(&mut self, config: &LoggingConfig) {
+        self.json = config.json;
+        self.max_size = u64::from(config.max_file_size_kb) * 1024;
+        self.max_old_files = config.max_old_files;
+    }
+
+    fn write(&mut self, line: &str) {
+        let len = line.len() as u64;
+        // A line bigger than the cap still gets a file to itself.
+        if self.can_rotate && self.max_size > 0 && self.size > 0 && self.size + len > self.max_size
+        {
+            self.rotate();
+        }
+
+        if let Some(handle) = &mut self.handle {
+            if handle.write_all(line.as_bytes()).is_ok() {
+                self.size += len;
+            }
+        }
+    }
+
+    // Shift each of the older files along by one, dropping the oldest, and start a new file.
+    //
+    // The current file is moved aside first, and the older files are only touched once that
+    // works. Windows won’t move a file that another process still has open, like another Flux
+    // sharing the log directory. The file then keeps growing, rather than the older files being
+    // dropped on every write that follows.
+    fn rotate(&mut self) {
+        // Windows won’t rename a file that’s still open.
+        self.handle = None;
+
+        let rotating = self.log_dir.join(format!("{}.rotating.log", self.stem));
+        match fs::rename(self.path(0), &rotating) {
+            Ok(()) if self.max_old_files == 0 => {
+                let _ = fs::remove_file(&rotating);
+            }
+            Ok(()) => {
+                let _ = fs::remove_file(self.path(self.max_old_files));
+                for index in (1..self.max_old_files).rev() {
+                    let _ = fs::rename(self.path(index), self.path(index + 1));
+                }
+                let _ = fs::rename(&rotating, self.path(1));
+            }
+            Err(err) => {
+                eprintln!(
+                    "Failed to start a new log file, so {} will keep growing: {}",
+                    self.path(0).display(),
+                    err
+                );
+                self.can_rotate = false;
+            }
+        }
+
+        self.reopen();
+    }
+
+    fn reopen(&mut self) {
+        let path = self.path(0);
+        match fs::OpenOptions::new().append(true).create(true).open(&path) {
+            Ok(handle) => {
+                self.size = handle.metadata().map_or(0, |metadata| metadata.len());
+                self.handle = Some(handle);
+            }
+            Err(err) => {
+                eprintln!("Failed to open the log file at {}: {}", path.display(), err);
+                self.handle = None;
+            }
+        }
+    }
+
+    fn path(&self, index: u32) -> path::PathBuf {
+        if index == 0 {
+            self.log_dir.join(format!("{}.log", self.stem))
+        } else {
+            self.log_dir.join(format!("{}.{}.log", self.stem, index))
+        }
+    }
+}
+
+// The time in UTC, in RFC 3339 format, down to the millisecond.
+fn format_time(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    let secs_of_day = secs % 86400;
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// The calendar date for a number of days since the Unix epoch, after Howard Hinnant’s
+/// `civil_from_days`.
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use log::Level;
+    use std::time::Duration;
+
+    #[test]
+    fn it_uses_the_longest_matching_module_path() {
+        let modules = BTreeMap::from([
+            ("Flux".to_string(), ModuleLogLevel(Level::Info)),
+            (
+                "Flux::render_thread".to_string(),
+                ModuleLogLevel(Level::Trace),
+            ),
+            ("glutin".to_string(), ModuleLogLevel(Level::Error)),
+        ]);
+        let filter = Filter::new(LevelFilter::Warn, &modules);
+
+        assert_eq!(filter.level_for("Flux::render_thread"), LevelFilter::Trace);
+        assert_eq!(
+            filter.level_for("Flux::render_thread::gl"),
+            LevelFilter::Trace
+        );
+        assert_eq!(filter.level_for("Flux::stats"), LevelFilter::Info);
+        assert_eq!(filter.level_for("glutin::api::wgl"), LevelFilter::Error);
+        // Only whole path segments match.
+        assert_eq!(filter.level_for("glutin_winit"), LevelFilter::Warn);
+        assert_eq!(filter.max_level(), LevelFilter::Trace);
+    }
+
+    #[test]
+    fn it_shifts_the_older_files_along_when_rotating() {
+        let dir = std::env::temp_dir().join(format!("flux-logging-test-{}", std::process::id()));
+        let config = LoggingConfig {
+            json: false,
+            max_file_size_kb: 1,
+            max_old_files: 2,
+            ..Default::default()
+        };
+        let mut file = LogFile::open(&dir, "flux_test", &config).unwrap();
+        let line = |c| format!("{}\n", c).repeat(400);
+        for c in ['a', 'b', 'c', 'd'] {
+            file.write(&line(c));
+        }
+        drop(file);
+
+        let read = |name| fs::read_to_string(dir.join(name)).unwrap_or_default();
+        assert_eq!(read("flux_test.log"), line('d'));
+        assert_eq!(read("flux_test.1.log"), line('c'));
+        assert_eq!(read("flux_test.2.log"), line('b'));
+        assert!(!dir.join("flux_test.3.log").exists());
+        assert!(!dir.join("flux_test.rotating.log").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn it_formats_the_time_in_utc() {
+        assert_eq!(format_time(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        assert_eq!(
+            format_time(UNIX_EPOCH + Duration::from_millis(1_709_210_096_789)),
+            "2024-02-29T12:34:56.789Z"
+        );
+    }
+}
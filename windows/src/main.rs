@@ -1,50 +1,107 @@
 // Disable the console window that pops up when you launch the .exe
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+#[cfg(not(any(feature = "sdl", feature = "winit-backend")))]
+compile_error!("Build with either the `sdl` or the `winit-backend` feature to open windows.");
+
+#[cfg(feature = "sdl")]
+mod audio;
+#[cfg(all(windows, feature = "sdl"))]
+mod benchmark;
+#[cfg(feature = "sdl")]
+mod blanking;
+#[cfg(feature = "sdl")]
+mod calibration;
 mod cli;
-mod config;
+mod clock;
+#[cfg(feature = "sdl")]
+mod command_palette;
+mod compositor;
+#[cfg(feature = "sdl")]
+mod coordinator;
+#[cfg(windows)]
+mod daemon;
+#[cfg(feature = "sdl")]
+mod gamepad;
 mod gl_context;
-mod platform;
+mod hud;
+mod logging;
+mod offscreen;
+mod overlay;
+#[cfg(all(windows, feature = "sdl"))]
+mod palette;
+#[cfg(feature = "sdl")]
+mod ping;
+#[cfg(feature = "sdl")]
+mod playlist;
+#[cfg(feature = "sdl")]
+mod record;
+mod render_thread;
+#[cfg(windows)]
+mod service;
+#[cfg(feature = "sdl")]
 mod settings_window;
-mod surface;
+mod shuffle;
+#[cfg(all(windows, feature = "sdl"))]
+mod soak;
+mod stats;
+mod watchdog;
+// Opens the windows when SDL is left out of the build
+#[cfg(all(feature = "winit-backend", not(feature = "sdl")))]
+mod winit_backend;
+// For hosts with a winit event loop of their own, and for builds without SDL. Only the winit
+// backend drives it so far, since hosts outside this binary can’t reach it yet.
+#[cfg(feature = "winit-host")]
+#[cfg_attr(
+    not(all(feature = "winit-backend", not(feature = "sdl"))),
+    allow(dead_code)
+)]
+mod winit_host;
+
+// Shared with other screensavers, through the library
+use flux_screensaver_host::{config, gpu, gradient, surface, winit_compat};
+#[cfg(feature = "sdl")]
+use flux_screensaver_host::{mask, power, spotlight};
 #[cfg(windows)]
-mod wallpaper;
-mod winit_compat;
+use flux_screensaver_host::{platform, wallpaper};
 
 use cli::Mode;
-use config::Config;
+use config::{Config, Presets};
 use flux::Flux;
+#[cfg(feature = "sdl")]
+use winit_compat::HasWinitWindow;
 use winit_compat::{HasMonitors, MonitorHandle};
 
+#[cfg(feature = "sdl")]
+use std::cell::RefCell;
+#[cfg(feature = "sdl")]
 use std::collections::HashMap;
-use std::{fs, path, process, rc::Rc};
-
-use glutin::context::PossiblyCurrentGlContext;
-use glutin::prelude::GlSurface;
+#[cfg(feature = "sdl")]
+use std::time::{Duration, Instant};
+use std::{path, process, rc::Rc};
 
-use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle, RawWindowHandle};
+#[cfg(all(windows, feature = "sdl"))]
+use raw_window_handle::RawWindowHandle;
+#[cfg(feature = "sdl")]
+use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
 
+#[cfg(feature = "sdl")]
 use sdl2::video::Window;
 
-#[cfg(windows)]
-use glow as GL;
-#[cfg(windows)]
-use glow::HasContext;
-#[cfg(windows)]
+#[cfg(all(windows, feature = "sdl"))]
 use windows::Win32::Foundation::HWND;
-#[cfg(windows)]
-use winit::dpi::PhysicalSize;
-#[cfg(windows)]
-use winit_compat::HasWinitWindow;
+use winit::dpi::{PhysicalPosition, PhysicalSize};
 
+// These hybrid graphics hints only exist for x86 drivers.
+//
 // http://developer.download.nvidia.com/devzone/devcenter/gamegraphics/files/OptimusRenderingPolicies.pdf
-#[cfg(target_os = "windows")]
+#[cfg(all(target_os = "windows", not(target_arch = "aarch64")))]
 #[allow(non_upper_case_globals)]
 #[no_mangle]
 pub static mut NvOptimusEnablement: i32 = 1;
 
 // https://gpuopen.com/learn/amdpowerxpressrequesthighperformance/
-#[cfg(target_os = "windows")]
+#[cfg(all(target_os = "windows", not(target_arch = "aarch64")))]
 #[allow(non_upper_case_globals)]
 #[no_mangle]
 pub static mut AmdPowerXpressRequestHighPerformance: i32 = 1;
@@ -52,60 +109,420 @@ pub static mut AmdPowerXpressRequestHighPerformance: i32 = 1;
 // Higher values will make the screensaver tolerate more mouse movement before exiting.
 const MINIMUM_MOUSE_MOTION_TO_EXIT_SCREENSAVER: f64 = 10.0;
 
+// The initial size of the window in windowed mode.
+const WINDOWED_SIZE: PhysicalSize<u32> = PhysicalSize::new(1280, 720);
+
+// How often to check whether the window is covered by other windows, and how long to wait for
+// events while paused.
+#[cfg(feature = "sdl")]
+const OCCLUSION_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// How long to let the monitors settle after one is connected or disconnected, before laying out
+// the surfaces again. Docking a laptop fires a burst of display events.
+#[cfg(feature = "sdl")]
+const DISPLAY_CHANGE_SETTLE_TIME: Duration = Duration::from_millis(750);
+
+// How long to wait for events when every instance is paused.
+#[cfg(feature = "sdl")]
+const PAUSED_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// How often to check whether the screensaver preview pane has changed size.
+#[cfg(feature = "sdl")]
+const PREVIEW_RESIZE_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+// How often to check whether the desktop icons have been rearranged.
+#[cfg(all(windows, feature = "sdl"))]
+const DESKTOP_ICONS_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+// How often to check whether the user is presenting or running something full screen.
+#[cfg(all(windows, feature = "sdl"))]
+const DO_NOT_DISTURB_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+// How often a screensaver on standby checks in with the daemon, and clears out its window messages
+#[cfg(all(windows, feature = "sdl"))]
+const STANDBY_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// The size of the screenshots the adaptive palette is taken from. A palette needs little detail.
+#[cfg(all(windows, feature = "sdl"))]
+const PALETTE_CAPTURE_SIDE: u32 = 64;
+
+// How long to fade over to the colors of the adaptive palette, slow enough to go unnoticed
+#[cfg(all(windows, feature = "sdl"))]
+const PALETTE_CROSSFADE: Duration = Duration::from_secs(10);
+
+#[cfg(all(windows, feature = "sdl"))]
+thread_local! {
+    // The thread that opens the gates for the held frames on the vertical blank that the
+    // presents on every monitor are lined up with. Started the first time it’s needed, and again
+    // after the monitors change, or `Some(None)` if the vertical blank can’t be waited on.
+    static PRIMARY_VBLANK: std::cell::RefCell<
+        Option<Option<std::sync::mpsc::Sender<render_thread::StartGate>>>,
+    > = std::cell::RefCell::new(None);
+}
+
+thread_local! {
+    // Set once a window spanning several monitors fails to come up, after which every monitor
+    // gets a window of its own, including after the monitors change
+    static IS_SPAN_DOWNGRADED: std::cell::Cell<bool> = std::cell::Cell::new(false);
+}
+
+#[cfg(feature = "sdl")]
 type WindowId = u32;
 
-#[allow(dead_code)]
+#[cfg(feature = "sdl")]
 struct Instance {
-    flux: Flux,
+    // Declared before the window, so that the render thread exits before the window goes away
+    renderer: render_thread::RenderThread,
     window: Window,
-    gl_context: gl_context::GLContext,
-    swapchain: Swapchain,
+    // The GPU that the instance renders with
+    gpu: String,
+    // Position of the instance on the virtual screen
+    position: PhysicalPosition<i32>,
+    // The surface the instance was created for, to match it up with the new layout when the
+    // monitors change
+    surface: Option<surface::Surface>,
+    // Overrides the window’s scale factor to match the physical density of the monitors
+    physical_scale_factor: Option<f64>,
+    // The image assigned to the monitor, which the colors come from instead of the color mode
+    color_image: Option<path::PathBuf>,
+    // How long to wait after the windows are shown before starting the animation
+    start_delay: Duration,
+    // Drives the animation independently of the other instances
+    clock: clock::Clock,
+    // Windows are kept hidden until the first frame is rendered
+    shown: bool,
+    // The minimum time between frames, if the frame rate is capped
+    frame_interval: Option<Duration>,
+    last_frame: Option<Instant>,
+    // When the frame that’s being rendered was started
+    render_started: Option<Instant>,
+    // Holds back rendering to save power on battery
+    throttle: power::Throttle,
+    // Frame timings for the stats overlay
+    stats: stats::Stats,
+    // Sleeps precisely enough to pace frames, for as long as the frame rate is capped
+    #[cfg(windows)]
+    timer_resolution: Option<platform::windows::timer::HighResolutionTimer>,
+    // Holds frames back to present them together with the other monitors
+    sync_presents: bool,
+    // Keeps looking for the wallpaper, if it wasn’t available at startup
+    #[cfg(windows)]
+    wallpaper_requery: Option<wallpaper::Requery>,
+    // Follows the wallpaper slideshow, if there is one
+    #[cfg(windows)]
+    slideshow: Option<wallpaper::SlideshowWatch>,
+    // Takes the colors from what’s on the monitor, if the adaptive palette is on
+    #[cfg(windows)]
+    adaptive_palette: Option<palette::Adaptive>,
 }
 
-enum Swapchain {
-    Gl,
+#[cfg(feature = "sdl")]
+impl Instance {
+    // Render the window on a thread of its own, and wrap it up into an instance.
+    fn new(
+        window: Window,
+        config: &Config,
+        handles: render_thread::Handles,
+        options: render_thread::Options,
+    ) -> Result<Self, String> {
+        let (renderer, ready) = render_thread::RenderThread::spawn(config, handles, options)?;
+
+        let mut instance = Self {
+            renderer,
+            window,
+            gpu: ready.gpu,
+            position: PhysicalPosition::new(0, 0),
+            surface: None,
+            physical_scale_factor: None,
+            color_image: None,
+            start_delay: Duration::ZERO,
+            clock: clock::Clock::new(Instant::now()),
+            shown: false,
+            frame_interval: ready.frame_interval,
+            last_frame: None,
+            render_started: None,
+            throttle: power::Throttle::None,
+            stats: stats::Stats::default(),
+            #[cfg(windows)]
+            timer_resolution: None,
+            sync_presents: config.platform.windows.sync_presents && ready.can_hold_frames,
+            #[cfg(windows)]
+            wallpaper_requery: None,
+            #[cfg(windows)]
+            slideshow: None,
+            #[cfg(windows)]
+            adaptive_palette: None,
+        };
+        instance.update_timer_resolution();
+
+        Ok(instance)
+    }
+
+    pub fn draw(&mut self, timestamp: f64) -> Result<(), String> {
+        self.start_frame(Some(timestamp), false, None);
+        self.finish_frame()
+    }
+
+    // Start rendering the next frame on the render thread, without waiting for it. Until the
+    // animation starts, there’s only a black frame to present. With `hold`, the frame waits for
+    // `present_held`. With a `gate`, the render thread only starts on the frame once it opens.
+    pub fn start_frame(
+        &mut self,
+        timestamp: Option<f64>,
+        hold: bool,
+        gate: Option<&render_thread::StartGate>,
+    ) {
+        self.render_started = Some(Instant::now());
+        let command = match timestamp {
+            Some(timestamp) if hold => render_thread::Command::DrawHeld(timestamp),
+            Some(timestamp) => render_thread::Command::Draw(timestamp),
+            None => render_thread::Command::Clear,
+        };
+        self.renderer.send(match gate {
+            Some(gate) => render_thread::Command::Gated(gate.clone(), Box::new(command)),
+            None => command,
+        });
+    }
+
+    // Present the frame held back by `start_frame` once the gate opens, without waiting for it.
+    pub fn present_held(&mut self, gate: &render_thread::StartGate) {
+        self.renderer.send(render_thread::Command::Gated(
+            gate.clone(),
+            Box::new(render_thread::Command::Present),
+        ));
+    }
+
+    // Wait for the frame started last to be presented.
+    pub fn finish_frame(&mut self) -> Result<(), String> {
+        let result = self.renderer.wait();
+        if result.is_ok() {
+            let now = Instant::now();
+            self.stats.record_frame(now);
+            if let Some(render_started) = self.render_started.take() {
+                self.stats
+                    .record_render_time(now.saturating_duration_since(render_started));
+            }
+        }
+
+        result
+    }
+
+    // Stop the animation and stop presenting frames, until resumed.
+    pub fn pause(&mut self, reason: stats::PauseReason) {
+        log::info!("Pausing Flux ({})", reason);
+        self.clock.pause(Instant::now());
+        self.stats.set_paused(Some(reason));
+    }
+
+    pub fn resume(&mut self) {
+        log::info!("Resuming Flux");
+        self.clock.resume(Instant::now());
+        self.stats.set_paused(None);
+    }
 
+    // Pause the animation while the user doesn’t want to be disturbed, without undoing a pause
+    // for any other reason.
     #[cfg(windows)]
-    Dxgi(platform::windows::dxgi_swapchain::DXGIInterop),
-}
+    pub fn set_do_not_disturb(&mut self, do_not_disturb: bool) {
+        match self.stats.paused() {
+            None if do_not_disturb => self.pause(stats::PauseReason::DoNotDisturb),
+            Some(stats::PauseReason::DoNotDisturb) if !do_not_disturb => self.resume(),
+            _ => (),
+        }
+    }
 
-impl Instance {
-    pub fn draw(&mut self, timestamp: f64) -> glutin::error::Result<()> {
-        match self.swapchain {
-            Swapchain::Gl => {
-                self.gl_context
-                    .context
-                    .make_current(&self.gl_context.surface)?;
+    // Throttle rendering, pausing the animation altogether if the battery is running low.
+    pub fn set_throttle(&mut self, throttle: power::Throttle) {
+        self.throttle = throttle;
+        self.update_timer_resolution();
+
+        let is_low_battery = throttle == power::Throttle::Pause;
+        match self.stats.paused() {
+            None if is_low_battery => self.pause(stats::PauseReason::LowBattery),
+            Some(stats::PauseReason::LowBattery) if !is_low_battery => self.resume(),
+            _ => (),
+        }
+    }
 
-                self.flux.animate(timestamp);
+    // The minimum time between frames, including any throttling.
+    fn min_frame_interval(&self) -> Option<Duration> {
+        match (self.frame_interval, self.throttle.frame_interval()) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        }
+    }
 
-                self.gl_context
-                    .surface
-                    .swap_buffers(&self.gl_context.context)
+    // Only raise the resolution of the system timer while there’s a frame rate to keep to, since
+    // it costs power across the whole system. Otherwise vsync paces the frames.
+    fn update_timer_resolution(&mut self) {
+        #[cfg(windows)]
+        {
+            let is_capped = self.min_frame_interval().is_some();
+            if is_capped != self.timer_resolution.is_some() {
+                self.timer_resolution =
+                    is_capped.then(platform::windows::timer::HighResolutionTimer::new);
             }
+        }
+    }
 
-            #[cfg(windows)]
-            Swapchain::Dxgi(ref mut dxgi_interop) => unsafe {
-                platform::windows::dxgi_swapchain::with_dxgi_swapchain(dxgi_interop, |fbo| {
-                    self.gl_context
-                        .context
-                        .make_current(&self.gl_context.surface)?;
+    fn scale_factor(&self) -> f64 {
+        self.physical_scale_factor
+            .unwrap_or_else(|| self.window.scale_factor())
+    }
 
-                    self.flux.compute(timestamp);
+    // Draw text over the animation, or stop drawing it.
+    pub fn set_hud(&mut self, text: Option<String>) -> Result<(), String> {
+        self.renderer.run(render_thread::Command::SetHud(text))
+    }
 
-                    self.gl_context
-                        .gl
-                        .bind_framebuffer(GL::FRAMEBUFFER, Some(*fbo));
+    // Draw the stats over the animation.
+    pub fn set_stats(&mut self, stats: String) -> Result<(), String> {
+        self.renderer.run(render_thread::Command::SetStats(stats))
+    }
 
-                    self.flux.render();
+    // Fade out as the screensaver exits, from a brightness of 1.0 down to 0.0. Fading to the
+    // desktop makes the window itself see-through, if the window manager allows it.
+    pub fn fade_out(&mut self, style: config::ExitStyle, brightness: f32) -> Result<(), String> {
+        match style {
+            config::ExitStyle::None => Ok(()),
+            config::ExitStyle::FadeToBlack => self
+                .renderer
+                .run(render_thread::Command::FadeOut(brightness)),
+            config::ExitStyle::FadeToDesktop => self.window.set_opacity(brightness),
+        }
+    }
 
-                    self.gl_context.gl.bind_framebuffer(GL::FRAMEBUFFER, None);
-                    self.gl_context.gl.finish();
+    // Clear out the frames left over from before the display went into standby.
+    #[cfg(windows)]
+    pub fn wake(&mut self) -> Result<(), String> {
+        self.renderer.run(render_thread::Command::Wake)
+    }
 
-                    Ok(())
-                })
-            },
+    // Save the frame as it is now to an image file.
+    pub fn screenshot(&mut self, path: &path::Path) -> Result<(), String> {
+        self.renderer
+            .run(render_thread::Command::Screenshot(path.to_path_buf()))
+    }
+
+    // Restart the simulation, to pick up new settings.
+    pub fn restart_flux(&mut self, config: &Config) -> Result<(), String> {
+        self.renderer.run(render_thread::Command::Restart {
+            config: Box::new(self.instance_config(config)),
+            scale_factor: self.scale_factor(),
+        })
+    }
+
+    // Fade over to a simulation with new settings.
+    pub fn crossfade_to(&mut self, config: &Config, duration: Duration) -> Result<(), String> {
+        self.renderer.run(render_thread::Command::Crossfade {
+            config: Box::new(self.instance_config(config)),
+            duration,
+            scale_factor: self.scale_factor(),
+        })
+    }
+
+    // Fade over to colors from a different wallpaper.
+    #[cfg(windows)]
+    pub fn set_wallpaper(&mut self, config: &Config, wallpaper: &path::Path) -> Result<(), String> {
+        self.renderer.run(render_thread::Command::SetWallpaper {
+            config: Box::new(self.instance_config(config)),
+            wallpaper: wallpaper.to_path_buf(),
+            scale_factor: self.scale_factor(),
+        })
+    }
+
+    // The settings for this instance, with the colors from its own image, if it has one, or from
+    // the adaptive palette.
+    fn instance_config(&self, config: &Config) -> Config {
+        if let Some(image_path) = &self.color_image {
+            return config.with_color_image(image_path);
+        }
+
+        #[cfg(windows)]
+        if let Some(image_path) = self
+            .adaptive_palette
+            .as_ref()
+            .and_then(palette::Adaptive::image_file)
+        {
+            return config.with_color_image(image_path);
+        }
+
+        config.clone()
+    }
+
+    // Check whether enough time has passed since the last frame to render another one.
+    pub fn is_frame_due(&mut self, now: Instant) -> bool {
+        let (Some(interval), Some(last_frame)) = (self.min_frame_interval(), self.last_frame)
+        else {
+            self.last_frame = Some(now);
+            return true;
+        };
+
+        let since_last_frame = now.duration_since(last_frame);
+        if since_last_frame < interval {
+            return false;
         }
+
+        // Schedule frames at a steady cadence, so that waking up late doesn’t push back every
+        // frame that follows. Start over after a stall, instead of rushing to catch up.
+        self.last_frame = Some(if since_last_frame < 2 * interval {
+            last_frame + interval
+        } else {
+            now
+        });
+
+        true
+    }
+
+    // When the next frame is due, if the frame rate is capped.
+    pub fn next_frame_at(&self) -> Option<Instant> {
+        self.min_frame_interval()
+            .zip(self.last_frame)
+            .map(|(interval, last_frame)| last_frame + interval)
+    }
+
+    // Resize the surface and the simulation, for windows that are sized by someone else.
+    pub fn resize(&mut self, size: PhysicalSize<u32>) -> Result<(), String> {
+        self.renderer.run(render_thread::Command::Resize {
+            size,
+            scale_factor: self.scale_factor(),
+        })
+    }
+
+    // Resize the surface and the simulation to match the window, if the window has changed size.
+    pub fn fit_to_window(&mut self) -> Result<(), String> {
+        self.renderer.run(render_thread::Command::Resize {
+            size: self.window.inner_size(),
+            scale_factor: self.scale_factor(),
+        })
+    }
+
+    // Show the window. Render at least one frame beforehand to avoid flashing an empty window
+    // while the first, much slower, frame is being drawn.
+    pub fn show(&mut self) {
+        self.window.show();
+        self.shown = true;
+    }
+
+    // Dim the given regions of the screen, along with the middle for edge lighting, if the
+    // instance has a compositor to do so. With `motion` below 1.0, the motion in the regions is
+    // calmed too, and the motion in the middle is calmed as much as it’s dimmed.
+    #[cfg(windows)]
+    pub fn set_mask(
+        &mut self,
+        regions: &[mask::Region],
+        brightness: f32,
+        motion: f32,
+        layers: &mask::Layers,
+    ) -> Result<(), String> {
+        let size = self.window.size().into();
+        let brightness = mask::Mask::from_regions(self.position, size, regions, brightness);
+        let brightness = layers.apply(brightness, self.position, size);
+        let motion = (motion < 1.0 && !regions.is_empty())
+            .then(|| mask::Mask::from_regions(self.position, size, regions, motion));
+        let motion = layers.apply_motion(motion, size);
+        self.renderer
+            .run(render_thread::Command::SetMask { brightness, motion })
     }
 }
 
@@ -113,22 +530,155 @@ fn main() {
     let project_dirs = directories::ProjectDirs::from("me", "sandydoo", "Flux");
     let log_dir = project_dirs.as_ref().map(|dirs| dirs.data_local_dir());
     let config_dir = project_dirs.as_ref().map(|dirs| dirs.preference_dir());
+    // Why the screensaver exited each time, next to the log
+    let exit_log = log_dir.map(|dir| dir.join("exits.jsonl"));
 
-    init_logging(log_dir);
+    let instance_surface = match cli::read_flags() {
+        Ok(Mode::Instance { surface }) => Some(surface),
+        _ => None,
+    };
+    logging::init(log_dir, instance_surface);
+    // Save a crash report next to the log
+    #[cfg(windows)]
+    if let Some(log_dir) = log_dir {
+        platform::windows::crash::install(log_dir);
+    }
 
-    let config = Config::load(config_dir);
+    let mut config = Config::load(config_dir);
+    logging::configure(&config);
+    #[cfg(windows)]
+    platform::windows::crash::set_show_dialog(config.platform.windows.crash_dialog);
 
     let res = cli::read_flags().and_then(|mode| {
         if mode == Mode::Settings {
-            settings_window::run(config)
-                .map_err(|err| log::error!("{}", err))
-                .unwrap();
+            #[cfg(not(feature = "sdl"))]
+            return Err(
+                "The settings window needs SDL, which this build leaves out. Change the settings \
+                 with `config set` instead."
+                    .to_string(),
+            );
+
+            #[cfg(feature = "sdl")]
+            {
+                settings_window::run(config)
+                    .map_err(|err| log::error!("{}", err))
+                    .unwrap();
+                return Ok(());
+            }
+        }
+
+        let change = match &mode {
+            // `--set` has always dropped what it doesn’t recognize, which deployment scripts
+            // written for newer releases count on.
+            Mode::SetConfig(overrides) => Some(config.apply_overrides(overrides)),
+            Mode::SetConfigStrictly(settings) => Some(config.set(settings)),
+            Mode::ResetConfig(key) => Some(config.reset(key.as_deref())),
+            Mode::Shuffle => {
+                config.flux.simulation =
+                    shuffle::shuffle(&config.flux.simulation, shuffle::new_seed());
+                Some(Ok(()))
+            }
+            _ => None,
+        };
+        if let Some(change) = change {
+            change
+                .and_then(|_| config.save())
+                .map_err(|err| err.to_string())?;
+
+            #[cfg(windows)]
+            platform::windows::autostart::set_live_wallpaper_at_login(
+                config.platform.windows.live_wallpaper.launch_at_login,
+            )?;
+
+            return Ok(());
+        }
+
+        if mode == Mode::ConfigSchema {
+            #[cfg(windows)]
+            platform::windows::console::attach();
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&Config::schema()).unwrap()
+            );
+            return Ok(());
+        }
+
+        // The settings as saved, before any overrides from the environment
+        if let Mode::GetConfig(key) = &mode {
+            let value = config.get(key).map_err(|err| err.to_string())?;
+
+            #[cfg(windows)]
+            platform::windows::console::attach();
+            println!("{}", serde_json::to_string_pretty(&value).unwrap());
+            return Ok(());
+        }
+
+        let env_overrides = Config::env_overrides();
+        apply_launch_overrides(&mut config)?;
+        logging::configure(&config);
+
+        if watchdog::is_safe_mode() {
+            log::warn!("Starting in safe mode, since the last try stalled");
+        }
+
+        if cli::read_stats() {
+            config.platform.windows.show_stats = true;
+        }
+
+        if let Mode::PrintConfig(cli_overrides) = &mode {
+            config
+                .apply_overrides(cli_overrides)
+                .map_err(|err| err.to_string())?;
+
+            #[cfg(windows)]
+            platform::windows::console::attach();
+            let description = config.describe(&env_overrides, cli_overrides);
+            println!("{}", serde_json::to_string_pretty(&description).unwrap());
             return Ok(());
         }
 
-        run_flux(mode, config)
+        #[cfg(windows)]
+        if mode == Mode::Daemon {
+            return daemon::run(&config);
+        }
+
+        #[cfg(windows)]
+        match &mode {
+            Mode::Service(options) => return service::run(options.clone()),
+            Mode::InstallService(options) => {
+                platform::windows::console::attach();
+                return service::install(options);
+            }
+            Mode::UninstallService => {
+                platform::windows::console::attach();
+                return service::uninstall();
+            }
+            _ => (),
+        }
+
+        #[cfg(feature = "sdl")]
+        if let Mode::Record { seconds, output } = &mode {
+            #[cfg(windows)]
+            platform::windows::console::attach();
+            return record::run(&config, *seconds, output);
+        }
+
+        // Put the wallpaper back after a live wallpaper crashed, without waiting for it to be
+        // started again. The live wallpaper checks for itself, before it replaces the backup.
+        #[cfg(windows)]
+        if !matches!(mode, Mode::Wallpaper) {
+            if let Some(wallpaper_backup) = wallpaper_backup_path(exit_log.as_deref()) {
+                wallpaper::restore_after_crash(&wallpaper_backup);
+            }
+        }
+
+        run_flux(mode, config, exit_log.as_deref())
     });
 
+    // The settings window draws a new image with every change to a gradient, so the temp
+    // directory would fill up with them otherwise.
+    gradient::remove_image_files();
+
     match res {
         Ok(_) => process::exit(0),
         Err(err) => {
@@ -138,42 +688,32 @@ fn main() {
     };
 }
 
-fn init_logging(optional_log_dir: Option<&path::Path>) {
-    use simplelog::*;
-
-    let mut loggers: Vec<Box<dyn SharedLogger>> = vec![TermLogger::new(
-        LevelFilter::Warn,
-        Config::default(),
-        TerminalMode::Mixed,
-        ColorChoice::Auto,
-    )];
-
-    if let Some(log_dir) = optional_log_dir {
-        let maybe_log_file = {
-            fs::create_dir_all(log_dir).unwrap();
-            let log_path = log_dir.join("flux_screensaver.log");
-            fs::OpenOptions::new()
-                .append(true)
-                .create(true)
-                .open(log_path)
-        };
+// Where the live wallpaper backs up the wallpaper settings: in the log directory, next to the
+// exit log.
+#[cfg(windows)]
+fn wallpaper_backup_path(exit_log: Option<&path::Path>) -> Option<path::PathBuf> {
+    exit_log
+        .and_then(path::Path::parent)
+        .map(|dir| dir.join("wallpaper_backup.json"))
+}
 
-        if let Ok(log_file) = maybe_log_file {
-            loggers.push(WriteLogger::new(
-                LevelFilter::Warn,
-                Config::default(),
-                log_file,
-            ));
+#[cfg(feature = "sdl")]
+fn run_flux(mode: Mode, mut config: Config, exit_log: Option<&path::Path>) -> Result<(), String> {
+    #[cfg(windows)]
+    if platform::windows::wine::is_wine() {
+        log::info!("Running under Wine");
+        platform::windows::wine::set_sdl_hints();
+
+        // Wine’s DPI support varies between versions, so carry on without it.
+        if let Err(err) = platform::windows::dpi_awareness::set_dpi_awareness() {
+            log::warn!("{}", err);
         }
+    } else {
+        platform::windows::dpi_awareness::set_dpi_awareness()?;
     }
 
-    let _ = CombinedLogger::init(loggers);
-    log_panics::init();
-}
-
-fn run_flux(mode: Mode, config: Config) -> Result<(), String> {
     #[cfg(windows)]
-    platform::windows::dpi_awareness::set_dpi_awareness()?;
+    apply_gpu_preference(&config);
 
     // By default, SDL disables the screensaver and doesn’t allow the display to sleep. We want
     // both of these things to happen in both screensaver and preview modes.
@@ -186,73 +726,287 @@ fn run_flux(mode: Mode, config: Config) -> Result<(), String> {
         #[cfg(windows)]
         Mode::Preview(raw_window_handle) => {
             let mut instance = new_preview_window(&video_subsystem, raw_window_handle, &config)?;
-            let start = std::time::Instant::now();
             let mut event_pump = sdl_context.event_pump()?;
 
-            run_preview_loop(&mut event_pump, &mut instance, start)
+            let preview_hwnd = preview_hwnd(raw_window_handle)?;
+            run_preview_loop(&mut event_pump, &mut instance, || unsafe {
+                platform::windows::window::client_size(preview_hwnd)
+            })
         }
 
-        Mode::Screensaver => {
-            #[cfg(windows)]
-            let wallpaper_api = wallpaper::DesktopWallpaper::new().ok();
-            let monitors = video_subsystem
-                .available_monitors()
-                .enumerate()
-                .map(|(_index, monitor)| {
-                    (
-                        monitor.clone(),
-                        #[cfg(windows)]
-                        wallpaper_api
-                            .as_ref()
-                            .and_then(|wallpaper| wallpaper.get(_index as u32).ok()),
-                        #[cfg(not(windows))]
-                        None,
-                    )
-                })
-                .collect::<Vec<(MonitorHandle, Option<std::path::PathBuf>)>>();
-            log::debug!("Available monitors: {:?}", monitors);
+        #[cfg(target_os = "linux")]
+        Mode::XScreenSaver(window_id) => {
+            let mut instance = new_xscreensaver_instance(&video_subsystem, window_id, &config)?;
+            let mut event_pump = sdl_context.event_pump()?;
+
+            // XScreenSaver sizes its window to fit the screen, once.
+            run_preview_loop(&mut event_pump, &mut instance, || None)
+        }
 
+        Mode::Screensaver { lock_on_exit } => {
+            let surfaces = build_surfaces(&video_subsystem, &config, &mode);
+            if let Err(err) = calibration::run_if_needed(&video_subsystem, &mut config, &surfaces) {
+                log::warn!("Failed to calibrate the quality: {}", err);
+            }
+
+            let mut event_pump = sdl_context.event_pump()?;
             #[cfg(windows)]
-            let fill_mode = config.platform.windows.fill_mode;
-            #[cfg(not(windows))]
-            let fill_mode = config::FillMode::None;
-            let surfaces = surface::build(&monitors, fill_mode);
-            log::debug!("Creating windows: {:?}", surfaces);
-
-            let mut instances = surfaces
-                .iter()
-                .map(|surface| {
-                    new_instance(&video_subsystem, &config, surface)
-                        .map(|instance| (instance.window.id(), instance))
-                })
-                .collect::<Result<HashMap<WindowId, Instance>, String>>()?;
+            let standby = daemon::Standby::from_env().transpose()?;
+
+            let result = if config.platform.windows.isolate_monitors && surfaces.len() > 1 {
+                // The processes for each surface can’t be set up ahead of time, so on standby,
+                // only start them once the daemon calls for the screensaver.
+                #[cfg(windows)]
+                if let Some(standby) = &standby {
+                    match wait_on_standby(&mut event_pump, standby) {
+                        StandbyCall::Called => (),
+                        StandbyCall::Quit => return Ok(()),
+                        StandbyCall::MonitorsChanged => respawn_standby(),
+                    }
+                }
+
+                log::info!(
+                    "Running each of the {} surfaces in its own process",
+                    surfaces.len()
+                );
+                coordinator::run(&config, surfaces.len())
+            } else {
+                let pending_instances = new_instances(&video_subsystem, &config, &surfaces, &mode);
+
+                // On standby, set up every instance right away, but keep them hidden until the
+                // daemon calls for them.
+                #[cfg(windows)]
+                let pending_instances: Box<
+                    dyn Iterator<Item = Result<Instance, String>> + '_,
+                > = match &standby {
+                    Some(standby) => {
+                        let prepared = pending_instances.collect::<Vec<_>>();
+                        match wait_on_standby(&mut event_pump, standby) {
+                            StandbyCall::Called => (),
+                            StandbyCall::Quit => return Ok(()),
+                            StandbyCall::MonitorsChanged => respawn_standby(),
+                        }
+                        Box::new(prepared.into_iter())
+                    }
+                    None => Box::new(pending_instances),
+                };
+
+                // Hide the cursor
+                sdl_context.mouse().show_cursor(false);
+
+                let mut instances = HashMap::new();
+
+                let result = run_main_loop(
+                    &video_subsystem,
+                    &mut event_pump,
+                    &mut instances,
+                    pending_instances,
+                    &config,
+                    &mode,
+                    None,
+                );
+                record_exit(exit_log, &result);
+                result.map(|_| ())
+            };
+
+            lock_after(
+                result,
+                lock_on_exit || config.platform.windows.lock_on_exit,
+                lock_workstation,
+            )
+        }
+
+        // The coordinator has already calibrated the quality.
+        Mode::Instance { .. } => {
+            let (mut connection, flux_settings) = coordinator::Connection::open()?;
+            config.flux = flux_settings;
+
+            let surfaces = build_surfaces(&video_subsystem, &config, &mode);
+            let pending_instances = new_instances(&video_subsystem, &config, &surfaces, &mode);
 
             // Hide the cursor
             sdl_context.mouse().show_cursor(false);
 
-            // Unhide windows after context setup
-            for instance in instances.values_mut() {
-                instance.window.show();
+            let mut instances = HashMap::new();
+            let mut event_pump = sdl_context.event_pump()?;
+
+            let result = run_main_loop(
+                &video_subsystem,
+                &mut event_pump,
+                &mut instances,
+                pending_instances,
+                &config,
+                &mode,
+                Some(&mut connection),
+            );
+            record_exit(exit_log, &result);
+            result.map(|_| ())
+        }
+
+        #[cfg(windows)]
+        Mode::Wallpaper => {
+            // A backup that couldn’t be put back is kept as it is for next time, rather than
+            // replaced with the settings it should fix.
+            let wallpaper_backup = wallpaper_backup_path(exit_log)
+                .filter(|wallpaper_backup| wallpaper::restore_after_crash(wallpaper_backup));
+            if let Some(wallpaper_backup) = &wallpaper_backup {
+                if let Err(err) = wallpaper::save_backup(wallpaper_backup) {
+                    log::warn!("{}", err);
+                }
+            }
+
+            let surfaces = build_surfaces(&video_subsystem, &config, &mode);
+            if let Err(err) = calibration::run_if_needed(&video_subsystem, &mut config, &surfaces) {
+                log::warn!("Failed to calibrate the quality: {}", err);
+            }
+            let pending_instances = new_instances(&video_subsystem, &config, &surfaces, &mode);
+
+            let mut instances = HashMap::new();
+            let mut event_pump = sdl_context.event_pump()?;
+
+            let live_wallpaper = &config.platform.windows.live_wallpaper;
+            let icon_brightness = live_wallpaper
+                .dim_icons
+                .then_some(live_wallpaper.icon_brightness);
+
+            let res = run_wallpaper_loop(
+                &video_subsystem,
+                &mut event_pump,
+                &mut instances,
+                pending_instances,
+                &config,
+                icon_brightness,
+                live_wallpaper.icon_motion,
+            );
+
+            // Destroy our windows before asking Explorer to repaint the wallpaper beneath them.
+            drop(instances);
+            unsafe { platform::windows::window::repaint_desktop() };
+            if let Some(wallpaper_backup) = &wallpaper_backup {
+                wallpaper::remove_backup(wallpaper_backup);
             }
 
+            res
+        }
+
+        Mode::Windowed => {
+            let instance = Rc::new(RefCell::new(new_windowed_instance(
+                &video_subsystem,
+                &config,
+            )?));
+            let mut event_pump = sdl_context.event_pump()?;
+
+            // Polling for events blocks while the window is dragged or resized, so render from a
+            // timer until the user lets go.
+            #[cfg(windows)]
+            let _size_move_renderer = {
+                let raw_window_handle = instance.borrow().window.raw_window_handle();
+                let instance = Rc::clone(&instance);
+                unsafe {
+                    platform::windows::size_move::SizeMoveRenderer::new(
+                        &raw_window_handle,
+                        Box::new(move || {
+                            render_windowed(&instance);
+                        }),
+                    )
+                }
+                .map_err(|err| log::warn!("Failed to render during window moves: {}", err))
+                .ok()
+            };
+
+            run_windowed_loop(&mut event_pump, &instance, &config)
+        }
+
+        #[cfg(windows)]
+        Mode::Benchmark { compare_swapchains } => {
+            platform::windows::console::attach();
+            let mut event_pump = sdl_context.event_pump()?;
+
+            benchmark::run(
+                &video_subsystem,
+                &mut event_pump,
+                &config,
+                compare_swapchains,
+            )
+        }
+
+        #[cfg(windows)]
+        Mode::Soak { duration, windowed } => {
+            platform::windows::console::attach();
             let mut event_pump = sdl_context.event_pump()?;
-            let start = std::time::Instant::now();
 
-            run_main_loop(&mut event_pump, &mut instances, start)
+            soak::run(
+                &video_subsystem,
+                &mut event_pump,
+                &config,
+                duration,
+                windowed,
+            )
         }
 
         _ => unreachable!(),
     }
 }
 
-#[cfg(windows)]
+// How the wait on standby ended
+#[cfg(all(windows, feature = "sdl"))]
+enum StandbyCall {
+    Called,
+    Quit,
+    // The screensaver was set up for the monitors as they were
+    MonitorsChanged,
+}
+
+// Wait for the daemon to call on the screensaver. Nothing is shown yet, so the input only needs
+// clearing out.
+#[cfg(all(windows, feature = "sdl"))]
+fn wait_on_standby(event_pump: &mut sdl2::EventPump, standby: &daemon::Standby) -> StandbyCall {
+    use sdl2::event::Event;
+
+    log::info!("Standing by until the computer goes idle");
+    loop {
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. } => return StandbyCall::Quit,
+                Event::Display { display_event, .. } => {
+                    log::info!("The displays changed on standby: {:?}", display_event);
+                    return StandbyCall::MonitorsChanged;
+                }
+                _ => (),
+            }
+        }
+
+        match standby.poll(STANDBY_POLL_INTERVAL) {
+            Ok(true) => return StandbyCall::Called,
+            Ok(false) => (),
+            Err(err) => {
+                log::info!("{}. Quitting.", err);
+                return StandbyCall::Quit;
+            }
+        }
+    }
+}
+
+// Quit for the daemon to start another screensaver on standby right away, set up for the monitors
+// as they are now.
+#[cfg(all(windows, feature = "sdl"))]
+fn respawn_standby() -> ! {
+    gradient::remove_image_files();
+    process::exit(daemon::RESPAWN_EXIT_CODE)
+}
+
+#[cfg(all(any(windows, target_os = "linux"), feature = "sdl"))]
 fn run_preview_loop(
     event_pump: &mut sdl2::EventPump,
     instance: &mut Instance,
-    start: std::time::Instant,
+    // The current size of the window the preview renders into, if it can change
+    mut parent_size: impl FnMut() -> Option<PhysicalSize<u32>>,
 ) -> Result<(), String> {
     use sdl2::event::Event;
 
+    let mut size: Option<PhysicalSize<u32>> = None;
+    let mut last_size_check: Option<Instant> = None;
+
     'main: loop {
         for event in event_pump.poll_iter() {
             match event {
@@ -266,7 +1020,20 @@ fn run_preview_loop(
             }
         }
 
-        let timestamp = start.elapsed().as_secs_f64() * 1000.0;
+        // Nothing tells the preview that the window it renders into was resized, so keep checking.
+        if last_size_check.map_or(true, |check| {
+            check.elapsed() >= PREVIEW_RESIZE_POLL_INTERVAL
+        }) {
+            last_size_check = Some(Instant::now());
+            if let Some(new_size) = parent_size().filter(|new_size| Some(*new_size) != size) {
+                size = Some(new_size);
+                if let Err(err) = instance.resize(new_size) {
+                    log::warn!("Failed to resize the preview: {}", err);
+                }
+            }
+        }
+
+        let timestamp = instance.clock.timestamp(Instant::now()).unwrap_or_default();
         if let Err(err) = instance.draw(timestamp) {
             log::error!("Failed to render Flux: {}", err);
         }
@@ -275,71 +1042,1485 @@ fn run_preview_loop(
     Ok(())
 }
 
-fn run_main_loop(
+#[cfg(feature = "sdl")]
+fn run_windowed_loop(
     event_pump: &mut sdl2::EventPump,
-    instances: &mut HashMap<WindowId, Instance>,
-    start: std::time::Instant,
+    instance: &RefCell<Instance>,
+    config: &Config,
 ) -> Result<(), String> {
-    use sdl2::event::Event;
+    use sdl2::event::{Event, WindowEvent};
+    use sdl2::keyboard::Keycode;
+
+    let mut overlay = stats::TitleOverlay::new("Flux");
+    let mut hud = config
+        .platform
+        .windows
+        .show_stats
+        .then(hud::Collector::default);
+    let mut power = power::Monitor::new(config.platform.windows.battery);
+    let mut minimized = false;
+    let mut occluded = false;
+    let mut last_occlusion_check: Option<Instant> = None;
+
+    let presets = Presets::load(config);
+    let mut palette = command_palette::CommandPalette::new(&presets);
+    let palette_key = Keycode::from_name(&config.platform.windows.command_palette.key);
+    if palette_key.is_none() {
+        log::warn!(
+            "I don’t know the key {} for the command palette",
+            config.platform.windows.command_palette.key
+        );
+    }
+    let mut paused_by_user = false;
+    // The text drawn over the animation, and the stats to go back to once the palette closes
+    let mut shown_hud: Option<String> = None;
+    let mut stats_report: Option<String> = None;
 
     'main: loop {
-        for event in event_pump.poll_iter() {
+        // Wait for something to happen while paused, instead of spinning.
+        let paused_event = if instance.borrow().clock.is_paused() {
+            event_pump.wait_event_timeout(OCCLUSION_POLL_INTERVAL.as_millis() as u32)
+        } else {
+            None
+        };
+
+        for event in paused_event.into_iter().chain(event_pump.poll_iter()) {
             match event {
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    repeat: false,
+                    ..
+                } if Some(keycode) == palette_key => palette.toggle(),
+
                 Event::Quit { .. }
                 | Event::Window {
-                    win_event: sdl2::event::WindowEvent::Close,
+                    win_event: WindowEvent::Close,
                     ..
-                }
-                | Event::KeyDown { .. }
-                | Event::MouseButtonDown { .. } => {
-                    break 'main;
-                }
+                } => break 'main,
 
-                Event::MouseMotion { xrel, yrel, .. } => {
-                    if f64::max(xrel.abs() as f64, yrel.abs() as f64)
-                        > MINIMUM_MOUSE_MOTION_TO_EXIT_SCREENSAVER
-                    {
-                        break 'main;
+                // Keys go to the palette while it’s open, so Escape only closes the palette.
+                Event::KeyDown { .. } | Event::TextInput { .. } if palette.is_open() => {
+                    if let Some(command) = palette.handle_event(&event) {
+                        run_palette_command(
+                            command,
+                            &mut instance.borrow_mut(),
+                            config,
+                            &presets,
+                            &mut paused_by_user,
+                        );
                     }
                 }
 
+                Event::KeyDown {
+                    keycode: Some(Keycode::Escape),
+                    ..
+                } => break 'main,
+
+                Event::Window {
+                    win_event: WindowEvent::Minimized,
+                    ..
+                } => minimized = true,
+
+                Event::Window {
+                    win_event: WindowEvent::Restored | WindowEvent::Maximized,
+                    ..
+                } => minimized = false,
+
                 _ => (),
             }
         }
 
-        for (_, instance) in instances.iter_mut() {
-            let timestamp = start.elapsed().as_secs_f64() * 1000.0;
-            if let Err(err) = instance.draw(timestamp) {
-                log::error!("Failed to render Flux: {}", err);
-            }
+        let mut instance_ref = instance.borrow_mut();
+
+        // Only check for occlusion every so often, since it walks through every window above ours.
+        if last_occlusion_check.map_or(true, |check| check.elapsed() >= OCCLUSION_POLL_INTERVAL) {
+            last_occlusion_check = Some(Instant::now());
+            occluded = !minimized && instance_ref.shown && is_occluded(&instance_ref.window);
+        }
+
+        instance_ref.throttle = power.poll(Instant::now());
+        instance_ref.update_timer_resolution();
+
+        let pause_reason = if minimized {
+            Some(stats::PauseReason::Minimized)
+        } else if occluded {
+            Some(stats::PauseReason::Occluded)
+        } else if paused_by_user {
+            Some(stats::PauseReason::Requested)
+        } else if instance_ref.throttle == power::Throttle::Pause {
+            Some(stats::PauseReason::LowBattery)
+        } else {
+            None
+        };
+
+        if pause_reason != instance_ref.stats.paused() {
+            match pause_reason {
+                Some(reason) => instance_ref.pause(reason),
+                None => instance_ref.resume(),
+            }
+        }
+
+        let Instance {
+            window, stats, gpu, ..
+        } = &mut *instance_ref;
+        overlay.update(window, stats, Instant::now());
+        let report = hud
+            .as_mut()
+            .and_then(|hud| hud.report(Instant::now(), std::iter::once((gpu.as_str(), &*stats))));
+        if report.is_some() {
+            stats_report = report;
+        }
+        // The palette is drawn over the animation in place of the stats, while it’s open.
+        let hud_text = match palette.is_open() {
+            true => Some(palette.summary()),
+            false => stats_report.clone(),
+        };
+        if hud_text != shown_hud {
+            let result = match (&hud_text, palette.is_open()) {
+                (Some(stats), false) => instance_ref.set_stats(stats.clone()),
+                _ => instance_ref.set_hud(hud_text.clone()),
+            };
+            if let Err(err) = result {
+                log::debug!("Failed to draw over the animation: {}", err);
+            }
+            shown_hud = hud_text;
+        }
+        drop(instance_ref);
+
+        if !render_windowed(instance) {
+            let next_frame_at = instance.borrow().next_frame_at();
+            clock::sleep_until(
+                next_frame_at.unwrap_or_else(|| Instant::now() + Duration::from_millis(1)),
+            );
         }
     }
 
     Ok(())
 }
 
+#[cfg(feature = "sdl")]
+fn run_palette_command(
+    command: command_palette::Command,
+    instance: &mut Instance,
+    config: &Config,
+    presets: &Presets,
+    paused_by_user: &mut bool,
+) {
+    use command_palette::Command;
+
+    match command {
+        Command::UsePreset(name) => {
+            let Some(preset) = presets.find(&name) else {
+                return;
+            };
+            let mut config = config.clone();
+            preset.apply(&mut config.flux);
+            if let Err(err) = instance.restart_flux(&config) {
+                log::warn!("Failed to switch to the preset {}: {}", name, err);
+            }
+        }
+
+        Command::TogglePause => *paused_by_user = !*paused_by_user,
+
+        Command::Screenshot => {
+            let path = command_palette::screenshot_path();
+            match instance.screenshot(&path) {
+                Ok(()) => log::info!("Saved a screenshot to {}", path.display()),
+                Err(err) => log::error!("Failed to take a screenshot: {}", err),
+            }
+        }
+
+        // The settings window runs in a process of its own, so it can stay open after Flux
+        // closes.
+        #[cfg(windows)]
+        Command::OpenSettings => {
+            let result = std::env::current_exe()
+                .and_then(|exe| process::Command::new(exe).arg("/c").spawn());
+            if let Err(err) = result {
+                log::error!("Failed to open the settings: {}", err);
+            }
+        }
+    }
+}
+
+// Check whether the window is completely covered by other windows.
+#[cfg(all(windows, feature = "sdl"))]
+fn is_occluded(window: &Window) -> bool {
+    unsafe { platform::windows::occlusion::is_occluded(&window.raw_window_handle()) }
+}
+
+#[cfg(all(not(windows), feature = "sdl"))]
+fn is_occluded(_window: &Window) -> bool {
+    false
+}
+
+// Render the next frame in windowed mode, returning whether a frame was due.
+//
+// This is called from both the event loop and the timer that runs while the window is being
+// moved, so the instance might already be in use.
+#[cfg(feature = "sdl")]
+fn render_windowed(instance: &RefCell<Instance>) -> bool {
+    let Ok(mut instance) = instance.try_borrow_mut() else {
+        return false;
+    };
+
+    let now = Instant::now();
+    if instance.clock.is_paused() || !instance.is_frame_due(now) {
+        return false;
+    }
+
+    let timestamp = instance.clock.timestamp(now).unwrap_or_default();
+    let result = instance
+        .fit_to_window()
+        .and_then(|_| instance.draw(timestamp));
+
+    match result {
+        Ok(()) if !instance.shown => instance.show(),
+        Ok(()) => (),
+        Err(err) => log::error!("Failed to render Flux: {}", err),
+    }
+
+    true
+}
+
+#[cfg(feature = "sdl")]
+fn run_main_loop(
+    video_subsystem: &sdl2::VideoSubsystem,
+    event_pump: &mut sdl2::EventPump,
+    instances: &mut HashMap<WindowId, Instance>,
+    pending_instances: impl Iterator<Item = Result<Instance, String>>,
+    config: &Config,
+    mode: &Mode,
+    // Set when each monitor runs in its own process
+    mut coordinator: Option<&mut coordinator::Connection>,
+) -> Result<stats::ExitReason, String> {
+    use coordinator::protocol::{FromInstance, ToInstance};
+    use sdl2::event::Event;
+    use stats::ExitReason;
+
+    let mut power = power::Monitor::new(config.platform.windows.battery);
+    // The settings last sent by the coordinator, or changed in the settings file. Instances that
+    // haven’t started yet still come up with the settings they were created with.
+    let mut updated_config: Option<Config> = None;
+    // An isolated instance leaves it to the coordinator to watch the settings.
+    let mut settings_watch = coordinator.is_none().then(|| config::Watch::new(config));
+    // When a monitor was last connected, disconnected or rotated
+    let mut display_changed_at: Option<Instant> = None;
+    // The monitors the instances were laid out for, which any display indices in the settings
+    // still point at
+    let mut laid_out_monitors: Vec<MonitorHandle> = video_subsystem.available_monitors().collect();
+    // The instances still to come were laid out for the old monitors
+    let mut is_layout_stale = false;
+    let mut playlist = playlist::Scheduler::new(config, Instant::now());
+    let mut exit_transition = config.platform.windows.exit_transition;
+    let mut hud = config
+        .platform
+        .windows
+        .show_stats
+        .then(hud::Collector::default);
+    // When the screensaver started to fade out, and what it’s exiting for
+    let mut exiting: Option<(Instant, ExitReason)> = None;
+    // Turns the displays off once the screensaver has been running for a while
+    let mut blanking_schedule =
+        blanking::Schedule::new(&config.platform.windows.blanking, Instant::now());
+    // Notices the displays coming back on, like from input that went to another process
+    #[cfg(windows)]
+    let wake_watch = blanking_schedule.as_ref().and_then(|_| {
+        platform::windows::display_power::WakeWatch::new()
+            .map_err(|err| log::warn!("{}", err))
+            .ok()
+    });
+    // Stands by until every instance has come up and shown a frame
+    let mut watchdog = start_watchdog(config, mode);
+    let has_started_all = std::cell::Cell::new(false);
+    let mut pending_instances = pending_instances.chain(std::iter::from_fn(|| {
+        has_started_all.set(true);
+        None
+    }));
+    // Tells apart the mice, keyboards, and other HID devices that shouldn’t wake the screensaver
+    #[cfg(windows)]
+    let activity_watch = match config.platform.windows.input.ignored_devices.is_empty() {
+        true => None,
+        false => {
+            use platform::windows::raw_input::ActivityWatch;
+
+            for (name, value) in ActivityWatch::SDL_HINTS {
+                sdl2::hint::set(name, value);
+            }
+            ActivityWatch::new(&config.platform.windows.input)
+                .map_err(|err| log::warn!("Can’t ignore any input devices: {}", err))
+                .ok()
+        }
+    };
+
+    // Lets gamepads and remotes wake the screensaver, like on a media PC
+    let mut controllers =
+        gamepad::Controllers::open(&video_subsystem.sdl(), &config.platform.windows.input);
+
+    // Only one process plays the sound, when each monitor runs in a process of its own.
+    let mut ambient_audio = matches!(
+        mode,
+        Mode::Screensaver { .. } | Mode::Instance { surface: 0 }
+    )
+    .then(|| audio::Ambient::start(&video_subsystem.sdl(), &config.ambient_audio))
+    .flatten();
+
+    let exit_reason = 'main: loop {
+        // The first input or message this time round that should end the screensaver
+        let mut trigger: Option<ExitReason> = None;
+        // Input too slight to end the screensaver, which still wakes the displays
+        let mut is_nudged = false;
+        for event in event_pump.poll_iter() {
+            match event {
+                // Ignore auto-repeat from keys that were already held down when we started, like
+                // the hotkey used to blank the screen.
+                Event::KeyDown { repeat: true, .. } => (),
+
+                Event::Quit { .. }
+                | Event::Window {
+                    win_event: sdl2::event::WindowEvent::Close,
+                    ..
+                } => {
+                    // Keep the input that started the fade out, if there was one.
+                    break 'main exiting
+                        .as_ref()
+                        .map_or(ExitReason::Quit, |(_, reason)| reason.clone());
+                }
+
+                Event::KeyDown {
+                    keycode, scancode, ..
+                } => {
+                    let key = keycode
+                        .map(|keycode| keycode.name())
+                        .or_else(|| scancode.map(|scancode| scancode.name().to_string()))
+                        .unwrap_or_else(|| "unknown".to_string());
+                    trigger.get_or_insert(ExitReason::Key { key });
+                }
+
+                Event::MouseButtonDown { mouse_btn, .. } => {
+                    let button = format!("{:?}", mouse_btn).to_lowercase();
+                    trigger.get_or_insert(ExitReason::MouseButton { button });
+                }
+
+                Event::ControllerButtonDown { which, button, .. } => {
+                    trigger.get_or_insert(ExitReason::Controller {
+                        device: which,
+                        button: button.string(),
+                    });
+                }
+
+                Event::JoyButtonDown {
+                    which, button_idx, ..
+                } => {
+                    trigger.get_or_insert(ExitReason::Controller {
+                        device: which,
+                        button: button_idx.to_string(),
+                    });
+                }
+
+                Event::ControllerAxisMotion {
+                    which, axis, value, ..
+                } if controllers.as_ref().is_some_and(|controllers| {
+                    controllers.is_past_threshold(which, axis as u8, value)
+                }) =>
+                {
+                    trigger.get_or_insert(ExitReason::ControllerAxis {
+                        device: which,
+                        axis: axis.string(),
+                    });
+                }
+
+                // The sticks on joysticks that SDL doesn’t know the layout of
+                Event::JoyAxisMotion {
+                    which,
+                    axis_idx,
+                    value,
+                    ..
+                } if controllers.as_ref().is_some_and(|controllers| {
+                    controllers.is_joystick(which)
+                        && controllers.is_past_threshold(which, axis_idx, value)
+                }) =>
+                {
+                    trigger.get_or_insert(ExitReason::ControllerAxis {
+                        device: which,
+                        axis: axis_idx.to_string(),
+                    });
+                }
+
+                // The direction pad on remotes and joysticks that SDL doesn’t know the layout of
+                Event::JoyHatMotion {
+                    which,
+                    hat_idx,
+                    state,
+                    ..
+                } if state != sdl2::joystick::HatState::Centered => {
+                    trigger.get_or_insert(ExitReason::Controller {
+                        device: which,
+                        button: format!("hat {} {:?}", hat_idx, state).to_lowercase(),
+                    });
+                }
+
+                Event::JoyDeviceAdded { which, .. } => {
+                    if let Some(controllers) = controllers.as_mut() {
+                        controllers.add(which);
+                    }
+                }
+
+                Event::JoyDeviceRemoved { which, .. } => {
+                    if let Some(controllers) = controllers.as_mut() {
+                        controllers.remove(which);
+                    }
+                }
+
+                Event::MouseMotion { xrel, yrel, .. } => {
+                    let distance = xrel.abs().max(yrel.abs());
+                    if distance as f64 > MINIMUM_MOUSE_MOTION_TO_EXIT_SCREENSAVER {
+                        trigger.get_or_insert(ExitReason::MouseMotion { distance });
+                    } else {
+                        is_nudged = true;
+                    }
+                }
+
+                // Each process in an isolated screensaver was started for a single surface, so
+                // there’s no layout for it to redo.
+                Event::Display { display_event, .. } if coordinator.is_none() => {
+                    log::info!("The displays changed: {:?}", display_event);
+                    display_changed_at = Some(Instant::now());
+                }
+
+                _ => (),
+            }
+        }
+
+        // The raw input arrives alongside the events it caused, so it’s all in by now.
+        #[cfg(windows)]
+        if let Some(watch) = &activity_watch {
+            use platform::windows::raw_input::Activity;
+
+            let is_from_input = matches!(
+                trigger,
+                Some(
+                    ExitReason::Key { .. }
+                        | ExitReason::MouseButton { .. }
+                        | ExitReason::MouseMotion { .. }
+                        | ExitReason::Controller { .. }
+                        | ExitReason::ControllerAxis { .. }
+                )
+            );
+            if watch.take() == Activity::Ignored && is_from_input {
+                log::debug!("Ignoring input from an ignored device: {:?}", trigger);
+                trigger = None;
+            }
+        }
+
+        if display_changed_at.is_some_and(|at| at.elapsed() >= DISPLAY_CHANGE_SETTLE_TIME) {
+            display_changed_at = None;
+            is_layout_stale = true;
+            // The indices may point at other monitors now, so pin them to the ones they meant.
+            let mut resolved = updated_config.take().unwrap_or_else(|| config.clone());
+            resolved
+                .platform
+                .windows
+                .resolve_monitors(&laid_out_monitors);
+            relayout_instances(video_subsystem, instances, &resolved, mode);
+            updated_config = Some(resolved);
+            laid_out_monitors = video_subsystem.available_monitors().collect();
+        }
+
+        if let Some(connection) = coordinator.as_deref_mut() {
+            while let Some(message) = connection.try_recv() {
+                match message {
+                    ToInstance::Exit => {
+                        trigger.get_or_insert(ExitReason::Coordinator);
+                    }
+                    ToInstance::Heartbeat => connection.send(&FromInstance::Heartbeat),
+                    ToInstance::Settings { flux } => {
+                        let updated = switch_settings(instances, config, flux);
+                        playlist = playlist::Scheduler::new(&updated, Instant::now());
+                        updated_config = Some(updated);
+                    }
+                    ToInstance::Init { .. } | ToInstance::Unknown => (),
+                }
+            }
+        }
+
+        if let Some(flux) = settings_watch.as_mut().and_then(|watch| {
+            watch.poll(
+                updated_config.as_ref().unwrap_or(config),
+                Instant::now(),
+                reapply_launch_overrides,
+            )
+        }) {
+            log::info!("The settings have changed, so switching over to them");
+            let updated = switch_settings(instances, config, flux);
+            // The scenes are built on top of the regular settings, which the instances have just
+            // switched back to, so the playlist starts over from them.
+            playlist = playlist::Scheduler::new(&updated, Instant::now());
+            updated_config = Some(updated);
+        }
+
+        if let Some(schedule) = blanking_schedule.as_mut().filter(|_| exiting.is_none()) {
+            #[cfg(windows)]
+            let is_nudged = is_nudged || wake_watch.as_ref().is_some_and(|watch| watch.has_woken());
+            let now = Instant::now();
+            let action = match is_nudged {
+                true => schedule.wake(now),
+                false => schedule.poll(now),
+            };
+            blank_instances(instances, action);
+        }
+
+        if let Some(reason) = trigger.filter(|_| exiting.is_none()) {
+            if reason != ExitReason::Coordinator {
+                // Let the other monitors know that the user is back, so that they fade out
+                // alongside this one.
+                if let Some(connection) = coordinator.as_deref_mut() {
+                    connection.send(&FromInstance::Dismissed);
+                }
+            }
+            if exit_transition.style == config::ExitStyle::None || exit_transition.duration_ms == 0
+            {
+                return Ok(reason);
+            }
+            exiting = Some((Instant::now(), reason));
+        }
+
+        if let Some((since, reason)) = &exiting {
+            let duration = Duration::from_millis(exit_transition.duration_ms as u64);
+            let progress = since.elapsed().as_secs_f32() / duration.as_secs_f32();
+            if progress >= 1.0 {
+                break 'main reason.clone();
+            }
+
+            if let Some(ambient_audio) = ambient_audio.as_mut() {
+                ambient_audio.set_fade(1.0 - progress);
+            }
+            for instance in instances.values_mut() {
+                match instance.fade_out(exit_transition.style, 1.0 - progress) {
+                    Ok(()) => (),
+                    Err(err) if exit_transition.style == config::ExitStyle::FadeToDesktop => {
+                        log::warn!(
+                            "Failed to fade out to the desktop, so fading to black: {}",
+                            err
+                        );
+                        exit_transition.style = config::ExitStyle::FadeToBlack;
+                    }
+                    Err(err) => log::debug!("Failed to fade out: {}", err),
+                }
+            }
+        }
+
+        play_next_scene(
+            instances,
+            playlist.as_mut(),
+            updated_config.as_ref().unwrap_or(config),
+        );
+        throttle_instances(instances, power.poll(Instant::now()));
+        // There’s no point bringing up more monitors on the way out, or with the displays off.
+        let is_blanking = blanking_schedule
+            .as_ref()
+            .is_some_and(blanking::Schedule::is_blanking);
+        if is_layout_stale || exiting.is_some() || is_blanking {
+            render_instances(instances, &mut std::iter::empty())?;
+        } else {
+            render_instances(instances, &mut pending_instances)?;
+        }
+        update_hud(hud.as_mut(), instances);
+        #[cfg(windows)]
+        requery_wallpapers(instances, updated_config.as_ref().unwrap_or(config));
+
+        let is_up = (has_started_all.get() || is_layout_stale)
+            && instances.values().all(|instance| instance.shown);
+        if is_up && watchdog.take().is_some() {
+            log::debug!("Every instance is up and running");
+        }
+    };
+    drop(ambient_audio);
+
+    // Let the other monitors know that the screensaver was closed. They already know if it faded
+    // out.
+    if let Some(connection) = coordinator.filter(|_| exiting.is_none()) {
+        connection.send(&FromInstance::Dismissed);
+    }
+
+    Ok(exit_reason)
+}
+
+// Open the windows with winit instead, in builds without SDL.
+#[cfg(not(feature = "sdl"))]
+fn run_flux(mode: Mode, config: Config, exit_log: Option<&path::Path>) -> Result<(), String> {
+    #[cfg(windows)]
+    apply_gpu_preference(&config);
+
+    winit_backend::run(mode, config, exit_log)
+}
+
+// Point OpenGL at the chosen GPU before any contexts are created, or let Windows pick again if the
+// choice was reset. Safe mode only drops the choice for this try, so it leaves the Windows
+// preference be, rather than take back one that the next start would set again.
 #[cfg(windows)]
-fn new_preview_window(
+fn apply_gpu_preference(config: &Config) {
+    if watchdog::is_safe_mode() {
+        return;
+    }
+
+    match platform::windows::gpu_preference::set_os_preference(
+        config.platform.windows.preferred_gpu.as_deref(),
+    ) {
+        #[cfg(not(target_arch = "aarch64"))]
+        Ok(preference) => gl_context::set_hybrid_graphics_preference(preference),
+        #[cfg(target_arch = "aarch64")]
+        Ok(_) => (),
+        Err(err) => log::warn!("{}", err),
+    }
+}
+
+// Give up on a screensaver that never gets going, rather than leave black windows over the
+// monitors. The first time round, it starts over in safe mode.
+#[cfg(feature = "sdl")]
+fn start_watchdog(config: &Config, mode: &Mode) -> Option<watchdog::Watchdog> {
+    let timeout_secs = config.platform.windows.watchdog.timeout_secs;
+    if timeout_secs == 0 {
+        return None;
+    }
+    // Each isolated instance leaves it to the coordinator to carry on without it.
+    let is_screensaver = matches!(mode, Mode::Screensaver { .. });
+    let can_retry = is_screensaver && !watchdog::is_safe_mode();
+
+    let on_stall = move || {
+        log::error!(
+            "The screensaver didn’t start within {} seconds",
+            timeout_secs
+        );
+        if can_retry {
+            match watchdog::restart_in_safe_mode() {
+                Ok(()) => {
+                    log::warn!("Trying again in safe mode");
+                    process::exit(1);
+                }
+                Err(err) => log::error!("{}", err),
+            }
+        }
+
+        #[cfg(windows)]
+        if is_screensaver {
+            platform::windows::window::show_error(
+                "Flux couldn’t start",
+                "The graphics driver stopped responding while Flux was starting up. Updating \
+                 the driver might help.",
+            );
+        }
+        process::exit(1);
+    };
+
+    Some(watchdog::Watchdog::start(
+        Duration::from_secs(timeout_secs as u64),
+        on_stall,
+    ))
+}
+
+// Note down what ended the screensaver, including any error.
+fn record_exit(exit_log: Option<&path::Path>, result: &Result<stats::ExitReason, String>) {
+    let reason = match result {
+        Ok(reason) => reason.clone(),
+        Err(message) => stats::ExitReason::Error {
+            message: message.clone(),
+        },
+    };
+    stats::record_exit(exit_log, &reason);
+}
+
+// Lock the session once the screensaver is over, even if it failed, since the user expects to
+// come back to a locked session either way. An error from the screensaver itself wins over one
+// from locking.
+fn lock_after(
+    result: Result<(), String>,
+    should_lock: bool,
+    lock: impl FnOnce() -> Result<(), String>,
+) -> Result<(), String> {
+    if !should_lock {
+        return result;
+    }
+
+    match (result, lock()) {
+        (Err(err), Err(lock_err)) => {
+            log::error!("{}", lock_err);
+            Err(err)
+        }
+        (result, lock_result) => result.and(lock_result),
+    }
+}
+
+fn lock_workstation() -> Result<(), String> {
+    #[cfg(windows)]
+    platform::windows::session::lock_workstation()?;
+
+    Ok(())
+}
+
+#[cfg(all(windows, feature = "sdl"))]
+fn run_wallpaper_loop(
     video_subsystem: &sdl2::VideoSubsystem,
-    raw_window_handle: RawWindowHandle,
+    event_pump: &mut sdl2::EventPump,
+    instances: &mut HashMap<WindowId, Instance>,
+    mut pending_instances: impl Iterator<Item = Result<Instance, String>>,
     config: &Config,
-) -> Result<Instance, String> {
-    use windows::Win32::Foundation::RECT;
-    use windows::Win32::UI::WindowsAndMessaging::GetClientRect;
+    // Dim the areas behind the desktop icons to this brightness
+    icon_brightness: Option<f32>,
+    // How much of each new frame to take in behind the icons
+    icon_motion: f32,
+) -> Result<(), String> {
+    use sdl2::event::Event;
+
+    // When a monitor was last connected, disconnected or rotated
+    let mut display_changed_at: Option<Instant> = None;
+    // The monitors the instances were laid out for, and the settings with any display indices
+    // pinned to them
+    let mut laid_out_monitors: Vec<MonitorHandle> = video_subsystem.available_monitors().collect();
+    let mut layout_config = config.clone();
+    // The instances still to come were laid out for the old monitors
+    let mut is_layout_stale = false;
+    // Edge lighting and the areas masked in the settings, which move along with the monitors
+    let mut mask_layers = config
+        .platform
+        .windows
+        .live_wallpaper
+        .mask_layers(&laid_out_monitors);
+
+    let mut icons = Vec::new();
+    let mut last_icon_query: Option<Instant> = None;
+    let mut masked_instances = 0;
+    let mut power = power::Monitor::new(config.platform.windows.battery);
+    let mut do_not_disturb = false;
+    let mut last_do_not_disturb_query: Option<Instant> = None;
+    let mut blocked_by: Option<String> = None;
+    let mut playlist = playlist::Scheduler::new(config, Instant::now());
+    let mut hud = config
+        .platform
+        .windows
+        .show_stats
+        .then(hud::Collector::default);
+    let wake_watch = platform::windows::display_power::WakeWatch::new()
+        .map_err(|err| log::warn!("{}", err))
+        .ok();
+
+    // Unlike the screensaver, the wallpaper keeps running regardless of any input.
+    'main: loop {
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. }
+                | Event::Window {
+                    win_event: sdl2::event::WindowEvent::Close,
+                    ..
+                } => break 'main,
+
+                Event::Display { display_event, .. } => {
+                    log::info!("The displays changed: {:?}", display_event);
+                    display_changed_at = Some(Instant::now());
+                }
+
+                _ => (),
+            }
+        }
 
-    let win32_handle = match raw_window_handle {
-        RawWindowHandle::Win32(handle) => handle,
-        _ => return Err("This platform is not supported yet".to_string()),
+        if display_changed_at.is_some_and(|at| at.elapsed() >= DISPLAY_CHANGE_SETTLE_TIME) {
+            display_changed_at = None;
+            is_layout_stale = true;
+            layout_config
+                .platform
+                .windows
+                .resolve_monitors(&laid_out_monitors);
+            relayout_instances(video_subsystem, instances, &layout_config, &Mode::Wallpaper);
+            laid_out_monitors = video_subsystem.available_monitors().collect();
+
+            // Mask every instance again, for the monitors where they are now.
+            mask_layers = layout_config
+                .platform
+                .windows
+                .live_wallpaper
+                .mask_layers(&laid_out_monitors);
+            last_icon_query = None;
+            masked_instances = 0;
+        }
+
+        // Check every so often whether the icons have been rearranged.
+        if icon_brightness.is_some() || !mask_layers.is_empty() {
+            if icon_brightness.is_some()
+                && last_icon_query
+                    .map_or(true, |query| query.elapsed() >= DESKTOP_ICONS_POLL_INTERVAL)
+            {
+                last_icon_query = Some(Instant::now());
+
+                match platform::windows::desktop_icons::query() {
+                    Ok(new_icons) if new_icons != icons => {
+                        icons = new_icons;
+                        masked_instances = 0;
+                    }
+                    Ok(_) => (),
+                    Err(err) => log::debug!("Failed to find the desktop icons: {}", err),
+                }
+            }
+
+            // Instances are created progressively, so newcomers need a mask too.
+            if masked_instances != instances.len() {
+                for instance in instances.values_mut() {
+                    if let Err(err) = instance.set_mask(
+                        &icons,
+                        icon_brightness.unwrap_or(1.0),
+                        icon_motion,
+                        &mask_layers,
+                    ) {
+                        log::error!("Failed to mask the wallpaper: {}", err);
+                    }
+                }
+                masked_instances = instances.len();
+            }
+        }
+
+        // Hold the animation still while the user is presenting or playing a game, or while any
+        // of the programs listed in the settings are running.
+        if last_do_not_disturb_query.map_or(true, |query| {
+            query.elapsed() >= DO_NOT_DISTURB_POLL_INTERVAL
+        }) {
+            last_do_not_disturb_query = Some(Instant::now());
+            let blocking_process = platform::windows::process::find_running(
+                &config.platform.windows.blocking_processes,
+            )
+            .unwrap_or_else(|err| {
+                log::debug!("{}", err);
+                None
+            });
+            if blocking_process != blocked_by {
+                if let Some(name) = &blocking_process {
+                    log::info!("{} is running", name);
+                }
+                blocked_by = blocking_process;
+            }
+            do_not_disturb = platform::windows::idle::is_do_not_disturb() || blocked_by.is_some();
+        }
+        for instance in instances.values_mut().filter(|instance| instance.shown) {
+            instance.set_do_not_disturb(do_not_disturb);
+        }
+
+        if wake_watch.as_ref().is_some_and(|watch| watch.has_woken()) {
+            for instance in instances.values_mut() {
+                if let Err(err) = instance.wake() {
+                    log::warn!("Failed to clear the frames from before standby: {}", err);
+                }
+            }
+        }
+
+        play_next_scene(instances, playlist.as_mut(), config);
+        // Barely any of the wallpaper shows while the user is busy, so it can take it easy.
+        let idle_time = platform::windows::idle::idle_time().unwrap_or(Duration::MAX);
+        let activity_throttle =
+            power::Throttle::from_activity(&config.platform.windows.live_wallpaper, idle_time);
+        throttle_instances(
+            instances,
+            power.poll(Instant::now()).stricter(activity_throttle),
+        );
+        if is_layout_stale {
+            render_instances(instances, &mut std::iter::empty())?;
+        } else {
+            render_instances(instances, &mut pending_instances)?;
+        }
+        update_hud(hud.as_mut(), instances);
+        requery_wallpapers(instances, config);
+        adapt_palettes(instances, config);
+    }
+
+    Ok(())
+}
+
+// Fade out and turn the displays off, or wake them back up, for the blanking schedule.
+#[cfg(feature = "sdl")]
+fn blank_instances(instances: &mut HashMap<WindowId, Instance>, action: blanking::Action) {
+    match action {
+        blanking::Action::None => (),
+        blanking::Action::Fade(brightness) => {
+            for instance in instances.values_mut() {
+                if let Err(err) = instance.fade_out(config::ExitStyle::FadeToBlack, brightness) {
+                    log::debug!("Failed to fade out: {}", err);
+                }
+            }
+        }
+        blanking::Action::Blank => {
+            log::info!("Turning the displays off");
+            for instance in instances
+                .values_mut()
+                .filter(|instance| instance.stats.paused().is_none())
+            {
+                instance.pause(stats::PauseReason::Blanked);
+            }
+            // The displays all go off together, so any window will do.
+            #[cfg(windows)]
+            if let Some(instance) = instances.values().next() {
+                unsafe {
+                    platform::windows::display_power::turn_off(&instance.window.raw_window_handle())
+                };
+            }
+        }
+        blanking::Action::Wake => {
+            log::info!("Turning the displays back on");
+            #[cfg(windows)]
+            if let Some(instance) = instances.values().next() {
+                unsafe {
+                    platform::windows::display_power::turn_on(&instance.window.raw_window_handle())
+                };
+            }
+            for instance in instances.values_mut() {
+                if instance.stats.paused() == Some(stats::PauseReason::Blanked) {
+                    instance.resume();
+                }
+                // The schedule fades the animation back in from here.
+                if let Err(err) = instance.fade_out(config::ExitStyle::FadeToBlack, 0.0) {
+                    log::debug!("Failed to fade back in: {}", err);
+                }
+                #[cfg(windows)]
+                if let Err(err) = instance.wake() {
+                    log::warn!("Failed to clear the frames from before standby: {}", err);
+                }
+            }
+        }
+    }
+}
+
+// Fade every instance over to the next scene in the playlist, once it’s due.
+#[cfg(feature = "sdl")]
+fn play_next_scene(
+    instances: &mut HashMap<WindowId, Instance>,
+    playlist: Option<&mut playlist::Scheduler>,
+    config: &Config,
+) {
+    let Some(playlist) = playlist else {
+        return;
+    };
+    let crossfade = playlist.crossfade();
+    let Some(flux) = playlist.poll(Instant::now()) else {
+        return;
     };
 
-    let preview_hwnd = HWND(win32_handle.hwnd as _);
+    let mut config = config.clone();
+    config.flux = flux.clone();
+    for instance in instances.values_mut() {
+        if let Err(err) = instance.crossfade_to(&config, crossfade) {
+            log::warn!("Failed to move on to the next scene: {}", err);
+        }
+    }
+}
 
-    let mut rect = RECT::default();
-    unsafe {
-        let _ = GetClientRect(preview_hwnd, &mut rect);
+// Lay out the surfaces again after the monitors have changed. Instances whose surface is still
+// there carry on as they were, the rest are closed, and the new surfaces get instances of their
+// own.
+#[cfg(feature = "sdl")]
+fn relayout_instances(
+    video_subsystem: &sdl2::VideoSubsystem,
+    instances: &mut HashMap<WindowId, Instance>,
+    config: &Config,
+    mode: &Mode,
+) {
+    let surfaces = build_surfaces(video_subsystem, config, mode);
+
+    // The primary monitor may have changed, or gone, so look for its vertical blank again.
+    #[cfg(windows)]
+    PRIMARY_VBLANK.with(|vblank| *vblank.borrow_mut() = None);
+
+    instances.retain(|_, instance| {
+        let is_orphaned = instance
+            .surface
+            .as_ref()
+            .is_some_and(|surface| !surfaces.contains(surface));
+        if is_orphaned {
+            log::info!("Closing the surface at {:?}", instance.position);
+        }
+        !is_orphaned
+    });
+
+    for (index, surface) in surfaces.iter().enumerate() {
+        let is_covered = instances
+            .values()
+            .any(|instance| instance.surface.as_ref() == Some(surface));
+        if is_covered || !mode.shows_surface(index) {
+            continue;
+        }
+
+        log::info!("Adding a surface at {:?}", surface.position());
+        for instance in new_surface_instances(video_subsystem, config, surface, mode) {
+            match instance {
+                Ok(mut instance) => {
+                    if let Some(epoch) = shared_epoch(instances) {
+                        instance.clock = clock::Clock::in_step_with(epoch, Instant::now());
+                    }
+                    instances.insert(instance.window.id(), instance);
+                }
+                Err(err) => log::error!("Failed to create a window for the new monitor: {}", err),
+            }
+        }
+    }
+}
+
+// Switch to the wallpaper colors for any instance whose wallpaper has turned up since it started,
+// or whose wallpaper slideshow has moved on to another image.
+#[cfg(all(windows, feature = "sdl"))]
+fn requery_wallpapers(instances: &mut HashMap<WindowId, Instance>, config: &Config) {
+    let now = Instant::now();
+
+    for instance in instances.values_mut().filter(|instance| instance.shown) {
+        if let Some(requery) = instance.wallpaper_requery.as_mut() {
+            let monitor = requery.monitor().clone();
+            let found = requery.poll(now);
+            if requery.is_exhausted() || found.is_some() {
+                instance.wallpaper_requery = None;
+            }
+
+            if let Some(wallpaper) = found {
+                log::info!("Found the wallpaper {}", wallpaper.display());
+                if let Err(err) = instance.set_wallpaper(config, &wallpaper) {
+                    log::warn!("Failed to switch to the wallpaper colors: {}", err);
+                }
+                instance.slideshow = wallpaper::SlideshowWatch::start(monitor, wallpaper, now);
+            }
+        }
+
+        if let Some(wallpaper) = instance
+            .slideshow
+            .as_mut()
+            .and_then(|slideshow| slideshow.poll(now))
+        {
+            log::info!("The slideshow moved on to {}", wallpaper.display());
+            if let Err(err) = instance.set_wallpaper(config, &wallpaper) {
+                log::warn!("Failed to switch to the new wallpaper colors: {}", err);
+            }
+        }
+    }
+}
+
+// Put what Flux was launched with on top of the settings file: the preset picked with `--preset`,
+// the overrides from the environment, and safe mode. The settings are read again when the file
+// changes, which needs these put back on top.
+fn apply_launch_overrides(config: &mut Config) -> Result<(), String> {
+    // A saved look to run with, underneath any overrides
+    if let Some(name) = cli::read_preset()? {
+        let presets = Presets::load(config);
+        let preset = presets
+            .find(&name)
+            .ok_or_else(|| format!("There’s no preset called {}", name))?;
+        preset.apply(&mut config.flux);
+    }
+
+    if let Err(err) = config.apply_overrides(&Config::env_overrides()) {
+        log::warn!("{}", err);
+    }
+
+    if watchdog::is_safe_mode() {
+        watchdog::apply_safe_mode(config);
+    }
+
+    Ok(())
+}
+
+// Put the launch overrides back on top of settings read again from the file. They applied at
+// startup, so they can only fail the same way again.
+#[cfg(feature = "sdl")]
+fn reapply_launch_overrides(config: &mut Config) {
+    if let Err(err) = apply_launch_overrides(config) {
+        log::warn!("{}", err);
+    }
+}
+
+// Restart every instance with new Flux settings, returning the settings they now run with.
+#[cfg(feature = "sdl")]
+fn switch_settings(
+    instances: &mut HashMap<WindowId, Instance>,
+    config: &Config,
+    flux: config::FluxSettings,
+) -> Config {
+    let mut new_config = config.clone();
+    new_config.flux = flux;
+    for instance in instances.values_mut() {
+        if let Err(err) = instance.restart_flux(&new_config) {
+            log::warn!("Failed to switch to the new settings: {}", err);
+        }
+    }
+    new_config
+}
+
+// Fade each instance over to colors closer to what’s on its monitor, whenever a new capture is in.
+#[cfg(all(windows, feature = "sdl"))]
+fn adapt_palettes(instances: &mut HashMap<WindowId, Instance>, config: &Config) {
+    let now = Instant::now();
+
+    for instance in instances.values_mut().filter(|instance| instance.shown) {
+        let Some(adaptive_palette) = instance.adaptive_palette.as_mut() else {
+            continue;
+        };
+        let size = instance.window.inner_size();
+        let center = (
+            instance.position.x + (size.width / 2) as i32,
+            instance.position.y + (size.height / 2) as i32,
+        );
+        if adaptive_palette.poll(now, move || {
+            platform::windows::screen_capture::capture_monitor(center, PALETTE_CAPTURE_SIDE)
+        }) {
+            if let Err(err) = instance.crossfade_to(config, PALETTE_CROSSFADE) {
+                log::warn!("Failed to fade over to the adaptive palette: {}", err);
+            }
+        }
+    }
+}
+
+// Apply the power throttle to the instances that are up and running.
+#[cfg(feature = "sdl")]
+fn throttle_instances(instances: &mut HashMap<WindowId, Instance>, throttle: power::Throttle) {
+    for instance in instances.values_mut().filter(|instance| instance.shown) {
+        instance.set_throttle(throttle);
+    }
+}
+
+// Where the clocks of the running instances count from.
+#[cfg(feature = "sdl")]
+fn shared_epoch(instances: &HashMap<WindowId, Instance>) -> Option<Instant> {
+    instances
+        .values()
+        .map(|instance| instance.clock.epoch())
+        .min()
+}
+
+// Show the latest stats over every instance, every so often.
+#[cfg(feature = "sdl")]
+fn update_hud(hud: Option<&mut hud::Collector>, instances: &mut HashMap<WindowId, Instance>) {
+    let Some(hud) = hud else {
+        return;
+    };
+
+    // Number the instances in the same order every time.
+    let mut ids = instances.keys().copied().collect::<Vec<WindowId>>();
+    ids.sort_unstable();
+    let report = hud.report(
+        Instant::now(),
+        ids.iter()
+            .filter_map(|id| instances.get(id))
+            .map(|instance| (instance.gpu.as_str(), &instance.stats)),
+    );
+
+    if let Some(report) = report {
+        for instance in instances.values_mut() {
+            if let Err(err) = instance.set_stats(report.clone()) {
+                log::debug!("Failed to show the stats: {}", err);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "sdl")]
+fn render_instances(
+    instances: &mut HashMap<WindowId, Instance>,
+    pending_instances: &mut impl Iterator<Item = Result<Instance, String>>,
+) -> Result<(), String> {
+    // Bring up the instances one at a time, so that the first monitors start animating without
+    // waiting for the rest to initialize.
+    if let Some(instance) = pending_instances.next() {
+        let mut instance = instance?;
+        // Count the start delay from the moment the instance is ready, but keep time with the
+        // instances that are already running, so that the monitors show the same moment.
+        let start = Instant::now() + instance.start_delay;
+        instance.clock = match shared_epoch(instances) {
+            Some(epoch) => clock::Clock::in_step_with(epoch, start),
+            None => clock::Clock::new(start),
+        };
+        instances.insert(instance.window.id(), instance);
+    }
+
+    // Start every frame that’s due before waiting on any of them, so that the monitors render
+    // side by side on their own threads. The threads wait at the gate until all of them have
+    // their frame, and then start together.
+    let now = Instant::now();
+    let due = instances
+        .iter()
+        .filter(|(_, instance)| !instance.clock.is_paused() && instance.is_frame_due(now))
+        .map(|(id, _)| *id)
+        .collect::<Vec<WindowId>>();
+    // Presenting together only makes a difference with more than one monitor to line up.
+    let sync_presents = due
+        .iter()
+        .filter(|id| instances[*id].sync_presents && instances[*id].shown)
+        .count()
+        > 1;
+    let gate = (due.len() > 1).then(render_thread::StartGate::default);
+    let mut started = Vec::new();
+    let mut held = Vec::new();
+    for id in due {
+        let Some(instance) = instances.get_mut(&id) else {
+            continue;
+        };
+
+        // Keep the window blank until it’s this instance’s turn to start
+        let timestamp = instance.clock.timestamp(now);
+        let hold = sync_presents && instance.sync_presents && instance.shown && timestamp.is_some();
+        instance.start_frame(timestamp, hold, gate.as_ref());
+        started.push(id);
+        if hold {
+            held.push(id);
+        }
+    }
+    if let Some(gate) = &gate {
+        gate.open();
+    }
+    let rendered_any = !started.is_empty();
+
+    for id in started {
+        let Some(instance) = instances.get_mut(&id) else {
+            continue;
+        };
+
+        match instance.finish_frame() {
+            Ok(()) if !instance.shown => instance.show(),
+            Ok(()) => (),
+            Err(err) => log::error!("Failed to render Flux: {}", err),
+        }
+    }
+    present_together(instances, &held);
+
+    // Nothing is blocking on vsync, so sleep until the next frame is due, instead of spinning.
+    if !rendered_any {
+        // Paused instances have nothing to render, so only wake up to check for events.
+        let all_paused = !instances.is_empty()
+            && instances
+                .values()
+                .all(|instance| instance.clock.is_paused());
+        let fallback = if all_paused {
+            PAUSED_POLL_INTERVAL
+        } else {
+            Duration::from_millis(1)
+        };
+
+        let next_frame_at = instances
+            .values()
+            .filter(|instance| !instance.clock.is_paused())
+            .filter_map(Instance::next_frame_at)
+            .min()
+            .unwrap_or_else(|| Instant::now() + fallback);
+        clock::sleep_until(next_frame_at);
+    }
+
+    Ok(())
+}
+
+// Release the held frames right after the vertical blank of the primary monitor, so that they all
+// make it onto the next one together, instead of tearing against each other where the monitors
+// meet. The render threads wait for the vertical blank, so that the main thread keeps answering
+// window messages in the meantime.
+#[cfg(feature = "sdl")]
+fn present_together(instances: &mut HashMap<WindowId, Instance>, held: &[WindowId]) {
+    if held.is_empty() {
+        return;
+    }
+
+    let gate = render_thread::StartGate::default();
+    for id in held {
+        if let Some(instance) = instances.get_mut(id) {
+            instance.present_held(&gate);
+        }
+    }
+
+    #[cfg(windows)]
+    let is_waiting = PRIMARY_VBLANK.with(|vblank| {
+        let mut vblank = vblank.borrow_mut();
+        let sender = vblank.get_or_insert_with(|| Some(start_vblank_thread()));
+        let is_waiting = sender
+            .as_ref()
+            .is_some_and(|sender| sender.send(gate.clone()).is_ok());
+        // The thread stops if it can’t wait on the vertical blank, so stop asking it to.
+        if !is_waiting {
+            *sender = None;
+        }
+        is_waiting
+    });
+    #[cfg(not(windows))]
+    let is_waiting = false;
+    if !is_waiting {
+        gate.open();
+    }
+
+    for id in held {
+        if let Some(instance) = instances.get_mut(id) {
+            if let Err(err) = instance.renderer.wait() {
+                log::error!("Failed to present the frame: {}", err);
+            }
+        }
+    }
+}
+
+// Open each gate sent over right after the next vertical blank of the primary monitor. The
+// thread stops once the sender is dropped, or the vertical blank can’t be waited on.
+#[cfg(all(windows, feature = "sdl"))]
+fn start_vblank_thread() -> std::sync::mpsc::Sender<render_thread::StartGate> {
+    let (sender, receiver) = std::sync::mpsc::channel::<render_thread::StartGate>();
+    std::thread::spawn(move || {
+        let vblank = match platform::windows::dxgi_swapchain::VBlank::primary() {
+            Ok(vblank) => vblank,
+            Err(err) => {
+                log::warn!("{}", err);
+                receiver.try_iter().for_each(|gate| gate.open());
+                return;
+            }
+        };
+        for gate in &receiver {
+            let result = vblank.wait();
+            // Present anyway, rather than hold the frames back for good.
+            gate.open();
+            if let Err(err) = result {
+                log::debug!("{}", err);
+                receiver.try_iter().for_each(|gate| gate.open());
+                return;
+            }
+        }
+    });
+    sender
+}
+
+fn build_surfaces(
+    display: &impl HasMonitors,
+    config: &Config,
+    mode: &Mode,
+) -> Vec<surface::Surface> {
+    // Leave the taskbar and any docked toolbars over the static wallpaper, unless asked not to.
+    let available_monitors: Box<dyn Iterator<Item = MonitorHandle>> =
+        if mode.is_wallpaper() && !config.platform.windows.live_wallpaper.full_bleed {
+            Box::new(display.available_work_areas())
+        } else {
+            Box::new(display.available_monitors())
+        };
+
+    // Explorer might not have the wallpaper API up yet, but a requery waits for it off the main
+    // thread.
+    #[cfg(windows)]
+    let wallpaper_api = wallpaper::DesktopWallpaper::new()
+        .map_err(|err| log::warn!("Failed to connect to the wallpaper API: {}", err))
+        .ok();
+    let monitors = available_monitors
+        .filter(|monitor| {
+            let is_excluded = config.platform.windows.is_excluded(monitor);
+            if is_excluded {
+                log::debug!("Leaving out the excluded monitor {:?}", monitor);
+            }
+            !is_excluded
+        })
+        .map(|monitor| {
+            #[cfg(windows)]
+            let wallpaper = wallpaper_api
+                .as_ref()
+                .and_then(|wallpaper| wallpaper.get(&monitor).ok());
+            #[cfg(not(windows))]
+            let wallpaper = None;
+
+            (monitor, wallpaper)
+        })
+        .collect::<Vec<(MonitorHandle, Option<std::path::PathBuf>)>>();
+    log::debug!("Available monitors: {:?}", monitors);
+
+    #[cfg(windows)]
+    let fill_mode = match IS_SPAN_DOWNGRADED.with(std::cell::Cell::get) {
+        true => config::FillMode::None,
+        false => config.platform.windows.fill_mode,
+    };
+    #[cfg(not(windows))]
+    let fill_mode = config::FillMode::None;
+    let mut surfaces = surface::build(
+        &monitors,
+        &surface::Layout {
+            fill_mode,
+            ..surface::Layout::from_config(&config.platform.windows)
+        },
+    );
+    // Start with the primary monitor, which sits at the origin of the virtual screen.
+    surfaces.sort_by_key(|surface| !surface.contains(PhysicalPosition::new(0, 0)));
+    log::debug!("Creating windows: {:?}", surfaces);
+
+    surfaces
+}
+
+// Lazily create an instance for each surface.
+#[cfg(feature = "sdl")]
+fn new_instances<'a>(
+    video_subsystem: &'a sdl2::VideoSubsystem,
+    config: &'a Config,
+    surfaces: &'a [surface::Surface],
+    mode: &'a Mode,
+) -> impl Iterator<Item = Result<Instance, String>> + 'a {
+    // Stagger the start of each instance to avoid rendering the expensive first frames of every
+    // simulation at the same time.
+    let start_delay = Duration::from_millis(config.platform.windows.start_delay_ms.into());
+    let monitor_stagger = Duration::from_millis(config.platform.windows.monitor_stagger_ms.into());
+
+    surfaces
+        .iter()
+        .enumerate()
+        .filter(move |(index, _)| mode.shows_surface(*index))
+        .flat_map(move |(index, surface)| {
+            new_surface_instances(video_subsystem, config, surface, mode)
+                .into_iter()
+                .map(move |instance| {
+                    instance.map(|mut instance| {
+                        instance.start_delay = start_delay + monitor_stagger * index as u32;
+
+                        // The GPU is only known once there’s a context to ask.
+                        if index == 0 {
+                            ping::send_if_due(config, &instance.gpu);
+                        }
+
+                        instance
+                    })
+                })
+        })
+}
+
+// Create the instance for a surface. A window spanning several monitors can fail to come up, like
+// past the texture size limit of the driver or on a virtual GPU, so fall back to a window for each
+// of the monitors instead of giving up on the screensaver.
+#[cfg(feature = "sdl")]
+fn new_surface_instances(
+    video_subsystem: &sdl2::VideoSubsystem,
+    config: &Config,
+    surface: &surface::Surface,
+    mode: &Mode,
+) -> Vec<Result<Instance, String>> {
+    let err = match new_instance(video_subsystem, config, surface, mode) {
+        Ok(instance) => return vec![Ok(instance)],
+        Err(err) => err,
+    };
+
+    let mut monitor_config = config.clone();
+    monitor_config.platform.windows.fill_mode = config::FillMode::None;
+    let monitor_surfaces = build_surfaces(video_subsystem, &monitor_config, mode)
+        .into_iter()
+        .filter(|monitor_surface| surface.contains(monitor_surface.position()))
+        .collect::<Vec<_>>();
+    if monitor_surfaces.len() < 2 {
+        return vec![Err(err)];
     }
 
-    let inner_size = PhysicalSize::new(rect.right as u32, rect.bottom as u32);
+    log::warn!(
+        "Failed to create a window across {} monitors, so showing each monitor on its own: {}",
+        monitor_surfaces.len(),
+        err
+    );
+    IS_SPAN_DOWNGRADED.with(|is_downgraded| is_downgraded.set(true));
+    monitor_surfaces
+        .iter()
+        .map(|monitor_surface| new_instance(video_subsystem, config, monitor_surface, mode))
+        .collect()
+}
+
+#[cfg(all(windows, feature = "sdl"))]
+fn new_preview_window(
+    video_subsystem: &sdl2::VideoSubsystem,
+    raw_window_handle: RawWindowHandle,
+    config: &Config,
+) -> Result<Instance, String> {
+    let preview_hwnd = preview_hwnd(raw_window_handle)?;
+    let inner_size = unsafe { platform::windows::window::client_size(preview_hwnd) }
+        .ok_or("The preview window has gone away")?;
 
     // You need to create an actual window to listen to events. We’ll
     // then link this to the preview window as a child to cleanup when
@@ -367,149 +2548,387 @@ fn new_preview_window(
         _ => (),
     }
 
-    let gl_context = gl_context::new_gl_context(
-        window.raw_display_handle(),
-        inner_size,
-        raw_window_handle,
-        Some(window.raw_window_handle()),
-    );
+    let current_monitor = window
+        .current_monitor()
+        .or_else(|| video_subsystem.available_monitors().next());
+    let wallpaper = current_monitor
+        .and_then(|monitor| wallpaper::DesktopWallpaper::new().ok()?.get(&monitor).ok());
+
+    // Render into the preview window, with the pixel format of our own window.
+    let handles = render_thread::Handles {
+        display: window.raw_display_handle(),
+        window: raw_window_handle,
+        attr_window: Some(window.raw_window_handle()),
+    };
+    let options = render_thread::Options {
+        size: inner_size,
+        scale_factor: window.scale_factor(),
+        wallpaper,
+        compositor_opacity: None,
+        rotation: config::Rotation::None,
+        tiles: (1, 1),
+        // The preview pane can change size along with the dialog around it, but isn’t dragged
+        // about like a regular window, so it presents through the swapchain and resizes it.
+        resizable: false,
+        letterbox: Vec::new(),
+    };
 
-    let swapchain = create_swapchain(&raw_window_handle, &gl_context);
+    Instance::new(window, config, handles, options)
+}
 
-    let some_current_monitor = window.current_monitor();
-    let current_monitor_index = some_current_monitor
-        .and_then(|current_monitor| {
-            video_subsystem
-                .available_monitors()
-                .position(|monitor| monitor == current_monitor)
-                .map(|index| index as u32)
-        })
-        .unwrap_or(0);
-    let wallpaper = wallpaper::DesktopWallpaper::new()
-        .ok()
-        .and_then(|wallpaper| wallpaper.get(current_monitor_index).ok());
+#[cfg(all(windows, feature = "sdl"))]
+fn preview_hwnd(raw_window_handle: RawWindowHandle) -> Result<HWND, String> {
+    match raw_window_handle {
+        RawWindowHandle::Win32(handle) => Ok(HWND(handle.hwnd as _)),
+        _ => Err("This platform is not supported yet".to_string()),
+    }
+}
 
-    let physical_size = window.inner_size();
-    let scale_factor = window.scale_factor();
-    let logical_size = physical_size.to_logical(scale_factor);
-    let settings = config.to_settings(wallpaper);
-    let flux = Flux::new(
-        &gl_context.gl,
-        logical_size.width,
-        logical_size.height,
-        physical_size.width,
-        physical_size.height,
-        &Rc::new(settings),
-    )
-    .map_err(|err| err.to_string())?;
+// Draw into a window created by XScreenSaver.
+#[cfg(all(target_os = "linux", feature = "sdl"))]
+fn new_xscreensaver_instance(
+    video_subsystem: &sdl2::VideoSubsystem,
+    window_id: u64,
+    config: &Config,
+) -> Result<Instance, String> {
+    // Wrapping the window in SDL gets us its size and events, including the quit event that SDL
+    // sends when XScreenSaver terminates the hack.
+    let window = unsafe {
+        let raw = sdl2::sys::SDL_CreateWindowFrom(window_id as *const std::ffi::c_void);
+        if raw.is_null() {
+            return Err(format!(
+                "Failed to use the window {:#x}: {}",
+                window_id,
+                sdl2::get_error()
+            ));
+        }
+        Window::from_ll(video_subsystem.clone(), raw)
+    };
+    let handles = render_thread::Handles {
+        display: window.raw_display_handle(),
+        window: window.raw_window_handle(),
+        attr_window: None,
+    };
+    let options = render_thread::Options {
+        size: window.inner_size(),
+        scale_factor: window.scale_factor(),
+        wallpaper: None,
+        compositor_opacity: None,
+        rotation: config::Rotation::None,
+        tiles: (1, 1),
+        resizable: false,
+        letterbox: Vec::new(),
+    };
 
-    Ok(Instance {
-        flux,
-        gl_context,
-        window,
-        swapchain,
-    })
+    let mut instance = Instance::new(window, config, handles, options)?;
+    // XScreenSaver has already mapped the window.
+    instance.shown = true;
+
+    Ok(instance)
 }
 
+#[cfg(feature = "sdl")]
 fn new_instance(
     video_subsystem: &sdl2::VideoSubsystem,
     config: &Config,
     surface: &surface::Surface,
+    mode: &Mode,
 ) -> Result<Instance, String> {
+    let is_wallpaper = mode.is_wallpaper();
+
+    // Look through the Spotlight cache while the window is set up, since it decides whether the
+    // colors might come from the wallpaper.
+    #[cfg(windows)]
+    let spotlight_lookup = config
+        .uses_spotlight()
+        .then(|| std::thread::spawn(|| spotlight::find().is_some()));
+
     // Create the SDL window
-    let window = video_subsystem
-        .window("Flux", surface.size().width, surface.size().height)
+    let mut window_builder =
+        video_subsystem.window("Flux", surface.size().width, surface.size().height);
+    window_builder
         .position(surface.position().x, surface.position().y)
-        .input_grabbed()
         .borderless()
         .hidden()
         .allow_highdpi()
-        .metal_view()
-        .build()
-        .map_err(|err| err.to_string())?;
+        .metal_view();
+
+    // The wallpaper sits behind the desktop and shouldn’t capture any input.
+    if !is_wallpaper {
+        window_builder.input_grabbed();
+    }
+
+    let window = window_builder.build().map_err(|err| err.to_string())?;
 
     #[cfg(windows)]
     unsafe {
-        platform::windows::window::enable_transparency(&window.raw_window_handle())
+        if is_wallpaper {
+            platform::windows::window::attach_to_desktop(
+                &window.raw_window_handle(),
+                surface.position(),
+                surface.size(),
+            )?;
+        } else {
+            platform::windows::window::enable_transparency(&window.raw_window_handle());
+        }
+
+        if let Some(clip) = surface.clip() {
+            if let Err(err) = platform::windows::window::clip_to(
+                &window.raw_window_handle(),
+                surface.position(),
+                clip,
+            ) {
+                log::warn!("{}", err);
+            }
+        }
     };
 
-    let gl_context = gl_context::new_gl_context(
-        window.raw_display_handle(),
-        window.size().into(),
-        window.raw_window_handle(),
-        None,
-    );
+    let physical_scale_factor = if config.platform.windows.uniform_physical_density {
+        let physical_scale_factor = surface.physical_scale_factor();
+        if physical_scale_factor.is_none() {
+            log::info!("The monitor doesn’t report its physical size, so using its scale factor");
+        }
+        physical_scale_factor
+    } else {
+        None
+    };
 
-    let swapchain = create_swapchain(&window.raw_window_handle(), &gl_context);
-
-    let physical_size = surface.size();
-    let logical_size = physical_size.to_logical(surface.scale_factor());
-    let settings = config.to_settings(surface.wallpaper().clone());
-    let flux = Flux::new(
-        &Rc::clone(&gl_context.gl),
-        logical_size.width,
-        logical_size.height,
-        physical_size.width,
-        physical_size.height,
-        &Rc::new(settings),
-    )
-    .map_err(|err| err.to_string())?;
-
-    Ok(Instance {
-        flux,
-        gl_context,
-        window,
-        swapchain,
-    })
-}
-
-#[cfg(not(windows))]
-fn create_swapchain(
-    _raw_window_handle: &RawWindowHandle,
-    gl_context: &gl_context::GLContext,
-) -> Swapchain {
-    use glutin::surface::SwapInterval;
-    use std::num::NonZeroU32;
-
-    // Try setting vsync.
-    if let Err(res) = gl_context.surface.set_swap_interval(
-        &gl_context.context,
-        SwapInterval::Wait(NonZeroU32::new(1).unwrap()),
-    ) {
-        log::error!("Failed to set vsync: {res:?}");
+    // The monitors that the surface covers
+    let monitors: Vec<MonitorHandle> = video_subsystem
+        .available_monitors()
+        .filter(|monitor| {
+            let center = PhysicalPosition::new(
+                monitor.position().x + monitor.size().width as i32 / 2,
+                monitor.position().y + monitor.size().height as i32 / 2,
+            );
+            surface.contains(center)
+        })
+        .collect();
+
+    // Take the colors from the image assigned to the monitor, if there is one.
+    let color_image = config
+        .platform
+        .windows
+        .monitor_image(&monitors)
+        .map(path::Path::to_path_buf);
+    let surface_config;
+    let config = match &color_image {
+        Some(image_path) => {
+            surface_config = config.with_color_image(image_path);
+            &surface_config
+        }
+        None => config,
+    };
+
+    // Explorer might not have been ready to tell us the wallpaper yet, so check back later. If
+    // the wallpaper is a slideshow, keep following it instead.
+    #[cfg(windows)]
+    let (wallpaper_requery, slideshow) = {
+        let has_spotlight_image =
+            spotlight_lookup.is_some_and(|lookup| lookup.join().unwrap_or(false));
+        let monitor = monitors
+            .first()
+            .cloned()
+            .filter(|_| config.prefers_desktop_image(has_spotlight_image));
+
+        match (monitor, surface.wallpaper()) {
+            (Some(monitor), None) => (Some(wallpaper::Requery::new(monitor, Instant::now())), None),
+            (Some(monitor), Some(wallpaper)) => (
+                None,
+                wallpaper::SlideshowWatch::start(monitor, wallpaper.clone(), Instant::now()),
+            ),
+            (None, _) => (None, None),
+        }
+    };
+
+    // Blend with the existing wallpaper, or dim parts of it, if requested. The compositor also
+    // blends between simulation steps on fast displays.
+    let live_wallpaper = &config.platform.windows.live_wallpaper;
+    let opacity = live_wallpaper.opacity;
+    let compositor_opacity =
+        (is_wallpaper && (opacity < 1.0 || live_wallpaper.is_masked())).then_some(opacity);
+
+    let handles = render_thread::Handles {
+        display: window.raw_display_handle(),
+        window: window.raw_window_handle(),
+        attr_window: None,
+    };
+    let options = render_thread::Options {
+        size: surface.size(),
+        scale_factor: physical_scale_factor.unwrap_or(surface.scale_factor()),
+        wallpaper: surface.wallpaper().clone(),
+        compositor_opacity,
+        rotation: surface.rotation(),
+        tiles: surface.tiles(),
+        resizable: false,
+        letterbox: surface
+            .letterbox()
+            .into_iter()
+            .map(|((position, size), scale)| compositor::Letterbox {
+                position: PhysicalPosition::new(
+                    position.x - surface.position().x,
+                    position.y - surface.position().y,
+                ),
+                size,
+                scale: scale as f32,
+            })
+            .collect(),
+    };
+
+    let mut instance = Instance::new(window, config, handles, options)?;
+    instance.position = surface.position();
+    instance.surface = Some(surface.clone());
+    instance.physical_scale_factor = physical_scale_factor;
+    instance.color_image = color_image;
+    #[cfg(windows)]
+    {
+        instance.wallpaper_requery = wallpaper_requery;
+        instance.slideshow = slideshow;
+        // An image picked for the monitor wins over the adaptive palette.
+        instance.adaptive_palette =
+            (is_wallpaper && live_wallpaper.adaptive_palette && instance.color_image.is_none())
+                .then(|| {
+                    // One image for each monitor, overwritten as the colors change
+                    let image_path = std::env::temp_dir().join(format!(
+                        "flux-adaptive-palette-{}x{}.png",
+                        surface.position().x,
+                        surface.position().y
+                    ));
+                    palette::Adaptive::new(
+                        Duration::from_secs(live_wallpaper.adaptive_palette_interval_secs.into()),
+                        Instant::now(),
+                        image_path,
+                    )
+                });
     }
 
-    Swapchain::Gl
+    Ok(instance)
 }
 
-#[cfg(windows)]
-fn create_swapchain(
-    raw_window_handle: &RawWindowHandle,
-    gl_context: &gl_context::GLContext,
-) -> Swapchain {
-    let dxgi_interop =
-        platform::windows::dxgi_swapchain::create_dxgi_swapchain(raw_window_handle, &gl_context.gl);
-
-    match dxgi_interop {
-        Ok(dxgi_interop) => Swapchain::Dxgi(dxgi_interop),
-        Err(err) => {
-            use glutin::surface::SwapInterval;
-            use std::num::NonZeroU32;
+// Create a regular, resizable window, for trying out Flux outside of the screensaver.
+#[cfg(feature = "sdl")]
+fn new_windowed_instance(
+    video_subsystem: &sdl2::VideoSubsystem,
+    config: &Config,
+) -> Result<Instance, String> {
+    let window = video_subsystem
+        .window("Flux", WINDOWED_SIZE.width, WINDOWED_SIZE.height)
+        .position_centered()
+        .resizable()
+        .hidden()
+        .allow_highdpi()
+        .metal_view()
+        .build()
+        .map_err(|err| err.to_string())?;
 
-            log::warn!(
-                "Failed to create DXGI swapchain: {}. Falling back to GL.",
-                err
-            );
+    let handles = render_thread::Handles {
+        display: window.raw_display_handle(),
+        window: window.raw_window_handle(),
+        attr_window: None,
+    };
+    let options = render_thread::Options {
+        size: window.inner_size(),
+        scale_factor: window.scale_factor(),
+        wallpaper: None,
+        compositor_opacity: None,
+        rotation: config::Rotation::None,
+        tiles: (1, 1),
+        resizable: true,
+        letterbox: Vec::new(),
+    };
 
-            // Try setting vsync.
-            if let Err(res) = gl_context.surface.set_swap_interval(
-                &gl_context.context,
-                SwapInterval::Wait(NonZeroU32::new(1).unwrap()),
-            ) {
-                log::error!("Failed to set vsync: {res:?}");
-            }
+    Instance::new(window, config, handles, options)
+}
 
-            Swapchain::Gl
+// Create Flux with the preferred color source, falling back through the configured alternatives
+// if one fails to load.
+fn new_flux(
+    gl: &Rc<glow::Context>,
+    config: &Config,
+    wallpaper: Option<&path::Path>,
+    physical_size: PhysicalSize<u32>,
+    scale_factor: f64,
+) -> Result<Flux, String> {
+    let logical_size = physical_size.to_logical(scale_factor);
+    let renderer = gpu::renderer(gl);
+
+    // Without a wallpaper image, the desktop might have a solid color background instead. Only
+    // ask, since it takes a trip to Explorer, if the colors might come from the desktop.
+    #[cfg(windows)]
+    let background_color = (wallpaper.is_none() && config.uses_desktop_image())
+        .then(|| {
+            wallpaper::DesktopWallpaper::new()
+                .ok()
+                .filter(wallpaper::DesktopWallpaper::is_solid_color)
+                .and_then(|desktop| desktop.background_color().ok())
+        })
+        .flatten();
+    #[cfg(not(windows))]
+    let background_color = None;
+
+    let mut last_error = String::new();
+    for color_mode in config.color_modes(wallpaper, background_color) {
+        let settings = config.to_settings(color_mode, &renderer, physical_size);
+        match Flux::new(
+            gl,
+            logical_size.width,
+            logical_size.height,
+            physical_size.width,
+            physical_size.height,
+            &Rc::new(settings),
+        ) {
+            Ok(flux) => return Ok(flux),
+            Err(err) => {
+                log::warn!(
+                    "Failed to start Flux: {}. Trying the next color source.",
+                    err
+                );
+                last_error = err.to_string();
+            }
         }
     }
+
+    Err(last_error)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::cell::Cell;
+
+    #[test]
+    fn it_locks_after_the_screensaver_fails() {
+        let locked = Cell::new(false);
+        let result = lock_after(Err("Failed to draw".to_string()), true, || {
+            locked.set(true);
+            Ok(())
+        });
+
+        assert!(locked.get());
+        assert_eq!(result, Err("Failed to draw".to_string()));
+    }
+
+    #[test]
+    fn it_keeps_the_screensaver_error_when_locking_fails_too() {
+        let result = lock_after(Err("Failed to draw".to_string()), true, || {
+            Err("Failed to lock".to_string())
+        });
+
+        assert_eq!(result, Err("Failed to draw".to_string()));
+    }
+
+    #[test]
+    fn it_reports_a_failure_to_lock() {
+        let result = lock_after(Ok(()), true, || Err("Failed to lock".to_string()));
+
+        assert_eq!(result, Err("Failed to lock".to_string()));
+    }
+
+    #[test]
+    fn it_only_locks_when_asked_to() {
+        let result = lock_after(Ok(()), false, || panic!("Locked the workstation"));
+
+        assert_eq!(result, Ok(()));
+    }
 }
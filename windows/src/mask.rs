@@ -0,0 +1,375 @@
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+// The size of each mask cell in physical pixels. The mask is sampled with linear filtering, so a
+// coarse grid is plenty.
+const CELL_SIZE: u32 = 32;
+
+// How many cells to spread each region over, so that the edges of the mask are soft.
+const BLUR_RADIUS: i32 = 2;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Region {
+    pub position: PhysicalPosition<i32>,
+    pub size: PhysicalSize<u32>,
+}
+
+/// A low-resolution brightness mask covering a surface.
+#[derive(Debug, PartialEq)]
+pub struct Mask {
+    pub width: u32,
+    pub height: u32,
+    // Brightness of each cell, from 0 (black) to 255 (unchanged), in rows from top to bottom.
+    pub values: Vec<u8>,
+}
+
+impl Mask {
+    /// Build a mask for the surface at `position` that dims the given regions.
+    ///
+    /// Areas covered by many regions end up darker than areas with a single isolated region, with
+    /// `brightness` being the darkest the mask can go.
+    pub fn from_regions(
+        position: PhysicalPosition<i32>,
+        size: PhysicalSize<u32>,
+        regions: &[Region],
+        brightness: f32,
+    ) -> Self {
+        let width = size.width.div_ceil(CELL_SIZE).max(1);
+        let height = size.height.div_ceil(CELL_SIZE).max(1);
+
+        // How much of each cell is covered by regions
+        let mut coverage = vec![0.0f32; (width * height) as usize];
+        for region in regions {
+            let left = region.position.x - position.x;
+            let top = region.position.y - position.y;
+            let right = left + region.size.width as i32;
+            let bottom = top + region.size.height as i32;
+
+            for y in 0..height as i32 {
+                let cell_top = y * CELL_SIZE as i32;
+                let overlap_y =
+                    (bottom.min(cell_top + CELL_SIZE as i32) - top.max(cell_top)).max(0);
+                if overlap_y == 0 {
+                    continue;
+                }
+
+                for x in 0..width as i32 {
+                    let cell_left = x * CELL_SIZE as i32;
+                    let overlap_x =
+                        (right.min(cell_left + CELL_SIZE as i32) - left.max(cell_left)).max(0);
+                    coverage[(y * width as i32 + x) as usize] +=
+                        (overlap_x * overlap_y) as f32 / (CELL_SIZE * CELL_SIZE) as f32;
+                }
+            }
+        }
+
+        let density = blur(&coverage, width as i32, height as i32);
+        let brightness = brightness.clamp(0.0, 1.0);
+        let values = density
+            .iter()
+            .map(|density| {
+                let value = 1.0 - density.min(1.0) * (1.0 - brightness);
+                (value * 255.0).round() as u8
+            })
+            .collect();
+
+        Self {
+            width,
+            height,
+            values,
+        }
+    }
+}
+
+/// Bright edges around a dim middle, like the ambient lighting strips behind a monitor.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EdgeLighting {
+    /// How far in from the edges the light reaches, as a fraction of the shorter side
+    pub width: f32,
+    /// How dark the middle gets, from 0 (black) to 1 (unchanged)
+    pub center_brightness: f32,
+}
+
+impl Mask {
+    /// Build a mask that leaves the edges of the surface bright, and fades to `center_brightness`
+    /// further in.
+    pub fn from_edge_lighting(size: PhysicalSize<u32>, edge_lighting: &EdgeLighting) -> Self {
+        let width = size.width.div_ceil(CELL_SIZE).max(1);
+        let height = size.height.div_ceil(CELL_SIZE).max(1);
+        let reach = (edge_lighting.width.max(0.0) * size.width.min(size.height) as f32).max(1.0);
+        let center_brightness = edge_lighting.center_brightness.clamp(0.0, 1.0);
+
+        let mut values = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                // The distance from the middle of the cell to the nearest edge
+                let center_x = ((x * CELL_SIZE + CELL_SIZE / 2) as f32).min(size.width as f32);
+                let center_y = ((y * CELL_SIZE + CELL_SIZE / 2) as f32).min(size.height as f32);
+                let distance = center_x
+                    .min(size.width as f32 - center_x)
+                    .min(center_y)
+                    .min(size.height as f32 - center_y);
+
+                let t = (distance / reach).clamp(0.0, 1.0);
+                let falloff = t * t * (3.0 - 2.0 * t);
+                let value = 1.0 - falloff * (1.0 - center_brightness);
+                values.push((value * 255.0).round() as u8);
+            }
+        }
+
+        Self {
+            width,
+            height,
+            values,
+        }
+    }
+
+    /// Stack another mask of the same size on top, so that both of them dim the surface.
+    pub fn multiply(mut self, other: &Mask) -> Self {
+        if (self.width, self.height) != (other.width, other.height) {
+            return self;
+        }
+
+        for (value, other) in self.values.iter_mut().zip(&other.values) {
+            *value = ((*value as u32 * *other as u32 + 127) / 255) as u8;
+        }
+        self
+    }
+}
+
+/// An area of a monitor, chosen in the settings, where the fluid is hidden or dimmed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MaskedArea {
+    /// Where the area is on the virtual screen
+    pub region: Region,
+    pub shape: MaskShape,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MaskShape {
+    /// Dim the whole rectangle to this brightness, from 0 (hidden) to 1 (unchanged)
+    Rectangle { brightness: f32 },
+    /// Stretch a grayscale image over the area, where black hides the fluid and white leaves it
+    /// unchanged
+    Image(image::GrayImage),
+}
+
+/// Everything besides the desktop icons that dims a wallpaper surface.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Layers {
+    pub edge_lighting: Option<EdgeLighting>,
+    pub areas: Vec<MaskedArea>,
+}
+
+impl Layers {
+    pub fn is_empty(&self) -> bool {
+        self.edge_lighting.is_none() && self.areas.is_empty()
+    }
+
+    /// Dim the mask for the surface at `position` with each of the layers in turn.
+    pub fn apply(
+        &self,
+        mut mask: Mask,
+        position: PhysicalPosition<i32>,
+        size: PhysicalSize<u32>,
+    ) -> Mask {
+        if let Some(edge_lighting) = &self.edge_lighting {
+            mask = mask.multiply(&Mask::from_edge_lighting(size, edge_lighting));
+        }
+        for area in &self.areas {
+            mask = mask.multiply(&Mask::from_area(position, size, area));
+        }
+        mask
+    }
+
+    /// Calm the motion in the dim middle of the edge lighting, on top of the motion mask for the
+    /// desktop icons, if there is one.
+    pub fn apply_motion(&self, motion: Option<Mask>, size: PhysicalSize<u32>) -> Option<Mask> {
+        let Some(edge_lighting) = &self.edge_lighting else {
+            return motion;
+        };
+        let falloff = Mask::from_edge_lighting(size, edge_lighting);
+        Some(match motion {
+            Some(motion) => motion.multiply(&falloff),
+            None => falloff,
+        })
+    }
+}
+
+impl Mask {
+    /// Build a mask for the surface at `position` that dims a single area. Unlike the icon mask,
+    /// the edges stay sharp, so that the area lines up with whatever it’s meant to keep clear.
+    pub fn from_area(
+        position: PhysicalPosition<i32>,
+        size: PhysicalSize<u32>,
+        area: &MaskedArea,
+    ) -> Self {
+        let width = size.width.div_ceil(CELL_SIZE).max(1);
+        let height = size.height.div_ceil(CELL_SIZE).max(1);
+        let left = area.region.position.x - position.x;
+        let top = area.region.position.y - position.y;
+        let right = left + area.region.size.width as i32;
+        let bottom = top + area.region.size.height as i32;
+
+        let mut values = Vec::with_capacity((width * height) as usize);
+        for y in 0..height as i32 {
+            let cell_top = y * CELL_SIZE as i32;
+            let overlap_y = (bottom.min(cell_top + CELL_SIZE as i32) - top.max(cell_top)).max(0);
+
+            for x in 0..width as i32 {
+                let cell_left = x * CELL_SIZE as i32;
+                let overlap_x =
+                    (right.min(cell_left + CELL_SIZE as i32) - left.max(cell_left)).max(0);
+
+                let value = match &area.shape {
+                    MaskShape::Rectangle { brightness } => {
+                        let coverage =
+                            (overlap_x * overlap_y) as f32 / (CELL_SIZE * CELL_SIZE) as f32;
+                        1.0 - coverage * (1.0 - brightness.clamp(0.0, 1.0))
+                    }
+                    MaskShape::Image(image) => {
+                        // Sample the image at the middle of the cell.
+                        let center_x = cell_left + CELL_SIZE as i32 / 2;
+                        let center_y = cell_top + CELL_SIZE as i32 / 2;
+                        if (left..right).contains(&center_x) && (top..bottom).contains(&center_y) {
+                            let u = (center_x - left) as f32 / (right - left) as f32;
+                            let v = (center_y - top) as f32 / (bottom - top) as f32;
+                            let pixel_x = ((u * image.width() as f32) as u32)
+                                .min(image.width().saturating_sub(1));
+                            let pixel_y = ((v * image.height() as f32) as u32)
+                                .min(image.height().saturating_sub(1));
+                            image.get_pixel(pixel_x, pixel_y).0[0] as f32 / 255.0
+                        } else {
+                            1.0
+                        }
+                    }
+                };
+                values.push((value * 255.0).round() as u8);
+            }
+        }
+
+        Self {
+            width,
+            height,
+            values,
+        }
+    }
+}
+
+// Average each cell with its neighbours.
+fn blur(values: &[f32], width: i32, height: i32) -> Vec<f32> {
+    let mut blurred = vec![0.0; values.len()];
+    let samples = ((2 * BLUR_RADIUS + 1) * (2 * BLUR_RADIUS + 1)) as f32;
+
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = 0.0;
+            for dy in -BLUR_RADIUS..=BLUR_RADIUS {
+                for dx in -BLUR_RADIUS..=BLUR_RADIUS {
+                    let (sx, sy) = (x + dx, y + dy);
+                    if (0..width).contains(&sx) && (0..height).contains(&sy) {
+                        sum += values[(sy * width + sx) as usize];
+                    }
+                }
+            }
+            // Scale up so that a single fully-covered cell is noticeably dimmed.
+            blurred[(y * width + x) as usize] = sum / samples * 4.0;
+        }
+    }
+
+    blurred
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_leaves_uncovered_surfaces_unchanged() {
+        let mask = Mask::from_regions((0, 0).into(), (1920, 1080).into(), &[], 0.5);
+
+        assert_eq!(mask.width, 60);
+        assert_eq!(mask.height, 34);
+        assert!(mask.values.iter().all(|value| *value == 255));
+    }
+
+    #[test]
+    fn it_dims_dense_regions_the_most() {
+        let icon = |x, y| Region {
+            position: PhysicalPosition::new(x, y),
+            size: PhysicalSize::new(96, 96),
+        };
+        // A column of icons on a secondary monitor to the left of the primary one.
+        let icons = (0..8).map(|row| icon(-1920, row * 96)).collect::<Vec<_>>();
+        let mask = Mask::from_regions((-1920, 0).into(), (1920, 1080).into(), &icons, 0.25);
+
+        let cell = |x: u32, y: u32| mask.values[(y * mask.width + x) as usize];
+        assert_eq!(cell(1, 4), 64);
+        assert!(cell(4, 4) > cell(1, 4));
+        assert_eq!(cell(59, 4), 255);
+    }
+
+    #[test]
+    fn it_lights_up_the_edges_and_dims_the_middle() {
+        let edge_lighting = EdgeLighting {
+            width: 0.25,
+            center_brightness: 0.2,
+        };
+        let mask = Mask::from_edge_lighting((1920, 1080).into(), &edge_lighting);
+
+        let cell = |x: u32, y: u32| mask.values[(y * mask.width + x) as usize];
+        assert!(cell(0, 17) > 240);
+        assert!(cell(30, 0) > 240);
+        assert_eq!(cell(30, 17), 51);
+        assert!(cell(4, 17) > cell(30, 17) && cell(4, 17) < cell(0, 17));
+
+        let dimmed =
+            Mask::from_regions((0, 0).into(), (1920, 1080).into(), &[], 0.5).multiply(&mask);
+        assert_eq!(dimmed, mask);
+
+        let layers = Layers {
+            edge_lighting: Some(edge_lighting),
+            areas: Vec::new(),
+        };
+        assert_eq!(layers.apply_motion(None, (1920, 1080).into()), Some(mask));
+        assert_eq!(
+            Layers::default().apply_motion(None, (1920, 1080).into()),
+            None
+        );
+    }
+
+    #[test]
+    fn it_hides_the_masked_areas() {
+        let hidden = MaskedArea {
+            region: Region {
+                position: PhysicalPosition::new(1920 + 320, 64),
+                size: PhysicalSize::new(640, 320),
+            },
+            shape: MaskShape::Rectangle { brightness: 0.0 },
+        };
+        // Black on the left half, white on the right
+        let gradient = MaskedArea {
+            region: Region {
+                position: PhysicalPosition::new(1920, 640),
+                size: PhysicalSize::new(640, 320),
+            },
+            shape: MaskShape::Image(image::GrayImage::from_fn(2, 1, |x, _| {
+                image::Luma([if x == 0 { 0 } else { 255 }])
+            })),
+        };
+        let layers = Layers {
+            edge_lighting: None,
+            areas: vec![hidden, gradient],
+        };
+        let size = PhysicalSize::new(1920, 1080);
+        let unmasked = Mask::from_regions((1920, 0).into(), size, &[], 1.0);
+        let mask = layers.apply(unmasked, (1920, 0).into(), size);
+
+        let cell = |x: u32, y: u32| mask.values[(y * mask.width + x) as usize];
+        assert_eq!(cell(10, 2), 0);
+        assert_eq!(cell(29, 11), 0);
+        assert_eq!(cell(30, 2), 255);
+        assert_eq!(cell(2, 22), 0);
+        assert_eq!(cell(17, 22), 255);
+        assert_eq!(cell(40, 30), 255);
+    }
+}
@@ -0,0 +1,120 @@
+use glow as GL;
+use glow::HasContext;
+use winit::dpi::PhysicalSize;
+
+/// An offscreen framebuffer to render Flux into, for when the frames are read back rather than
+/// shown in a window.
+///
+/// The windows behind these are hidden, and hidden windows don’t reliably keep what’s drawn to
+/// them.
+pub struct Target {
+    texture: GL::NativeTexture,
+    framebuffer: GL::NativeFramebuffer,
+    size: PhysicalSize<u32>,
+}
+
+impl Target {
+    pub unsafe fn new(gl: &glow::Context, size: PhysicalSize<u32>) -> Result<Self, String> {
+        let texture = gl.create_texture()?;
+        gl.bind_texture(GL::TEXTURE_2D, Some(texture));
+        gl.tex_image_2d(
+            GL::TEXTURE_2D,
+            0,
+            GL::RGBA8 as i32,
+            size.width as i32,
+            size.height as i32,
+            0,
+            GL::RGBA,
+            GL::UNSIGNED_BYTE,
+            None,
+        );
+        gl.bind_texture(GL::TEXTURE_2D, None);
+
+        let framebuffer = gl.create_framebuffer()?;
+        gl.bind_framebuffer(GL::FRAMEBUFFER, Some(framebuffer));
+        gl.framebuffer_texture_2d(
+            GL::FRAMEBUFFER,
+            GL::COLOR_ATTACHMENT0,
+            GL::TEXTURE_2D,
+            Some(texture),
+            0,
+        );
+        let status = gl.check_framebuffer_status(GL::FRAMEBUFFER);
+        gl.bind_framebuffer(GL::FRAMEBUFFER, None);
+
+        if status != GL::FRAMEBUFFER_COMPLETE {
+            gl.delete_framebuffer(framebuffer);
+            gl.delete_texture(texture);
+            return Err(format!("Offscreen framebuffer incomplete: {:#x}", status));
+        }
+
+        Ok(Self {
+            texture,
+            framebuffer,
+            size,
+        })
+    }
+
+    pub fn size(&self) -> PhysicalSize<u32> {
+        self.size
+    }
+
+    /// The size of a frame read back with `read`, in bytes.
+    pub fn frame_len(&self) -> usize {
+        self.size.width as usize * self.size.height as usize * 4
+    }
+
+    /// Draw into the target, rather than the window.
+    pub unsafe fn bind(&self, gl: &glow::Context) {
+        gl.bind_framebuffer(GL::FRAMEBUFFER, Some(self.framebuffer));
+    }
+
+    pub unsafe fn unbind(&self, gl: &glow::Context) {
+        gl.bind_framebuffer(GL::FRAMEBUFFER, None);
+    }
+
+    /// Read back the frame as rows of RGBA pixels, from the top down.
+    pub unsafe fn read(&self, gl: &glow::Context, pixels: &mut [u8]) {
+        gl.read_pixels(
+            0,
+            0,
+            self.size.width as i32,
+            self.size.height as i32,
+            GL::RGBA,
+            GL::UNSIGNED_BYTE,
+            glow::PixelPackData::Slice(pixels),
+        );
+
+        // GL starts from the bottom row.
+        flip_rows(pixels, self.size.width as usize * 4);
+    }
+
+    pub unsafe fn delete(&self, gl: &glow::Context) {
+        gl.delete_framebuffer(self.framebuffer);
+        gl.delete_texture(self.texture);
+    }
+}
+
+fn flip_rows(pixels: &mut [u8], row_length: usize) {
+    let rows = pixels.len() / row_length;
+    for row in 0..rows / 2 {
+        let (top, bottom) = pixels.split_at_mut((rows - row - 1) * row_length);
+        top[row * row_length..(row + 1) * row_length].swap_with_slice(&mut bottom[..row_length]);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_flips_the_rows() {
+        let mut pixels = vec![1, 1, 2, 2, 3, 3];
+        flip_rows(&mut pixels, 2);
+        assert_eq!(pixels, vec![3, 3, 2, 2, 1, 1]);
+
+        let mut pixels = vec![1, 1, 2, 2, 3, 3, 4, 4];
+        flip_rows(&mut pixels, 2);
+        assert_eq!(pixels, vec![4, 4, 3, 3, 2, 2, 1, 1]);
+    }
+}
@@ -0,0 +1,194 @@
+use crate::config::{OverlayContent, OverlaySettings, Rotation};
+use crate::hud;
+
+use std::rc::Rc;
+
+use glow as GL;
+use winit::dpi::PhysicalSize;
+
+const WEEKDAYS: [&str; 7] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+const MONTHS: [&str; 12] = [
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+
+/// A reading of the wall clock, down to the minute.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LocalTime {
+    /// From 1 for January
+    pub month: u32,
+    pub day: u32,
+    /// From 0 for Sunday
+    pub weekday: u32,
+    pub hour: u32,
+    pub minute: u32,
+}
+
+impl LocalTime {
+    /// The time now, in the local time zone. It falls back to UTC if the time zone can’t be read.
+    pub fn now() -> Self {
+        #[cfg(windows)]
+        {
+            let time = unsafe { windows::Win32::System::SystemInformation::GetLocalTime() };
+            Self {
+                month: time.wMonth as u32,
+                day: time.wDay as u32,
+                weekday: time.wDayOfWeek as u32,
+                hour: time.wHour as u32,
+                minute: time.wMinute as u32,
+            }
+        }
+
+        #[cfg(unix)]
+        {
+            let now = unsafe { libc::time(std::ptr::null_mut()) };
+            let mut time: libc::tm = unsafe { std::mem::zeroed() };
+            if !unsafe { libc::localtime_r(&now, &mut time) }.is_null() {
+                return Self {
+                    month: time.tm_mon as u32 + 1,
+                    day: time.tm_mday as u32,
+                    weekday: time.tm_wday as u32,
+                    hour: time.tm_hour as u32,
+                    minute: time.tm_min as u32,
+                };
+            }
+        }
+
+        #[cfg(not(windows))]
+        Self::from_utc(std::time::SystemTime::now())
+    }
+
+    #[cfg_attr(windows, allow(dead_code))]
+    fn from_utc(time: std::time::SystemTime) -> Self {
+        let secs = time
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let days = (secs / 86400) as i64;
+        let (_, month, day) = crate::logging::civil_from_days(days);
+        let secs_of_day = secs % 86400;
+
+        Self {
+            month,
+            day,
+            // The epoch fell on a Thursday.
+            weekday: ((days + 4) % 7) as u32,
+            hour: (secs_of_day / 3600) as u32,
+            minute: (secs_of_day / 60 % 60) as u32,
+        }
+    }
+}
+
+/// The text to show at this time. Empty if there’s nothing to show.
+pub fn text(settings: &OverlaySettings, time: LocalTime) -> String {
+    let clock = || format!("{:02}:{:02}", time.hour, time.minute);
+    let date = || {
+        format!(
+            "{} {} {}",
+            WEEKDAYS[time.weekday as usize % 7],
+            time.day,
+            MONTHS[time.month.saturating_sub(1) as usize % 12]
+        )
+    };
+
+    match settings.content {
+        OverlayContent::None => String::new(),
+        OverlayContent::Clock => clock(),
+        OverlayContent::Date => date(),
+        OverlayContent::ClockAndDate => format!("{}\n{}", clock(), date()),
+        OverlayContent::Text => settings.text.clone(),
+    }
+}
+
+/// Draws the clock, date, or text from the settings over each frame.
+pub struct Overlay {
+    hud: hud::Hud,
+    settings: OverlaySettings,
+    // The text on show, to only lay it out again once it changes
+    text: String,
+}
+
+impl Overlay {
+    /// `None` if there’s nothing to show, or it can’t be drawn. The GL context has to be current.
+    /// The text is turned upright for a monitor mounted at `rotation`.
+    pub fn new(
+        gl: &Rc<glow::Context>,
+        settings: &OverlaySettings,
+        scale_factor: f64,
+        rotation: Rotation,
+    ) -> Option<Self> {
+        if settings.content == OverlayContent::None {
+            return None;
+        }
+
+        let style = hud::Style {
+            position: settings.position,
+            scale: hud::scale_for_font_size(settings.font_size, scale_factor),
+            opacity: settings.opacity.into_inner().clamp(0.0, 1.0),
+            backing: false,
+            rotation,
+        };
+        match hud::Hud::with_style(gl, style) {
+            Ok(hud) => Some(Self {
+                hud,
+                settings: settings.clone(),
+                text: String::new(),
+            }),
+            Err(err) => {
+                log::warn!("Failed to set up the overlay: {}", err);
+                None
+            }
+        }
+    }
+
+    /// Catch the text up with the time.
+    pub fn update(&mut self, time: LocalTime) {
+        let text = text(&self.settings, time);
+        if text != self.text {
+            self.hud.set_text(&text);
+            self.text = text;
+        }
+    }
+
+    /// Fade the overlay along with the animation, from 1.0 for the opacity in the settings to 0.0
+    /// for hidden.
+    pub fn set_fade(&mut self, fade: f32) {
+        self.hud
+            .set_opacity(self.settings.opacity.into_inner() * fade);
+    }
+
+    /// Draw the overlay over whatever is in the target framebuffer, which is `size` pixels large.
+    pub fn draw(
+        &self,
+        target: Option<GL::NativeFramebuffer>,
+        size: PhysicalSize<u32>,
+        white_level: Option<f32>,
+    ) {
+        self.hud.draw(target, size, white_level);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn it_shows_the_time_and_date() {
+        // Wednesday, the 14th of October 2026, at 21:05 UTC
+        let time = LocalTime::from_utc(UNIX_EPOCH + Duration::from_secs(1_792_011_900));
+        let show = |content| {
+            let settings = OverlaySettings {
+                content,
+                text: "Gone fishing".to_string(),
+                ..Default::default()
+            };
+            text(&settings, time)
+        };
+
+        assert_eq!(show(OverlayContent::Clock), "21:05");
+        assert_eq!(show(OverlayContent::Date), "WED 14 OCT");
+        assert_eq!(show(OverlayContent::ClockAndDate), "21:05\nWED 14 OCT");
+        assert_eq!(show(OverlayContent::Text), "Gone fishing");
+        assert_eq!(show(OverlayContent::None), "");
+    }
+}
@@ -0,0 +1,311 @@
+use crate::config::Color;
+use crate::gradient::{self, Stop};
+
+use std::path;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use ordered_float::OrderedFloat;
+
+// How many colors to pull out of each capture
+const PALETTE_SIZE: usize = 5;
+
+// How far to move toward the colors of each new capture. The monitor shows the wallpaper itself
+// wherever no windows cover it, so moving only part of the way keeps the colors from locking on to
+// their own reflection.
+const DRIFT: f32 = 0.35;
+
+// Leave the colors be if no channel is further than this from the capture, rather than fade over
+// to colors no one could tell apart from the current ones.
+const MIN_CHANGE: u8 = 6;
+
+/// Colors that follow what’s on the monitor, for the adaptive palette.
+pub struct Adaptive {
+    interval: Duration,
+    next_capture: Instant,
+    // The capture that’s running on a thread of its own
+    pending: Option<mpsc::Receiver<Result<Vec<Color>, String>>>,
+    colors: Option<Vec<Color>>,
+    // The image the colors are drawn into, which is overwritten as they change
+    image_path: path::PathBuf,
+    has_image: bool,
+}
+
+impl Adaptive {
+    /// The first capture is taken right away. The colors are drawn into an image at
+    /// `image_path`, which should be different for each monitor.
+    pub fn new(interval: Duration, now: Instant, image_path: path::PathBuf) -> Self {
+        Self {
+            interval: interval.max(Duration::from_secs(1)),
+            next_capture: now,
+            pending: None,
+            colors: None,
+            image_path,
+            has_image: false,
+        }
+    }
+
+    /// The image to take the colors from, once the first capture is in.
+    pub fn image_file(&self) -> Option<&path::Path> {
+        self.has_image.then_some(self.image_path.as_path())
+    }
+
+    /// Start a capture once it’s due, and drift the colors toward the palette it finds once it’s
+    /// done. Captures run on a thread of their own, so that reading the screen back doesn’t hold
+    /// up the frames. Returns whether the colors have changed.
+    pub fn poll<F>(&mut self, now: Instant, capture: F) -> bool
+    where
+        F: FnOnce() -> Result<image::RgbImage, String> + Send + 'static,
+    {
+        if let Some(pending) = &self.pending {
+            let found = match pending.try_recv() {
+                Ok(found) => found,
+                Err(mpsc::TryRecvError::Empty) => return false,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    Err("The screen capture stopped unexpectedly".to_string())
+                }
+            };
+            self.pending = None;
+
+            match found {
+                Ok(target) if !target.is_empty() => {
+                    let Some(colors) = next_colors(self.colors.as_deref(), target) else {
+                        return false;
+                    };
+
+                    match gradient::save_image(&to_stops(&colors), &self.image_path) {
+                        Ok(()) => {
+                            self.colors = Some(colors);
+                            self.has_image = true;
+                            return true;
+                        }
+                        Err(err) => log::warn!("Failed to draw the adaptive palette: {}", err),
+                    }
+                }
+                Ok(_) => (),
+                Err(err) => log::debug!("Failed to capture the screen: {}", err),
+            }
+        }
+
+        if now >= self.next_capture {
+            self.next_capture = now + self.interval;
+            let (sender, receiver) = mpsc::channel();
+            std::thread::spawn(move || {
+                let _ = sender.send(capture().map(|image| {
+                    let pixels = image.pixels().map(|pixel| pixel.0).collect::<Vec<_>>();
+                    extract(&pixels, PALETTE_SIZE)
+                }));
+            });
+            self.pending = Some(receiver);
+        }
+
+        false
+    }
+}
+
+/// The main colors among the pixels, from darkest to lightest. The pixels are split in two,
+/// halfway along the channel that varies the most, again and again, and each group is averaged
+/// into a color. There are fewer colors than asked for if the pixels are too alike to
+/// split any further.
+pub fn extract(pixels: &[[u8; 3]], count: usize) -> Vec<Color> {
+    if pixels.is_empty() {
+        return Vec::new();
+    }
+
+    let mut groups = vec![pixels.to_vec()];
+    while groups.len() < count {
+        let widest = groups
+            .iter()
+            .enumerate()
+            .map(|(index, group)| (index, widest_channel(group)))
+            .max_by_key(|(_, (_, _, range))| *range);
+        let Some((index, (channel, min, range))) = widest else {
+            break;
+        };
+        if range == 0 {
+            break;
+        }
+
+        // Both ends of the range are in the group, so neither half is empty.
+        let (lower, upper): (Vec<[u8; 3]>, Vec<[u8; 3]>) = groups
+            .swap_remove(index)
+            .into_iter()
+            .partition(|pixel| pixel[channel] <= min + range / 2);
+        groups.push(lower);
+        groups.push(upper);
+    }
+
+    let mut colors = groups
+        .iter()
+        .map(|group| average(group))
+        .collect::<Vec<_>>();
+    colors.sort_by(|a, b| luminance(*a).total_cmp(&luminance(*b)));
+    colors
+}
+
+/// Move each color part of the way toward its counterpart in the target. Palettes of different
+/// sizes can’t be paired up, so those jump straight to the target.
+pub fn drift(colors: &[Color], target: &[Color], amount: f32) -> Vec<Color> {
+    if colors.len() != target.len() {
+        return target.to_vec();
+    }
+
+    let mix = |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * amount).round() as u8;
+    colors
+        .iter()
+        .zip(target)
+        .map(|(from, to)| Color {
+            r: mix(from.r, to.r),
+            g: mix(from.g, to.g),
+            b: mix(from.b, to.b),
+        })
+        .collect()
+}
+
+// The colors to fade over to for a new capture, or `None` if they’re already close enough. Once
+// a step toward the capture would be too small to see, the colors go the rest of the way, so
+// that they settle on it rather than stop short.
+fn next_colors(current: Option<&[Color]>, target: Vec<Color>) -> Option<Vec<Color>> {
+    let Some(current) = current else {
+        return Some(target);
+    };
+    if max_change(current, &target) <= MIN_CHANGE {
+        return None;
+    }
+
+    let colors = drift(current, &target, DRIFT);
+    if max_change(current, &colors) <= MIN_CHANGE {
+        Some(target)
+    } else {
+        Some(colors)
+    }
+}
+
+// The most any channel moves between two palettes. Palettes of different sizes change entirely.
+fn max_change(colors: &[Color], other: &[Color]) -> u8 {
+    if colors.len() != other.len() {
+        return u8::MAX;
+    }
+
+    colors
+        .iter()
+        .zip(other)
+        .flat_map(|(a, b)| [a.r.abs_diff(b.r), a.g.abs_diff(b.g), a.b.abs_diff(b.b)])
+        .max()
+        .unwrap_or(0)
+}
+
+// Spread the colors evenly along a gradient, in the order they come in.
+fn to_stops(colors: &[Color]) -> Vec<Stop> {
+    match colors {
+        [] => Vec::new(),
+        [color] => gradient::from_color(*color),
+        _ => colors
+            .iter()
+            .enumerate()
+            .map(|(index, color)| {
+                (
+                    OrderedFloat(index as f32 / (colors.len() - 1) as f32),
+                    *color,
+                )
+            })
+            .collect(),
+    }
+}
+
+// The channel that varies the most, with its lowest value and range.
+fn widest_channel(pixels: &[[u8; 3]]) -> (usize, u8, u8) {
+    (0..3)
+        .map(|channel| {
+            let (min, max) = pixels.iter().fold((u8::MAX, u8::MIN), |(min, max), pixel| {
+                (min.min(pixel[channel]), max.max(pixel[channel]))
+            });
+            (channel, min, max.saturating_sub(min))
+        })
+        .max_by_key(|(_, _, range)| *range)
+        .unwrap_or((0, 0, 0))
+}
+
+fn average(pixels: &[[u8; 3]]) -> Color {
+    let mut sum = [0u64; 3];
+    for pixel in pixels {
+        for (sum, value) in sum.iter_mut().zip(pixel) {
+            *sum += u64::from(*value);
+        }
+    }
+    let count = pixels.len().max(1) as u64;
+    let channel = |channel: usize| ((sum[channel] + count / 2) / count) as u8;
+    Color {
+        r: channel(0),
+        g: channel(1),
+        b: channel(2),
+    }
+}
+
+fn luminance(color: Color) -> f32 {
+    0.2126 * color.r as f32 + 0.7152 * color.g as f32 + 0.0722 * color.b as f32
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const RED: Color = Color { r: 200, g: 0, b: 0 };
+    const BLUE: Color = Color { r: 0, g: 0, b: 200 };
+
+    #[test]
+    fn it_extracts_the_main_colors_from_dark_to_light() {
+        let mut pixels = vec![[200, 0, 0]; 30];
+        pixels.extend(vec![[0, 0, 200]; 10]);
+
+        assert_eq!(extract(&pixels, 2), vec![BLUE, RED]);
+        // The pixels can’t be split into more colors than there are.
+        assert_eq!(extract(&pixels, 5), vec![BLUE, RED]);
+        assert_eq!(extract(&[], 5), Vec::new());
+    }
+
+    #[test]
+    fn it_drifts_part_of_the_way_toward_the_target() {
+        assert_eq!(
+            drift(&[RED, BLUE], &[BLUE, RED], 0.25),
+            vec![
+                Color {
+                    r: 150,
+                    g: 0,
+                    b: 50
+                },
+                Color {
+                    r: 50,
+                    g: 0,
+                    b: 150
+                },
+            ]
+        );
+        assert_eq!(drift(&[RED], &[BLUE, RED], 0.25), vec![BLUE, RED]);
+    }
+
+    #[test]
+    fn it_measures_the_change_between_palettes() {
+        let nudged = Color { r: 196, g: 3, b: 0 };
+
+        assert_eq!(max_change(&[RED, BLUE], &[nudged, BLUE]), 4);
+        assert_eq!(max_change(&[RED, BLUE], &[BLUE, RED]), 200);
+        assert_eq!(max_change(&[RED], &[RED, BLUE]), u8::MAX);
+    }
+
+    #[test]
+    fn it_settles_on_the_colors_it_drifts_toward() {
+        let mut colors = vec![RED, BLUE];
+        let target = vec![BLUE, RED];
+        for _ in 0..20 {
+            if let Some(next) = next_colors(Some(&colors), target.clone()) {
+                colors = next;
+            }
+        }
+        assert_eq!(colors, target);
+
+        let nudged = Color { r: 196, g: 3, b: 0 };
+        assert_eq!(next_colors(Some(&[RED, BLUE]), vec![nudged, BLUE]), None);
+        assert_eq!(next_colors(None, vec![RED]), Some(vec![RED]));
+    }
+}
@@ -0,0 +1,163 @@
+use crate::config::{Config, PingConfig};
+use crate::gpu;
+
+use serde::Serialize;
+use std::process;
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// Give up on a slow endpoint, rather than keep a process around.
+const TIMEOUT_SECS: &str = "10";
+
+/// Everything that the ping reports.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct Ping {
+    version: &'static str,
+    os: &'static str,
+    gpu_vendor: &'static str,
+}
+
+impl Ping {
+    fn new(renderer: &str) -> Self {
+        Self {
+            version: VERSION,
+            os: std::env::consts::OS,
+            gpu_vendor: gpu::vendor(renderer),
+        }
+    }
+}
+
+/// Send the anonymous ping in the background, if the user opted in and this release hasn’t sent
+/// one yet.
+///
+/// The ping is only attempted once per release, whether or not it gets through.
+pub fn send_if_due(config: &Config, renderer: &str) {
+    let Some(endpoint) = due_endpoint(&config.ping, VERSION) else {
+        return;
+    };
+    if is_disabled_by_policy() {
+        log::debug!("The anonymous ping is disabled by policy");
+        return;
+    }
+
+    if let Err(err) = config.save_change(|saved| {
+        saved.ping.last_sent_version = Some(VERSION.to_string());
+    }) {
+        log::warn!("Skipping the anonymous ping: {}", err);
+        return;
+    }
+
+    let body = match serde_json::to_string(&Ping::new(renderer)) {
+        Ok(body) => body,
+        Err(err) => {
+            log::warn!("Failed to encode the anonymous ping: {}", err);
+            return;
+        }
+    };
+    log::info!("Sending an anonymous ping to {}: {}", endpoint, body);
+
+    std::thread::spawn(move || {
+        if let Err(err) = post(&endpoint, &body) {
+            log::warn!("Failed to send the anonymous ping: {}", err);
+        }
+    });
+}
+
+// Where to send the ping, if it’s enabled and this version hasn’t sent one yet.
+fn due_endpoint(config: &PingConfig, version: &str) -> Option<String> {
+    if !config.enabled || config.last_sent_version.as_deref() == Some(version) {
+        return None;
+    }
+
+    config
+        .endpoint
+        .as_ref()
+        .filter(|endpoint| !endpoint.trim().is_empty())
+        .cloned()
+}
+
+// Post with curl, which ships with Windows, rather than pull in an HTTP client for a single
+// request.
+fn post(endpoint: &str, body: &str) -> Result<(), String> {
+    let mut command = process::Command::new("curl");
+    command
+        .args(["--silent", "--fail", "--max-time", TIMEOUT_SECS])
+        .args(["--header", "Content-Type: application/json"])
+        // Pass the body and the endpoint as they are, so that neither is read as a file to send,
+        // or as another option.
+        .args(["--data-raw", body])
+        .args(["--url", endpoint])
+        .stdout(process::Stdio::null())
+        .stderr(process::Stdio::null());
+
+    // Don’t flash a console window from the screensaver.
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+
+    let status = command
+        .status()
+        .map_err(|err| format!("Failed to run curl: {}", err))?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("curl exited with {}", status))
+    }
+}
+
+#[cfg(windows)]
+fn is_disabled_by_policy() -> bool {
+    crate::platform::windows::policy::is_ping_disabled()
+}
+
+#[cfg(not(windows))]
+fn is_disabled_by_policy() -> bool {
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_only_pings_once_per_release_when_enabled() {
+        let mut config = PingConfig {
+            enabled: false,
+            endpoint: Some("https://example.com/ping".to_string()),
+            last_sent_version: None,
+        };
+        assert_eq!(due_endpoint(&config, "1.7.0"), None);
+
+        config.enabled = true;
+        assert_eq!(
+            due_endpoint(&config, "1.7.0").as_deref(),
+            Some("https://example.com/ping")
+        );
+
+        config.last_sent_version = Some("1.7.0".to_string());
+        assert_eq!(due_endpoint(&config, "1.7.0"), None);
+        assert!(due_endpoint(&config, "1.8.0").is_some());
+
+        config.endpoint = None;
+        assert_eq!(due_endpoint(&config, "1.8.0"), None);
+    }
+
+    #[test]
+    fn it_reports_nothing_but_the_version_os_and_gpu_vendor() {
+        let ping = serde_json::to_value(Ping::new("NVIDIA GeForce RTX 3070/PCIe/SSE2")).unwrap();
+
+        assert_eq!(
+            ping,
+            serde_json::json!({
+                "version": VERSION,
+                "os": std::env::consts::OS,
+                "gpuVendor": "nvidia",
+            })
+        );
+    }
+}
@@ -0,0 +1,49 @@
+// XScreenSaver creates the window for the hack to draw into, and passes it down in this variable.
+// It’s set in both `-root` and `-window-id` modes.
+const WINDOW_ENV_VAR: &str = "XSCREENSAVER_WINDOW";
+
+/// Find the X11 window to draw the screensaver into, as set by XScreenSaver.
+pub fn window_from_env() -> Result<u64, String> {
+    let value = std::env::var(WINDOW_ENV_VAR).map_err(|_| {
+        format!(
+            "Can’t find the window to draw into. Run Flux from XScreenSaver, or set {}.",
+            WINDOW_ENV_VAR
+        )
+    })?;
+
+    parse_window_id(&value)
+}
+
+/// Parse an X11 window ID, which XScreenSaver writes in hex, and `xwininfo` in either hex or
+/// decimal.
+pub fn parse_window_id(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let parsed = match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => value.parse::<u64>(),
+    };
+
+    match parsed {
+        Ok(0) => Err("The window ID can’t be 0".to_string()),
+        Ok(id) => Ok(id),
+        Err(err) => Err(format!("Can’t parse the window ID {}: {}", value, err)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_parses_window_ids() {
+        assert_eq!(parse_window_id("0x3a00007"), Ok(0x3a00007));
+        assert_eq!(parse_window_id("0X3A00007\n"), Ok(0x3a00007));
+        assert_eq!(parse_window_id("60817415"), Ok(60817415));
+        assert!(parse_window_id("0x").is_err());
+        assert!(parse_window_id("0").is_err());
+        assert!(parse_window_id("window").is_err());
+    }
+}
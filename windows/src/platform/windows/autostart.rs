@@ -0,0 +1,52 @@
+use windows::core::{w, PCWSTR};
+use windows::Win32::Foundation::{ERROR_FILE_NOT_FOUND, ERROR_SUCCESS};
+use windows::Win32::System::Registry::{
+    RegDeleteKeyValueW, RegSetKeyValueW, HKEY_CURRENT_USER, REG_SZ,
+};
+
+// Programs listed here are started by Explorer when the user logs in.
+const RUN_KEY: PCWSTR = w!("Software\\Microsoft\\Windows\\CurrentVersion\\Run");
+const VALUE_NAME: PCWSTR = w!("Flux Live Wallpaper");
+
+/// Start the live wallpaper when the current user logs in, or stop doing so.
+///
+/// This points the login entry at the running executable, so it follows the screensaver if it’s
+/// reinstalled somewhere else and the settings are saved again.
+pub fn set_live_wallpaper_at_login(enabled: bool) -> Result<(), String> {
+    if !enabled {
+        let res = unsafe { RegDeleteKeyValueW(HKEY_CURRENT_USER, RUN_KEY, VALUE_NAME) };
+        return match res {
+            ERROR_SUCCESS | ERROR_FILE_NOT_FOUND => Ok(()),
+            err => Err(format!(
+                "Failed to remove the live wallpaper from startup: {:?}",
+                err
+            )),
+        };
+    }
+
+    let exe = std::env::current_exe()
+        .map_err(|err| format!("Failed to find the screensaver executable: {}", err))?;
+    let command = format!("\"{}\" --wallpaper", exe.display())
+        .encode_utf16()
+        .chain(std::iter::once(0))
+        .collect::<Vec<u16>>();
+
+    let res = unsafe {
+        RegSetKeyValueW(
+            HKEY_CURRENT_USER,
+            RUN_KEY,
+            VALUE_NAME,
+            REG_SZ.0,
+            Some(command.as_ptr() as *const _),
+            (command.len() * std::mem::size_of::<u16>()) as u32,
+        )
+    };
+
+    match res {
+        ERROR_SUCCESS => Ok(()),
+        err => Err(format!(
+            "Failed to add the live wallpaper to startup: {:?}",
+            err
+        )),
+    }
+}
@@ -0,0 +1,9 @@
+use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+
+/// Print to the console that launched us.
+///
+/// Release builds use the GUI subsystem, so they don’t get a console of their own.
+pub fn attach() {
+    // Fails if there’s no parent console or we already have one, which is fine either way.
+    let _ = unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+}
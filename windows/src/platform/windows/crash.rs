@@ -0,0 +1,258 @@
+use std::fs;
+use std::os::windows::io::AsRawHandle;
+use std::panic;
+use std::path;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicU32, Ordering};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use windows::core::{HSTRING, PCWSTR};
+use windows::Win32::Foundation::{FALSE, HANDLE, HWND, TRUE};
+use windows::Win32::System::Diagnostics::Debug::{
+    MiniDumpWithIndirectlyReferencedMemory, MiniDumpWithThreadInfo, MiniDumpWriteDump,
+    SetUnhandledExceptionFilter, EXCEPTION_POINTERS, MINIDUMP_EXCEPTION_INFORMATION, MINIDUMP_TYPE,
+};
+use windows::Win32::System::Threading::{
+    CreateEventW, GetCurrentProcess, GetCurrentProcessId, GetCurrentThreadId, SetEvent,
+    WaitForSingleObject, INFINITE,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    MessageBoxW, IDYES, MB_ICONERROR, MB_SETFOREGROUND, MB_TOPMOST, MB_YESNO,
+};
+
+// Leave the crash to Windows Error Reporting once the dump is written.
+const EXCEPTION_CONTINUE_SEARCH: i32 = 0;
+
+// Enough to go on for a crash that keeps coming back, without filling up the disk
+const MAX_DUMPS: usize = 5;
+
+// How long a crashed thread waits for the reporter, in milliseconds. Long enough to answer the
+// dialog, without keeping a hung process around if the reporter gets stuck on a lock that the
+// crashed thread holds.
+const REPORT_TIMEOUT_MS: u32 = 5 * 60 * 1000;
+
+static DUMP_DIR: OnceLock<path::PathBuf> = OnceLock::new();
+static SHOW_DIALOG: AtomicBool = AtomicBool::new(false);
+// Only the first crash gets a dump, when a panic turns into an exception or another panic.
+static CRASHED: AtomicBool = AtomicBool::new(false);
+
+// Handed from the exception filter to the reporter thread
+static EXCEPTION: AtomicPtr<EXCEPTION_POINTERS> = AtomicPtr::new(std::ptr::null_mut());
+static EXCEPTION_THREAD_ID: AtomicU32 = AtomicU32::new(0);
+static EVENTS: OnceLock<Events> = OnceLock::new();
+
+struct Events {
+    // Set by the exception filter
+    crashed: HANDLE,
+    // Set by the reporter thread, once the dump is written and the dialog is answered
+    reported: HANDLE,
+}
+
+/// Write a minidump into the log directory when Flux panics on the main thread, or crashes
+/// outright with an exception, such as an access violation in the graphics driver.
+///
+/// The panic hook goes on top of the one that logs the panic, so install this after the logger.
+/// A panic on any other thread is only logged, since the thread that waits on it carries on or
+/// exits with an error of its own.
+pub fn install(log_dir: &path::Path) {
+    if DUMP_DIR.set(log_dir.to_owned()).is_err() {
+        return;
+    }
+
+    let log_panic = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        log_panic(info);
+
+        let is_fatal = thread::current().name() == Some("main");
+        if !is_fatal || CRASHED.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        match unsafe { report(None) } {
+            Ok(dump_path) => log::error!("Saved a crash report to {}", dump_path.display()),
+            Err(err) => log::error!("{}", err),
+        }
+    }));
+
+    // The dump is written on a thread of its own, started now, rather than in the exception
+    // filter. The filter runs on the thread that crashed, which might have run out of stack, or
+    // be holding a lock, like the logger’s.
+    if let Err(err) = start_reporter() {
+        log::warn!("Failed to start the crash reporter: {}", err);
+        return;
+    }
+    unsafe {
+        SetUnhandledExceptionFilter(Some(on_exception));
+    }
+}
+
+/// Offer to open the log directory after a crash. This waits for the settings, which load after
+/// the crash handler is installed.
+pub fn set_show_dialog(show_dialog: bool) {
+    SHOW_DIALOG.store(show_dialog, Ordering::Relaxed);
+}
+
+fn start_reporter() -> Result<(), String> {
+    let events = unsafe {
+        Events {
+            crashed: CreateEventW(None, FALSE, FALSE, PCWSTR::null())
+                .map_err(|err| err.to_string())?,
+            reported: CreateEventW(None, TRUE, FALSE, PCWSTR::null())
+                .map_err(|err| err.to_string())?,
+        }
+    };
+    let (crashed, reported) = (events.crashed, events.reported);
+    let _ = EVENTS.set(events);
+
+    thread::Builder::new()
+        .name("crash reporter".to_string())
+        .spawn(move || unsafe {
+            WaitForSingleObject(crashed, INFINITE);
+            let exception = EXCEPTION.load(Ordering::SeqCst);
+            let thread_id = EXCEPTION_THREAD_ID.load(Ordering::SeqCst);
+            // Nothing is logged from here on, in case the crashed thread holds the lock.
+            let _ = report(Some((exception, thread_id)));
+            let _ = SetEvent(reported);
+        })
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
+
+// Hand the crash over to the reporter thread, and wait for it. Nothing else happens on the crashed
+// thread.
+unsafe extern "system" fn on_exception(exception: *const EXCEPTION_POINTERS) -> i32 {
+    if CRASHED.swap(true, Ordering::SeqCst) {
+        return EXCEPTION_CONTINUE_SEARCH;
+    }
+    let Some(events) = EVENTS.get() else {
+        return EXCEPTION_CONTINUE_SEARCH;
+    };
+
+    EXCEPTION.store(exception as *mut _, Ordering::SeqCst);
+    EXCEPTION_THREAD_ID.store(GetCurrentThreadId(), Ordering::SeqCst);
+    if SetEvent(events.crashed).is_ok() {
+        WaitForSingleObject(events.reported, REPORT_TIMEOUT_MS);
+    }
+    EXCEPTION_CONTINUE_SEARCH
+}
+
+// Write the dump, clear out the old ones, and offer to open the folder. The exception, if any, is
+// given with the thread that raised it.
+unsafe fn report(
+    exception: Option<(*const EXCEPTION_POINTERS, u32)>,
+) -> Result<path::PathBuf, String> {
+    let dump_dir = DUMP_DIR
+        .get()
+        .ok_or("The crash handler isn’t installed".to_string())?;
+    let dump_path = write_dump(dump_dir, exception)?;
+    remove_old_dumps(dump_dir);
+
+    // Keep the dialog and Explorer off the login screen, where they would run as SYSTEM.
+    if SHOW_DIALOG.load(Ordering::Relaxed) && super::session::is_interactive_user() {
+        let message = format!(
+            "Flux crashed, and saved a crash report to {}.\n\nOpen the folder with the crash \
+             report and the log?",
+            dump_path.display()
+        );
+        let answer = MessageBoxW(
+            HWND(0),
+            &HSTRING::from(message),
+            &HSTRING::from("Flux crashed"),
+            MB_YESNO | MB_ICONERROR | MB_TOPMOST | MB_SETFOREGROUND,
+        );
+        if answer == IDYES {
+            let _ = std::process::Command::new("explorer").arg(dump_dir).spawn();
+        }
+    }
+
+    Ok(dump_path)
+}
+
+unsafe fn write_dump(
+    dump_dir: &path::Path,
+    exception: Option<(*const EXCEPTION_POINTERS, u32)>,
+) -> Result<path::PathBuf, String> {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let dump_path = dump_dir.join(dump_file_name(secs, GetCurrentProcessId()));
+    let file = fs::File::create(&dump_path).map_err(|err| {
+        format!(
+            "Failed to create the crash report at {}: {}",
+            dump_path.display(),
+            err
+        )
+    })?;
+
+    let exception_information =
+        exception.map(|(exception, thread_id)| MINIDUMP_EXCEPTION_INFORMATION {
+            ThreadId: thread_id,
+            ExceptionPointers: exception as *mut _,
+            ClientPointers: FALSE,
+        });
+    // The stacks of every thread, and the memory they point to, without the whole heap
+    let dump_type =
+        MINIDUMP_TYPE(MiniDumpWithIndirectlyReferencedMemory.0 | MiniDumpWithThreadInfo.0);
+
+    MiniDumpWriteDump(
+        GetCurrentProcess(),
+        GetCurrentProcessId(),
+        HANDLE(file.as_raw_handle() as isize),
+        dump_type,
+        exception_information
+            .as_ref()
+            .map(|information| information as *const _),
+        None,
+        None,
+    )
+    .map_err(|err| format!("Failed to write the crash report: {}", err))?;
+
+    Ok(dump_path)
+}
+
+// Zero-padded, so that the names sort from oldest to newest
+fn dump_file_name(secs: u64, pid: u32) -> String {
+    format!("flux_crash_{:012}_{}.dmp", secs, pid)
+}
+
+fn remove_old_dumps(dump_dir: &path::Path) {
+    let Ok(entries) = fs::read_dir(dump_dir) else {
+        return;
+    };
+    let names = entries
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .collect();
+    for name in dumps_to_remove(names) {
+        let _ = fs::remove_file(dump_dir.join(name));
+    }
+}
+
+fn dumps_to_remove(names: Vec<String>) -> Vec<String> {
+    let mut dumps: Vec<String> = names
+        .into_iter()
+        .filter(|name| name.starts_with("flux_crash_") && name.ends_with(".dmp"))
+        .collect();
+    dumps.sort();
+    let excess = dumps.len().saturating_sub(MAX_DUMPS);
+    dumps.truncate(excess);
+    dumps
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_removes_the_oldest_dumps_past_the_limit() {
+        let mut names: Vec<String> = (0..MAX_DUMPS as u64 + 2)
+            .rev()
+            .map(|secs| dump_file_name(secs * 1000, 42))
+            .collect();
+        names.push("flux_screensaver.log".to_string());
+
+        assert_eq!(
+            dumps_to_remove(names),
+            vec![dump_file_name(0, 42), dump_file_name(1000, 42)]
+        );
+    }
+}
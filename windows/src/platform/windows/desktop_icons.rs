@@ -0,0 +1,72 @@
+use crate::mask::Region;
+
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+use windows::core::ComInterface;
+use windows::Win32::Foundation::{POINT, S_OK};
+use windows::Win32::System::Com::{CoCreateInstance, CoTaskMemFree, IServiceProvider, CLSCTX_ALL};
+use windows::Win32::System::Variant::VARIANT;
+use windows::Win32::UI::Shell::Common::ITEMIDLIST;
+use windows::Win32::UI::Shell::{
+    IEnumIDList, IFolderView, IShellBrowser, IShellWindows, SID_STopLevelBrowser, ShellWindows,
+    SVGIO_ALLVIEW, SWC_DESKTOP, SWFO_NEEDDISPATCH,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetSystemMetrics, SM_XVIRTUALSCREEN, SM_YVIRTUALSCREEN,
+};
+
+/// Find the regions of the screen covered by desktop icons, including their labels.
+///
+/// This goes through the same shell view that Explorer uses to arrange the icons.
+/// https://devblogs.microsoft.com/oldnewthing/20130318-00/?p=4933
+pub fn query() -> Result<Vec<Region>, String> {
+    crate::wallpaper::com_initialized();
+
+    unsafe { query_folder_view() }.map_err(|err| err.to_string())
+}
+
+unsafe fn query_folder_view() -> windows::core::Result<Vec<Region>> {
+    let shell_windows: IShellWindows = CoCreateInstance(&ShellWindows, None, CLSCTX_ALL)?;
+
+    let mut hwnd = 0;
+    let desktop = shell_windows.FindWindowSW(
+        &VARIANT::default(),
+        &VARIANT::default(),
+        SWC_DESKTOP,
+        &mut hwnd,
+        SWFO_NEEDDISPATCH,
+    )?;
+
+    let browser: IShellBrowser = desktop
+        .cast::<IServiceProvider>()?
+        .QueryService(&SID_STopLevelBrowser)?;
+    let view: IFolderView = browser.QueryActiveShellView()?.cast()?;
+
+    // Each icon takes up one cell of the desktop grid.
+    let mut spacing = POINT::default();
+    view.GetSpacing(Some(&mut spacing))?;
+    let size = PhysicalSize::new(spacing.x.max(0) as u32, spacing.y.max(0) as u32);
+
+    // Icon positions are relative to the top-left corner of the virtual screen.
+    let origin = PhysicalPosition::new(
+        GetSystemMetrics(SM_XVIRTUALSCREEN),
+        GetSystemMetrics(SM_YVIRTUALSCREEN),
+    );
+
+    let items: IEnumIDList = view.Items(SVGIO_ALLVIEW.0 as u32)?;
+    let mut regions = Vec::new();
+    let mut item: [*mut ITEMIDLIST; 1] = [std::ptr::null_mut()];
+
+    while items.Next(&mut item, None) == S_OK {
+        let position = view.GetItemPosition(item[0]);
+        CoTaskMemFree(Some(item[0] as *const _));
+
+        let position = position?;
+        regions.push(Region {
+            position: PhysicalPosition::new(origin.x + position.x, origin.y + position.y),
+            size,
+        });
+    }
+
+    Ok(regions)
+}
@@ -0,0 +1,109 @@
+use windows::Win32::Devices::Display::{
+    DisplayConfigGetDeviceInfo, GetDisplayConfigBufferSizes, QueryDisplayConfig,
+    DISPLAYCONFIG_DEVICE_INFO_GET_TARGET_NAME, DISPLAYCONFIG_MODE_INFO,
+    DISPLAYCONFIG_MODE_INFO_TYPE_SOURCE, DISPLAYCONFIG_PATH_INFO, DISPLAYCONFIG_TARGET_DEVICE_NAME,
+    QDC_ONLY_ACTIVE_PATHS,
+};
+use windows::Win32::Foundation::{ERROR_INSUFFICIENT_BUFFER, ERROR_SUCCESS};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+/// An active display, as Windows sees it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Display {
+    pub position: PhysicalPosition<i32>,
+    pub size: PhysicalSize<u32>,
+    /// The device path of the monitor, like `\\?\DISPLAY#DEL40A3#5&2b3c4d&0&UID4353#{…}`.
+    ///
+    /// This is built from the monitor’s EDID and the port it’s plugged into, so it stays the same
+    /// when displays are unplugged and plugged back in, unlike the display index. It’s also the
+    /// monitor ID that the wallpaper API expects.
+    pub device_path: String,
+}
+
+/// List the active displays, with their bounds on the virtual screen.
+pub fn query() -> Result<Vec<Display>, String> {
+    let (paths, modes) = unsafe { query_paths()? };
+
+    let displays = paths
+        .iter()
+        .filter_map(|path| unsafe {
+            let mode = modes.get(path.sourceInfo.Anonymous.modeInfoIdx as usize)?;
+            if mode.infoType != DISPLAYCONFIG_MODE_INFO_TYPE_SOURCE {
+                return None;
+            }
+            let source_mode = mode.Anonymous.sourceMode;
+
+            Some(Display {
+                position: PhysicalPosition::new(source_mode.position.x, source_mode.position.y),
+                size: PhysicalSize::new(source_mode.width, source_mode.height),
+                device_path: device_path(path)?,
+            })
+        })
+        .collect();
+
+    Ok(displays)
+}
+
+unsafe fn query_paths(
+) -> Result<(Vec<DISPLAYCONFIG_PATH_INFO>, Vec<DISPLAYCONFIG_MODE_INFO>), String> {
+    // The configuration can change between asking for the sizes and querying it, in which case
+    // Windows asks us to try again.
+    for _ in 0..3 {
+        let (mut path_count, mut mode_count) = (0, 0);
+        let res =
+            GetDisplayConfigBufferSizes(QDC_ONLY_ACTIVE_PATHS, &mut path_count, &mut mode_count);
+        if res != ERROR_SUCCESS {
+            return Err(format!(
+                "Failed to size the display configuration: {:?}",
+                res
+            ));
+        }
+
+        let mut paths = vec![DISPLAYCONFIG_PATH_INFO::default(); path_count as usize];
+        let mut modes = vec![DISPLAYCONFIG_MODE_INFO::default(); mode_count as usize];
+        let res = QueryDisplayConfig(
+            QDC_ONLY_ACTIVE_PATHS,
+            &mut path_count,
+            paths.as_mut_ptr(),
+            &mut mode_count,
+            modes.as_mut_ptr(),
+            None,
+        );
+
+        match res {
+            ERROR_SUCCESS => {
+                paths.truncate(path_count as usize);
+                modes.truncate(mode_count as usize);
+                return Ok((paths, modes));
+            }
+            ERROR_INSUFFICIENT_BUFFER => continue,
+            err => {
+                return Err(format!(
+                    "Failed to query the display configuration: {:?}",
+                    err
+                ))
+            }
+        }
+    }
+
+    Err("The display configuration kept changing while querying it".to_string())
+}
+
+unsafe fn device_path(path: &DISPLAYCONFIG_PATH_INFO) -> Option<String> {
+    let mut target_name = DISPLAYCONFIG_TARGET_DEVICE_NAME::default();
+    target_name.header.r#type = DISPLAYCONFIG_DEVICE_INFO_GET_TARGET_NAME;
+    target_name.header.size = std::mem::size_of::<DISPLAYCONFIG_TARGET_DEVICE_NAME>() as u32;
+    target_name.header.adapterId = path.targetInfo.adapterId;
+    target_name.header.id = path.targetInfo.id;
+
+    if DisplayConfigGetDeviceInfo(&mut target_name.header) != 0 {
+        return None;
+    }
+
+    let device_path = &target_name.monitorDevicePath;
+    let len = device_path
+        .iter()
+        .position(|&c| c == 0)
+        .unwrap_or(device_path.len());
+    (len > 0).then(|| String::from_utf16_lossy(&device_path[..len]))
+}
@@ -0,0 +1,163 @@
+use raw_window_handle::RawWindowHandle;
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use windows::core::GUID;
+use windows::Win32::Foundation::{ERROR_SUCCESS, HANDLE, HWND, LPARAM, WPARAM};
+use windows::Win32::System::Power::{
+    PowerSettingRegisterNotification, PowerSettingUnregisterNotification, SetThreadExecutionState,
+    DEVICE_NOTIFY_CALLBACK, DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS, ES_DISPLAY_REQUIRED, HPOWERNOTIFY,
+    POWERBROADCAST_SETTING,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    DefWindowProcW, PBT_POWERSETTINGCHANGE, SC_MONITORPOWER, WM_SYSCOMMAND,
+};
+
+// GUID_CONSOLE_DISPLAY_STATE, which reports the display turning off, on, or dimming. Defined here
+// to avoid pulling in all of SystemServices for a single constant.
+const GUID_CONSOLE_DISPLAY_STATE: GUID = GUID::from_u128(0x6fe69556_704a_47a0_8f24_c28d936fda47);
+
+// The values of the display state setting
+const DISPLAY_OFF: u8 = 0;
+const DISPLAY_ON: u8 = 1;
+
+// The power states for SC_MONITORPOWER
+const MONITOR_ON: isize = -1;
+const MONITOR_OFF: isize = 2;
+
+/// Put the displays into power saving, the same as when Windows turns them off after a while.
+///
+/// SDL swallows SC_MONITORPOWER while the screensaver is disabled, which it is while Flux runs, so
+/// this goes straight to the default window procedure instead.
+///
+/// # Safety
+///
+/// `handle` has to be a window that’s still open.
+pub unsafe fn turn_off(handle: &RawWindowHandle) {
+    if let RawWindowHandle::Win32(window_handle) = handle {
+        let hwnd = HWND(window_handle.hwnd as _);
+        DefWindowProcW(
+            hwnd,
+            WM_SYSCOMMAND,
+            WPARAM(SC_MONITORPOWER as usize),
+            LPARAM(MONITOR_OFF),
+        );
+    }
+}
+
+/// Bring the displays back from power saving.
+///
+/// Windows 8 and later ignore SC_MONITORPOWER for turning the displays on, so this also resets
+/// the display idle timer, which brings them back. Unlike faking some input, this leaves the
+/// system idle timer alone, so the computer still goes to sleep on time.
+///
+/// # Safety
+///
+/// `handle` has to be a window that’s still open.
+pub unsafe fn turn_on(handle: &RawWindowHandle) {
+    if let RawWindowHandle::Win32(window_handle) = handle {
+        let hwnd = HWND(window_handle.hwnd as _);
+        DefWindowProcW(
+            hwnd,
+            WM_SYSCOMMAND,
+            WPARAM(SC_MONITORPOWER as usize),
+            LPARAM(MONITOR_ON),
+        );
+    }
+
+    if SetThreadExecutionState(ES_DISPLAY_REQUIRED).0 == 0 {
+        log::debug!("Failed to reset the display idle timer");
+    }
+}
+
+/// Watch for the displays waking up from standby.
+///
+/// Windows reports the display state from a thread pool thread, so the watch only raises a flag
+/// for the main loop to pick up. The notification is unregistered when this is dropped.
+pub struct WakeWatch {
+    registration: *mut c_void,
+    state: *const State,
+}
+
+#[derive(Default)]
+struct State {
+    is_off: AtomicBool,
+    has_woken: AtomicBool,
+}
+
+impl WakeWatch {
+    pub fn new() -> Result<Self, String> {
+        let state = Arc::into_raw(Arc::new(State::default()));
+        let mut parameters = DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS {
+            Callback: Some(on_power_setting_change),
+            Context: state as *mut c_void,
+        };
+        let mut registration = std::ptr::null_mut();
+
+        let result = unsafe {
+            PowerSettingRegisterNotification(
+                &GUID_CONSOLE_DISPLAY_STATE,
+                DEVICE_NOTIFY_CALLBACK,
+                HANDLE(&mut parameters as *mut _ as isize),
+                &mut registration,
+            )
+        };
+        if result != ERROR_SUCCESS {
+            unsafe { drop(Arc::from_raw(state)) };
+            return Err(format!(
+                "Failed to watch the display state: error {}",
+                result.0
+            ));
+        }
+
+        Ok(Self {
+            registration,
+            state,
+        })
+    }
+
+    /// Whether the displays have come back on since the last time this was asked.
+    pub fn has_woken(&self) -> bool {
+        let state = unsafe { &*self.state };
+        state.has_woken.swap(false, Ordering::Relaxed)
+    }
+}
+
+impl Drop for WakeWatch {
+    fn drop(&mut self) {
+        unsafe {
+            // Waits for any callback that’s still running, so the state can go after it.
+            let _ = PowerSettingUnregisterNotification(HPOWERNOTIFY(self.registration as isize));
+            drop(Arc::from_raw(self.state));
+        }
+    }
+}
+
+unsafe extern "system" fn on_power_setting_change(
+    context: *const c_void,
+    kind: u32,
+    setting: *const c_void,
+) -> u32 {
+    if kind != PBT_POWERSETTINGCHANGE || context.is_null() || setting.is_null() {
+        return 0;
+    }
+
+    let state = &*(context as *const State);
+    let setting = &*(setting as *const POWERBROADCAST_SETTING);
+    if setting.PowerSetting != GUID_CONSOLE_DISPLAY_STATE || setting.DataLength < 1 {
+        return 0;
+    }
+
+    match setting.Data[0] {
+        DISPLAY_OFF => state.is_off.store(true, Ordering::Relaxed),
+        // Dimming doesn’t power down the panel, so it only counts as waking after being off.
+        DISPLAY_ON if state.is_off.swap(false, Ordering::Relaxed) => {
+            log::info!("The displays woke up from standby");
+            state.has_woken.store(true, Ordering::Relaxed);
+        }
+        _ => (),
+    }
+
+    0
+}
@@ -3,30 +3,61 @@ use std::ffi::CStr;
 use std::fmt;
 use std::mem;
 use std::os::raw::{c_char, c_int, c_uint, c_void};
+use std::time::{Duration, Instant};
 
 use glow as GL;
 use glow::HasContext;
 use raw_window_handle::RawWindowHandle;
 
 use windows::core::{Interface, PCSTR};
-use windows::Win32::Foundation::{BOOL, HANDLE, HWND};
-use windows::Win32::Graphics::Direct3D::D3D_DRIVER_TYPE_HARDWARE;
+use windows::Win32::Foundation::{CloseHandle, BOOL, HANDLE, HWND, POINT, WAIT_OBJECT_0};
+use windows::Win32::Graphics::Direct3D::{
+    D3D_DRIVER_TYPE, D3D_DRIVER_TYPE_HARDWARE, D3D_DRIVER_TYPE_UNKNOWN,
+};
 use windows::Win32::Graphics::Direct3D11::{
-    D3D11CreateDeviceAndSwapChain, ID3D11Device, ID3D11DeviceContext, ID3D11RenderTargetView,
-    ID3D11Texture2D, D3D11_CREATE_DEVICE_FLAG, D3D11_SDK_VERSION,
+    D3D11CreateDevice, D3D11CreateDeviceAndSwapChain, ID3D11Device, ID3D11DeviceContext,
+    ID3D11RenderTargetView, ID3D11Texture2D, D3D11_CREATE_DEVICE_FLAG, D3D11_SDK_VERSION,
 };
+use windows::Win32::Graphics::Dwm::DwmIsCompositionEnabled;
 use windows::Win32::Graphics::Dxgi::Common::{
-    DXGI_FORMAT_R8G8B8A8_UNORM, DXGI_MODE_DESC, DXGI_SAMPLE_DESC,
+    DXGI_COLOR_SPACE_RGB_FULL_G10_NONE_P709, DXGI_COLOR_SPACE_RGB_FULL_G2084_NONE_P2020,
+    DXGI_COLOR_SPACE_RGB_FULL_G22_NONE_P709, DXGI_COLOR_SPACE_TYPE, DXGI_FORMAT,
+    DXGI_FORMAT_R10G10B10A2_UNORM, DXGI_FORMAT_R16G16B16A16_FLOAT, DXGI_FORMAT_R8G8B8A8_UNORM,
+    DXGI_MODE_DESC, DXGI_SAMPLE_DESC,
 };
 use windows::Win32::Graphics::Dxgi::{
-    IDXGISwapChain, DXGI_SWAP_CHAIN_DESC, DXGI_SWAP_EFFECT_DISCARD, DXGI_USAGE_RENDER_TARGET_OUTPUT,
+    CreateDXGIFactory1, IDXGIAdapter, IDXGIDevice, IDXGIFactory1, IDXGIFactory2, IDXGIOutput,
+    IDXGIOutput2, IDXGIOutput6, IDXGISwapChain, IDXGISwapChain2, IDXGISwapChain3,
+    IDXGISwapChainMedia, DXGI_ERROR_WAS_STILL_DRAWING, DXGI_FRAME_PRESENTATION_MODE_COMPOSED,
+    DXGI_FRAME_PRESENTATION_MODE_COMPOSITION_FAILURE, DXGI_FRAME_PRESENTATION_MODE_NONE,
+    DXGI_FRAME_PRESENTATION_MODE_OVERLAY, DXGI_FRAME_STATISTICS_MEDIA, DXGI_PRESENT_DO_NOT_WAIT,
+    DXGI_SWAP_CHAIN_COLOR_SPACE_SUPPORT_FLAG_PRESENT, DXGI_SWAP_CHAIN_DESC, DXGI_SWAP_CHAIN_DESC1,
+    DXGI_SWAP_CHAIN_FLAG_FRAME_LATENCY_WAITABLE_OBJECT, DXGI_SWAP_EFFECT_DISCARD,
+    DXGI_SWAP_EFFECT_FLIP_DISCARD, DXGI_USAGE_RENDER_TARGET_OUTPUT,
+};
+use windows::Win32::Graphics::Gdi::{
+    MonitorFromPoint, MonitorFromWindow, HDC, MONITOR_DEFAULTTONEAREST, MONITOR_DEFAULTTOPRIMARY,
 };
-use windows::Win32::Graphics::Gdi::HDC;
 use windows::Win32::Graphics::OpenGL::{wglGetCurrentDC, wglGetProcAddress};
+use windows::Win32::System::Threading::WaitForSingleObjectEx;
+
+// How long to keep trying to queue up a held frame without blocking, before waiting on the
+// display after all. It’s only a fraction of a frame, so the frame still makes the next vblank.
+const PRESENT_RETRY_LIMIT: Duration = Duration::from_millis(4);
 
+// How long to wait for the swapchain to take another frame, before rendering one anyway. The
+// swapchain stops signalling while the window is hidden on some drivers.
+const FRAME_LATENCY_TIMEOUT: Duration = Duration::from_secs(1);
+
+// The most frames DXGI lets queue up ahead of the display
+const MAX_FRAME_LATENCY: u32 = 16;
+
+/// Why the DXGI swapchain couldn’t be set up.
 #[derive(Debug)]
-pub(crate) enum Problem {
+pub enum Problem {
+    /// The driver doesn’t have the interop extension, or the GPU is known not to cope with it
     Unsupported,
+    /// Something went wrong along the way
     Failure(String),
 }
 
@@ -50,17 +81,103 @@ impl fmt::Display for Problem {
     }
 }
 
+/// How the swapchain stores colors
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// 8 bits per channel, in sRGB
+    Standard,
+    /// 10 bits per channel, in sRGB, for smoother gradients
+    TenBit,
+    /// 16-bit floats in linear scRGB, for a display in HDR mode
+    ScRgb,
+}
+
+impl OutputFormat {
+    fn format(self) -> DXGI_FORMAT {
+        match self {
+            OutputFormat::Standard => DXGI_FORMAT_R8G8B8A8_UNORM,
+            OutputFormat::TenBit => DXGI_FORMAT_R10G10B10A2_UNORM,
+            OutputFormat::ScRgb => DXGI_FORMAT_R16G16B16A16_FLOAT,
+        }
+    }
+
+    fn color_space(self) -> DXGI_COLOR_SPACE_TYPE {
+        match self {
+            OutputFormat::Standard | OutputFormat::TenBit => {
+                DXGI_COLOR_SPACE_RGB_FULL_G22_NONE_P709
+            }
+            OutputFormat::ScRgb => DXGI_COLOR_SPACE_RGB_FULL_G10_NONE_P709,
+        }
+    }
+
+    /// Whether the colors have to be converted to linear light before they’re presented
+    pub fn is_linear(self) -> bool {
+        self == OutputFormat::ScRgb
+    }
+}
+
+/// A DXGI swapchain that OpenGL renders into, through the `WGL_NV_DX_interop2` extension. Flux
+/// draws to its framebuffer, and presents it with DXGI, in 10-bit or scRGB if asked.
 #[allow(dead_code)]
-pub(crate) struct DXGIInterop {
+pub struct DXGIInterop {
     device: ID3D11Device,
     context: ID3D11DeviceContext,
     swap_chain: IDXGISwapChain,
+    output_format: OutputFormat,
+    // Whether the swapchain flips its buffers to the screen, rather than copying them over
+    flip_model: bool,
+    // Signalled whenever the swapchain is ready for another frame, with a frame latency set
+    frame_latency_waitable: Option<HANDLE>,
     gl_handle_d3d: HANDLE,
     dx_interop: WGLDXInteropExtensionFunctions,
     color_handle_gl: HANDLE,
+    // What GL sees the swapchain’s buffer as, which is registered again after a resize
+    color_object_gl: ColorObject,
     fbo: GL::NativeFramebuffer,
 }
 
+// Some drivers share the swapchain’s buffer as a renderbuffer, others only as a texture.
+#[derive(Clone, Copy)]
+enum ColorObject {
+    Renderbuffer(GL::NativeRenderbuffer),
+    Texture(GL::NativeTexture),
+}
+
+impl ColorObject {
+    fn name(self) -> GLuint {
+        match self {
+            ColorObject::Renderbuffer(rbo) => rbo.0.into(),
+            ColorObject::Texture(texture) => texture.0.into(),
+        }
+    }
+
+    fn target(self) -> GLenum {
+        match self {
+            ColorObject::Renderbuffer(_) => GL::RENDERBUFFER,
+            ColorObject::Texture(_) => GL::TEXTURE_2D,
+        }
+    }
+
+    unsafe fn attach(self, gl: &glow::Context, fbo: GL::NativeFramebuffer) {
+        gl.bind_framebuffer(GL::FRAMEBUFFER, Some(fbo));
+        match self {
+            ColorObject::Renderbuffer(rbo) => gl.framebuffer_renderbuffer(
+                GL::FRAMEBUFFER,
+                GL::COLOR_ATTACHMENT0,
+                GL::RENDERBUFFER,
+                Some(rbo),
+            ),
+            ColorObject::Texture(texture) => gl.framebuffer_texture_2d(
+                GL::FRAMEBUFFER,
+                GL::COLOR_ATTACHMENT0,
+                GL::TEXTURE_2D,
+                Some(texture),
+                0,
+            ),
+        }
+    }
+}
+
 type GLint = c_int;
 type GLenum = c_uint;
 type GLuint = c_uint;
@@ -69,29 +186,235 @@ type GLuint = c_uint;
 const WGL_ACCESS_READ_WRITE_DISCARD_NV: u32 = 0x0002;
 
 #[allow(non_snake_case, dead_code)]
-pub(crate) struct WGLDXInteropExtensionFunctions {
-    pub(crate) DXCloseDeviceNV: unsafe extern "C" fn(hDevice: HANDLE) -> BOOL,
-    pub(crate) DXLockObjectsNV:
+struct WGLDXInteropExtensionFunctions {
+    DXCloseDeviceNV: unsafe extern "C" fn(hDevice: HANDLE) -> BOOL,
+    DXLockObjectsNV:
         unsafe extern "C" fn(hDevice: HANDLE, count: GLint, hObjects: *mut HANDLE) -> BOOL,
-    pub(crate) DXOpenDeviceNV: unsafe extern "C" fn(dxDevice: *mut c_void) -> HANDLE,
-    pub(crate) DXRegisterObjectNV: unsafe extern "C" fn(
+    DXOpenDeviceNV: unsafe extern "C" fn(dxDevice: *mut c_void) -> HANDLE,
+    DXRegisterObjectNV: unsafe extern "C" fn(
         hDevice: HANDLE,
         dxResource: *mut c_void,
         name: GLuint,
         object_type: GLenum,
         access: GLenum,
     ) -> HANDLE,
-    pub(crate) DXSetResourceShareHandleNV:
+    DXSetResourceShareHandleNV:
         unsafe extern "C" fn(dxResource: *mut c_void, shareHandle: HANDLE) -> BOOL,
-    pub(crate) DXUnlockObjectsNV:
+    DXUnlockObjectsNV:
         unsafe extern "C" fn(hDevice: HANDLE, count: GLint, hObjects: *mut HANDLE) -> BOOL,
-    pub(crate) DXUnregisterObjectNV: unsafe extern "C" fn(hDevice: HANDLE, hObject: HANDLE) -> BOOL,
+    DXUnregisterObjectNV: unsafe extern "C" fn(hDevice: HANDLE, hObject: HANDLE) -> BOOL,
+}
+
+impl DXGIInterop {
+    /// The format the swapchain presents in, which may fall short of the one asked for.
+    pub fn output_format(&self) -> OutputFormat {
+        self.output_format
+    }
+
+    /// Whether the D3D device is gone, like after the driver has reset.
+    pub fn is_device_removed(&self) -> bool {
+        unsafe { self.device.GetDeviceRemovedReason() }.is_err()
+    }
+
+    /// Resize the swapchain’s buffers to fit the window, for windows that someone else resizes,
+    /// like the screensaver preview pane. GL has to let go of the old buffer first, and then share
+    /// the new one.
+    pub unsafe fn resize(
+        &mut self,
+        gl: &glow::Context,
+        width: u32,
+        height: u32,
+    ) -> Result<(), String> {
+        (self.dx_interop.DXUnregisterObjectNV)(self.gl_handle_d3d, self.color_handle_gl);
+        // The immediate context still refers to the old buffer, which would keep it alive.
+        self.context.OMSetRenderTargets(None, None);
+        self.context.ClearState();
+        self.context.Flush();
+
+        let desc = self
+            .swap_chain
+            .GetDesc()
+            .map_err(|err| format!("Failed to read the swapchain: {}", err))?;
+        self.swap_chain
+            .ResizeBuffers(
+                desc.BufferCount,
+                width,
+                height,
+                desc.BufferDesc.Format,
+                desc.Flags,
+            )
+            .map_err(|err| format!("Failed to resize the swapchain: {}", err))?;
+
+        let color_buffer: ID3D11Texture2D = self
+            .swap_chain
+            .GetBuffer(0)
+            .map_err(|err| format!("Failed to fetch the swapchain buffer: {}", err))?;
+        let color_handle_gl = (self.dx_interop.DXRegisterObjectNV)(
+            self.gl_handle_d3d,
+            color_buffer.as_raw(),
+            self.color_object_gl.name(),
+            self.color_object_gl.target(),
+            WGL_ACCESS_READ_WRITE_DISCARD_NV,
+        );
+        if color_handle_gl.is_invalid() {
+            let msg = std::io::Error::last_os_error();
+            return Err(format!(
+                "Failed to register the resized swapchain with GL. OS Error: {:?}",
+                msg
+            ));
+        }
+        self.color_handle_gl = color_handle_gl;
+
+        self.color_object_gl.attach(gl, self.fbo);
+        gl.bind_framebuffer(GL::FRAMEBUFFER, None);
+
+        Ok(())
+    }
+
+    /// How many buffers the swapchain rotates through, which grows with the frame latency.
+    pub fn buffer_count(&self) -> usize {
+        unsafe { self.swap_chain.GetDesc() }.map_or(2, |desc| desc.BufferCount as usize)
+    }
+
+    /// Present a frame that was held back, without blocking on the display. Presenting the frames
+    /// for every monitor in quick succession, right after a vblank, gets them all onto the next
+    /// one together.
+    pub fn present_held(&self) {
+        let started = Instant::now();
+        loop {
+            let result = unsafe { self.swap_chain.Present(1, DXGI_PRESENT_DO_NOT_WAIT) };
+            if result != DXGI_ERROR_WAS_STILL_DRAWING {
+                return;
+            }
+            if started.elapsed() >= PRESENT_RETRY_LIMIT {
+                let _ = unsafe { self.swap_chain.Present(1, 0) };
+                return;
+            }
+            std::thread::yield_now();
+        }
+    }
+
+    /// How the frames make it to the screen, for the stats: whether the swapchain flips or copies
+    /// its buffers, how Windows presented the latest frame, and whether the monitor has hardware
+    /// overlay planes (MPO) to scan the frames out of directly.
+    pub fn describe_presentation(&self) -> String {
+        let mut description = if self.flip_model {
+            "DXGI flip model".to_string()
+        } else {
+            "DXGI bitblt".to_string()
+        };
+
+        unsafe {
+            // Only flip model swapchains report how their frames were presented.
+            if let Some(mode) = self.presentation_mode().filter(|_| self.flip_model) {
+                description.push_str(", ");
+                description.push_str(mode);
+            }
+            if let Some(supports_overlays) = self.supports_overlays() {
+                description.push_str(if supports_overlays {
+                    ", overlays supported"
+                } else {
+                    ", no overlays"
+                });
+            }
+        }
+
+        description
+    }
+
+    unsafe fn presentation_mode(&self) -> Option<&'static str> {
+        let media = self.swap_chain.cast::<IDXGISwapChainMedia>().ok()?;
+        let mut stats = DXGI_FRAME_STATISTICS_MEDIA::default();
+        media.GetFrameStatisticsMedia(&mut stats).ok()?;
+
+        Some(match stats.CompositionMode {
+            DXGI_FRAME_PRESENTATION_MODE_COMPOSED => "composed by DWM",
+            DXGI_FRAME_PRESENTATION_MODE_OVERLAY => "in a hardware overlay",
+            DXGI_FRAME_PRESENTATION_MODE_NONE => "independent flip",
+            DXGI_FRAME_PRESENTATION_MODE_COMPOSITION_FAILURE => "composition failed",
+            _ => "presented some other way",
+        })
+    }
+
+    unsafe fn supports_overlays(&self) -> Option<bool> {
+        let output = self
+            .swap_chain
+            .GetContainingOutput()
+            .and_then(|output| output.cast::<IDXGIOutput2>())
+            .ok()?;
+        Some(output.SupportsOverlays().as_bool())
+    }
+}
+
+/// Whether DWM composes the desktop, which it always does from Windows 8 on. When it doesn’t,
+/// there’s no vsync for windowed swapchains to follow, and frames tear.
+pub fn is_composition_enabled() -> Option<bool> {
+    unsafe { DwmIsCompositionEnabled() }
+        .ok()
+        .map(|enabled| enabled.as_bool())
+}
+
+impl Drop for DXGIInterop {
+    fn drop(&mut self) {
+        if let Some(waitable) = self.frame_latency_waitable.take() {
+            let _ = unsafe { CloseHandle(waitable) };
+        }
+    }
+}
+
+/// Waits for the vertical blank of the primary monitor, to line up the presents on every monitor
+/// with.
+pub struct VBlank {
+    output: IDXGIOutput,
 }
 
-pub(crate) unsafe fn with_dxgi_swapchain<R>(
+impl VBlank {
+    /// Find the output of the primary monitor, or fail if DXGI doesn’t know about it.
+    pub fn primary() -> Result<Self, String> {
+        unsafe {
+            let primary = MonitorFromPoint(POINT::default(), MONITOR_DEFAULTTOPRIMARY);
+            let factory: IDXGIFactory1 = CreateDXGIFactory1()
+                .map_err(|err| format!("Failed to create a DXGI factory: {}", err))?;
+
+            (0..)
+                .map_while(|index| factory.EnumAdapters1(index).ok())
+                .flat_map(|adapter| (0..).map_while(move |index| adapter.EnumOutputs(index).ok()))
+                .find(|output| output.GetDesc().is_ok_and(|desc| desc.Monitor == primary))
+                .map(|output| Self { output })
+                .ok_or_else(|| {
+                    "Failed to find the primary monitor among the GPU outputs".to_string()
+                })
+        }
+    }
+
+    /// Block until the next vertical blank. This fails once the monitor has been unplugged.
+    pub fn wait(&self) -> Result<(), String> {
+        unsafe { self.output.WaitForVBlank() }
+            .map_err(|err| format!("Failed to wait for the vertical blank: {}", err))
+    }
+}
+
+/// Render into the swapchain, and present the frame, unless it’s to be held back until
+/// `present_held`.
+///
+/// With a frame latency set, this first waits until the swapchain is ready for another frame, so
+/// that the frames are paced by the display rather than piling up in the queue.
+///
+/// # Safety
+///
+/// The GL context that the swapchain was created with has to be current on this thread.
+pub unsafe fn with_dxgi_swapchain<R>(
     dxgi_interop: &mut DXGIInterop,
+    hold: bool,
     render: impl FnOnce(&GL::NativeFramebuffer) -> R,
 ) -> R {
+    if let Some(waitable) = dxgi_interop.frame_latency_waitable {
+        let timeout = FRAME_LATENCY_TIMEOUT.as_millis() as u32;
+        if WaitForSingleObjectEx(waitable, timeout, true) != WAIT_OBJECT_0 {
+            log::debug!("Timed out waiting for the swapchain to take another frame");
+        }
+    }
+
     (dxgi_interop.dx_interop.DXLockObjectsNV)(
         dxgi_interop.gl_handle_d3d,
         1,
@@ -106,25 +429,39 @@ pub(crate) unsafe fn with_dxgi_swapchain<R>(
         &mut dxgi_interop.color_handle_gl as *mut _,
     );
 
-    let _ = dxgi_interop.swap_chain.Present(1, 0);
+    if !hold {
+        let _ = dxgi_interop.swap_chain.Present(1, 0);
+    }
 
     result
 }
 
-// Detect Intel GPUs.
-// The Intel drivers don't play well with the DXGI interop extension.
-pub(crate) fn is_intel_gpu(gl: &glow::Context) -> bool {
+/// Detect Intel GPUs.
+/// The Intel drivers don't play well with the DXGI interop extension.
+pub fn is_intel_gpu(gl: &glow::Context) -> bool {
     let vendor = unsafe { gl.get_parameter_string(GL::VENDOR) };
     log::debug!("OpenGL Vendor: {}", vendor);
     vendor.contains("Intel")
 }
 
+/// Create a DXGI swapchain for the window, and share its back buffer with OpenGL.
+///
+/// With `high_dynamic_range`, present in 10 bits per channel, or in scRGB if the display is in HDR
+/// mode. With `max_frame_latency`, queue up at most that many frames, and wait for the swapchain
+/// before rendering each one. Anything goes wrong, and it’s back to the plain 8-bit swapchain.
+///
+/// The device is created on the preferred GPU, if there is one. The interop only works if that’s
+/// also the GPU that OpenGL renders on.
+//
 // https://github.com/Osspial/render_to_dxgi/blob/master/src/main.rs
 // https://github.com/nlguillemot/OpenGL-on-DXGI/blob/master/main.cpp
 #[allow(non_snake_case)]
-pub(crate) fn create_dxgi_swapchain(
+pub fn create_dxgi_swapchain(
     raw_window_handle: &RawWindowHandle,
     gl: &glow::Context,
+    high_dynamic_range: bool,
+    max_frame_latency: Option<u32>,
+    preferred_gpu: Option<&str>,
 ) -> Result<DXGIInterop, Problem> {
     if is_intel_gpu(gl) {
         log::debug!("Intel GPU detected. Disabling DXGI swapchain");
@@ -137,51 +474,39 @@ pub(crate) fn create_dxgi_swapchain(
     };
 
     let hwnd = HWND(win32_handle.hwnd as _);
+    let adapter = preferred_gpu
+        .and_then(super::gpu_preference::find)
+        .and_then(|adapter| adapter.cast::<IDXGIAdapter>().ok());
+
+    let created = if high_dynamic_range || max_frame_latency.is_some() {
+        unsafe {
+            create_flip_swapchain(
+                hwnd,
+                adapter.as_ref(),
+                high_dynamic_range,
+                max_frame_latency,
+            )
+        }
+        .map_err(|err| log::warn!("{}. Falling back to the standard swapchain.", err))
+        .ok()
+    } else {
+        None
+    };
+    let flip_model = created.is_some();
+    let (device, context, swap_chain, output_format, frame_latency_waitable) = match created {
+        Some(created) => created,
+        None => unsafe { create_standard_swapchain(hwnd, adapter.as_ref())? },
+    };
 
-    let mut p_device: Option<ID3D11Device> = None;
-    let mut p_context: Option<ID3D11DeviceContext> = None;
-    let mut p_swap_chain: Option<IDXGISwapChain> = None;
-
-    unsafe {
-        D3D11CreateDeviceAndSwapChain(
-            None,                        // Adapter
-            D3D_DRIVER_TYPE_HARDWARE,    // Driver type
-            None,                        // Software
-            D3D11_CREATE_DEVICE_FLAG(0), // Flags (do not set D3D11_CREATE_DEVICE_SINGLETHREADED)
-            None,                        // Feature levels
-            D3D11_SDK_VERSION,           // SDK version
-            Some(&DXGI_SWAP_CHAIN_DESC {
-                BufferDesc: DXGI_MODE_DESC {
-                    Format: DXGI_FORMAT_R8G8B8A8_UNORM,
-                    ..Default::default()
-                },
-                BufferUsage: DXGI_USAGE_RENDER_TARGET_OUTPUT,
-                BufferCount: 2,
-                OutputWindow: hwnd,
-                Windowed: true.into(),
-                // FLIP modes don't work on NVIDIA cards.
-                SwapEffect: DXGI_SWAP_EFFECT_DISCARD,
-                SampleDesc: DXGI_SAMPLE_DESC {
-                    // Disable MSAA (also unsupported with the 'flip' model)
-                    Count: 1,
-                    Quality: 0,
-                },
-                ..Default::default()
-            }),
-            Some(&mut p_swap_chain),
-            Some(&mut p_device),
-            None,
-            Some(&mut p_context),
-        )
-        .map_err(|_| "Failed to create DXGI device and swapchain")?;
+    log::debug!(
+        "Created device, context, and swapchain in the {:?} format",
+        output_format
+    );
+    if let Some(max_frame_latency) = max_frame_latency.filter(|_| frame_latency_waitable.is_some())
+    {
+        log::debug!("Queuing up at most {} frames", max_frame_latency);
     }
 
-    let swap_chain = p_swap_chain.expect("failed to created swapchain");
-    let context = p_context.expect("failed to create immediate context");
-    let device = p_device.expect("failed to create device");
-
-    log::debug!("Created device, context, and swapchain");
-
     log::debug!("Fetching WGL extensions");
 
     unsafe {
@@ -267,11 +592,12 @@ pub(crate) fn create_dxgi_swapchain(
         let fbo = gl.create_framebuffer().unwrap();
         let rbo = gl.create_renderbuffer().unwrap();
 
+        let mut color_object_gl = ColorObject::Renderbuffer(rbo);
         let mut color_handle_gl = (dx_interop.DXRegisterObjectNV)(
             gl_handle_d3d,
             color_buffer.as_raw(),
-            rbo.0.into(),
-            GL::RENDERBUFFER,
+            color_object_gl.name(),
+            color_object_gl.target(),
             WGL_ACCESS_READ_WRITE_DISCARD_NV,
         );
 
@@ -280,13 +606,14 @@ pub(crate) fn create_dxgi_swapchain(
 
             gl.delete_renderbuffer(rbo);
             let texture = gl.create_texture().unwrap();
+            color_object_gl = ColorObject::Texture(texture);
 
             // According to my testing, AMD graphics cards don't support sharing renderbuffers.
             color_handle_gl = (dx_interop.DXRegisterObjectNV)(
                 gl_handle_d3d,
                 color_buffer.as_raw(),
-                texture.0.into(),
-                GL::TEXTURE_2D,
+                color_object_gl.name(),
+                color_object_gl.target(),
                 WGL_ACCESS_READ_WRITE_DISCARD_NV,
             );
 
@@ -298,27 +625,10 @@ pub(crate) fn create_dxgi_swapchain(
             }
 
             log::debug!("Registered DXGI swapchain as GL texture");
-
-            // Bind the texture to the framebuffer
-            gl.bind_framebuffer(GL::FRAMEBUFFER, Some(fbo));
-            gl.framebuffer_texture_2d(
-                GL::FRAMEBUFFER,
-                GL::COLOR_ATTACHMENT0,
-                GL::TEXTURE_2D,
-                Some(texture),
-                0,
-            );
         } else {
             log::debug!("Registered DXGI swapchain as GL renderbuffer");
-
-            gl.bind_framebuffer(GL::FRAMEBUFFER, Some(fbo));
-            gl.framebuffer_renderbuffer(
-                GL::FRAMEBUFFER,
-                GL::COLOR_ATTACHMENT0,
-                GL::RENDERBUFFER,
-                Some(rbo),
-            );
         }
+        color_object_gl.attach(gl, fbo);
 
         match gl.check_framebuffer_status(GL::FRAMEBUFFER) {
             GL::FRAMEBUFFER_COMPLETE => {
@@ -341,10 +651,207 @@ pub(crate) fn create_dxgi_swapchain(
             device,
             context,
             swap_chain,
+            output_format,
+            flip_model,
+            frame_latency_waitable,
             gl_handle_d3d,
             dx_interop,
             color_handle_gl,
+            color_object_gl,
             fbo,
         })
     }
 }
+
+type Created = (
+    ID3D11Device,
+    ID3D11DeviceContext,
+    IDXGISwapChain,
+    OutputFormat,
+    Option<HANDLE>,
+);
+
+// D3D11 insists on an unknown driver type when it’s given a specific adapter.
+fn driver_type(adapter: Option<&IDXGIAdapter>) -> D3D_DRIVER_TYPE {
+    match adapter {
+        Some(_) => D3D_DRIVER_TYPE_UNKNOWN,
+        None => D3D_DRIVER_TYPE_HARDWARE,
+    }
+}
+
+unsafe fn create_standard_swapchain(
+    hwnd: HWND,
+    adapter: Option<&IDXGIAdapter>,
+) -> Result<Created, Problem> {
+    let mut p_device: Option<ID3D11Device> = None;
+    let mut p_context: Option<ID3D11DeviceContext> = None;
+    let mut p_swap_chain: Option<IDXGISwapChain> = None;
+
+    D3D11CreateDeviceAndSwapChain(
+        adapter,                     // Adapter
+        driver_type(adapter),        // Driver type
+        None,                        // Software
+        D3D11_CREATE_DEVICE_FLAG(0), // Flags (do not set D3D11_CREATE_DEVICE_SINGLETHREADED)
+        None,                        // Feature levels
+        D3D11_SDK_VERSION,           // SDK version
+        Some(&DXGI_SWAP_CHAIN_DESC {
+            BufferDesc: DXGI_MODE_DESC {
+                Format: OutputFormat::Standard.format(),
+                ..Default::default()
+            },
+            BufferUsage: DXGI_USAGE_RENDER_TARGET_OUTPUT,
+            BufferCount: 2,
+            OutputWindow: hwnd,
+            Windowed: true.into(),
+            // FLIP modes don't work on NVIDIA cards.
+            SwapEffect: DXGI_SWAP_EFFECT_DISCARD,
+            SampleDesc: DXGI_SAMPLE_DESC {
+                // Disable MSAA (also unsupported with the 'flip' model)
+                Count: 1,
+                Quality: 0,
+            },
+            ..Default::default()
+        }),
+        Some(&mut p_swap_chain),
+        Some(&mut p_device),
+        None,
+        Some(&mut p_context),
+    )
+    .map_err(|_| "Failed to create DXGI device and swapchain")?;
+
+    let swap_chain = p_swap_chain.expect("failed to created swapchain");
+    let context = p_context.expect("failed to create immediate context");
+    let device = p_device.expect("failed to create device");
+
+    Ok((device, context, swap_chain, OutputFormat::Standard, None))
+}
+
+// DXGI only presents formats other than 8-bit sRGB, and only hands out a frame latency waitable
+// object, through a flip model swapchain. Flip model swapchains don’t get along with the interop
+// on some NVIDIA cards, which is why this is opt-in.
+unsafe fn create_flip_swapchain(
+    hwnd: HWND,
+    adapter: Option<&IDXGIAdapter>,
+    high_dynamic_range: bool,
+    max_frame_latency: Option<u32>,
+) -> Result<Created, String> {
+    let mut p_device: Option<ID3D11Device> = None;
+    let mut p_context: Option<ID3D11DeviceContext> = None;
+    D3D11CreateDevice(
+        adapter,
+        driver_type(adapter),
+        None,
+        D3D11_CREATE_DEVICE_FLAG(0),
+        None,
+        D3D11_SDK_VERSION,
+        Some(&mut p_device),
+        None,
+        Some(&mut p_context),
+    )
+    .map_err(|err| format!("Failed to create a D3D11 device: {}", err))?;
+    let device = p_device.ok_or("Failed to create a D3D11 device")?;
+    let context = p_context.ok_or("Failed to create an immediate context")?;
+
+    let adapter = device
+        .cast::<IDXGIDevice>()
+        .and_then(|dxgi_device| dxgi_device.GetAdapter())
+        .map_err(|err| format!("Failed to find the GPU: {}", err))?;
+    let factory: IDXGIFactory2 = adapter
+        .GetParent()
+        .map_err(|err| format!("Failed to find the DXGI factory: {}", err))?;
+
+    let output_format = if !high_dynamic_range {
+        OutputFormat::Standard
+    } else if is_hdr_enabled(&adapter, hwnd) {
+        OutputFormat::ScRgb
+    } else {
+        OutputFormat::TenBit
+    };
+    let max_frame_latency = max_frame_latency.map(|latency| latency.clamp(1, MAX_FRAME_LATENCY));
+    let flags = match max_frame_latency {
+        Some(_) => DXGI_SWAP_CHAIN_FLAG_FRAME_LATENCY_WAITABLE_OBJECT.0 as u32,
+        None => 0,
+    };
+
+    let swap_chain = factory
+        .CreateSwapChainForHwnd(
+            &device,
+            hwnd,
+            &DXGI_SWAP_CHAIN_DESC1 {
+                Format: output_format.format(),
+                SampleDesc: DXGI_SAMPLE_DESC {
+                    Count: 1,
+                    Quality: 0,
+                },
+                BufferUsage: DXGI_USAGE_RENDER_TARGET_OUTPUT,
+                // One more than the frames in the queue, to render into
+                BufferCount: max_frame_latency.map_or(2, |latency| (latency + 1).max(2)),
+                SwapEffect: DXGI_SWAP_EFFECT_FLIP_DISCARD,
+                Flags: flags,
+                ..Default::default()
+            },
+            None,
+            None,
+        )
+        .map_err(|err| format!("Failed to create a {:?} swapchain: {}", output_format, err))?;
+
+    if high_dynamic_range {
+        let color_space = output_format.color_space();
+        let swap_chain3 = swap_chain
+            .cast::<IDXGISwapChain3>()
+            .map_err(|err| format!("Failed to set the color space: {}", err))?;
+        let support = swap_chain3.CheckColorSpaceSupport(color_space).unwrap_or(0);
+        if support & DXGI_SWAP_CHAIN_COLOR_SPACE_SUPPORT_FLAG_PRESENT.0 as u32 == 0 {
+            return Err(format!(
+                "The display can’t present the {:?} format",
+                output_format
+            ));
+        }
+        swap_chain3
+            .SetColorSpace1(color_space)
+            .map_err(|err| format!("Failed to set the color space: {}", err))?;
+    }
+
+    let frame_latency_waitable = match max_frame_latency {
+        Some(max_frame_latency) => {
+            let swap_chain2 = swap_chain
+                .cast::<IDXGISwapChain2>()
+                .map_err(|err| format!("Failed to set the frame latency: {}", err))?;
+            swap_chain2
+                .SetMaximumFrameLatency(max_frame_latency)
+                .map_err(|err| format!("Failed to set the frame latency: {}", err))?;
+            let waitable = swap_chain2.GetFrameLatencyWaitableObject();
+            if waitable.is_invalid() {
+                return Err("Failed to get the frame latency waitable object".to_string());
+            }
+            Some(waitable)
+        }
+        None => None,
+    };
+
+    let swap_chain = swap_chain
+        .cast::<IDXGISwapChain>()
+        .map_err(|err| err.to_string())?;
+
+    Ok((
+        device,
+        context,
+        swap_chain,
+        output_format,
+        frame_latency_waitable,
+    ))
+}
+
+// Check whether the display that the window is on is in HDR mode.
+unsafe fn is_hdr_enabled(adapter: &IDXGIAdapter, hwnd: HWND) -> bool {
+    let monitor = MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST);
+
+    (0..)
+        .map_while(|index| adapter.EnumOutputs(index).ok())
+        .filter_map(|output| output.cast::<IDXGIOutput6>().ok())
+        .filter_map(|output| output.GetDesc1().ok())
+        .find(|desc| desc.Monitor == monitor)
+        .map_or(false, |desc| {
+            desc.ColorSpace == DXGI_COLOR_SPACE_RGB_FULL_G2084_NONE_P2020
+        })
+}
@@ -0,0 +1,164 @@
+use std::ffi::c_void;
+
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::ERROR_SUCCESS;
+use windows::Win32::System::Registry::{RegGetValueW, HKEY_LOCAL_MACHINE, RRF_RT_REG_BINARY};
+use winit::dpi::PhysicalSize;
+
+const HEADER: [u8; 8] = [0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00];
+const BASE_BLOCK_LEN: usize = 128;
+
+/// The physical size of a monitor in millimetres, as reported by its EDID.
+///
+/// Windows keeps a copy of the EDID of every monitor it has seen in the registry, under the
+/// monitor’s device instance. Projectors and some TVs don’t report a size, since it depends on how
+/// far away the screen is.
+pub fn physical_size(device_path: &str) -> Option<PhysicalSize<u32>> {
+    let key = registry_key(device_path)?;
+    let edid = read_edid(&key)?;
+    parse_physical_size(&edid)
+}
+
+// Find the registry key for a monitor from its device path.
+//
+// The device path looks like `\\?\DISPLAY#DEL40A3#5&2b3c4d&0&UID4353#{e6f07b5f-…}`. Dropping the
+// prefix and the interface class at the end leaves the device instance ID, with `#` in place of
+// the backslashes.
+fn registry_key(device_path: &str) -> Option<String> {
+    let path = device_path.strip_prefix(r"\\?\")?;
+    let (instance_id, _interface_class) = path.rsplit_once('#')?;
+    if instance_id.is_empty() {
+        return None;
+    }
+
+    Some(format!(
+        r"SYSTEM\CurrentControlSet\Enum\{}\Device Parameters",
+        instance_id.replace('#', r"\")
+    ))
+}
+
+fn read_edid(key: &str) -> Option<Vec<u8>> {
+    let key = key.encode_utf16().chain(Some(0)).collect::<Vec<u16>>();
+    let value = "EDID".encode_utf16().chain(Some(0)).collect::<Vec<u16>>();
+    let (key, value) = (PCWSTR(key.as_ptr()), PCWSTR(value.as_ptr()));
+
+    unsafe {
+        // Ask for the size first: the EDID is longer than the base block if it has extensions.
+        let mut size = 0;
+        let res = RegGetValueW(
+            HKEY_LOCAL_MACHINE,
+            key,
+            value,
+            RRF_RT_REG_BINARY,
+            None,
+            None,
+            Some(&mut size),
+        );
+        if res != ERROR_SUCCESS {
+            return None;
+        }
+
+        let mut edid = vec![0u8; size as usize];
+        let res = RegGetValueW(
+            HKEY_LOCAL_MACHINE,
+            key,
+            value,
+            RRF_RT_REG_BINARY,
+            None,
+            Some(edid.as_mut_ptr() as *mut c_void),
+            Some(&mut size),
+        );
+        if res != ERROR_SUCCESS {
+            return None;
+        }
+
+        edid.truncate(size as usize);
+        Some(edid)
+    }
+}
+
+// Read the physical size from the base block of an EDID.
+//
+// The first detailed timing descriptor, which describes the preferred mode, has the size in
+// millimetres. The basic display parameters only have it in whole centimetres, so they’re the
+// fallback.
+fn parse_physical_size(edid: &[u8]) -> Option<PhysicalSize<u32>> {
+    if edid.len() < BASE_BLOCK_LEN || edid[..HEADER.len()] != HEADER {
+        return None;
+    }
+
+    let descriptor = &edid[54..72];
+    let is_timing = descriptor[0] != 0 || descriptor[1] != 0;
+    if is_timing {
+        let width = descriptor[12] as u32 | ((descriptor[14] as u32 & 0xF0) << 4);
+        let height = descriptor[13] as u32 | ((descriptor[14] as u32 & 0x0F) << 8);
+        if width > 0 && height > 0 {
+            return Some(PhysicalSize::new(width, height));
+        }
+    }
+
+    let (width_cm, height_cm) = (edid[21] as u32, edid[22] as u32);
+    (width_cm > 0 && height_cm > 0).then(|| PhysicalSize::new(width_cm * 10, height_cm * 10))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // A 24" 1080p monitor that reports 53 × 30 cm, and 527 × 296 mm in its preferred mode
+    fn edid() -> Vec<u8> {
+        let mut edid = vec![0; BASE_BLOCK_LEN];
+        edid[..8].copy_from_slice(&HEADER);
+        edid[21] = 53;
+        edid[22] = 30;
+        // Pixel clock of 148.5 MHz
+        edid[54] = 0x02;
+        edid[55] = 0x3A;
+        edid[66] = 0x0F;
+        edid[67] = 0x28;
+        edid[68] = 0x21;
+        edid
+    }
+
+    #[test]
+    fn it_finds_the_registry_key() {
+        assert_eq!(
+            registry_key(
+                r"\\?\DISPLAY#DEL40A3#5&2b3c4d&0&UID4353#{e6f07b5f-ee97-4a90-b076-33f57bf4eaa7}"
+            )
+            .as_deref(),
+            Some(
+                r"SYSTEM\CurrentControlSet\Enum\DISPLAY\DEL40A3\5&2b3c4d&0&UID4353\Device Parameters"
+            )
+        );
+        assert_eq!(registry_key("DISPLAY1"), None);
+    }
+
+    #[test]
+    fn it_reads_the_size_from_the_preferred_mode() {
+        assert_eq!(
+            parse_physical_size(&edid()),
+            Some(PhysicalSize::new(527, 296))
+        );
+    }
+
+    #[test]
+    fn it_falls_back_to_the_size_in_centimetres() {
+        let mut edid = edid();
+        edid[66..69].fill(0);
+        assert_eq!(
+            parse_physical_size(&edid),
+            Some(PhysicalSize::new(530, 300))
+        );
+    }
+
+    #[test]
+    fn it_skips_monitors_without_a_size() {
+        let mut edid = edid();
+        edid[66..69].fill(0);
+        edid[21] = 0;
+        edid[22] = 0;
+        assert_eq!(parse_physical_size(&edid), None);
+        assert_eq!(parse_physical_size(&edid[..64]), None);
+    }
+}
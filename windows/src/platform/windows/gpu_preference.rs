@@ -0,0 +1,323 @@
+use std::ffi::c_void;
+
+use windows::core::{w, PCWSTR};
+use windows::Win32::Foundation::{ERROR_FILE_NOT_FOUND, ERROR_SUCCESS, LUID, WIN32_ERROR};
+use windows::Win32::Graphics::Dxgi::{
+    CreateDXGIFactory1, IDXGIAdapter1, IDXGIFactory1, IDXGIFactory6, DXGI_ADAPTER_FLAG_SOFTWARE,
+    DXGI_GPU_PREFERENCE, DXGI_GPU_PREFERENCE_HIGH_PERFORMANCE, DXGI_GPU_PREFERENCE_MINIMUM_POWER,
+};
+use windows::Win32::System::Registry::{
+    RegDeleteKeyValueW, RegGetValueW, RegSetKeyValueW, HKEY_CURRENT_USER, REG_SZ, RRF_RT_REG_SZ,
+};
+
+// Where Windows keeps the GPU picked for each program in the graphics settings
+const USER_GPU_PREFERENCES_KEY: PCWSTR = w!("Software\\Microsoft\\DirectX\\UserGpuPreferences");
+// Where Flux notes down the preferences it set itself, so that it only ever takes back its own,
+// and not one picked in the graphics settings
+const FLUX_GPU_PREFERENCES_KEY: PCWSTR = w!("Software\\Flux\\UserGpuPreferences");
+
+/// A GPU, as DXGI lists it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Adapter {
+    pub name: String,
+    /// The name, numbered in the order DXGI lists them if there’s more than one GPU by that
+    /// name, like “NVIDIA RTX A2000 #2”
+    pub label: String,
+    /// The locally unique ID, in hex. Unlike the name, this tells identical GPUs apart, but it
+    /// only lasts until the computer restarts.
+    pub luid: String,
+}
+
+impl Adapter {
+    /// Whether this is the GPU the user asked for, by its LUID, its label, or by part of its
+    /// name.
+    pub fn matches(&self, preference: &str) -> bool {
+        let preference = preference.trim();
+        !preference.is_empty()
+            && (self.luid.eq_ignore_ascii_case(preference)
+                || self.label.eq_ignore_ascii_case(preference)
+                || self
+                    .name
+                    .to_lowercase()
+                    .contains(&preference.to_lowercase()))
+    }
+}
+
+/// The GPUs in the computer, leaving out the software renderer.
+pub fn adapters() -> Vec<Adapter> {
+    match unsafe { enumerate() } {
+        Ok(adapters) => adapters.into_iter().map(|(adapter, _)| adapter).collect(),
+        Err(err) => {
+            log::warn!("{}", err);
+            Vec::new()
+        }
+    }
+}
+
+/// The GPU matching the preference, to create the DXGI device on.
+pub fn find(preference: &str) -> Option<IDXGIAdapter1> {
+    let adapters = unsafe { enumerate() }
+        .map_err(|err| log::warn!("{}", err))
+        .ok()?;
+    match adapters
+        .into_iter()
+        .find(|(adapter, _)| adapter.matches(preference))
+    {
+        Some((adapter, dxgi_adapter)) => {
+            log::debug!("Using the preferred GPU: {}", adapter.name);
+            Some(dxgi_adapter)
+        }
+        None => {
+            log::warn!(
+                "There’s no GPU matching “{}”, so letting Windows pick one",
+                preference
+            );
+            None
+        }
+    }
+}
+
+/// How Windows tells the GPUs apart when picking one for a program.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OsPreference {
+    MinimumPower,
+    HighPerformance,
+}
+
+impl OsPreference {
+    // The value that the graphics settings use for this preference.
+    fn registry_value(self) -> &'static str {
+        match self {
+            OsPreference::MinimumPower => "GpuPreference=1;",
+            OsPreference::HighPerformance => "GpuPreference=2;",
+        }
+    }
+}
+
+/// Ask Windows to start OpenGL on the preferred GPU, the same way as picking a GPU for Flux in the
+/// graphics settings. Returns the preference that Windows was told about, if any, to point the
+/// hybrid graphics drivers at the same GPU.
+///
+/// OpenGL can’t be pointed at a GPU directly. Windows only knows the GPUs as power saving or high
+/// performance, so a GPU that’s neither, like the second discrete GPU in a desktop, is left up to
+/// the driver.
+///
+/// Without a preference, or one that Windows can’t be told about, a preference that Flux set
+/// before is taken back, so that Windows is free to pick again. One that the user picked in the
+/// graphics settings is left alone.
+pub fn set_os_preference(preference: Option<&str>) -> Result<Option<OsPreference>, String> {
+    let exe = std::env::current_exe()
+        .map_err(|err| format!("Failed to find the screensaver executable: {}", err))?;
+    let exe = to_wide(&exe.display().to_string());
+    let exe = PCWSTR(exe.as_ptr());
+
+    let os_preference = preference.map(os_preference).transpose()?.flatten();
+    match os_preference {
+        Some(os_preference) => {
+            let value = os_preference.registry_value();
+            set_value(USER_GPU_PREFERENCES_KEY, exe, value)
+                .map_err(|err| format!("Failed to set the GPU preference: {:?}", err))?;
+            // Not being able to note it down only means it can’t be taken back later on.
+            if let Err(err) = set_value(FLUX_GPU_PREFERENCES_KEY, exe, value) {
+                log::warn!("Failed to note down the GPU preference: {:?}", err);
+            }
+        }
+        None => {
+            let Some(ours) = get_value(FLUX_GPU_PREFERENCES_KEY, exe) else {
+                return Ok(None);
+            };
+            // The user may have picked something else in the graphics settings since.
+            if get_value(USER_GPU_PREFERENCES_KEY, exe).as_ref() == Some(&ours) {
+                delete_value(USER_GPU_PREFERENCES_KEY, exe)
+                    .map_err(|err| format!("Failed to reset the GPU preference: {:?}", err))?;
+            }
+            if let Err(err) = delete_value(FLUX_GPU_PREFERENCES_KEY, exe) {
+                log::warn!("Failed to forget the GPU preference: {:?}", err);
+            }
+        }
+    }
+
+    Ok(os_preference)
+}
+
+fn get_value(key: PCWSTR, name: PCWSTR) -> Option<String> {
+    let mut value = [0u16; 256];
+    let mut size = std::mem::size_of_val(&value) as u32;
+    let res = unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            key,
+            name,
+            RRF_RT_REG_SZ,
+            None,
+            Some(value.as_mut_ptr() as *mut c_void),
+            Some(&mut size),
+        )
+    };
+    if res != ERROR_SUCCESS {
+        return None;
+    }
+
+    let len = value.iter().position(|&c| c == 0).unwrap_or(value.len());
+    Some(String::from_utf16_lossy(&value[..len]))
+}
+
+fn set_value(key: PCWSTR, name: PCWSTR, value: &str) -> Result<(), WIN32_ERROR> {
+    let value = to_wide(value);
+    let res = unsafe {
+        RegSetKeyValueW(
+            HKEY_CURRENT_USER,
+            key,
+            name,
+            REG_SZ.0,
+            Some(value.as_ptr() as *const _),
+            (value.len() * std::mem::size_of::<u16>()) as u32,
+        )
+    };
+
+    match res {
+        ERROR_SUCCESS => Ok(()),
+        err => Err(err),
+    }
+}
+
+fn delete_value(key: PCWSTR, name: PCWSTR) -> Result<(), WIN32_ERROR> {
+    match unsafe { RegDeleteKeyValueW(HKEY_CURRENT_USER, key, name) } {
+        ERROR_SUCCESS | ERROR_FILE_NOT_FOUND => Ok(()),
+        err => Err(err),
+    }
+}
+
+// How Windows can be told to prefer the GPU, if it can be.
+fn os_preference(preference: &str) -> Result<Option<OsPreference>, String> {
+    let adapters = unsafe { enumerate()? };
+    let (adapter, _) = adapters
+        .iter()
+        .find(|(adapter, _)| adapter.matches(preference))
+        .ok_or_else(|| format!("There’s no GPU matching “{}”", preference))?;
+
+    let first_by = |gpu_preference: DXGI_GPU_PREFERENCE| unsafe {
+        CreateDXGIFactory1::<IDXGIFactory6>()
+            .and_then(|factory| {
+                factory.EnumAdapterByGpuPreference::<IDXGIAdapter1>(0, gpu_preference)
+            })
+            .and_then(|adapter| adapter.GetDesc1())
+            .map(|desc| format_luid(desc.AdapterLuid))
+            .ok()
+    };
+    let high_performance = first_by(DXGI_GPU_PREFERENCE_HIGH_PERFORMANCE);
+    let minimum_power = first_by(DXGI_GPU_PREFERENCE_MINIMUM_POWER);
+    if high_performance == minimum_power {
+        // A single GPU, or Windows can’t tell them apart
+        Ok(None)
+    } else if high_performance.as_ref() == Some(&adapter.luid) {
+        Ok(Some(OsPreference::HighPerformance))
+    } else if minimum_power.as_ref() == Some(&adapter.luid) {
+        Ok(Some(OsPreference::MinimumPower))
+    } else {
+        log::info!(
+            "Windows can’t be told to prefer {}, so leaving it up to the driver",
+            adapter.name
+        );
+        Ok(None)
+    }
+}
+
+unsafe fn enumerate() -> Result<Vec<(Adapter, IDXGIAdapter1)>, String> {
+    let factory: IDXGIFactory1 =
+        CreateDXGIFactory1().map_err(|err| format!("Failed to list the GPUs: {}", err))?;
+
+    let mut adapters = (0..)
+        .map_while(|index| factory.EnumAdapters1(index).ok())
+        .filter_map(|dxgi_adapter| {
+            let desc = dxgi_adapter.GetDesc1().ok()?;
+            if desc.Flags & DXGI_ADAPTER_FLAG_SOFTWARE.0 as u32 != 0 {
+                return None;
+            }
+
+            let len = desc
+                .Description
+                .iter()
+                .position(|&c| c == 0)
+                .unwrap_or(desc.Description.len());
+            let name = String::from_utf16_lossy(&desc.Description[..len]);
+            let adapter = Adapter {
+                label: name.clone(),
+                name,
+                luid: format_luid(desc.AdapterLuid),
+            };
+            Some((adapter, dxgi_adapter))
+        })
+        .collect::<Vec<_>>();
+
+    let names = adapters
+        .iter()
+        .map(|(adapter, _)| adapter.name.as_str())
+        .collect::<Vec<_>>();
+    let labels = labels(&names);
+    for ((adapter, _), label) in adapters.iter_mut().zip(labels) {
+        adapter.label = label;
+    }
+
+    Ok(adapters)
+}
+
+// Number the GPUs that share a name, like two of the same card in a desktop, so that each can be
+// picked. The LUIDs tell them apart too, but change whenever the computer restarts.
+fn labels(names: &[&str]) -> Vec<String> {
+    names
+        .iter()
+        .enumerate()
+        .map(|(index, name)| {
+            if names.iter().filter(|other| *other == name).count() < 2 {
+                return name.to_string();
+            }
+            let number = names[..index].iter().filter(|other| *other == name).count() + 1;
+            format!("{} #{}", name, number)
+        })
+        .collect()
+}
+
+fn format_luid(luid: LUID) -> String {
+    format!("{:08X}{:08X}", luid.HighPart as u32, luid.LowPart)
+}
+
+fn to_wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_matches_by_luid_or_part_of_the_name() {
+        let adapter = Adapter {
+            name: "NVIDIA GeForce RTX 4060 Laptop GPU".to_string(),
+            label: "NVIDIA GeForce RTX 4060 Laptop GPU".to_string(),
+            luid: "000000000001A2B3".to_string(),
+        };
+
+        assert!(adapter.matches("rtx 4060"));
+        assert!(adapter.matches("000000000001a2b3"));
+        assert!(!adapter.matches("Intel"));
+        assert!(!adapter.matches(" "));
+    }
+
+    #[test]
+    fn it_numbers_gpus_that_share_a_name() {
+        let labels = labels(&["NVIDIA RTX A2000", "Intel UHD", "NVIDIA RTX A2000"]);
+        assert_eq!(
+            labels,
+            ["NVIDIA RTX A2000 #1", "Intel UHD", "NVIDIA RTX A2000 #2"]
+        );
+
+        let second = Adapter {
+            name: "NVIDIA RTX A2000".to_string(),
+            label: labels[2].clone(),
+            luid: "0000000000000002".to_string(),
+        };
+        assert!(second.matches("nvidia rtx a2000 #2"));
+        assert!(!second.matches("NVIDIA RTX A2000 #1"));
+    }
+}
@@ -0,0 +1,63 @@
+use std::ffi::c_void;
+use std::time::Duration;
+
+use windows::Win32::System::Power::{
+    CallNtPowerInformation, SystemExecutionState, ES_DISPLAY_REQUIRED, EXECUTION_STATE,
+};
+use windows::Win32::System::SystemInformation::GetTickCount;
+use windows::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+use windows::Win32::UI::Shell::{
+    SHQueryUserNotificationState, QUNS_BUSY, QUNS_PRESENTATION_MODE, QUNS_RUNNING_D3D_FULL_SCREEN,
+};
+
+/// How long it’s been since the last keyboard or mouse input in this session.
+pub fn idle_time() -> Result<Duration, String> {
+    let mut info = LASTINPUTINFO {
+        cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+        dwTime: 0,
+    };
+
+    unsafe {
+        if !GetLastInputInfo(&mut info).as_bool() {
+            return Err("Failed to get the time of the last input".to_string());
+        }
+
+        // Both are milliseconds since boot, which wrap around every 49.7 days.
+        let elapsed = GetTickCount().wrapping_sub(info.dwTime);
+        Ok(Duration::from_millis(elapsed.into()))
+    }
+}
+
+/// Whether any program has asked to keep the display on, like a video player or a presentation.
+/// Windows holds off its own screensaver while this is set.
+pub fn is_display_required() -> bool {
+    let mut state = EXECUTION_STATE::default();
+    let res = unsafe {
+        CallNtPowerInformation(
+            SystemExecutionState,
+            None,
+            0,
+            Some(&mut state as *mut EXECUTION_STATE as *mut c_void),
+            std::mem::size_of::<EXECUTION_STATE>() as u32,
+        )
+    };
+
+    res.is_ok() && state.0 & ES_DISPLAY_REQUIRED.0 != 0
+}
+
+/// Whether the user is presenting, or has something running full screen, like a game or a video.
+/// Focus Assist holds back notifications at these times, and so should we.
+pub fn is_do_not_disturb() -> bool {
+    match unsafe { SHQueryUserNotificationState() } {
+        Ok(state) => [
+            QUNS_BUSY,
+            QUNS_RUNNING_D3D_FULL_SCREEN,
+            QUNS_PRESENTATION_MODE,
+        ]
+        .contains(&state),
+        Err(err) => {
+            log::debug!("Failed to check whether the user is busy: {}", err);
+            false
+        }
+    }
+}
@@ -1,3 +1,24 @@
+pub mod autostart;
+pub mod console;
+pub mod crash;
+pub mod desktop_icons;
+pub mod display_config;
+pub mod display_power;
 pub mod dpi_awareness;
 pub mod dxgi_swapchain;
+pub mod edid;
+pub mod gpu_preference;
+pub mod idle;
+pub mod named_event;
+pub mod occlusion;
+pub mod policy;
+pub mod power;
+pub mod process;
+pub mod raw_input;
+pub mod screen_capture;
+pub mod service;
+pub mod session;
+pub mod size_move;
+pub mod timer;
 pub mod window;
+pub mod wine;
@@ -0,0 +1,56 @@
+use std::time::Duration;
+
+use windows::core::HSTRING;
+use windows::Win32::Foundation::{CloseHandle, FALSE, HANDLE, WAIT_OBJECT_0};
+use windows::Win32::System::Threading::{
+    CreateEventW, OpenEventW, ResetEvent, SetEvent, WaitForSingleObject, EVENT_MODIFY_STATE,
+    SYNCHRONIZATION_SYNCHRONIZE,
+};
+
+/// An event that one process sets for another to wait on, found by name. Each signal wakes up a
+/// single wait.
+pub struct NamedEvent {
+    handle: HANDLE,
+}
+
+impl NamedEvent {
+    pub fn create(name: &str) -> Result<Self, String> {
+        unsafe { CreateEventW(None, FALSE, FALSE, &HSTRING::from(name)) }
+            .map(|handle| Self { handle })
+            .map_err(|err| format!("Failed to create the event {}: {}", name, err))
+    }
+
+    /// Open an event created by another process.
+    pub fn open(name: &str) -> Result<Self, String> {
+        unsafe {
+            OpenEventW(
+                SYNCHRONIZATION_SYNCHRONIZE | EVENT_MODIFY_STATE,
+                FALSE,
+                &HSTRING::from(name),
+            )
+        }
+        .map(|handle| Self { handle })
+        .map_err(|err| format!("Failed to open the event {}: {}", name, err))
+    }
+
+    pub fn set(&self) -> Result<(), String> {
+        unsafe { SetEvent(self.handle) }.map_err(|err| format!("Failed to set the event: {}", err))
+    }
+
+    /// Take back a signal that nobody has waited on yet.
+    pub fn reset(&self) {
+        let _ = unsafe { ResetEvent(self.handle) };
+    }
+
+    /// Wait for the event to be set, for at most `timeout`. Returns whether it was.
+    pub fn wait(&self, timeout: Duration) -> bool {
+        let timeout = timeout.as_millis().min(u32::MAX as u128 - 1) as u32;
+        unsafe { WaitForSingleObject(self.handle, timeout) == WAIT_OBJECT_0 }
+    }
+}
+
+impl Drop for NamedEvent {
+    fn drop(&mut self) {
+        let _ = unsafe { CloseHandle(self.handle) };
+    }
+}
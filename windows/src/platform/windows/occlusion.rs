@@ -0,0 +1,80 @@
+use raw_window_handle::RawWindowHandle;
+use std::ffi::c_void;
+
+use windows::Win32::Foundation::{HWND, RECT};
+use windows::Win32::Graphics::Dwm::{DwmGetWindowAttribute, DWMWA_CLOAKED};
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetWindow, GetWindowLongW, GetWindowRect, IsIconic, IsWindowVisible, GWL_EXSTYLE, GW_HWNDPREV,
+    WINDOW_EX_STYLE, WS_EX_LAYERED, WS_EX_TRANSPARENT,
+};
+
+/// Check whether a window is out of view, either because it’s on another virtual desktop or
+/// because another window covers it completely.
+///
+/// Windows doesn’t report occlusion for OpenGL windows, so this walks the windows above ours
+/// instead. It only looks for a single window that covers ours. Several windows that only cover
+/// it together don’t count, which errs on the side of rendering.
+///
+/// # Safety
+///
+/// `handle` has to be a window that’s still open.
+pub unsafe fn is_occluded(handle: &RawWindowHandle) -> bool {
+    let hwnd = match handle {
+        RawWindowHandle::Win32(window_handle) => HWND(window_handle.hwnd as _),
+        _ => return false,
+    };
+
+    if is_cloaked(hwnd) {
+        return true;
+    }
+
+    let Some(rect) = window_rect(hwnd) else {
+        return false;
+    };
+
+    let mut above = GetWindow(hwnd, GW_HWNDPREV);
+    while above.0 != 0 {
+        if is_opaque(above) && window_rect(above).is_some_and(|other| contains(&other, &rect)) {
+            return true;
+        }
+        above = GetWindow(above, GW_HWNDPREV);
+    }
+
+    false
+}
+
+// Windows on other virtual desktops are cloaked, rather than hidden.
+unsafe fn is_cloaked(hwnd: HWND) -> bool {
+    let mut cloaked: u32 = 0;
+    DwmGetWindowAttribute(
+        hwnd,
+        DWMWA_CLOAKED,
+        &mut cloaked as *mut u32 as *mut c_void,
+        std::mem::size_of::<u32>() as u32,
+    )
+    .is_ok()
+        && cloaked != 0
+}
+
+// Whether the window is on screen and hides whatever is behind it. Layered windows can be
+// translucent, so they’re assumed to let the animation show through.
+unsafe fn is_opaque(hwnd: HWND) -> bool {
+    let ex_style = WINDOW_EX_STYLE(GetWindowLongW(hwnd, GWL_EXSTYLE) as u32);
+
+    IsWindowVisible(hwnd).as_bool()
+        && !IsIconic(hwnd).as_bool()
+        && !is_cloaked(hwnd)
+        && (ex_style & (WS_EX_LAYERED | WS_EX_TRANSPARENT)).0 == 0
+}
+
+unsafe fn window_rect(hwnd: HWND) -> Option<RECT> {
+    let mut rect = RECT::default();
+    GetWindowRect(hwnd, &mut rect).ok().map(|_| rect)
+}
+
+fn contains(outer: &RECT, inner: &RECT) -> bool {
+    outer.left <= inner.left
+        && outer.top <= inner.top
+        && outer.right >= inner.right
+        && outer.bottom >= inner.bottom
+}
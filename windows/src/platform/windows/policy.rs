@@ -0,0 +1,33 @@
+use std::ffi::c_void;
+
+use windows::core::{w, PCWSTR};
+use windows::Win32::Foundation::ERROR_SUCCESS;
+use windows::Win32::System::Registry::{RegGetValueW, HKEY_LOCAL_MACHINE, RRF_RT_REG_DWORD};
+
+// Where administrators set Group Policy for Flux.
+const POLICY_KEY: PCWSTR = w!("Software\\Policies\\Flux");
+
+/// Whether an administrator has turned off the anonymous ping, with a `DisablePing` DWORD set to
+/// 1. This takes precedence over the settings file.
+pub fn is_ping_disabled() -> bool {
+    read_dword(w!("DisablePing")).is_some_and(|value| value != 0)
+}
+
+fn read_dword(name: PCWSTR) -> Option<u32> {
+    let mut value: u32 = 0;
+    let mut size = std::mem::size_of::<u32>() as u32;
+
+    let res = unsafe {
+        RegGetValueW(
+            HKEY_LOCAL_MACHINE,
+            POLICY_KEY,
+            name,
+            RRF_RT_REG_DWORD,
+            None,
+            Some(&mut value as *mut u32 as *mut c_void),
+            Some(&mut size),
+        )
+    };
+
+    (res == ERROR_SUCCESS).then_some(value)
+}
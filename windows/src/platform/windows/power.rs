@@ -0,0 +1,22 @@
+use crate::power::PowerStatus;
+
+use windows::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+// BatteryLifePercent is 255 when the battery level is unknown, or there’s no battery at all.
+const UNKNOWN_BATTERY_LEVEL: u8 = 255;
+
+/// Check whether the machine is running on battery, and how much charge is left.
+pub fn status() -> Result<PowerStatus, String> {
+    let mut status = SYSTEM_POWER_STATUS::default();
+    unsafe { GetSystemPowerStatus(&mut status) }
+        .map_err(|err| format!("Failed to get the power status: {}", err))?;
+
+    Ok(PowerStatus {
+        // 0 is offline, 1 is online, and 255 is unknown. Desktops without a battery report
+        // online.
+        on_battery: status.ACLineStatus == 0,
+        battery_saver: status.SystemStatusFlag == 1,
+        battery_percent: (status.BatteryLifePercent != UNKNOWN_BATTERY_LEVEL)
+            .then_some(status.BatteryLifePercent),
+    })
+}
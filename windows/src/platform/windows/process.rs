@@ -0,0 +1,110 @@
+use windows::Win32::Foundation::{CloseHandle, FALSE, WAIT_TIMEOUT};
+use windows::Win32::System::Diagnostics::ToolHelp::{
+    CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W, TH32CS_SNAPPROCESS,
+};
+use windows::Win32::System::ProcessStatus::{
+    GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS, PROCESS_MEMORY_COUNTERS_EX,
+};
+use windows::Win32::System::Threading::{
+    GetCurrentProcess, GetGuiResources, GetProcessHandleCount, OpenProcess, WaitForSingleObject,
+    GR_GDIOBJECTS, GR_USEROBJECTS, PROCESS_SYNCHRONIZE,
+};
+
+/// What the screensaver process is holding on to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Usage {
+    /// Memory in RAM right now, in bytes
+    pub working_set: u64,
+    /// Memory committed to the process alone, in bytes, which is what grows with a leak
+    pub private_bytes: u64,
+    /// Kernel handles, like events, files, and threads
+    pub handles: u32,
+    pub gdi_objects: u32,
+    pub user_objects: u32,
+}
+
+pub fn usage() -> Result<Usage, String> {
+    unsafe {
+        let process = GetCurrentProcess();
+
+        let mut counters = PROCESS_MEMORY_COUNTERS_EX {
+            cb: std::mem::size_of::<PROCESS_MEMORY_COUNTERS_EX>() as u32,
+            ..Default::default()
+        };
+        GetProcessMemoryInfo(
+            process,
+            &mut counters as *mut PROCESS_MEMORY_COUNTERS_EX as *mut PROCESS_MEMORY_COUNTERS,
+            counters.cb,
+        )
+        .map_err(|err| format!("Failed to get the memory use: {}", err))?;
+
+        let mut handles = 0;
+        GetProcessHandleCount(process, &mut handles)
+            .map_err(|err| format!("Failed to count the handles: {}", err))?;
+
+        Ok(Usage {
+            working_set: counters.WorkingSetSize as u64,
+            private_bytes: counters.PrivateUsage as u64,
+            handles,
+            gdi_objects: GetGuiResources(process, GR_GDIOBJECTS),
+            user_objects: GetGuiResources(process, GR_USEROBJECTS),
+        })
+    }
+}
+
+/// Whether a process with this ID is still running. IDs are reused once a process exits, so this
+/// can also turn up an unrelated process.
+pub fn is_running(pid: u32) -> bool {
+    unsafe {
+        let Ok(process) = OpenProcess(PROCESS_SYNCHRONIZE, FALSE, pid) else {
+            return false;
+        };
+        let is_running = WaitForSingleObject(process, 0) == WAIT_TIMEOUT;
+        let _ = CloseHandle(process);
+        is_running
+    }
+}
+
+/// The first of the named programs that’s running, if any, as it’s named in the list. Names are
+/// matched against the executable of each process, ignoring case, with or without the `.exe`.
+///
+/// This only takes a snapshot of the process list, which is cheap enough to do every few seconds.
+pub fn find_running(names: &[String]) -> Result<Option<String>, String> {
+    if names.is_empty() {
+        return Ok(None);
+    }
+
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0)
+            .map_err(|err| format!("Failed to list the running processes: {}", err))?;
+
+        let mut entry = PROCESSENTRY32W {
+            dwSize: std::mem::size_of::<PROCESSENTRY32W>() as u32,
+            ..Default::default()
+        };
+        let mut found = None;
+        let mut has_entry = Process32FirstW(snapshot, &mut entry).is_ok();
+        while has_entry {
+            let length = entry
+                .szExeFile
+                .iter()
+                .position(|c| *c == 0)
+                .unwrap_or(entry.szExeFile.len());
+            let exe_name = String::from_utf16_lossy(&entry.szExeFile[..length]);
+            if let Some(name) = names.iter().find(|name| matches(&exe_name, name)) {
+                found = Some(name.clone());
+                break;
+            }
+            has_entry = Process32NextW(snapshot, &mut entry).is_ok();
+        }
+        let _ = CloseHandle(snapshot);
+
+        Ok(found)
+    }
+}
+
+fn matches(exe_name: &str, name: &str) -> bool {
+    let exe_name = exe_name.to_lowercase();
+    let name = name.trim().to_lowercase();
+    exe_name == name || exe_name.strip_suffix(".exe") == Some(name.as_str())
+}
@@ -0,0 +1,300 @@
+use crate::config::InputConfig;
+
+use std::collections::HashMap;
+use std::ffi::c_void;
+use std::mem::size_of;
+
+use windows::core::w;
+use windows::Win32::Foundation::{HANDLE, HINSTANCE, HWND, LPARAM, LRESULT, WPARAM};
+use windows::Win32::UI::Input::{
+    GetRawInputData, GetRawInputDeviceInfoW, GetRawInputDeviceList, RegisterRawInputDevices,
+    HRAWINPUT, RAWINPUTDEVICE, RAWINPUTDEVICELIST, RAWINPUTHEADER, RIDEV_INPUTSINK, RIDEV_REMOVE,
+    RIDI_DEVICENAME, RID_HEADER, RIM_TYPEHID, RIM_TYPEKEYBOARD, RIM_TYPEMOUSE,
+};
+use windows::Win32::UI::Shell::{DefSubclassProc, RemoveWindowSubclass, SetWindowSubclass};
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DestroyWindow, HMENU, HWND_MESSAGE, WINDOW_EX_STYLE, WINDOW_STYLE, WM_INPUT,
+};
+
+const SUBCLASS_ID: usize = 1;
+
+// The HID usages to listen to: mice, keyboards, and joysticks, gamepads, and other controllers
+// with axes, which covers throttles and pedals
+const GENERIC_DESKTOP_PAGE: u16 = 0x01;
+const MOUSE_USAGE: u16 = 0x02;
+const JOYSTICK_USAGE: u16 = 0x04;
+const GAMEPAD_USAGE: u16 = 0x05;
+const KEYBOARD_USAGE: u16 = 0x06;
+const MULTI_AXIS_CONTROLLER_USAGE: u16 = 0x08;
+const USAGES: [u16; 5] = [
+    MOUSE_USAGE,
+    JOYSTICK_USAGE,
+    GAMEPAD_USAGE,
+    KEYBOARD_USAGE,
+    MULTI_AXIS_CONTROLLER_USAGE,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeviceKind {
+    Mouse,
+    Keyboard,
+    /// Anything else, like joysticks, throttles, and pedals
+    Hid,
+}
+
+/// An input device that’s plugged in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Device {
+    /// The Raw Input device name, which stays the same for as long as the device is plugged
+    /// into the same port
+    pub name: String,
+    pub kind: DeviceKind,
+}
+
+impl Device {
+    /// A short description of the device, with its USB vendor and product IDs if it has them.
+    pub fn label(&self) -> String {
+        let kind = match self.kind {
+            DeviceKind::Mouse => "Mouse",
+            DeviceKind::Keyboard => "Keyboard",
+            DeviceKind::Hid => "HID device",
+        };
+        match hardware_id(&self.name) {
+            Some((vendor, product)) => format!("{} {}:{}", kind, vendor, product),
+            None => kind.to_string(),
+        }
+    }
+}
+
+/// The mice, keyboards, and other HID devices that are plugged in right now.
+pub fn devices() -> Vec<Device> {
+    let entry_size = size_of::<RAWINPUTDEVICELIST>() as u32;
+    let mut count = 0;
+    unsafe { GetRawInputDeviceList(None, &mut count, entry_size) };
+
+    let mut list = vec![RAWINPUTDEVICELIST::default(); count as usize];
+    let found = unsafe { GetRawInputDeviceList(Some(list.as_mut_ptr()), &mut count, entry_size) };
+    if found == u32::MAX {
+        log::warn!("Failed to list the input devices");
+        return Vec::new();
+    }
+    list.truncate(found as usize);
+
+    list.iter()
+        .filter_map(|device| {
+            let kind = match device.dwType {
+                RIM_TYPEMOUSE => DeviceKind::Mouse,
+                RIM_TYPEKEYBOARD => DeviceKind::Keyboard,
+                RIM_TYPEHID => DeviceKind::Hid,
+                _ => return None,
+            };
+            Some(Device {
+                name: device_name(device.hDevice)?,
+                kind,
+            })
+        })
+        .collect()
+}
+
+fn device_name(device: HANDLE) -> Option<String> {
+    let mut len = 0;
+    unsafe { GetRawInputDeviceInfoW(device, RIDI_DEVICENAME, None, &mut len) };
+    if len == 0 {
+        return None;
+    }
+
+    let mut name = vec![0u16; len as usize];
+    let copied = unsafe {
+        GetRawInputDeviceInfoW(
+            device,
+            RIDI_DEVICENAME,
+            Some(name.as_mut_ptr() as *mut c_void),
+            &mut len,
+        )
+    };
+    if copied == 0 || copied == u32::MAX {
+        return None;
+    }
+
+    let len = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+    Some(String::from_utf16_lossy(&name[..len]))
+}
+
+/// The USB vendor and product IDs in a device name, like `\\?\HID#VID_046D&PID_C52B&MI_01#...`.
+pub fn hardware_id(name: &str) -> Option<(String, String)> {
+    let name = name.to_uppercase();
+    let id_after = |prefix: &str| {
+        let start = name.find(prefix)? + prefix.len();
+        let id = name.get(start..start + 4)?;
+        id.chars()
+            .all(|c| c.is_ascii_hexdigit())
+            .then(|| id.to_string())
+    };
+    Some((id_after("VID_")?, id_after("PID_")?))
+}
+
+/// Which devices the input came from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Activity {
+    #[default]
+    None,
+    /// Only from devices that are ignored
+    Ignored,
+    /// From at least one device that isn’t ignored
+    Allowed,
+}
+
+/// Tell input from ignored devices apart from everything else.
+///
+/// SDL reports keys and mouse movement without saying which device they came from. This listens
+/// for Raw Input on a hidden message-only window, which SDL pumps along with its own windows, and
+/// notes which devices were active since the last time it was asked.
+pub struct ActivityWatch {
+    hwnd: HWND,
+    state: *mut State,
+}
+
+struct State {
+    input: InputConfig,
+    // Whether each device that’s been active is ignored, by its handle
+    devices: HashMap<isize, bool>,
+    activity: Activity,
+}
+
+impl State {
+    fn note(&mut self, device: HANDLE) {
+        // Input injected with SendInput, like from remote desktop, doesn’t come from a device.
+        let is_ignored = device.0 != 0
+            && *self.devices.entry(device.0).or_insert_with(|| {
+                device_name(device).is_some_and(|name| self.input.is_ignored(&name))
+            });
+        let activity = match is_ignored {
+            true => Activity::Ignored,
+            false => Activity::Allowed,
+        };
+        self.activity = self.activity.max(activity);
+    }
+}
+
+impl ActivityWatch {
+    /// The SDL hints to set before SDL opens any controllers, so that it doesn’t take the Raw
+    /// Input for controllers away from the watch. SDL still reads them through XInput,
+    /// DirectInput, and HIDAPI.
+    pub const SDL_HINTS: [(&'static str, &'static str); 1] = [("SDL_JOYSTICK_RAWINPUT", "0")];
+
+    pub fn new(input: &InputConfig) -> Result<Self, String> {
+        let hwnd = unsafe {
+            CreateWindowExW(
+                WINDOW_EX_STYLE::default(),
+                w!("STATIC"),
+                w!("Flux input"),
+                WINDOW_STYLE::default(),
+                0,
+                0,
+                0,
+                0,
+                HWND_MESSAGE,
+                HMENU(0),
+                HINSTANCE(0),
+                None,
+            )
+        };
+        if hwnd.0 == 0 {
+            return Err("Failed to create a window to listen for input".to_string());
+        }
+
+        let state = Box::into_raw(Box::new(State {
+            input: input.clone(),
+            devices: HashMap::new(),
+            activity: Activity::None,
+        }));
+        unsafe {
+            if !SetWindowSubclass(hwnd, Some(subclass_proc), SUBCLASS_ID, state as usize).as_bool()
+            {
+                drop(Box::from_raw(state));
+                let _ = DestroyWindow(hwnd);
+                return Err("Failed to subclass the input window".to_string());
+            }
+        }
+        let watch = Self { hwnd, state };
+
+        // Listen even when the window isn’t in the foreground, since it never is. Only one window
+        // in the process gets the raw input for each usage, so SDL has to read controllers some
+        // other way; see `ActivityWatch::SDL_HINTS`.
+        let devices = USAGES.map(|usage| RAWINPUTDEVICE {
+            usUsagePage: GENERIC_DESKTOP_PAGE,
+            usUsage: usage,
+            dwFlags: RIDEV_INPUTSINK,
+            hwndTarget: hwnd,
+        });
+        unsafe { RegisterRawInputDevices(&devices, size_of::<RAWINPUTDEVICE>() as u32) }
+            .map_err(|err| format!("Failed to listen for raw input: {}", err))?;
+
+        Ok(watch)
+    }
+
+    /// Which devices were active since the last time this was asked.
+    pub fn take(&self) -> Activity {
+        let state = unsafe { &mut *self.state };
+        std::mem::take(&mut state.activity)
+    }
+}
+
+impl Drop for ActivityWatch {
+    fn drop(&mut self) {
+        let devices = USAGES.map(|usage| RAWINPUTDEVICE {
+            usUsagePage: GENERIC_DESKTOP_PAGE,
+            usUsage: usage,
+            dwFlags: RIDEV_REMOVE,
+            hwndTarget: HWND(0),
+        });
+        unsafe {
+            let _ = RegisterRawInputDevices(&devices, size_of::<RAWINPUTDEVICE>() as u32);
+            RemoveWindowSubclass(self.hwnd, Some(subclass_proc), SUBCLASS_ID);
+            let _ = DestroyWindow(self.hwnd);
+            drop(Box::from_raw(self.state));
+        }
+    }
+}
+
+unsafe extern "system" fn subclass_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    _subclass_id: usize,
+    state: usize,
+) -> LRESULT {
+    if msg == WM_INPUT {
+        let mut header = RAWINPUTHEADER::default();
+        let mut size = size_of::<RAWINPUTHEADER>() as u32;
+        let copied = GetRawInputData(
+            HRAWINPUT(lparam.0),
+            RID_HEADER,
+            Some(&mut header as *mut _ as *mut c_void),
+            &mut size,
+            size_of::<RAWINPUTHEADER>() as u32,
+        );
+        if copied != u32::MAX {
+            let state = &mut *(state as *mut State);
+            state.note(header.hDevice);
+        }
+    }
+
+    // Raw input has to go through the default procedure to be cleaned up.
+    DefSubclassProc(hwnd, msg, wparam, lparam)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_reads_the_hardware_id_from_the_device_name() {
+        assert_eq!(
+            hardware_id(r"\\?\HID#VID_046d&PID_C52B&MI_01&Col01#8&2ac5b1a3&0&0000"),
+            Some(("046D".to_string(), "C52B".to_string()))
+        );
+        assert_eq!(hardware_id(r"\\?\Root#RDP_MOU#0000#{378de44c}"), None);
+    }
+}
@@ -0,0 +1,195 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use windows::core::ComInterface;
+use windows::Graphics::Capture::{Direct3D11CaptureFramePool, GraphicsCaptureItem};
+use windows::Graphics::DirectX::Direct3D11::IDirect3DDevice;
+use windows::Graphics::DirectX::DirectXPixelFormat;
+use windows::Win32::Foundation::POINT;
+use windows::Win32::Graphics::Direct3D::D3D_DRIVER_TYPE_HARDWARE;
+use windows::Win32::Graphics::Direct3D11::{
+    D3D11CreateDevice, ID3D11Device, ID3D11DeviceContext, ID3D11Texture2D, D3D11_CPU_ACCESS_READ,
+    D3D11_CREATE_DEVICE_BGRA_SUPPORT, D3D11_MAPPED_SUBRESOURCE, D3D11_MAP_READ, D3D11_SDK_VERSION,
+    D3D11_TEXTURE2D_DESC, D3D11_USAGE_STAGING,
+};
+use windows::Win32::Graphics::Dxgi::IDXGIDevice;
+use windows::Win32::Graphics::Gdi::{MonitorFromPoint, MONITOR_DEFAULTTONEAREST};
+use windows::Win32::System::Com::{CoInitializeEx, CoUninitialize, COINIT_MULTITHREADED};
+use windows::Win32::System::WinRT::Direct3D11::{
+    CreateDirect3D11DeviceFromDXGIDevice, IDirect3DDxgiInterfaceAccess,
+};
+use windows::Win32::System::WinRT::Graphics::Capture::IGraphicsCaptureItemInterop;
+
+// How long to wait for the first frame before giving up
+const FRAME_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Take a screenshot of the monitor at the given point on the virtual screen with Windows Graphics
+/// Capture, shrunk down so that its longer side is at most `max_side` pixels.
+///
+/// This blocks until the frame comes in, and reads the whole frame back from the GPU, so call it
+/// off the render thread. The cursor is left out.
+///
+/// Needs Windows 11. Earlier versions can only capture with a yellow border drawn around the
+/// monitor, so this fails there instead.
+///
+/// Windows 10 always outlines the monitor in yellow while it’s being captured, so
+/// the capture fails there instead of flashing the outline every time.
+pub fn capture_monitor(point: (i32, i32), max_side: u32) -> Result<image::RgbImage, String> {
+    unsafe {
+        // Fails harmlessly if the thread is already set up for COM.
+        let com_initialized = CoInitializeEx(None, COINIT_MULTITHREADED).is_ok();
+        let captured = capture(point, max_side);
+        if com_initialized {
+            CoUninitialize();
+        }
+        captured
+    }
+}
+
+unsafe fn capture(point: (i32, i32), max_side: u32) -> Result<image::RgbImage, String> {
+    let monitor = MonitorFromPoint(
+        POINT {
+            x: point.0,
+            y: point.1,
+        },
+        MONITOR_DEFAULTTONEAREST,
+    );
+    let interop = windows::core::factory::<GraphicsCaptureItem, IGraphicsCaptureItemInterop>()
+        .map_err(|err| format!("Windows Graphics Capture isn’t available: {}", err))?;
+    let item: GraphicsCaptureItem = interop
+        .CreateForMonitor(monitor)
+        .map_err(|err| format!("Failed to capture the monitor: {}", err))?;
+
+    let mut p_device: Option<ID3D11Device> = None;
+    let mut p_context: Option<ID3D11DeviceContext> = None;
+    D3D11CreateDevice(
+        None,
+        D3D_DRIVER_TYPE_HARDWARE,
+        None,
+        D3D11_CREATE_DEVICE_BGRA_SUPPORT,
+        None,
+        D3D11_SDK_VERSION,
+        Some(&mut p_device),
+        None,
+        Some(&mut p_context),
+    )
+    .map_err(|err| format!("Failed to create a D3D11 device: {}", err))?;
+    let device = p_device.ok_or("Failed to create a D3D11 device")?;
+    let context = p_context.ok_or("Failed to create an immediate context")?;
+    let winrt_device: IDirect3DDevice = device
+        .cast::<IDXGIDevice>()
+        .and_then(|dxgi_device| CreateDirect3D11DeviceFromDXGIDevice(&dxgi_device))
+        .and_then(|inspectable| inspectable.cast())
+        .map_err(|err| format!("Failed to share the D3D11 device: {}", err))?;
+
+    let frame_pool = Direct3D11CaptureFramePool::CreateFreeThreaded(
+        &winrt_device,
+        DirectXPixelFormat::B8G8R8A8UIntNormalized,
+        1,
+        item.Size().map_err(|err| err.to_string())?,
+    )
+    .map_err(|err| format!("Failed to create the capture frame pool: {}", err))?;
+    let session = frame_pool
+        .CreateCaptureSession(&item)
+        .map_err(|err| format!("Failed to start capturing: {}", err))?;
+    // Only supported on newer versions of Windows
+    let _ = session.SetIsCursorCaptureEnabled(false);
+    session.SetIsBorderRequired(false).map_err(|err| {
+        format!(
+            "This version of Windows can’t hide the capture border: {}",
+            err
+        )
+    })?;
+    session
+        .StartCapture()
+        .map_err(|err| format!("Failed to start capturing: {}", err))?;
+
+    let started = Instant::now();
+    let frame = loop {
+        if let Ok(frame) = frame_pool.TryGetNextFrame() {
+            break Ok(frame);
+        }
+        if started.elapsed() >= FRAME_TIMEOUT {
+            break Err("Timed out waiting for a frame".to_string());
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    let texture = frame.and_then(|frame| {
+        frame
+            .Surface()
+            .and_then(|surface| surface.cast::<IDirect3DDxgiInterfaceAccess>())
+            .and_then(|access| access.GetInterface::<ID3D11Texture2D>())
+            .map_err(|err| format!("Failed to read the frame: {}", err))
+    });
+    let _ = session.Close();
+    let _ = frame_pool.Close();
+
+    read_back(&device, &context, &texture?, max_side)
+}
+
+// Copy the frame over to the CPU, and average it down in blocks.
+unsafe fn read_back(
+    device: &ID3D11Device,
+    context: &ID3D11DeviceContext,
+    texture: &ID3D11Texture2D,
+    max_side: u32,
+) -> Result<image::RgbImage, String> {
+    let mut desc = D3D11_TEXTURE2D_DESC::default();
+    texture.GetDesc(&mut desc);
+    let (width, height) = (desc.Width, desc.Height);
+    if width == 0 || height == 0 {
+        return Err("The frame is empty".to_string());
+    }
+
+    let staging_desc = D3D11_TEXTURE2D_DESC {
+        Usage: D3D11_USAGE_STAGING,
+        BindFlags: 0,
+        CPUAccessFlags: D3D11_CPU_ACCESS_READ.0 as u32,
+        MiscFlags: 0,
+        ..desc
+    };
+    let mut p_staging: Option<ID3D11Texture2D> = None;
+    device
+        .CreateTexture2D(&staging_desc, None, Some(&mut p_staging))
+        .map_err(|err| format!("Failed to create a staging texture: {}", err))?;
+    let staging = p_staging.ok_or("Failed to create a staging texture")?;
+    context.CopyResource(&staging, texture);
+
+    let mut mapped = D3D11_MAPPED_SUBRESOURCE::default();
+    context
+        .Map(&staging, 0, D3D11_MAP_READ, 0, Some(&mut mapped))
+        .map_err(|err| format!("Failed to read the frame back: {}", err))?;
+    let row_pitch = mapped.RowPitch as usize;
+    let data = std::slice::from_raw_parts(mapped.pData as *const u8, row_pitch * height as usize);
+
+    let scale = (width.max(height) as f64 / max_side.max(1) as f64).max(1.0);
+    let (out_width, out_height) = (
+        ((width as f64 / scale) as u32).max(1),
+        ((height as f64 / scale) as u32).max(1),
+    );
+    // A few pixels from each block are plenty for a palette.
+    let block = |out: u32, size: u32, out_size: u32| {
+        (out * size / out_size)..((out + 1) * size / out_size).max(out * size / out_size + 1)
+    };
+    let image = image::RgbImage::from_fn(out_width, out_height, |x, y| {
+        let (mut sum, mut count) = ([0u32; 3], 0u32);
+        let (xs, ys) = (block(x, width, out_width), block(y, height, out_height));
+        let step_x = (xs.len() / 4).max(1);
+        let step_y = (ys.len() / 4).max(1);
+        for py in ys.step_by(step_y) {
+            for px in xs.clone().step_by(step_x) {
+                let offset = py as usize * row_pitch + px as usize * 4;
+                // BGRA
+                sum[0] += u32::from(data[offset + 2]);
+                sum[1] += u32::from(data[offset + 1]);
+                sum[2] += u32::from(data[offset]);
+                count += 1;
+            }
+        }
+        let count = count.max(1);
+        image::Rgb(sum.map(|channel| (channel / count) as u8))
+    });
+    context.Unmap(&staging, 0);
+
+    Ok(image)
+}
@@ -0,0 +1,400 @@
+use std::ffi::c_void;
+use std::sync::{mpsc, Mutex, OnceLock};
+
+use windows::core::{w, PCWSTR, PWSTR};
+use windows::Win32::Foundation::{CloseHandle, FALSE, HANDLE, WAIT_OBJECT_0};
+use windows::Win32::Security::{
+    DuplicateTokenEx, SecurityIdentification, SetTokenInformation, TokenPrimary, TokenSessionId,
+    TOKEN_ADJUST_DEFAULT, TOKEN_ADJUST_SESSIONID, TOKEN_ALL_ACCESS, TOKEN_ASSIGN_PRIMARY,
+    TOKEN_DUPLICATE, TOKEN_QUERY,
+};
+use windows::Win32::System::Environment::{CreateEnvironmentBlock, DestroyEnvironmentBlock};
+use windows::Win32::System::RemoteDesktop::{
+    WTSActive, WTSEnumerateSessionsW, WTSFreeMemory, WTSGetActiveConsoleSessionId,
+    WTSQueryUserToken, WTS_CURRENT_SERVER_HANDLE, WTS_SESSION_INFOW,
+};
+use windows::Win32::System::Services::{
+    ChangeServiceConfig2W, CloseServiceHandle, ControlService, CreateServiceW, DeleteService,
+    OpenSCManagerW, OpenServiceW, RegisterServiceCtrlHandlerExW, SetServiceStatus,
+    StartServiceCtrlDispatcherW, StartServiceW, SC_HANDLE, SC_MANAGER_CONNECT,
+    SC_MANAGER_CREATE_SERVICE, SERVICE_ACCEPT_SESSIONCHANGE, SERVICE_ACCEPT_SHUTDOWN,
+    SERVICE_ACCEPT_STOP, SERVICE_ALL_ACCESS, SERVICE_AUTO_START, SERVICE_CONFIG_DESCRIPTION,
+    SERVICE_CONTROL_SESSIONCHANGE, SERVICE_CONTROL_SHUTDOWN, SERVICE_CONTROL_STOP,
+    SERVICE_DESCRIPTIONW, SERVICE_ERROR_NORMAL, SERVICE_QUERY_STATUS, SERVICE_RUNNING,
+    SERVICE_STATUS, SERVICE_STATUS_CURRENT_STATE, SERVICE_STATUS_HANDLE, SERVICE_STOP,
+    SERVICE_STOPPED, SERVICE_TABLE_ENTRYW, SERVICE_WIN32_OWN_PROCESS,
+};
+use windows::Win32::System::Threading::{
+    CreateProcessAsUserW, GetCurrentProcess, OpenProcessToken, TerminateProcess,
+    WaitForSingleObject, CREATE_UNICODE_ENVIRONMENT, PROCESS_INFORMATION, STARTUPINFOW,
+};
+
+const SERVICE_NAME: PCWSTR = w!("FluxKiosk");
+const SERVICE_DISPLAY_NAME: PCWSTR = w!("Flux Kiosk");
+const SERVICE_DESCRIPTION: &str = "Keeps Flux running in every session, for signage and kiosks.";
+// The standard right to delete an object, which isn’t one of the service rights
+const DELETE: u32 = 0x0001_0000;
+// What the control handler answers with once it’s passed the control on
+const NO_ERROR: u32 = 0;
+
+/// What Windows asks of the service while it runs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ServiceEvent {
+    Stop,
+    // Someone logged in or out, or a session connected or disconnected.
+    SessionChanged,
+}
+
+type ServiceMain = Box<dyn Fn(mpsc::Receiver<ServiceEvent>) -> Result<(), String> + Send + Sync>;
+
+static SERVICE_MAIN: OnceLock<ServiceMain> = OnceLock::new();
+static EVENTS: Mutex<Option<mpsc::Sender<ServiceEvent>>> = Mutex::new(None);
+
+/// Hand the process over to the service control manager, which calls back into `main` on a
+/// thread of its own. This returns once the service has stopped.
+pub fn run(
+    main: impl Fn(mpsc::Receiver<ServiceEvent>) -> Result<(), String> + Send + Sync + 'static,
+) -> Result<(), String> {
+    SERVICE_MAIN
+        .set(Box::new(main))
+        .map_err(|_| "The service is already running".to_string())?;
+
+    let table = [
+        SERVICE_TABLE_ENTRYW {
+            lpServiceName: PWSTR(SERVICE_NAME.as_ptr() as *mut _),
+            lpServiceProc: Some(service_main),
+        },
+        // The end of the table
+        SERVICE_TABLE_ENTRYW::default(),
+    ];
+    unsafe { StartServiceCtrlDispatcherW(table.as_ptr()) }.map_err(|err| {
+        format!(
+            "Failed to start the service. It’s only meant to be started by Windows: {}",
+            err
+        )
+    })
+}
+
+unsafe extern "system" fn service_main(_argc: u32, _argv: *mut PWSTR) {
+    let status_handle =
+        match RegisterServiceCtrlHandlerExW(SERVICE_NAME, Some(control_handler), None) {
+            Ok(status_handle) => status_handle,
+            Err(err) => {
+                log::error!("Failed to register the service: {}", err);
+                return;
+            }
+        };
+
+    let (sender, events) = mpsc::channel();
+    *EVENTS.lock().unwrap_or_else(|err| err.into_inner()) = Some(sender);
+
+    set_status(status_handle, SERVICE_RUNNING, 0);
+    let result = match SERVICE_MAIN.get() {
+        Some(main) => main(events),
+        None => Err("The service has nothing to run".to_string()),
+    };
+    if let Err(err) = &result {
+        log::error!("{}", err);
+    }
+
+    set_status(status_handle, SERVICE_STOPPED, result.map_or(1, |_| 0));
+}
+
+unsafe extern "system" fn control_handler(
+    control: u32,
+    _event_type: u32,
+    _event_data: *mut c_void,
+    _context: *mut c_void,
+) -> u32 {
+    let event = match control {
+        SERVICE_CONTROL_STOP | SERVICE_CONTROL_SHUTDOWN => ServiceEvent::Stop,
+        SERVICE_CONTROL_SESSIONCHANGE => ServiceEvent::SessionChanged,
+        _ => return NO_ERROR,
+    };
+    if let Some(sender) = EVENTS
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .as_ref()
+    {
+        let _ = sender.send(event);
+    }
+    NO_ERROR
+}
+
+unsafe fn set_status(
+    status_handle: SERVICE_STATUS_HANDLE,
+    state: SERVICE_STATUS_CURRENT_STATE,
+    exit_code: u32,
+) {
+    let controls_accepted = if state == SERVICE_RUNNING {
+        SERVICE_ACCEPT_STOP | SERVICE_ACCEPT_SHUTDOWN | SERVICE_ACCEPT_SESSIONCHANGE
+    } else {
+        0
+    };
+    let status = SERVICE_STATUS {
+        dwServiceType: SERVICE_WIN32_OWN_PROCESS,
+        dwCurrentState: state,
+        dwControlsAccepted: controls_accepted,
+        dwWin32ExitCode: exit_code,
+        dwServiceSpecificExitCode: 0,
+        dwCheckPoint: 0,
+        dwWaitHint: 0,
+    };
+    if let Err(err) = SetServiceStatus(status_handle, &status) {
+        log::warn!("Failed to report the service status: {}", err);
+    }
+}
+
+/// Register the service to start with Windows, running the given command line, and start it.
+pub fn install(command_line: &str) -> Result<(), String> {
+    let command_line = to_wide(command_line);
+    let mut description = to_wide(SERVICE_DESCRIPTION);
+
+    unsafe {
+        let manager = ServiceHandle(
+            OpenSCManagerW(PCWSTR::null(), PCWSTR::null(), SC_MANAGER_CREATE_SERVICE).map_err(
+                |err| {
+                    format!(
+                        "Failed to open the services. Run this as an administrator: {}",
+                        err
+                    )
+                },
+            )?,
+        );
+        let service = ServiceHandle(
+            CreateServiceW(
+                manager.0,
+                SERVICE_NAME,
+                SERVICE_DISPLAY_NAME,
+                SERVICE_ALL_ACCESS,
+                SERVICE_WIN32_OWN_PROCESS,
+                SERVICE_AUTO_START,
+                SERVICE_ERROR_NORMAL,
+                PCWSTR(command_line.as_ptr()),
+                PCWSTR::null(),
+                None,
+                PCWSTR::null(),
+                // Run as LocalSystem, which can start processes in other sessions
+                PCWSTR::null(),
+                PCWSTR::null(),
+            )
+            .map_err(|err| format!("Failed to install the service: {}", err))?,
+        );
+
+        let description = SERVICE_DESCRIPTIONW {
+            lpDescription: PWSTR(description.as_mut_ptr()),
+        };
+        if let Err(err) = ChangeServiceConfig2W(
+            service.0,
+            SERVICE_CONFIG_DESCRIPTION,
+            Some(&description as *const _ as *const c_void),
+        ) {
+            log::warn!("Failed to describe the service: {}", err);
+        }
+
+        StartServiceW(service.0, None)
+            .map_err(|err| format!("Installed the service, but failed to start it: {}", err))
+    }
+}
+
+/// Stop the service, if it’s running, and remove it.
+pub fn uninstall() -> Result<(), String> {
+    unsafe {
+        let manager = ServiceHandle(
+            OpenSCManagerW(PCWSTR::null(), PCWSTR::null(), SC_MANAGER_CONNECT).map_err(|err| {
+                format!(
+                    "Failed to open the services. Run this as an administrator: {}",
+                    err
+                )
+            })?,
+        );
+        let service = ServiceHandle(
+            OpenServiceW(
+                manager.0,
+                SERVICE_NAME,
+                SERVICE_STOP | SERVICE_QUERY_STATUS | DELETE,
+            )
+            .map_err(|err| format!("Failed to find the service: {}", err))?,
+        );
+
+        // Fails if it isn’t running, which is just as well.
+        let mut status = SERVICE_STATUS::default();
+        let _ = ControlService(service.0, SERVICE_CONTROL_STOP, &mut status);
+
+        DeleteService(service.0).map_err(|err| format!("Failed to remove the service: {}", err))
+    }
+}
+
+struct ServiceHandle(SC_HANDLE);
+
+impl Drop for ServiceHandle {
+    fn drop(&mut self) {
+        let _ = unsafe { CloseServiceHandle(self.0) };
+    }
+}
+
+/// The sessions that are showing on a screen, locally or over Remote Desktop, leaving out the
+/// session that the services run in.
+///
+/// The session on the physical console counts even before anyone logs in to it. It’s only
+/// connected then, not active, while it shows the login screen.
+pub fn active_sessions() -> Result<Vec<u32>, String> {
+    let mut sessions: *mut WTS_SESSION_INFOW = std::ptr::null_mut();
+    let mut count = 0;
+    unsafe {
+        WTSEnumerateSessionsW(WTS_CURRENT_SERVER_HANDLE, 0, 1, &mut sessions, &mut count)
+            .map_err(|err| format!("Failed to list the sessions: {}", err))?;
+        // 0xFFFFFFFF while the console is between sessions
+        let console_session = WTSGetActiveConsoleSessionId();
+        let ids = std::slice::from_raw_parts(sessions, count as usize)
+            .iter()
+            .filter(|session| {
+                session.SessionId != 0
+                    && (session.State == WTSActive || session.SessionId == console_session)
+            })
+            .map(|session| session.SessionId)
+            .collect();
+        WTSFreeMemory(sessions as *mut c_void);
+        Ok(ids)
+    }
+}
+
+/// Whether someone is logged in to the session, rather than it sitting on the login screen.
+pub fn has_user(session_id: u32) -> bool {
+    user_token(session_id).is_ok()
+}
+
+/// A process started by the service in another session.
+pub struct SessionProcess {
+    handle: Handle,
+    /// Whether it runs as the user logged in to the session, rather than on the login screen
+    pub as_user: bool,
+}
+
+impl SessionProcess {
+    pub fn has_exited(&self) -> bool {
+        unsafe { WaitForSingleObject(self.handle.0, 0) == WAIT_OBJECT_0 }
+    }
+
+    pub fn terminate(&self) {
+        let _ = unsafe { TerminateProcess(self.handle.0, 0) };
+    }
+}
+
+/// Start the command line in the session, as the user logged in to it. Without a user, it runs
+/// on the login screen instead, as the service itself, if `login_screen` allows it.
+pub fn launch_in_session(
+    session_id: u32,
+    command_line: &str,
+    login_screen: bool,
+) -> Result<SessionProcess, String> {
+    let (token, desktop, as_user) = match user_token(session_id) {
+        Ok(token) => (token, "winsta0\\default", true),
+        Err(_) if login_screen => (service_token_for(session_id)?, "winsta0\\Winlogon", false),
+        Err(err) => return Err(err),
+    };
+
+    let mut command_line = to_wide(command_line);
+    let mut desktop = to_wide(desktop);
+    let startup_info = STARTUPINFOW {
+        cb: std::mem::size_of::<STARTUPINFOW>() as u32,
+        lpDesktop: PWSTR(desktop.as_mut_ptr()),
+        ..Default::default()
+    };
+    let mut process_info = PROCESS_INFORMATION::default();
+
+    unsafe {
+        // The user’s own environment, with their profile and temp directories
+        let mut environment: *mut c_void = std::ptr::null_mut();
+        CreateEnvironmentBlock(&mut environment, token.0, FALSE)
+            .map_err(|err| format!("Failed to set up the environment for the session: {}", err))?;
+
+        let created = CreateProcessAsUserW(
+            token.0,
+            PCWSTR::null(),
+            PWSTR(command_line.as_mut_ptr()),
+            None,
+            None,
+            FALSE,
+            CREATE_UNICODE_ENVIRONMENT,
+            Some(environment),
+            PCWSTR::null(),
+            &startup_info,
+            &mut process_info,
+        );
+        let _ = DestroyEnvironmentBlock(environment);
+        created
+            .map_err(|err| format!("Failed to start Flux in session {}: {}", session_id, err))?;
+
+        let _ = CloseHandle(process_info.hThread);
+    }
+
+    Ok(SessionProcess {
+        handle: Handle(process_info.hProcess),
+        as_user,
+    })
+}
+
+fn user_token(session_id: u32) -> Result<Handle, String> {
+    let mut token = HANDLE::default();
+    unsafe { WTSQueryUserToken(session_id, &mut token) }
+        .map(|_| Handle(token))
+        .map_err(|err| format!("Nobody is logged in to session {}: {}", session_id, err))
+}
+
+// A copy of the service’s own token, moved over to the session
+fn service_token_for(session_id: u32) -> Result<Handle, String> {
+    unsafe {
+        let mut own_token = HANDLE::default();
+        OpenProcessToken(
+            GetCurrentProcess(),
+            TOKEN_DUPLICATE
+                | TOKEN_QUERY
+                | TOKEN_ASSIGN_PRIMARY
+                | TOKEN_ADJUST_DEFAULT
+                | TOKEN_ADJUST_SESSIONID,
+            &mut own_token,
+        )
+        .map_err(|err| format!("Failed to open the service’s token: {}", err))?;
+        let own_token = Handle(own_token);
+
+        let mut token = HANDLE::default();
+        DuplicateTokenEx(
+            own_token.0,
+            TOKEN_ALL_ACCESS,
+            None,
+            SecurityIdentification,
+            TokenPrimary,
+            &mut token,
+        )
+        .map_err(|err| format!("Failed to copy the service’s token: {}", err))?;
+        let token = Handle(token);
+
+        SetTokenInformation(
+            token.0,
+            TokenSessionId,
+            &session_id as *const u32 as *const c_void,
+            std::mem::size_of::<u32>() as u32,
+        )
+        .map_err(|err| {
+            format!(
+                "Failed to move the token to session {}: {}",
+                session_id, err
+            )
+        })?;
+
+        Ok(token)
+    }
+}
+
+struct Handle(HANDLE);
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        let _ = unsafe { CloseHandle(self.0) };
+    }
+}
+
+fn to_wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(std::iter::once(0)).collect()
+}
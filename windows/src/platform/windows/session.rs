@@ -0,0 +1,28 @@
+// Lock the workstation, the same way Win + L does.
+//
+// The call only requests the lock. It returns before the lock screen appears.
+pub fn lock_workstation() -> Result<(), String> {
+    use windows::Win32::System::Shutdown::LockWorkStation;
+
+    unsafe { LockWorkStation() }
+        .map_err(|err| format!("Failed to lock the workstation: {}", err))
+}
+
+// Whether Flux runs as a user who logged in interactively. The service starts it as itself on the
+// login screen, where anything it opens would run as SYSTEM in front of the user.
+pub fn is_interactive_user() -> bool {
+    use windows::Win32::Foundation::{BOOL, HANDLE};
+    use windows::Win32::Security::{
+        CheckTokenMembership, CreateWellKnownSid, WinInteractiveSid, PSID, SECURITY_MAX_SID_SIZE,
+    };
+
+    let mut sid_buffer = [0u8; SECURITY_MAX_SID_SIZE as usize];
+    let mut sid_size = sid_buffer.len() as u32;
+    let sid = PSID(sid_buffer.as_mut_ptr().cast());
+    let mut is_member = BOOL::default();
+    unsafe {
+        CreateWellKnownSid(WinInteractiveSid, PSID::default(), sid, &mut sid_size)
+            .and_then(|_| CheckTokenMembership(HANDLE::default(), sid, &mut is_member))
+            .map_or(false, |_| is_member.as_bool())
+    }
+}
@@ -0,0 +1,88 @@
+use raw_window_handle::RawWindowHandle;
+
+use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
+use windows::Win32::UI::Shell::{DefSubclassProc, RemoveWindowSubclass, SetWindowSubclass};
+use windows::Win32::UI::WindowsAndMessaging::{
+    KillTimer, SetTimer, WM_ENTERSIZEMOVE, WM_EXITSIZEMOVE, WM_TIMER,
+};
+
+const SUBCLASS_ID: usize = 1;
+const TIMER_ID: usize = 1;
+
+// Roughly one frame at 60 FPS. Timers aren’t precise, but vsync paces the frames anyway.
+const TIMER_INTERVAL_MS: u32 = 16;
+
+type Callback = Box<dyn FnMut()>;
+
+/// Keep rendering while a window is being dragged or resized.
+///
+/// Windows runs a modal loop of its own for as long as the user holds down the title bar or the
+/// window borders. SDL doesn’t return from polling events until the loop ends, so nothing else
+/// gets to render in the meantime. This subclasses the window to drive the given callback from a
+/// timer instead.
+///
+/// The subclass is removed when this is dropped, which must happen before the window is
+/// destroyed.
+pub struct SizeMoveRenderer {
+    hwnd: HWND,
+    callback: *mut Callback,
+}
+
+impl SizeMoveRenderer {
+    /// # Safety
+    ///
+    /// `handle` has to be a window that’s still open, and that outlives the renderer.
+    pub unsafe fn new(handle: &RawWindowHandle, callback: Callback) -> Result<Self, String> {
+        let hwnd = match handle {
+            RawWindowHandle::Win32(window_handle) => HWND(window_handle.hwnd as _),
+            _ => return Err("This platform is not supported yet".to_string()),
+        };
+
+        let callback = Box::into_raw(Box::new(callback));
+        if !SetWindowSubclass(hwnd, Some(subclass_proc), SUBCLASS_ID, callback as usize).as_bool() {
+            drop(Box::from_raw(callback));
+            return Err("Failed to subclass the window".to_string());
+        }
+
+        Ok(Self { hwnd, callback })
+    }
+}
+
+impl Drop for SizeMoveRenderer {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = KillTimer(self.hwnd, TIMER_ID);
+            RemoveWindowSubclass(self.hwnd, Some(subclass_proc), SUBCLASS_ID);
+            drop(Box::from_raw(self.callback));
+        }
+    }
+}
+
+unsafe extern "system" fn subclass_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    _subclass_id: usize,
+    callback: usize,
+) -> LRESULT {
+    match msg {
+        WM_ENTERSIZEMOVE => {
+            SetTimer(hwnd, TIMER_ID, TIMER_INTERVAL_MS, None);
+        }
+
+        WM_EXITSIZEMOVE => {
+            let _ = KillTimer(hwnd, TIMER_ID);
+        }
+
+        WM_TIMER if wparam.0 == TIMER_ID => {
+            let callback = &mut *(callback as *mut Callback);
+            callback();
+            return LRESULT(0);
+        }
+
+        _ => (),
+    }
+
+    DefSubclassProc(hwnd, msg, wparam, lparam)
+}
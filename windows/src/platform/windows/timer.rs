@@ -0,0 +1,29 @@
+use windows::Win32::Media::{timeBeginPeriod, timeEndPeriod};
+
+// In milliseconds
+const TIMER_RESOLUTION: u32 = 1;
+
+/// Raise the resolution of the system timer for as long as this is alive.
+///
+/// Windows only wakes sleeping threads every 15.6ms by default, which is too coarse to pace
+/// frames at 60 FPS or more.
+pub struct HighResolutionTimer(());
+
+impl HighResolutionTimer {
+    pub fn new() -> Self {
+        unsafe { timeBeginPeriod(TIMER_RESOLUTION) };
+        Self(())
+    }
+}
+
+impl Default for HighResolutionTimer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for HighResolutionTimer {
+    fn drop(&mut self) {
+        unsafe { timeEndPeriod(TIMER_RESOLUTION) };
+    }
+}
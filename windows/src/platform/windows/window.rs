@@ -1,6 +1,12 @@
 use raw_window_handle::RawWindowHandle;
 use windows::Win32::Foundation::HWND;
+use winit::dpi::{PhysicalPosition, PhysicalSize};
 
+/// Make the window a child of the parent window, like the preview in the screensaver settings.
+///
+/// # Safety
+///
+/// Both handles have to be windows that are still open.
 pub unsafe fn set_window_parent_win32(handle: HWND, parent_handle: HWND) -> bool {
     use windows::Win32::UI::WindowsAndMessaging::{
         GetWindowLongW, SetParent, SetWindowLongPtrA, GWL_STYLE, WINDOW_STYLE, WS_CHILD, WS_POPUP,
@@ -21,6 +27,46 @@ pub unsafe fn set_window_parent_win32(handle: HWND, parent_handle: HWND) -> bool
     true
 }
 
+/// The size of the window’s client area, if the window still exists.
+///
+/// # Safety
+///
+/// `handle` can be a window that’s gone away, but not one that was never a window.
+pub unsafe fn client_size(handle: HWND) -> Option<PhysicalSize<u32>> {
+    use windows::Win32::Foundation::RECT;
+    use windows::Win32::UI::WindowsAndMessaging::GetClientRect;
+
+    let mut rect = RECT::default();
+    GetClientRect(handle, &mut rect).ok()?;
+
+    Some(PhysicalSize::new(
+        (rect.right - rect.left).max(0) as u32,
+        (rect.bottom - rect.top).max(0) as u32,
+    ))
+}
+
+/// Show an error above everything else on the screen, and wait for it to be dismissed.
+pub fn show_error(title: &str, message: &str) {
+    use windows::core::HSTRING;
+    use windows::Win32::UI::WindowsAndMessaging::{
+        MessageBoxW, MB_ICONERROR, MB_OK, MB_SETFOREGROUND, MB_SYSTEMMODAL, MB_TOPMOST,
+    };
+
+    unsafe {
+        MessageBoxW(
+            HWND(0),
+            &HSTRING::from(message),
+            &HSTRING::from(title),
+            MB_OK | MB_ICONERROR | MB_TOPMOST | MB_SETFOREGROUND | MB_SYSTEMMODAL,
+        );
+    }
+}
+
+/// Let the window be see-through where it draws with alpha, for fading out to the desktop.
+///
+/// # Safety
+///
+/// `handle` has to be a window that’s still open.
 pub unsafe fn enable_transparency(handle: &RawWindowHandle) {
     use windows::Win32::Graphics::{
         Dwm::{DwmEnableBlurBehindWindow, DWM_BB_BLURREGION, DWM_BB_ENABLE, DWM_BLURBEHIND},
@@ -48,3 +94,205 @@ pub unsafe fn enable_transparency(handle: &RawWindowHandle) {
     }
     DeleteObject(region);
 }
+
+/// Only show the window over the given areas of the screen, for a window at `position`.
+///
+/// # Safety
+///
+/// `handle` has to be a window that’s still open.
+pub unsafe fn clip_to(
+    handle: &RawWindowHandle,
+    position: PhysicalPosition<i32>,
+    areas: &[(PhysicalPosition<i32>, PhysicalSize<u32>)],
+) -> Result<(), String> {
+    use windows::Win32::Graphics::Gdi::{
+        CombineRgn, CreateRectRgn, DeleteObject, SetWindowRgn, RGN_ERROR, RGN_OR,
+    };
+
+    let hwnd = match handle {
+        raw_window_handle::RawWindowHandle::Win32(event_window_handle) => {
+            HWND(event_window_handle.hwnd as _)
+        }
+        _ => return Err("This platform is not supported yet".to_string()),
+    };
+
+    let region = CreateRectRgn(0, 0, 0, 0);
+    for (area_position, size) in areas {
+        let left = area_position.x - position.x;
+        let top = area_position.y - position.y;
+        let area = CreateRectRgn(
+            left,
+            top,
+            left + size.width as i32,
+            top + size.height as i32,
+        );
+        let combined = CombineRgn(region, region, area, RGN_OR);
+        DeleteObject(area);
+        if combined == RGN_ERROR {
+            DeleteObject(region);
+            return Err("Failed to combine the window regions".to_string());
+        }
+    }
+
+    // The window owns the region from here on.
+    if SetWindowRgn(hwnd, region, true) == 0 {
+        DeleteObject(region);
+        return Err("Failed to clip the window to the monitors".to_string());
+    }
+
+    Ok(())
+}
+
+/// How many times a second the monitor that the window is on refreshes, if Windows knows.
+///
+/// # Safety
+///
+/// `handle` has to be a window that’s still open.
+pub unsafe fn refresh_rate(handle: &RawWindowHandle) -> Option<u32> {
+    use windows::core::PCWSTR;
+    use windows::Win32::Graphics::Gdi::{
+        EnumDisplaySettingsW, GetMonitorInfoW, MonitorFromWindow, DEVMODEW, ENUM_CURRENT_SETTINGS,
+        MONITORINFOEXW, MONITOR_DEFAULTTONEAREST,
+    };
+
+    let RawWindowHandle::Win32(handle) = handle else {
+        return None;
+    };
+
+    let monitor = MonitorFromWindow(HWND(handle.hwnd as _), MONITOR_DEFAULTTONEAREST);
+    let mut info = MONITORINFOEXW::default();
+    info.monitorInfo.cbSize = std::mem::size_of::<MONITORINFOEXW>() as u32;
+    GetMonitorInfoW(monitor, &mut info.monitorInfo).ok()?;
+
+    let mut mode = DEVMODEW {
+        dmSize: std::mem::size_of::<DEVMODEW>() as u16,
+        ..Default::default()
+    };
+    EnumDisplaySettingsW(
+        PCWSTR(info.szDevice.as_ptr()),
+        ENUM_CURRENT_SETTINGS,
+        &mut mode,
+    )
+    .ok()
+    .ok()?;
+
+    // 0 and 1 stand for the display’s default rate, whatever that is.
+    (mode.dmDisplayFrequency > 1).then_some(mode.dmDisplayFrequency)
+}
+
+/// Find the WorkerW window that sits between the desktop wallpaper and the desktop icons.
+///
+/// Explorer only creates this window after Progman receives the undocumented 0x052C message, which
+/// it also uses to animate wallpaper changes.
+///
+/// # Safety
+///
+/// This waits on Explorer to answer its window messages, so it mustn’t be called from a window
+/// procedure that Explorer could be waiting on in turn.
+pub unsafe fn find_desktop_worker() -> Option<HWND> {
+    use windows::core::{w, PCWSTR};
+    use windows::Win32::Foundation::{BOOL, LPARAM, WPARAM};
+    use windows::Win32::UI::WindowsAndMessaging::{
+        EnumWindows, FindWindowExW, FindWindowW, SendMessageTimeoutW, SMTO_NORMAL,
+    };
+
+    let progman = FindWindowW(w!("Progman"), PCWSTR::null());
+    if progman.0 == 0 {
+        return None;
+    }
+
+    SendMessageTimeoutW(
+        progman,
+        0x052C,
+        WPARAM(0xD),
+        LPARAM(0x1),
+        SMTO_NORMAL,
+        1000,
+        None,
+    );
+
+    unsafe extern "system" fn find_worker(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        let shell_view = FindWindowExW(hwnd, HWND(0), w!("SHELLDLL_DefView"), PCWSTR::null());
+        if shell_view.0 == 0 {
+            return true.into();
+        }
+
+        // The WorkerW we’re after is the next sibling of the window hosting the icons.
+        *(lparam.0 as *mut HWND) = FindWindowExW(HWND(0), hwnd, w!("WorkerW"), PCWSTR::null());
+        false.into()
+    }
+
+    let mut worker = HWND(0);
+    let _ = EnumWindows(Some(find_worker), LPARAM(&mut worker as *mut HWND as isize));
+    if worker.0 != 0 {
+        return Some(worker);
+    }
+
+    // Newer builds of Windows 11 place the WorkerW inside Progman instead.
+    let worker = FindWindowExW(progman, HWND(0), w!("WorkerW"), PCWSTR::null());
+    (worker.0 != 0).then_some(worker)
+}
+
+/// Attach a window to the desktop, behind the icons, while keeping its position on screen.
+///
+/// # Safety
+///
+/// `handle` has to be a window that’s still open, and the same goes as for
+/// [`find_desktop_worker`].
+pub unsafe fn attach_to_desktop(
+    handle: &RawWindowHandle,
+    position: PhysicalPosition<i32>,
+    size: PhysicalSize<u32>,
+) -> Result<(), String> {
+    use windows::Win32::Foundation::POINT;
+    use windows::Win32::Graphics::Gdi::ScreenToClient;
+    use windows::Win32::UI::WindowsAndMessaging::{SetWindowPos, SWP_NOACTIVATE, SWP_NOZORDER};
+
+    let hwnd = match handle {
+        RawWindowHandle::Win32(handle) => HWND(handle.hwnd as _),
+        _ => return Err("This platform is not supported yet".to_string()),
+    };
+
+    let worker = find_desktop_worker().ok_or("Can’t find the desktop window to attach to.")?;
+    set_window_parent_win32(hwnd, worker);
+
+    // Child windows are positioned relative to their parent, which spans the whole virtual
+    // screen.
+    let mut origin = POINT {
+        x: position.x,
+        y: position.y,
+    };
+    ScreenToClient(worker, &mut origin);
+
+    SetWindowPos(
+        hwnd,
+        HWND(0),
+        origin.x,
+        origin.y,
+        size.width as i32,
+        size.height as i32,
+        SWP_NOZORDER | SWP_NOACTIVATE,
+    )
+    .map_err(|err| format!("Failed to position the wallpaper window: {}", err))
+}
+
+/// Ask Explorer to repaint the desktop. Otherwise, the last frame we drew lingers until the
+/// wallpaper changes.
+///
+/// # Safety
+///
+/// The same goes as for [`find_desktop_worker`].
+pub unsafe fn repaint_desktop() {
+    use windows::Win32::Graphics::Gdi::{
+        RedrawWindow, HRGN, RDW_ALLCHILDREN, RDW_ERASE, RDW_INVALIDATE,
+    };
+
+    if let Some(worker) = find_desktop_worker() {
+        RedrawWindow(
+            worker,
+            None,
+            HRGN(0),
+            RDW_INVALIDATE | RDW_ERASE | RDW_ALLCHILDREN,
+        );
+    }
+}
@@ -0,0 +1,28 @@
+use std::sync::OnceLock;
+
+use windows::core::{s, w};
+use windows::Win32::System::LibraryLoader::{GetModuleHandleW, GetProcAddress};
+
+/// Detect whether we’re running under Wine or Proton.
+///
+/// Wine’s ntdll exports a few extra functions that the real one doesn’t, which is the check Wine
+/// itself recommends.
+pub fn is_wine() -> bool {
+    static IS_WINE: OnceLock<bool> = OnceLock::new();
+
+    *IS_WINE.get_or_init(|| unsafe {
+        GetModuleHandleW(w!("ntdll.dll"))
+            .map(|ntdll| GetProcAddress(ntdll, s!("wine_get_version")).is_some())
+            .unwrap_or(false)
+    })
+}
+
+/// Adjust SDL for Wine, before it’s initialized.
+#[cfg(feature = "sdl")]
+pub fn set_sdl_hints() {
+    // The host window manager, rather than Windows, decides when our windows lose focus.
+    sdl2::hint::set("SDL_VIDEO_MINIMIZE_ON_FOCUS_LOSS", "0");
+    // Wine reports the DPI of the X11 or Wayland display, which doesn’t always match the scaling
+    // of the windows it creates.
+    sdl2::hint::set("SDL_WINDOWS_DPI_SCALING", "0");
+}
@@ -0,0 +1,123 @@
+use crate::config::{Config, FluxSettings, Presets, Scene};
+
+use std::time::{Duration, Instant};
+
+/// Moves through the scenes in the playlist, one every interval.
+///
+/// The regular settings count as the first scene, since that’s what the instances start with, and
+/// the playlist loops back around to them after the last scene.
+pub struct Scheduler {
+    scenes: Vec<FluxSettings>,
+    interval: Duration,
+    crossfade: Duration,
+    current: usize,
+    switched_at: Instant,
+}
+
+impl Scheduler {
+    /// The scheduler for the playlist in the config, if there’s anything to rotate through.
+    pub fn new(config: &Config, now: Instant) -> Option<Self> {
+        let playlist = &config.playlist;
+        if playlist.scenes.is_empty() || playlist.interval_mins == 0 {
+            return None;
+        }
+
+        let presets = Presets::load(config);
+        let mut scenes = vec![config.flux.clone()];
+        scenes.extend(
+            playlist
+                .scenes
+                .iter()
+                .map(|scene| scene_settings(scene, &config.flux, &presets)),
+        );
+        log::info!("Playing a playlist of {} scenes", scenes.len());
+
+        Some(Self {
+            scenes,
+            interval: Duration::from_secs(playlist.interval_mins as u64 * 60),
+            crossfade: Duration::from_secs(playlist.crossfade_secs as u64),
+            current: 0,
+            switched_at: now,
+        })
+    }
+
+    /// How long to fade between two scenes.
+    pub fn crossfade(&self) -> Duration {
+        self.crossfade
+    }
+
+    /// Move on to the next scene, if the current one has played for long enough.
+    pub fn poll(&mut self, now: Instant) -> Option<&FluxSettings> {
+        if now.saturating_duration_since(self.switched_at) < self.interval {
+            return None;
+        }
+
+        self.current = (self.current + 1) % self.scenes.len();
+        self.switched_at = now;
+        Some(&self.scenes[self.current])
+    }
+}
+
+// The settings for a scene: the preset it names, or else the regular settings, with its tweaks to
+// the simulation on top.
+fn scene_settings(scene: &Scene, base: &FluxSettings, presets: &Presets) -> FluxSettings {
+    let mut flux = base.clone();
+    if let Some(name) = scene.preset.as_deref() {
+        match presets.find(name) {
+            Some(preset) => preset.apply(&mut flux),
+            None => log::warn!(
+                "The playlist refers to a preset called {}, which doesn’t exist",
+                name
+            ),
+        }
+    }
+    flux.simulation = scene.simulation.or(flux.simulation);
+    flux
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::{PlaylistConfig, SimulationSettings};
+
+    use ordered_float::OrderedFloat;
+
+    #[test]
+    fn it_rotates_through_the_scenes() {
+        let tweak = |scale| Scene {
+            preset: None,
+            simulation: SimulationSettings {
+                line_width: Some(OrderedFloat(scale)),
+                ..Default::default()
+            },
+        };
+        let mut config = Config::default();
+        config.playlist = PlaylistConfig {
+            scenes: vec![tweak(0.5), tweak(1.5)],
+            interval_mins: 1,
+            crossfade_secs: 5,
+        };
+
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut scheduler = Scheduler::new(&config, start).unwrap();
+        let line_width = |flux: Option<&FluxSettings>| flux.map(|flux| flux.simulation.line_width);
+
+        assert_eq!(line_width(scheduler.poll(at(30))), None);
+        assert_eq!(
+            line_width(scheduler.poll(at(60))),
+            Some(Some(OrderedFloat(0.5)))
+        );
+        assert_eq!(
+            line_width(scheduler.poll(at(120))),
+            Some(Some(OrderedFloat(1.5)))
+        );
+        // Back to the regular settings
+        assert_eq!(line_width(scheduler.poll(at(180))), Some(None));
+    }
+
+    #[test]
+    fn it_skips_empty_playlists() {
+        assert!(Scheduler::new(&Config::default(), Instant::now()).is_none());
+    }
+}
@@ -0,0 +1,247 @@
+use crate::config::{BatteryConfig, LiveWallpaperConfig};
+
+use std::time::{Duration, Instant};
+
+// The power source rarely changes, and each check is a system call.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+// How long the wallpaper takes to speed back up once the user stops, and the frame rate it
+// works up to before the limit comes off. Jumping straight from a crawl to full speed is jarring.
+const ACTIVITY_RAMP_DURATION: Duration = Duration::from_secs(2);
+const ACTIVITY_RAMP_FPS: u32 = 60;
+
+/// The power source of the machine.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PowerStatus {
+    pub on_battery: bool,
+    pub battery_saver: bool,
+    pub battery_percent: Option<u8>,
+}
+
+/// How much to hold back rendering to save power.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Throttle {
+    #[default]
+    None,
+    LimitFps(u32),
+    Pause,
+}
+
+impl Throttle {
+    pub fn from_status(config: &BatteryConfig, status: &PowerStatus) -> Self {
+        if !status.on_battery && !status.battery_saver {
+            return Throttle::None;
+        }
+
+        let is_low = status.on_battery
+            && status
+                .battery_percent
+                .zip(config.pause_below_percent)
+                .is_some_and(|(percent, threshold)| percent <= threshold);
+        if is_low {
+            return Throttle::Pause;
+        }
+
+        match config.max_fps {
+            Some(max_fps) if max_fps > 0 => Throttle::LimitFps(max_fps),
+            _ => Throttle::None,
+        }
+    }
+
+    /// Slow the wallpaper down while the user is typing or moving the mouse, given how long it’s
+    /// been since their last input.
+    pub fn from_activity(config: &LiveWallpaperConfig, idle_time: Duration) -> Self {
+        let Some(active_fps) = config.active_max_fps.filter(|&fps| fps > 0) else {
+            return Throttle::None;
+        };
+
+        let cooldown = Duration::from_secs(config.active_cooldown_secs as u64);
+        let Some(since_cooldown) = idle_time.checked_sub(cooldown) else {
+            return Throttle::LimitFps(active_fps);
+        };
+        if since_cooldown >= ACTIVITY_RAMP_DURATION || active_fps >= ACTIVITY_RAMP_FPS {
+            return Throttle::None;
+        }
+
+        let progress = since_cooldown.as_secs_f32() / ACTIVITY_RAMP_DURATION.as_secs_f32();
+        let fps = active_fps as f32 + (ACTIVITY_RAMP_FPS - active_fps) as f32 * progress;
+        Throttle::LimitFps(fps.round() as u32)
+    }
+
+    /// Whichever of the two holds back rendering more.
+    pub fn stricter(self, other: Self) -> Self {
+        match (self, other) {
+            (Throttle::Pause, _) | (_, Throttle::Pause) => Throttle::Pause,
+            // A limit of zero is no limit, so it mustn’t win over a real one.
+            (Throttle::LimitFps(0), throttle) | (throttle, Throttle::LimitFps(0)) => throttle,
+            (Throttle::LimitFps(a), Throttle::LimitFps(b)) => Throttle::LimitFps(a.min(b)),
+            (Throttle::None, throttle) | (throttle, Throttle::None) => throttle,
+        }
+    }
+
+    // The minimum time between frames imposed by this throttle.
+    pub fn frame_interval(&self) -> Option<Duration> {
+        match self {
+            // No frames at all would be a pause, so take a limit of zero to mean no limit.
+            Throttle::LimitFps(max_fps) if *max_fps > 0 => Some(Duration::from_secs(1) / *max_fps),
+            _ => None,
+        }
+    }
+}
+
+/// Keeps track of the power source, to decide how much to throttle rendering.
+pub struct Monitor {
+    config: BatteryConfig,
+    throttle: Throttle,
+    last_check: Option<Instant>,
+}
+
+impl Monitor {
+    pub fn new(config: BatteryConfig) -> Self {
+        Self {
+            config,
+            throttle: Throttle::None,
+            last_check: None,
+        }
+    }
+
+    /// Check the power source every so often, and return the throttle to apply.
+    pub fn poll(&mut self, now: Instant) -> Throttle {
+        let is_due = self.last_check.map_or(true, |last_check| {
+            now.duration_since(last_check) >= POLL_INTERVAL
+        });
+        if !is_due {
+            return self.throttle;
+        }
+        self.last_check = Some(now);
+
+        let throttle = status().map_or(Throttle::None, |status| {
+            Throttle::from_status(&self.config, &status)
+        });
+        if throttle != self.throttle {
+            log::info!(
+                "Power throttle changed from {:?} to {:?}",
+                self.throttle,
+                throttle
+            );
+            self.throttle = throttle;
+        }
+
+        self.throttle
+    }
+}
+
+// Check the power source, if the platform can tell.
+#[cfg(windows)]
+fn status() -> Option<PowerStatus> {
+    crate::platform::windows::power::status()
+        .map_err(|err| log::debug!("{}", err))
+        .ok()
+}
+
+// Assume the machine is plugged in elsewhere.
+#[cfg(not(windows))]
+fn status() -> Option<PowerStatus> {
+    Some(PowerStatus::default())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn on_battery(battery_percent: u8) -> PowerStatus {
+        PowerStatus {
+            on_battery: true,
+            battery_saver: false,
+            battery_percent: Some(battery_percent),
+        }
+    }
+
+    #[test]
+    fn it_throttles_on_battery() {
+        let config = BatteryConfig::default();
+
+        assert_eq!(
+            Throttle::from_status(&config, &PowerStatus::default()),
+            Throttle::None
+        );
+        assert_eq!(
+            Throttle::from_status(&config, &on_battery(80)),
+            Throttle::LimitFps(30)
+        );
+        assert_eq!(
+            Throttle::from_status(&config, &on_battery(10)),
+            Throttle::Pause
+        );
+    }
+
+    #[test]
+    fn it_throttles_in_battery_saver_mode_while_plugged_in() {
+        let config = BatteryConfig::default();
+        let status = PowerStatus {
+            on_battery: false,
+            battery_saver: true,
+            battery_percent: Some(5),
+        };
+
+        assert_eq!(
+            Throttle::from_status(&config, &status),
+            Throttle::LimitFps(30)
+        );
+    }
+
+    #[test]
+    fn it_can_be_turned_off() {
+        let config = BatteryConfig {
+            max_fps: None,
+            pause_below_percent: None,
+        };
+
+        assert_eq!(
+            Throttle::from_status(&config, &on_battery(5)),
+            Throttle::None
+        );
+    }
+
+    #[test]
+    fn it_takes_a_limit_of_zero_as_no_limit() {
+        assert_eq!(
+            Throttle::LimitFps(20).frame_interval(),
+            Some(Duration::from_millis(50))
+        );
+        assert_eq!(Throttle::LimitFps(0).frame_interval(), None);
+    }
+
+    #[test]
+    fn it_slows_down_while_the_user_is_active() {
+        let config = LiveWallpaperConfig {
+            active_max_fps: Some(10),
+            active_cooldown_secs: 3,
+            ..Default::default()
+        };
+        let throttle = |idle_ms| Throttle::from_activity(&config, Duration::from_millis(idle_ms));
+
+        assert_eq!(throttle(0), Throttle::LimitFps(10));
+        assert_eq!(throttle(2_999), Throttle::LimitFps(10));
+        // Halfway through speeding back up
+        assert_eq!(throttle(4_000), Throttle::LimitFps(35));
+        assert_eq!(throttle(5_000), Throttle::None);
+
+        let off = LiveWallpaperConfig::default();
+        assert_eq!(
+            Throttle::from_activity(&off, Duration::ZERO),
+            Throttle::None
+        );
+    }
+
+    #[test]
+    fn it_picks_the_stricter_throttle() {
+        use Throttle::{LimitFps, Pause};
+
+        assert_eq!(LimitFps(30).stricter(LimitFps(10)), LimitFps(10));
+        assert_eq!(Throttle::None.stricter(LimitFps(10)), LimitFps(10));
+        assert_eq!(LimitFps(0).stricter(LimitFps(10)), LimitFps(10));
+        assert_eq!(LimitFps(30).stricter(Pause), Pause);
+        assert_eq!(Throttle::None.stricter(Throttle::None), Throttle::None);
+    }
+}
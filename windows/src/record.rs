@@ -0,0 +1,107 @@
+use crate::config::Config;
+use crate::{gl_context, offscreen};
+
+use std::io::Write;
+use std::path::Path;
+use std::process;
+
+use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
+use winit::dpi::PhysicalSize;
+
+// Recordings are always made at 1080p and 60 FPS, whatever the monitors look like.
+const SIZE: PhysicalSize<u32> = PhysicalSize {
+    width: 1920,
+    height: 1080,
+};
+const FRAMES_PER_SECOND: u32 = 60;
+
+/// Render a clip of Flux offscreen and encode it to a video file.
+///
+/// Each frame advances the animation by exactly one frame’s worth of time, however long it takes
+/// to render and encode, so the clip plays back smoothly even on a slow machine. The frames are
+/// piped to `ffmpeg` as raw pixels, which has to be installed and on the `PATH`.
+pub fn run(config: &Config, seconds: u32, output: &Path) -> Result<(), String> {
+    let sdl_context = sdl2::init()?;
+    let video_subsystem = sdl_context.video()?;
+    let window = video_subsystem
+        .window("Flux recording", SIZE.width, SIZE.height)
+        .hidden()
+        .build()
+        .map_err(|err| err.to_string())?;
+
+    let gl_context = gl_context::new_gl_context(
+        window.raw_display_handle(),
+        SIZE,
+        window.raw_window_handle(),
+        None,
+        false,
+        false,
+    )?;
+    let gl = &gl_context.gl;
+    let target = unsafe { offscreen::Target::new(gl, SIZE)? };
+    let mut flux = crate::new_flux(gl, config, None, target.size(), 1.0)?;
+
+    let mut encoder = spawn_encoder(target.size(), output)?;
+    let mut stdin = encoder
+        .stdin
+        .take()
+        .ok_or("Failed to open a pipe to ffmpeg")?;
+
+    let frame_count = seconds * FRAMES_PER_SECOND;
+    let mut pixels = vec![0; target.frame_len()];
+    log::info!("Recording {} frames to {}", frame_count, output.display());
+
+    let res = (0..frame_count).try_for_each(|frame| {
+        let timestamp = frame as f64 * 1000.0 / FRAMES_PER_SECOND as f64;
+        flux.compute(timestamp);
+
+        unsafe {
+            target.bind(gl);
+            flux.render();
+            target.read(gl, &mut pixels);
+            target.unbind(gl);
+        }
+
+        stdin
+            .write_all(&pixels)
+            .map_err(|err| format!("Failed to send frame {} to ffmpeg: {}", frame, err))
+    });
+
+    // Closing the pipe tells ffmpeg that the clip is over.
+    drop(stdin);
+    unsafe { target.delete(gl) };
+
+    let status = encoder
+        .wait()
+        .map_err(|err| format!("Failed to wait for ffmpeg: {}", err))?;
+    res?;
+
+    if !status.success() {
+        return Err(format!("ffmpeg failed to encode the video: {}", status));
+    }
+
+    log::info!("Saved the recording to {}", output.display());
+    Ok(())
+}
+
+fn spawn_encoder(size: PhysicalSize<u32>, output: &Path) -> Result<process::Child, String> {
+    let mut command = process::Command::new("ffmpeg");
+    command
+        .args(["-hide_banner", "-loglevel", "error", "-y"])
+        .args(["-f", "rawvideo", "-pix_fmt", "rgba"])
+        .args(["-s", &format!("{}x{}", size.width, size.height)])
+        .args(["-r", &FRAMES_PER_SECOND.to_string()])
+        .args(["-i", "-"])
+        // Widely supported, so the clip plays back anywhere
+        .args(["-c:v", "libx264", "-pix_fmt", "yuv420p"])
+        .arg(output)
+        .stdin(process::Stdio::piped())
+        .stdout(process::Stdio::null());
+
+    command.spawn().map_err(|err| {
+        format!(
+            "Failed to start ffmpeg, which is needed to encode the video: {}",
+            err
+        )
+    })
+}
@@ -0,0 +1,1570 @@
+use crate::config::{self, Config};
+use crate::winit_compat::NonZeroU32PhysicalSize;
+use crate::{clock, compositor, gl_context, gpu, hud, offscreen, overlay};
+#[cfg(windows)]
+use crate::{mask, platform};
+
+use std::borrow::Cow;
+use std::path;
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use flux::Flux;
+use glow as GL;
+use glow::HasContext;
+use glutin::context::PossiblyCurrentGlContext;
+use glutin::prelude::GlSurface;
+use raw_window_handle::{RawDisplayHandle, RawWindowHandle};
+use winit::dpi::PhysicalSize;
+
+// How often to step the simulation, independently of the display’s refresh rate.
+const SIMULATION_RATE_HZ: f64 = 60.0;
+// The rate to step at instead, when asked to interpolate the frames in between to save work
+const INTERPOLATED_SIMULATION_RATE_HZ: f64 = 30.0;
+
+// How many times in a row to recreate a lost GL surface before giving up on it.
+const BAD_SURFACE_BUDGET: u32 = 3;
+
+// How often to answer window messages while waiting for the render thread
+const PUMP_INTERVAL: Duration = Duration::from_millis(5);
+
+// The longest a render thread waits at the start gate, in case the main thread never opens it
+const START_GATE_TIMEOUT: Duration = Duration::from_millis(100);
+
+// How many times to try setting up the renderer again after the GPU resets, and how long to give
+// the driver to come back between attempts.
+const RECOVERY_ATTEMPTS: u32 = 5;
+const RECOVERY_DELAY: Duration = Duration::from_secs(1);
+
+// The brightness of white in an scRGB swapchain, where 1.0 is 80 nits. Windows shows SDR content
+// at around 200 nits by default, so match that rather than look dim next to other windows.
+const SCRGB_WHITE_LEVEL: f32 = 2.5;
+
+// How long to fade over to the colors of a new wallpaper for.
+#[cfg(windows)]
+const WALLPAPER_CROSSFADE: Duration = Duration::from_secs(3);
+// How much smaller than the window to render, when falling back to a software renderer. The
+// frames are stretched to fit.
+const SOFTWARE_RENDER_SCALE: f64 = 0.5;
+
+// How long to ease the animation in for, when it first starts.
+const STARTUP_RAMP: Duration = Duration::from_secs(2);
+
+// How long to fade the lines back in for, after the display wakes up from standby.
+#[cfg(windows)]
+const WAKE_FADE: Duration = Duration::from_millis(750);
+
+// As many buffers as the GL surface rotates through. The DXGI swapchain reports its own.
+#[cfg(windows)]
+const GL_SURFACE_BUFFERS: usize = 2;
+
+/// The native handles of the window to render into.
+#[derive(Clone, Copy)]
+pub struct Handles {
+    pub display: RawDisplayHandle,
+    pub window: RawWindowHandle,
+    /// The window to pick the pixel format from, if it isn’t the one being rendered into
+    pub attr_window: Option<RawWindowHandle>,
+}
+
+// The raw handles are plain pointers, so they aren’t Send by themselves. They only point at the
+// window, which stays on the main thread. The render thread only passes them to the calls that
+// set up the GL context and the swapchain, which work from any thread as long as the main thread
+// keeps answering the window’s messages, and it does while it waits. Each instance declares its
+// render thread before its window, so the thread exits before the window is destroyed.
+unsafe impl Send for Handles {}
+
+#[derive(Clone)]
+pub struct Options {
+    pub size: PhysicalSize<u32>,
+    pub scale_factor: f64,
+    pub wallpaper: Option<path::PathBuf>,
+    /// Blend with the wallpaper at this opacity, or dim the desktop icons, through a compositor
+    pub compositor_opacity: Option<f32>,
+    /// How far the monitor is physically turned, which also goes through a compositor
+    pub rotation: config::Rotation,
+    /// The monitors across and down a video wall, which are each turned on their own
+    pub tiles: (u32, u32),
+    /// Whether the window can be resized from its own frame, in which case it presents through GL
+    pub resizable: bool,
+    /// The less dense monitors of a span, to shrink the frame into, which also goes through a
+    /// compositor
+    pub letterbox: Vec<compositor::Letterbox>,
+}
+
+/// What the render thread found out while starting up.
+pub struct Ready {
+    /// The name of the GPU
+    pub gpu: String,
+    /// The minimum time between frames, from the frame rate limit and any GPU workarounds
+    pub frame_interval: Option<Duration>,
+    /// How frames are presented, which falls back to GL if the DXGI swapchain isn’t available
+    pub swapchain: &'static str,
+    /// Whether frames can be held back with `DrawHeld`, to present them together with the other
+    /// monitors
+    #[cfg_attr(not(feature = "sdl"), allow(dead_code))]
+    pub can_hold_frames: bool,
+}
+
+// Without SDL, the winit host only draws, clears and resizes.
+#[cfg_attr(not(feature = "sdl"), allow(dead_code))]
+pub enum Command {
+    /// Advance the simulation and present a frame
+    Draw(f64),
+    /// Advance the simulation, but hold the frame back until `Present`, if the swapchain can
+    DrawHeld(f64),
+    /// Present the frame held back by `DrawHeld`
+    Present,
+    /// Present a black frame without advancing the simulation
+    Clear,
+    /// Resize the surface and the simulation, if the window has changed size
+    Resize {
+        size: PhysicalSize<u32>,
+        scale_factor: f64,
+    },
+    /// Restart the simulation, to pick up new settings
+    Restart {
+        config: Box<Config>,
+        scale_factor: f64,
+    },
+    /// Fade over to colors from a different wallpaper, behind the new wallpaper
+    #[cfg(windows)]
+    SetWallpaper {
+        config: Box<Config>,
+        wallpaper: path::PathBuf,
+        scale_factor: f64,
+    },
+    /// Fade from the current simulation to one with new settings
+    Crossfade {
+        config: Box<Config>,
+        duration: Duration,
+        scale_factor: f64,
+    },
+    /// Save the current frame as an image
+    Screenshot(path::PathBuf),
+    /// Dim the frames from here on, from 1.0 down to 0.0 for black, as the screensaver exits
+    FadeOut(f32),
+    /// Clear out whatever the swapchain held while the display was in standby, and fade back in
+    #[cfg(windows)]
+    Wake,
+    /// Dim parts of the surface, and calm the motion in them, if there’s a compositor to do so
+    #[cfg(windows)]
+    SetMask {
+        brightness: mask::Mask,
+        motion: Option<mask::Mask>,
+    },
+    /// Draw text over the frames from here on, or stop drawing it
+    SetHud(Option<String>),
+    /// Draw the stats over the frames from here on, along with how much video memory is left
+    SetStats(String),
+    /// Send back how much video memory is free, if the driver says
+    QueryGpuMemory(mpsc::Sender<Option<gpu::Memory>>),
+    /// Wait for the gate to open before carrying out the command
+    Gated(StartGate, Box<Command>),
+}
+
+/// Holds the render threads back until the main thread has handed out every frame that’s due,
+/// so that the monitors start their frames at the same moment, rather than one after another
+/// as the commands come in. The held frames wait at one too, for the vertical blank.
+#[cfg_attr(not(feature = "sdl"), allow(dead_code))]
+#[derive(Clone, Default)]
+pub struct StartGate(Arc<(Mutex<bool>, Condvar)>);
+
+#[cfg_attr(not(feature = "sdl"), allow(dead_code))]
+impl StartGate {
+    /// Let every render thread waiting at the gate through.
+    pub fn open(&self) {
+        let (is_open, opened) = &*self.0;
+        if let Ok(mut is_open) = is_open.lock() {
+            *is_open = true;
+        }
+        opened.notify_all();
+    }
+
+    fn wait(&self) {
+        let (is_open, opened) = &*self.0;
+        if let Ok(is_open) = is_open.lock() {
+            let _ = opened.wait_timeout_while(is_open, START_GATE_TIMEOUT, |is_open| !*is_open);
+        }
+    }
+}
+
+/// A thread that renders into a single window.
+///
+/// The GL context is current on one thread at a time, so each window gets a thread of its own to
+/// keep it current on. This lets the monitors render side by side: the main thread starts a frame
+/// on every render thread at once, and only then waits for them to finish.
+pub struct RenderThread {
+    commands: Option<mpsc::Sender<Command>>,
+    replies: mpsc::Receiver<Result<(), String>>,
+    // Whether a command has been sent without waiting for its reply yet
+    in_flight: bool,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl RenderThread {
+    /// Start rendering into the window, once the thread has set up its GL context and Flux.
+    pub fn spawn(
+        config: &Config,
+        handles: Handles,
+        options: Options,
+    ) -> Result<(Self, Ready), String> {
+        let (command_sender, commands) = mpsc::channel();
+        let (reply_sender, replies) = mpsc::channel();
+        let (ready_sender, ready) = mpsc::channel();
+        let config = config.clone();
+
+        let thread = thread::Builder::new()
+            .name("render".to_string())
+            .spawn(move || {
+                let mut renderer = match Renderer::new(&config, handles, options) {
+                    Ok((renderer, ready)) => {
+                        let _ = ready_sender.send(Ok(ready));
+                        renderer
+                    }
+                    Err(err) => {
+                        let _ = ready_sender.send(Err(err));
+                        return;
+                    }
+                };
+
+                for command in commands {
+                    let result = renderer.run(command);
+
+                    // The GPU has reset, leaving nothing to render with, so start over.
+                    if renderer.is_context_lost() {
+                        log::warn!("Lost the GPU. Setting up the renderer again.");
+                        let (config, options) = renderer.rebuild_options();
+                        #[cfg(windows)]
+                        let mask = renderer.mask.take();
+                        drop(renderer);
+                        renderer = match recover(&config, handles, options) {
+                            Ok(renderer) => renderer,
+                            Err(err) => {
+                                let _ = reply_sender.send(Err(err));
+                                return;
+                            }
+                        };
+                        #[cfg(windows)]
+                        if let Some((brightness, motion)) = mask {
+                            if let Err(err) = renderer.set_mask(brightness, motion) {
+                                log::warn!("Failed to set the mask again: {}", err);
+                            }
+                        }
+                    }
+
+                    if reply_sender.send(result).is_err() {
+                        break;
+                    }
+                }
+            })
+            .map_err(|err| format!("Failed to start the render thread: {}", err))?;
+
+        let ready = wait_pumping(&ready)
+            .unwrap_or_else(|| Err("The render thread exited while starting up".to_string()))?;
+
+        let render_thread = Self {
+            commands: Some(command_sender),
+            replies,
+            in_flight: false,
+            thread: Some(thread),
+        };
+        Ok((render_thread, ready))
+    }
+
+    /// Hand a command to the render thread, without waiting for it to be carried out.
+    pub fn send(&mut self, command: Command) {
+        if self.in_flight {
+            if let Err(err) = self.wait() {
+                log::error!("{}", err);
+            }
+        }
+
+        // If the thread has exited, that’s reported when waiting for the reply.
+        if let Some(commands) = self.commands.as_ref() {
+            let _ = commands.send(command);
+        }
+        self.in_flight = true;
+    }
+
+    /// Wait for the last command sent to be carried out.
+    pub fn wait(&mut self) -> Result<(), String> {
+        if !std::mem::take(&mut self.in_flight) {
+            return Ok(());
+        }
+
+        wait_pumping(&self.replies)
+            .unwrap_or_else(|| Err("The render thread has exited".to_string()))
+    }
+
+    /// Carry out a command, and wait for it to finish.
+    pub fn run(&mut self, command: Command) -> Result<(), String> {
+        self.send(command);
+        self.wait()
+    }
+}
+
+impl Drop for RenderThread {
+    fn drop(&mut self) {
+        // Closing the channel ends the thread, which then tears down its GL context.
+        drop(self.commands.take());
+        while wait_pumping(&self.replies).is_some() {}
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+// Wait for the render thread, while answering any messages sent to the windows in the meantime.
+// Setting up, presenting to and releasing the DXGI swapchain talks to the window, which lives on
+// the main thread, so blocking outright would deadlock.
+fn wait_pumping<T>(receiver: &mpsc::Receiver<T>) -> Option<T> {
+    loop {
+        match receiver.recv_timeout(PUMP_INTERVAL) {
+            Ok(value) => return Some(value),
+            Err(mpsc::RecvTimeoutError::Timeout) => pump_events(),
+            Err(mpsc::RecvTimeoutError::Disconnected) => return None,
+        }
+    }
+}
+
+#[cfg(feature = "sdl")]
+fn pump_events() {
+    unsafe { sdl2::sys::SDL_PumpEvents() }
+}
+
+// Without SDL, Flux is hosted in a winit event loop, which presents through GL. That never talks
+// to the window from the render thread.
+#[cfg(not(feature = "sdl"))]
+fn pump_events() {}
+
+enum Swapchain {
+    Gl,
+
+    #[cfg(windows)]
+    Dxgi(platform::windows::dxgi_swapchain::DXGIInterop),
+}
+
+impl Swapchain {
+    fn name(&self) -> &'static str {
+        match self {
+            Swapchain::Gl => "GL",
+            #[cfg(windows)]
+            Swapchain::Dxgi(_) => "DXGI",
+        }
+    }
+
+    // How many buffers the swapchain rotates through
+    #[cfg(windows)]
+    fn buffer_count(&self) -> usize {
+        match self {
+            Swapchain::Gl => GL_SURFACE_BUFFERS,
+            Swapchain::Dxgi(dxgi_interop) => dxgi_interop.buffer_count(),
+        }
+    }
+
+    // Only DXGI presents separately from rendering. GL swaps and waits on vsync in one go.
+    fn can_hold_frames(&self) -> bool {
+        match self {
+            Swapchain::Gl => false,
+            #[cfg(windows)]
+            Swapchain::Dxgi(_) => true,
+        }
+    }
+
+    // How the frames make it to the screen, for the stats
+    fn describe_presentation(&self) -> String {
+        let description = match self {
+            Swapchain::Gl => "GL buffer swap".to_string(),
+            #[cfg(windows)]
+            Swapchain::Dxgi(dxgi_interop) => dxgi_interop.describe_presentation(),
+        };
+
+        #[cfg(windows)]
+        if platform::windows::dxgi_swapchain::is_composition_enabled() == Some(false) {
+            return format!("{}, without DWM composition", description);
+        }
+
+        description
+    }
+
+    // Whether the swapchain takes colors in linear light, rather than in sRGB
+    fn is_linear(&self) -> bool {
+        match self {
+            Swapchain::Gl => false,
+            #[cfg(windows)]
+            Swapchain::Dxgi(dxgi_interop) => dxgi_interop.output_format().is_linear(),
+        }
+    }
+}
+
+// Everything that needs the GL context, which stays on the render thread.
+struct Renderer {
+    flux: Flux,
+    gl_context: gl_context::GLContext,
+    swapchain: Swapchain,
+    // Blends Flux with other content before presenting
+    compositor: Option<compositor::Compositor>,
+    // Decouples the simulation from the presentation rate
+    timestep: clock::FixedTimestep,
+    // How often each new simulation steps
+    simulation_rate: f64,
+    // How many times in a row the GL surface has been recreated without a frame getting through
+    bad_surface_errors: u32,
+    handles: Handles,
+    size: PhysicalSize<u32>,
+    scale_factor: f64,
+    rotation: config::Rotation,
+    tiles: (u32, u32),
+    // The wallpaper that the colors come from, if any
+    wallpaper: Option<path::PathBuf>,
+    // Kept to set everything up again if the GPU resets
+    config: Config,
+    compositor_opacity: Option<f32>,
+    resizable: bool,
+    // The brightness and motion masks last set, to set again if the renderer is set up again
+    #[cfg(windows)]
+    mask: Option<(mask::Mask, Option<mask::Mask>)>,
+    // Draws text over the frame, like the command palette, while there is some
+    hud: Option<hud::Hud>,
+    // The less dense monitors of a span, to letterbox again if the renderer is set up again
+    letterbox: Vec<compositor::Letterbox>,
+    // The next scene in the playlist, while it fades in
+    crossfade: Option<Crossfade>,
+    // Eases the animation in when it first starts
+    startup_ramp: clock::StartupRamp,
+    // Whether the lines are still fading in from black
+    is_fading_in: bool,
+    // How far the lines have faded out, once the screensaver is exiting
+    fade_out: Option<f32>,
+    // Brings the lines back in after the display wakes up
+    wake_fade: Option<clock::FadeIn>,
+    // Fades the animation in and out and blends scenes, if there’s no compositor already. It’s
+    // only kept around while it’s needed.
+    transition_compositor: Option<compositor::Compositor>,
+    // Draws the clock or text from the settings over the frame, if there is one
+    overlay: Option<overlay::Overlay>,
+    // Whether the last frame is waiting on `Command::Present`
+    held_frame: bool,
+    // Whether the GPU driver failed, and the frames are rendered in software instead
+    software: bool,
+}
+
+// A second simulation, fading in over the current one. It takes over once the fade is done.
+struct Crossfade {
+    flux: Flux,
+    timestep: clock::FixedTimestep,
+    config: Config,
+    scale_factor: f64,
+    duration: Duration,
+    // The timestamp of the first frame of the fade
+    started_at: Option<f64>,
+}
+
+impl Crossfade {
+    // How far along the fade is at this timestamp, from 0 to 1.
+    fn progress(&mut self, timestamp: f64) -> f32 {
+        let started_at = *self.started_at.get_or_insert(timestamp);
+        let duration = self.duration.as_secs_f64() * 1000.0;
+        if duration <= 0.0 {
+            return 1.0;
+        }
+
+        ((timestamp - started_at) / duration).clamp(0.0, 1.0) as f32
+    }
+}
+
+impl Renderer {
+    // If the GPU driver can’t create a context, or Flux fails to start on it, fall back to
+    // rendering in software, which is slow, but beats exiting with an error.
+    fn new(config: &Config, handles: Handles, options: Options) -> Result<(Self, Ready), String> {
+        Self::with_software(config, handles, options.clone(), false).or_else(|err| {
+            log::warn!("{}. Falling back to rendering in software.", err);
+            Self::with_software(config, handles, options, true).map_err(|software_err| {
+                format!(
+                    "{}. Rendering in software failed too: {}",
+                    err, software_err
+                )
+            })
+        })
+    }
+
+    fn with_software(
+        config: &Config,
+        handles: Handles,
+        options: Options,
+        software: bool,
+    ) -> Result<(Self, Ready), String> {
+        let (gl_context, gpu_workarounds) =
+            new_gl_context(config, handles, options.size, software)?;
+        let render_scale = if software { SOFTWARE_RENDER_SCALE } else { 1.0 };
+
+        let swapchain = if options.resizable {
+            // Windows that can be resized from their own frame stick to presenting through GL.
+            enable_vsync(&gl_context);
+            Swapchain::Gl
+        } else {
+            create_swapchain(
+                &handles.window,
+                &gl_context,
+                &gpu_workarounds,
+                config.platform.windows.high_dynamic_range,
+                config.platform.windows.max_frame_latency,
+                config.platform.windows.preferred_gpu.as_deref(),
+            )
+        };
+
+        let flux = crate::new_flux(
+            &gl_context.gl,
+            &flux_config(config, software),
+            options.wallpaper.as_deref(),
+            compositor::scale_size(
+                compositor::render_size(options.size, options.rotation, options.tiles),
+                render_scale,
+            ),
+            options.scale_factor,
+        )?;
+        let overlay = overlay::Overlay::new(
+            &gl_context.gl,
+            &config.flux.overlay,
+            options.scale_factor,
+            options.rotation,
+        );
+
+        // Turning the picture, letterboxing it, converting it to linear light, or stretching
+        // smaller frames to fit needs a compositor too, though there’s nothing to blend it with.
+        let is_rotated = options.rotation != config::Rotation::None;
+        let is_letterboxed = !options.letterbox.is_empty();
+        // On a display that refreshes faster than the simulation steps, or when asked to step
+        // less often to save work, the frames in between cross-fade towards the next step,
+        // rather than show the same one again. The compositor starts blending afresh whenever
+        // the window is resized.
+        let frame_interval = frame_interval(config, &gpu_workarounds);
+        let interpolate = config.platform.windows.interpolate_frames
+            || is_faster_than_steps(&handles.window, frame_interval);
+        let is_linear = swapchain.is_linear();
+        let compositor = options
+            .compositor_opacity
+            .or(
+                (is_rotated || is_letterboxed || is_linear || interpolate || software)
+                    .then_some(1.0),
+            )
+            .and_then(|opacity| {
+                let background = options.compositor_opacity.and(options.wallpaper.as_deref());
+                compositor::Compositor::new(
+                    &gl_context.gl,
+                    options.size,
+                    options.rotation,
+                    options.tiles,
+                    render_scale,
+                    opacity,
+                    background,
+                )
+                .map(|mut compositor| {
+                    compositor.set_linear_output(is_linear.then_some(SCRGB_WHITE_LEVEL));
+                    compositor.set_letterbox(&options.letterbox);
+                    if interpolate {
+                        match compositor.set_interpolation(true) {
+                            Ok(()) => log::debug!("Blending between simulation steps"),
+                            Err(err) => {
+                                log::warn!("Failed to set up frame interpolation: {}", err)
+                            }
+                        }
+                    }
+                    compositor
+                })
+                .map_err(|err| log::warn!("Failed to set up the compositor: {}", err))
+                .ok()
+            });
+
+        let simulation_rate = simulation_rate(config, compositor.as_ref());
+        let ready = Ready {
+            gpu: gpu::renderer(&gl_context.gl),
+            frame_interval,
+            swapchain: swapchain.name(),
+            can_hold_frames: swapchain.can_hold_frames(),
+        };
+        let renderer = Self {
+            flux,
+            gl_context,
+            swapchain,
+            compositor,
+            timestep: clock::FixedTimestep::new(simulation_rate),
+            simulation_rate,
+            bad_surface_errors: 0,
+            handles,
+            size: options.size,
+            scale_factor: options.scale_factor,
+            rotation: options.rotation,
+            tiles: options.tiles,
+            wallpaper: options.wallpaper,
+            config: config.clone(),
+            compositor_opacity: options.compositor_opacity,
+            resizable: options.resizable,
+            #[cfg(windows)]
+            mask: None,
+            hud: None,
+            letterbox: options.letterbox,
+            crossfade: None,
+            startup_ramp: clock::StartupRamp::new(STARTUP_RAMP),
+            is_fading_in: true,
+            fade_out: None,
+            wake_fade: None,
+            transition_compositor: None,
+            overlay,
+            held_frame: false,
+            software,
+        };
+
+        Ok((renderer, ready))
+    }
+
+    fn run(&mut self, command: Command) -> Result<(), String> {
+        match command {
+            Command::Draw(timestamp) => self.draw(timestamp, false).map_err(|err| err.to_string()),
+            Command::DrawHeld(timestamp) => {
+                self.draw(timestamp, true).map_err(|err| err.to_string())
+            }
+            Command::Present => {
+                self.present_held();
+                Ok(())
+            }
+            Command::Clear => self.clear().map_err(|err| err.to_string()),
+            Command::Resize { size, scale_factor } => self
+                .resize(size, scale_factor)
+                .map_err(|err| err.to_string()),
+            Command::Restart {
+                config,
+                scale_factor,
+            } => self.restart_flux(&config, scale_factor),
+            Command::Crossfade {
+                config,
+                duration,
+                scale_factor,
+            } => self.crossfade_to(&config, duration, scale_factor),
+            #[cfg(windows)]
+            Command::SetWallpaper {
+                config,
+                wallpaper,
+                scale_factor,
+            } => self.set_wallpaper(&config, wallpaper, scale_factor),
+            Command::Screenshot(path) => self.screenshot(&path),
+            Command::FadeOut(brightness) => {
+                self.fade_out = Some(brightness);
+                Ok(())
+            }
+            #[cfg(windows)]
+            Command::Wake => self.wake().map_err(|err| err.to_string()),
+            #[cfg(windows)]
+            Command::SetMask { brightness, motion } => self.set_mask(brightness, motion),
+            Command::SetHud(text) => self.set_hud(text.as_deref()),
+            Command::SetStats(stats) => self.set_stats(&stats),
+            Command::QueryGpuMemory(reply) => {
+                self.gl_context
+                    .context
+                    .make_current(&self.gl_context.surface)
+                    .map_err(|err| err.to_string())?;
+                let _ = reply.send(gpu::memory(&self.gl_context.gl));
+                Ok(())
+            }
+            Command::Gated(gate, command) => {
+                gate.wait();
+                self.run(*command)
+            }
+        }
+    }
+
+    // Whether the GPU has reset, taking the GL context or the D3D device with it.
+    fn is_context_lost(&self) -> bool {
+        let gl = &self.gl_context.gl;
+        // A robust context reports the reset directly. Otherwise, there’s only the error, and
+        // other errors might be queued up ahead of it.
+        let is_gl_context_lost = unsafe { gl.get_graphics_reset_status() } != GL::NO_ERROR
+            || (0..8)
+                .map(|_| unsafe { gl.get_error() })
+                .take_while(|error| *error != GL::NO_ERROR)
+                .any(|error| error == GL::CONTEXT_LOST);
+
+        match &self.swapchain {
+            Swapchain::Gl => is_gl_context_lost,
+            #[cfg(windows)]
+            Swapchain::Dxgi(dxgi_interop) => is_gl_context_lost || dxgi_interop.is_device_removed(),
+        }
+    }
+
+    // The settings to set the renderer up again with, as it is now.
+    fn rebuild_options(&self) -> (Config, Options) {
+        let options = Options {
+            size: self.size,
+            scale_factor: self.scale_factor,
+            wallpaper: self.wallpaper.clone(),
+            compositor_opacity: self.compositor_opacity,
+            rotation: self.rotation,
+            tiles: self.tiles,
+            resizable: self.resizable,
+            letterbox: self.letterbox.clone(),
+        };
+        (self.config.clone(), options)
+    }
+
+    fn draw(&mut self, timestamp: f64, hold: bool) -> glutin::error::Result<()> {
+        // A frame that was never released would be overwritten.
+        self.present_held();
+
+        // The simulation runs on the eased time from here on.
+        let simulated = self.startup_ramp.warp(timestamp);
+        let fade_in = self
+            .startup_ramp
+            .brightness(timestamp)
+            .filter(|_| self.is_fading_in);
+        let wake_fade = self
+            .wake_fade
+            .as_mut()
+            .and_then(|fade| fade.brightness(timestamp));
+        let brightness = [fade_in, wake_fade, self.fade_out]
+            .into_iter()
+            .flatten()
+            .reduce(|a, b| a * b);
+        if let Some(brightness) = brightness {
+            self.gl_context
+                .context
+                .make_current(&self.gl_context.surface)?;
+            match self.transition_compositor() {
+                Ok(compositor) => compositor.set_fade(brightness),
+                Err(err) => log::debug!("Failed to fade in: {}", err),
+            }
+        }
+        if let Some(overlay) = self.overlay.as_mut() {
+            overlay.set_fade(brightness.unwrap_or(1.0));
+            overlay.update(overlay::LocalTime::now());
+        }
+
+        let crossfade = self
+            .crossfade
+            .as_mut()
+            .map(|crossfade| crossfade.progress(simulated));
+        let result = self.draw_frame(simulated, crossfade, hold);
+
+        match &result {
+            Ok(()) => {
+                self.bad_surface_errors = 0;
+                if crossfade.is_some_and(|progress| progress >= 1.0) {
+                    self.finish_crossfade();
+                }
+                if self.is_fading_in && fade_in.is_none() {
+                    self.is_fading_in = false;
+                    self.finish_fade();
+                }
+                if self.wake_fade.is_some() && wake_fade.is_none() {
+                    self.wake_fade = None;
+                    self.finish_fade();
+                }
+            }
+
+            // Surfaces can go stale after hybrid sleep, and stay that way. Swap in a new one,
+            // rather than tear down the whole instance.
+            Err(err)
+                if err.error_kind() == glutin::error::ErrorKind::BadSurface
+                    && self.bad_surface_errors < BAD_SURFACE_BUDGET =>
+            {
+                self.bad_surface_errors += 1;
+                log::warn!("Lost the GL surface ({}). Recreating it.", err);
+                return self.recreate_surface();
+            }
+
+            Err(_) => (),
+        }
+
+        result
+    }
+
+    // Present the frame held back by `DrawHeld`, if there is one.
+    fn present_held(&mut self) {
+        if !std::mem::take(&mut self.held_frame) {
+            return;
+        }
+
+        #[cfg(windows)]
+        if let Swapchain::Dxgi(dxgi_interop) = &self.swapchain {
+            dxgi_interop.present_held();
+        }
+    }
+
+    fn draw_frame(
+        &mut self,
+        timestamp: f64,
+        crossfade: Option<f32>,
+        // Hold the frame back until `present_held`, if the swapchain can
+        hold: bool,
+    ) -> glutin::error::Result<()> {
+        let white_level = self.swapchain.is_linear().then_some(SCRGB_WHITE_LEVEL);
+
+        match self.swapchain {
+            Swapchain::Gl => {
+                self.gl_context
+                    .context
+                    .make_current(&self.gl_context.surface)?;
+
+                render_frame(
+                    &mut self.flux,
+                    &mut self.timestep,
+                    self.compositor
+                        .as_mut()
+                        .or(self.transition_compositor.as_mut()),
+                    self.crossfade.as_mut().zip(crossfade),
+                    &self.gl_context.gl,
+                    timestamp,
+                    None,
+                );
+                if let Some(overlay) = &self.overlay {
+                    overlay.draw(None, self.size, white_level);
+                }
+                if let Some(hud) = &self.hud {
+                    hud.draw(None, self.size, white_level);
+                }
+
+                self.gl_context
+                    .surface
+                    .swap_buffers(&self.gl_context.context)
+            }
+
+            #[cfg(windows)]
+            Swapchain::Dxgi(ref mut dxgi_interop) => unsafe {
+                let result = platform::windows::dxgi_swapchain::with_dxgi_swapchain(
+                    dxgi_interop,
+                    hold,
+                    |fbo| {
+                        self.gl_context
+                            .context
+                            .make_current(&self.gl_context.surface)?;
+
+                        render_frame(
+                            &mut self.flux,
+                            &mut self.timestep,
+                            self.compositor
+                                .as_mut()
+                                .or(self.transition_compositor.as_mut()),
+                            self.crossfade.as_mut().zip(crossfade),
+                            &self.gl_context.gl,
+                            timestamp,
+                            Some(*fbo),
+                        );
+                        if let Some(overlay) = &self.overlay {
+                            overlay.draw(Some(*fbo), self.size, white_level);
+                        }
+                        if let Some(hud) = &self.hud {
+                            hud.draw(Some(*fbo), self.size, white_level);
+                        }
+
+                        self.gl_context.gl.bind_framebuffer(GL::FRAMEBUFFER, None);
+                        self.gl_context.gl.finish();
+
+                        Ok(())
+                    },
+                );
+                // A frame that failed to render has nothing to present.
+                self.held_frame = hold && result.is_ok();
+                result
+            },
+        }
+    }
+
+    // Draw the text over the frames, or stop drawing it.
+    fn set_hud(&mut self, text: Option<&str>) -> Result<(), String> {
+        self.gl_context
+            .context
+            .make_current(&self.gl_context.surface)
+            .map_err(|err| err.to_string())?;
+
+        let Some(text) = text else {
+            self.hud = None;
+            return Ok(());
+        };
+        if self.hud.is_none() {
+            self.hud = Some(hud::Hud::new(&self.gl_context.gl, self.scale_factor)?);
+        }
+        if let Some(hud) = self.hud.as_mut() {
+            hud.set_text(text);
+        }
+
+        Ok(())
+    }
+
+    // Show the stats, along with how much video memory is left and how the frames are presented.
+    fn set_stats(&mut self, stats: &str) -> Result<(), String> {
+        self.gl_context
+            .context
+            .make_current(&self.gl_context.surface)
+            .map_err(|err| err.to_string())?;
+
+        let mut text = match gpu::memory(&self.gl_context.gl) {
+            Some(memory) => format!("{}\nGPU memory: {}", stats, memory),
+            None => stats.to_string(),
+        };
+        text.push_str("\nPresenting: ");
+        text.push_str(&self.swapchain.describe_presentation());
+        self.set_hud(Some(&text))
+    }
+
+    fn recreate_surface(&mut self) -> glutin::error::Result<()> {
+        self.gl_context
+            .recreate_surface(self.handles.window, self.size)?;
+
+        if let Swapchain::Gl = self.swapchain {
+            enable_vsync(&self.gl_context);
+        }
+
+        Ok(())
+    }
+
+    // How much smaller than the window Flux renders
+    fn render_scale(&self) -> f64 {
+        if self.software {
+            SOFTWARE_RENDER_SCALE
+        } else {
+            1.0
+        }
+    }
+
+    // The size that Flux renders at
+    fn flux_size(&self) -> PhysicalSize<u32> {
+        compositor::scale_size(
+            compositor::render_size(self.size, self.rotation, self.tiles),
+            self.render_scale(),
+        )
+    }
+
+    fn restart_flux(&mut self, config: &Config, scale_factor: f64) -> Result<(), String> {
+        self.gl_context
+            .context
+            .make_current(&self.gl_context.surface)
+            .map_err(|err| err.to_string())?;
+        self.cancel_crossfade();
+
+        self.flux = crate::new_flux(
+            &self.gl_context.gl,
+            &flux_config(config, self.software),
+            self.wallpaper.as_deref(),
+            self.flux_size(),
+            scale_factor,
+        )?;
+        self.timestep = clock::FixedTimestep::new(self.simulation_rate);
+        if let Some(compositor) = &mut self.compositor {
+            compositor.reset_steps();
+        }
+        self.config = config.clone();
+        self.scale_factor = scale_factor;
+        self.reset_overlay();
+
+        Ok(())
+    }
+
+    // Set the overlay up again for the current settings. The GL context has to be current.
+    fn reset_overlay(&mut self) {
+        self.overlay = overlay::Overlay::new(
+            &self.gl_context.gl,
+            &self.config.flux.overlay,
+            self.scale_factor,
+            self.rotation,
+        );
+    }
+
+    // Start fading in a new simulation, rendered alongside the current one until it takes over.
+    fn crossfade_to(
+        &mut self,
+        config: &Config,
+        duration: Duration,
+        scale_factor: f64,
+    ) -> Result<(), String> {
+        self.gl_context
+            .context
+            .make_current(&self.gl_context.surface)
+            .map_err(|err| err.to_string())?;
+
+        // Settle any fade that’s still going, so that there are never more than two scenes.
+        self.finish_crossfade();
+
+        let flux = crate::new_flux(
+            &self.gl_context.gl,
+            &flux_config(config, self.software),
+            self.wallpaper.as_deref(),
+            self.flux_size(),
+            scale_factor,
+        )?;
+        self.transition_compositor()?.set_crossfade(true)?;
+
+        self.crossfade = Some(Crossfade {
+            flux,
+            timestep: clock::FixedTimestep::new(self.simulation_rate),
+            config: config.clone(),
+            scale_factor,
+            duration,
+            started_at: None,
+        });
+
+        Ok(())
+    }
+
+    // Carry on with the scene that faded in. The GL context has to be current.
+    fn finish_crossfade(&mut self) {
+        let Some(crossfade) = self.crossfade.take() else {
+            return;
+        };
+
+        self.flux = crossfade.flux;
+        self.timestep = crossfade.timestep;
+        self.config = crossfade.config;
+        self.scale_factor = crossfade.scale_factor;
+        self.reset_overlay();
+        self.release_crossfade_target();
+        // The next frame is a step of its own, rather than blended from the scene that faded out.
+        if let Some(compositor) = &mut self.compositor {
+            compositor.reset_steps();
+        }
+    }
+
+    // Drop the scene that’s fading in, staying on the current one. The GL context has to be
+    // current.
+    fn cancel_crossfade(&mut self) {
+        if self.crossfade.take().is_some() {
+            self.release_crossfade_target();
+        }
+    }
+
+    fn release_crossfade_target(&mut self) {
+        if let Some(compositor) = self
+            .compositor
+            .as_mut()
+            .or(self.transition_compositor.as_mut())
+        {
+            // Releasing the texture doesn’t fail.
+            let _ = compositor.set_crossfade(false);
+        }
+        self.release_transition_compositor();
+    }
+
+    // Stop dimming the lines once they’ve faded back in, apart from any fade out that’s still
+    // going.
+    fn finish_fade(&mut self) {
+        if let Some(compositor) = self
+            .compositor
+            .as_mut()
+            .or(self.transition_compositor.as_mut())
+        {
+            compositor.set_fade(self.fade_out.unwrap_or(1.0));
+        }
+        self.release_transition_compositor();
+    }
+
+    // The compositor to fade through, setting one up if the frames don’t go through one
+    // already. The GL context has to be current.
+    fn transition_compositor(&mut self) -> Result<&mut compositor::Compositor, String> {
+        if self.compositor.is_none() && self.transition_compositor.is_none() {
+            let mut compositor = compositor::Compositor::new(
+                &self.gl_context.gl,
+                self.size,
+                self.rotation,
+                self.tiles,
+                self.render_scale(),
+                1.0,
+                None,
+            )?;
+            compositor.set_linear_output(self.swapchain.is_linear().then_some(SCRGB_WHITE_LEVEL));
+            self.transition_compositor = Some(compositor);
+        }
+
+        Ok(self
+            .compositor
+            .as_mut()
+            .or(self.transition_compositor.as_mut())
+            .expect("a compositor was just set up"))
+    }
+
+    // Stop drawing through the transition compositor, once nothing is fading anymore.
+    fn release_transition_compositor(&mut self) {
+        if self.crossfade.is_none()
+            && !self.is_fading_in
+            && self.wake_fade.is_none()
+            && self.fade_out.is_none()
+        {
+            self.transition_compositor = None;
+        }
+    }
+
+    // Render the simulation as it is into an offscreen target, and save it. It comes out the way
+    // Flux draws it, before any rotation or blending with the wallpaper.
+    fn screenshot(&mut self, path: &path::Path) -> Result<(), String> {
+        self.gl_context
+            .context
+            .make_current(&self.gl_context.surface)
+            .map_err(|err| err.to_string())?;
+
+        let gl = &self.gl_context.gl;
+        let target = unsafe { offscreen::Target::new(gl, self.flux_size())? };
+        let mut pixels = vec![0; target.frame_len()];
+        unsafe {
+            target.bind(gl);
+            self.flux.render();
+            target.read(gl, &mut pixels);
+            target.unbind(gl);
+            target.delete(gl);
+        }
+
+        let size = target.size();
+        let image = image::RgbaImage::from_raw(size.width, size.height, pixels)
+            .ok_or("The screenshot doesn’t match the size of the frame")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        image
+            .save(path)
+            .map_err(|err| format!("Failed to save {}: {}", path.display(), err))
+    }
+
+    #[cfg(windows)]
+    fn set_wallpaper(
+        &mut self,
+        config: &Config,
+        wallpaper: path::PathBuf,
+        scale_factor: f64,
+    ) -> Result<(), String> {
+        self.wallpaper = Some(wallpaper);
+        self.gl_context
+            .context
+            .make_current(&self.gl_context.surface)
+            .map_err(|err| err.to_string())?;
+
+        // Swap the backdrop in place, and fade the running simulation over to the new colors,
+        // rather than start the scene over from scratch.
+        if let Some(compositor) = self.compositor.as_mut() {
+            compositor.set_background(self.wallpaper.as_deref());
+        }
+
+        self.crossfade_to(config, WALLPAPER_CROSSFADE, scale_factor)
+    }
+
+    // The swapchain buffers can come back from standby holding garbage, which shows until each of
+    // them is drawn to again. Present black through all of them, then fade the lines back in.
+    #[cfg(windows)]
+    fn wake(&mut self) -> glutin::error::Result<()> {
+        for _ in 0..self.swapchain.buffer_count() {
+            self.clear()?;
+        }
+        self.wake_fade = Some(clock::FadeIn::new(WAKE_FADE));
+
+        Ok(())
+    }
+
+    fn resize(&mut self, size: PhysicalSize<u32>, scale_factor: f64) -> glutin::error::Result<()> {
+        let surface = &self.gl_context.surface;
+        if surface.width() == Some(size.width) && surface.height() == Some(size.height) {
+            return Ok(());
+        }
+
+        let Some((width, height)) = size.non_zero() else {
+            return Ok(());
+        };
+
+        self.gl_context.context.make_current(surface)?;
+        // Jump to the scene fading in, rather than resize both, and skip the rest of the fade in,
+        // rather than resize the compositor for it.
+        self.finish_crossfade();
+        self.is_fading_in = false;
+        self.wake_fade = None;
+        self.finish_fade();
+        let surface = &self.gl_context.surface;
+        surface.resize(&self.gl_context.context, width, height);
+        #[cfg(windows)]
+        if let Swapchain::Dxgi(dxgi_interop) = &mut self.swapchain {
+            if let Err(err) =
+                unsafe { dxgi_interop.resize(&self.gl_context.gl, width.get(), height.get()) }
+            {
+                log::warn!("Failed to resize the swapchain: {}", err);
+            }
+        }
+        let is_rescaled = self.scale_factor != scale_factor;
+        self.size = size;
+        self.scale_factor = scale_factor;
+        // The text is sized in pixels at 100% scaling.
+        if is_rescaled {
+            self.reset_overlay();
+        }
+
+        let render_size = self.flux_size();
+        let logical_size = render_size.to_logical(scale_factor);
+        self.flux.resize(
+            logical_size.width,
+            logical_size.height,
+            render_size.width,
+            render_size.height,
+        );
+        if let Some(compositor) = self.compositor.as_mut() {
+            if let Err(err) = compositor.resize(size) {
+                log::warn!("Failed to resize the compositor: {}", err);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[cfg(windows)]
+    fn set_mask(
+        &mut self,
+        brightness: mask::Mask,
+        motion: Option<mask::Mask>,
+    ) -> Result<(), String> {
+        let result = match self.compositor.as_mut() {
+            Some(compositor) => self
+                .gl_context
+                .context
+                .make_current(&self.gl_context.surface)
+                .map_err(|err| err.to_string())
+                .and_then(|_| {
+                    compositor.set_mask(Some(&brightness));
+                    compositor.set_motion_mask(motion.as_ref())
+                }),
+            None => Ok(()),
+        };
+        self.mask = Some((brightness, motion));
+
+        result
+    }
+
+    fn clear(&mut self) -> glutin::error::Result<()> {
+        // The held frame would cover up the cleared one.
+        self.held_frame = false;
+        let gl = &self.gl_context.gl;
+
+        match self.swapchain {
+            Swapchain::Gl => {
+                self.gl_context
+                    .context
+                    .make_current(&self.gl_context.surface)?;
+
+                unsafe {
+                    gl.clear_color(0.0, 0.0, 0.0, 1.0);
+                    gl.clear(GL::COLOR_BUFFER_BIT);
+                }
+
+                self.gl_context
+                    .surface
+                    .swap_buffers(&self.gl_context.context)
+            }
+
+            #[cfg(windows)]
+            Swapchain::Dxgi(ref mut dxgi_interop) => unsafe {
+                platform::windows::dxgi_swapchain::with_dxgi_swapchain(dxgi_interop, false, |fbo| {
+                    self.gl_context
+                        .context
+                        .make_current(&self.gl_context.surface)?;
+
+                    gl.bind_framebuffer(GL::FRAMEBUFFER, Some(*fbo));
+                    gl.clear_color(0.0, 0.0, 0.0, 1.0);
+                    gl.clear(GL::COLOR_BUFFER_BIT);
+                    gl.bind_framebuffer(GL::FRAMEBUFFER, None);
+                    gl.finish();
+
+                    Ok(())
+                })
+            },
+        }
+    }
+}
+
+// Set up the renderer again after the GPU has reset, giving the driver a moment to come back.
+fn recover(config: &Config, handles: Handles, options: Options) -> Result<Renderer, String> {
+    let mut attempt = 1;
+    loop {
+        thread::sleep(RECOVERY_DELAY);
+
+        match Renderer::new(config, handles, options.clone()) {
+            Ok((renderer, _)) => {
+                log::info!("Recovered from losing the GPU");
+                return Ok(renderer);
+            }
+            Err(err) if attempt < RECOVERY_ATTEMPTS => {
+                log::warn!("Failed to set up the renderer again: {}", err);
+                attempt += 1;
+            }
+            Err(err) => {
+                return Err(format!(
+                    "Failed to recover from losing the GPU after {} attempts: {}",
+                    RECOVERY_ATTEMPTS, err
+                ))
+            }
+        }
+    }
+}
+
+// Advance the simulation and render a frame into the target framebuffer.
+fn render_frame(
+    flux: &mut Flux,
+    timestep: &mut clock::FixedTimestep,
+    compositor: Option<&mut compositor::Compositor>,
+    // The scene fading in, and how far along it is
+    crossfade: Option<(&mut Crossfade, f32)>,
+    gl: &glow::Context,
+    timestamp: f64,
+    target: Option<GL::NativeFramebuffer>,
+) {
+    let ticks = timestep.ticks(timestamp);
+    let has_ticked = !ticks.is_empty();
+    // Frames presented between ticks redraw the last simulated state, or blend towards it.
+    for tick in ticks {
+        flux.compute(tick);
+    }
+
+    let incoming = crossfade.map(|(crossfade, progress)| {
+        for tick in crossfade.timestep.ticks(timestamp) {
+            crossfade.flux.compute(tick);
+        }
+        (&mut crossfade.flux, progress)
+    });
+
+    let Some(compositor) = compositor else {
+        unsafe { gl.bind_framebuffer(GL::FRAMEBUFFER, target) };
+        flux.render();
+        return;
+    };
+
+    if !compositor.is_interpolating() {
+        compositor.bind();
+        flux.render();
+    } else {
+        // Only render the steps themselves, which the frames in between blend towards.
+        if has_ticked || !compositor.has_step() {
+            compositor.next_step();
+            compositor.bind();
+            flux.render();
+        }
+        compositor.set_step_progress(timestep.progress(timestamp));
+    }
+
+    match incoming {
+        Some((incoming, progress)) => {
+            compositor.bind_incoming();
+            incoming.render();
+            compositor.draw(target, Some(progress));
+        }
+        None => compositor.draw(target, None),
+    }
+}
+
+// The rate to step the simulation at, which is lower when asked to interpolate to save work,
+// and the compositor managed to set that up.
+fn simulation_rate(config: &Config, compositor: Option<&compositor::Compositor>) -> f64 {
+    if config.platform.windows.interpolate_frames
+        && compositor.is_some_and(compositor::Compositor::is_interpolating)
+    {
+        INTERPOLATED_SIMULATION_RATE_HZ
+    } else {
+        SIMULATION_RATE_HZ
+    }
+}
+
+// Whether the display refreshes faster than the simulation steps, even with the frame rate
+// capped.
+#[cfg_attr(not(windows), allow(unused_variables))]
+fn is_faster_than_steps(
+    raw_window_handle: &RawWindowHandle,
+    frame_interval: Option<Duration>,
+) -> bool {
+    let is_capped =
+        frame_interval.is_some_and(|interval| interval.as_secs_f64() >= 1.0 / SIMULATION_RATE_HZ);
+
+    #[cfg(windows)]
+    if let Some(refresh_rate) =
+        unsafe { platform::windows::window::refresh_rate(raw_window_handle) }
+    {
+        return refresh_rate as f64 > SIMULATION_RATE_HZ && !is_capped;
+    }
+
+    false
+}
+
+// Create a GL context, in software if asked to, and look up any workarounds needed for its GPU.
+fn new_gl_context(
+    config: &Config,
+    handles: Handles,
+    inner_size: PhysicalSize<u32>,
+    software: bool,
+) -> Result<(gl_context::GLContext, config::GpuWorkarounds), String> {
+    let new_context = |force_gles| {
+        gl_context::new_gl_context(
+            handles.display,
+            inner_size,
+            handles.window,
+            handles.attr_window,
+            force_gles,
+            software,
+        )
+    };
+
+    let gl_context = new_context(false)?;
+    let renderer = gpu::renderer(&gl_context.gl);
+    let gpu_workarounds = gpu::workarounds(&renderer, &config.platform.windows.gpu_workarounds)
+        .merge(config::GpuWorkarounds {
+            // The DXGI interop extension isn’t available through Wine’s OpenGL.
+            #[cfg(windows)]
+            disable_dxgi_interop: platform::windows::wine::is_wine(),
+            ..Default::default()
+        });
+
+    if gpu_workarounds != Default::default() {
+        log::info!(
+            "Applying workarounds for {}: {:?}",
+            renderer,
+            gpu_workarounds
+        );
+    }
+
+    // There’s no way to know the renderer before creating a context, so start over with GLES.
+    if gpu_workarounds.force_gles && !gl_context.gl.version().is_embedded {
+        drop(gl_context);
+        return Ok((new_context(true)?, gpu_workarounds));
+    }
+
+    Ok((gl_context, gpu_workarounds))
+}
+
+// Go easy on a software renderer, on top of rendering smaller frames.
+fn flux_config(config: &Config, software: bool) -> Cow<'_, Config> {
+    if !software {
+        return Cow::Borrowed(config);
+    }
+
+    let mut config = config.clone();
+    config.flux.quality = config::Quality::Low;
+    Cow::Owned(config)
+}
+
+// The minimum time between frames, from the frame rate limit and any GPU workarounds.
+fn frame_interval(config: &Config, gpu_workarounds: &config::GpuWorkarounds) -> Option<Duration> {
+    gpu_workarounds
+        .merge(config::GpuWorkarounds {
+            // No frames at all would be a pause, so take a limit of zero to mean no limit.
+            max_fps: config.flux.max_fps.filter(|max_fps| *max_fps > 0),
+            ..Default::default()
+        })
+        .max_fps
+        .filter(|max_fps| *max_fps > 0)
+        .map(|max_fps| Duration::from_secs(1) / max_fps)
+}
+
+fn enable_vsync(gl_context: &gl_context::GLContext) {
+    use glutin::surface::SwapInterval;
+    use std::num::NonZeroU32;
+
+    if let Err(res) = gl_context.surface.set_swap_interval(
+        &gl_context.context,
+        SwapInterval::Wait(NonZeroU32::new(1).unwrap()),
+    ) {
+        log::error!("Failed to set vsync: {res:?}");
+    }
+}
+
+#[cfg(not(windows))]
+fn create_swapchain(
+    _raw_window_handle: &RawWindowHandle,
+    gl_context: &gl_context::GLContext,
+    _gpu_workarounds: &config::GpuWorkarounds,
+    _high_dynamic_range: bool,
+    _max_frame_latency: Option<u32>,
+    _preferred_gpu: Option<&str>,
+) -> Swapchain {
+    enable_vsync(gl_context);
+
+    Swapchain::Gl
+}
+
+#[cfg(windows)]
+fn create_swapchain(
+    raw_window_handle: &RawWindowHandle,
+    gl_context: &gl_context::GLContext,
+    gpu_workarounds: &config::GpuWorkarounds,
+    high_dynamic_range: bool,
+    max_frame_latency: Option<u32>,
+    preferred_gpu: Option<&str>,
+) -> Swapchain {
+    if gpu_workarounds.disable_dxgi_interop {
+        log::debug!("DXGI swapchain disabled for this GPU");
+        enable_vsync(gl_context);
+        return Swapchain::Gl;
+    }
+
+    let dxgi_interop = platform::windows::dxgi_swapchain::create_dxgi_swapchain(
+        raw_window_handle,
+        &gl_context.gl,
+        high_dynamic_range,
+        max_frame_latency,
+        preferred_gpu,
+    );
+
+    match dxgi_interop {
+        Ok(dxgi_interop) => Swapchain::Dxgi(dxgi_interop),
+        Err(err) => {
+            log::warn!(
+                "Failed to create DXGI swapchain: {}. Falling back to GL.",
+                err
+            );
+
+            enable_vsync(gl_context);
+
+            Swapchain::Gl
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn it_holds_the_render_threads_until_the_gate_opens() {
+        let gate = StartGate::default();
+        let (sender, passed) = mpsc::channel();
+        let threads = (0..3)
+            .map(|_| {
+                let (gate, sender) = (gate.clone(), sender.clone());
+                thread::spawn(move || {
+                    gate.wait();
+                    let _ = sender.send(Instant::now());
+                })
+            })
+            .collect::<Vec<_>>();
+
+        thread::sleep(START_GATE_TIMEOUT / 4);
+        assert!(passed.try_recv().is_err());
+
+        let opened = Instant::now();
+        gate.open();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        for passed_at in passed.try_iter() {
+            assert!(passed_at >= opened);
+        }
+    }
+
+    #[test]
+    fn it_lets_the_render_threads_through_a_gate_that_never_opens() {
+        let gate = StartGate::default();
+        let started = Instant::now();
+        gate.wait();
+
+        assert!(started.elapsed() >= START_GATE_TIMEOUT);
+    }
+
+    #[test]
+    fn it_lets_the_render_threads_straight_through_an_open_gate() {
+        let gate = StartGate::default();
+        gate.open();
+        let started = Instant::now();
+        gate.wait();
+
+        assert!(started.elapsed() < START_GATE_TIMEOUT);
+    }
+}
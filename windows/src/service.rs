@@ -0,0 +1,140 @@
+use crate::cli::ServiceOptions;
+use crate::platform::windows::service::{self, ServiceEvent, SessionProcess};
+
+use std::collections::HashMap;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+// How often to look for new sessions, and check on the instances
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+// How long to wait before starting an instance again, so that one that crashes on start isn’t
+// retried in a tight loop
+const RESTART_DELAY: Duration = Duration::from_secs(5);
+
+/// Keep Flux going in every session, for signage and kiosks that nobody logs in to by hand.
+///
+/// Windows starts the service at boot, as LocalSystem, and the service starts an instance in
+/// each session as the user logged in to it. Instances that exit are started again after a short
+/// delay, which turns the screensaver into an attract loop that comes back after each
+/// interaction. With `login_screen`, it also runs on the login screen of sessions that nobody is
+/// logged in to, and switches over once someone logs in.
+pub fn run(options: ServiceOptions) -> Result<(), String> {
+    service::run(move |events| supervise(&options, events))
+}
+
+/// Register the service with Windows, passing the options along, and start it.
+pub fn install(options: &ServiceOptions) -> Result<(), String> {
+    let exe = std::env::current_exe()
+        .map_err(|err| format!("Failed to find the screensaver executable: {}", err))?;
+    let mut command_line = format!("\"{}\" --service", exe.display());
+    for arg in options.to_args() {
+        command_line.push(' ');
+        command_line.push_str(arg);
+    }
+
+    service::install(&command_line)?;
+    println!("Installed and started the Flux Kiosk service");
+    Ok(())
+}
+
+pub fn uninstall() -> Result<(), String> {
+    service::uninstall()?;
+    println!("Removed the Flux Kiosk service");
+    Ok(())
+}
+
+struct Supervised {
+    process: Option<SessionProcess>,
+    last_started: Option<Instant>,
+}
+
+fn supervise(options: &ServiceOptions, events: mpsc::Receiver<ServiceEvent>) -> Result<(), String> {
+    let exe = std::env::current_exe()
+        .map_err(|err| format!("Failed to find the screensaver executable: {}", err))?;
+    let command_line = format!(
+        "\"{}\" {}",
+        exe.display(),
+        if options.screensaver {
+            "/s"
+        } else {
+            "--wallpaper"
+        }
+    );
+    log::info!("Keeping “{}” going in every session", command_line);
+
+    let mut sessions: HashMap<u32, Supervised> = HashMap::new();
+
+    loop {
+        match events.recv_timeout(POLL_INTERVAL) {
+            Ok(ServiceEvent::Stop) | Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            Ok(ServiceEvent::SessionChanged) | Err(mpsc::RecvTimeoutError::Timeout) => (),
+        }
+
+        let active_sessions = match service::active_sessions() {
+            Ok(active_sessions) => active_sessions,
+            Err(err) => {
+                log::warn!("{}", err);
+                continue;
+            }
+        };
+
+        // Stop the instances in sessions that ended or disconnected.
+        sessions.retain(|session_id, supervised| {
+            let is_active = active_sessions.contains(session_id);
+            if !is_active {
+                log::info!("Session {} is no longer active", session_id);
+                if let Some(process) = &supervised.process {
+                    process.terminate();
+                }
+            }
+            is_active
+        });
+
+        let now = Instant::now();
+        for session_id in active_sessions {
+            let supervised = sessions.entry(session_id).or_insert(Supervised {
+                process: None,
+                last_started: None,
+            });
+
+            if let Some(process) = &supervised.process {
+                if process.has_exited() {
+                    log::info!("Flux exited in session {}", session_id);
+                    supervised.process = None;
+                } else if !process.as_user && service::has_user(session_id) {
+                    // Make way for the user’s own instance, once someone logs in.
+                    process.terminate();
+                    supervised.process = None;
+                } else {
+                    continue;
+                }
+            }
+
+            if supervised
+                .last_started
+                .map_or(false, |last_started| now - last_started < RESTART_DELAY)
+            {
+                continue;
+            }
+
+            supervised.last_started = Some(now);
+            match service::launch_in_session(session_id, &command_line, options.login_screen) {
+                Ok(process) => {
+                    log::info!("Started Flux in session {}", session_id);
+                    supervised.process = Some(process);
+                }
+                // Expected for sessions sitting on the login screen
+                Err(err) => log::debug!("{}", err),
+            }
+        }
+    }
+
+    log::info!("Stopping the service");
+    for supervised in sessions.values() {
+        if let Some(process) = &supervised.process {
+            process.terminate();
+        }
+    }
+
+    Ok(())
+}
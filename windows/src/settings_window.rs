@@ -1,24 +1,44 @@
-use crate::config::{ColorMode, Config, FillMode};
+mod gradient_editor;
+mod image_check;
+mod monitor_diagram;
+mod overlay_controls;
+mod preview;
+mod simulation_controls;
+
+use crate::config::{
+    Color, ColorMode, Config, FillMode, MaskedRegion, MonitorRef, OverlaySettings, Preset, Presets,
+    Quality, SimulationSettings,
+};
+use crate::gradient;
+use crate::shuffle;
+use crate::winit_compat::MonitorHandle;
 
 use async_std::task;
-use indoc::indoc;
+use std::fmt;
 use std::path::PathBuf;
 use tinyfiledialogs::open_file_dialog;
 
 use iced::alignment::{Alignment, Horizontal};
 use iced::executor;
 use iced::theme;
-use iced::widget::{button, column, container, pick_list, row, text, vertical_space};
-use iced::window;
-use iced::{Application, Command, Element, Length, Theme};
+use iced::widget::{
+    button, canvas, checkbox, column, container, image, pick_list, row, text, text_input,
+    vertical_space,
+};
+use iced::{clipboard, window};
+use iced::{Application, Command, Element, Length, Subscription, Theme};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+// The frame rate for the live wallpaper while the user is typing or moving the mouse
+const ACTIVE_WALLPAPER_FPS: u32 = 10;
+
 pub fn run(config: Config) -> iced::Result {
-    Config::run(iced::Settings {
-        flags: config,
+    let (preview, monitors) = preview::Preview::start(&config.flux);
+    SettingsWindow::run(iced::Settings {
+        flags: (config, monitors, preview),
         window: iced::window::Settings {
-            size: (420, 600),
+            size: (420, 1160),
             resizable: false,
             decorations: true,
             ..Default::default()
@@ -28,24 +48,264 @@ pub fn run(config: Config) -> iced::Result {
     })
 }
 
+// The frame rate limits to pick from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameRateLimit(Option<u32>);
+
+impl FrameRateLimit {
+    const ALL: [FrameRateLimit; 4] = [
+        FrameRateLimit(None),
+        FrameRateLimit(Some(30)),
+        FrameRateLimit(Some(60)),
+        FrameRateLimit(Some(120)),
+    ];
+}
+
+impl fmt::Display for FrameRateLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            None => write!(f, "Unlimited"),
+            Some(max_fps) => write!(f, "{} FPS", max_fps),
+        }
+    }
+}
+
+// The GPUs to pick from, by name. None leaves it up to Windows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GpuChoice(Option<String>);
+
+impl fmt::Display for GpuChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            None => write!(f, "Let Windows decide"),
+            Some(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tab {
+    Settings,
+    Simulation,
+    Presets,
+}
+
 #[derive(Debug, Clone)]
 pub enum Message {
+    SetTab(Tab),
     SetColorMode(ColorMode),
     OpenFilePicker,
     SetImageFile(Option<String>),
+    ImageChecked(PathBuf, image_check::Checked),
+    SpotlightChecked(bool),
+    SelectMonitor(Option<u32>),
+    OpenMonitorImagePicker,
+    SetMonitorImage(Option<PathBuf>),
+    FileDropped(PathBuf),
+    AddGradientStop,
+    RemoveGradientStop(usize),
+    SwapGradientStops(usize, usize),
+    SetGradientStopPosition(usize, f32),
+    SetGradientStopColor(usize, String),
+    SetSimulation(SimulationSettings),
+    Shuffle,
+    SetPresetName(String),
+    SavePreset,
+    SetPresetRename(usize, String),
+    RenamePreset(usize),
+    UsePreset(usize),
+    DuplicatePreset(usize),
+    DeletePreset(usize),
+    CopyShareCode(usize),
+    SetShareCode(String),
+    ImportShareCode,
+    SetQuality(Quality),
+    SetFrameRateLimit(FrameRateLimit),
+    SetOverlay(OverlaySettings),
+    SetPreferredGpu(GpuChoice),
     SetFillMode(FillMode),
+    SpanMonitors { from: u32, to: u32 },
+    UnspanMonitor(u32),
+    SetLaunchAtLogin(bool),
+    SetSlowDownWhenActive(bool),
+    AddMaskedRegion(MaskedRegion),
+    RemoveMaskedRegion(usize),
+    SetLockOnExit(bool),
+    SetControllers(bool),
+    SetDeviceIgnored(String, bool),
+    SetPing(bool),
+    PreviewFrame(image::Handle),
     Save,
     Cancel,
 }
 
-impl Application for Config {
+struct SettingsWindow {
+    config: Config,
+    // The monitors to draw in the fill mode diagram
+    monitors: Vec<MonitorHandle>,
+    // The monitor to assign an image to, selected in the diagram
+    selected_monitor: Option<u32>,
+    preview: preview::Preview,
+    // The latest frame from the preview
+    preview_frame: Option<image::Handle>,
+    // The colors of the custom gradient as they’re being typed in
+    gradient_inputs: Vec<String>,
+    tab: Tab,
+    presets: Presets,
+    // The name to save the current look under
+    preset_name: String,
+    // The names of the presets as they’re being edited
+    preset_renames: Vec<String>,
+    // A share code being pasted in, and why it couldn’t be imported
+    share_code: String,
+    share_code_error: Option<String>,
+    // The selected image, which is checked in the background
+    image_thumbnail: Option<image::Handle>,
+    // What might not work well with the selected image
+    image_warning: Option<String>,
+    // Whether Windows Spotlight has an image to take the colors from, checked in the background
+    // when it’s picked
+    spotlight_available: bool,
+    // The mice, keyboards, and other HID devices that are plugged in, by their device name, with a
+    // label to show
+    input_devices: Vec<(String, String)>,
+    // The GPUs in the computer, after the choice to let Windows decide
+    gpu_choices: Vec<GpuChoice>,
+}
+
+impl SettingsWindow {
+    fn gradient_stops(&mut self) -> Option<&mut Vec<gradient::Stop>> {
+        match &mut self.config.flux.color_mode {
+            ColorMode::CustomGradient { stops } => Some(stops),
+            _ => None,
+        }
+    }
+
+    // Start the color inputs over from the stops, after they’ve been added, removed or moved.
+    fn reset_gradient_inputs(&mut self) {
+        self.gradient_inputs = match &self.config.flux.color_mode {
+            ColorMode::CustomGradient { stops } => stops
+                .iter()
+                .map(|(_, color)| String::from(*color))
+                .collect(),
+            _ => Vec::new(),
+        };
+    }
+
+    // Look over the selected image in the background, since large images take a while to decode.
+    fn check_image(&mut self) -> Command<Message> {
+        self.image_thumbnail = None;
+        self.image_warning = None;
+        match &self.config.flux.color_mode {
+            ColorMode::ImageFile {
+                image_path: Some(path),
+                ..
+            } => {
+                let path = path.clone();
+                Command::perform(
+                    task::spawn_blocking({
+                        let path = path.clone();
+                        move || image_check::check(&path)
+                    }),
+                    move |checked| Message::ImageChecked(path, checked),
+                )
+            }
+            _ => Command::none(),
+        }
+    }
+
+    // Look through the Spotlight cache in the background, if Spotlight is picked. Until the look
+    // is done, it’s taken to have an image, rather than flash up the warning.
+    fn check_spotlight(&mut self) -> Command<Message> {
+        if self.config.flux.color_mode != ColorMode::Spotlight {
+            return Command::none();
+        }
+
+        self.spotlight_available = true;
+        Command::perform(
+            task::spawn_blocking(|| crate::spotlight::find().is_some()),
+            Message::SpotlightChecked,
+        )
+    }
+
+    // Write the presets out as soon as they change.
+    fn save_presets(&mut self) {
+        self.presets
+            .save()
+            .unwrap_or_else(|err| log::error!("{}", err));
+        self.reset_preset_renames();
+    }
+
+    fn reset_preset_renames(&mut self) {
+        self.preset_renames = self
+            .presets
+            .presets
+            .iter()
+            .map(|preset| preset.name.clone())
+            .collect();
+    }
+
+    // Look up a monitor from the diagram, to save it by its stable ID.
+    fn monitor_ref(&self, id: u32) -> Option<MonitorRef> {
+        self.monitors
+            .iter()
+            .find(|monitor| monitor.id() == id)
+            .map(MonitorRef::from)
+    }
+}
+
+impl Application for SettingsWindow {
     type Executor = executor::Default;
     type Message = Message;
     type Theme = Theme;
-    type Flags = Config;
+    type Flags = (Config, Vec<MonitorHandle>, preview::Preview);
 
-    fn new(config: Config) -> (Self, Command<Message>) {
-        (config, Command::none())
+    fn new((mut config, monitors, preview): Self::Flags) -> (Self, Command<Message>) {
+        config.platform.windows.resolve_monitors(&monitors);
+        let presets = Presets::load(&config);
+        #[cfg(windows)]
+        let input_devices = crate::platform::windows::raw_input::devices()
+            .into_iter()
+            .map(|device| {
+                let label = device.label();
+                (device.name, label)
+            })
+            .collect();
+        #[cfg(not(windows))]
+        let input_devices = Vec::new();
+        let mut gpu_choices = vec![GpuChoice(None)];
+        #[cfg(windows)]
+        gpu_choices.extend(
+            crate::platform::windows::gpu_preference::adapters()
+                .into_iter()
+                .map(|adapter| GpuChoice(Some(adapter.label))),
+        );
+        let mut settings_window = Self {
+            config,
+            monitors,
+            selected_monitor: None,
+            preview,
+            preview_frame: None,
+            gradient_inputs: Vec::new(),
+            tab: Tab::Settings,
+            presets,
+            preset_name: String::new(),
+            preset_renames: Vec::new(),
+            share_code: String::new(),
+            share_code_error: None,
+            image_thumbnail: None,
+            image_warning: None,
+            spotlight_available: true,
+            input_devices,
+            gpu_choices,
+        };
+        settings_window.reset_gradient_inputs();
+        settings_window.reset_preset_renames();
+        let command = Command::batch([
+            settings_window.check_image(),
+            settings_window.check_spotlight(),
+        ]);
+        (settings_window, command)
     }
 
     fn title(&self) -> String {
@@ -53,10 +313,18 @@ impl Application for Config {
     }
 
     fn update(&mut self, message: Message) -> Command<Message> {
-        match message {
+        let command = match message {
             Message::SetColorMode(new_color) => {
-                self.flux.color_mode = new_color;
-                Command::none()
+                self.config.flux.color_mode = match new_color {
+                    ColorMode::CustomGradient { stops } if stops.is_empty() => {
+                        ColorMode::CustomGradient {
+                            stops: gradient::default_stops(),
+                        }
+                    }
+                    new_color => new_color,
+                };
+                self.reset_gradient_inputs();
+                self.check_spotlight()
             }
 
             Message::OpenFilePicker => Command::perform(
@@ -70,34 +338,372 @@ impl Application for Config {
                 Message::SetImageFile,
             ),
 
+            Message::SelectMonitor(monitor) => {
+                self.selected_monitor = monitor;
+                Command::none()
+            }
+
+            Message::OpenMonitorImagePicker => Command::perform(
+                task::spawn_blocking(|| {
+                    open_file_dialog(
+                        "Select an image for the monitor",
+                        "",
+                        Some((&["*.jpg", "*.jpeg", "*.png"], "Images")),
+                    )
+                }),
+                |some_path| Message::SetMonitorImage(some_path.map(PathBuf::from)),
+            ),
+
+            Message::SetMonitorImage(image_path) => {
+                if let Some(monitor) = self.selected_monitor.and_then(|id| self.monitor_ref(id)) {
+                    self.config
+                        .platform
+                        .windows
+                        .set_monitor_image(monitor, image_path);
+                }
+                Command::none()
+            }
+
+            // Images dropped onto the window go to the selected monitor.
+            Message::FileDropped(path) => {
+                if self.selected_monitor.is_some() && ::image::ImageFormat::from_path(&path).is_ok()
+                {
+                    self.update(Message::SetMonitorImage(Some(path)))
+                } else {
+                    Command::none()
+                }
+            }
+
             Message::SetImageFile(some_path) => {
                 if let Some(path_string) = some_path {
                     let path = PathBuf::from(path_string);
-                    self.flux.color_mode = ColorMode::ImageFile {
+                    self.config.flux.color_mode = ColorMode::ImageFile {
                         image_path: Some(path),
+                        image_size: None,
                     };
+                    self.check_image()
+                } else {
+                    Command::none()
+                }
+            }
+
+            // Spotlight might have been swapped for another color mode while it was being checked,
+            // in which case the result goes unused.
+            Message::SpotlightChecked(is_available) => {
+                self.spotlight_available = is_available;
+                Command::none()
+            }
+
+            // The image might have been changed again while it was being checked.
+            Message::ImageChecked(path, checked) => {
+                if let ColorMode::ImageFile {
+                    image_path: Some(image_path),
+                    image_size,
+                } = &mut self.config.flux.color_mode
+                {
+                    if *image_path == path {
+                        // Noted for scaling down large images when Flux starts
+                        *image_size = checked.size;
+                        self.image_thumbnail = checked.thumbnail;
+                        self.image_warning = checked.warning;
+                    }
+                }
+                Command::none()
+            }
+
+            Message::AddGradientStop => {
+                if let Some(stops) = self.gradient_stops() {
+                    if stops.len() < gradient::MAX_STOPS {
+                        let stop = gradient_editor::new_stop(stops);
+                        stops.push(stop);
+                    }
+                }
+                self.reset_gradient_inputs();
+                Command::none()
+            }
+
+            Message::RemoveGradientStop(index) => {
+                if let Some(stops) = self.gradient_stops() {
+                    if index < stops.len() && stops.len() > 1 {
+                        stops.remove(index);
+                    }
+                }
+                self.reset_gradient_inputs();
+                Command::none()
+            }
+
+            // Swap the colors, but leave the positions where they are, so that the colors trade
+            // places along the gradient.
+            Message::SwapGradientStops(a, b) => {
+                if let Some(stops) = self.gradient_stops() {
+                    if a < stops.len() && b < stops.len() {
+                        let (color_a, color_b) = (stops[a].1, stops[b].1);
+                        stops[a].1 = color_b;
+                        stops[b].1 = color_a;
+                    }
                 }
+                self.reset_gradient_inputs();
+                Command::none()
+            }
+
+            Message::SetGradientStopPosition(index, position) => {
+                if let Some(stop) = self.gradient_stops().and_then(|stops| stops.get_mut(index)) {
+                    stop.0 = position.clamp(0.0, 1.0).into();
+                }
+                Command::none()
+            }
+
+            Message::SetGradientStopColor(index, hex) => {
+                if let Ok(color) = Color::try_from(hex.clone()) {
+                    if let Some(stop) = self.gradient_stops().and_then(|stops| stops.get_mut(index))
+                    {
+                        stop.1 = color;
+                    }
+                }
+                if let Some(input) = self.gradient_inputs.get_mut(index) {
+                    *input = hex;
+                }
+                Command::none()
+            }
+
+            Message::SetSimulation(simulation) => {
+                self.config.flux.simulation = simulation;
+                Command::none()
+            }
+
+            Message::Shuffle => {
+                self.config.flux.simulation =
+                    shuffle::shuffle(&self.config.flux.simulation, shuffle::new_seed());
+                Command::none()
+            }
+
+            Message::SetPresetName(name) => {
+                self.preset_name = name;
+                Command::none()
+            }
+
+            Message::SavePreset => {
+                let name = self.preset_name.trim().to_string();
+                if !name.is_empty() {
+                    self.presets.insert(Preset::new(name, &self.config.flux));
+                    self.preset_name.clear();
+                    self.save_presets();
+                }
+                Command::none()
+            }
+
+            Message::SetTab(tab) => {
+                self.tab = tab;
+                Command::none()
+            }
+
+            Message::SetPresetRename(index, name) => {
+                if let Some(input) = self.preset_renames.get_mut(index) {
+                    *input = name;
+                }
+                Command::none()
+            }
+
+            Message::RenamePreset(index) => {
+                let name = self.preset_renames.get(index).cloned().unwrap_or_default();
+                match self.presets.rename(index, name) {
+                    Ok(()) => self.save_presets(),
+                    Err(err) => log::warn!("{}", err),
+                }
+                Command::none()
+            }
+
+            Message::UsePreset(index) => {
+                if let Some(preset) = self.presets.presets.get(index) {
+                    preset.apply(&mut self.config.flux);
+                    self.reset_gradient_inputs();
+                    self.tab = Tab::Settings;
+                }
+                self.check_image()
+            }
+
+            Message::DuplicatePreset(index) => {
+                self.presets.duplicate(index);
+                self.save_presets();
+                Command::none()
+            }
+
+            Message::DeletePreset(index) => {
+                self.presets.remove(index);
+                self.save_presets();
+                Command::none()
+            }
+
+            Message::CopyShareCode(index) => match self.presets.presets.get(index) {
+                Some(preset) => clipboard::write(preset.to_share_code()),
+                None => Command::none(),
+            },
+
+            Message::SetShareCode(code) => {
+                self.share_code = code;
+                self.share_code_error = None;
+                Command::none()
+            }
+
+            Message::ImportShareCode => {
+                match Preset::from_share_code(&self.share_code) {
+                    Ok(preset) => {
+                        // Don’t overwrite a preset of your own that happens to share the name.
+                        self.presets.add(preset);
+                        self.save_presets();
+                        self.share_code.clear();
+                    }
+                    Err(err) => self.share_code_error = Some(err),
+                }
+                Command::none()
+            }
+
+            Message::SetQuality(new_quality) => {
+                self.config.flux.quality = new_quality;
+                Command::none()
+            }
+
+            Message::SetPreferredGpu(GpuChoice(preferred_gpu)) => {
+                self.config.platform.windows.preferred_gpu = preferred_gpu;
+                Command::none()
+            }
+
+            Message::SetFrameRateLimit(FrameRateLimit(max_fps)) => {
+                self.config.flux.max_fps = max_fps;
+                Command::none()
+            }
+
+            Message::SetOverlay(overlay) => {
+                self.config.flux.overlay = overlay;
                 Command::none()
             }
 
             Message::SetFillMode(new_fill_mode) => {
-                self.platform.windows.fill_mode = new_fill_mode;
+                self.config.platform.windows.fill_mode = new_fill_mode;
+                Command::none()
+            }
+
+            Message::SpanMonitors { from, to } => {
+                if let (Some(from), Some(to)) = (self.monitor_ref(from), self.monitor_ref(to)) {
+                    self.config.platform.windows.span_together(from, to);
+                }
+                Command::none()
+            }
+
+            Message::UnspanMonitor(monitor) => {
+                if let Some(monitor) = self.monitor_ref(monitor) {
+                    self.config.platform.windows.unspan(&monitor);
+                }
+                Command::none()
+            }
+
+            Message::SetLaunchAtLogin(launch_at_login) => {
+                self.config.platform.windows.live_wallpaper.launch_at_login = launch_at_login;
+                Command::none()
+            }
+
+            Message::SetSlowDownWhenActive(slow_down) => {
+                self.config.platform.windows.live_wallpaper.active_max_fps =
+                    slow_down.then_some(ACTIVE_WALLPAPER_FPS);
+                Command::none()
+            }
+
+            Message::AddMaskedRegion(masked_region) => {
+                self.config
+                    .platform
+                    .windows
+                    .live_wallpaper
+                    .masked_regions
+                    .push(masked_region);
+                Command::none()
+            }
+
+            Message::RemoveMaskedRegion(index) => {
+                let masked_regions =
+                    &mut self.config.platform.windows.live_wallpaper.masked_regions;
+                if index < masked_regions.len() {
+                    masked_regions.remove(index);
+                }
+                Command::none()
+            }
+
+            Message::SetLockOnExit(lock_on_exit) => {
+                self.config.platform.windows.lock_on_exit = lock_on_exit;
+                Command::none()
+            }
+
+            Message::SetControllers(controllers) => {
+                self.config.platform.windows.input.controllers = controllers;
+                Command::none()
+            }
+
+            Message::SetDeviceIgnored(name, is_ignored) => {
+                self.config
+                    .platform
+                    .windows
+                    .input
+                    .set_ignored(&name, is_ignored);
+                Command::none()
+            }
+
+            Message::SetPing(enabled) => {
+                self.config.ping.enabled = enabled;
+                Command::none()
+            }
+
+            Message::PreviewFrame(frame) => {
+                self.preview_frame = Some(frame);
                 Command::none()
             }
 
             Message::Save => {
-                self.save().unwrap_or_else(|err| log::error!("{}", err));
+                self.config
+                    .save()
+                    .unwrap_or_else(|err| log::error!("{}", err));
+                #[cfg(windows)]
+                crate::platform::windows::autostart::set_live_wallpaper_at_login(
+                    self.config.platform.windows.live_wallpaper.launch_at_login,
+                )
+                .unwrap_or_else(|err| log::error!("{}", err));
                 window::close()
             }
 
             Message::Cancel => window::close(),
-        }
+        };
+
+        self.preview.set_settings(&self.config.flux);
+        command
+    }
+
+    fn subscription(&self) -> Subscription<Message> {
+        let preview_frames =
+            iced::subscription::unfold("preview", self.preview.frames(), |frames| async move {
+                match frames.recv().await {
+                    Ok(frame) => (Message::PreviewFrame(frame), frames),
+                    // The preview has stopped, so there’s nothing more to wait for.
+                    Err(_) => async_std::future::pending().await,
+                }
+            });
+        let dropped_files = iced::subscription::events_with(|event, _| match event {
+            iced::Event::Window(window::Event::FileDropped(path)) => {
+                Some(Message::FileDropped(path))
+            }
+            _ => None,
+        });
+
+        Subscription::batch([preview_frames, dropped_files])
     }
 
     fn view(&self) -> Element<Message> {
+        match self.tab {
+            Tab::Settings => (),
+            Tab::Simulation => return self.simulation_view(),
+            Tab::Presets => return self.presets_view(),
+        }
+
         let color_list = pick_list(
             &ColorMode::ALL[..],
-            Some(self.flux.color_mode.clone()),
+            Some(self.config.flux.color_mode.clone()),
             Message::SetColorMode,
         )
         .padding(8);
@@ -105,11 +711,12 @@ impl Application for Config {
         let mut color_section = column![
             text("Colors").size(20.0),
             "Choose from a selection of presets or use an image.",
+            self.preview_view(),
             color_list
         ]
         .spacing(12);
 
-        if let ColorMode::ImageFile { image_path } = &self.flux.color_mode {
+        if let ColorMode::ImageFile { image_path, .. } = &self.config.flux.color_mode {
             let mut image_picker = row![]
                 .push(
                     button("Select image")
@@ -119,6 +726,14 @@ impl Application for Config {
                 .align_items(Alignment::Center)
                 .spacing(12);
 
+            if let Some(thumbnail) = &self.image_thumbnail {
+                image_picker = image_picker.push(
+                    image(thumbnail.clone())
+                        .width(Length::Fixed(image_check::PREVIEW_SIDE as f32))
+                        .height(Length::Fixed(image_check::PREVIEW_SIDE as f32)),
+                );
+            }
+
             if let Some(path) = &image_path {
                 let filename = path
                     .file_name()
@@ -129,20 +744,52 @@ impl Application for Config {
             }
 
             color_section = color_section.push(image_picker);
+            if let Some(warning) = &self.image_warning {
+                color_section = color_section.push(text(warning).size(14.0));
+            }
         }
 
-        let save_button = button(text("Save").horizontal_alignment(Horizontal::Center))
-            .padding(8)
-            .width(Length::Fixed(96.0))
-            .on_press(Message::Save);
-        let cancel_button = button(text("Cancel").horizontal_alignment(Horizontal::Center))
-            .style(theme::Button::Secondary)
-            .padding(8)
-            .width(Length::Fixed(96.0))
-            .on_press(Message::Cancel);
-        let button_row = container(row![save_button, cancel_button].spacing(12));
+        if self.config.flux.color_mode == ColorMode::Spotlight && !self.spotlight_available {
+            color_section = color_section.push(
+                text(
+                    "Windows Spotlight hasn’t downloaded any images yet, so Flux uses the next \
+                     color source until it does.",
+                )
+                .size(14.0),
+            );
+        }
+
+        if let ColorMode::CustomGradient { stops } = &self.config.flux.color_mode {
+            color_section = color_section.push(gradient_editor::view(stops, &self.gradient_inputs));
+        }
+
+        // Only worth offering with more than one monitor to tell apart.
+        if cfg!(windows) && self.monitors.len() > 1 {
+            color_section = color_section.push(self.monitor_images_view());
+        }
+
+        let quality_list = pick_list(
+            &Quality::ALL[..],
+            Some(self.config.flux.quality),
+            Message::SetQuality,
+        )
+        .padding(8);
+
+        let frame_rate_list = pick_list(
+            &FrameRateLimit::ALL[..],
+            Some(FrameRateLimit(self.config.flux.max_fps)),
+            Message::SetFrameRateLimit,
+        )
+        .padding(8);
+
+        let quality_section = column![
+            text("Quality").size(20.0),
+            "Lower the quality or limit the frame rate if the animation stutters.",
+            row![quality_list, frame_rate_list].spacing(12),
+        ]
+        .spacing(12);
 
-        let mut content = column![color_section]
+        let mut content = column![self.tab_bar(), color_section, quality_section]
             .width(Length::Fill)
             .spacing(36)
             .padding(36);
@@ -150,30 +797,166 @@ impl Application for Config {
         if cfg!(windows) {
             let fill_list = pick_list(
                 &FillMode::ALL[..],
-                Some(self.platform.windows.fill_mode),
+                Some(self.config.platform.windows.fill_mode),
                 Message::SetFillMode,
             )
             .padding(8);
 
-            let fill_section = column![
+            // Drawn from the monitors that are plugged in, so picking a mode shows what it does
+            // on this setup.
+            let fill_diagram = monitor_diagram::MonitorDiagram::new(
+                &self.monitors,
+                self.config.platform.windows.fill_mode,
+                &self.config.platform.windows.span_groups,
+                &self.config.platform.windows.monitor_rotations,
+                self.config.platform.windows.uniform_physical_density,
+                &self.config.platform.windows.custom_layout,
+                self.config.platform.windows.mixed_dpi_span,
+            );
+
+            let mut fill_section = column![
                 text("Fill mode").size(20.0),
                 "Configure how Flux works across multiple monitors.",
-                indoc! {"
-                    None: Each monitor is a separate surface.
-                    Span: Combines any matching adjacent monitors.
-                    Fill: Combines all monitors into a single seamless surface.
-                "},
                 fill_list,
+                text(fill_diagram.summary()).size(14.0),
+                canvas(fill_diagram)
+                    .width(Length::Fill)
+                    .height(Length::Fixed(160.0)),
             ]
             .spacing(12);
 
+            if self.config.platform.windows.fill_mode == FillMode::Span {
+                fill_section = fill_section.push(
+                    text("Drag from one monitor to another to span them together. Click a monitor to separate it again.")
+                        .size(14.0),
+                );
+            }
+            if self.config.platform.windows.fill_mode == FillMode::Custom {
+                fill_section = fill_section.push(
+                    text("Custom layouts are set up in the settings file, under customLayout.")
+                        .size(14.0),
+                );
+            }
+
             content = content.push(fill_section);
+
+            // Only worth asking about with more than one GPU to choose from.
+            if self.gpu_choices.len() > 2 {
+                let gpu_section = column![
+                    text("Graphics card").size(20.0),
+                    "The GPU to render with. Flux starts on the new one the next time it runs.",
+                    pick_list(
+                        &self.gpu_choices[..],
+                        Some(GpuChoice(
+                            self.config.platform.windows.preferred_gpu.clone()
+                        )),
+                        Message::SetPreferredGpu,
+                    )
+                    .padding(8),
+                ]
+                .spacing(12);
+
+                content = content.push(gpu_section);
+            }
+
+            let wallpaper_section = column![
+                text("Live wallpaper").size(20.0),
+                "Run Flux behind the desktop icons.",
+                checkbox(
+                    "Start at login",
+                    self.config.platform.windows.live_wallpaper.launch_at_login,
+                    Message::SetLaunchAtLogin,
+                ),
+                checkbox(
+                    "Slow down while I’m using the computer",
+                    self.config
+                        .platform
+                        .windows
+                        .live_wallpaper
+                        .active_max_fps
+                        .is_some(),
+                    Message::SetSlowDownWhenActive,
+                ),
+                canvas(
+                    monitor_diagram::MonitorDiagram::new(
+                        &self.monitors,
+                        self.config.platform.windows.fill_mode,
+                        &self.config.platform.windows.span_groups,
+                        &self.config.platform.windows.monitor_rotations,
+                        self.config.platform.windows.uniform_physical_density,
+                        &self.config.platform.windows.custom_layout,
+                        self.config.platform.windows.mixed_dpi_span,
+                    )
+                    .with_masked_regions(
+                        &self.config.platform.windows.live_wallpaper.masked_regions
+                    )
+                )
+                .width(Length::Fill)
+                .height(Length::Fixed(160.0)),
+                text("Drag across a monitor to hide the wallpaper there, like behind a desktop widget. Click a hidden area to show it again.")
+                    .size(14.0),
+            ]
+            .spacing(12);
+
+            content = content.push(wallpaper_section);
+
+            let lock_section = column![
+                text("Security").size(20.0),
+                "Windows only asks for your password after screensavers that it started itself.",
+                checkbox(
+                    "Lock the computer when Flux exits",
+                    self.config.platform.windows.lock_on_exit,
+                    Message::SetLockOnExit,
+                ),
+            ]
+            .spacing(12);
+
+            content = content.push(lock_section);
+
+            let mut input_section = column![
+                text("Input").size(20.0),
+                "Ignore devices that send input on their own, so that they don’t wake the screensaver.",
+            ]
+            .spacing(12);
+            let input = &self.config.platform.windows.input;
+            input_section = input_section.push(checkbox(
+                "Wake the screensaver with gamepads and remotes",
+                input.controllers,
+                Message::SetControllers,
+            ));
+            for (name, label) in &self.input_devices {
+                input_section = input_section.push(checkbox(
+                    format!("Ignore {}", label),
+                    input.is_ignored(name),
+                    {
+                        let name = name.clone();
+                        move |is_ignored| Message::SetDeviceIgnored(name.clone(), is_ignored)
+                    },
+                ));
+            }
+
+            content = content.push(input_section);
+        }
+
+        // Only offer the ping once there’s somewhere to send it.
+        if let Some(endpoint) = &self.config.ping.endpoint {
+            let ping_section = column![
+                text("Anonymous ping").size(20.0),
+                text(format!(
+                    "Once per release, tell {} which version of Flux you’re running, on which operating system and GPU vendor. Nothing else is sent.",
+                    endpoint
+                )),
+                checkbox("Send the ping", self.config.ping.enabled, Message::SetPing),
+            ]
+            .spacing(12);
+
+            content = content.push(ping_section);
         }
 
         let version_text = text(format!("v{VERSION}")).size(12.0);
 
         content = content
-            .push(button_row)
+            .push(self.button_row())
             .push(vertical_space(Length::Fill))
             .push(version_text);
 
@@ -184,3 +967,228 @@ impl Application for Config {
         Theme::Dark
     }
 }
+
+impl SettingsWindow {
+    fn tab_bar(&self) -> Element<Message> {
+        let tab_button = |label: &'static str, tab: Tab| {
+            let style = if self.tab == tab {
+                theme::Button::Primary
+            } else {
+                theme::Button::Secondary
+            };
+            button(label)
+                .style(style)
+                .padding(8)
+                .on_press(Message::SetTab(tab))
+        };
+
+        row![
+            tab_button("Settings", Tab::Settings),
+            tab_button("Simulation", Tab::Simulation),
+            tab_button("Presets", Tab::Presets),
+        ]
+        .spacing(12)
+        .into()
+    }
+
+    fn preview_view(&self) -> Element<Message> {
+        match &self.preview_frame {
+            Some(frame) => image(frame.clone())
+                .width(Length::Fixed(preview::WIDTH as f32))
+                .height(Length::Fixed(preview::HEIGHT as f32))
+                .into(),
+            None => container(text("Starting the preview…").size(14.0))
+                .width(Length::Fixed(preview::WIDTH as f32))
+                .height(Length::Fixed(preview::HEIGHT as f32))
+                .center_x()
+                .center_y()
+                .into(),
+        }
+    }
+
+    fn monitor_images_view(&self) -> Element<Message> {
+        let monitor_images = &self.config.platform.windows.monitor_images;
+        let has_image = self
+            .selected_monitor
+            .and_then(|id| self.monitors.iter().find(|monitor| monitor.id() == id))
+            .is_some_and(|monitor| {
+                monitor_images
+                    .iter()
+                    .any(|monitor_image| monitor_image.monitor.matches(monitor))
+            });
+
+        let mut select_button = button("Select image").padding(8);
+        if self.selected_monitor.is_some() {
+            select_button = select_button.on_press(Message::OpenMonitorImagePicker);
+        }
+        let mut buttons = row![select_button].spacing(12);
+        if has_image {
+            buttons = buttons.push(
+                button("Use the color mode")
+                    .style(theme::Button::Secondary)
+                    .padding(8)
+                    .on_press(Message::SetMonitorImage(None)),
+            );
+        }
+
+        column![
+            text("Colors for each monitor").size(16.0),
+            text("Click a monitor, then select an image or drop one onto this window to give it colors of its own.")
+                .size(14.0),
+            canvas(
+                monitor_diagram::MonitorDiagram::new(
+                    &self.monitors,
+                    self.config.platform.windows.fill_mode,
+                    &self.config.platform.windows.span_groups,
+                    &self.config.platform.windows.monitor_rotations,
+                    self.config.platform.windows.uniform_physical_density,
+                    &self.config.platform.windows.custom_layout,
+                    self.config.platform.windows.mixed_dpi_span,
+                )
+                .with_monitor_images(monitor_images, self.selected_monitor)
+            )
+            .width(Length::Fill)
+            .height(Length::Fixed(160.0)),
+            buttons,
+        ]
+        .spacing(12)
+        .into()
+    }
+
+    fn button_row(&self) -> Element<Message> {
+        let save_button = button(text("Save").horizontal_alignment(Horizontal::Center))
+            .padding(8)
+            .width(Length::Fixed(96.0))
+            .on_press(Message::Save);
+        let cancel_button = button(text("Cancel").horizontal_alignment(Horizontal::Center))
+            .style(theme::Button::Secondary)
+            .padding(8)
+            .width(Length::Fixed(96.0))
+            .on_press(Message::Cancel);
+        container(row![save_button, cancel_button].spacing(12)).into()
+    }
+
+    // Tune the simulation, or shuffle it, and keep the result as a preset.
+    fn simulation_view(&self) -> Element<Message> {
+        let shuffle_button = button("Shuffle")
+            .style(theme::Button::Secondary)
+            .padding(8)
+            .on_press(Message::Shuffle);
+        let preset_name_input =
+            text_input("Preset name", &self.preset_name, Message::SetPresetName).padding(8);
+        let mut save_preset_button = button("Save as preset").padding(8);
+        if !self.preset_name.trim().is_empty() {
+            save_preset_button = save_preset_button.on_press(Message::SavePreset);
+        }
+        let simulation_section = column![
+            text("Simulation").size(20.0),
+            "Tune the lines and the flow, or shuffle for something new.",
+            self.preview_view(),
+            simulation_controls::view(self.config.flux.simulation),
+            row![shuffle_button, preset_name_input, save_preset_button]
+                .align_items(Alignment::Center)
+                .spacing(12),
+        ]
+        .spacing(12);
+
+        // Drawn by the screensaver itself, so it only shows up once Flux runs, not in the preview.
+        let overlay_section = column![
+            text("Overlay").size(20.0),
+            "Show the time, the date, or a line of text over the animation.",
+            overlay_controls::view(&self.config.flux.overlay),
+        ]
+        .spacing(12);
+
+        let content = column![
+            self.tab_bar(),
+            simulation_section,
+            overlay_section,
+            self.button_row()
+        ]
+        .push(vertical_space(Length::Fill))
+        .push(text(format!("v{VERSION}")).size(12.0))
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .spacing(36)
+        .padding(36);
+
+        container(content).into()
+    }
+
+    // Rename, use, duplicate or delete the saved presets.
+    fn presets_view(&self) -> Element<Message> {
+        let mut list = column![
+            text("Presets").size(20.0),
+            "Presets keep the colors and the look of the simulation. Press enter to rename one, or share it with a code.",
+        ]
+        .spacing(12);
+
+        if self.presets.presets.is_empty() {
+            list =
+                list.push(text("Save the current settings as a preset to see it here.").size(14.0));
+        }
+
+        for (index, name) in self.preset_renames.iter().enumerate() {
+            list = list.push(
+                column![
+                    text_input("Preset name", name, move |name| {
+                        Message::SetPresetRename(index, name)
+                    })
+                    .on_submit(Message::RenamePreset(index))
+                    .padding(8),
+                    row![
+                        button("Use").padding(8).on_press(Message::UsePreset(index)),
+                        button("Duplicate")
+                            .style(theme::Button::Secondary)
+                            .padding(8)
+                            .on_press(Message::DuplicatePreset(index)),
+                        button("Copy share code")
+                            .style(theme::Button::Secondary)
+                            .padding(8)
+                            .on_press(Message::CopyShareCode(index)),
+                        button("Delete")
+                            .style(theme::Button::Destructive)
+                            .padding(8)
+                            .on_press(Message::DeletePreset(index)),
+                    ]
+                    .spacing(8),
+                ]
+                .spacing(8),
+            );
+        }
+
+        let mut import_button = button("Import").padding(8);
+        if !self.share_code.trim().is_empty() {
+            import_button = import_button.on_press(Message::ImportShareCode);
+        }
+        let mut import_section = column![
+            text("Import").size(20.0),
+            row![
+                text_input(
+                    "Paste a share code",
+                    &self.share_code,
+                    Message::SetShareCode
+                )
+                .on_submit(Message::ImportShareCode)
+                .padding(8),
+                import_button,
+            ]
+            .align_items(Alignment::Center)
+            .spacing(12),
+        ]
+        .spacing(12);
+        if let Some(err) = &self.share_code_error {
+            import_section = import_section.push(text(err).size(14.0));
+        }
+
+        let content = column![self.tab_bar(), list, import_section]
+            .push(vertical_space(Length::Fill))
+            .push(text(format!("v{VERSION}")).size(12.0))
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .spacing(36)
+            .padding(36);
+
+        container(content).into()
+    }
+}
@@ -0,0 +1,105 @@
+use super::Message;
+use crate::config::Color;
+use crate::gradient::{self, Stop};
+
+use iced::alignment::Alignment;
+use iced::theme;
+use iced::widget::{button, column, container, row, slider, text, text_input};
+use iced::{Element, Length};
+use ordered_float::OrderedFloat;
+
+/// A row for each stop, with its color as a hex code and its position along the gradient.
+///
+/// The color inputs are edited as text, and only applied to the stop once they hold a valid
+/// color, so they’re passed in separately from the stops.
+pub fn view<'a>(stops: &'a [Stop], color_inputs: &'a [String]) -> Element<'a, Message> {
+    let mut editor = column![].spacing(8);
+
+    for (index, ((position, color), input)) in stops.iter().zip(color_inputs).enumerate() {
+        let swatch = container(text(""))
+            .width(Length::Fixed(24.0))
+            .height(Length::Fixed(24.0))
+            .style(theme::Container::Custom(Box::new(Swatch(*color))));
+
+        let mut move_up = button("↑").padding(4).style(theme::Button::Secondary);
+        if index > 0 {
+            move_up = move_up.on_press(Message::SwapGradientStops(index - 1, index));
+        }
+        let mut move_down = button("↓").padding(4).style(theme::Button::Secondary);
+        if index + 1 < stops.len() {
+            move_down = move_down.on_press(Message::SwapGradientStops(index, index + 1));
+        }
+        // A gradient needs at least one color.
+        let mut remove = button("✕").padding(4).style(theme::Button::Destructive);
+        if stops.len() > 1 {
+            remove = remove.on_press(Message::RemoveGradientStop(index));
+        }
+
+        editor = editor.push(
+            row![
+                swatch,
+                text_input("#ff8800", input, move |hex| {
+                    Message::SetGradientStopColor(index, hex)
+                })
+                .padding(4)
+                .width(Length::Fixed(88.0)),
+                slider(0.0..=1.0, position.into_inner(), move |position| {
+                    Message::SetGradientStopPosition(index, position)
+                })
+                .step(0.01),
+                move_up,
+                move_down,
+                remove,
+            ]
+            .align_items(Alignment::Center)
+            .spacing(8),
+        );
+    }
+
+    let mut add = button("Add color").padding(8);
+    if stops.len() < gradient::MAX_STOPS {
+        add = add.on_press(Message::AddGradientStop);
+    }
+
+    editor.push(add).into()
+}
+
+/// A new stop in the middle of the widest gap between the existing ones, in the color that the
+/// gradient already has there, so adding it doesn’t change the gradient until it’s edited.
+pub fn new_stop(stops: &[Stop]) -> Stop {
+    let mut positions = stops
+        .iter()
+        .map(|(position, _)| position.into_inner())
+        .collect::<Vec<f32>>();
+    positions.sort_by(f32::total_cmp);
+
+    // Count the space before the first stop and after the last one as gaps too.
+    let mut edges = vec![0.0];
+    edges.extend(positions);
+    edges.push(1.0);
+    let position = edges
+        .windows(2)
+        .max_by(|a, b| (a[1] - a[0]).total_cmp(&(b[1] - b[0])))
+        .map_or(0.5, |gap| (gap[0] + gap[1]) / 2.0);
+
+    let color = gradient::sample(stops, position).unwrap_or(Color {
+        r: 255,
+        g: 255,
+        b: 255,
+    });
+    (OrderedFloat(position), color)
+}
+
+struct Swatch(Color);
+
+impl container::StyleSheet for Swatch {
+    type Style = iced::Theme;
+
+    fn appearance(&self, _style: &Self::Style) -> container::Appearance {
+        container::Appearance {
+            background: Some(iced::Color::from_rgb8(self.0.r, self.0.g, self.0.b).into()),
+            border_radius: 4.0,
+            ..Default::default()
+        }
+    }
+}
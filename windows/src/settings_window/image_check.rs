@@ -0,0 +1,139 @@
+use crate::config::ImageSize;
+
+use std::path::Path;
+
+use iced::widget::image::Handle;
+
+// Larger than two 8K monitors’ worth of pixels. Flux decodes the whole image on every start, and
+// an image this large holds up the screensaver noticeably.
+const MAX_PIXELS: u64 = 2 * 7680 * 4320;
+
+// The colors are sampled across the image, and a tiny image only has a handful of them.
+const MIN_SIDE: u32 = 64;
+
+// How much the brightness varies across the image, from 0 to 1, below which the lines all end
+// up looking the same.
+const MIN_CONTRAST: f32 = 0.04;
+
+// Judge the contrast from a thumbnail, instead of every pixel.
+const THUMBNAIL_SIDE: u32 = 64;
+
+/// The side of the thumbnail shown next to the image name, in logical pixels
+pub const PREVIEW_SIDE: u32 = 96;
+
+// Decode the thumbnail at twice the size it’s shown at, so it stays sharp on high-DPI monitors.
+const PREVIEW_SCALE: u32 = 2;
+
+/// What the settings window shows for the selected image.
+#[derive(Clone, Debug, Default)]
+pub struct Checked {
+    /// Missing if the image can’t be read
+    pub size: Option<ImageSize>,
+    pub thumbnail: Option<Handle>,
+    /// What might not work well with the image
+    pub warning: Option<String>,
+}
+
+/// Look over an image picked for the colors, and explain what might not work well with it.
+///
+/// This decodes the image, so run it off the UI thread.
+pub fn check(path: &Path) -> Checked {
+    let (width, height) = match image::image_dimensions(path) {
+        Ok(dimensions) => dimensions,
+        Err(err) => return unreadable(err),
+    };
+    let size = Some(ImageSize { width, height });
+
+    // Too large to decode for a thumbnail in good time
+    if width as u64 * height as u64 > MAX_PIXELS {
+        return Checked {
+            size,
+            thumbnail: None,
+            warning: Some(format!(
+                "This image is very large ({}×{}), which slows down starting Flux. A copy scaled \
+                 down to the size of your monitors looks the same.",
+                width, height
+            )),
+        };
+    }
+
+    let image = match image::open(path) {
+        Ok(image) => image,
+        Err(err) => return unreadable(err),
+    };
+    let preview = image
+        .thumbnail(PREVIEW_SIDE * PREVIEW_SCALE, PREVIEW_SIDE * PREVIEW_SCALE)
+        .into_rgba8();
+    let thumbnail = Some(Handle::from_pixels(
+        preview.width(),
+        preview.height(),
+        preview.into_raw(),
+    ));
+
+    let warning = if width < MIN_SIDE || height < MIN_SIDE {
+        Some(format!(
+            "This image is only {}×{}, which is too small to take a good range of colors from.",
+            width, height
+        ))
+    } else if contrast(&image.thumbnail(THUMBNAIL_SIDE, THUMBNAIL_SIDE).into_rgb8()) < MIN_CONTRAST
+    {
+        Some("This image is mostly a single shade, so the lines will all look alike.".to_string())
+    } else {
+        None
+    };
+
+    Checked {
+        size,
+        thumbnail,
+        warning,
+    }
+}
+
+fn unreadable(err: image::ImageError) -> Checked {
+    let warning = match err {
+        image::ImageError::Unsupported(_) => {
+            "Flux can’t read this kind of image. Try a JPEG, PNG, or BMP instead.".to_string()
+        }
+        err => format!("Flux can’t read this image: {}", err),
+    };
+    Checked {
+        warning: Some(warning),
+        ..Default::default()
+    }
+}
+
+// The standard deviation of the brightness across the image, from 0 to 1.
+fn contrast(image: &image::RgbImage) -> f32 {
+    let lumas = image
+        .pixels()
+        .map(|pixel| {
+            let [r, g, b] = pixel.0.map(|channel| channel as f32 / 255.0);
+            0.2126 * r + 0.7152 * g + 0.0722 * b
+        })
+        .collect::<Vec<f32>>();
+    if lumas.is_empty() {
+        return 0.0;
+    }
+
+    let count = lumas.len() as f32;
+    let mean = lumas.iter().sum::<f32>() / count;
+    let variance = lumas.iter().map(|luma| (luma - mean).powi(2)).sum::<f32>() / count;
+    variance.sqrt()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_measures_the_contrast() {
+        let flat = image::RgbImage::from_pixel(8, 8, image::Rgb([120, 60, 200]));
+        assert_eq!(contrast(&flat), 0.0);
+
+        let checkerboard = image::RgbImage::from_fn(8, 8, |x, y| match (x + y) % 2 {
+            0 => image::Rgb([0, 0, 0]),
+            _ => image::Rgb([255, 255, 255]),
+        });
+        assert!((contrast(&checkerboard) - 0.5).abs() < 0.001);
+    }
+}
@@ -0,0 +1,617 @@
+use super::Message;
+use crate::config::{
+    CustomSurface, FillMode, MaskedRegion, MixedDpiSpan, MonitorImage, MonitorRef, MonitorRotation,
+};
+use crate::surface;
+use crate::winit_compat::MonitorHandle;
+
+use iced::alignment;
+use iced::mouse;
+use iced::widget::canvas::{self, event, Event, Frame, Geometry, Path, Stroke, Text};
+use iced::{Color, Point, Rectangle, Size, Theme};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+// Space to leave around the monitors, in logical pixels
+const PADDING: f32 = 8.0;
+
+// Space between a surface and the outline of its monitors, so both stay visible
+const SURFACE_INSET: f32 = 4.0;
+
+// Surfaces smaller than this in the diagram are too small to label
+const MINIMUM_LABEL_WIDTH: f32 = 64.0;
+
+// How far the mouse has to move between pressing and releasing the button to count as a drag
+const MINIMUM_DRAG: f32 = 4.0;
+
+// Cycled through to tell the surfaces apart
+const SURFACE_COLORS: [Color; 4] = [
+    Color::from_rgb(0.38, 0.56, 0.98),
+    Color::from_rgb(0.96, 0.55, 0.33),
+    Color::from_rgb(0.42, 0.80, 0.52),
+    Color::from_rgb(0.82, 0.46, 0.88),
+];
+
+/// A scale drawing of the monitors, with the surfaces that Flux would create for them under a
+/// fill mode drawn on top.
+///
+/// In span mode, dragging from one monitor to another spans them together, and clicking a
+/// monitor takes it out of its group.
+///
+/// With masked regions, the diagram edits those instead. Dragging across a monitor hides the
+/// live wallpaper in that rectangle, and clicking a region removes it again.
+///
+/// With monitor images, clicking a monitor selects it, to assign it an image of its own.
+pub struct MonitorDiagram {
+    monitors: Vec<(MonitorHandle, Option<std::path::PathBuf>)>,
+    fill_mode: FillMode,
+    span_groups: Vec<Vec<MonitorRef>>,
+    monitor_rotations: Vec<MonitorRotation>,
+    uniform_physical_density: bool,
+    custom_layout: Vec<CustomSurface>,
+    mixed_dpi_span: MixedDpiSpan,
+    editing: Editing,
+}
+
+// What clicking and dragging in the diagram changes
+enum Editing {
+    SpanGroups,
+    MaskedRegions(Vec<MaskedRegion>),
+    MonitorImages {
+        monitor_images: Vec<MonitorImage>,
+        selected: Option<u32>,
+    },
+}
+
+impl MonitorDiagram {
+    pub fn new(
+        monitors: &[MonitorHandle],
+        fill_mode: FillMode,
+        span_groups: &[Vec<MonitorRef>],
+        monitor_rotations: &[MonitorRotation],
+        uniform_physical_density: bool,
+        custom_layout: &[CustomSurface],
+        mixed_dpi_span: MixedDpiSpan,
+    ) -> Self {
+        Self {
+            monitors: monitors
+                .iter()
+                .map(|monitor| (monitor.clone(), None))
+                .collect(),
+            fill_mode,
+            span_groups: span_groups.to_vec(),
+            monitor_rotations: monitor_rotations.to_vec(),
+            uniform_physical_density,
+            custom_layout: custom_layout.to_vec(),
+            mixed_dpi_span,
+            editing: Editing::SpanGroups,
+        }
+    }
+
+    /// Draw the masked regions over the monitors, and edit them instead of the span groups.
+    pub fn with_masked_regions(mut self, masked_regions: &[MaskedRegion]) -> Self {
+        self.editing = Editing::MaskedRegions(masked_regions.to_vec());
+        self
+    }
+
+    /// Label the monitors with the images assigned to them, and select a monitor on click.
+    pub fn with_monitor_images(
+        mut self,
+        monitor_images: &[MonitorImage],
+        selected: Option<u32>,
+    ) -> Self {
+        self.editing = Editing::MonitorImages {
+            monitor_images: monitor_images.to_vec(),
+            selected,
+        };
+        self
+    }
+
+    /// Describe the surfaces that the fill mode makes out of the monitors, to go with the diagram.
+    pub fn summary(&self) -> String {
+        let sizes: Vec<PhysicalSize<u32>> =
+            self.surfaces().iter().map(surface::Surface::size).collect();
+        describe(self.monitors.len(), &sizes)
+    }
+
+    fn surfaces(&self) -> Vec<surface::Surface> {
+        surface::build(
+            &self.monitors,
+            &surface::Layout {
+                fill_mode: self.fill_mode,
+                span_groups: &self.span_groups,
+                rotations: &self.monitor_rotations,
+                custom_layout: &self.custom_layout,
+                mixed_dpi: self.mixed_dpi_span,
+                uniform_physical_density: self.uniform_physical_density,
+            },
+        )
+    }
+
+    fn outlines(&self) -> Vec<(PhysicalPosition<i32>, PhysicalSize<u32>)> {
+        self.monitors
+            .iter()
+            .map(|(monitor, _)| (monitor.position(), monitor.size()))
+            .collect()
+    }
+
+    // Find the monitor under a point in the diagram.
+    fn monitor_at(&self, point: Point, size: Size) -> Option<u32> {
+        let transform = Transform::fit(&self.outlines(), size)?;
+        self.monitors
+            .iter()
+            .find(|(monitor, _)| {
+                transform
+                    .apply(monitor.position(), monitor.size())
+                    .contains(point)
+            })
+            .map(|(monitor, _)| monitor.id())
+    }
+
+    fn monitor(&self, id: u32) -> Option<&MonitorHandle> {
+        self.monitors
+            .iter()
+            .map(|(monitor, _)| monitor)
+            .find(|monitor| monitor.id() == id)
+    }
+
+    // Where each of the masked regions on connected monitors is in the diagram, along with its
+    // index in the settings.
+    fn masked_rectangles(&self, transform: &Transform) -> Vec<(usize, Rectangle)> {
+        let Editing::MaskedRegions(masked_regions) = &self.editing else {
+            return Vec::new();
+        };
+
+        masked_regions
+            .iter()
+            .enumerate()
+            .filter_map(|(index, masked_region)| {
+                let (monitor, _) = self
+                    .monitors
+                    .iter()
+                    .find(|(monitor, _)| masked_region.monitor.matches(monitor))?;
+                let region = masked_region.to_region(monitor);
+                Some((index, transform.apply(region.position, region.size)))
+            })
+            .collect()
+    }
+
+    fn update_masked_regions(
+        &self,
+        drag_start: &mut Option<(u32, Point)>,
+        event: Event,
+        size: Size,
+        point: Point,
+    ) -> (event::Status, Option<Message>) {
+        let Some(transform) = Transform::fit(&self.outlines(), size) else {
+            return (event::Status::Ignored, None);
+        };
+
+        match event {
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
+                *drag_start = self.monitor_at(point, size).map(|monitor| (monitor, point));
+                (event::Status::Captured, None)
+            }
+
+            Event::Mouse(mouse::Event::CursorMoved { .. }) if drag_start.is_some() => {
+                (event::Status::Captured, None)
+            }
+
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+                let Some((monitor_id, start)) = drag_start.take() else {
+                    return (event::Status::Captured, None);
+                };
+
+                let message = if start.distance(point) < MINIMUM_DRAG {
+                    // The last region drawn is on top.
+                    self.masked_rectangles(&transform)
+                        .into_iter()
+                        .rev()
+                        .find(|(_, rectangle)| rectangle.contains(point))
+                        .map(|(index, _)| Message::RemoveMaskedRegion(index))
+                } else {
+                    self.monitor(monitor_id).map(|monitor| {
+                        let bounds = transform.apply(monitor.position(), monitor.size());
+                        let fraction = |point: Point| {
+                            (
+                                ((point.x - bounds.x) / bounds.width).clamp(0.0, 1.0),
+                                ((point.y - bounds.y) / bounds.height).clamp(0.0, 1.0),
+                            )
+                        };
+                        let (start_x, start_y) = fraction(start);
+                        let (end_x, end_y) = fraction(point);
+
+                        Message::AddMaskedRegion(MaskedRegion {
+                            monitor: MonitorRef::from(monitor),
+                            x: start_x.min(end_x),
+                            y: start_y.min(end_y),
+                            width: (end_x - start_x).abs(),
+                            height: (end_y - start_y).abs(),
+                            brightness: 0.0,
+                        })
+                    })
+                };
+                (event::Status::Captured, message)
+            }
+
+            _ => (event::Status::Ignored, None),
+        }
+    }
+}
+
+impl canvas::Program<Message> for MonitorDiagram {
+    // The monitor that a drag started from, and where
+    type State = Option<(u32, Point)>;
+
+    fn update(
+        &self,
+        drag_start: &mut Self::State,
+        event: Event,
+        bounds: Rectangle,
+        cursor: canvas::Cursor,
+    ) -> (event::Status, Option<Message>) {
+        if matches!(self.editing, Editing::SpanGroups) && self.fill_mode != FillMode::Span {
+            return (event::Status::Ignored, None);
+        }
+        let Some(point) = cursor.position_in(&bounds) else {
+            *drag_start = None;
+            return (event::Status::Ignored, None);
+        };
+        let monitor = self.monitor_at(point, bounds.size());
+        match self.editing {
+            Editing::SpanGroups => (),
+            Editing::MaskedRegions(_) => {
+                return self.update_masked_regions(drag_start, event, bounds.size(), point)
+            }
+            Editing::MonitorImages { .. } => {
+                return match event {
+                    Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => (
+                        event::Status::Captured,
+                        Some(Message::SelectMonitor(monitor)),
+                    ),
+                    _ => (event::Status::Ignored, None),
+                }
+            }
+        }
+
+        match event {
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
+                *drag_start = monitor.map(|monitor| (monitor, point));
+                (event::Status::Captured, None)
+            }
+
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+                let message = match (drag_start.take().map(|(from, _)| from), monitor) {
+                    (Some(from), Some(to)) if from != to => {
+                        Some(Message::SpanMonitors { from, to })
+                    }
+                    (Some(from), Some(_)) => Some(Message::UnspanMonitor(from)),
+                    _ => None,
+                };
+                (event::Status::Captured, message)
+            }
+
+            _ => (event::Status::Ignored, None),
+        }
+    }
+
+    fn draw(
+        &self,
+        drag_start: &Self::State,
+        theme: &Theme,
+        bounds: Rectangle,
+        cursor: canvas::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(bounds.size());
+
+        let outlines = self.outlines();
+        let Some(transform) = Transform::fit(&outlines, bounds.size()) else {
+            return vec![frame.into_geometry()];
+        };
+
+        match &self.editing {
+            Editing::SpanGroups => self.draw_surfaces(&mut frame, &transform),
+            Editing::MaskedRegions(_) => {
+                self.draw_masked_regions(&mut frame, &transform, theme, drag_start, cursor, bounds)
+            }
+            Editing::MonitorImages { monitor_images, .. } => {
+                self.draw_monitor_images(&mut frame, &transform, monitor_images)
+            }
+        }
+
+        let outline_color = theme.palette().text;
+        for (position, size) in outlines {
+            let rectangle = transform.apply(position, size);
+            frame.stroke(
+                &Path::rectangle(rectangle.position(), rectangle.size()),
+                Stroke::default().with_color(outline_color).with_width(1.5),
+            );
+        }
+
+        // Outline the selected monitor over the others.
+        if let Editing::MonitorImages {
+            selected: Some(selected),
+            ..
+        } = &self.editing
+        {
+            if let Some(monitor) = self.monitor(*selected) {
+                let rectangle = transform.apply(monitor.position(), monitor.size());
+                frame.stroke(
+                    &Path::rectangle(rectangle.position(), rectangle.size()),
+                    Stroke::default()
+                        .with_color(theme.palette().primary)
+                        .with_width(3.0),
+                );
+            }
+        }
+
+        vec![frame.into_geometry()]
+    }
+}
+
+impl MonitorDiagram {
+    fn draw_surfaces(&self, frame: &mut Frame, transform: &Transform) {
+        for (index, surface) in self.surfaces().iter().enumerate() {
+            let rectangle = transform.apply(surface.position(), surface.size());
+            let color = SURFACE_COLORS[index % SURFACE_COLORS.len()];
+            frame.fill(
+                &Path::rectangle(
+                    Point::new(rectangle.x + SURFACE_INSET, rectangle.y + SURFACE_INSET),
+                    Size::new(
+                        (rectangle.width - 2.0 * SURFACE_INSET).max(0.0),
+                        (rectangle.height - 2.0 * SURFACE_INSET).max(0.0),
+                    ),
+                ),
+                Color { a: 0.6, ..color },
+            );
+
+            if rectangle.width >= MINIMUM_LABEL_WIDTH {
+                frame.fill_text(Text {
+                    content: format!("{} × {}", surface.size().width, surface.size().height),
+                    position: rectangle.center(),
+                    color: Color::WHITE,
+                    size: 14.0,
+                    horizontal_alignment: alignment::Horizontal::Center,
+                    vertical_alignment: alignment::Vertical::Center,
+                    ..Text::default()
+                });
+            }
+        }
+    }
+
+    fn draw_masked_regions(
+        &self,
+        frame: &mut Frame,
+        transform: &Transform,
+        theme: &Theme,
+        drag_start: &Option<(u32, Point)>,
+        cursor: canvas::Cursor,
+        bounds: Rectangle,
+    ) {
+        // The fluid, where it isn’t masked
+        for (monitor, _) in &self.monitors {
+            let rectangle = transform.apply(monitor.position(), monitor.size());
+            frame.fill(
+                &Path::rectangle(rectangle.position(), rectangle.size()),
+                Color {
+                    a: 0.6,
+                    ..SURFACE_COLORS[0]
+                },
+            );
+        }
+
+        let Editing::MaskedRegions(masked_regions) = &self.editing else {
+            return;
+        };
+        for (index, rectangle) in self.masked_rectangles(transform) {
+            let brightness = masked_regions[index].brightness.clamp(0.0, 1.0);
+            frame.fill(
+                &Path::rectangle(rectangle.position(), rectangle.size()),
+                Color {
+                    a: 1.0 - brightness,
+                    ..Color::BLACK
+                },
+            );
+        }
+
+        // The region being drawn
+        if let (Some((_, start)), Some(point)) = (drag_start, cursor.position_in(&bounds)) {
+            let top_left = Point::new(start.x.min(point.x), start.y.min(point.y));
+            let size = Size::new((point.x - start.x).abs(), (point.y - start.y).abs());
+            frame.stroke(
+                &Path::rectangle(top_left, size),
+                Stroke::default()
+                    .with_color(theme.palette().primary)
+                    .with_width(1.5),
+            );
+        }
+    }
+}
+
+fn describe(monitor_count: usize, sizes: &[PhysicalSize<u32>]) -> String {
+    let sizes: Vec<String> = sizes
+        .iter()
+        .map(|size| format!("{} × {}", size.width, size.height))
+        .collect();
+
+    match sizes.as_slice() {
+        [] => "Flux can’t find any monitors.".to_string(),
+        [size] if monitor_count <= 1 => format!("Flux draws one {} surface on the monitor.", size),
+        [size] => format!(
+            "Flux draws one {} surface across the {} monitors.",
+            size, monitor_count
+        ),
+        _ if sizes.len() == monitor_count => format!(
+            "Flux draws a separate surface on each of the {} monitors.",
+            monitor_count
+        ),
+        [rest @ .., last] => format!(
+            "Flux draws {} surfaces across the {} monitors: {} and {}.",
+            sizes.len(),
+            monitor_count,
+            rest.join(", "),
+            last
+        ),
+    }
+}
+
+impl MonitorDiagram {
+    fn draw_monitor_images(
+        &self,
+        frame: &mut Frame,
+        transform: &Transform,
+        monitor_images: &[MonitorImage],
+    ) {
+        for (monitor, _) in &self.monitors {
+            let rectangle = transform.apply(monitor.position(), monitor.size());
+            let image_name = monitor_images
+                .iter()
+                .find(|monitor_image| monitor_image.monitor.matches(monitor))
+                .and_then(|monitor_image| monitor_image.image_path.file_name())
+                .map(|file_name| file_name.to_string_lossy().into_owned());
+
+            // Monitors with an image of their own stand out from the ones that follow the
+            // color mode.
+            let color = match image_name {
+                Some(_) => SURFACE_COLORS[1],
+                None => SURFACE_COLORS[0],
+            };
+            frame.fill(
+                &Path::rectangle(rectangle.position(), rectangle.size()),
+                Color { a: 0.6, ..color },
+            );
+
+            if rectangle.width >= MINIMUM_LABEL_WIDTH {
+                frame.fill_text(Text {
+                    content: image_name.unwrap_or_else(|| "Color mode".to_string()),
+                    position: rectangle.center(),
+                    color: Color::WHITE,
+                    size: 14.0,
+                    horizontal_alignment: alignment::Horizontal::Center,
+                    vertical_alignment: alignment::Vertical::Center,
+                    ..Text::default()
+                });
+            }
+        }
+    }
+}
+
+// Maps the virtual screen onto the diagram, keeping the aspect ratio.
+#[derive(Debug, PartialEq)]
+struct Transform {
+    origin: PhysicalPosition<i32>,
+    scale: f32,
+    offset: Point,
+}
+
+impl Transform {
+    fn fit(monitors: &[(PhysicalPosition<i32>, PhysicalSize<u32>)], target: Size) -> Option<Self> {
+        let left = monitors.iter().map(|(position, _)| position.x).min()?;
+        let top = monitors.iter().map(|(position, _)| position.y).min()?;
+        let right = monitors
+            .iter()
+            .map(|(position, size)| position.x + size.width as i32)
+            .max()?;
+        let bottom = monitors
+            .iter()
+            .map(|(position, size)| position.y + size.height as i32)
+            .max()?;
+
+        let (width, height) = ((right - left) as f32, (bottom - top) as f32);
+        if width <= 0.0 || height <= 0.0 {
+            return None;
+        }
+
+        let available = Size::new(
+            (target.width - 2.0 * PADDING).max(0.0),
+            (target.height - 2.0 * PADDING).max(0.0),
+        );
+        let scale = f32::min(available.width / width, available.height / height);
+
+        // Center the monitors in the diagram
+        let offset = Point::new(
+            (target.width - width * scale) / 2.0,
+            (target.height - height * scale) / 2.0,
+        );
+
+        Some(Self {
+            origin: PhysicalPosition::new(left, top),
+            scale,
+            offset,
+        })
+    }
+
+    fn apply(&self, position: PhysicalPosition<i32>, size: PhysicalSize<u32>) -> Rectangle {
+        Rectangle {
+            x: self.offset.x + (position.x - self.origin.x) as f32 * self.scale,
+            y: self.offset.y + (position.y - self.origin.y) as f32 * self.scale,
+            width: size.width as f32 * self.scale,
+            height: size.height as f32 * self.scale,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_fits_the_monitors_into_the_diagram() {
+        // Two monitors side by side, with the second one to the left of the primary.
+        let monitors = [
+            (PhysicalPosition::new(0, 0), PhysicalSize::new(1920, 1080)),
+            (
+                PhysicalPosition::new(-1920, 0),
+                PhysicalSize::new(1920, 1080),
+            ),
+        ];
+        let transform = Transform::fit(&monitors, Size::new(400.0, 200.0)).unwrap();
+
+        assert_eq!(transform.scale, 0.1);
+        assert_eq!(
+            transform.apply(monitors[1].0, monitors[1].1),
+            Rectangle {
+                x: 8.0,
+                y: 46.0,
+                width: 192.0,
+                height: 108.0,
+            }
+        );
+        assert_eq!(
+            transform.apply(monitors[0].0, monitors[0].1),
+            Rectangle {
+                x: 200.0,
+                y: 46.0,
+                width: 192.0,
+                height: 108.0,
+            }
+        );
+    }
+
+    #[test]
+    fn it_describes_the_surfaces() {
+        let wide = PhysicalSize::new(3840, 1080);
+        let single = PhysicalSize::new(1920, 1080);
+
+        assert_eq!(
+            describe(2, &[wide]),
+            "Flux draws one 3840 × 1080 surface across the 2 monitors."
+        );
+        assert_eq!(
+            describe(1, &[single]),
+            "Flux draws one 1920 × 1080 surface on the monitor."
+        );
+        assert_eq!(
+            describe(2, &[single, single]),
+            "Flux draws a separate surface on each of the 2 monitors."
+        );
+        assert_eq!(
+            describe(3, &[wide, single]),
+            "Flux draws 2 surfaces across the 3 monitors: 3840 × 1080 and 1920 × 1080."
+        );
+    }
+
+    #[test]
+    fn it_skips_an_empty_layout() {
+        assert_eq!(Transform::fit(&[], Size::new(400.0, 200.0)), None);
+    }
+}
@@ -0,0 +1,99 @@
+use super::Message;
+use crate::config::{OverlayContent, OverlayPosition, OverlaySettings};
+
+use std::ops::RangeInclusive;
+
+use iced::alignment::Alignment;
+use iced::widget::{column, pick_list, row, slider, text, text_input};
+use iced::{Element, Length};
+use ordered_float::OrderedFloat;
+
+// The font sizes the slider goes between. The settings file takes larger ones, for giant screens.
+const FONT_SIZES: RangeInclusive<f32> = 8.0..=200.0;
+
+/// What to draw over the animation, and where and how large to draw it.
+pub fn view<'a>(overlay: &OverlaySettings) -> Element<'a, Message> {
+    let content_list = pick_list(&OverlayContent::ALL[..], Some(overlay.content), {
+        let overlay = overlay.clone();
+        move |content| {
+            Message::SetOverlay(OverlaySettings {
+                content,
+                ..overlay.clone()
+            })
+        }
+    })
+    .padding(8);
+
+    let mut controls = column![content_list].spacing(8);
+    if overlay.content == OverlayContent::None {
+        return controls.into();
+    }
+
+    if overlay.content == OverlayContent::Text {
+        controls = controls.push(
+            text_input("Text to show", &overlay.text, {
+                let overlay = overlay.clone();
+                move |text| {
+                    Message::SetOverlay(OverlaySettings {
+                        text,
+                        ..overlay.clone()
+                    })
+                }
+            })
+            .padding(8),
+        );
+    }
+
+    let position_list = pick_list(&OverlayPosition::ALL[..], Some(overlay.position), {
+        let overlay = overlay.clone();
+        move |position| {
+            Message::SetOverlay(OverlaySettings {
+                position,
+                ..overlay.clone()
+            })
+        }
+    })
+    .padding(8);
+
+    let font_size = overlay.font_size as f32;
+    let size_slider = row![
+        text("Size").width(Length::Fixed(136.0)),
+        slider(FONT_SIZES, font_size, {
+            let overlay = overlay.clone();
+            move |font_size| {
+                Message::SetOverlay(OverlaySettings {
+                    font_size: font_size.round() as u32,
+                    ..overlay.clone()
+                })
+            }
+        })
+        .step(1.0),
+        text(format!("{} px", overlay.font_size)).width(Length::Fixed(48.0)),
+    ]
+    .align_items(Alignment::Center)
+    .spacing(12);
+
+    let opacity = overlay.opacity.into_inner();
+    let opacity_slider = row![
+        text("Opacity").width(Length::Fixed(136.0)),
+        slider(0.0..=1.0, opacity, {
+            let overlay = overlay.clone();
+            move |opacity| {
+                Message::SetOverlay(OverlaySettings {
+                    opacity: OrderedFloat(opacity),
+                    ..overlay.clone()
+                })
+            }
+        })
+        .step(0.05),
+        text(format!("{:.0}%", opacity * 100.0)).width(Length::Fixed(48.0)),
+    ]
+    .align_items(Alignment::Center)
+    .spacing(12);
+
+    controls
+        .push(position_list)
+        .push(size_slider)
+        .push(opacity_slider)
+        .into()
+}
@@ -0,0 +1,169 @@
+use crate::config::{Config, FluxSettings};
+use crate::winit_compat::{HasMonitors, MonitorHandle};
+use crate::{clock, gl_context, offscreen};
+
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use iced::widget::image;
+use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
+use winit::dpi::PhysicalSize;
+
+// The size of the preview in the settings window, in logical pixels
+pub const WIDTH: u32 = 348;
+pub const HEIGHT: u32 = 160;
+
+// Render at twice the size, so the lines stay crisp on high-DPI displays.
+const SCALE_FACTOR: f64 = 2.0;
+
+// The preview doesn’t need to be as smooth as the real thing.
+const FRAME_INTERVAL: Duration = Duration::from_millis(33);
+
+/// A small live preview of Flux for the settings window.
+///
+/// Flux renders offscreen on a thread of its own, with a separate GL context, so that it can’t
+/// interfere with the context that iced draws the window with. The frames are read back and
+/// shown as images.
+///
+/// SDL can only be used from one thread, so the preview thread also finds the monitors for the
+/// fill mode diagram.
+pub struct Preview {
+    settings: mpsc::Sender<FluxSettings>,
+    frames: async_std::channel::Receiver<image::Handle>,
+    last_sent: FluxSettings,
+}
+
+impl Preview {
+    pub fn start(flux_settings: &FluxSettings) -> (Self, Vec<MonitorHandle>) {
+        let (monitors_sender, monitors_receiver) = mpsc::channel();
+        let (settings_sender, settings_receiver) = mpsc::channel();
+        // Drop frames, rather than queue them up, if the window falls behind.
+        let (frame_sender, frame_receiver) = async_std::channel::bounded(1);
+
+        let _ = settings_sender.send(flux_settings.clone());
+        thread::spawn(move || {
+            if let Err(err) = run(monitors_sender, settings_receiver, frame_sender) {
+                log::warn!("Failed to run the preview: {}", err);
+            }
+        });
+
+        // The sender is dropped without sending anything if SDL fails to start.
+        let monitors = monitors_receiver.recv().unwrap_or_else(|_| {
+            log::warn!("Failed to find the monitors");
+            Vec::new()
+        });
+
+        let preview = Self {
+            settings: settings_sender,
+            frames: frame_receiver,
+            last_sent: flux_settings.clone(),
+        };
+        (preview, monitors)
+    }
+
+    /// Restart the preview with new settings, if they’ve changed.
+    pub fn set_settings(&mut self, flux_settings: &FluxSettings) {
+        // The preview doesn’t draw the overlay, so changing it is no reason to start over.
+        let changed = FluxSettings {
+            overlay: self.last_sent.overlay.clone(),
+            ..flux_settings.clone()
+        } != self.last_sent;
+        if changed {
+            self.last_sent = flux_settings.clone();
+            let _ = self.settings.send(flux_settings.clone());
+        }
+    }
+
+    /// The rendered frames, as they come in.
+    pub fn frames(&self) -> async_std::channel::Receiver<image::Handle> {
+        self.frames.clone()
+    }
+}
+
+// Render frames until the settings window goes away.
+fn run(
+    monitors: mpsc::Sender<Vec<MonitorHandle>>,
+    settings: mpsc::Receiver<FluxSettings>,
+    frames: async_std::channel::Sender<image::Handle>,
+) -> Result<(), String> {
+    let physical_size = PhysicalSize::new(
+        (WIDTH as f64 * SCALE_FACTOR) as u32,
+        (HEIGHT as f64 * SCALE_FACTOR) as u32,
+    );
+
+    let sdl_context = sdl2::init()?;
+    let video_subsystem = sdl_context.video()?;
+    let _ = monitors.send(video_subsystem.available_monitors().collect());
+
+    let window = video_subsystem
+        .window("Flux preview", physical_size.width, physical_size.height)
+        .hidden()
+        .build()
+        .map_err(|err| err.to_string())?;
+
+    let gl_context = gl_context::new_gl_context(
+        window.raw_display_handle(),
+        physical_size,
+        window.raw_window_handle(),
+        None,
+        false,
+        false,
+    )?;
+    let gl = &gl_context.gl;
+    let target = unsafe { offscreen::Target::new(gl, physical_size)? };
+
+    let mut config = Config::default();
+    let mut flux = None;
+    let mut clock = clock::Clock::new(Instant::now());
+    let mut pixels = vec![0; target.frame_len()];
+
+    loop {
+        // Only the latest settings matter.
+        let mut new_settings = None;
+        loop {
+            match settings.try_recv() {
+                Ok(flux_settings) => new_settings = Some(flux_settings),
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => return Ok(()),
+            }
+        }
+
+        if let Some(flux_settings) = new_settings {
+            config.flux = flux_settings;
+            flux = crate::new_flux(gl, &config, None, physical_size, SCALE_FACTOR)
+                .map_err(|err| log::warn!("Failed to start the preview: {}", err))
+                .ok();
+            clock = clock::Clock::new(Instant::now());
+        }
+
+        let frame_start = Instant::now();
+        if let Some(flux) = flux.as_mut() {
+            flux.compute(clock.timestamp(frame_start).unwrap_or_default());
+
+            unsafe {
+                target.bind(gl);
+                flux.render();
+                target.read(gl, &mut pixels);
+                target.unbind(gl);
+            }
+
+            // The window behind the preview shouldn’t show through.
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel[3] = u8::MAX;
+            }
+
+            let frame = image::Handle::from_pixels(
+                physical_size.width,
+                physical_size.height,
+                pixels.clone(),
+            );
+            match frames.try_send(frame) {
+                Ok(()) | Err(async_std::channel::TrySendError::Full(_)) => (),
+                Err(async_std::channel::TrySendError::Closed(_)) => return Ok(()),
+            }
+        }
+
+        clock::sleep_until(frame_start + FRAME_INTERVAL);
+    }
+}
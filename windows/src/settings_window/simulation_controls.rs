@@ -0,0 +1,73 @@
+use super::Message;
+use crate::config::SimulationSettings;
+
+use std::ops::RangeInclusive;
+
+use iced::alignment::Alignment;
+use iced::theme;
+use iced::widget::{button, column, row, slider, text};
+use iced::{Element, Length};
+use ordered_float::OrderedFloat;
+
+type Scale = Option<OrderedFloat<f32>>;
+
+/// A slider for each of the simulation settings, as a multiple of the Flux default.
+pub fn view<'a>(simulation: SimulationSettings) -> Element<'a, Message> {
+    let control = |label: &'static str,
+                   range: RangeInclusive<f32>,
+                   scale: Scale,
+                   set: fn(&mut SimulationSettings, Scale)| {
+        let scale = scale.map_or(1.0, OrderedFloat::into_inner);
+        row![
+            text(label).width(Length::Fixed(136.0)),
+            slider(range, scale, move |scale| {
+                let mut simulation = simulation;
+                set(&mut simulation, Some(OrderedFloat(scale)));
+                Message::SetSimulation(simulation)
+            })
+            .step(0.05),
+            text(format!("{:.2}×", scale)).width(Length::Fixed(48.0)),
+        ]
+        .align_items(Alignment::Center)
+        .spacing(12)
+    };
+
+    column![
+        control(
+            "Line length",
+            SimulationSettings::SCALE_RANGE,
+            simulation.line_length,
+            |simulation, scale| simulation.line_length = scale
+        ),
+        control(
+            "Line width",
+            SimulationSettings::SCALE_RANGE,
+            simulation.line_width,
+            |simulation, scale| simulation.line_width = scale
+        ),
+        control(
+            "Line count",
+            SimulationSettings::LINE_DENSITY_RANGE,
+            simulation.line_density,
+            |simulation, scale| simulation.line_density = scale
+        ),
+        control(
+            "Noise strength",
+            SimulationSettings::SCALE_RANGE,
+            simulation.noise_strength,
+            |simulation, scale| simulation.noise_strength = scale
+        ),
+        control(
+            "Dissipation",
+            SimulationSettings::SCALE_RANGE,
+            simulation.velocity_dissipation,
+            |simulation, scale| simulation.velocity_dissipation = scale
+        ),
+        button("Reset to defaults")
+            .style(theme::Button::Secondary)
+            .padding(8)
+            .on_press(Message::SetSimulation(SimulationSettings::default())),
+    ]
+    .spacing(8)
+    .into()
+}
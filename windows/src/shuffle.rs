@@ -0,0 +1,95 @@
+use crate::config::SimulationSettings;
+
+use std::ops::RangeInclusive;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ordered_float::OrderedFloat;
+
+// How far each setting strays from the Flux defaults. Past these, the lines either clump into
+// blobs or thin out into noise.
+const LINE_LENGTH: RangeInclusive<f32> = 0.6..=1.5;
+const LINE_WIDTH: RangeInclusive<f32> = 0.6..=1.6;
+const NOISE_STRENGTH: RangeInclusive<f32> = 0.5..=1.8;
+
+/// Pick new simulation settings at random, within ranges that still look good. The settings
+/// that aren’t shuffled are kept as they are in `current`.
+///
+/// The same seed always gives the same settings.
+pub fn shuffle(current: &SimulationSettings, seed: u64) -> SimulationSettings {
+    let mut rng = SplitMix64(seed);
+    let mut pick = |range: RangeInclusive<f32>| {
+        let t = rng.next_f32();
+        let value = range.start() + (range.end() - range.start()) * t;
+        // Round to two decimals, so the settings file stays readable.
+        Some(OrderedFloat((value * 100.0).round() / 100.0))
+    };
+
+    SimulationSettings {
+        line_length: pick(LINE_LENGTH),
+        line_width: pick(LINE_WIDTH),
+        noise_strength: pick(NOISE_STRENGTH),
+        // Denser lines are slower to draw, so leave them be.
+        ..current.clone()
+    }
+}
+
+/// A seed that changes every time.
+pub fn new_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_nanos() as u64)
+}
+
+// Plenty random enough to pick a few settings, without pulling in a crate for it.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // A number from 0 to 1
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_stays_within_the_ranges() {
+        let current = SimulationSettings::default();
+        for seed in 0..1000 {
+            let settings = shuffle(&current, seed);
+            let within = |value: Option<OrderedFloat<f32>>, range: RangeInclusive<f32>| {
+                value.is_some_and(|value| range.contains(&value.into_inner()))
+            };
+
+            assert!(within(settings.line_length, LINE_LENGTH));
+            assert!(within(settings.line_width, LINE_WIDTH));
+            assert!(within(settings.noise_strength, NOISE_STRENGTH));
+        }
+
+        assert_eq!(shuffle(&current, 42), shuffle(&current, 42));
+        assert_ne!(shuffle(&current, 42), shuffle(&current, 43));
+    }
+
+    #[test]
+    fn it_keeps_the_settings_it_doesnt_shuffle() {
+        let current = SimulationSettings {
+            line_density: Some(OrderedFloat(1.5)),
+            velocity_dissipation: Some(OrderedFloat(0.8)),
+            ..Default::default()
+        };
+        let settings = shuffle(&current, 42);
+
+        assert_eq!(settings.line_density, current.line_density);
+        assert_eq!(settings.velocity_dissipation, current.velocity_dissipation);
+    }
+}
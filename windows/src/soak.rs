@@ -0,0 +1,298 @@
+use crate::config::{Config, Rotation, SoakConfig};
+use crate::gpu;
+use crate::platform::windows::process;
+use crate::render_thread::{self, Command, RenderThread};
+use crate::winit_compat::HasWinitWindow;
+
+use std::fmt;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
+
+// Paces the frames of a hidden window, which has no display to wait on
+const HEADLESS_FRAME_INTERVAL: Duration = Duration::from_micros(16_667);
+
+// The size to render at without a window on show
+const HEADLESS_SIZE: (u32, u32) = (1920, 1080);
+
+const MB: u64 = 1024 * 1024;
+
+/// Run Flux for hours on end, logging what the process holds on to every so often, and fail as
+/// soon as any of it grows past the limits in the settings.
+///
+/// Leaks small enough to go unnoticed in a screensaver that runs for minutes add up in a live
+/// wallpaper that runs for days. The first samples come while shaders compile and caches fill, so
+/// growth is measured from a baseline taken once the warmup is over.
+///
+/// Without `windowed`, Flux renders into a hidden window, which leaves the monitors free.
+pub fn run(
+    video_subsystem: &sdl2::VideoSubsystem,
+    event_pump: &mut sdl2::EventPump,
+    config: &Config,
+    duration: Duration,
+    windowed: bool,
+) -> Result<(), String> {
+    let soak = config.platform.windows.soak;
+    let window = if windowed {
+        let bounds = video_subsystem.display_bounds(0)?;
+        video_subsystem
+            .window("Flux soak test", bounds.width(), bounds.height())
+            .position(bounds.x(), bounds.y())
+            .borderless()
+            .allow_highdpi()
+            .build()
+    } else {
+        video_subsystem
+            .window("Flux soak test", HEADLESS_SIZE.0, HEADLESS_SIZE.1)
+            .hidden()
+            .build()
+    }
+    .map_err(|err| err.to_string())?;
+
+    let handles = render_thread::Handles {
+        display: window.raw_display_handle(),
+        window: window.raw_window_handle(),
+        attr_window: None,
+    };
+    let options = render_thread::Options {
+        size: window.inner_size(),
+        scale_factor: window.scale_factor(),
+        wallpaper: None,
+        compositor_opacity: None,
+        rotation: Rotation::None,
+        tiles: (1, 1),
+        // A hidden window can’t hold a DXGI swapchain, so present through GL.
+        resizable: !windowed,
+        letterbox: Vec::new(),
+    };
+    let (mut renderer, ready) = RenderThread::spawn(config, handles, options)?;
+    log::info!(
+        "Soak testing for {:.1} hours through the {} swapchain on {}",
+        duration.as_secs_f64() / 3600.0,
+        ready.swapchain,
+        ready.gpu
+    );
+
+    let sample_interval = Duration::from_secs(soak.sample_interval_secs.max(1).into());
+    let warmup = Duration::from_secs(soak.warmup_secs.into());
+    let start = Instant::now();
+    let mut next_sample = start + sample_interval;
+    let mut frames: u32 = 0;
+    let mut last_sample_at = start;
+    let mut baseline: Option<Sample> = None;
+    let mut last_sample = None;
+
+    while start.elapsed() < duration {
+        // Keep the window responsive, but don’t let input end the run.
+        for _ in event_pump.poll_iter() {}
+
+        let frame_start = Instant::now();
+        renderer.run(Command::Draw(start.elapsed().as_secs_f64() * 1000.0))?;
+        frames += 1;
+        if !windowed {
+            crate::clock::sleep_until(frame_start + HEADLESS_FRAME_INTERVAL);
+        }
+
+        let now = Instant::now();
+        if now < next_sample {
+            continue;
+        }
+        next_sample = now + sample_interval;
+
+        let sample = Sample {
+            elapsed: now - start,
+            usage: process::usage()?,
+            gpu_memory: query_gpu_memory(&mut renderer)?,
+            fps: frames as f64 / (now - last_sample_at).as_secs_f64(),
+        };
+        frames = 0;
+        last_sample_at = now;
+        log::info!("{}", sample);
+        println!("{}", sample);
+
+        match &baseline {
+            None if sample.elapsed >= warmup => {
+                log::info!("Warmed up. Measuring growth from here on.");
+                baseline = Some(sample);
+            }
+            None => (),
+            Some(baseline) => {
+                let problems = check(baseline, &sample, &soak);
+                if !problems.is_empty() {
+                    return Err(format!(
+                        "The soak test failed after {}: {}",
+                        format_elapsed(sample.elapsed),
+                        problems.join("; ")
+                    ));
+                }
+            }
+        }
+        last_sample = Some(sample);
+    }
+
+    // Release the swapchain before the window goes away.
+    drop(renderer);
+
+    let summary =
+        match (&baseline, &last_sample) {
+            (Some(baseline), Some(last_sample)) => format!(
+            "The soak test passed after {}. Private memory grew by {:.1} MB, and handles by {}.",
+            format_elapsed(start.elapsed()),
+            growth(baseline.usage.private_bytes, last_sample.usage.private_bytes) as f64
+                / MB as f64,
+            growth(baseline.usage.handles, last_sample.usage.handles),
+        ),
+            _ => "The soak test ended before the warmup was over, so there was nothing to measure."
+                .to_string(),
+        };
+    log::info!("{}", summary);
+    println!("{}", summary);
+    Ok(())
+}
+
+fn query_gpu_memory(renderer: &mut RenderThread) -> Result<Option<gpu::Memory>, String> {
+    let (sender, receiver) = mpsc::channel();
+    renderer.run(Command::QueryGpuMemory(sender))?;
+    Ok(receiver.try_recv().ok().flatten())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Sample {
+    elapsed: Duration,
+    usage: process::Usage,
+    gpu_memory: Option<gpu::Memory>,
+    // Since the last sample
+    fps: f64,
+}
+
+impl fmt::Display for Sample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {:.1} MB private, {:.1} MB working set, {} handles, {} GDI objects, {} USER \
+             objects, ",
+            format_elapsed(self.elapsed),
+            self.usage.private_bytes as f64 / MB as f64,
+            self.usage.working_set as f64 / MB as f64,
+            self.usage.handles,
+            self.usage.gdi_objects,
+            self.usage.user_objects,
+        )?;
+        if let Some(gpu_memory) = self.gpu_memory {
+            write!(f, "GPU memory {}, ", gpu_memory)?;
+        }
+        write!(f, "{:.1} FPS", self.fps)
+    }
+}
+
+// Everything that has grown past its limit since the baseline.
+fn check(baseline: &Sample, sample: &Sample, soak: &SoakConfig) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    let memory_growth = growth(baseline.usage.private_bytes, sample.usage.private_bytes);
+    if memory_growth > u64::from(soak.max_memory_growth_mb) * MB {
+        problems.push(format!(
+            "private memory grew by {:.1} MB, over the limit of {} MB",
+            memory_growth as f64 / MB as f64,
+            soak.max_memory_growth_mb
+        ));
+    }
+
+    for (name, before, after) in [
+        ("handles", baseline.usage.handles, sample.usage.handles),
+        (
+            "GDI objects",
+            baseline.usage.gdi_objects,
+            sample.usage.gdi_objects,
+        ),
+        (
+            "USER objects",
+            baseline.usage.user_objects,
+            sample.usage.user_objects,
+        ),
+    ] {
+        let handle_growth = growth(before, after);
+        if handle_growth > soak.max_handle_growth {
+            problems.push(format!(
+                "{} grew by {}, over the limit of {}",
+                name, handle_growth, soak.max_handle_growth
+            ));
+        }
+    }
+
+    if let (Some(before), Some(after)) = (baseline.gpu_memory, sample.gpu_memory) {
+        let drop = growth(after.free_mb, before.free_mb);
+        if drop > soak.max_gpu_memory_drop_mb {
+            problems.push(format!(
+                "free video memory dropped by {} MB, over the limit of {} MB",
+                drop, soak.max_gpu_memory_drop_mb
+            ));
+        }
+    }
+
+    problems
+}
+
+fn growth<T: std::ops::Sub<Output = T> + Ord + Default>(before: T, after: T) -> T {
+    if after > before {
+        after - before
+    } else {
+        T::default()
+    }
+}
+
+fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    format!("{}h{:02}m{:02}s", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sample(private_mb: u64, handles: u32, free_mb: Option<u32>) -> Sample {
+        Sample {
+            elapsed: Duration::from_secs(3600),
+            usage: process::Usage {
+                working_set: private_mb * MB,
+                private_bytes: private_mb * MB,
+                handles,
+                gdi_objects: 20,
+                user_objects: 10,
+            },
+            gpu_memory: free_mb.map(|free_mb| gpu::Memory {
+                free_mb,
+                total_mb: None,
+            }),
+            fps: 60.0,
+        }
+    }
+
+    #[test]
+    fn it_flags_growth_past_the_limits() {
+        let soak = SoakConfig::default();
+        let baseline = sample(200, 300, Some(4000));
+
+        // Shrinking, or growing within the limits, is fine.
+        assert!(check(&baseline, &sample(180, 250, Some(4100)), &soak).is_empty());
+        assert!(check(&baseline, &sample(250, 390, Some(3900)), &soak).is_empty());
+
+        let problems = check(&baseline, &sample(300, 500, Some(3500)), &soak);
+        assert_eq!(problems.len(), 3);
+        assert!(problems[0].starts_with("private memory grew by 100.0 MB"));
+        assert!(problems[1].starts_with("handles grew by 200"));
+        assert!(problems[2].starts_with("free video memory dropped by 500 MB"));
+
+        // Not every driver reports the video memory.
+        assert!(check(&baseline, &sample(200, 300, None), &soak).is_empty());
+    }
+
+    #[test]
+    fn it_formats_the_elapsed_time() {
+        assert_eq!(
+            format_elapsed(Duration::from_secs(26 * 3600 + 61)),
+            "26h01m01s"
+        );
+    }
+}
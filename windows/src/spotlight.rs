@@ -0,0 +1,132 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path;
+use std::time::SystemTime;
+
+// Where Windows keeps the images Spotlight downloads for the lock screen, and on Windows 11, for
+// the desktop too. Both are under the local app data folder.
+const ASSET_CACHES: [&str; 2] = [
+    r"Packages\Microsoft.Windows.ContentDeliveryManager_cw5n1h2txyewy\LocalState\Assets",
+    r"Packages\MicrosoftWindows.Client.CBS_cw5n1h2txyewy\LocalCache\Microsoft\IrisService",
+];
+
+// The caches also hold app tiles, logos and portrait images for phones, which are all smaller than
+// a wallpaper.
+const MIN_WIDTH: u32 = 1280;
+
+/// The latest Spotlight image, looked up afresh each time, so that every start picks up the one
+/// Windows has moved on to. `None` if Spotlight is turned off or hasn’t downloaded anything yet.
+pub fn find() -> Option<path::PathBuf> {
+    let local_app_data = path::PathBuf::from(std::env::var_os("LOCALAPPDATA")?);
+    let mut candidates = Vec::new();
+    for cache in ASSET_CACHES {
+        collect_files(&local_app_data.join(cache), 1, &mut candidates);
+    }
+    latest_wallpaper(candidates)
+}
+
+/// The latest Spotlight image, copied out of the cache with a proper extension, since the images
+/// in the cache don’t have one and Flux goes by it to decode them.
+pub fn current_image() -> Option<path::PathBuf> {
+    let image_path = find()?;
+    copy_out(&image_path)
+        .map_err(|err| log::warn!("Failed to copy the Spotlight image: {}", err))
+        .ok()
+}
+
+// The newest file that decodes as a landscape image at least as wide as a wallpaper.
+fn latest_wallpaper(mut candidates: Vec<(path::PathBuf, SystemTime)>) -> Option<path::PathBuf> {
+    candidates.sort_by(|a, b| b.1.cmp(&a.1));
+    candidates
+        .into_iter()
+        .map(|(path, _)| path)
+        .find(|path| is_wallpaper(path))
+}
+
+fn is_wallpaper(path: &path::Path) -> bool {
+    image::io::Reader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .ok()
+        .and_then(|reader| reader.into_dimensions().ok())
+        .is_some_and(|(width, height)| width >= MIN_WIDTH && width > height)
+}
+
+// Gather the files in the directory along with when they were last modified, going down into the
+// subdirectories up to `depth` levels.
+fn collect_files(dir: &path::Path, depth: u32, files: &mut Vec<(path::PathBuf, SystemTime)>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.is_dir() {
+            if depth > 0 {
+                collect_files(&entry.path(), depth - 1, files);
+            }
+        } else if let Ok(modified) = metadata.modified() {
+            files.push((entry.path(), modified));
+        }
+    }
+}
+
+fn copy_out(image_path: &path::Path) -> Result<path::PathBuf, String> {
+    let format = image::io::Reader::open(image_path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|err| format!("Failed to read {}: {}", image_path.display(), err))?
+        .format()
+        .ok_or_else(|| format!("Failed to recognize {}", image_path.display()))?;
+
+    let mut hasher = DefaultHasher::new();
+    image_path.hash(&mut hasher);
+    let path = std::env::temp_dir().join(format!(
+        "flux-spotlight-{:016x}.{}",
+        hasher.finish(),
+        format.extensions_str().first().unwrap_or(&"jpg")
+    ));
+    if path.is_file() {
+        return Ok(path);
+    }
+
+    std::fs::copy(image_path, &path)
+        .map_err(|err| format!("Failed to copy {}: {}", image_path.display(), err))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn it_picks_the_newest_landscape_image() {
+        let dir = std::env::temp_dir().join(format!("flux-spotlight-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let save = |name: &str, width: u32, height: u32| {
+            let path = dir.join(name);
+            // Saved as PNG, but without the extension, like the images in the cache
+            image::RgbImage::new(width, height)
+                .save_with_format(&path, image::ImageFormat::Png)
+                .unwrap();
+            path
+        };
+        let older = save("older", 1920, 1080);
+        let newer = save("newer", 1920, 1080);
+        let portrait = save("portrait", 1080, 1920);
+        let logo = save("logo", 256, 128);
+
+        let time = |secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+        let candidates = vec![
+            (older, time(1)),
+            (newer.clone(), time(2)),
+            (portrait, time(3)),
+            (logo, time(4)),
+        ];
+        assert_eq!(latest_wallpaper(candidates), Some(newer));
+        assert_eq!(latest_wallpaper(Vec::new()), None);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,350 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::{fmt, fs, io::Write, path};
+
+#[cfg(feature = "sdl")]
+use sdl2::video::Window;
+use serde::Serialize;
+
+/// Why an instance has stopped rendering.
+// Without SDL, the winit backend only pauses the windows that are hidden.
+#[cfg_attr(not(feature = "sdl"), allow(dead_code))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PauseReason {
+    Minimized,
+    Occluded,
+    LowBattery,
+    // Paused by the user, from the command palette, or by the host application
+    Requested,
+    // The user is presenting or has something running full screen
+    DoNotDisturb,
+    // The displays were turned off by the blanking schedule
+    Blanked,
+}
+
+impl fmt::Display for PauseReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PauseReason::Minimized => write!(f, "minimized"),
+            PauseReason::Occluded => write!(f, "hidden behind other windows"),
+            PauseReason::LowBattery => write!(f, "low battery"),
+            PauseReason::Requested => write!(f, "on request"),
+            PauseReason::DoNotDisturb => write!(f, "do not disturb"),
+            PauseReason::Blanked => write!(f, "the displays are off"),
+        }
+    }
+}
+
+/// What made the screensaver exit.
+// Without SDL, there are no controllers or isolated monitors to exit on.
+#[cfg_attr(not(feature = "sdl"), allow(dead_code))]
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "reason", rename_all = "camelCase")]
+pub enum ExitReason {
+    Key {
+        key: String,
+    },
+    MouseButton {
+        button: String,
+    },
+    MouseMotion {
+        // The furthest the mouse moved along either axis in a single event, in pixels
+        distance: i32,
+    },
+    Controller {
+        device: u32,
+        button: String,
+    },
+    ControllerAxis {
+        device: u32,
+        // The stick or trigger, like `leftx` or `triggerright`
+        axis: String,
+    },
+    // The window was closed, or Windows asked the screensaver to quit
+    Quit,
+    // The screensaver was dismissed on another monitor, when each monitor runs in its own process
+    Coordinator,
+    Error {
+        message: String,
+    },
+}
+
+impl fmt::Display for ExitReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExitReason::Key { key } => write!(f, "the {} key", key),
+            ExitReason::MouseButton { button } => write!(f, "the {} mouse button", button),
+            ExitReason::MouseMotion { distance } => {
+                write!(f, "the mouse moving by {} pixels", distance)
+            }
+            ExitReason::Controller { device, button } => {
+                write!(f, "button {} on controller {}", button, device)
+            }
+            ExitReason::ControllerAxis { device, axis } => {
+                write!(f, "moving {} on controller {}", axis, device)
+            }
+            ExitReason::Quit => write!(f, "a request to quit"),
+            ExitReason::Coordinator => write!(f, "the screensaver exiting on another monitor"),
+            ExitReason::Error { message } => write!(f, "an error: {}", message),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ExitRecord<'a> {
+    // Seconds since the Unix epoch
+    at: u64,
+    #[serde(flatten)]
+    reason: &'a ExitReason,
+}
+
+/// Note down why the screensaver exited, in the log and as a line of JSON in the exit log. This
+/// is what to look at when the screensaver seems to close by itself.
+pub fn record_exit(exit_log: Option<&path::Path>, reason: &ExitReason) {
+    log::info!("Exiting because of {}", reason);
+
+    let Some(path) = exit_log else {
+        return;
+    };
+    let record = ExitRecord {
+        at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs()),
+        reason,
+    };
+    let line = serde_json::to_string(&record).expect("the exit reason serializes to JSON");
+
+    let result = fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)
+        .and_then(|mut file| writeln!(file, "{}", line));
+    if let Err(err) = result {
+        log::warn!(
+            "Failed to write to the exit log at {}: {}",
+            path.display(),
+            err
+        );
+    }
+}
+
+/// Frame timings and the current state of an instance, for the stats overlay.
+#[derive(Debug, Default)]
+pub struct Stats {
+    // The time between each of the most recent frames
+    frame_times: VecDeque<Duration>,
+    // How long each of the most recent frames took on the render thread
+    #[cfg(feature = "sdl")]
+    render_times: VecDeque<Duration>,
+    last_frame: Option<Instant>,
+    paused: Option<PauseReason>,
+}
+
+impl Stats {
+    // Average over about a second’s worth of frames at 60 FPS.
+    const SAMPLE_SIZE: usize = 60;
+
+    pub fn record_frame(&mut self, now: Instant) {
+        if let Some(last_frame) = self.last_frame {
+            if self.frame_times.len() == Self::SAMPLE_SIZE {
+                self.frame_times.pop_front();
+            }
+            self.frame_times
+                .push_back(now.saturating_duration_since(last_frame));
+        }
+
+        self.last_frame = Some(now);
+    }
+
+    /// Note how long the render thread took to draw and present a frame.
+    #[cfg(feature = "sdl")]
+    pub fn record_render_time(&mut self, render_time: Duration) {
+        if self.render_times.len() == Self::SAMPLE_SIZE {
+            self.render_times.pop_front();
+        }
+        self.render_times.push_back(render_time);
+    }
+
+    /// The average time between frames, if enough frames have been rendered to tell.
+    pub fn average_frame_time(&self) -> Option<Duration> {
+        let count = self.frame_times.len() as u32;
+        (count > 0).then(|| self.frame_times.iter().sum::<Duration>() / count)
+    }
+
+    #[cfg(feature = "sdl")]
+    pub fn average_render_time(&self) -> Option<Duration> {
+        let count = self.render_times.len() as u32;
+        (count > 0).then(|| self.render_times.iter().sum::<Duration>() / count)
+    }
+
+    /// The time between each of the most recent frames.
+    #[cfg(feature = "sdl")]
+    pub fn frame_times(&self) -> impl Iterator<Item = Duration> + '_ {
+        self.frame_times.iter().copied()
+    }
+
+    #[cfg(feature = "sdl")]
+    pub fn paused(&self) -> Option<PauseReason> {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, reason: Option<PauseReason>) {
+        self.paused = reason;
+
+        // Don’t count the pause as one very long frame.
+        self.frame_times.clear();
+        #[cfg(feature = "sdl")]
+        self.render_times.clear();
+        self.last_frame = None;
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(reason) = self.paused {
+            return write!(f, "Paused ({})", reason);
+        }
+
+        match self.average_frame_time() {
+            Some(frame_time) if !frame_time.is_zero() => write!(
+                f,
+                "{:.0} FPS, {:.1} ms",
+                1.0 / frame_time.as_secs_f64(),
+                frame_time.as_secs_f64() * 1000.0
+            ),
+            _ => write!(f, "Starting"),
+        }
+    }
+}
+
+/// The frame time that this fraction of frames, from 0 to 1, came in at or under.
+#[cfg(feature = "sdl")]
+pub fn percentile(frame_times: &mut [Duration], fraction: f64) -> Option<Duration> {
+    if frame_times.is_empty() {
+        return None;
+    }
+
+    frame_times.sort_unstable();
+    let rank = (fraction.clamp(0.0, 1.0) * frame_times.len() as f64).ceil() as usize;
+    Some(frame_times[rank.saturating_sub(1)])
+}
+
+/// Shows the stats of an instance in its window title.
+///
+/// This is the stats overlay for windowed mode, where the title bar is always in view and keeps
+/// the stats out of the way of the animation.
+#[cfg(feature = "sdl")]
+pub struct TitleOverlay {
+    title: String,
+    last_update: Option<Instant>,
+    last_paused: Option<PauseReason>,
+}
+
+#[cfg(feature = "sdl")]
+impl TitleOverlay {
+    const UPDATE_INTERVAL: Duration = Duration::from_millis(500);
+
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            last_update: None,
+            last_paused: None,
+        }
+    }
+
+    /// Refresh the title every so often, or right away if the instance was paused or resumed.
+    pub fn update(&mut self, window: &mut Window, stats: &Stats, now: Instant) {
+        let is_due = self.last_update.map_or(true, |last_update| {
+            now.duration_since(last_update) >= Self::UPDATE_INTERVAL
+        });
+        if !is_due && stats.paused() == self.last_paused {
+            return;
+        }
+
+        self.last_update = Some(now);
+        self.last_paused = stats.paused();
+
+        if let Err(err) = window.set_title(&format!("{} — {}", self.title, stats)) {
+            log::debug!("Failed to update the window title: {}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_averages_the_recent_frame_times() {
+        let now = Instant::now();
+        let at = |millis| now + Duration::from_millis(millis);
+        let mut stats = Stats::default();
+
+        stats.record_frame(at(0));
+        assert_eq!(stats.average_frame_time(), None);
+        assert_eq!(stats.to_string(), "Starting");
+
+        stats.record_frame(at(10));
+        stats.record_frame(at(30));
+        assert_eq!(stats.average_frame_time(), Some(Duration::from_millis(15)));
+        assert_eq!(stats.to_string(), "67 FPS, 15.0 ms");
+    }
+
+    #[cfg(feature = "sdl")]
+    #[test]
+    fn it_picks_the_frame_time_at_a_percentile() {
+        let mut frame_times = [16, 17, 15, 40, 16, 16, 17, 16, 16, 33]
+            .map(Duration::from_millis)
+            .to_vec();
+
+        assert_eq!(
+            percentile(&mut frame_times, 0.5),
+            Some(Duration::from_millis(16))
+        );
+        assert_eq!(
+            percentile(&mut frame_times, 0.9),
+            Some(Duration::from_millis(33))
+        );
+        assert_eq!(
+            percentile(&mut frame_times, 1.0),
+            Some(Duration::from_millis(40))
+        );
+        assert_eq!(percentile(&mut [], 0.5), None);
+    }
+
+    #[test]
+    fn it_records_what_the_exit_came_from() {
+        let record = ExitRecord {
+            at: 1_700_000_000,
+            reason: &ExitReason::MouseMotion { distance: 42 },
+        };
+        assert_eq!(
+            serde_json::to_string(&record).unwrap(),
+            r#"{"at":1700000000,"reason":"mouseMotion","distance":42}"#
+        );
+        assert_eq!(
+            ExitReason::Key {
+                key: "Space".to_string()
+            }
+            .to_string(),
+            "the Space key"
+        );
+    }
+
+    #[test]
+    fn it_ignores_time_spent_paused() {
+        let now = Instant::now();
+        let at = |millis| now + Duration::from_millis(millis);
+        let mut stats = Stats::default();
+
+        stats.record_frame(at(0));
+        stats.record_frame(at(20));
+        stats.set_paused(Some(PauseReason::Minimized));
+        assert_eq!(stats.to_string(), "Paused (minimized)");
+
+        stats.set_paused(None);
+        stats.record_frame(at(5000));
+        stats.record_frame(at(5020));
+        assert_eq!(stats.average_frame_time(), Some(Duration::from_millis(20)));
+    }
+}
@@ -1,4 +1,3 @@
-use std::collections::HashMap;
 use std::{cmp::Ordering, path};
 
 use ordered_float::OrderedFloat;
@@ -7,14 +6,35 @@ use winit::dpi::{PhysicalPosition, PhysicalSize};
 use crate::config;
 use crate::winit_compat::MonitorHandle;
 
+// The pixel density that a scale factor of 1 stands for: 96 DPI, in pixels per millimetre
+const REFERENCE_DENSITY: f64 = 96.0 / 25.4;
+
+// How far apart the physical densities of two monitors can be for the lines to still look the
+// same size on both. Different models rarely report exactly the same density.
+const PHYSICAL_DENSITY_TOLERANCE: f64 = 0.05;
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Surface {
     position: PhysicalPosition<i32>,
     size: PhysicalSize<u32>,
     scale_factor: OrderedFloat<f64>,
+    // The scale factor that matches the physical pixel density, if the monitors report their size
+    physical_scale_factor: Option<OrderedFloat<f64>>,
+    // How far the monitors are physically turned, for a surface that covers a single monitor or
+    // a video wall
+    rotation: config::Rotation,
+    // How many monitors across and down the surface is made up of, each turned on its own
+    tiles: (u32, u32),
     wallpaper: Option<path::PathBuf>,
+    // The monitors that make up the surface, if they leave parts of it uncovered
+    clip: Option<Vec<Area>>,
+    // The monitors that show the picture shrunk by some amount, with black bars around it
+    letterbox: Vec<(Area, OrderedFloat<f64>)>,
 }
 
+// The position and size of a monitor
+pub type Area = (PhysicalPosition<i32>, PhysicalSize<u32>);
+
 impl PartialOrd for Surface {
     fn partial_cmp(&self, other: &Surface) -> Option<Ordering> {
         Some(self.cmp(other))
@@ -41,9 +61,53 @@ impl Surface {
         self.scale_factor.into()
     }
     #[inline]
+    pub fn physical_scale_factor(&self) -> Option<f64> {
+        self.physical_scale_factor.map(Into::into)
+    }
+    #[inline]
+    pub fn rotation(&self) -> config::Rotation {
+        self.rotation
+    }
+    /// How many monitors across and down the surface is made up of. Each of them is turned by the
+    /// rotation on its own, like the monitors of a video wall.
+    #[inline]
+    pub fn tiles(&self) -> (u32, u32) {
+        self.tiles
+    }
+    #[inline]
     pub fn wallpaper(&self) -> &Option<path::PathBuf> {
         &self.wallpaper
     }
+    /// The parts of the surface to show, if it doesn’t show all of it. A surface spanning monitors
+    /// that don’t fill its bounding box would otherwise draw over the gaps between them, and over
+    /// any other monitor that sits in between.
+    #[inline]
+    pub fn clip(&self) -> Option<&[Area]> {
+        self.clip.as_deref()
+    }
+    /// The monitors that show the picture shrunk, with black bars around it, and by how much. In
+    /// a span sized for its densest monitor, the lines would otherwise come out thicker on the
+    /// others.
+    pub fn letterbox(&self) -> Vec<(Area, f64)> {
+        self.letterbox
+            .iter()
+            .map(|(area, scale)| (*area, scale.into_inner()))
+            .collect()
+    }
+
+    pub fn contains(&self, point: PhysicalPosition<i32>) -> bool {
+        let bottom_right = self.bottom_right();
+        (self.position.x..bottom_right.x).contains(&point.x)
+            && (self.position.y..bottom_right.y).contains(&point.y)
+    }
+
+    // The corner just past the bottom-right edge of the surface.
+    fn bottom_right(&self) -> PhysicalPosition<i32> {
+        PhysicalPosition::new(
+            self.position.x + self.size.width as i32,
+            self.position.y + self.size.height as i32,
+        )
+    }
 }
 
 impl Surface {
@@ -52,51 +116,261 @@ impl Surface {
             position: monitor.position(),
             size: monitor.size(),
             scale_factor: monitor.scale_factor().into(),
+            physical_scale_factor: physical_scale_factor(monitor).map(Into::into),
+            rotation: config::Rotation::None,
+            tiles: (1, 1),
             wallpaper: wallpaper.clone(),
+            clip: None,
+            letterbox: Vec::new(),
         }
     }
 
+    // Grow the surface to the bounding box of both surfaces, clipped to the monitors in either.
     fn merge(&mut self, surface: &Self) {
-        // if self.scale_factor != surface.scale_factor {
-        //     return None;
-        // }
+        let mut areas = self.areas();
+        areas.extend(surface.areas());
 
+        let (a, b) = (self.bottom_right(), surface.bottom_right());
         let top_left = PhysicalPosition::new(
             self.position.x.min(surface.position.x),
             self.position.y.min(surface.position.y),
         );
-
-        let bottom_right = PhysicalPosition::new(
-            (self.position.x + self.size.width as i32)
-                .max(surface.position.x + surface.size.width as i32),
-            (self.position.y + self.size.height as i32)
-                .max(surface.position.y + surface.size.height as i32),
-        );
+        let bottom_right = PhysicalPosition::new(a.x.max(b.x), a.y.max(b.y));
 
         self.position = top_left;
         self.size = PhysicalSize::new(
             top_left.x.abs_diff(bottom_right.x),
             top_left.y.abs_diff(bottom_right.y),
         );
+        // A single simulation can only have one density, so go with the densest monitor. The lines
+        // then come out a bit thicker on the others, rather than blurry on the densest one.
+        self.physical_scale_factor = self
+            .physical_scale_factor
+            .max(surface.physical_scale_factor);
+        // Turning the whole surface wouldn’t line up with the monitors that make it up.
+        self.rotation = config::Rotation::None;
+        self.tiles = (1, 1);
+
+        // Monitors don’t overlap, so they only cover the surface if their areas add up to it.
+        let covered = areas.iter().map(|(_, size)| area(*size)).sum::<u64>();
+        self.clip = (covered < area(self.size)).then_some(areas);
+    }
+
+    // Size the lines for the densest of the monitors that make up the surface, and shrink the
+    // picture on the others by as much as they’re less dense.
+    fn letterbox_to_densest(&mut self, monitors: &[(MonitorHandle, Option<path::PathBuf>)]) {
+        let spanned = monitors
+            .iter()
+            .map(|(monitor, _)| monitor)
+            .filter(|monitor| match &self.clip {
+                Some(areas) => areas.contains(&(monitor.position(), monitor.size())),
+                None => self.contains(monitor.position()),
+            })
+            .collect::<Vec<_>>();
+        let Some(densest) = spanned
+            .iter()
+            .map(|monitor| OrderedFloat(monitor.scale_factor()))
+            .max()
+        else {
+            return;
+        };
+
+        self.scale_factor = densest;
+        self.letterbox = spanned
+            .iter()
+            .filter(|monitor| monitor.scale_factor() < densest.into_inner())
+            .map(|monitor| {
+                let scale = monitor.scale_factor() / densest.into_inner();
+                ((monitor.position(), monitor.size()), OrderedFloat(scale))
+            })
+            .collect();
+    }
+
+    // The monitors that make up the surface.
+    fn areas(&self) -> Vec<Area> {
+        self.clip
+            .clone()
+            .unwrap_or_else(|| vec![(self.position, self.size)])
+    }
+
+    // Whether the lines would come out the same physical size on both surfaces if they shared a
+    // simulation.
+    fn has_physical_density_of(&self, other: &Self) -> bool {
+        match (self.physical_scale_factor, other.physical_scale_factor) {
+            (Some(a), Some(b)) => {
+                (a.into_inner() / b.into_inner() - 1.0).abs() <= PHYSICAL_DENSITY_TOLERANCE
+            }
+            (a, b) => a == b,
+        }
+    }
+
+    // Join two surfaces that line up edge to edge. Surfaces that would leave a gap, or overlap
+    // with each other, aren’t joined.
+    fn join(&self, surface: &Self) -> Option<Self> {
+        let (a, b) = (self.bottom_right(), surface.bottom_right());
+        let side_by_side = self.position.y == surface.position.y
+            && a.y == b.y
+            && (a.x == surface.position.x || b.x == self.position.x);
+        let stacked = self.position.x == surface.position.x
+            && a.x == b.x
+            && (a.y == surface.position.y || b.y == self.position.y);
+        if !side_by_side && !stacked {
+            return None;
+        }
+
+        let mut joined = self.clone();
+        joined.merge(surface);
+        Some(joined)
+    }
+}
+
+// Compare the diagonals, which don’t change when the monitor is rotated. The physical size is
+// always reported in the monitor’s native orientation.
+fn physical_scale_factor(monitor: &MonitorHandle) -> Option<f64> {
+    let physical_size = monitor.physical_size()?;
+    let diagonal_mm = (physical_size.width as f64).hypot(physical_size.height as f64);
+    if diagonal_mm == 0.0 {
+        return None;
+    }
+
+    let size = monitor.size();
+    let diagonal_px = (size.width as f64).hypot(size.height as f64);
+    Some(diagonal_px / diagonal_mm / REFERENCE_DENSITY)
+}
+
+// Span each group of rotated monitors into a video wall, if they’re all turned the same way and
+// make up a grid of monitors of the same size. Returns the walls, along with the rotated
+// monitors left over.
+fn video_walls(
+    monitors: &[(MonitorHandle, Option<path::PathBuf>)],
+    span_groups: &[Vec<config::MonitorRef>],
+    rotation: impl Fn(&MonitorHandle) -> config::Rotation,
+) -> (Vec<Surface>, Vec<(MonitorHandle, Option<path::PathBuf>)>) {
+    let mut walls = Vec::new();
+    let mut rest = monitors.to_vec();
+    for group in span_groups {
+        let (grouped, ungrouped): (Vec<_>, Vec<_>) = rest
+            .into_iter()
+            .partition(|(monitor, _)| group.iter().any(|monitor_ref| monitor_ref.matches(monitor)));
+        rest = ungrouped;
+
+        match video_wall(&grouped, &rotation) {
+            Some(wall) => walls.push(wall),
+            None => {
+                if grouped.len() > 1 {
+                    log::info!(
+                        "Turning the rotated monitors {:?} on their own, since they don’t make \
+                         up a video wall",
+                        grouped
+                            .iter()
+                            .map(|(monitor, _)| monitor)
+                            .collect::<Vec<_>>()
+                    );
+                }
+                rest.extend(grouped);
+            }
+        }
     }
+
+    (walls, rest)
 }
 
-fn from_monitors(monitors: &[(MonitorHandle, Option<path::PathBuf>)]) -> Vec<Surface> {
+fn video_wall(
+    monitors: &[(MonitorHandle, Option<path::PathBuf>)],
+    rotation: impl Fn(&MonitorHandle) -> config::Rotation,
+) -> Option<Surface> {
+    let ((first, wallpaper), rest) = monitors.split_first()?;
+    if rest.is_empty() {
+        return None;
+    }
+    let (turn, size) = (rotation(first), first.size());
+    if rest
+        .iter()
+        .any(|(monitor, _)| rotation(monitor) != turn || monitor.size() != size)
+    {
+        return None;
+    }
+
+    let mut wall = Surface::from_monitor(first, wallpaper);
+    for (monitor, wallpaper) in rest {
+        wall.merge(&Surface::from_monitor(monitor, wallpaper));
+    }
+    // The monitors have to cover the whole wall, lined up in a grid.
+    let is_grid = monitors.iter().all(|(monitor, _)| {
+        let x = monitor.position().x - wall.position.x;
+        let y = monitor.position().y - wall.position.y;
+        x % size.width as i32 == 0 && y % size.height as i32 == 0
+    });
+    if wall.clip.is_some() || !is_grid {
+        return None;
+    }
+
+    wall.rotation = turn;
+    wall.tiles = (wall.size.width / size.width, wall.size.height / size.height);
+    Some(wall)
+}
+
+fn from_monitors(
+    monitors: &[(MonitorHandle, Option<path::PathBuf>)],
+    rotation: impl Fn(&MonitorHandle) -> config::Rotation,
+) -> Vec<Surface> {
     monitors
         .iter()
-        .map(|(monitor, wallpaper)| Surface::from_monitor(monitor, wallpaper))
+        .map(|(monitor, wallpaper)| Surface {
+            rotation: rotation(monitor),
+            tiles: (1, 1),
+            ..Surface::from_monitor(monitor, wallpaper)
+        })
         .collect()
 }
 
-fn extend(surfaces: Vec<Surface>) -> Vec<Surface> {
-    let mut grouping: HashMap<PhysicalSize<u32>, Surface> = HashMap::new();
-    for surface in surfaces.into_iter() {
-        grouping
-            .entry(surface.size)
-            .and_modify(|existing_surface| existing_surface.merge(&surface))
-            .or_insert_with(|| surface);
+// Span adjacent monitors with matching dimensions, and matching scale factors unless mixing them
+// is allowed. When the lines are sized by the physical density, only monitors with the same
+// physical density are spanned, so that each of them keeps its own.
+//
+// Monitors are only joined if they line up edge to edge, so a surface never covers the gaps
+// between monitors, or a monitor of a different size that sits in between.
+fn extend(
+    surfaces: Vec<Surface>,
+    mixed_dpi: config::MixedDpiSpan,
+    uniform_physical_density: bool,
+) -> Vec<Surface> {
+    // Each surface, along with the size of the monitors it spans
+    let mut grouping = surfaces
+        .into_iter()
+        .map(|surface| (surface.size, surface))
+        .collect::<Vec<(PhysicalSize<u32>, Surface)>>();
+
+    // Keep joining pairs of surfaces until none of them line up any more.
+    'join: loop {
+        for i in 0..grouping.len() {
+            for j in i + 1..grouping.len() {
+                let ((size_a, a), (size_b, b)) = (&grouping[i], &grouping[j]);
+                if size_a != size_b {
+                    continue;
+                }
+                if mixed_dpi == config::MixedDpiSpan::Separate && a.scale_factor != b.scale_factor {
+                    continue;
+                }
+                if uniform_physical_density && !a.has_physical_density_of(b) {
+                    continue;
+                }
+
+                if let Some(joined) = a.join(b) {
+                    grouping[i].1 = joined;
+                    grouping.remove(j);
+                    continue 'join;
+                }
+            }
+        }
+
+        break;
     }
-    let mut extended_surfaces = grouping.into_values().collect::<Vec<Surface>>();
+
+    let mut extended_surfaces = grouping
+        .into_iter()
+        .map(|(_, surface)| surface)
+        .collect::<Vec<Surface>>();
     extended_surfaces.sort();
     extended_surfaces
 }
@@ -114,23 +388,447 @@ fn fill(surfaces: Vec<Surface>) -> Vec<Surface> {
     }
 }
 
-pub fn build(
+// Span the monitors in each of the given groups, then group the rest automatically.
+fn span(
     monitors: &[(MonitorHandle, Option<path::PathBuf>)],
-    fill_mode: config::FillMode,
+    span_groups: &[Vec<config::MonitorRef>],
+    mixed_dpi: config::MixedDpiSpan,
+    uniform_physical_density: bool,
 ) -> Vec<Surface> {
-    let surfaces = from_monitors(monitors);
+    let in_group = |group: &[config::MonitorRef], monitor: &MonitorHandle| {
+        group.iter().any(|monitor_ref| monitor_ref.matches(monitor))
+    };
+
+    let mut surfaces = span_groups
+        .iter()
+        .flat_map(|group| {
+            let grouped = monitors
+                .iter()
+                .filter(|(monitor, _)| in_group(group, monitor))
+                .map(|(monitor, wallpaper)| Surface::from_monitor(monitor, wallpaper))
+                .collect();
+            if uniform_physical_density {
+                return fill_by(grouped, Surface::has_physical_density_of);
+            }
+            match mixed_dpi {
+                config::MixedDpiSpan::First | config::MixedDpiSpan::Densest => fill(grouped),
+                config::MixedDpiSpan::Separate => {
+                    fill_by(grouped, |a, b| a.scale_factor == b.scale_factor)
+                }
+            }
+        })
+        .collect::<Vec<Surface>>();
+
+    let ungrouped = monitors
+        .iter()
+        .filter(|(monitor, _)| !span_groups.iter().any(|group| in_group(group, monitor)))
+        .map(|(monitor, wallpaper)| Surface::from_monitor(monitor, wallpaper))
+        .collect();
+    surfaces.extend(extend(ungrouped, mixed_dpi, uniform_physical_density));
+
+    // Sizing the lines by the physical density already makes them the same size everywhere.
+    if mixed_dpi == config::MixedDpiSpan::Densest && !uniform_physical_density {
+        for surface in surfaces.iter_mut() {
+            surface.letterbox_to_densest(monitors);
+        }
+    }
+
+    surfaces.sort();
+    surfaces
+}
 
+// Span the monitors that share a density. If that would cover a monitor with another density
+// sitting in between, the monitors are all left on their own instead.
+fn fill_by(
+    surfaces: Vec<Surface>,
+    same_density: impl Fn(&Surface, &Surface) -> bool,
+) -> Vec<Surface> {
+    let mut by_density: Vec<Vec<Surface>> = Vec::new();
+    for surface in surfaces {
+        match by_density
+            .iter_mut()
+            .find(|group| same_density(&group[0], &surface))
+        {
+            Some(group) => group.push(surface),
+            None => by_density.push(vec![surface]),
+        }
+    }
+    if by_density.len() > 1 {
+        log::info!("Spanning the monitors with different densities separately");
+    }
+
+    let filled = by_density
+        .iter()
+        .flat_map(|group| fill(group.clone()))
+        .collect::<Vec<Surface>>();
+    let is_covered = filled
+        .iter()
+        .enumerate()
+        .any(|(index, a)| filled[index + 1..].iter().any(|b| overlaps(a, b)));
+    match is_covered {
+        true => by_density.into_iter().flatten().collect(),
+        false => filled,
+    }
+}
+
+// Lay out the surfaces by hand. Monitors that aren’t connected are skipped, along with any
+// surface that’s left without monitors, but a layout that doesn’t line up with the monitors that
+// are connected is refused.
+fn custom(
+    monitors: &[(MonitorHandle, Option<path::PathBuf>)],
+    layout: &[config::CustomSurface],
+    rotation: impl Fn(&MonitorHandle) -> config::Rotation,
+) -> Result<Vec<Surface>, String> {
+    let mut assigned: Vec<&MonitorHandle> = Vec::new();
+    let mut surfaces: Vec<Surface> = Vec::new();
+
+    for (index, planned) in layout.iter().enumerate() {
+        let name = format!("Surface {}", index + 1);
+        if planned.width == 0 || planned.height == 0 {
+            return Err(format!("{} has no area", name));
+        }
+        let reaches = |start: i32, length: u32| {
+            i32::try_from(length)
+                .ok()
+                .and_then(|length| start.checked_add(length))
+        };
+        if reaches(planned.x, planned.width).is_none()
+            || reaches(planned.y, planned.height).is_none()
+        {
+            return Err(format!("{} reaches past the edge of the screen", name));
+        }
+
+        let bounds = Surface {
+            position: PhysicalPosition::new(planned.x, planned.y),
+            size: PhysicalSize::new(planned.width, planned.height),
+            scale_factor: OrderedFloat(1.0),
+            physical_scale_factor: None,
+            rotation: config::Rotation::None,
+            tiles: (1, 1),
+            wallpaper: None,
+            clip: None,
+            letterbox: Vec::new(),
+        };
+        let covered = monitors
+            .iter()
+            .filter(|(monitor, _)| {
+                planned
+                    .monitors
+                    .iter()
+                    .any(|monitor_ref| monitor_ref.matches(monitor))
+            })
+            .collect::<Vec<_>>();
+        let Some(((first, wallpaper), rest)) = covered.split_first() else {
+            log::debug!(
+                "Skipping {} of the custom layout, since none of its monitors are connected",
+                name
+            );
+            continue;
+        };
+
+        for (monitor, _) in covered.iter() {
+            if !overlaps(&bounds, &Surface::from_monitor(monitor, &None)) {
+                return Err(format!("{} doesn’t reach {:?}", name, monitor));
+            }
+            if assigned.contains(&monitor) {
+                return Err(format!("{:?} is in more than one surface", monitor));
+            }
+            assigned.push(monitor);
+        }
+        if surfaces.iter().any(|other| overlaps(&bounds, other)) {
+            return Err(format!("{} overlaps another surface", name));
+        }
+
+        // Take the density from the monitors, the same way as spanning them would.
+        let mut surface = Surface::from_monitor(first, wallpaper);
+        for (monitor, wallpaper) in rest.iter() {
+            surface.merge(&Surface::from_monitor(monitor, wallpaper));
+        }
+        surface.position = bounds.position;
+        surface.size = bounds.size;
+        // The surface is laid out by hand, and is already kept clear of the others.
+        surface.clip = None;
+        // A single monitor can be turned, but like a span, turning several wouldn’t line up.
+        if rest.is_empty() {
+            surface.rotation = rotation(first);
+        }
+        surfaces.push(surface);
+    }
+
+    let unassigned = monitors
+        .iter()
+        .filter(|(monitor, _)| !assigned.contains(&monitor))
+        .cloned()
+        .collect::<Vec<_>>();
+    surfaces.extend(from_monitors(&unassigned, rotation));
+
+    surfaces.sort();
+    Ok(surfaces)
+}
+
+fn area(size: PhysicalSize<u32>) -> u64 {
+    size.width as u64 * size.height as u64
+}
+
+fn overlaps(a: &Surface, b: &Surface) -> bool {
+    let (a_end, b_end) = (a.bottom_right(), b.bottom_right());
+    a.position.x < b_end.x
+        && b.position.x < a_end.x
+        && a.position.y < b_end.y
+        && b.position.y < a_end.y
+}
+
+/// How to make surfaces out of the monitors.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Layout<'a> {
+    pub fill_mode: config::FillMode,
+    /// Monitors to span together, before grouping the rest automatically
+    pub span_groups: &'a [Vec<config::MonitorRef>],
+    pub rotations: &'a [config::MonitorRotation],
+    pub custom_layout: &'a [config::CustomSurface],
+    pub mixed_dpi: config::MixedDpiSpan,
+    /// Keep monitors with different physical densities apart, since the lines are sized by it
+    pub uniform_physical_density: bool,
+}
+
+impl<'a> Layout<'a> {
+    /// The layout in the settings.
+    pub fn from_config(config: &'a config::WindowsConfig) -> Self {
+        Self {
+            fill_mode: config.fill_mode,
+            span_groups: &config.span_groups,
+            rotations: &config.monitor_rotations,
+            custom_layout: &config.custom_layout,
+            mixed_dpi: config.mixed_dpi_span,
+            uniform_physical_density: config.uniform_physical_density,
+        }
+    }
+}
+
+pub fn build(monitors: &[(MonitorHandle, Option<path::PathBuf>)], layout: &Layout) -> Vec<Surface> {
     use config::FillMode;
-    match fill_mode {
-        FillMode::None => surfaces,
-        FillMode::Span => extend(surfaces),
-        FillMode::Fill => fill(surfaces),
+    let rotation = |monitor: &MonitorHandle| {
+        layout
+            .rotations
+            .iter()
+            .find(|rotation| rotation.monitor.matches(monitor))
+            .map_or(config::Rotation::None, |rotation| rotation.rotation)
+    };
+
+    match layout.fill_mode {
+        FillMode::None => from_monitors(monitors, rotation),
+        // A rotated monitor is turned on its own, so it’s left out of the automatic spans. Rotated
+        // monitors in a span group make up a video wall instead, with each monitor turned.
+        FillMode::Span => {
+            let (rotated, unrotated): (Vec<_>, Vec<_>) = monitors
+                .iter()
+                .cloned()
+                .partition(|(monitor, _)| rotation(monitor) != config::Rotation::None);
+            let mut surfaces = span(
+                &unrotated,
+                layout.span_groups,
+                layout.mixed_dpi,
+                layout.uniform_physical_density,
+            );
+            let (walls, rotated) = video_walls(&rotated, layout.span_groups, rotation);
+            surfaces.extend(walls);
+            surfaces.extend(from_monitors(&rotated, rotation));
+            surfaces.sort();
+            surfaces
+        }
+        FillMode::Fill => fill(from_monitors(monitors, rotation)),
+        FillMode::Custom => {
+            custom(monitors, layout.custom_layout, &rotation).unwrap_or_else(|err| {
+                log::warn!(
+                    "The custom layout doesn’t fit the monitors ({}). Showing each monitor on \
+                     its own.",
+                    err
+                );
+                from_monitors(monitors, rotation)
+            })
+        }
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use proptest::prelude::*;
+
+    type Monitors = Vec<(MonitorHandle, Option<path::PathBuf>)>;
+
+    fn covers(surface: &Surface, monitor: &MonitorHandle) -> bool {
+        let position = monitor.position();
+        let size = monitor.size();
+        surface.contains(position)
+            && surface.contains(PhysicalPosition::new(
+                position.x + size.width as i32 - 1,
+                position.y + size.height as i32 - 1,
+            ))
+    }
+
+    // Columns of one or two stacked monitors of mixed sizes and vertical offsets. The whole layout
+    // is shifted so that any of the monitors can be the primary one, at the origin, leaving the
+    // others at negative coordinates.
+    fn monitor_layouts() -> impl Strategy<Value = Monitors> {
+        let size = prop::sample::select(vec![
+            PhysicalSize::<u32>::new(1920, 1080),
+            PhysicalSize::new(2560, 1440),
+            PhysicalSize::new(1280, 1024),
+            PhysicalSize::new(1080, 1920),
+        ]);
+        let offset = prop::sample::select(vec![0, -360, 360, 1080]);
+        let column = (size.clone(), offset, prop::option::of(size));
+
+        prop::collection::vec(column, 1..5)
+            .prop_map(|columns| {
+                let mut monitors = Vec::new();
+                let mut x = 0;
+                for (top, y, bottom) in columns {
+                    monitors.push((PhysicalPosition::new(x, y), top));
+                    if let Some(bottom) = bottom {
+                        monitors.push((PhysicalPosition::new(x, y + top.height as i32), bottom));
+                    }
+                    x += top.width.max(bottom.map_or(0, |bottom| bottom.width)) as i32;
+                }
+                monitors
+            })
+            .prop_flat_map(|monitors| {
+                let primary = 0..monitors.len();
+                (Just(monitors), primary)
+            })
+            .prop_map(|(monitors, primary)| {
+                let origin = monitors[primary].0;
+                monitors
+                    .into_iter()
+                    .enumerate()
+                    .map(|(id, (position, size))| {
+                        let position =
+                            PhysicalPosition::new(position.x - origin.x, position.y - origin.y);
+                        (MonitorHandle::new(id as u32, position, size, 1.0), None)
+                    })
+                    .collect()
+            })
+    }
+
+    fn layout(fill_mode: config::FillMode) -> Layout<'static> {
+        Layout {
+            fill_mode,
+            ..Default::default()
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn it_gives_each_monitor_its_own_surface(monitors in monitor_layouts()) {
+            let surfaces = build(&monitors, &layout(config::FillMode::None));
+
+            prop_assert_eq!(surfaces.len(), monitors.len());
+            for (surface, (monitor, _)) in surfaces.iter().zip(&monitors) {
+                prop_assert_eq!(surface.position(), monitor.position());
+                prop_assert_eq!(surface.size(), monitor.size());
+            }
+        }
+
+        #[test]
+        fn it_spans_surfaces_that_exactly_cover_their_monitors(monitors in monitor_layouts()) {
+            let surfaces = build(&monitors, &layout(config::FillMode::Span));
+
+            for (monitor, _) in monitors.iter() {
+                let covering = surfaces.iter().filter(|surface| covers(surface, monitor)).count();
+                prop_assert_eq!(covering, 1, "{:?} is covered by {} surfaces", monitor, covering);
+            }
+
+            // With every monitor inside exactly one surface, matching areas leave no room for
+            // gaps, or for overlapping other monitors.
+            for surface in surfaces.iter() {
+                let covered = monitors
+                    .iter()
+                    .filter(|(monitor, _)| covers(surface, monitor))
+                    .map(|(monitor, _)| area(monitor.size()))
+                    .sum::<u64>();
+                prop_assert_eq!(area(surface.size()), covered, "{:?} covers a gap", surface);
+            }
+        }
+
+        #[test]
+        fn it_fills_the_bounding_box_of_all_monitors(monitors in monitor_layouts()) {
+            let surfaces = build(&monitors, &layout(config::FillMode::Fill));
+
+            prop_assert_eq!(surfaces.len(), 1);
+            for (monitor, _) in monitors.iter() {
+                prop_assert!(covers(&surfaces[0], monitor));
+            }
+        }
+    }
+
+    #[test]
+    fn it_does_not_span_over_a_monitor_of_a_different_size() {
+        // 1080p + 1440p + 1080p, with the primary monitor in the middle
+        let monitors = [
+            (
+                MonitorHandle::new(1, (-1920, 0).into(), (1920, 1080).into(), 1.0),
+                None,
+            ),
+            (
+                MonitorHandle::new(0, (0, 0).into(), (2560, 1440).into(), 1.0),
+                None,
+            ),
+            (
+                MonitorHandle::new(2, (2560, 0).into(), (1920, 1080).into(), 1.0),
+                None,
+            ),
+        ];
+
+        assert_eq!(
+            build(
+                &monitors,
+                &Layout {
+                    fill_mode: config::FillMode::Span,
+                    ..Default::default()
+                }
+            )
+            .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn it_spans_a_grid_of_monitors_left_of_the_primary() {
+        let monitor = |id, x, y| {
+            (
+                MonitorHandle::new(id, (x, y).into(), (2560, 1440).into(), 1.0),
+                None,
+            )
+        };
+        let monitors = [
+            monitor(0, 0, 0),
+            monitor(1, -2560, 0),
+            monitor(2, -2560, -1440),
+            monitor(3, 0, -1440),
+        ];
+
+        assert_eq!(
+            build(
+                &monitors,
+                &Layout {
+                    fill_mode: config::FillMode::Span,
+                    ..Default::default()
+                }
+            ),
+            vec![Surface {
+                position: (-2560, -1440).into(),
+                size: (5120, 2880).into(),
+                scale_factor: 1.0.into(),
+                physical_scale_factor: None,
+                rotation: config::Rotation::None,
+                tiles: (1, 1),
+                wallpaper: None,
+                clip: None,
+                letterbox: Vec::new(),
+            }]
+        );
+    }
 
     #[test]
     fn it_does_not_extend_two_different_displays() {
@@ -138,34 +836,486 @@ mod test {
             position: (0, 0).into(),
             size: (3360, 2100).into(),
             scale_factor: 1.0.into(),
+            physical_scale_factor: None,
+            rotation: config::Rotation::None,
+            tiles: (1, 1),
             wallpaper: None,
+            clip: None,
+            letterbox: Vec::new(),
         };
         let display1 = Surface {
             position: (3360, 0).into(),
             size: (2560, 1440).into(),
             scale_factor: 1.0.into(),
+            physical_scale_factor: None,
+            rotation: config::Rotation::None,
+            tiles: (1, 1),
             wallpaper: None,
+            clip: None,
+            letterbox: Vec::new(),
         };
 
         assert_eq!(
-            extend(vec![display0.clone(), display1.clone()]),
+            extend(
+                vec![display0.clone(), display1.clone()],
+                config::MixedDpiSpan::Densest,
+                false
+            ),
             vec![display0, display1]
         );
     }
 
+    #[test]
+    fn it_contains_points_within_its_bounds() {
+        let display = Surface {
+            position: (-1920, 0).into(),
+            size: (1920, 1080).into(),
+            scale_factor: 1.0.into(),
+            physical_scale_factor: None,
+            rotation: config::Rotation::None,
+            tiles: (1, 1),
+            wallpaper: None,
+            clip: None,
+            letterbox: Vec::new(),
+        };
+
+        assert!(display.contains((-1920, 0).into()));
+        assert!(display.contains((-1, 1079).into()));
+        assert!(!display.contains((0, 0).into()));
+        assert!(!display.contains((-1, 1080).into()));
+    }
+
+    #[test]
+    fn it_spans_explicit_groups_before_matching_sizes() {
+        let monitor = |id, x| {
+            (
+                MonitorHandle::new(id, (x, 0).into(), (1920, 1080).into(), 1.0),
+                None,
+            )
+        };
+        let monitors = [monitor(0, 0), monitor(1, 1920), monitor(2, 3840)];
+        let surface = |x, width| Surface {
+            position: (x, 0).into(),
+            size: (width, 1080).into(),
+            scale_factor: 1.0.into(),
+            physical_scale_factor: None,
+            rotation: config::Rotation::None,
+            tiles: (1, 1),
+            wallpaper: None,
+            clip: None,
+            letterbox: Vec::new(),
+        };
+
+        assert_eq!(
+            build(
+                &monitors,
+                &Layout {
+                    fill_mode: config::FillMode::Span,
+                    ..Default::default()
+                }
+            ),
+            vec![surface(0, 5760)]
+        );
+        // The third monitor is left on its own.
+        assert_eq!(
+            build(
+                &monitors,
+                &Layout {
+                    fill_mode: config::FillMode::Span,
+                    span_groups: &[vec![
+                        config::MonitorRef::Index(0),
+                        config::MonitorRef::Index(1)
+                    ]],
+                    ..Default::default()
+                }
+            ),
+            vec![surface(0, 3840), surface(3840, 1920)]
+        );
+        // Groups only apply to span mode.
+        assert_eq!(
+            build(
+                &monitors,
+                &Layout {
+                    fill_mode: config::FillMode::None,
+                    span_groups: &[vec![
+                        config::MonitorRef::Index(0),
+                        config::MonitorRef::Index(1)
+                    ]],
+                    ..Default::default()
+                }
+            )
+            .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn it_clips_a_group_to_its_monitors() {
+        // Two monitors side by side, with a third below the first one
+        let monitors = [
+            (
+                MonitorHandle::new(0, (0, 0).into(), (1920, 1080).into(), 1.0),
+                None,
+            ),
+            (
+                MonitorHandle::new(1, (1920, 0).into(), (1920, 1080).into(), 1.0),
+                None,
+            ),
+            (
+                MonitorHandle::new(2, (0, 1080).into(), (1920, 1080).into(), 1.0),
+                None,
+            ),
+        ];
+        let surfaces = build(
+            &monitors,
+            &Layout {
+                fill_mode: config::FillMode::Span,
+                span_groups: &[vec![
+                    config::MonitorRef::Index(0),
+                    config::MonitorRef::Index(2),
+                ]],
+                ..Default::default()
+            },
+        );
+
+        // A column of two monitors fills its bounding box.
+        assert_eq!(surfaces.len(), 2);
+        assert_eq!(surfaces[0].size(), (1920, 2160).into());
+        assert_eq!(surfaces[0].clip(), None);
+
+        let surfaces = build(
+            &monitors,
+            &Layout {
+                fill_mode: config::FillMode::Span,
+                span_groups: &[(0..3).map(config::MonitorRef::Index).collect()],
+                ..Default::default()
+            },
+        );
+
+        // The L shape leaves the bottom right corner uncovered.
+        assert_eq!(surfaces.len(), 1);
+        assert_eq!(surfaces[0].size(), (3840, 2160).into());
+        assert_eq!(
+            surfaces[0].clip(),
+            Some(
+                &[
+                    ((0, 0).into(), (3840, 1080).into()),
+                    ((0, 1080).into(), (1920, 1080).into()),
+                ][..]
+            )
+        );
+    }
+
+    #[test]
+    fn it_spans_mixed_scale_factors_as_configured() {
+        let monitors = |scale_factors: [f64; 3]| {
+            scale_factors
+                .iter()
+                .enumerate()
+                .map(|(id, &scale_factor)| {
+                    let position = (id as i32 * 1920, 0).into();
+                    (
+                        MonitorHandle::new(id as u32, position, (1920, 1080).into(), scale_factor),
+                        None,
+                    )
+                })
+                .collect::<Monitors>()
+        };
+        let spans = |monitors: &Monitors, span_groups: &[Vec<config::MonitorRef>], mixed_dpi| {
+            build(
+                monitors,
+                &Layout {
+                    fill_mode: config::FillMode::Span,
+                    span_groups,
+                    mixed_dpi,
+                    ..Default::default()
+                },
+            )
+            .iter()
+            .map(|surface| (surface.size().width, surface.scale_factor()))
+            .collect::<Vec<_>>()
+        };
+        let everything = [(0..3).map(config::MonitorRef::Index).collect::<Vec<_>>()];
+
+        let laptop_on_the_right = monitors([1.0, 1.0, 1.5]);
+        for span_groups in [&[][..], &everything[..]] {
+            assert_eq!(
+                spans(
+                    &laptop_on_the_right,
+                    span_groups,
+                    config::MixedDpiSpan::First
+                ),
+                [(5760, 1.0)]
+            );
+            assert_eq!(
+                spans(
+                    &laptop_on_the_right,
+                    span_groups,
+                    config::MixedDpiSpan::Densest
+                ),
+                [(5760, 1.5)]
+            );
+            assert_eq!(
+                spans(
+                    &laptop_on_the_right,
+                    span_groups,
+                    config::MixedDpiSpan::Separate
+                ),
+                [(3840, 1.0), (1920, 1.5)]
+            );
+        }
+
+        // Spanning the outer two would cover the one in the middle.
+        let laptop_in_the_middle = monitors([1.0, 1.5, 1.0]);
+        assert_eq!(
+            spans(
+                &laptop_in_the_middle,
+                &everything,
+                config::MixedDpiSpan::Separate
+            ),
+            [(1920, 1.0), (1920, 1.5), (1920, 1.0)]
+        );
+    }
+
+    #[test]
+    fn it_letterboxes_the_less_dense_monitors_of_a_span() {
+        let monitors = [
+            (
+                MonitorHandle::new(0, (0, 0).into(), (1920, 1080).into(), 1.0),
+                None,
+            ),
+            (
+                MonitorHandle::new(1, (1920, 0).into(), (1920, 1080).into(), 2.0),
+                None,
+            ),
+        ];
+        let layout = |mixed_dpi| Layout {
+            fill_mode: config::FillMode::Span,
+            mixed_dpi,
+            ..Default::default()
+        };
+
+        let surfaces = build(&monitors, &layout(config::MixedDpiSpan::Densest));
+        assert_eq!(surfaces.len(), 1);
+        assert_eq!(surfaces[0].scale_factor(), 2.0);
+        assert_eq!(
+            surfaces[0].letterbox(),
+            [(((0, 0).into(), (1920, 1080).into()), 0.5)]
+        );
+
+        let surfaces = build(&monitors, &layout(config::MixedDpiSpan::First));
+        assert_eq!(surfaces[0].scale_factor(), 1.0);
+        assert!(surfaces[0].letterbox().is_empty());
+    }
+
+    #[test]
+    fn it_lays_out_custom_surfaces() {
+        // A landscape monitor with a portrait one stacked beneath it, on the left
+        let monitors = [
+            (
+                MonitorHandle::new(0, (0, 0).into(), (2560, 1440).into(), 1.5),
+                None,
+            ),
+            (
+                MonitorHandle::new(1, (0, 1440).into(), (1080, 1920).into(), 1.0),
+                None,
+            ),
+            (
+                MonitorHandle::new(2, (2560, 0).into(), (1920, 1080).into(), 1.0),
+                None,
+            ),
+        ];
+        let planned = |x, y, width, height, monitors: &[u32]| config::CustomSurface {
+            x,
+            y,
+            width,
+            height,
+            monitors: monitors
+                .iter()
+                .map(|&id| config::MonitorRef::Index(id))
+                .collect(),
+        };
+        let build_custom = |layout: &[config::CustomSurface]| {
+            custom(&monitors, layout, |_| config::Rotation::None)
+        };
+
+        let surfaces = build_custom(&[planned(0, 0, 2560, 3360, &[0, 1])]).unwrap();
+        assert_eq!(surfaces.len(), 2);
+        assert_eq!(surfaces[0].size(), PhysicalSize::new(2560, 3360));
+        assert_eq!(surfaces[0].scale_factor(), 1.5);
+        // The monitor left out gets a surface of its own.
+        assert_eq!(surfaces[1].position(), PhysicalPosition::new(2560, 0));
+
+        // Monitors that aren’t connected are skipped.
+        assert_eq!(
+            build_custom(&[planned(0, 0, 10, 10, &[7])]).unwrap().len(),
+            3
+        );
+
+        assert!(build_custom(&[planned(0, 0, 0, 1440, &[0])]).is_err());
+        assert!(build_custom(&[planned(0, 0, 2560, 1440, &[0, 2])]).is_err());
+        assert!(build_custom(&[planned(i32::MAX, 0, 2560, 1440, &[0])]).is_err());
+        assert!(build_custom(&[
+            planned(0, 0, 2560, 1440, &[0]),
+            planned(0, 1000, 1080, 2360, &[1])
+        ])
+        .is_err());
+        assert!(build_custom(&[
+            planned(0, 0, 1280, 1440, &[0]),
+            planned(1280, 0, 1280, 1440, &[0])
+        ])
+        .is_err());
+
+        // A surface on a single monitor turns with it, but a surface across several doesn’t.
+        let rotations = [config::MonitorRotation {
+            monitor: config::MonitorRef::Index(1),
+            rotation: config::Rotation::Clockwise90,
+        }];
+        let layout = [
+            planned(0, 1440, 1080, 1920, &[1]),
+            planned(0, 0, 4480, 1440, &[0, 2]),
+        ];
+        let surfaces = build(
+            &monitors,
+            &Layout {
+                fill_mode: config::FillMode::Custom,
+                rotations: &rotations,
+                custom_layout: &layout,
+                ..Default::default()
+            },
+        );
+        assert_eq!(surfaces.len(), 2);
+        assert_eq!(surfaces[0].rotation(), config::Rotation::None);
+        assert_eq!(surfaces[1].rotation(), config::Rotation::Clockwise90);
+
+        // An invalid layout falls back to a surface on each monitor.
+        let layout = [planned(0, 0, 0, 0, &[0])];
+        assert_eq!(
+            build(
+                &monitors,
+                &Layout {
+                    fill_mode: config::FillMode::Custom,
+                    custom_layout: &layout,
+                    ..Default::default()
+                }
+            )
+            .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn it_turns_rotated_monitors_on_their_own() {
+        let monitor = |id, x| {
+            (
+                MonitorHandle::new(id, (x, 0).into(), (1920, 1080).into(), 1.0),
+                None,
+            )
+        };
+        let monitors = [monitor(0, 0), monitor(1, 1920), monitor(2, 3840)];
+        let rotations = [config::MonitorRotation {
+            monitor: config::MonitorRef::Index(2),
+            rotation: config::Rotation::Clockwise90,
+        }];
+
+        let surfaces = build(
+            &monitors,
+            &Layout {
+                fill_mode: config::FillMode::Span,
+                rotations: &rotations,
+                ..Default::default()
+            },
+        );
+        assert_eq!(surfaces.len(), 2);
+        assert_eq!(surfaces[0].size(), (3840, 1080).into());
+        assert_eq!(surfaces[0].rotation(), config::Rotation::None);
+        assert_eq!(surfaces[1].size(), (1920, 1080).into());
+        assert_eq!(surfaces[1].rotation(), config::Rotation::Clockwise90);
+
+        // A single surface across every monitor can’t be turned.
+        let surfaces = build(
+            &monitors,
+            &Layout {
+                fill_mode: config::FillMode::Fill,
+                rotations: &rotations,
+                ..Default::default()
+            },
+        );
+        assert_eq!(surfaces[0].rotation(), config::Rotation::None);
+    }
+
+    #[test]
+    fn it_turns_each_monitor_of_a_video_wall() {
+        let monitor = |id, x, y| {
+            (
+                MonitorHandle::new(id, (x, y).into(), (1920, 1080).into(), 1.0),
+                None,
+            )
+        };
+        let monitors = [
+            monitor(0, 0, 0),
+            monitor(1, 1920, 0),
+            monitor(2, 0, 1080),
+            monitor(3, 1920, 1080),
+        ];
+        let turned = |rotation| {
+            (0..4)
+                .map(|index| config::MonitorRotation {
+                    monitor: config::MonitorRef::Index(index),
+                    rotation,
+                })
+                .collect::<Vec<_>>()
+        };
+        let wall = [(0..4).map(config::MonitorRef::Index).collect::<Vec<_>>()];
+        let spans = |rotations: &[config::MonitorRotation]| {
+            build(
+                &monitors,
+                &Layout {
+                    fill_mode: config::FillMode::Span,
+                    span_groups: &wall,
+                    rotations,
+                    ..Default::default()
+                },
+            )
+        };
+
+        let surfaces = spans(&turned(config::Rotation::Clockwise90));
+        assert_eq!(surfaces.len(), 1);
+        assert_eq!(surfaces[0].size(), (3840, 2160).into());
+        assert_eq!(surfaces[0].rotation(), config::Rotation::Clockwise90);
+        assert_eq!(surfaces[0].tiles(), (2, 2));
+
+        // Monitors turned different ways can’t share a picture.
+        let mut mixed = turned(config::Rotation::Clockwise90);
+        mixed[3].rotation = config::Rotation::Clockwise270;
+        let surfaces = spans(&mixed);
+        assert_eq!(surfaces.len(), 4);
+        assert!(surfaces.iter().all(|surface| surface.tiles() == (1, 1)));
+    }
+
     #[test]
     fn it_fills_all_displays() {
         let display0 = Surface {
             position: (-500, 0).into(),
             size: (1920, 1080).into(),
             scale_factor: 1.0.into(),
+            physical_scale_factor: None,
+            rotation: config::Rotation::None,
+            tiles: (1, 1),
             wallpaper: None,
+            clip: None,
+            letterbox: Vec::new(),
         };
         let display1 = Surface {
             position: (1420, 0).into(),
             size: (2560, 1440).into(),
             scale_factor: 1.0.into(),
+            physical_scale_factor: None,
+            rotation: config::Rotation::None,
+            tiles: (1, 1),
             wallpaper: None,
+            clip: None,
+            letterbox: Vec::new(),
         };
         assert_eq!(
             fill(vec![display0, display1]),
@@ -173,10 +1323,70 @@ mod test {
                 position: (-500, 0).into(),
                 size: (4480, 1440).into(),
                 scale_factor: 1.0.into(),
+                physical_scale_factor: None,
+                rotation: config::Rotation::None,
+                tiles: (1, 1),
                 wallpaper: None,
+                clip: Some(vec![
+                    ((-500, 0).into(), (1920, 1080).into()),
+                    ((1420, 0).into(), (2560, 1440).into()),
+                ]),
+                letterbox: Vec::new(),
             }]
         );
     }
+
+    #[test]
+    fn it_matches_the_physical_density_of_each_monitor() {
+        // A 24" 1080p monitor next to a 27" 4K one
+        let monitors = [
+            (
+                MonitorHandle::new(0, (0, 0).into(), (1920, 1080).into(), 1.0)
+                    .with_physical_size(Some((527, 296).into())),
+                None,
+            ),
+            (
+                MonitorHandle::new(1, (1920, 0).into(), (3840, 2160).into(), 1.5)
+                    .with_physical_size(Some((597, 336).into())),
+                None,
+            ),
+        ];
+        let surfaces = build(
+            &monitors,
+            &Layout {
+                fill_mode: config::FillMode::None,
+                ..Default::default()
+            },
+        );
+
+        // The same number of logical pixels, which set the size of the lines, to the millimetre
+        let logical_density = |surface: &Surface, width_mm: f64| {
+            surface.size().width as f64 / surface.physical_scale_factor().unwrap() / width_mm
+        };
+        let (a, b) = (
+            logical_density(&surfaces[0], 527.0),
+            logical_density(&surfaces[1], 597.0),
+        );
+        assert!((a - b).abs() < 0.01, "{} != {}", a, b);
+        assert!((a - REFERENCE_DENSITY).abs() < 0.01);
+
+        // Spanning them together would make the lines bigger on one of them, so each monitor is
+        // left with its own density.
+        let everything = [vec![
+            config::MonitorRef::Index(0),
+            config::MonitorRef::Index(1),
+        ]];
+        let spanned = build(
+            &monitors,
+            &Layout {
+                fill_mode: config::FillMode::Span,
+                span_groups: &everything,
+                uniform_physical_density: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(spanned, surfaces);
+    }
 }
 //
 //     #[test]
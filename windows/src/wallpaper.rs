@@ -1,6 +1,32 @@
-use std::{ffi::OsString, os::windows::prelude::OsStringExt, path::PathBuf, ptr};
+use crate::config::Color;
+use crate::platform::windows::process;
+use crate::winit_compat::MonitorHandle;
+
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use std::{ffi::OsString, fs, os::windows::prelude::OsStringExt, path::Path, path::PathBuf, ptr};
+use windows::Win32::Foundation::COLORREF;
 use windows::{core::*, Win32::System::Com::*, Win32::UI::Shell::*};
 
+// Explorer can take a moment to register the wallpaper API at the start of a session.
+const CONNECT_ATTEMPTS: u32 = 5;
+const CONNECT_RETRY_DELAY: Duration = Duration::from_millis(50);
+
+// Look for a missing wallpaper again after the animation has started, backing off each time.
+const REQUERY_ATTEMPTS: u32 = 5;
+const REQUERY_DELAY: Duration = Duration::from_secs(1);
+
+// How often to check for the next image in a slideshow. Windows changes the image every minute,
+// at the most.
+const SLIDESHOW_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+// The copies of wallpapers that Windows keeps without a file extension, in the temp directory
+const COPY_PREFIX: &str = "flux-wallpaper-";
+
 pub struct DesktopWallpaper {
     interface: IDesktopWallpaper,
 }
@@ -15,12 +41,41 @@ impl DesktopWallpaper {
         Ok(Self { interface })
     }
 
-    pub fn get(&self, index: u32) -> std::result::Result<PathBuf, String> {
-        let monitor_id = unsafe {
-            self.interface
-                .GetMonitorDevicePathAt(index)
-                .and_then(|mid| mid.to_hstring())
-                .map_err(|e| e.to_string())?
+    /// Connect to the wallpaper API, retrying with a backoff if it isn’t available yet.
+    ///
+    /// This blocks for less than a second, in the worst case, so the windows are better off
+    /// going without the wallpaper for a moment, and leaving it to a `Requery`.
+    pub fn connect() -> Result<Self> {
+        let mut delay = CONNECT_RETRY_DELAY;
+        let mut attempt = 1;
+
+        loop {
+            match Self::new() {
+                Ok(wallpaper) => return Ok(wallpaper),
+                Err(err) if attempt < CONNECT_ATTEMPTS => {
+                    log::debug!("Failed to connect to the wallpaper API: {}. Retrying.", err);
+                    thread::sleep(delay);
+                    delay *= 2;
+                    attempt += 1;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Find the wallpaper of a monitor.
+    ///
+    /// The wallpaper API lists the monitors in an order of its own, so look them up by device
+    /// path, and only fall back to the display index where the device path is unknown.
+    pub fn get(&self, monitor: &MonitorHandle) -> std::result::Result<PathBuf, String> {
+        let monitor_id = match monitor.device_path() {
+            Some(device_path) => HSTRING::from(device_path),
+            None => unsafe {
+                self.interface
+                    .GetMonitorDevicePathAt(monitor.id())
+                    .and_then(|mid| mid.to_hstring())
+                    .map_err(|e| e.to_string())?
+            },
         };
 
         let wallpaper = unsafe {
@@ -32,9 +87,301 @@ impl DesktopWallpaper {
         let wallpaper_string = unsafe { OsString::from_wide(wallpaper.as_wide()) };
         let path = PathBuf::from(&wallpaper_string);
 
-        (path.exists() && path.is_file())
-            .then_some(path)
-            .ok_or("Failed to get wallpaper".to_string())
+        // With a Windows Spotlight background, the wallpaper is an image without a file
+        // extension, either in Spotlight’s cache or the copy Windows keeps of the image it put
+        // up. A slideshow can report an empty wallpaper while it moves on to the next image.
+        if path.is_file() {
+            match path.extension() {
+                Some(_) => Ok(path),
+                None => readable_copy(&path).ok_or("Failed to read the wallpaper".to_string()),
+            }
+        } else {
+            self.slideshow_image()
+                .ok_or("Failed to get wallpaper".to_string())
+        }
+    }
+
+    /// The color behind the wallpaper, which is all there is on a desktop with a solid color
+    /// background.
+    pub fn background_color(&self) -> Result<Color> {
+        // Stored as 0x00BBGGRR
+        let color = unsafe { self.interface.GetBackgroundColor()? }.0;
+        Ok(Color {
+            r: (color & 0xff) as u8,
+            g: (color >> 8 & 0xff) as u8,
+            b: (color >> 16 & 0xff) as u8,
+        })
+    }
+
+    /// Whether the desktop shows just the background color, without a wallpaper image.
+    pub fn is_solid_color(&self) -> bool {
+        // Without a monitor, this asks for the wallpaper shared by all of them.
+        let Ok(wallpaper) = (unsafe { self.interface.GetWallpaper(PCWSTR::null()) }) else {
+            return false;
+        };
+        let is_empty = unsafe { take_string(wallpaper) }.is_empty();
+        is_empty && !self.is_slideshow()
+    }
+
+    // The folder, or image, that the slideshow shows images from.
+    fn slideshow_folder(&self) -> Result<PathBuf> {
+        unsafe {
+            let items = self.interface.GetSlideshow()?;
+            let folder = items.GetItemAt(0)?.GetDisplayName(SIGDN_FILESYSPATH)?;
+            Ok(PathBuf::from(take_string(folder)))
+        }
+    }
+
+    // The slide on show. Windows keeps a copy of the image it last put up, without a file
+    // extension.
+    fn slideshow_image(&self) -> Option<PathBuf> {
+        if !self.is_slideshow() {
+            return None;
+        }
+
+        let transcoded = PathBuf::from(std::env::var_os("APPDATA")?)
+            .join("Microsoft\\Windows\\Themes\\TranscodedWallpaper");
+        readable_copy(&transcoded)
+    }
+
+    /// Whether the wallpaper is a slideshow, which changes images during the session.
+    pub fn is_slideshow(&self) -> bool {
+        let Ok(status) = (unsafe { self.interface.GetStatus() }) else {
+            return false;
+        };
+
+        status.0 & DSS_ENABLED.0 != 0 && status.0 & DSS_SLIDESHOW.0 != 0
+    }
+
+    /// Read the wallpaper settings of every monitor, along with the slideshow.
+    pub fn snapshot(&self) -> Result<Snapshot> {
+        unsafe {
+            let mut monitors = Vec::new();
+            for index in 0..self.interface.GetMonitorDevicePathCount()? {
+                let device_path = take_string(self.interface.GetMonitorDevicePathAt(index)?);
+                // Monitors that are listed but turned off have no wallpaper.
+                let Ok(wallpaper) = self.interface.GetWallpaper(&HSTRING::from(&device_path))
+                else {
+                    continue;
+                };
+                monitors.push(MonitorWallpaper {
+                    device_path,
+                    wallpaper: PathBuf::from(take_string(wallpaper)),
+                });
+            }
+
+            let slideshow = if self.is_slideshow() {
+                let folder = self.slideshow_folder()?;
+                let mut options = DESKTOP_SLIDESHOW_OPTIONS::default();
+                let mut interval_ms = 0;
+                self.interface
+                    .GetSlideshowOptions(&mut options, &mut interval_ms)?;
+                Some(Slideshow {
+                    folder,
+                    options: options.0,
+                    interval_ms,
+                })
+            } else {
+                None
+            };
+
+            Ok(Snapshot {
+                monitors,
+                position: self.interface.GetPosition()?.0,
+                background_color: self.interface.GetBackgroundColor()?.0,
+                slideshow,
+            })
+        }
+    }
+
+    fn apply(&self, change: &Change) -> Result<()> {
+        unsafe {
+            match change {
+                Change::Position(position) => self
+                    .interface
+                    .SetPosition(DESKTOP_WALLPAPER_POSITION(*position)),
+                Change::BackgroundColor(color) => {
+                    self.interface.SetBackgroundColor(COLORREF(*color))
+                }
+                Change::Slideshow(slideshow) => {
+                    let folder: IShellItem = SHCreateItemFromParsingName(
+                        &HSTRING::from(slideshow.folder.as_path()),
+                        None,
+                    )?;
+                    let items: IShellItemArray = SHCreateShellItemArrayFromShellItem(&folder)?;
+                    self.interface.SetSlideshow(&items)?;
+                    self.interface.SetSlideshowOptions(
+                        DESKTOP_SLIDESHOW_OPTIONS(slideshow.options),
+                        slideshow.interval_ms,
+                    )
+                }
+                Change::Wallpaper(monitor) => self.interface.SetWallpaper(
+                    &HSTRING::from(&monitor.device_path),
+                    &HSTRING::from(monitor.wallpaper.as_path()),
+                ),
+            }
+        }
+    }
+}
+
+// Copy a string handed over by the shell, and free it.
+unsafe fn take_string(string: PWSTR) -> String {
+    let copy = String::from_utf16_lossy(string.as_wide());
+    CoTaskMemFree(Some(string.0 as *const _));
+    copy
+}
+
+/// The wallpaper settings, as Windows shows them in the personalization settings.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Snapshot {
+    monitors: Vec<MonitorWallpaper>,
+    // How the images are fitted to the monitors, like fill or tile
+    position: i32,
+    // Shows around images that don’t cover the monitor
+    background_color: u32,
+    slideshow: Option<Slideshow>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MonitorWallpaper {
+    device_path: String,
+    wallpaper: PathBuf,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Slideshow {
+    // Windows only lets you pick a single folder to show the images from.
+    folder: PathBuf,
+    // Whether to shuffle the images
+    options: i32,
+    interval_ms: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Change {
+    Position(i32),
+    BackgroundColor(u32),
+    Slideshow(Slideshow),
+    Wallpaper(MonitorWallpaper),
+}
+
+impl Snapshot {
+    // What it takes to get from the current settings back to these ones. A slideshow sets the
+    // wallpaper of every monitor, so the wallpapers are only compared without one. Monitors that
+    // have since been unplugged are left alone.
+    fn changes_from(&self, current: &Snapshot) -> Vec<Change> {
+        let mut changes = Vec::new();
+        if self.position != current.position {
+            changes.push(Change::Position(self.position));
+        }
+        if self.background_color != current.background_color {
+            changes.push(Change::BackgroundColor(self.background_color));
+        }
+
+        match &self.slideshow {
+            Some(slideshow) if current.slideshow.as_ref() != Some(slideshow) => {
+                changes.push(Change::Slideshow(slideshow.clone()))
+            }
+            Some(_) => (),
+            None => {
+                for monitor in &self.monitors {
+                    let changed = current.monitors.iter().any(|current| {
+                        current.device_path == monitor.device_path
+                            && current.wallpaper != monitor.wallpaper
+                    });
+                    if changed {
+                        changes.push(Change::Wallpaper(monitor.clone()));
+                    }
+                }
+            }
+        }
+
+        changes
+    }
+}
+
+// Saved while the live wallpaper runs, and removed once it has cleaned up
+#[derive(Debug, Deserialize, Serialize)]
+struct Backup {
+    pid: u32,
+    snapshot: Snapshot,
+}
+
+/// Save the wallpaper settings before the live wallpaper attaches to the desktop, so that they
+/// can be put back if Flux crashes before it cleans up after itself.
+pub fn save_backup(path: &Path) -> std::result::Result<(), String> {
+    let snapshot = DesktopWallpaper::connect()
+        .and_then(|wallpaper| wallpaper.snapshot())
+        .map_err(|err| format!("Failed to read the wallpaper settings: {}", err))?;
+    let backup = Backup {
+        pid: std::process::id(),
+        snapshot,
+    };
+
+    let json = serde_json::to_string_pretty(&backup).map_err(|err| err.to_string())?;
+    fs::write(path, json).map_err(|err| {
+        format!(
+            "Failed to back up the wallpaper settings to {}: {}",
+            path.display(),
+            err
+        )
+    })
+}
+
+/// Remove the backup after a clean exit.
+pub fn remove_backup(path: &Path) {
+    let _ = fs::remove_file(path);
+}
+
+/// Check the wallpaper settings against a backup left behind by a live wallpaper that crashed,
+/// and put back whatever has changed since. The desktop is repainted either way, since the last
+/// frame the live wallpaper drew can linger even when the settings are as they were. A backup
+/// from a live wallpaper that’s still running is left alone.
+///
+/// Returns whether the backup is out of the way, so that a new one can take its place.
+pub fn restore_after_crash(path: &Path) -> bool {
+    let Ok(json) = fs::read_to_string(path) else {
+        return true;
+    };
+    let backup: Backup = match serde_json::from_str(&json) {
+        Ok(backup) => backup,
+        Err(err) => {
+            log::warn!("Failed to read the wallpaper backup: {}", err);
+            remove_backup(path);
+            return true;
+        }
+    };
+    if backup.pid != std::process::id() && process::is_running(backup.pid) {
+        log::debug!(
+            "The live wallpaper is already running, as process {}",
+            backup.pid
+        );
+        return false;
+    }
+
+    log::info!("The live wallpaper didn’t exit cleanly last time. Checking the wallpaper.");
+    let restored = DesktopWallpaper::connect().and_then(|wallpaper| {
+        let changes = backup.snapshot.changes_from(&wallpaper.snapshot()?);
+        for change in &changes {
+            log::info!("Putting back the wallpaper setting {:?}", change);
+            wallpaper.apply(change)?;
+        }
+        Ok(())
+    });
+    unsafe { crate::platform::windows::window::repaint_desktop() };
+    match restored {
+        Ok(()) => {
+            remove_backup(path);
+            true
+        }
+        // Try again next time, in case Explorer wasn’t ready.
+        Err(err) => {
+            log::warn!("Failed to restore the wallpaper settings: {}", err);
+            false
+        }
     }
 }
 
@@ -59,3 +406,226 @@ thread_local! {
 pub fn com_initialized() {
     COM_INITIALIZED.with(|_| {});
 }
+
+/// Keeps looking for the wallpaper of a monitor that didn’t have one at startup.
+///
+/// The wallpaper API can return nothing for a while after logging in, until Explorer has fully
+/// started. Rather than going without the wallpaper colors for the whole run, check back a few
+/// times, waiting longer after each attempt. Each attempt connects on a thread of its own, so
+/// that retrying the connection doesn’t hold up the animation.
+#[derive(Debug)]
+pub struct Requery {
+    monitor: MonitorHandle,
+    next_attempt: Instant,
+    delay: Duration,
+    attempts_left: u32,
+    // The attempt still looking
+    pending: Option<mpsc::Receiver<Option<PathBuf>>>,
+}
+
+impl Requery {
+    /// Start looking right away.
+    pub fn new(monitor: MonitorHandle, now: Instant) -> Self {
+        Self {
+            monitor,
+            next_attempt: now,
+            delay: REQUERY_DELAY,
+            attempts_left: REQUERY_ATTEMPTS,
+            pending: None,
+        }
+    }
+
+    /// The wallpaper, if the last attempt has found it. Starts another attempt when it’s time.
+    pub fn poll(&mut self, now: Instant) -> Option<PathBuf> {
+        if let Some(pending) = &self.pending {
+            match pending.try_recv() {
+                Ok(wallpaper) => {
+                    self.pending = None;
+                    if wallpaper.is_some() {
+                        return wallpaper;
+                    }
+                }
+                Err(mpsc::TryRecvError::Empty) => return None,
+                Err(mpsc::TryRecvError::Disconnected) => self.pending = None,
+            }
+        }
+
+        if self.attempts_left == 0 || now < self.next_attempt {
+            return None;
+        }
+
+        self.attempts_left -= 1;
+        self.next_attempt = now + self.delay;
+        self.delay *= 2;
+
+        let monitor = self.monitor.clone();
+        let (sender, receiver) = mpsc::channel();
+        let spawned = thread::Builder::new()
+            .name("wallpaper".to_string())
+            .spawn(move || {
+                let wallpaper = DesktopWallpaper::connect()
+                    .ok()
+                    .and_then(|wallpaper| wallpaper.get(&monitor).ok());
+                let _ = sender.send(wallpaper);
+            });
+        match spawned {
+            Ok(_) => self.pending = Some(receiver),
+            Err(err) => log::warn!("Failed to look for the wallpaper: {}", err),
+        }
+
+        None
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.attempts_left == 0 && self.pending.is_none()
+    }
+
+    pub fn monitor(&self) -> &MonitorHandle {
+        &self.monitor
+    }
+}
+
+/// Follows the wallpaper of a monitor that’s set to a slideshow, so that the colors can change
+/// along with the images.
+#[derive(Debug)]
+pub struct SlideshowWatch {
+    monitor: MonitorHandle,
+    current: PathBuf,
+    next_check: Instant,
+}
+
+impl SlideshowWatch {
+    /// Start watching the wallpaper of a monitor, if it’s a slideshow, or a Windows Spotlight
+    /// background, which moves on to another image every so often too.
+    pub fn start(monitor: MonitorHandle, current: PathBuf, now: Instant) -> Option<Self> {
+        let is_copy = current
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with(COPY_PREFIX));
+        (is_copy || DesktopWallpaper::new().is_ok_and(|wallpaper| wallpaper.is_slideshow())).then(
+            || Self {
+                monitor,
+                current,
+                next_check: now + SLIDESHOW_POLL_INTERVAL,
+            },
+        )
+    }
+
+    /// Check whether the slideshow has moved on to another image, if it’s time to.
+    pub fn poll(&mut self, now: Instant) -> Option<PathBuf> {
+        if now < self.next_check {
+            return None;
+        }
+        self.next_check = now + SLIDESHOW_POLL_INTERVAL;
+
+        let wallpaper = DesktopWallpaper::new().ok()?.get(&self.monitor).ok()?;
+        if wallpaper == self.current {
+            return None;
+        }
+
+        self.current = wallpaper.clone();
+        Some(wallpaper)
+    }
+}
+
+// Copy an image without a file extension to one with an extension for Flux to read, named after
+// the image and when it last changed, so that a new image gets a new name. Copies of earlier
+// images are removed.
+fn readable_copy(image_path: &Path) -> Option<PathBuf> {
+    let modified = fs::metadata(image_path)
+        .and_then(|metadata| metadata.modified())
+        .ok()?;
+    let format = image::io::Reader::open(image_path)
+        .ok()?
+        .with_guessed_format()
+        .ok()?
+        .format()?;
+
+    let mut hasher = DefaultHasher::new();
+    (image_path, modified).hash(&mut hasher);
+    let name = format!(
+        "{}{:016x}.{}",
+        COPY_PREFIX,
+        hasher.finish(),
+        format.extensions_str().first()?
+    );
+    let copy = std::env::temp_dir().join(&name);
+    if copy.is_file() {
+        return Some(copy);
+    }
+
+    if let Ok(entries) = fs::read_dir(std::env::temp_dir()) {
+        for entry in entries.flatten() {
+            let file_name = entry.file_name();
+            if file_name
+                .to_str()
+                .is_some_and(|file_name| file_name.starts_with(COPY_PREFIX) && file_name != name)
+            {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+
+    fs::copy(image_path, &copy)
+        .map_err(|err| log::debug!("Failed to copy {}: {}", image_path.display(), err))
+        .ok()?;
+    Some(copy)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn monitor(device_path: &str, wallpaper: &str) -> MonitorWallpaper {
+        MonitorWallpaper {
+            device_path: device_path.to_string(),
+            wallpaper: PathBuf::from(wallpaper),
+        }
+    }
+
+    #[test]
+    fn it_only_puts_back_what_changed() {
+        let saved = Snapshot {
+            monitors: vec![
+                monitor("left", "C:\\mountains.jpg"),
+                monitor("right", "C:\\lake.jpg"),
+                monitor("unplugged", "C:\\forest.jpg"),
+            ],
+            position: 4,
+            background_color: 0,
+            slideshow: None,
+        };
+        let current = Snapshot {
+            monitors: vec![
+                monitor("left", "C:\\mountains.jpg"),
+                monitor("right", "C:\\black.png"),
+            ],
+            position: 3,
+            ..saved.clone()
+        };
+
+        assert_eq!(
+            saved.changes_from(&current),
+            vec![
+                Change::Position(4),
+                Change::Wallpaper(monitor("right", "C:\\lake.jpg")),
+            ]
+        );
+        assert_eq!(saved.changes_from(&saved), vec![]);
+
+        // The slideshow takes care of the monitors.
+        let slideshow = Slideshow {
+            folder: PathBuf::from("C:\\Pictures"),
+            options: 0,
+            interval_ms: 60_000,
+        };
+        let saved = Snapshot {
+            slideshow: Some(slideshow.clone()),
+            ..saved
+        };
+        assert_eq!(
+            saved.changes_from(&current),
+            vec![Change::Position(4), Change::Slideshow(slideshow)]
+        );
+    }
+}
@@ -0,0 +1,102 @@
+use crate::config::{Config, GpuWorkaround, GpuWorkarounds, Quality};
+
+#[cfg(feature = "sdl")]
+use std::sync::mpsc;
+#[cfg(feature = "sdl")]
+use std::thread;
+#[cfg(feature = "sdl")]
+use std::time::Duration;
+
+// Set on the second try, after the first one stalled
+const SAFE_MODE_ENV_VAR: &str = "FLUX_SAFE_MODE";
+
+/// Gives up on the screensaver if it doesn’t get every monitor going in time.
+///
+/// A driver that hangs while setting up, or while compiling the shaders, leaves black windows
+/// over the monitors. The main thread is stuck waiting on the render thread, so not even input
+/// gets through to close them. The watchdog keeps time on a thread of its own, and calls the
+/// given function if it isn’t dropped before the timeout.
+#[cfg(feature = "sdl")]
+pub struct Watchdog {
+    // Dropping the sender lets the thread know that it’s no longer needed.
+    _disarm: mpsc::Sender<()>,
+}
+
+#[cfg(feature = "sdl")]
+impl Watchdog {
+    pub fn start(timeout: Duration, on_stall: impl FnOnce() + Send + 'static) -> Self {
+        let (disarm, disarmed) = mpsc::channel::<()>();
+        let spawned = thread::Builder::new()
+            .name("watchdog".to_string())
+            .spawn(move || {
+                if let Err(mpsc::RecvTimeoutError::Timeout) = disarmed.recv_timeout(timeout) {
+                    on_stall();
+                }
+            });
+        if let Err(err) = spawned {
+            log::warn!("Failed to start the watchdog: {}", err);
+        }
+
+        Self { _disarm: disarm }
+    }
+}
+
+/// Whether this is the second try, after the first one stalled.
+pub fn is_safe_mode() -> bool {
+    std::env::var_os(SAFE_MODE_ENV_VAR).is_some()
+}
+
+/// Start the screensaver over in a new process, in safe mode.
+#[cfg(feature = "sdl")]
+pub fn restart_in_safe_mode() -> Result<(), String> {
+    let exe = std::env::current_exe()
+        .map_err(|err| format!("Failed to find the screensaver executable: {}", err))?;
+    std::process::Command::new(exe)
+        .args(std::env::args_os().skip(1))
+        .env(SAFE_MODE_ENV_VAR, "1")
+        .spawn()
+        .map(|_| ())
+        .map_err(|err| format!("Failed to start the screensaver again: {}", err))
+}
+
+/// Steer clear of the usual driver trouble: present through plain OpenGL, at low quality, on
+/// whichever GPU Windows picks. A GPU picked in the Windows graphics settings, by the user or by
+/// Flux, still applies.
+pub fn apply_safe_mode(config: &mut Config) {
+    config.flux.quality = Quality::Low;
+
+    let windows = &mut config.platform.windows;
+    windows.high_dynamic_range = false;
+    windows.max_frame_latency = None;
+    windows.preferred_gpu = None;
+    windows
+        .gpu_workarounds
+        .push(GpuWorkaround::for_every_gpu(GpuWorkarounds {
+            disable_dxgi_interop: true,
+            ..Default::default()
+        }));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(feature = "sdl")]
+    #[test]
+    fn it_only_calls_out_a_stall_when_not_disarmed_in_time() {
+        let (sender, stalls) = mpsc::channel();
+        let _watchdog = Watchdog::start(Duration::from_millis(10), move || {
+            let _ = sender.send(());
+        });
+        assert_eq!(stalls.recv_timeout(Duration::from_secs(5)), Ok(()));
+
+        let (sender, stalls) = mpsc::channel();
+        drop(Watchdog::start(Duration::from_millis(50), move || {
+            let _ = sender.send(());
+        }));
+        assert_eq!(
+            stalls.recv_timeout(Duration::from_millis(200)),
+            Err(mpsc::RecvTimeoutError::Disconnected)
+        );
+    }
+}
@@ -0,0 +1,173 @@
+use crate::cli::Mode;
+use crate::config::Config;
+use crate::stats::ExitReason;
+use crate::winit_host::WinitHost;
+use crate::{MINIMUM_MOUSE_MOTION_TO_EXIT_SCREENSAVER, WINDOWED_SIZE};
+
+use std::collections::HashMap;
+use std::path;
+
+use winit::dpi::PhysicalPosition;
+use winit::event::{ElementState, Event, KeyEvent, WindowEvent};
+use winit::event_loop::EventLoop;
+use winit::keyboard::{Key, NamedKey};
+use winit::window::{WindowBuilder, WindowId};
+
+/// Run Flux with winit opening the windows and reading the input, for builds without SDL.
+///
+/// This covers the screensaver and windowed mode. The previews, the live wallpaper, the
+/// benchmark, and isolating the monitors in processes of their own all need the SDL build.
+pub fn run(mode: Mode, config: Config, exit_log: Option<&path::Path>) -> Result<(), String> {
+    let (is_screensaver, lock_on_exit) = screensaver_options(&mode)?;
+
+    let event_loop = EventLoop::new().map_err(|err| err.to_string())?;
+    let mut host = WinitHost::new(&config);
+
+    if is_screensaver {
+        for surface in crate::build_surfaces(&*event_loop, &config, &mode) {
+            let window = WindowBuilder::new()
+                .with_title("Flux")
+                .with_decorations(false)
+                .with_position(surface.position())
+                .with_inner_size(surface.size())
+                .build(&event_loop)
+                .map_err(|err| err.to_string())?;
+            window.set_cursor_visible(false);
+            #[cfg(windows)]
+            if let Some(clip) = surface.clip() {
+                use raw_window_handle::HasRawWindowHandle;
+                let handle = window.raw_window_handle();
+                if let Err(err) = unsafe {
+                    crate::platform::windows::window::clip_to(&handle, surface.position(), clip)
+                } {
+                    log::warn!("{}", err);
+                }
+            }
+            host.attach(window)?;
+        }
+    } else {
+        let window = WindowBuilder::new()
+            .with_title("Flux")
+            .with_inner_size(WINDOWED_SIZE)
+            .build(&event_loop)
+            .map_err(|err| err.to_string())?;
+        host.attach(window)?;
+    }
+
+    let mut result = Ok(ExitReason::Quit);
+    // Where the cursor was last seen over each window
+    let mut cursor_positions: HashMap<WindowId, PhysicalPosition<f64>> = HashMap::new();
+
+    let run = event_loop
+        .run(|event, target| {
+            if let Err(err) = host.handle_event(&event, target) {
+                result = Err(err);
+                target.exit();
+                return;
+            }
+            if host.is_empty() {
+                target.exit();
+                return;
+            }
+
+            let Event::WindowEvent { window_id, event } = event else {
+                return;
+            };
+            let exit_reason = match event {
+                WindowEvent::CloseRequested => Some(ExitReason::Quit),
+
+                // In a window, only Escape closes Flux.
+                WindowEvent::KeyboardInput {
+                    event:
+                        KeyEvent {
+                            logical_key,
+                            state: ElementState::Pressed,
+                            repeat: false,
+                            ..
+                        },
+                    ..
+                } if is_screensaver || logical_key == Key::Named(NamedKey::Escape) => {
+                    Some(ExitReason::Key {
+                        key: format!("{:?}", logical_key),
+                    })
+                }
+
+                WindowEvent::MouseInput {
+                    state: ElementState::Pressed,
+                    button,
+                    ..
+                } if is_screensaver => Some(ExitReason::MouseButton {
+                    button: format!("{:?}", button),
+                }),
+
+                // Windows sends a motion event when the windows first appear under the cursor,
+                // so it takes some actual movement to exit.
+                WindowEvent::CursorMoved { position, .. } if is_screensaver => cursor_positions
+                    .insert(window_id, position)
+                    .map(|last| (position.x - last.x).abs().max((position.y - last.y).abs()))
+                    .filter(|distance| *distance > MINIMUM_MOUSE_MOTION_TO_EXIT_SCREENSAVER)
+                    .map(|distance| ExitReason::MouseMotion {
+                        distance: distance as i32,
+                    }),
+
+                // Stop drawing while the window is hidden, like the SDL instances do.
+                WindowEvent::Occluded(is_occluded) => {
+                    match is_occluded {
+                        true => host.pause(window_id),
+                        false => host.resume(window_id),
+                    }
+                    None
+                }
+
+                _ => None,
+            };
+
+            if let Some(exit_reason) = exit_reason {
+                result = Ok(exit_reason);
+                target.exit();
+            }
+        })
+        .map_err(|err| err.to_string());
+    let result = run.and(result);
+
+    if !is_screensaver {
+        return result.map(|_| ());
+    }
+    crate::record_exit(exit_log, &result);
+
+    crate::lock_after(
+        result.map(|_| ()),
+        lock_on_exit || config.platform.windows.lock_on_exit,
+        crate::lock_workstation,
+    )
+}
+
+// Whether the mode runs as a screensaver, and if so, whether to lock the session once it exits.
+// The other modes are turned down, rather than run in a window they weren’t asked for.
+fn screensaver_options(mode: &Mode) -> Result<(bool, bool), String> {
+    match mode {
+        Mode::Screensaver { lock_on_exit } => Ok((true, *lock_on_exit)),
+        Mode::Windowed => Ok((false, false)),
+        _ => Err(
+            "This build of Flux only runs as a screensaver or in a window. Everything else needs \
+             SDL."
+                .to_string(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_only_runs_as_a_screensaver_or_in_a_window() {
+        assert_eq!(
+            screensaver_options(&Mode::Screensaver { lock_on_exit: true }),
+            Ok((true, true))
+        );
+        assert_eq!(screensaver_options(&Mode::Windowed), Ok((false, false)));
+        assert!(screensaver_options(&Mode::Instance { surface: 0 }).is_err());
+        assert!(screensaver_options(&Mode::Settings).is_err());
+    }
+}
@@ -1,18 +1,62 @@
 use std::num::NonZeroU32;
 
+#[cfg(feature = "sdl")]
 use sdl2::video::Window;
+#[cfg(feature = "sdl")]
 use sdl2::VideoSubsystem;
 
 use winit::dpi::{PhysicalPosition, PhysicalSize};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct MonitorHandle {
+    // The display index
+    id: u32,
     position: PhysicalPosition<i32>,
     size: PhysicalSize<u32>,
     scale_factor: f64,
+    // Identifies the monitor across reboots and replugging, where the platform supports it
+    device_path: Option<String>,
+    // In millimetres, if the monitor reports it
+    physical_size: Option<PhysicalSize<u32>>,
 }
 
 impl MonitorHandle {
+    pub fn new(
+        id: u32,
+        position: PhysicalPosition<i32>,
+        size: PhysicalSize<u32>,
+        scale_factor: f64,
+    ) -> Self {
+        Self {
+            id,
+            position,
+            size,
+            scale_factor,
+            device_path: None,
+            physical_size: None,
+        }
+    }
+
+    pub fn with_device_path(mut self, device_path: Option<String>) -> Self {
+        self.device_path = device_path;
+        self
+    }
+
+    pub fn with_physical_size(mut self, physical_size: Option<PhysicalSize<u32>>) -> Self {
+        self.physical_size = physical_size;
+        self
+    }
+
+    #[cfg(feature = "sdl")]
+    fn with_details(self, details: Details) -> Self {
+        self.with_device_path(details.device_path)
+            .with_physical_size(details.physical_size)
+    }
+
+    #[inline]
+    pub fn id(&self) -> u32 {
+        self.id
+    }
     #[inline]
     pub fn position(&self) -> PhysicalPosition<i32> {
         self.position
@@ -25,6 +69,14 @@ impl MonitorHandle {
     pub fn scale_factor(&self) -> f64 {
         self.scale_factor
     }
+    #[inline]
+    pub fn device_path(&self) -> Option<&str> {
+        self.device_path.as_deref()
+    }
+    #[inline]
+    pub fn physical_size(&self) -> Option<PhysicalSize<u32>> {
+        self.physical_size
+    }
 }
 
 pub trait HasWinitWindow {
@@ -33,6 +85,7 @@ pub trait HasWinitWindow {
     fn current_monitor(&self) -> Option<MonitorHandle>;
 }
 
+#[cfg(feature = "sdl")]
 impl HasWinitWindow for Window {
     fn inner_size(&self) -> PhysicalSize<u32> {
         let (w, h) = self.size();
@@ -46,40 +99,169 @@ impl HasWinitWindow for Window {
 
     fn current_monitor(&self) -> Option<MonitorHandle> {
         self.display_index().ok().and_then(|id| {
-            self.subsystem()
-                .display_bounds(id)
-                .ok()
-                .map(|bounds| MonitorHandle {
-                    position: PhysicalPosition::new(bounds.x, bounds.y),
-                    size: bounds.size().into(),
-                    scale_factor: compute_dpi(self.subsystem().display_dpi(id).ok()),
-                })
+            self.subsystem().display_bounds(id).ok().map(|bounds| {
+                MonitorHandle::new(
+                    id as u32,
+                    PhysicalPosition::new(bounds.x, bounds.y),
+                    bounds.size().into(),
+                    compute_dpi(self.subsystem().display_dpi(id).ok()),
+                )
+                .with_details(take_details(&mut display_details(self.subsystem()), id))
+            })
         })
     }
 }
 
 pub trait HasMonitors {
     fn available_monitors(&self) -> impl Iterator<Item = MonitorHandle> + '_;
+
+    // The monitors shrunk to their work areas, which exclude the taskbar and any docked toolbars.
+    fn available_work_areas(&self) -> impl Iterator<Item = MonitorHandle> + '_;
 }
 
+#[cfg(feature = "sdl")]
 impl HasMonitors for VideoSubsystem {
     fn available_monitors(&self) -> impl Iterator<Item = MonitorHandle> + '_ {
         let monitor_count = self.num_video_displays().unwrap();
-        (0..monitor_count).map(|id| {
+        let mut details = display_details(self);
+        (0..monitor_count).map(move |id| {
             let bounds = self.display_bounds(id).unwrap();
-            MonitorHandle {
-                position: PhysicalPosition::new(bounds.x, bounds.y),
-                size: bounds.size().into(),
-                scale_factor: compute_dpi(self.display_dpi(id).ok()),
-            }
+            MonitorHandle::new(
+                id as u32,
+                PhysicalPosition::new(bounds.x, bounds.y),
+                bounds.size().into(),
+                compute_dpi(self.display_dpi(id).ok()),
+            )
+            .with_details(take_details(&mut details, id))
+        })
+    }
+
+    fn available_work_areas(&self) -> impl Iterator<Item = MonitorHandle> + '_ {
+        let monitor_count = self.num_video_displays().unwrap();
+        let mut details = display_details(self);
+        (0..monitor_count).map(move |id| {
+            let bounds = self
+                .display_usable_bounds(id)
+                .or_else(|_| self.display_bounds(id))
+                .unwrap();
+            MonitorHandle::new(
+                id as u32,
+                PhysicalPosition::new(bounds.x, bounds.y),
+                bounds.size().into(),
+                compute_dpi(self.display_dpi(id).ok()),
+            )
+            .with_details(take_details(&mut details, id))
         })
     }
 }
 
+// What the platform knows about a display, beyond what SDL tells us
+#[cfg(feature = "sdl")]
+#[derive(Default)]
+struct Details {
+    device_path: Option<String>,
+    physical_size: Option<PhysicalSize<u32>>,
+}
+
+// Find the details of each display, by display index. SDL only knows the index, which changes
+// when displays are plugged in and out, so match the displays up by their bounds.
+#[cfg(all(windows, feature = "sdl"))]
+fn display_details(video_subsystem: &VideoSubsystem) -> Vec<Details> {
+    use crate::platform::windows::{display_config, edid};
+
+    let displays = display_config::query().unwrap_or_else(|err| {
+        log::debug!("Failed to find the display device paths: {}", err);
+        Vec::new()
+    });
+
+    let monitor_count = video_subsystem.num_video_displays().unwrap_or(0);
+    (0..monitor_count)
+        .map(|id| {
+            let Ok(bounds) = video_subsystem.display_bounds(id) else {
+                return Details::default();
+            };
+            let (position, size) = (
+                PhysicalPosition::new(bounds.x, bounds.y),
+                PhysicalSize::from(bounds.size()),
+            );
+            // Cloned displays share their bounds. Pick the same one of them every time, rather than
+            // whichever Windows happens to list first, so that the ID doesn’t change between runs.
+            displays
+                .iter()
+                .filter(|display| display.position == position && display.size == size)
+                .min_by_key(|display| &display.device_path)
+                .map(|display| Details {
+                    device_path: Some(display.device_path.clone()),
+                    physical_size: edid::physical_size(&display.device_path),
+                })
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+#[cfg(all(not(windows), feature = "sdl"))]
+fn display_details(_video_subsystem: &VideoSubsystem) -> Vec<Details> {
+    Vec::new()
+}
+
+#[cfg(feature = "sdl")]
+fn take_details(details: &mut [Details], id: i32) -> Details {
+    details
+        .get_mut(id as usize)
+        .map(std::mem::take)
+        .unwrap_or_default()
+}
+
+#[cfg(feature = "sdl")]
 fn compute_dpi(some_dpi: Option<(f32, f32, f32)>) -> f64 {
     some_dpi.map(|dpi| dpi.0 as f64).unwrap_or(1.0) / 96.0
 }
 
+#[cfg(feature = "winit-host")]
+impl HasWinitWindow for winit::window::Window {
+    fn inner_size(&self) -> PhysicalSize<u32> {
+        winit::window::Window::inner_size(self)
+    }
+
+    fn scale_factor(&self) -> f64 {
+        winit::window::Window::scale_factor(self)
+    }
+
+    fn current_monitor(&self) -> Option<MonitorHandle> {
+        let current = winit::window::Window::current_monitor(self)?;
+        // winit doesn’t number the monitors, so go by the order it lists them in.
+        let id = self
+            .available_monitors()
+            .position(|monitor| monitor == current)
+            .unwrap_or(0);
+        Some(to_monitor_handle(id, &current))
+    }
+}
+
+#[cfg(feature = "winit-host")]
+impl<T> HasMonitors for winit::event_loop::EventLoopWindowTarget<T> {
+    fn available_monitors(&self) -> impl Iterator<Item = MonitorHandle> + '_ {
+        winit::event_loop::EventLoopWindowTarget::available_monitors(self)
+            .enumerate()
+            .map(|(id, monitor)| to_monitor_handle(id, &monitor))
+    }
+
+    // winit doesn’t know about the taskbar, so the work areas are the whole monitors.
+    fn available_work_areas(&self) -> impl Iterator<Item = MonitorHandle> + '_ {
+        HasMonitors::available_monitors(self)
+    }
+}
+
+#[cfg(feature = "winit-host")]
+fn to_monitor_handle(id: usize, monitor: &winit::monitor::MonitorHandle) -> MonitorHandle {
+    MonitorHandle::new(
+        id as u32,
+        monitor.position(),
+        monitor.size(),
+        monitor.scale_factor(),
+    )
+}
+
 /// [`winit::dpi::PhysicalSize<u32>`] non-zero extensions.
 pub trait NonZeroU32PhysicalSize {
     /// Converts to non-zero `(width, height)`.
@@ -0,0 +1,305 @@
+use crate::config::{Config, GpuWorkaround, GpuWorkarounds, Rotation};
+use crate::{clock, render_thread, stats};
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
+use winit::dpi::PhysicalSize;
+use winit::event::{Event, WindowEvent};
+use winit::event_loop::{ControlFlow, EventLoopWindowTarget};
+use winit::window::{Window, WindowId};
+
+/// Drive Flux from a winit event loop, for hosts that already have one of their own.
+///
+/// This is the same render thread and frame pacing as the SDL screensaver, hooked up to windows
+/// that the host creates with winit. Hand each window over with [`WinitHost::attach`]
+/// and pass every event from the event loop on to [`WinitHost::handle_event`].
+pub struct WinitHost {
+    config: Config,
+    instances: HashMap<WindowId, HostedInstance>,
+    // The wake-up last asked of the event loop, to tell it apart from one the host asked for
+    wake_at: Option<Instant>,
+}
+
+struct HostedInstance {
+    // Declared before the window, so that the render thread exits before the window goes away
+    renderer: render_thread::RenderThread,
+    window: Window,
+    clock: clock::Clock,
+    // The minimum time between frames, if the frame rate is capped
+    frame_interval: Option<Duration>,
+    last_frame: Option<Instant>,
+    stats: stats::Stats,
+    // Sleeps precisely enough to pace frames, if the frame rate is capped
+    #[cfg(windows)]
+    _timer_resolution: Option<crate::platform::windows::timer::HighResolutionTimer>,
+}
+
+impl WinitHost {
+    pub fn new(config: &Config) -> Self {
+        let mut config = config.clone();
+        // The DXGI swapchain talks to the window while it’s set up on the render thread. The SDL
+        // instances answer the window messages while they wait, but the host’s event loop is
+        // stuck in `attach` in the meantime. Present through OpenGL.
+        config
+            .platform
+            .windows
+            .gpu_workarounds
+            .push(GpuWorkaround::for_every_gpu(GpuWorkarounds {
+                disable_dxgi_interop: true,
+                ..Default::default()
+            }));
+
+        Self {
+            config,
+            instances: HashMap::new(),
+            wake_at: None,
+        }
+    }
+
+    /// Start rendering Flux into the window. The host keeps the window until it’s detached or
+    /// closed.
+    pub fn attach(&mut self, window: Window) -> Result<WindowId, String> {
+        let handles = render_thread::Handles {
+            display: window.raw_display_handle(),
+            window: window.raw_window_handle(),
+            attr_window: None,
+        };
+        let options = render_thread::Options {
+            size: window.inner_size(),
+            scale_factor: window.scale_factor(),
+            wallpaper: None,
+            compositor_opacity: None,
+            rotation: Rotation::None,
+            tiles: (1, 1),
+            resizable: true,
+            letterbox: Vec::new(),
+        };
+        let (renderer, ready) = render_thread::RenderThread::spawn(&self.config, handles, options)?;
+        log::debug!(
+            "Rendering into a winit window on {}, presenting through {}",
+            ready.gpu,
+            ready.swapchain
+        );
+
+        let id = window.id();
+        window.request_redraw();
+        self.instances.insert(
+            id,
+            HostedInstance {
+                renderer,
+                window,
+                clock: clock::Clock::new(Instant::now()),
+                frame_interval: ready.frame_interval,
+                last_frame: None,
+                stats: stats::Stats::default(),
+                #[cfg(windows)]
+                _timer_resolution: ready
+                    .frame_interval
+                    .map(|_| crate::platform::windows::timer::HighResolutionTimer::new()),
+            },
+        );
+        Ok(id)
+    }
+
+    /// Stop rendering into the window, and hand it back.
+    pub fn detach(&mut self, id: WindowId) -> Option<Window> {
+        self.instances.remove(&id).map(|instance| {
+            let HostedInstance {
+                renderer, window, ..
+            } = instance;
+            // Wait for the render thread to let go of the window.
+            drop(renderer);
+            window
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+
+    /// Frame timings for the window.
+    // The winit backend doesn’t have a stats overlay to show them in.
+    #[allow(dead_code)]
+    pub fn stats(&self, id: WindowId) -> Option<&stats::Stats> {
+        self.instances.get(&id).map(|instance| &instance.stats)
+    }
+
+    pub fn pause(&mut self, id: WindowId) {
+        if let Some(instance) = self.instances.get_mut(&id) {
+            instance.clock.pause(Instant::now());
+            instance
+                .stats
+                .set_paused(Some(stats::PauseReason::Requested));
+        }
+    }
+
+    pub fn resume(&mut self, id: WindowId) {
+        if let Some(instance) = self.instances.get_mut(&id) {
+            instance.clock.resume(Instant::now());
+            instance.stats.set_paused(None);
+            instance.window.request_redraw();
+        }
+    }
+
+    /// Follow along with the event loop. Events for windows that aren’t attached are left alone.
+    ///
+    /// If the frame rate is capped, this has the event loop wake up for the next frame. A
+    /// `WaitUntil` that the host set is only brought forward, and `Poll` is left as it is.
+    pub fn handle_event<T>(
+        &mut self,
+        event: &Event<T>,
+        target: &EventLoopWindowTarget<T>,
+    ) -> Result<(), String> {
+        match event {
+            Event::WindowEvent { window_id, event } => {
+                let Some(instance) = self.instances.get_mut(window_id) else {
+                    return Ok(());
+                };
+
+                match event {
+                    WindowEvent::Resized(size) => instance.resize(*size)?,
+                    WindowEvent::ScaleFactorChanged { .. } => {
+                        instance.resize(instance.window.inner_size())?
+                    }
+                    WindowEvent::RedrawRequested => instance.draw(Instant::now())?,
+                    WindowEvent::CloseRequested | WindowEvent::Destroyed => {
+                        self.detach(*window_id);
+                    }
+                    _ => (),
+                }
+            }
+
+            // Ask for the frames that are due, and sleep until the next one otherwise.
+            Event::AboutToWait => {
+                let now = Instant::now();
+                let mut next_frame_at = None;
+                for instance in self.instances.values() {
+                    if instance.clock.is_paused() {
+                        continue;
+                    }
+                    match instance.next_frame_at() {
+                        Some(at) if at > now => {
+                            next_frame_at =
+                                Some(next_frame_at.map_or(at, |next: Instant| next.min(at)))
+                        }
+                        _ => instance.window.request_redraw(),
+                    }
+                }
+
+                let control_flow =
+                    next_control_flow(target.control_flow(), self.wake_at, next_frame_at);
+                self.wake_at = next_frame_at
+                    .filter(|&next_frame_at| control_flow == ControlFlow::WaitUntil(next_frame_at));
+                target.set_control_flow(control_flow);
+            }
+
+            _ => (),
+        }
+
+        Ok(())
+    }
+}
+
+// How long the event loop should wait for, to wake up for the next frame, if one is due. `ours` is
+// the wake-up asked for last time, which is let go of once there isn’t a frame to wake up for.
+fn next_control_flow(
+    current: ControlFlow,
+    ours: Option<Instant>,
+    next_frame_at: Option<Instant>,
+) -> ControlFlow {
+    let is_ours = matches!(current, ControlFlow::WaitUntil(at) if Some(at) == ours);
+    match (current, next_frame_at) {
+        (_, None) if is_ours => ControlFlow::Wait,
+        (_, None) | (ControlFlow::Poll, _) => current,
+        (ControlFlow::WaitUntil(at), Some(next_frame_at)) if !is_ours => {
+            ControlFlow::WaitUntil(at.min(next_frame_at))
+        }
+        (_, Some(next_frame_at)) => ControlFlow::WaitUntil(next_frame_at),
+    }
+}
+
+impl HostedInstance {
+    fn draw(&mut self, now: Instant) -> Result<(), String> {
+        if self.clock.is_paused() {
+            return Ok(());
+        }
+        if let Some(next_frame_at) = self.next_frame_at() {
+            if now < next_frame_at {
+                return Ok(());
+            }
+        }
+        self.last_frame = Some(now);
+
+        let command = match self.clock.timestamp(now) {
+            Some(timestamp) => render_thread::Command::Draw(timestamp),
+            None => render_thread::Command::Clear,
+        };
+        self.window.pre_present_notify();
+        self.renderer.run(command)?;
+        self.stats.record_frame(Instant::now());
+
+        Ok(())
+    }
+
+    fn resize(&mut self, size: PhysicalSize<u32>) -> Result<(), String> {
+        self.renderer.run(render_thread::Command::Resize {
+            size,
+            scale_factor: self.window.scale_factor(),
+        })
+    }
+
+    fn next_frame_at(&self) -> Option<Instant> {
+        self.frame_interval
+            .zip(self.last_frame)
+            .map(|(interval, last_frame)| last_frame + interval)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_wakes_up_for_the_next_frame() {
+        let now = Instant::now();
+        let next_frame_at = now + Duration::from_millis(16);
+        assert_eq!(
+            next_control_flow(ControlFlow::Wait, None, Some(next_frame_at)),
+            ControlFlow::WaitUntil(next_frame_at)
+        );
+        // The one asked for last time has passed.
+        assert_eq!(
+            next_control_flow(ControlFlow::WaitUntil(now), Some(now), Some(next_frame_at)),
+            ControlFlow::WaitUntil(next_frame_at)
+        );
+        assert_eq!(
+            next_control_flow(ControlFlow::WaitUntil(now), Some(now), None),
+            ControlFlow::Wait
+        );
+    }
+
+    #[test]
+    fn it_keeps_to_the_hosts_control_flow() {
+        let now = Instant::now();
+        let next_frame_at = now + Duration::from_millis(16);
+        let hosts = now + Duration::from_millis(5);
+        assert_eq!(
+            next_control_flow(ControlFlow::Poll, None, Some(next_frame_at)),
+            ControlFlow::Poll
+        );
+        assert_eq!(
+            next_control_flow(ControlFlow::WaitUntil(hosts), None, Some(next_frame_at)),
+            ControlFlow::WaitUntil(hosts)
+        );
+        assert_eq!(
+            next_control_flow(ControlFlow::WaitUntil(hosts), None, None),
+            ControlFlow::WaitUntil(hosts)
+        );
+        assert_eq!(
+            next_control_flow(ControlFlow::Wait, None, None),
+            ControlFlow::Wait
+        );
+    }
+}